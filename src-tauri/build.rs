@@ -20,9 +20,6 @@ fn main() {
     if let Ok(key) = env::var("SUPABASE_ANON_KEY") {
         println!("cargo:rustc-env=SUPABASE_ANON_KEY={}", key);
     }
-    if let Ok(service_key) = env::var("SUPABASE_SERVICE_KEY") {
-        println!("cargo:rustc-env=SUPABASE_SERVICE_KEY={}", service_key);
-    }
 
     tauri_build::build()
 }
@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+use ts_rs::TS;
+
+use crate::secret::SecretString;
+use crate::secrets::{self, SecretKind};
+
+// =============================================================================
+// Compte JellySetup (Supabase Auth)
+// =============================================================================
+//
+// Les données qui doivent suivre l'utilisateur d'un ordinateur à l'autre (points
+// de reprise, registre des Pis, modèles Jellyfin) sont rangées sous son
+// identifiant Supabase Auth: dossier `<user id>/` des buckets et colonne
+// `owner_id` des tables, protégés par RLS (supabase/schema.sql). Le jeton de
+// rafraîchissement est gardé dans le coffre de l'OS; le jeton d'accès, en mémoire,
+// est renouvelé une minute avant son expiration.
+
+/// Marge avant expiration du jeton d'accès (secondes)
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+static SESSION: Lazy<Mutex<Option<AccountSession>>> = Lazy::new(|| Mutex::new(None));
+
+/// Compte connecté (exposé au frontend)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AccountInfo {
+    pub user_id: String,
+    pub email: Option<String>,
+}
+
+#[derive(Clone)]
+struct AccountSession {
+    info: AccountInfo,
+    access_token: SecretString,
+    refresh_token: SecretString,
+    /// Horodatage Unix d'expiration du jeton d'accès
+    expires_at: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: SecretString,
+    refresh_token: SecretString,
+    expires_in: i64,
+    user: TokenUser,
+}
+
+#[derive(Deserialize)]
+struct TokenUser {
+    id: String,
+    email: Option<String>,
+}
+
+impl From<TokenResponse> for AccountSession {
+    fn from(token: TokenResponse) -> Self {
+        crate::redact::register(token.access_token.expose());
+        crate::redact::register(token.refresh_token.expose());
+        Self {
+            info: AccountInfo { user_id: token.user.id, email: token.user.email },
+            access_token: token.access_token,
+            refresh_token: token.refresh_token,
+            expires_at: chrono::Utc::now().timestamp() + token.expires_in,
+        }
+    }
+}
+
+/// Appel à l'API Auth (clé anon)
+async fn auth_request(path: &str, body: serde_json::Value) -> Result<TokenResponse> {
+    let client = crate::supabase::http_client()?;
+    let anon_key = crate::supabase::get_supabase_anon_key();
+    let response = client
+        .post(format!("{}/auth/v1/{}", crate::supabase::get_supabase_url_public(), path))
+        .header("apikey", &anon_key)
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Authentification refusée ({}): {}", status, text));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| anyhow!("Réponse d'authentification illisible (compte à confirmer par e-mail ?): {}", e))
+}
+
+/// Garde la session en mémoire et son jeton de rafraîchissement dans le coffre
+async fn store(session: AccountSession) -> Result<AccountInfo> {
+    secrets::store(SecretKind::AccountRefreshToken, secrets::DEFAULT_SCOPE, &session.refresh_token)?;
+    let info = session.info.clone();
+    *SESSION.lock().await = Some(session);
+    tracing::info!("Signed in as {}", info.user_id);
+    Ok(info)
+}
+
+/// Connexion par e-mail et mot de passe
+pub async fn sign_in(email: &str, password: &str) -> Result<AccountInfo> {
+    let token = auth_request("token?grant_type=password", json!({ "email": email, "password": password })).await?;
+    store(token.into()).await
+}
+
+/// Création du compte (session ouverte si la confirmation par e-mail est désactivée)
+pub async fn sign_up(email: &str, password: &str) -> Result<AccountInfo> {
+    let token = auth_request("signup", json!({ "email": email, "password": password })).await?;
+    store(token.into()).await
+}
+
+/// Déconnexion (jeton de rafraîchissement retiré du coffre)
+pub async fn sign_out() -> Result<()> {
+    *SESSION.lock().await = None;
+    secrets::delete(SecretKind::AccountRefreshToken, secrets::DEFAULT_SCOPE)
+}
+
+/// Session valide: restaurée depuis le coffre au premier appel, rafraîchie si besoin
+async fn session() -> Result<AccountSession> {
+    let mut guard = SESSION.lock().await;
+    if let Some(session) = guard.as_ref() {
+        if session.expires_at - REFRESH_MARGIN_SECS > chrono::Utc::now().timestamp() {
+            return Ok(session.clone());
+        }
+    }
+
+    let refresh_token = match guard.as_ref() {
+        Some(session) => session.refresh_token.clone(),
+        None => secrets::get(SecretKind::AccountRefreshToken, secrets::DEFAULT_SCOPE)?
+            .ok_or_else(|| anyhow!("Connectez-vous à votre compte JellySetup pour utiliser cette fonction"))?,
+    };
+    let token = auth_request("token?grant_type=refresh_token", json!({ "refresh_token": refresh_token.expose() })).await?;
+    let session = AccountSession::from(token);
+    secrets::store(SecretKind::AccountRefreshToken, secrets::DEFAULT_SCOPE, &session.refresh_token)?;
+    *guard = Some(session.clone());
+    Ok(session)
+}
+
+/// Compte connecté (None si aucune session)
+pub async fn current() -> Option<AccountInfo> {
+    session().await.ok().map(|s| s.info)
+}
+
+/// Jeton d'accès et identifiant du compte connecté (erreur si non connecté)
+pub async fn credentials() -> Result<(SecretString, String)> {
+    let session = session().await?;
+    Ok((session.access_token, session.info.user_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_from_token_response() {
+        let token: TokenResponse = serde_json::from_value(json!({
+            "access_token": "access-token-de-test-0123456789",
+            "refresh_token": "refresh-token-de-test-0123456789",
+            "expires_in": 3600,
+            "token_type": "bearer",
+            "user": { "id": "0b6c8f3e-user", "email": "pi@exemple.fr", "role": "authenticated" }
+        }))
+        .unwrap();
+
+        let before = chrono::Utc::now().timestamp();
+        let session = AccountSession::from(token);
+        assert_eq!(
+            session.info,
+            AccountInfo { user_id: "0b6c8f3e-user".to_string(), email: Some("pi@exemple.fr".to_string()) }
+        );
+        assert!((before + 3600..=chrono::Utc::now().timestamp() + 3600).contains(&session.expires_at));
+
+        // Les deux jetons sont masqués dans les journaux
+        let redacted = crate::redact::redact("a=access-token-de-test-0123456789 r=refresh-token-de-test-0123456789");
+        assert!(!redacted.contains("access-token-de-test"));
+        assert!(!redacted.contains("refresh-token-de-test"));
+    }
+}
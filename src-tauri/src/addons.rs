@@ -0,0 +1,499 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::compose::{ComposeBuilder, ContainerEnv, ServiceSpec, StackDefinition};
+use crate::preflight::{CheckStatus, PreflightCheck};
+use crate::secret::SecretString;
+use crate::ssh::{self, SshTarget};
+use crate::verification::HealthReport;
+
+// =============================================================================
+// Catalogue d'applications optionnelles (écran "ajouter des applications")
+// =============================================================================
+//
+// Une fois le stack installé, l'utilisateur peut ajouter en un clic des
+// applications décrites dans master_config.addons (repli: clé "addons" de
+// procedures/v2/steps.json embarqué). Chaque entrée fournit:
+//   services     services compose (même format que le stack, variables {{VAR}})
+//   minMemoryMb  mémoire disponible requise, minDiskGb espace libre requis
+//   health       port et chemin HTTP interrogés jusqu'à ce que l'app réponde
+//   configure    script shell optionnel lancé après le démarrage (sa sortie est
+//                affichée à l'utilisateur: mot de passe initial, ...)
+// Le compose de l'app est écrit dans ~/media-stack/addons/<nom>.yml et lancé dans
+// son propre projet compose (addon-<nom>) sur le réseau du stack: les
+// `docker compose up --remove-orphans` du stack (mise à jour, restauration) ne le
+// voient pas. {{ADDON_PASSWORD}} reçoit un mot de passe généré, renvoyé une seule
+// fois à l'installation.
+// La désinstallation supprime les conteneurs et le fichier compose de l'app;
+// ses dossiers (montages "./<dossier>") sont archivés sur le Pi dans
+// ~/jellysetup-backups/addons/ si l'utilisateur garde ses données, sinon
+// supprimés avec ses volumes nommés. Le reste du stack est ensuite revérifié.
+
+const ADDONS_DIR: &str = "~/media-stack/addons";
+const ADDON_BACKUPS_DIR: &str = "~/jellysetup-backups/addons";
+const INSTALL_TIMEOUT_SECS: u64 = 900;
+const HEALTH_TIMEOUT_SECS: u64 = 300;
+
+const RESOURCES_SCRIPT: &str = r#"
+echo "MEM_AVAILABLE_KB=$(awk '/MemAvailable/ {print $2}' /proc/meminfo)"
+echo "DISK_FREE_KB=$(df -Pk ~ | awk 'NR==2 {print $4}')"
+"#;
+
+/// Vérification de santé: `http://localhost:{port}{path}` doit répondre
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddonHealth {
+    pub port: u16,
+    #[serde(default = "default_health_path")]
+    pub path: String,
+}
+
+fn default_health_path() -> String {
+    "/".to_string()
+}
+
+/// Application du catalogue telle que décrite dans la définition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonSpec {
+    pub name: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub min_memory_mb: u32,
+    #[serde(default)]
+    pub min_disk_gb: u32,
+    pub health: AddonHealth,
+    #[serde(default)]
+    pub configure: Option<String>,
+    /// Volumes nommés utilisés par les services
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    pub services: Vec<ServiceSpec>,
+}
+
+impl AddonSpec {
+    fn compose_file(&self) -> String {
+        format!("{}/{}.yml", ADDONS_DIR, self.name)
+    }
+
+    /// Projet compose de l'app, distinct de media-stack
+    fn project(&self) -> String {
+        format!("addon-{}", self.name)
+    }
+
+    /// `docker compose` sur le projet de l'app, à lancer depuis ~/media-stack
+    /// (montages "./<dossier>" relatifs au stack)
+    fn compose_command(&self) -> String {
+        format!("docker compose -p {} --project-directory . -f addons/{}.yml", self.project(), self.name)
+    }
+
+    /// Dossiers de ~/media-stack montés par les services ("./immich/library:/data" -> "immich")
+    fn data_dirs(&self) -> Vec<String> {
+        let mut dirs: Vec<String> = Vec::new();
+        let mounts = self.services.iter().flat_map(|s| s.volumes.iter());
+        for dir in mounts.filter_map(|v| v.strip_prefix("./")?.split(['/', ':']).next()) {
+            if !dir.is_empty() && !dirs.iter().any(|d| d == dir) {
+                dirs.push(dir.to_string());
+            }
+        }
+        dirs
+    }
+
+    fn service_names(&self) -> Vec<&str> {
+        self.services.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    fn uses_password(&self) -> bool {
+        serde_json::to_string(&self.services).is_ok_and(|json| json.contains("{{ADDON_PASSWORD}}"))
+    }
+}
+
+/// Entrée du catalogue affichée par le frontend
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AddonInfo {
+    pub name: String,
+    pub display_name: String,
+    pub description: String,
+    pub min_memory_mb: u32,
+    pub min_disk_gb: u32,
+    pub port: u16,
+    pub installed: bool,
+}
+
+/// Résultat d'une installation
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AddonInstallResult {
+    pub name: String,
+    pub url: String,
+    /// Mot de passe généré pour {{ADDON_PASSWORD}} (affiché une seule fois)
+    #[ts(type = "string | null")]
+    pub password: Option<SecretString>,
+    /// Sortie du script `configure`
+    pub notes: String,
+}
+
+/// Résultat d'une désinstallation
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AddonUninstallReport {
+    pub name: String,
+    pub steps: Vec<String>,
+    /// Archive des données sur le Pi (si conservées)
+    pub backup: Option<String>,
+    /// Santé du stack restant
+    pub health: HealthReport,
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Extrait la clé "addons" d'une procédure (steps.json)
+fn from_procedure(procedure: &Value) -> Result<Vec<AddonSpec>> {
+    let addons = procedure.get("addons").cloned().unwrap_or(Value::Array(Vec::new()));
+    let addons: Vec<AddonSpec> = serde_json::from_value(addons)?;
+    if let Some(invalid) = addons.iter().find(|a| !is_valid_name(&a.name) || a.services.is_empty()) {
+        return Err(anyhow!("Application invalide dans le catalogue: {}", invalid.name));
+    }
+    Ok(addons)
+}
+
+fn embedded() -> Vec<AddonSpec> {
+    serde_json::from_str(crate::compose::EMBEDDED_PROCEDURE)
+        .map_err(anyhow::Error::from)
+        .and_then(|procedure| from_procedure(&procedure))
+        .expect("procedures/v2/steps.json embarqué invalide")
+}
+
+/// Catalogue à proposer (master_config, sinon catalogue embarqué)
+pub async fn resolve_catalog() -> Vec<AddonSpec> {
+    if !crate::offline_bundle::active() {
+        // Le catalogue ne dépend pas du type de stack: master_config active, quel que soit son type
+        let master = crate::cloud::backend().fetch_master_config(None).await.ok().flatten();
+        if let Some(addons) = master.as_ref().and_then(|m| m.addons.as_ref()) {
+            match from_procedure(&serde_json::json!({ "addons": addons })) {
+                Ok(addons) => {
+                    tracing::info!("✅ Catalog from master_config ({} apps)", addons.len());
+                    return addons;
+                }
+                Err(e) => tracing::warn!("⚠️ Invalid catalog in master_config: {}", e),
+            }
+        }
+    }
+    embedded()
+}
+
+async fn find(name: &str) -> Result<AddonSpec> {
+    resolve_catalog()
+        .await
+        .into_iter()
+        .find(|a| a.name == name)
+        .ok_or_else(|| anyhow!("Application '{}' absente du catalogue", name))
+}
+
+/// Applications déjà installées (fichiers ~/media-stack/addons/*.yml)
+async fn installed_names(target: &SshTarget<'_>) -> Vec<String> {
+    target
+        .exec(&format!("ls {} 2>/dev/null", ADDONS_DIR))
+        .await
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.trim().strip_suffix(".yml"))
+        .map(String::from)
+        .collect()
+}
+
+/// Catalogue avec l'état d'installation sur le Pi
+pub async fn list(target: &SshTarget<'_>) -> Vec<AddonInfo> {
+    let installed = installed_names(target).await;
+    resolve_catalog()
+        .await
+        .into_iter()
+        .map(|a| AddonInfo {
+            installed: installed.contains(&a.name),
+            port: a.health.port,
+            name: a.name,
+            display_name: a.display_name,
+            description: a.description,
+            min_memory_mb: a.min_memory_mb,
+            min_disk_gb: a.min_disk_gb,
+        })
+        .collect()
+}
+
+/// Compare les ressources du Pi (sortie de RESOURCES_SCRIPT) aux besoins de l'app
+pub fn evaluate_resources(spec: &AddonSpec, output: &str) -> Vec<PreflightCheck> {
+    let values: HashMap<&str, u64> = output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter_map(|(k, v)| Some((k.trim(), v.trim().parse().ok()?)))
+        .collect();
+    let mem_mb = values.get("MEM_AVAILABLE_KB").copied().unwrap_or(0) / 1024;
+    let disk_gb = values.get("DISK_FREE_KB").copied().unwrap_or(0) / 1024 / 1024;
+
+    let check = |name: &str, ok: bool, value: String, message: String| PreflightCheck {
+        name: name.to_string(),
+        status: if ok { CheckStatus::Pass } else { CheckStatus::Fail },
+        value,
+        message,
+    };
+    vec![
+        check(
+            "memory",
+            mem_mb >= spec.min_memory_mb as u64,
+            format!("{} Mo disponibles", mem_mb),
+            format!("{} nécessite {} Mo de mémoire disponible", spec.display_name, spec.min_memory_mb),
+        ),
+        check(
+            "disk",
+            disk_gb >= spec.min_disk_gb as u64,
+            format!("{} Go libres", disk_gb),
+            format!("{} nécessite {} Go d'espace libre", spec.display_name, spec.min_disk_gb),
+        ),
+    ]
+}
+
+/// Installe une application du catalogue sur le Pi
+pub async fn install(target: &SshTarget<'_>, host: &str, name: &str) -> Result<AddonInstallResult> {
+    let spec = find(name).await?;
+    if installed_names(target).await.contains(&spec.name) {
+        return Err(anyhow!("{} est déjà installé", spec.display_name));
+    }
+    tracing::info!("Installing {}...", spec.display_name);
+
+    // Ressources
+    let checks = evaluate_resources(&spec, &target.exec(RESOURCES_SCRIPT).await?);
+    let failures: Vec<String> = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .map(|c| format!("{} ({})", c.message, c.value))
+        .collect();
+    if !failures.is_empty() {
+        return Err(anyhow!("Ressources insuffisantes:\n{}", failures.join("\n")));
+    }
+
+    // Compose de l'app
+    let detected = target.exec(ContainerEnv::DETECT_COMMAND).await.unwrap_or_default();
+    let env = ContainerEnv::resolve(None, None, None, &detected);
+    let hostname = target.exec("hostname").await.unwrap_or_default().trim().to_string();
+    let paths = crate::master_config::installed_media_paths(target).await;
+    let password = spec
+        .uses_password()
+        .then(|| crate::crypto::generate_strong_password(crate::crypto::DEFAULT_PASSWORD_LENGTH))
+        .transpose()?
+        .map(|generated| SecretString::from(generated.password));
+
+    let mut vars = crate::template_engine::TemplateVars::new();
+    paths.apply_to(&mut vars);
+    let mut builder = ComposeBuilder::new(&hostname)
+        .env(&env)
+        .var("PI_IP", host)
+        .var("ADDON_PASSWORD", password.as_ref().map(SecretString::expose).unwrap_or_default())
+        .media_volume(paths.compose_volume())
+        .external_network();
+    for var in vars.names() {
+        builder = builder.var(var, vars.get(var).unwrap_or_default());
+    }
+    let compose = builder.build(&StackDefinition {
+        network: StackDefinition::embedded().network,
+        volumes: spec.volumes.clone(),
+        services: spec.services.clone(),
+    });
+
+    // Démarrage dans le projet de l'app
+    let options = ssh::SshOptions { command_timeout_secs: INSTALL_TIMEOUT_SECS, ..ssh::default_options() };
+    target
+        .exec_with_options(
+            &format!(
+                "mkdir -p {dir} && cat > {file} <<'JELLYSETUP_ADDON'\n{compose}\nJELLYSETUP_ADDON\n\
+                 cd ~/media-stack && {compose_command} up -d 2>&1",
+                dir = ADDONS_DIR,
+                file = spec.compose_file(),
+                compose = compose,
+                compose_command = spec.compose_command(),
+            ),
+            &options,
+        )
+        .await?;
+
+    // Santé
+    crate::services::wait_for_api(
+        target,
+        &spec.services[0].name,
+        spec.health.port,
+        &spec.health.path,
+        |body| !body.trim().is_empty(),
+        Duration::from_secs(HEALTH_TIMEOUT_SECS),
+    )
+    .await?;
+
+    // Configuration propre à l'app
+    let notes = match &spec.configure {
+        Some(script) => target
+            .exec_with_options(
+                &format!("cd ~/media-stack && bash -s <<'JELLYSETUP_ADDON'\n{}\nJELLYSETUP_ADDON", script),
+                &options,
+            )
+            .await
+            .unwrap_or_else(|e| format!("Configuration incomplète: {}", e))
+            .trim()
+            .to_string(),
+        None => String::new(),
+    };
+
+    record_services(&hostname, &spec, "running").await;
+
+    tracing::info!("✅ {} installed", spec.display_name);
+    Ok(AddonInstallResult {
+        url: format!("http://{}:{}", host, spec.health.port),
+        name: spec.name,
+        password,
+        notes,
+    })
+}
+
+/// Met à jour les services de l'app dans Supabase (non bloquant)
+async fn record_services(pi_name: &str, spec: &AddonSpec, status: &str) {
+    for (index, service) in spec.services.iter().enumerate() {
+        let port = (index == 0).then_some(spec.health.port as i32);
+        if let Err(e) = crate::cloud::backend()
+            .save_service(
+                pi_name,
+                &service.name,
+                None,
+                status,
+                port,
+                Some(&service.image),
+                Some(serde_json::json!({ "addon": spec.name })),
+            )
+            .await
+        {
+            tracing::warn!("⚠️ Could not record {} in Supabase: {}", service.name, e);
+        }
+    }
+}
+
+/// Script de désinstallation (`sudo`: "sudo" ou "echo 'pw' | sudo -S"); une ligne STEP= par étape
+fn uninstall_script(spec: &AddonSpec, keep_data: bool, backup_id: &str, sudo: &str) -> String {
+    let dirs = spec.data_dirs();
+    let mut lines = vec![format!(
+        "cd ~/media-stack && {compose_command} rm -s -f {services} && echo STEP=containers_removed",
+        compose_command = spec.compose_command(),
+        services = spec.service_names().join(" "),
+    )];
+
+    if !dirs.is_empty() {
+        let live: Vec<String> = dirs.iter().map(|d| format!("~/media-stack/{}", d)).collect();
+        if keep_data {
+            let archive = format!("{}/{}-{}.tar.gz", ADDON_BACKUPS_DIR, spec.name, backup_id);
+            // Suppression seulement si l'archive a bien été créée
+            lines.push(format!(
+                "mkdir -p {backups} && cd ~/media-stack && {sudo} tar czf {archive} {dirs} && {sudo} chown $(id -u) {archive} && \
+                 echo STEP=data_archived && echo ARCHIVE={archive} && {sudo} rm -rf {live} && echo STEP=data_removed",
+                backups = ADDON_BACKUPS_DIR,
+                sudo = sudo,
+                archive = archive,
+                dirs = dirs.join(" "),
+                live = live.join(" "),
+            ));
+        } else {
+            lines.push(format!("{} rm -rf {} && echo STEP=data_removed", sudo, live.join(" ")));
+        }
+    }
+    if !keep_data && !spec.volumes.is_empty() {
+        let volumes: Vec<String> = spec.volumes.iter().map(|v| format!("{}_{}", spec.project(), v)).collect();
+        lines.push(format!("docker volume rm {} && echo STEP=volumes_removed", volumes.join(" ")));
+    }
+    lines.push(format!("rm -f {} && echo STEP=compose_removed", spec.compose_file()));
+    // Une étape en échec interrompt la suite (STEP= indique où elle s'est arrêtée)
+    lines.join(" && ")
+}
+
+/// Désinstalle une application du catalogue, puis vérifie le reste du stack
+pub async fn uninstall(target: &SshTarget<'_>, name: &str, keep_data: bool, sudo: &str) -> Result<AddonUninstallReport> {
+    let spec = find(name).await?;
+    if !installed_names(target).await.contains(&spec.name) {
+        return Err(anyhow!("{} n'est pas installé", spec.display_name));
+    }
+    tracing::info!("Uninstalling {} (keep data: {})...", spec.display_name, keep_data);
+
+    let backup_id = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let options = ssh::SshOptions { command_timeout_secs: INSTALL_TIMEOUT_SECS, ..ssh::default_options() };
+    let output = target
+        .exec_with_options(&uninstall_script(&spec, keep_data, &backup_id, sudo), &options)
+        .await?;
+    let steps: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("STEP="))
+        .map(String::from)
+        .collect();
+    let backup = output.lines().find_map(|line| line.trim().strip_prefix("ARCHIVE=")).map(String::from);
+    if keep_data && !spec.data_dirs().is_empty() && backup.is_none() {
+        return Err(anyhow!(
+            "Archivage des données de {} impossible: dossiers conservés sur le Pi\n{}",
+            spec.display_name,
+            output.trim()
+        ));
+    }
+
+    let hostname = target.exec("hostname").await.unwrap_or_default().trim().to_string();
+    record_services(&hostname, &spec, "removed").await;
+
+    let health = crate::verification::verify_stack(target, &hostname).await?;
+    if health.all_passed {
+        tracing::info!("✅ {} removed, stack healthy", spec.display_name);
+    } else {
+        tracing::warn!("⚠️ {} removed, {} stack checks failing", spec.display_name, health.failures());
+    }
+    Ok(AddonUninstallReport { name: spec.name, steps, backup, health })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addon_catalog() {
+        let catalog = embedded();
+        let names: Vec<&str> = catalog.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["navidrome", "immich", "nextcloud", "qbittorrent"]);
+
+        let immich = catalog.iter().find(|a| a.name == "immich").unwrap();
+        assert!(immich.uses_password());
+        assert_eq!(immich.compose_file(), "~/media-stack/addons/immich.yml");
+        let compose = ComposeBuilder::new("pi")
+            .var("ADDON_PASSWORD", "secret")
+            .external_network()
+            .build(&StackDefinition { network: "media-network".to_string(), volumes: vec![], services: immich.services.clone() });
+        assert!(compose.contains("\n  immich-server:\n    image: ghcr.io/immich-app/immich-server:release\n"));
+        assert!(compose.contains("    - POSTGRES_PASSWORD=secret\n"));
+        assert!(!compose.contains("\nvolumes:\n"));
+        assert!(compose.ends_with("networks:\n  default:\n    name: media-network\n    external: true\n"));
+
+        let checks = evaluate_resources(immich, "MEM_AVAILABLE_KB=1048576\nDISK_FREE_KB=52428800\n");
+        assert_eq!(checks[0].status, CheckStatus::Fail);
+        assert_eq!(checks[0].value, "1024 Mo disponibles");
+        assert_eq!(checks[1].status, CheckStatus::Pass);
+
+        assert_eq!(immich.data_dirs(), ["immich"]);
+        let keep = uninstall_script(immich, true, "20250101-120000", "sudo");
+        assert!(keep.contains(
+            "docker compose -p addon-immich --project-directory . -f addons/immich.yml rm -s -f immich-server immich-redis immich-database && echo STEP=containers_removed"
+        ));
+        assert!(keep.contains("sudo tar czf ~/jellysetup-backups/addons/immich-20250101-120000.tar.gz immich &&"));
+        assert!(keep.ends_with("rm -f ~/media-stack/addons/immich.yml && echo STEP=compose_removed"));
+        let purge = uninstall_script(immich, false, "20250101-120000", "sudo");
+        assert!(purge.contains("sudo rm -rf ~/media-stack/immich && echo STEP=data_removed && rm -f"));
+        assert!(!purge.contains("tar czf"));
+
+        assert!(from_procedure(&serde_json::json!({})).unwrap().is_empty());
+        assert!(!is_valid_name("../etc"));
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ts_rs::TS;
+
+use crate::compose::StackDefinition;
+use crate::docker::ContainerInfo;
+use crate::registration::{InstallationRegistration, RegistrationAuth};
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Adoption d'un Pi installé à la main (stack Docker existant, sans reflasher)
+// =============================================================================
+//
+// Les conteneurs existants sont rapprochés des services du stack JellySetup par
+// leur image (dernier segment du dépôt, quel que soit l'éditeur: linuxserver,
+// hotio, ...) ou à défaut par leur nom. Les dossiers de configuration viennent des
+// montages `/config`, les clés API des config.xml lus dans les conteneurs. Le Pi
+// est ensuite enregistré comme une installation (Supabase + registre local).
+
+/// Services *arr dont la clé API est lue dans /config/config.xml
+const ARR_SERVICES: [&str; 3] = ["radarr", "sonarr", "prowlarr"];
+
+/// Montages et dossier compose de chaque conteneur
+const INSPECT_COMMAND: &str = "docker inspect --format \
+     '{{.Name}}|{{index .Config.Labels \"com.docker.compose.project.working_dir\"}}|{{range .Mounts}}{{.Source}}>{{.Destination}};{{end}}' \
+     $(docker ps -aq) 2>/dev/null";
+
+/// Service reconnu sur le Pi
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AdoptedService {
+    /// Nom du service dans le modèle JellySetup
+    pub service: String,
+    pub container: String,
+    pub image: String,
+    pub state: String,
+    /// Dossier hôte monté sur /config
+    pub config_path: Option<String>,
+    pub api_key_found: bool,
+}
+
+/// Résultat de l'adoption
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AdoptionReport {
+    pub pi_name: String,
+    pub host: String,
+    /// Dossier du docker-compose existant (label compose), None hors compose
+    pub compose_dir: Option<String>,
+    pub services: Vec<AdoptedService>,
+    /// Conteneurs sans équivalent JellySetup (laissés tels quels)
+    pub unmanaged: Vec<String>,
+    pub registered: bool,
+    pub warnings: Vec<String>,
+}
+
+/// Montages d'un conteneur (`docker inspect`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainerMounts {
+    pub working_dir: Option<String>,
+    /// (source hôte, destination conteneur)
+    pub mounts: Vec<(String, String)>,
+}
+
+/// "lscr.io/linuxserver/radarr:latest" -> "radarr"
+pub fn image_repository(image: &str) -> &str {
+    let without_digest = image.split('@').next().unwrap_or(image);
+    let name = without_digest.rsplit('/').next().unwrap_or(without_digest);
+    name.split(':').next().unwrap_or(name)
+}
+
+/// Service JellySetup correspondant à un conteneur existant
+pub fn map_service(stack: &StackDefinition, container: &ContainerInfo) -> Option<String> {
+    let repository = image_repository(&container.image);
+    stack
+        .services
+        .iter()
+        .find(|s| !s.image.contains("{{") && image_repository(&s.image) == repository)
+        .or_else(|| {
+            stack
+                .services
+                .iter()
+                .find(|s| s.name == container.name || s.name == container.service)
+        })
+        .map(|s| s.name.clone())
+}
+
+/// Parse la sortie d'INSPECT_COMMAND (une ligne par conteneur)
+pub fn parse_inspect(output: &str) -> HashMap<String, ContainerMounts> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().splitn(3, '|');
+            let name = fields.next()?.trim_start_matches('/').to_string();
+            let working_dir = fields.next().map(str::trim).filter(|d| !d.is_empty() && *d != "<no value>");
+            let mounts = fields
+                .next()
+                .unwrap_or_default()
+                .split(';')
+                .filter_map(|m| m.split_once('>'))
+                .map(|(source, destination)| (source.to_string(), destination.to_string()))
+                .collect();
+            Some((name, ContainerMounts { working_dir: working_dir.map(String::from), mounts }))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+fn config_path(mounts: Option<&ContainerMounts>) -> Option<String> {
+    mounts?
+        .mounts
+        .iter()
+        .find(|(_, destination)| destination == "/config")
+        .map(|(source, _)| source.clone())
+}
+
+/// Lit la clé API d'un *arr dans son conteneur
+async fn read_arr_api_key(target: &SshTarget<'_>, container: &str) -> Option<String> {
+    let xml = target
+        .exec(&format!("docker exec {} cat /config/config.xml 2>/dev/null", container))
+        .await
+        .ok()?;
+    crate::services::parse_config_xml_api_key(&xml)
+}
+
+/// Inventaire du Pi: services reconnus, clés API et conteneurs non gérés
+pub async fn discover(target: &SshTarget<'_>, pi_name: &str, host: &str) -> Result<(AdoptionReport, HashMap<String, String>)> {
+    let containers = crate::docker::ps(target).await?;
+    if containers.is_empty() {
+        return Err(anyhow!("Aucun conteneur Docker sur {}: rien à adopter", host));
+    }
+    let mounts = parse_inspect(&target.exec(INSPECT_COMMAND).await.unwrap_or_default());
+    let stack = StackDefinition::embedded();
+
+    let mut report = AdoptionReport {
+        pi_name: pi_name.to_string(),
+        host: host.to_string(),
+        compose_dir: None,
+        services: Vec::new(),
+        unmanaged: Vec::new(),
+        registered: false,
+        warnings: Vec::new(),
+    };
+    let mut api_keys = HashMap::new();
+
+    for container in &containers {
+        let container_mounts = mounts.get(&container.name);
+        let Some(service) = map_service(&stack, container) else {
+            report.unmanaged.push(container.name.clone());
+            continue;
+        };
+        if report.services.iter().any(|s| s.service == service) {
+            report.warnings.push(format!("{} en double ({}): seul le premier conteneur est géré", service, container.name));
+            continue;
+        }
+        if report.compose_dir.is_none() {
+            report.compose_dir = container_mounts.and_then(|m| m.working_dir.clone());
+        }
+
+        let mut api_key_found = false;
+        if ARR_SERVICES.contains(&service.as_str()) {
+            match read_arr_api_key(target, &container.name).await {
+                Some(key) => {
+                    api_key_found = true;
+                    api_keys.insert(service.clone(), key);
+                }
+                None => report.warnings.push(format!("Clé API de {} introuvable", service)),
+            }
+        }
+
+        report.services.push(AdoptedService {
+            service,
+            container: container.name.clone(),
+            image: container.image.clone(),
+            state: container.state.clone(),
+            config_path: config_path(container_mounts),
+            api_key_found,
+        });
+    }
+
+    if report.services.is_empty() {
+        return Err(anyhow!("Aucun service Jellyfin/*arr reconnu parmi {} conteneurs", containers.len()));
+    }
+    if report.compose_dir.as_deref().is_none_or(|dir| !dir.ends_with("/media-stack")) {
+        report.warnings.push("Stack hors de ~/media-stack: mises à jour et sauvegardes à vérifier manuellement".to_string());
+    }
+    Ok((report, api_keys))
+}
+
+/// Adopte le Pi: inventaire, puis enregistrement Supabase et registre local
+pub async fn adopt(
+    target: &SshTarget<'_>,
+    pi_name: &str,
+) -> Result<AdoptionReport> {
+    let (host, username) = match target {
+        SshTarget::Key { host, username, .. } | SshTarget::Password { host, username, .. } => (*host, *username),
+    };
+    tracing::info!("Inspecting existing stack on {}...", host);
+    let (mut report, api_keys) = discover(target, pi_name, host).await?;
+
+    let auth = match target {
+        SshTarget::Key { .. } => RegistrationAuth::Key { ssh_public_key: None, ssh_private_key_encrypted: None },
+        SshTarget::Password { .. } => RegistrationAuth::Password,
+    };
+    let fingerprint = crate::ssh::get_last_host_fingerprint();
+    let registration = InstallationRegistration::new(pi_name, host, auth, fingerprint.as_deref());
+
+    match crate::cloud::backend().save_installation(&registration).await {
+        Ok(config_id) => {
+            let key = |service: &str| api_keys.get(service).map(String::as_str);
+            let credentials = crate::supabase::PiCredentials {
+                radarr_api_key: key("radarr"),
+                sonarr_api_key: key("sonarr"),
+                prowlarr_api_key: key("prowlarr"),
+                ..Default::default()
+            };
+            if let Err(e) = crate::supabase::save_pi_config(pi_name, &config_id, &credentials).await {
+                report.warnings.push(format!("Clés API non sauvegardées: {}", e));
+            }
+            for service in &report.services {
+                crate::cloud::backend().save_service(
+                    pi_name, &service.service, None, &service.state, None, Some(&service.image),
+                    Some(serde_json::json!({ "container": service.container, "config_path": service.config_path, "adopted": true })),
+                ).await.ok();
+            }
+        }
+        Err(e) => report.warnings.push(format!("Enregistrement Supabase impossible: {}", e)),
+    }
+
+    if let SshTarget::Key { private_key, .. } = target {
+        crate::pi_registry::store_private_key(pi_name, private_key)?;
+    }
+    crate::pi_registry::add(crate::pi_registry::RegisteredPi::from_registration(&registration, username)).await?;
+    report.registered = true;
+
+    tracing::info!(
+        "✅ {} adopted: {} services, {} unmanaged containers",
+        pi_name,
+        report.services.len(),
+        report.unmanaged.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(name: &str, image: &str) -> ContainerInfo {
+        ContainerInfo {
+            name: name.to_string(),
+            service: String::new(),
+            image: image.to_string(),
+            state: "running".to_string(),
+            status: "Up 3 days".to_string(),
+            ports: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_map_existing_containers() {
+        assert_eq!(image_repository("lscr.io/linuxserver/radarr:latest"), "radarr");
+        assert_eq!(image_repository("ghcr.io/hotio/sonarr@sha256:abc"), "sonarr");
+        assert_eq!(image_repository("jellyfin/jellyfin"), "jellyfin");
+
+        let stack = StackDefinition::embedded();
+        assert_eq!(map_service(&stack, &container("films", "ghcr.io/hotio/radarr:release")).as_deref(), Some("radarr"));
+        assert_eq!(map_service(&stack, &container("jellyfin", "jellyfin/jellyfin:10.9")).as_deref(), Some("jellyfin"));
+        assert_eq!(map_service(&stack, &container("pihole", "pihole/pihole:latest")), None);
+
+        let mounts = parse_inspect(
+            "/radarr|/home/pi/docker|/home/pi/docker/radarr>/config;/mnt/media>/data;\n/pihole|<no value>|\n",
+        );
+        assert_eq!(mounts["radarr"].working_dir.as_deref(), Some("/home/pi/docker"));
+        assert_eq!(config_path(mounts.get("radarr")).as_deref(), Some("/home/pi/docker/radarr"));
+        assert_eq!(mounts["pihole"], ContainerMounts::default());
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::preflight::{CheckStatus, PreflightCheck};
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Compte AllDebrid: abonnement, expiration, quotas (fair-use)
+// =============================================================================
+//
+// Sans abonnement actif Decypharr ne débride plus rien: les médias "disparaissent"
+// sans erreur visible. Le compte est vérifié au pré-check, puis chaque jour par un
+// agent sur le Pi qui prévient via le webhook Discord avant l'expiration.
+
+const API_URL: &str = "https://api.alldebrid.com/v4/user";
+const UNLOCK_URL: &str = "https://api.alldebrid.com/v4/link/unlock";
+const AGENT: &str = "jellysetup";
+
+/// En dessous, l'expiration prochaine est signalée
+pub const WARN_DAYS_LEFT: i64 = 14;
+
+const AGENT_DIR: &str = "~/jellysetup-agent";
+const CRON_MARKER: &str = "# jellysetup-alldebrid";
+
+/// État du compte tel que renvoyé par /v4/user
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountStatus {
+    pub username: String,
+    pub premium: bool,
+    /// Timestamp Unix de fin d'abonnement
+    pub premium_until: Option<i64>,
+    /// Hébergeurs limités dont le quota (fair-use) est épuisé
+    pub exhausted_hosts: Vec<String>,
+}
+
+impl AccountStatus {
+    pub fn from_response(response: &Value) -> Result<Self> {
+        if response["status"] != "success" {
+            let message = response["error"]["message"].as_str().unwrap_or("réponse inattendue");
+            return Err(anyhow!("AllDebrid: {}", message));
+        }
+        let user = &response["data"]["user"];
+        let mut exhausted_hosts: Vec<String> = user["limitedHostersQuotas"]
+            .as_object()
+            .map(|quotas| {
+                quotas
+                    .iter()
+                    .filter(|(_, remaining)| remaining.as_i64() == Some(0))
+                    .map(|(host, _)| host.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        exhausted_hosts.sort();
+
+        Ok(Self {
+            username: user["username"].as_str().unwrap_or_default().to_string(),
+            premium: user["isPremium"].as_bool().unwrap_or(false),
+            premium_until: user["premiumUntil"].as_i64().filter(|t| *t > 0),
+            exhausted_hosts,
+        })
+    }
+
+    pub fn days_left(&self, now: i64) -> Option<i64> {
+        self.premium_until.map(|until| (until - now).div_euclid(86_400))
+    }
+}
+
+/// Vérification pré-installation (et avertissement in-app)
+pub fn evaluate(status: &AccountStatus, now: i64) -> PreflightCheck {
+    let (check_status, value, message) = match status.days_left(now) {
+        _ if !status.premium => (
+            CheckStatus::Fail,
+            "inactif".to_string(),
+            "Abonnement AllDebrid inactif: les médias ne pourront pas être lus".to_string(),
+        ),
+        Some(days) if days <= WARN_DAYS_LEFT => (
+            CheckStatus::Warn,
+            format!("{} j", days),
+            format!("L'abonnement AllDebrid expire dans {} jours: pensez à le renouveler", days),
+        ),
+        _ if !status.exhausted_hosts.is_empty() => (
+            CheckStatus::Warn,
+            "quota".to_string(),
+            format!("Quota fair-use épuisé pour: {}", status.exhausted_hosts.join(", ")),
+        ),
+        days => (
+            CheckStatus::Pass,
+            days.map_or("premium".to_string(), |d| format!("{} j", d)),
+            format!("Compte premium actif ({})", status.username),
+        ),
+    };
+    PreflightCheck { name: "alldebrid".to_string(), status: check_status, value, message }
+}
+
+/// Interroge l'API AllDebrid
+pub async fn fetch_status(api_key: &str) -> Result<AccountStatus> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(15)).build()?;
+    let response: Value = client
+        .get(API_URL)
+        .query(&[("agent", AGENT), ("apikey", api_key)])
+        .send()
+        .await?
+        .json()
+        .await?;
+    AccountStatus::from_response(&response)
+}
+
+/// Vérifie le compte maintenant (pré-check et alerte in-app)
+pub async fn check_account(api_key: &str) -> PreflightCheck {
+    match fetch_status(api_key).await {
+        Ok(status) => {
+            let check = evaluate(&status, chrono::Utc::now().timestamp());
+            tracing::info!("{:?}: {}", check.status, check.message);
+            check
+        }
+        Err(e) => PreflightCheck {
+            name: "alldebrid".to_string(),
+            status: CheckStatus::Warn,
+            value: "?".to_string(),
+            message: format!("Compte AllDebrid non vérifié: {}", e),
+        },
+    }
+}
+
+/// Débride un lien hébergeur (ou re-débride un lien expiré) via /v4/link/unlock
+pub async fn unlock_link(api_key: &str, link: &str) -> Result<String> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let response: Value = client
+        .get(UNLOCK_URL)
+        .query(&[("agent", AGENT), ("apikey", api_key), ("link", link)])
+        .send()
+        .await?
+        .json()
+        .await?;
+    if response["status"] != "success" {
+        let message = response["error"]["message"].as_str().unwrap_or("réponse inattendue");
+        return Err(anyhow!("AllDebrid: {}", message));
+    }
+    response["data"]["link"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("AllDebrid: lien débridé absent de la réponse"))
+}
+
+// =============================================================================
+// Agent quotidien sur le Pi
+// =============================================================================
+
+/// Lit alldebrid.json, écrit le dernier état et prévient Discord si besoin
+const AGENT_SCRIPT: &str = r#"import json, os, sys, time, urllib.parse, urllib.request
+
+base = os.path.expanduser("~/jellysetup-agent")
+with open(os.path.join(base, "alldebrid.json")) as f:
+    conf = json.load(f)
+
+url = "https://api.alldebrid.com/v4/user?" + urllib.parse.urlencode({"agent": "jellysetup", "apikey": conf["api_key"]})
+user = json.load(urllib.request.urlopen(url, timeout=20)).get("data", {}).get("user", {})
+until = user.get("premiumUntil") or 0
+days = int((until - time.time()) // 86400) if until else None
+exhausted = sorted(h for h, q in (user.get("limitedHostersQuotas") or {}).items() if q == 0)
+state = {"checked_at": int(time.time()), "premium": bool(user.get("isPremium")), "days_left": days, "exhausted_hosts": exhausted}
+with open(os.path.join(base, "alldebrid-status.json"), "w") as f:
+    json.dump(state, f)
+
+problem = None
+if not state["premium"]:
+    problem = "Abonnement AllDebrid inactif: les médias ne sont plus lisibles."
+elif days is not None and days <= conf["warn_days"]:
+    problem = "L'abonnement AllDebrid expire dans %d jours." % days
+elif exhausted:
+    problem = "Quota fair-use AllDebrid épuisé: " + ", ".join(exhausted)
+
+if problem and conf.get("discord_webhook"):
+    body = json.dumps({"username": "JellySetup", "embeds": [{"title": "⚠️ AllDebrid", "description": problem, "color": 16705372}]}).encode()
+    req = urllib.request.Request(conf["discord_webhook"], data=body, headers={"Content-Type": "application/json", "User-Agent": "jellysetup"})
+    urllib.request.urlopen(req, timeout=20)
+print(problem or "OK")
+"#;
+
+/// Installe l'agent (cron quotidien à 9h) et renvoie son premier résultat
+pub async fn install_agent(target: &SshTarget<'_>, api_key: &str, discord_webhook: Option<&str>) -> Result<String> {
+    let conf = json!({
+        "api_key": api_key,
+        "discord_webhook": discord_webhook.unwrap_or_default(),
+        "warn_days": WARN_DAYS_LEFT,
+    });
+    let cmd = format!(
+        "mkdir -p {dir} && umask 077 && cat > {dir}/alldebrid.json << 'EOFALLDEBRID'\n{conf}\nEOFALLDEBRID\n\
+         cat > {dir}/alldebrid_check.py << 'EOFALLDEBRID'\n{script}EOFALLDEBRID\n\
+         (crontab -l 2>/dev/null | grep -v '{marker}'; echo \"0 9 * * * python3 {dir}/alldebrid_check.py >/dev/null 2>&1 {marker}\") | crontab - && \
+         python3 {dir}/alldebrid_check.py",
+        dir = AGENT_DIR,
+        conf = conf,
+        script = AGENT_SCRIPT,
+        marker = CRON_MARKER
+    );
+    let output = target.exec(&cmd).await?;
+    let result = output.lines().last().unwrap_or_default().trim().to_string();
+    tracing::info!("✅ Daily account check installed ({})", result);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_evaluation() {
+        let now = 1_700_000_000;
+        let response = json!({
+            "status": "success",
+            "data": { "user": {
+                "username": "nico",
+                "isPremium": true,
+                "premiumUntil": now + 5 * 86_400 + 100,
+                "limitedHostersQuotas": { "ddownload": 0, "rapidgator": 2000 }
+            }}
+        });
+        let status = AccountStatus::from_response(&response).unwrap();
+        assert_eq!(status.days_left(now), Some(5));
+        assert_eq!(status.exhausted_hosts, vec!["ddownload"]);
+        assert_eq!(evaluate(&status, now).status, CheckStatus::Warn);
+
+        let renewed = AccountStatus { premium_until: Some(now + 90 * 86_400), exhausted_hosts: vec![], ..status.clone() };
+        assert_eq!(evaluate(&renewed, now).status, CheckStatus::Pass);
+
+        let lapsed = AccountStatus { premium: false, ..status };
+        assert_eq!(evaluate(&lapsed, now).status, CheckStatus::Fail);
+
+        let error = json!({ "status": "error", "error": { "code": "AUTH_BAD_APIKEY", "message": "The auth apikey is invalid" } });
+        assert!(AccountStatus::from_response(&error).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use ts_rs::TS;
+
+// =============================================================================
+// Journaux de l'application (tracing)
+// =============================================================================
+//
+// Deux sorties pour les événements tracing: la console (avec le module émetteur) et des
+// fichiers JSON (une ligne par événement) dans data_dir/jellysetup/logs/,
+// un par jour, MAX_LOG_FILES gardés. Chaque ligne passe par redact avant
+// d'être écrite. `recent` relit les derniers événements pour get_app_logs et
+// le bundle de support. Niveau par défaut: info, debug pour JellySetup
+// (surcharge possible avec RUST_LOG).
+
+const LOG_FILE_PREFIX: &str = "jellysetup";
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_FILTER: &str = "info,jellysetup=debug";
+pub const DEFAULT_RECENT_LIMIT: usize = 500;
+
+/// Garde le thread d'écriture des fichiers en vie jusqu'à la fin du programme
+static FILE_GUARD: OnceCell<WorkerGuard> = OnceCell::new();
+
+/// Événement relu depuis les fichiers de log
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AppLogEntry {
+    /// RFC 3339
+    pub timestamp: String,
+    pub level: String,
+    /// Module d'origine (jellysetup::flash, jellysetup::ssh...)
+    pub target: String,
+    pub message: String,
+    /// Spans englobants (install, ssh_exec...) et leurs champs
+    pub spans: Vec<String>,
+}
+
+pub fn logs_dir() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| anyhow!("Cannot determine data directory"))?
+        .join("jellysetup")
+        .join("logs"))
+}
+
+/// Écrit chaque événement formaté après masquage des secrets
+struct RedactingWriter(NonBlocking);
+
+impl Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let line = String::from_utf8_lossy(buf);
+        self.0.write_all(crate::redact::redact(&line).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+fn file_appender() -> Result<RollingFileAppender> {
+    let dir = logs_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)?)
+}
+
+/// Installe le subscriber tracing (console + fichiers), au démarrage de l'application
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let console = tracing_subscriber::fmt::layer();
+
+    match file_appender() {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = FILE_GUARD.set(guard);
+            let file = tracing_subscriber::fmt::layer()
+                .json()
+                .with_ansi(false)
+                .with_writer(move || RedactingWriter(writer.clone()));
+            tracing_subscriber::registry().with(filter).with(console).with(file).init();
+        }
+        Err(e) => {
+            tracing_subscriber::registry().with(filter).with(console).init();
+            tracing::warn!("⚠️ File logging disabled: {}", e);
+        }
+    }
+}
+
+/// Ligne JSON de tracing_subscriber en entrée lisible
+fn parse_line(line: &str) -> Option<AppLogEntry> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let text = |v: &serde_json::Value| v.as_str().unwrap_or_default().to_string();
+    let spans = value["spans"]
+        .as_array()
+        .map(|spans| {
+            spans
+                .iter()
+                .filter_map(|span| {
+                    let fields = span.as_object()?;
+                    let name = fields.get("name")?.as_str()?;
+                    let details: Vec<String> = fields
+                        .iter()
+                        .filter(|(key, _)| key.as_str() != "name")
+                        .map(|(key, value)| format!("{}={}", key, value.as_str().map(String::from).unwrap_or_else(|| value.to_string())))
+                        .collect();
+                    Some(if details.is_empty() { name.to_string() } else { format!("{}{{{}}}", name, details.join(" ")) })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(AppLogEntry {
+        timestamp: text(&value["timestamp"]),
+        level: text(&value["level"]),
+        target: text(&value["target"]),
+        message: text(&value["fields"]["message"]),
+        spans,
+    })
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "TRACE" => 0,
+        "DEBUG" => 1,
+        "INFO" => 2,
+        "WARN" => 3,
+        _ => 4,
+    }
+}
+
+/// Derniers événements (du plus ancien au plus récent), niveau minimum optionnel
+pub fn recent(limit: usize, min_level: Option<&str>) -> Result<Vec<AppLogEntry>> {
+    let dir = logs_dir()?;
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(LOG_FILE_PREFIX)))
+            .collect(),
+        Err(_) => return Ok(Vec::new()),
+    };
+    // Noms datés (jellysetup.2025-01-31.log): l'ordre alphabétique est chronologique
+    files.sort();
+
+    let min_rank = min_level.map(level_rank).unwrap_or(0);
+    let mut entries: Vec<AppLogEntry> = Vec::new();
+    for path in files.iter().rev() {
+        let file = std::fs::File::open(path)?;
+        let mut from_file: Vec<AppLogEntry> = BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| parse_line(&line))
+            .filter(|entry| level_rank(&entry.level) >= min_rank)
+            .collect();
+        from_file.append(&mut entries);
+        entries = from_file;
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_line() {
+        let line = r#"{"timestamp":"2025-01-31T10:00:00.000000Z","level":"WARN","fields":{"message":"[SSH] ⚠️ retry"},"target":"jellysetup::ssh","span":{"host":"192.168.1.20","name":"ssh_exec"},"spans":[{"host":"192.168.1.20","pi":"jellypi","name":"install"},{"host":"192.168.1.20","name":"ssh_exec"}]}"#;
+        let entry = parse_line(line).unwrap();
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.target, "jellysetup::ssh");
+        assert_eq!(entry.message, "[SSH] ⚠️ retry");
+        assert_eq!(entry.spans, ["install{host=192.168.1.20 pi=jellypi}", "ssh_exec{host=192.168.1.20}"]);
+        assert!(parse_line("not json").is_none());
+
+        assert!(level_rank("error") > level_rank("WARN"));
+        assert!(level_rank("DEBUG") < level_rank("INFO"));
+    }
+}
@@ -0,0 +1,510 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::Window;
+use ts_rs::TS;
+
+use crate::ssh::{self, SshTarget};
+use crate::verification::HealthReport;
+
+// =============================================================================
+// Sauvegarde et restauration des configurations des services
+// =============================================================================
+//
+// Une archive par dossier de ~/media-stack (configs des services, sans caches ni
+// logs) est construite sur le Pi, puis rapatriée en base64 sur un canal SSH
+// (décodée au fil de l'eau, pas de SFTP côté serveur requis). Taille et SHA-256
+// calculés sur le Pi sont vérifiés/consignés dans manifest.json, à côté des archives
+// dans data_dir/jellysetup/backups/<pi>/<date>. Envoi optionnel vers Supabase
+// Storage, référencé dans la table `backups` du Pi.
+//
+// La restauration (carte SD morte, Pi réinstallé) repart d'une sauvegarde locale
+// ou la retélécharge depuis Storage, renvoie les archives sur le Pi (empreintes
+// vérifiées avant de toucher au stack), arrête les conteneurs, remplace les
+// dossiers (propriétaire PUID/PGID du compose), redémarre puis vérifie le stack.
+
+const REMOTE_ARCHIVE: &str = "/tmp/jellysetup-backup.tgz";
+/// Données régénérables exclues des archives
+const EXCLUDES: [&str; 6] = ["*/cache", "*/Cache", "*/transcodes", "*/logs", "*/MediaCover", "*.pid"];
+const ARCHIVE_TIMEOUT_SECS: u64 = 1800;
+
+/// Archive d'un service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct BackupArchive {
+    pub service: String,
+    /// Chemin local de l'archive
+    pub file: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub sha256: String,
+    /// Chemin Supabase Storage si envoyée
+    pub storage_path: Option<String>,
+}
+
+/// manifest.json d'une sauvegarde
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct BackupManifest {
+    /// Identifiant de la sauvegarde (nom du dossier, "YYYYmmdd-HHMMSS")
+    pub id: String,
+    pub pi_name: String,
+    /// RFC 3339
+    pub created_at: String,
+    pub directory: String,
+    pub archives: Vec<BackupArchive>,
+}
+
+/// Événement "backup-progress"
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct BackupProgress {
+    pub service: String,
+    /// Position du service (à partir de 1)
+    pub index: usize,
+    pub total: usize,
+    #[ts(type = "number")]
+    pub received_bytes: u64,
+    #[ts(type = "number")]
+    pub expected_bytes: u64,
+}
+
+/// Décodeur base64 incrémental vers un fichier
+struct Base64Sink<W: Write> {
+    pending: String,
+    out: W,
+    written: u64,
+    error: Option<String>,
+}
+
+impl<W: Write> Base64Sink<W> {
+    fn new(out: W) -> Self {
+        Self { pending: String::new(), out, written: 0, error: None }
+    }
+
+    /// Décode tous les groupes complets de 4 caractères reçus
+    fn feed(&mut self, chunk: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        self.pending.extend(chunk.chars().filter(|c| !c.is_whitespace()));
+        let complete = self.pending.len() - self.pending.len() % 4;
+        if complete == 0 {
+            return;
+        }
+        let result = BASE64
+            .decode(&self.pending[..complete])
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| self.out.write_all(&bytes).map(|_| bytes.len()).map_err(|e| e.to_string()));
+        match result {
+            Ok(len) => {
+                self.written += len as u64;
+                self.pending.drain(..complete);
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn finish(mut self) -> Result<u64> {
+        if let Some(e) = self.error {
+            return Err(anyhow!("Archive corrompue pendant le transfert: {}", e));
+        }
+        if !self.pending.is_empty() {
+            return Err(anyhow!("Transfert incomplet ({} caractères base64 en trop)", self.pending.len()));
+        }
+        self.out.flush()?;
+        Ok(self.written)
+    }
+}
+
+/// Dossiers de ~/media-stack sauvegardables ("ls -1 -d */")
+pub fn parse_service_dirs(output: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = output
+        .lines()
+        .map(|line| line.trim().trim_end_matches('/').to_string())
+        .filter(|d| !d.is_empty() && !d.starts_with('.'))
+        .filter(|d| d.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// "SIZE=123\nSHA256=abc..." -> (taille, empreinte)
+pub fn parse_archive_info(output: &str) -> Option<(u64, String)> {
+    let value = |key: &str| output.lines().find_map(|l| l.trim().strip_prefix(key).map(str::to_string));
+    let size = value("SIZE=")?.parse().ok()?;
+    let sha256 = value("SHA256=").filter(|s| s.len() == 64)?;
+    Some((size, sha256))
+}
+
+fn archive_command(service: &str, sudo: &str) -> String {
+    let excludes: Vec<String> = EXCLUDES.iter().map(|e| format!("--exclude='{}'", e)).collect();
+    format!(
+        "cd ~/media-stack && {sudo} tar czf {archive} {excludes} {service} && {sudo} chown $(id -u) {archive} && \
+         echo \"SIZE=$(stat -c%s {archive})\" && echo \"SHA256=$(sha256sum {archive} | cut -d' ' -f1)\"",
+        sudo = sudo,
+        archive = REMOTE_ARCHIVE,
+        excludes = excludes.join(" "),
+        service = service
+    )
+}
+
+fn backups_dir(pi_name: &str, backup_id: &str) -> Result<PathBuf> {
+    if backup_id.is_empty() || !backup_id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Err(anyhow!("Identifiant de sauvegarde invalide: {}", backup_id));
+    }
+    let dir = dirs::data_dir()
+        .ok_or_else(|| anyhow!("Cannot determine data directory"))?
+        .join("jellysetup")
+        .join("backups")
+        .join(pi_name)
+        .join(backup_id);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Rapatrie une archive du Pi (supprimée ensuite) dans `path`, `size` octets attendus
+/// `on_progress` reçoit le nombre d'octets reçus (au plus une fois par Mo)
+pub(crate) async fn pull_archive(
+    target: &SshTarget<'_>,
+    remote: &str,
+    size: u64,
+    path: &Path,
+    options: &ssh::SshOptions,
+    on_progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<()> {
+    let mut sink = Base64Sink::new(std::io::BufWriter::new(std::fs::File::create(path)?));
+    let mut last_emitted = 0u64;
+    target
+        .exec_streaming(&format!("base64 -w 0 {archive}; rm -f {archive}", archive = remote), options, &mut |chunk| {
+            sink.feed(chunk);
+            if sink.written >= last_emitted + 1024 * 1024 || sink.written == size {
+                last_emitted = sink.written;
+                on_progress(sink.written);
+            }
+        })
+        .await?;
+
+    let written = sink.finish()?;
+    if written != size {
+        std::fs::remove_file(path).ok();
+        return Err(anyhow!("Archive tronquée: {} octets reçus sur {}", written, size));
+    }
+    Ok(())
+}
+
+/// Archive un service sur le Pi et la rapatrie dans `dir`
+async fn fetch_service(
+    window: &Window,
+    target: &SshTarget<'_>,
+    service: &str,
+    (index, total): (usize, usize),
+    dir: &Path,
+    sudo: &str,
+) -> Result<BackupArchive> {
+    let options = ssh::SshOptions { command_timeout_secs: ARCHIVE_TIMEOUT_SECS, ..ssh::default_options() };
+    let info = target.exec_with_options(&archive_command(service, sudo), &options).await?;
+    let (size, sha256) = parse_archive_info(&info).ok_or_else(|| anyhow!("Archive de {} non créée: {}", service, info.trim()))?;
+
+    let path = dir.join(format!("{}.tar.gz", service));
+    pull_archive(target, REMOTE_ARCHIVE, size, &path, &options, &mut |received| {
+        let _ = window.emit(
+            "backup-progress",
+            BackupProgress {
+                service: service.to_string(),
+                index,
+                total,
+                received_bytes: received,
+                expected_bytes: size,
+            },
+        );
+    })
+    .await
+    .map_err(|e| anyhow!("{}: {}", service, e))?;
+    tracing::info!("✅ {} ({} bytes, sha256 {}...)", service, size, &sha256[..12]);
+
+    Ok(BackupArchive { service: service.to_string(), file: path.to_string_lossy().to_string(), size, sha256, storage_path: None })
+}
+
+/// Envoie une archive dans Supabase Storage et la référence dans la table `backups`
+async fn upload(pi_name: &str, backup_id: &str, archive: &mut BackupArchive) -> Result<()> {
+    let storage_path = crate::supabase::backup_archive_path(pi_name, backup_id, &archive.service);
+    crate::supabase::upload_backup_archive(pi_name, &storage_path, std::fs::read(&archive.file)?).await?;
+    let file_path = format!("~/media-stack/{}", archive.service);
+    crate::supabase::save_backup(
+        pi_name,
+        crate::supabase::BackupRecord {
+            backup_type: "config",
+            service_name: Some(&archive.service),
+            file_path: &file_path,
+            file_size: archive.size as i64,
+            checksum: &archive.sha256,
+            storage_path: &storage_path,
+            metadata: Some(serde_json::json!({ "source": "desktop_app", "backup_id": backup_id })),
+        },
+    )
+    .await?;
+    archive.storage_path = Some(storage_path);
+    Ok(())
+}
+
+/// Sauvegarde les configurations de tous les services du Pi
+pub async fn backup_now(window: &Window, target: &SshTarget<'_>, pi_name: &str, sudo: &str, upload_to_cloud: bool) -> Result<BackupManifest> {
+    let services = parse_service_dirs(&target.exec("cd ~/media-stack && ls -1 -d */ 2>/dev/null").await?);
+    if services.is_empty() {
+        return Err(anyhow!("Aucune configuration de service dans ~/media-stack"));
+    }
+    let id = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let dir = backups_dir(pi_name, &id)?;
+    let created_at = chrono::Utc::now().to_rfc3339();
+    tracing::info!("Backing up {} services of {} to {:?}", services.len(), pi_name, dir);
+
+    let compose = target.exec("cat ~/media-stack/docker-compose.yml").await?;
+    std::fs::write(dir.join("docker-compose.yml"), compose)?;
+
+    let mut archives = Vec::new();
+    for (i, service) in services.iter().enumerate() {
+        let mut archive = fetch_service(window, target, service, (i + 1, services.len()), &dir, sudo).await?;
+        if upload_to_cloud {
+            if let Err(e) = upload(pi_name, &id, &mut archive).await {
+                tracing::warn!("⚠️ Upload of {} failed: {}", service, e);
+            }
+        }
+        archives.push(archive);
+    }
+
+    let manifest = BackupManifest {
+        id,
+        pi_name: pi_name.to_string(),
+        created_at,
+        directory: dir.to_string_lossy().to_string(),
+        archives,
+    };
+    std::fs::write(dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+    tracing::info!("✅ Backup of {} complete", pi_name);
+    Ok(manifest)
+}
+
+// =============================================================================
+// Restauration
+// =============================================================================
+
+/// Événement "restore-progress"
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct RestoreProgress {
+    /// "download", "upload", "stop", "extract", "start", "verify"
+    pub step: String,
+    pub message: String,
+    pub percent: u32,
+}
+
+/// Résultat de la restauration
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct RestoreReport {
+    pub backup_id: String,
+    pub pi_name: String,
+    pub restored: Vec<String>,
+    pub health: HealthReport,
+}
+
+fn emit_restore(window: &Window, step: &str, percent: u32, message: &str) {
+    tracing::info!("{}% {}", percent, message);
+    let _ = window.emit(
+        "restore-progress",
+        RestoreProgress { step: step.to_string(), message: message.to_string(), percent },
+    );
+}
+
+/// PUID/PGID du docker-compose.yml (premier service qui les définit)
+pub fn compose_owner(compose: &str) -> Option<(u32, u32)> {
+    let value = |key: &str| {
+        compose.lines().find_map(|line| {
+            let line = line.trim().trim_start_matches('-').trim().trim_matches('"');
+            line.strip_prefix(key)?.trim_start_matches([':', '=']).trim().trim_matches('"').parse::<u32>().ok()
+        })
+    };
+    Some((value("PUID")?, value("PGID")?))
+}
+
+/// Ligne de la table `backups` -> archive (fichier local à écrire dans `dir`)
+fn archive_from_row(row: &serde_json::Value, dir: &Path) -> Option<BackupArchive> {
+    let service = row.get("service_name")?.as_str()?.to_string();
+    Some(BackupArchive {
+        file: dir.join(format!("{}.tar.gz", service)).to_string_lossy().to_string(),
+        size: row.get("file_size")?.as_u64()?,
+        sha256: row.get("checksum")?.as_str()?.to_string(),
+        storage_path: row.get("storage_path").and_then(|p| p.as_str()).map(String::from),
+        service,
+    })
+}
+
+/// Sauvegarde locale, sinon retéléchargée depuis Supabase Storage
+async fn fetch_manifest(window: &Window, pi_name: &str, backup_id: &str) -> Result<BackupManifest> {
+    let dir = backups_dir(pi_name, backup_id)?;
+    if let Ok(content) = std::fs::read_to_string(dir.join("manifest.json")) {
+        tracing::info!("Using local backup {:?}", dir);
+        return Ok(serde_json::from_str(&content)?);
+    }
+
+    let rows = crate::supabase::list_backup_archives(pi_name, backup_id).await?;
+    let archives: Vec<BackupArchive> = rows.iter().filter_map(|row| archive_from_row(row, &dir)).collect();
+    if archives.is_empty() {
+        return Err(anyhow!("Sauvegarde {} introuvable (ni locale ni dans le cloud)", backup_id));
+    }
+    for (i, archive) in archives.iter().enumerate() {
+        emit_restore(window, "download", (i * 20 / archives.len()) as u32, &format!("Téléchargement de {}...", archive.service));
+        let storage_path = archive.storage_path.as_deref().ok_or_else(|| anyhow!("{}: archive absente du cloud", archive.service))?;
+        let bytes = crate::supabase::download_backup_archive(pi_name, storage_path).await?;
+        if bytes.len() as u64 != archive.size {
+            return Err(anyhow!("Archive de {} incomplète: {} octets sur {}", archive.service, bytes.len(), archive.size));
+        }
+        std::fs::write(&archive.file, bytes)?;
+    }
+
+    let manifest = BackupManifest {
+        id: backup_id.to_string(),
+        pi_name: pi_name.to_string(),
+        created_at: rows.first().and_then(|r| r.get("created_at")).and_then(|c| c.as_str()).unwrap_or_default().to_string(),
+        directory: dir.to_string_lossy().to_string(),
+        archives,
+    };
+    std::fs::write(dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+    Ok(manifest)
+}
+
+fn remote_restore_path(service: &str) -> String {
+    format!("/tmp/jellysetup-restore-{}.tgz", service)
+}
+
+/// Envoie un fichier sur le Pi (entrée standard du channel) et vérifie son SHA-256
+pub(crate) async fn push_file(target: &SshTarget<'_>, local: &Path, remote: &str, sha256: &str) -> Result<()> {
+    let bytes = std::fs::read(local)?;
+    let options = ssh::SshOptions { command_timeout_secs: ARCHIVE_TIMEOUT_SECS, ..ssh::default_options() };
+    target.upload(remote, &bytes, &options).await?;
+    let checksum = target.exec(&format!("sha256sum {} | cut -d' ' -f1", remote)).await?;
+    if checksum.trim() != sha256 {
+        return Err(anyhow!("Empreinte invalide après envoi ({} au lieu de {})", checksum.trim(), sha256));
+    }
+    Ok(())
+}
+
+/// Envoie l'archive d'un service sur le Pi
+async fn push_archive(target: &SshTarget<'_>, archive: &BackupArchive) -> Result<()> {
+    push_file(target, Path::new(&archive.file), &remote_restore_path(&archive.service), &archive.sha256)
+        .await
+        .map_err(|e| anyhow!("{}: {}", archive.service, e))
+}
+
+/// Restaure une sauvegarde sur un Pi (fraîchement réinstallé)
+pub async fn restore_backup(window: &Window, target: &SshTarget<'_>, pi_name: &str, backup_id: &str, sudo: &str) -> Result<RestoreReport> {
+    let manifest = fetch_manifest(window, pi_name, backup_id).await?;
+    let total = manifest.archives.len();
+
+    // 1. Envoi et vérification de toutes les archives avant de toucher au stack
+    for (i, archive) in manifest.archives.iter().enumerate() {
+        emit_restore(window, "upload", 20 + (i * 40 / total) as u32, &format!("Envoi de {} vers le Pi...", archive.service));
+        push_archive(target, archive).await?;
+    }
+
+    let compose = target.exec("cat ~/media-stack/docker-compose.yml").await?;
+    let (uid, gid) = match compose_owner(&compose) {
+        Some(owner) => owner,
+        None => {
+            let env = crate::compose::ContainerEnv::resolve(None, None, None, &target.exec(crate::compose::ContainerEnv::DETECT_COMMAND).await?);
+            (env.puid, env.pgid)
+        }
+    };
+
+    // 2. Arrêt du stack et remplacement des dossiers
+    emit_restore(window, "stop", 60, "Arrêt des conteneurs...");
+    let options = ssh::SshOptions { command_timeout_secs: ARCHIVE_TIMEOUT_SECS, ..ssh::default_options() };
+    target.exec_with_options("cd ~/media-stack && docker compose stop", &options).await?;
+
+    let mut restored = Vec::new();
+    for (i, archive) in manifest.archives.iter().enumerate() {
+        emit_restore(window, "extract", 65 + (i * 15 / total) as u32, &format!("Restauration de {}...", archive.service));
+        let command = format!(
+            "cd ~/media-stack && {sudo} rm -rf {service} && {sudo} tar xzf {archive} && \
+             {sudo} chown -R {uid}:{gid} {service} && rm -f {archive}",
+            sudo = sudo,
+            service = archive.service,
+            archive = remote_restore_path(&archive.service),
+            uid = uid,
+            gid = gid
+        );
+        target.exec_with_options(&command, &options).await?;
+        restored.push(archive.service.clone());
+    }
+
+    // 3. Redémarrage et vérification
+    emit_restore(window, "start", 80, "Redémarrage du stack...");
+    target.exec_with_options("cd ~/media-stack && docker compose up -d --remove-orphans", &options).await?;
+    tokio::time::sleep(std::time::Duration::from_secs(crate::config::delays::JELLYFIN_BOOT_SECS)).await;
+
+    emit_restore(window, "verify", 90, "Vérification des services...");
+    let health = crate::verification::verify_stack(target, pi_name).await?;
+    emit_restore(window, "verify", 100, &format!("{} services restaurés", restored.len()));
+    if health.all_passed {
+        tracing::info!("✅ Backup {} restored on {}", backup_id, pi_name);
+    } else {
+        tracing::warn!("⚠️ Backup {} restored, {} checks failed", backup_id, health.failures());
+    }
+
+    Ok(RestoreReport { backup_id: backup_id.to_string(), pi_name: pi_name.to_string(), restored, health })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_parsing_and_decoding() {
+        assert_eq!(
+            parse_service_dirs("radarr/\njellyfin/\n.history/\nbad name/\nsonarr/\n"),
+            vec!["jellyfin", "radarr", "sonarr"]
+        );
+        let sha = "a".repeat(64);
+        assert_eq!(parse_archive_info(&format!("SIZE=2048\nSHA256={}\n", sha)), Some((2048, sha)));
+        assert_eq!(parse_archive_info("SIZE=\nSHA256=\n"), None);
+
+        // Découpage arbitraire du flux base64
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 256) as u8).collect();
+        let encoded = BASE64.encode(&data);
+        let mut sink = Base64Sink::new(Vec::new());
+        for chunk in encoded.as_bytes().chunks(7) {
+            sink.feed(std::str::from_utf8(chunk).unwrap());
+        }
+        sink.feed("\n");
+        assert_eq!(sink.written, 1000);
+        assert_eq!(sink.out, data);
+        assert_eq!(sink.finish().unwrap(), 1000);
+
+        let mut truncated = Base64Sink::new(Vec::new());
+        truncated.feed(&encoded[..encoded.len() - 2]);
+        assert!(truncated.finish().is_err());
+    }
+
+    #[test]
+    fn test_restore_helpers() {
+        let compose = "services:\n  radarr:\n    environment:\n      - TZ=Europe/Paris\n      - PUID=1001\n      - PGID=1002\n";
+        assert_eq!(compose_owner(compose), Some((1001, 1002)));
+        assert_eq!(compose_owner("services:\n  jellyfin:\n    environment:\n      PUID: \"1000\"\n      PGID: 1000\n"), Some((1000, 1000)));
+        assert_eq!(compose_owner("services: {}\n"), None);
+
+        let row = serde_json::json!({
+            "service_name": "radarr",
+            "file_size": 4096,
+            "checksum": "a".repeat(64),
+            "storage_path": "pi_salon/20260101-120000/radarr.tar.gz",
+        });
+        let archive = archive_from_row(&row, Path::new("/tmp/backup")).unwrap();
+        assert_eq!(archive.size, 4096);
+        assert!(archive.file.ends_with("radarr.tar.gz"));
+        assert!(archive_from_row(&serde_json::json!({ "service_name": "radarr" }), Path::new("/tmp")).is_none());
+        assert!(backups_dir("salon", "../etc").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use ts_rs::TS;
+
+use crate::FlashConfig;
+
+// =============================================================================
+// Reprise d'une configuration de partition boot interrompue
+// =============================================================================
+//
+// Si l'application s'arrête entre l'écriture de l'image et configure_boot_partition,
+// la carte démarre sans hostname, utilisateur ni SSH. Après l'écriture, un marqueur
+// data_dir/jellysetup/pending_boot.json est posé (carte et hostname, sans secrets)
+// et retiré une fois custom.toml écrit. Au lancement suivant, `detect` repère une
+// partition bootfs fraîchement flashée (cmdline.txt contient encore l'appel au
+// firstboot de raspberrypi-sys-mods) sans custom.toml, et le wizard propose de
+// terminer la configuration sans re-flasher.
+
+/// Marqueur posé entre l'écriture de l'image et la configuration du boot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingBootConfig {
+    sd_path: String,
+    hostname: String,
+    /// RFC 3339
+    written_at: String,
+}
+
+/// Carte flashée mais jamais configurée
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct UnconfiguredCard {
+    /// Point de montage de la partition boot
+    pub boot_path: String,
+    /// Carte et hostname du flash interrompu (si le marqueur existe encore)
+    pub sd_path: Option<String>,
+    pub hostname: Option<String>,
+    pub written_at: Option<String>,
+}
+
+fn marker_path() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| anyhow!("Cannot determine data directory"))?
+        .join("jellysetup")
+        .join("pending_boot.json"))
+}
+
+fn load_marker() -> Option<PendingBootConfig> {
+    marker_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// Pose le marqueur (image écrite, boot pas encore configuré)
+pub fn mark_pending(config: &FlashConfig) {
+    let marker = PendingBootConfig {
+        sd_path: config.sd_path.clone(),
+        hostname: config.hostname.clone(),
+        written_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let saved = marker_path().and_then(|path| {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::write(path, serde_json::to_string_pretty(&marker)?)?)
+    });
+    if let Err(e) = saved {
+        tracing::warn!("⚠️ Could not write pending marker: {}", e);
+    }
+}
+
+/// Retire le marqueur (boot configuré)
+pub fn clear_pending() {
+    if let Ok(path) = marker_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Points de montage possibles de la partition boot
+fn candidate_boot_paths() -> Vec<PathBuf> {
+    if cfg!(target_os = "macos") {
+        ["bootfs", "boot", "BOOTFS", "BOOT", "NO NAME"]
+            .iter()
+            .map(|name| Path::new("/Volumes").join(name))
+            .collect()
+    } else if cfg!(windows) {
+        ('D'..='Z').map(|letter| PathBuf::from(format!("{}:\\", letter))).collect()
+    } else {
+        let user = std::env::var("USER").unwrap_or_default();
+        vec![
+            Path::new("/media").join(&user).join("bootfs"),
+            Path::new("/run/media").join(&user).join("bootfs"),
+            PathBuf::from("/media/bootfs"),
+        ]
+    }
+}
+
+/// Image jamais démarrée dont le premier boot passe par le firstboot de
+/// raspberrypi-sys-mods, qui lit custom.toml (il retire cet appel de cmdline.txt)
+pub fn supports_custom_toml(boot_path: &Path) -> bool {
+    std::fs::read_to_string(boot_path.join("cmdline.txt"))
+        .is_ok_and(|cmdline| cmdline.contains("raspberrypi-sys-mods/firstboot"))
+}
+
+/// Partition boot d'une image Raspberry Pi OS jamais démarrée et jamais configurée
+pub fn is_unconfigured(boot_path: &Path) -> bool {
+    supports_custom_toml(boot_path) && !boot_path.join("custom.toml").exists()
+}
+
+/// Cherche une carte flashée mais non configurée parmi les volumes montés
+pub fn detect() -> Option<UnconfiguredCard> {
+    let boot_path = candidate_boot_paths().into_iter().find(|path| is_unconfigured(path))?;
+    let marker = load_marker();
+    tracing::info!(
+        "Unconfigured card found at {:?} (interrupted flash: {})",
+        boot_path,
+        marker.is_some()
+    );
+    Some(UnconfiguredCard {
+        boot_path: boot_path.to_string_lossy().to_string(),
+        sd_path: marker.as_ref().map(|m| m.sd_path.clone()),
+        hostname: marker.as_ref().map(|m| m.hostname.clone()),
+        written_at: marker.map(|m| m.written_at),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unconfigured_boot_partition() {
+        let dir = std::env::temp_dir().join(format!("jellysetup-bootfs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Pas de cmdline.txt: pas une partition boot Raspberry Pi OS
+        assert!(!is_unconfigured(&dir));
+
+        std::fs::write(
+            dir.join("cmdline.txt"),
+            "console=serial0,115200 root=PARTUUID=1234-02 rootwait init=/usr/lib/raspberrypi-sys-mods/firstboot",
+        )
+        .unwrap();
+        assert!(is_unconfigured(&dir));
+
+        std::fs::write(dir.join("custom.toml"), "config_version = 1").unwrap();
+        assert!(!is_unconfigured(&dir));
+
+        // Déjà démarrée: le firstboot a été retiré de cmdline.txt
+        std::fs::remove_file(dir.join("custom.toml")).unwrap();
+        std::fs::write(dir.join("cmdline.txt"), "console=serial0,115200 root=PARTUUID=1234-02 rootwait").unwrap();
+        assert!(!is_unconfigured(&dir));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
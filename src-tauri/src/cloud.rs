@@ -0,0 +1,216 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::master_config::MasterConfig;
+use crate::registration::InstallationRegistration;
+use crate::secret::SecretString;
+
+// =============================================================================
+// Backend cloud (Supabase JellySetup, Supabase auto-hébergé ou local uniquement)
+// =============================================================================
+//
+// Les enregistrements de l'installation (Pi, statut, logs, services, état) et la
+// master_config passent par `backend()`, choisi dans les réglages:
+//   hosted       projet Supabase de JellySetup (URL et clé anon injectées au build,
+//                jamais la clé service)
+//   self_hosted  instance Supabase/PostgREST de l'utilisateur (Edge Functions
+//                jellysetup-* déployées), URL et clé service dans les réglages
+//   local        rien n'est envoyé: master_config par défaut, identifiants "local"
+// Les autres fonctions de supabase.rs (Storage, sauvegardes, médias) suivent le
+// même endpoint et sont refusées en mode local (supabase::http_client).
+
+/// Backend sélectionné dans les réglages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum CloudBackendKind {
+    #[default]
+    Hosted,
+    SelfHosted,
+    Local,
+}
+
+/// URL et clés d'un projet Supabase
+#[derive(Debug, Clone)]
+pub struct SupabaseEndpoint {
+    pub url: String,
+    /// Clé service (instance auto-hébergée uniquement)
+    pub service_key: Option<SecretString>,
+    pub anon_key: SecretString,
+}
+
+impl SupabaseEndpoint {
+    /// Projet JellySetup (valeurs injectées au build via .env)
+    pub fn hosted() -> Self {
+        Self {
+            url: option_env!("SUPABASE_URL").unwrap_or("https://ncxowprkehliisvnpmlt.supabase.co").to_string(),
+            service_key: None,
+            anon_key: option_env!("SUPABASE_ANON_KEY").unwrap_or("your-anon-key").into(),
+        }
+    }
+
+    /// Instance de l'utilisateur (la clé service sert aussi de clé anon)
+    fn self_hosted(url: &str, key: &SecretString) -> Self {
+        Self { url: url.trim_end_matches('/').to_string(), service_key: Some(key.clone()), anon_key: key.clone() }
+    }
+}
+
+/// Opérations d'enregistrement d'une installation
+#[async_trait::async_trait]
+pub trait CloudBackend: Send + Sync {
+    /// Identifiant de la config créée ("local" si non enregistrée)
+    async fn save_installation(&self, registration: &InstallationRegistration) -> Result<String>;
+
+    async fn update_status(&self, pi_name: &str, config_id: &str, status: &str, error: Option<&str>) -> Result<()>;
+
+    async fn add_log(&self, pi_name: &str, step: &str, level: &str, message: &str, duration_ms: Option<i64>) -> Result<()>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn save_service(
+        &self,
+        pi_name: &str,
+        service_name: &str,
+        container_id: Option<&str>,
+        status: &str,
+        port: Option<i32>,
+        image: Option<&str>,
+        config: Option<Value>,
+    ) -> Result<()>;
+
+    async fn save_install_state(&self, pi_name: &str, state: &Value) -> Result<()>;
+
+    /// master_config active (None: valeurs par défaut de l'application)
+    async fn fetch_master_config(&self, config_type: Option<&str>) -> Result<Option<MasterConfig>>;
+}
+
+/// Supabase, hébergé ou auto-hébergé (endpoint résolu par `endpoint()`)
+pub struct SupabaseBackend;
+
+#[async_trait::async_trait]
+impl CloudBackend for SupabaseBackend {
+    async fn save_installation(&self, registration: &InstallationRegistration) -> Result<String> {
+        crate::supabase::save_installation(registration).await
+    }
+
+    async fn update_status(&self, pi_name: &str, config_id: &str, status: &str, error: Option<&str>) -> Result<()> {
+        crate::supabase::update_status(pi_name, config_id, status, error).await
+    }
+
+    async fn add_log(&self, pi_name: &str, step: &str, level: &str, message: &str, duration_ms: Option<i64>) -> Result<()> {
+        crate::supabase::add_log(pi_name, step, level, message, duration_ms).await
+    }
+
+    async fn save_service(
+        &self,
+        pi_name: &str,
+        service_name: &str,
+        container_id: Option<&str>,
+        status: &str,
+        port: Option<i32>,
+        image: Option<&str>,
+        config: Option<Value>,
+    ) -> Result<()> {
+        crate::supabase::save_service(pi_name, service_name, container_id, status, port, image, config).await
+    }
+
+    async fn save_install_state(&self, pi_name: &str, state: &Value) -> Result<()> {
+        crate::supabase::save_install_state(pi_name, state).await
+    }
+
+    async fn fetch_master_config(&self, config_type: Option<&str>) -> Result<Option<MasterConfig>> {
+        crate::master_config::fetch_master_config(config_type).await
+    }
+}
+
+/// Aucun envoi: l'installation n'utilise que l'ordinateur et le Pi
+pub struct LocalOnlyBackend;
+
+#[async_trait::async_trait]
+impl CloudBackend for LocalOnlyBackend {
+    async fn save_installation(&self, registration: &InstallationRegistration) -> Result<String> {
+        tracing::info!("Local-only mode: installation of {} not registered", registration.pi_name);
+        Ok("local".to_string())
+    }
+
+    async fn update_status(&self, _pi_name: &str, _config_id: &str, _status: &str, _error: Option<&str>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn add_log(&self, _pi_name: &str, _step: &str, _level: &str, _message: &str, _duration_ms: Option<i64>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn save_service(
+        &self,
+        _pi_name: &str,
+        _service_name: &str,
+        _container_id: Option<&str>,
+        _status: &str,
+        _port: Option<i32>,
+        _image: Option<&str>,
+        _config: Option<Value>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn save_install_state(&self, _pi_name: &str, _state: &Value) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fetch_master_config(&self, _config_type: Option<&str>) -> Result<Option<MasterConfig>> {
+        Ok(None)
+    }
+}
+
+fn endpoint_for(settings: &crate::config::Settings) -> Option<SupabaseEndpoint> {
+    match settings.cloud_backend {
+        CloudBackendKind::Hosted => Some(SupabaseEndpoint::hosted()),
+        CloudBackendKind::SelfHosted => match (&settings.self_hosted_url, &settings.self_hosted_key) {
+            (Some(url), Some(key)) => Some(SupabaseEndpoint::self_hosted(url, key)),
+            _ => None,
+        },
+        CloudBackendKind::Local => None,
+    }
+}
+
+/// Endpoint Supabase courant (None en mode local)
+pub fn endpoint() -> Option<SupabaseEndpoint> {
+    endpoint_for(&crate::config::settings())
+}
+
+/// Backend courant, d'après les réglages
+pub fn backend() -> Box<dyn CloudBackend> {
+    match crate::config::settings().cloud_backend {
+        CloudBackendKind::Local => Box::new(LocalOnlyBackend),
+        CloudBackendKind::Hosted | CloudBackendKind::SelfHosted => Box::new(SupabaseBackend),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cloud_endpoint() {
+        let settings = crate::config::Settings::default();
+        assert_eq!(settings.cloud_backend, CloudBackendKind::Hosted);
+        assert_eq!(endpoint_for(&settings).map(|e| e.url), Some(SupabaseEndpoint::hosted().url));
+        assert!(SupabaseEndpoint::hosted().service_key.is_none());
+
+        let self_hosted = crate::config::Settings {
+            cloud_backend: CloudBackendKind::SelfHosted,
+            self_hosted_url: Some("https://supabase.maison.lan/".to_string()),
+            self_hosted_key: Some("cle-service".into()),
+            ..Default::default()
+        };
+        let endpoint = endpoint_for(&self_hosted).unwrap();
+        assert_eq!(endpoint.url, "https://supabase.maison.lan");
+        assert_eq!(endpoint.anon_key.expose(), "cle-service");
+
+        let local = crate::config::Settings { cloud_backend: CloudBackendKind::Local, ..Default::default() };
+        assert!(endpoint_for(&local).is_none());
+        assert_eq!(serde_json::to_value(CloudBackendKind::SelfHosted).unwrap(), "self_hosted");
+    }
+}
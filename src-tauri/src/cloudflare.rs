@@ -0,0 +1,300 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::secret::SecretString;
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Tunnel Cloudflare: provisionnement complet depuis un token API
+// =============================================================================
+//
+// À partir d'un token API (permissions Zone:DNS:Edit + Account:Cloudflare Tunnel:Edit):
+// 1. retrouve la zone du domaine (et son compte)
+// 2. crée le tunnel (ou réutilise celui du même nom), configuration locale
+// 3. crée/met à jour les CNAME <sous-domaine> -> <tunnel>.cfargotunnel.com
+// 4. écrit ~/media-stack/cloudflared/config.yml (ingress) sur le Pi
+// Le token du tunnel remplace alors le token collé à la main (CLOUDFLARE_TOKEN);
+// cloudflared lit config.yml dans /etc/cloudflared, monté depuis le Pi.
+
+const API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+/// Dossier de config cloudflared sur le Pi (monté sur /etc/cloudflared)
+pub const REMOTE_CONFIG_DIR: &str = "~/media-stack/cloudflared";
+
+fn default_jellyfin_subdomain() -> String {
+    "jellyfin".to_string()
+}
+
+fn default_jellyseerr_subdomain() -> String {
+    "jellyseerr".to_string()
+}
+
+/// Paramètres de provisionnement saisis dans le wizard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudflareSetup {
+    /// Token API (Zone:DNS:Edit + Cloudflare Tunnel:Edit)
+    pub api_token: SecretString,
+    /// Domaine géré par Cloudflare (ex: exemple.fr)
+    pub domain: String,
+    #[serde(default = "default_jellyfin_subdomain")]
+    pub jellyfin_subdomain: String,
+    #[serde(default = "default_jellyseerr_subdomain")]
+    pub jellyseerr_subdomain: String,
+}
+
+/// Route publique -> service du réseau docker
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngressRoute {
+    pub hostname: String,
+    pub service: String,
+}
+
+impl CloudflareSetup {
+    /// Routes exposées (Jellyseerr seulement s'il fait partie du stack)
+    pub fn routes(&self, jellyseerr: bool) -> Vec<IngressRoute> {
+        let mut routes = vec![IngressRoute {
+            hostname: format!("{}.{}", self.jellyfin_subdomain, self.domain),
+            service: "http://jellyfin:8096".to_string(),
+        }];
+        if jellyseerr {
+            routes.push(IngressRoute {
+                hostname: format!("{}.{}", self.jellyseerr_subdomain, self.domain),
+                service: "http://jellyseerr:5055".to_string(),
+            });
+        }
+        routes
+    }
+}
+
+/// Tunnel prêt à être lancé par le conteneur cloudflared
+#[derive(Debug, Clone)]
+pub struct ProvisionedTunnel {
+    pub tunnel_id: String,
+    /// Token de connexion (TUNNEL_TOKEN)
+    pub token: String,
+    pub routes: Vec<IngressRoute>,
+}
+
+/// config.yml de cloudflared (règle finale obligatoire: 404)
+pub fn ingress_config(tunnel_id: &str, routes: &[IngressRoute]) -> String {
+    let mut yaml = format!("tunnel: {}\ningress:\n", tunnel_id);
+    for route in routes {
+        yaml.push_str(&format!("  - hostname: {}\n    service: {}\n", route.hostname, route.service));
+    }
+    yaml.push_str("  - service: http_status:404\n");
+    yaml
+}
+
+/// Hostnames publics d'un config.yml (vérification post-installation)
+pub fn ingress_hostnames(config: &str) -> Vec<String> {
+    config
+        .lines()
+        .filter_map(|line| line.trim().trim_start_matches("- ").strip_prefix("hostname:"))
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .collect()
+}
+
+// =============================================================================
+// Client API Cloudflare
+// =============================================================================
+
+struct Zone {
+    id: String,
+    account_id: String,
+}
+
+struct CloudflareClient {
+    client: reqwest::Client,
+    api_token: SecretString,
+}
+
+impl CloudflareClient {
+    fn new(api_token: &str) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build()?;
+        Ok(Self { client, api_token: api_token.into() })
+    }
+
+    /// Appel API; renvoie `result` ou les erreurs Cloudflare
+    /// Les paramètres de `query` sont encodés par reqwest (noms de domaine saisis par l'utilisateur)
+    async fn call(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<Value>,
+    ) -> Result<Value> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", API_BASE, path))
+            .query(query)
+            .bearer_auth(self.api_token.expose());
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response: Value = request.send().await?.json().await?;
+        if response["success"].as_bool() != Some(true) {
+            let errors: Vec<String> = response["errors"]
+                .as_array()
+                .map(|errors| {
+                    errors
+                        .iter()
+                        .map(|e| format!("{} ({})", e["message"].as_str().unwrap_or("?"), e["code"]))
+                        .collect()
+                })
+                .unwrap_or_default();
+            return Err(anyhow!("Cloudflare API {}: {}", path, errors.join(", ")));
+        }
+        Ok(response["result"].clone())
+    }
+
+    async fn zone(&self, domain: &str) -> Result<Zone> {
+        let zones = self.call(reqwest::Method::GET, "/zones", &[("name", domain)], None).await?;
+        let zone = zones
+            .as_array()
+            .and_then(|zones| zones.first())
+            .ok_or_else(|| anyhow!("Domaine {} introuvable sur ce compte Cloudflare", domain))?;
+        Ok(Zone {
+            id: zone["id"].as_str().unwrap_or_default().to_string(),
+            account_id: zone["account"]["id"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Tunnel existant du même nom, sinon nouveau tunnel (configuration locale)
+    async fn ensure_tunnel(&self, account_id: &str, name: &str) -> Result<String> {
+        let existing = self
+            .call(
+                reqwest::Method::GET,
+                &format!("/accounts/{}/cfd_tunnel", account_id),
+                &[("name", name), ("is_deleted", "false")],
+                None,
+            )
+            .await?;
+        if let Some(id) = existing.as_array().and_then(|t| t.first()).and_then(|t| t["id"].as_str()) {
+            tracing::info!("Reusing tunnel {} ({})", name, id);
+            return Ok(id.to_string());
+        }
+
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let created = self
+            .call(
+                reqwest::Method::POST,
+                &format!("/accounts/{}/cfd_tunnel", account_id),
+                &[],
+                Some(json!({ "name": name, "config_src": "local", "tunnel_secret": BASE64.encode(secret) })),
+            )
+            .await?;
+        let id = created["id"].as_str().ok_or_else(|| anyhow!("Tunnel créé sans identifiant"))?;
+        tracing::info!("✅ Tunnel {} created ({})", name, id);
+        Ok(id.to_string())
+    }
+
+    async fn tunnel_token(&self, account_id: &str, tunnel_id: &str) -> Result<String> {
+        let token = self
+            .call(reqwest::Method::GET, &format!("/accounts/{}/cfd_tunnel/{}/token", account_id, tunnel_id), &[], None)
+            .await?;
+        token.as_str().map(String::from).ok_or_else(|| anyhow!("Token du tunnel absent de la réponse"))
+    }
+
+    /// CNAME proxifié vers le tunnel (créé ou redirigé s'il existe déjà)
+    async fn ensure_dns_route(&self, zone_id: &str, hostname: &str, tunnel_id: &str) -> Result<()> {
+        let record = json!({
+            "type": "CNAME",
+            "name": hostname,
+            "content": format!("{}.cfargotunnel.com", tunnel_id),
+            "proxied": true,
+        });
+        let existing = self
+            .call(reqwest::Method::GET, &format!("/zones/{}/dns_records", zone_id), &[("name", hostname)], None)
+            .await?;
+
+        match existing.as_array().and_then(|r| r.first()).and_then(|r| r["id"].as_str()) {
+            Some(record_id) => {
+                let path = format!("/zones/{}/dns_records/{}", zone_id, record_id);
+                self.call(reqwest::Method::PUT, &path, &[], Some(record)).await?;
+            }
+            None => {
+                self.call(reqwest::Method::POST, &format!("/zones/{}/dns_records", zone_id), &[], Some(record)).await?;
+            }
+        }
+        tracing::info!("✅ DNS route {} -> tunnel", hostname);
+        Ok(())
+    }
+}
+
+/// Crée tunnel et routes DNS pour le Pi (tunnel nommé "jellysetup-<pi>")
+pub async fn provision(setup: &CloudflareSetup, pi_name: &str, jellyseerr: bool) -> Result<ProvisionedTunnel> {
+    let client = CloudflareClient::new(setup.api_token.expose())?;
+    let zone = client.zone(&setup.domain).await?;
+    let tunnel_id = client.ensure_tunnel(&zone.account_id, &format!("jellysetup-{}", pi_name)).await?;
+
+    let routes = setup.routes(jellyseerr);
+    for route in &routes {
+        client.ensure_dns_route(&zone.id, &route.hostname, &tunnel_id).await?;
+    }
+
+    let token = client.tunnel_token(&zone.account_id, &tunnel_id).await?;
+    Ok(ProvisionedTunnel { tunnel_id, token, routes })
+}
+
+/// Écrit config.yml sur le Pi (lu par cloudflared au démarrage)
+pub async fn write_config(target: &SshTarget<'_>, tunnel: &ProvisionedTunnel) -> Result<()> {
+    let cmd = format!(
+        "mkdir -p {dir} && cat > {dir}/config.yml << 'EOFCLOUDFLARED'\n{config}EOFCLOUDFLARED\nchmod 644 {dir}/config.yml",
+        dir = REMOTE_CONFIG_DIR,
+        config = ingress_config(&tunnel.tunnel_id, &tunnel.routes)
+    );
+    target.exec(&cmd).await?;
+    tracing::info!("✅ Ingress config written ({} routes)", tunnel.routes.len());
+    Ok(())
+}
+
+/// Accès externe via le tunnel (toute réponse hors erreurs Cloudflare 52x/530)
+pub async fn check_external_access(hostname: &str) -> Result<String> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(15)).build()?;
+    let url = format!("https://{}/", hostname);
+
+    // Le DNS et le tunnel peuvent mettre une minute à se propager
+    let mut last_error = String::new();
+    for attempt in 1..=4 {
+        match client.get(&url).send().await {
+            Ok(response) if !(520..=530).contains(&response.status().as_u16()) => {
+                return Ok(format!("{} répond (HTTP {})", url, response.status().as_u16()));
+            }
+            Ok(response) => last_error = format!("HTTP {} (tunnel non connecté)", response.status().as_u16()),
+            Err(e) => last_error = e.to_string(),
+        }
+        if attempt < 4 {
+            tokio::time::sleep(Duration::from_secs(15)).await;
+        }
+    }
+    Err(anyhow!("{} injoignable: {}", url, last_error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ingress_config() {
+        let setup: CloudflareSetup =
+            serde_json::from_value(json!({ "api_token": "t", "domain": "exemple.fr" })).unwrap();
+        let routes = setup.routes(true);
+        assert_eq!(routes[1].hostname, "jellyseerr.exemple.fr");
+        assert_eq!(setup.routes(false).len(), 1);
+
+        let config = ingress_config("abc-123", &routes);
+        assert_eq!(
+            config,
+            "tunnel: abc-123\ningress:\n  - hostname: jellyfin.exemple.fr\n    service: http://jellyfin:8096\n  \
+             - hostname: jellyseerr.exemple.fr\n    service: http://jellyseerr:5055\n  - service: http_status:404\n"
+        );
+        assert_eq!(ingress_hostnames(&config), vec!["jellyfin.exemple.fr", "jellyseerr.exemple.fr"]);
+    }
+}
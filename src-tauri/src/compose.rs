@@ -0,0 +1,535 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::time::Duration;
+
+use crate::services::profiles::MediaProfile;
+use crate::template_engine::TemplateVars;
+
+// =============================================================================
+// Génération du docker-compose.yml depuis une définition déclarative du stack
+// =============================================================================
+//
+// Source de la définition (première disponible):
+// 1. master_config.stack (Supabase)
+// 2. clé "stack" de procedures/v2/steps.json sur GitHub
+// 3. même fichier embarqué dans le binaire
+// Les valeurs acceptent les variables {{VAR}} du template engine. Un service avec
+// `profile` n'est ajouté que si le profil est actif, un service avec `requires`
+// seulement si la variable correspondante est renseignée.
+
+/// Procédure embarquée (repli hors-ligne)
+pub(crate) const EMBEDDED_PROCEDURE: &str = include_str!("../../procedures/v2/steps.json");
+
+fn default_restart() -> String {
+    "unless-stopped".to_string()
+}
+
+fn default_network() -> String {
+    "media-network".to_string()
+}
+
+/// Service du stack tel que décrit dans la définition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceSpec {
+    pub name: String,
+    #[serde(default)]
+    pub comment: Option<String>,
+    pub image: String,
+    #[serde(default = "default_restart")]
+    pub restart: String,
+    /// Profil média requis (anime, music, audiobooks)
+    #[serde(default)]
+    pub profile: Option<MediaProfile>,
+    /// Variable à renseigner pour inclure le service (ex: CLOUDFLARE_TOKEN)
+    #[serde(default)]
+    pub requires: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub cap_add: Vec<String>,
+    #[serde(default)]
+    pub security_opt: Vec<String>,
+    #[serde(default)]
+    pub dns: Vec<String>,
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    /// Monte aussi la racine des médias si elle est hors de /mnt
+    #[serde(default)]
+    pub media_volume: bool,
+    #[serde(default)]
+    pub environment: Vec<String>,
+    #[serde(default)]
+    pub devices: Vec<String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub extra_hosts: Vec<String>,
+    #[serde(default)]
+    pub memory_limit: Option<String>,
+    #[serde(default)]
+    pub memory_reservation: Option<String>,
+    #[serde(default)]
+    pub cpus: Option<String>,
+    #[serde(default)]
+    pub logging: Option<Value>,
+    #[serde(default)]
+    pub healthcheck: Option<Value>,
+}
+
+/// Définition complète du stack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackDefinition {
+    #[serde(default = "default_network")]
+    pub network: String,
+    /// Volumes nommés déclarés en fin de fichier
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    pub services: Vec<ServiceSpec>,
+}
+
+impl StackDefinition {
+    /// Extrait la clé "stack" d'une procédure (steps.json)
+    pub fn from_procedure(procedure: &Value) -> Result<Self> {
+        let stack = procedure
+            .get("stack")
+            .ok_or_else(|| anyhow!("Procédure sans définition de stack"))?;
+        Ok(serde_json::from_value(stack.clone())?)
+    }
+
+    /// Définition embarquée dans le binaire
+    pub fn embedded() -> Self {
+        serde_json::from_str(EMBEDDED_PROCEDURE)
+            .map_err(anyhow::Error::from)
+            .and_then(|procedure| Self::from_procedure(&procedure))
+            .expect("procedures/v2/steps.json embarqué invalide")
+    }
+}
+
+async fn fetch_remote_stack() -> Result<StackDefinition> {
+    let procedure: Value = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .get(crate::config::settings().procedure_url(crate::procedure::PROCEDURE_VERSION))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    StackDefinition::from_procedure(&procedure)
+}
+
+/// Définition à utiliser pour cette installation
+pub async fn resolve_stack() -> StackDefinition {
+    if crate::offline_bundle::active() {
+        return match crate::offline_bundle::stack() {
+            Ok(stack) => {
+                tracing::info!("✅ Stack definition from offline bundle ({} services)", stack.services.len());
+                stack
+            }
+            Err(e) => {
+                tracing::warn!("⚠️ Offline bundle stack unavailable ({}), using embedded one", e);
+                StackDefinition::embedded()
+            }
+        };
+    }
+    let master = crate::cloud::backend().fetch_master_config(Some("streaming")).await.ok().flatten();
+    if let Some(stack) = master.as_ref().and_then(|m| m.stack.as_ref()) {
+        match serde_json::from_value::<StackDefinition>(stack.clone()) {
+            Ok(stack) => {
+                tracing::info!("✅ Stack definition from master_config ({} services)", stack.services.len());
+                return stack;
+            }
+            Err(e) => tracing::warn!("⚠️ Invalid stack in master_config: {}", e),
+        }
+    }
+
+    match fetch_remote_stack().await {
+        Ok(stack) => {
+            tracing::info!("✅ Stack definition from procedures ({} services)", stack.services.len());
+            stack
+        }
+        Err(e) => {
+            tracing::warn!("⚠️ Remote stack definition unavailable ({}), using embedded one", e);
+            StackDefinition::embedded()
+        }
+    }
+}
+
+// =============================================================================
+// Sérialisation YAML (serde_yaml, ordre des clés conservé)
+// =============================================================================
+
+/// Sérialise `value` puis décale chaque ligne de `indent` espaces (service sous `services:`)
+pub fn to_yaml_indented(value: &impl Serialize, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    serde_yaml::to_string(value)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| format!("{}{}\n", pad, line))
+        .collect()
+}
+
+fn yaml_list(items: Vec<String>) -> YamlValue {
+    YamlValue::Sequence(items.into_iter().map(YamlValue::String).collect())
+}
+
+/// Ports hôte d'une entrée de `ports`: "8080:80", "0.0.0.0:8080:80/tcp",
+/// "8000-8002:8000-8002" ou syntaxe longue (`published`)
+fn entry_host_ports(entry: &YamlValue) -> Vec<u16> {
+    let mapping = match entry {
+        YamlValue::Mapping(long) => return long.get("published").and_then(yaml_port).into_iter().collect(),
+        YamlValue::String(mapping) => mapping.as_str(),
+        // Port seul: choisi par Docker côté hôte
+        _ => return Vec::new(),
+    };
+    let parts: Vec<&str> = mapping.split('/').next().unwrap_or_default().split(':').collect();
+    if parts.len() < 2 {
+        return Vec::new();
+    }
+    let host = parts[parts.len() - 2];
+    match host.split_once('-') {
+        Some((start, end)) => match (start.parse::<u16>(), end.parse::<u16>()) {
+            (Ok(start), Ok(end)) if start <= end => (start..=end).collect(),
+            _ => Vec::new(),
+        },
+        None => host.parse().into_iter().collect(),
+    }
+}
+
+fn yaml_port(value: &YamlValue) -> Option<u16> {
+    match value {
+        YamlValue::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+        YamlValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Ports publiés sur l'hôte par un service (dans l'ordre de `ports`)
+pub fn service_host_ports(service: &YamlValue) -> Vec<u16> {
+    service
+        .get("ports")
+        .and_then(YamlValue::as_sequence)
+        .map(|entries| entries.iter().flat_map(entry_host_ports).collect())
+        .unwrap_or_default()
+}
+
+/// Services d'un docker-compose.yml (nom, définition), dans l'ordre du fichier
+pub fn parse_services(compose: &str) -> Result<Vec<(String, YamlValue)>> {
+    let document: YamlValue = serde_yaml::from_str(compose)?;
+    let services = match document.get("services") {
+        Some(YamlValue::Mapping(services)) => services,
+        _ => return Err(anyhow!("Section services absente du docker-compose")),
+    };
+    Ok(services
+        .iter()
+        .filter_map(|(name, service)| Some((name.as_str()?.to_string(), service.clone())))
+        .collect())
+}
+
+// =============================================================================
+// Environnement commun des conteneurs (TZ, PUID, PGID)
+// =============================================================================
+
+/// Fuseau horaire et identité des fichiers créés par les conteneurs linuxserver
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerEnv {
+    pub timezone: String,
+    pub puid: u32,
+    pub pgid: u32,
+}
+
+impl Default for ContainerEnv {
+    fn default() -> Self {
+        Self { timezone: "Europe/Paris".to_string(), puid: 1000, pgid: 1000 }
+    }
+}
+
+impl ContainerEnv {
+    /// Commande renvoyant uid, gid et fuseau horaire de l'utilisateur SSH (une valeur par ligne)
+    pub const DETECT_COMMAND: &'static str =
+        "id -u; id -g; cat /etc/timezone 2>/dev/null || timedatectl show -p Timezone --value 2>/dev/null";
+
+    /// Complète les valeurs manquantes avec la sortie de `DETECT_COMMAND`, puis les défauts
+    pub fn resolve(timezone: Option<&str>, puid: Option<u32>, pgid: Option<u32>, detected: &str) -> Self {
+        let mut lines = detected.lines().map(str::trim);
+        let detected_uid = lines.next().and_then(|l| l.parse::<u32>().ok());
+        let detected_gid = lines.next().and_then(|l| l.parse::<u32>().ok());
+        let detected_tz = lines.next().filter(|l| l.contains('/') && !l.contains(' '));
+
+        let defaults = Self::default();
+        Self {
+            timezone: timezone
+                .filter(|tz| !tz.trim().is_empty())
+                .or(detected_tz)
+                .map(String::from)
+                .unwrap_or(defaults.timezone),
+            puid: puid.or(detected_uid).unwrap_or(defaults.puid),
+            pgid: pgid.or(detected_gid).unwrap_or(defaults.pgid),
+        }
+    }
+
+    /// Variables {{TZ}}, {{PUID}}, {{PGID}}
+    pub fn apply_to(&self, vars: &mut TemplateVars) {
+        vars.set("TZ", &self.timezone);
+        vars.set("PUID", &self.puid.to_string());
+        vars.set("PGID", &self.pgid.to_string());
+    }
+}
+
+// =============================================================================
+// ComposeBuilder
+// =============================================================================
+
+pub struct ComposeBuilder {
+    hostname: String,
+    vars: TemplateVars,
+    profiles: Vec<MediaProfile>,
+    disabled: Vec<String>,
+    media_volume: Option<String>,
+    fragments: Vec<String>,
+    external_network: bool,
+}
+
+impl ComposeBuilder {
+    pub fn new(hostname: &str) -> Self {
+        let mut vars = TemplateVars::new();
+        vars.set("PI_HOSTNAME", hostname);
+        ContainerEnv::default().apply_to(&mut vars);
+        Self {
+            hostname: hostname.to_string(),
+            vars,
+            profiles: Vec::new(),
+            disabled: Vec::new(),
+            media_volume: None,
+            fragments: Vec::new(),
+            external_network: false,
+        }
+    }
+
+    /// Variable {{KEY}} disponible dans la définition
+    pub fn var(mut self, key: &str, value: &str) -> Self {
+        self.vars.set(key, value);
+        self
+    }
+
+    /// Fuseau horaire et PUID/PGID des conteneurs
+    pub fn env(mut self, env: &ContainerEnv) -> Self {
+        env.apply_to(&mut self.vars);
+        self
+    }
+
+    /// Profils média actifs (services optionnels)
+    pub fn profiles(mut self, profiles: &[MediaProfile]) -> Self {
+        self.profiles = profiles.to_vec();
+        self
+    }
+
+    /// Services de la définition à ne pas inclure (sélection de l'utilisateur)
+    pub fn without(mut self, services: &[String]) -> Self {
+        self.disabled = services.to_vec();
+        self
+    }
+
+    /// Volume supplémentaire pour les services `mediaVolume`
+    pub fn media_volume(mut self, volume: Option<String>) -> Self {
+        self.media_volume = volume;
+        self
+    }
+
+    /// Réseau créé par un autre projet compose (applications du catalogue)
+    pub fn external_network(mut self) -> Self {
+        self.external_network = true;
+        self
+    }
+
+    /// Service déjà rendu en YAML (Homepage, status), ajouté après ceux de la définition
+    pub fn fragment(mut self, yaml: impl Into<String>) -> Self {
+        self.fragments.push(yaml.into());
+        self
+    }
+
+    fn includes(&self, spec: &ServiceSpec) -> bool {
+        let profile_ok = spec.profile.is_none_or(|p| self.profiles.contains(&p));
+        let requires_ok = spec
+            .requires
+            .as_deref()
+            .is_none_or(|var| self.vars.get(var).is_some_and(|v| !v.is_empty()));
+        profile_ok && requires_ok && !self.disabled.contains(&spec.name)
+    }
+
+    fn service_node(&self, spec: &ServiceSpec) -> Mapping {
+        let replace_all = |items: &[String]| -> Vec<String> { items.iter().map(|i| self.vars.replace(i)).collect() };
+        let mut node = Mapping::new();
+        node.insert("image".into(), self.vars.replace(&spec.image).into());
+        node.insert("container_name".into(), spec.name.clone().into());
+        node.insert("restart".into(), spec.restart.clone().into());
+        if let Some(command) = &spec.command {
+            node.insert("command".into(), self.vars.replace(command).into());
+        }
+
+        let mut volumes = replace_all(&spec.volumes);
+        if spec.media_volume {
+            volumes.extend(self.media_volume.clone());
+        }
+        for (key, items) in [
+            ("cap_add", replace_all(&spec.cap_add)),
+            ("security_opt", replace_all(&spec.security_opt)),
+            ("dns", replace_all(&spec.dns)),
+            ("ports", replace_all(&spec.ports)),
+            ("volumes", volumes),
+            ("environment", replace_all(&spec.environment)),
+            ("devices", replace_all(&spec.devices)),
+            ("depends_on", replace_all(&spec.depends_on)),
+            ("extra_hosts", replace_all(&spec.extra_hosts)),
+        ] {
+            if !items.is_empty() {
+                node.insert(key.into(), yaml_list(items));
+            }
+        }
+
+        let mut limits = Mapping::new();
+        if let Some(memory) = &spec.memory_limit {
+            limits.insert("memory".into(), memory.clone().into());
+        }
+        if let Some(cpus) = &spec.cpus {
+            limits.insert("cpus".into(), cpus.clone().into());
+        }
+        let mut resources = Mapping::new();
+        if !limits.is_empty() {
+            resources.insert("limits".into(), limits.into());
+        }
+        if let Some(memory) = &spec.memory_reservation {
+            let mut reservations = Mapping::new();
+            reservations.insert("memory".into(), memory.clone().into());
+            resources.insert("reservations".into(), reservations.into());
+        }
+        if !resources.is_empty() {
+            let mut deploy = Mapping::new();
+            deploy.insert("resources".into(), resources.into());
+            node.insert("deploy".into(), deploy.into());
+        }
+
+        for (key, value) in [("logging", &spec.logging), ("healthcheck", &spec.healthcheck)] {
+            if let Some(value) = value {
+                let value = serde_yaml::to_value(self.vars.replace_in_json(value)).unwrap_or(YamlValue::Null);
+                node.insert(key.into(), value);
+            }
+        }
+
+        node
+    }
+
+    /// Contenu du docker-compose.yml
+    pub fn build(&self, stack: &StackDefinition) -> String {
+        let mut compose = format!(
+            "---\n\
+             # =============================================================================\n\
+             # Docker Compose - Media Stack\n\
+             # Généré par JellySetup\n\
+             # Pi: {}\n\
+             # =============================================================================\n\
+             \n\
+             services:\n",
+            self.hostname
+        );
+
+        for spec in stack.services.iter().filter(|s| self.includes(s)) {
+            if let Some(comment) = &spec.comment {
+                compose.push_str(&format!("\n  # {}\n", comment));
+            } else {
+                compose.push('\n');
+            }
+            let mut service = Mapping::new();
+            service.insert(spec.name.clone().into(), self.service_node(spec).into());
+            compose.push_str(&to_yaml_indented(&service, 2));
+        }
+
+        for fragment in &self.fragments {
+            compose.push_str(fragment);
+        }
+
+        if !stack.volumes.is_empty() {
+            let volumes: Mapping = stack.volumes.iter().map(|v| (v.clone().into(), Mapping::new().into())).collect();
+            let mut section = Mapping::new();
+            section.insert("volumes".into(), volumes.into());
+            compose.push('\n');
+            compose.push_str(&to_yaml_indented(&section, 0));
+        }
+        let mut network = Mapping::new();
+        network.insert("name".into(), stack.network.clone().into());
+        if self.external_network {
+            network.insert("external".into(), true.into());
+        }
+        let mut networks = Mapping::new();
+        networks.insert("default".into(), network.into());
+        let mut section = Mapping::new();
+        section.insert("networks".into(), networks.into());
+        compose.push('\n');
+        compose.push_str(&to_yaml_indented(&section, 0));
+
+        compose
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_builder() {
+        let stack = StackDefinition::embedded();
+        let compose = ComposeBuilder::new("pi-salon")
+            .var("SUPABASE_URL", "https://x.supabase.co")
+            .var("SUPABASE_ANON_KEY", "anon")
+            .var("SUPABASE_PI_TOKEN", "pi-token")
+            .profiles(&[MediaProfile::Music])
+            .media_volume(Some("/srv/media:/srv/media:rslave".to_string()))
+            .build(&stack);
+
+        assert!(compose.contains("\n  lidarr:\n    image: lscr.io/linuxserver/lidarr:latest\n"));
+        assert!(!compose.contains("audiobookshelf:"));
+        assert!(!compose.contains("readarr:"));
+        assert!(!compose.contains("cloudflared:"));
+        assert!(compose.contains("    - HOSTNAME=pi-salon\n"));
+        assert!(compose.contains("    - TZ=Europe/Paris\n    - PUID=1000\n"));
+        assert!(compose.contains("    - /mnt:/mnt:rslave\n    - /srv/media:/srv/media:rslave\n"));
+        assert!(compose.contains("    deploy:\n      resources:\n        limits:\n          memory: 4G\n        reservations:\n          memory: 1G\n"));
+        assert!(compose.contains("\nvolumes:\n  supabazarr_data: {}\n"));
+        let parsed: YamlValue = serde_yaml::from_str(&compose).unwrap();
+        assert_eq!(parsed["services"]["lidarr"]["container_name"], "lidarr");
+
+        let targets = crate::status::status_targets(&compose);
+        assert!(targets.iter().any(|t| t.name == "jellyseerr" && t.port == 5056));
+
+        let with_tunnel = ComposeBuilder::new("pi").var("CLOUDFLARE_TOKEN", "abc").build(&stack);
+        assert!(with_tunnel.contains("    - TUNNEL_TOKEN=abc\n"));
+
+        let env = ContainerEnv::resolve(None, Some(1001), None, "1000\n1000\nAmerica/Montreal\n");
+        assert_eq!(env, ContainerEnv { timezone: "America/Montreal".to_string(), puid: 1001, pgid: 1000 });
+        assert_eq!(ContainerEnv::resolve(Some("Asia/Tokyo"), None, None, ""), ContainerEnv {
+            timezone: "Asia/Tokyo".to_string(),
+            ..ContainerEnv::default()
+        });
+        let montreal = ComposeBuilder::new("pi").env(&env).build(&stack);
+        assert!(montreal.contains("    - TZ=America/Montreal\n"));
+        assert!(!montreal.contains("Europe/Paris"));
+
+        let minimal = ComposeBuilder::new("pi").without(&["bazarr".to_string()]).build(&stack);
+        assert!(!minimal.contains("\n  bazarr:\n"));
+        assert!(minimal.contains("\n  supabazarr:\n"));
+
+        let mut quoted = Mapping::new();
+        quoted.insert("environment".into(), yaml_list(vec!["TZ=Europe/Paris".into(), "a: b".into(), "*".into(), "yes".into()]));
+        let quoted = to_yaml_indented(&quoted, 2);
+        assert!(quoted.starts_with("  environment:\n  - TZ=Europe/Paris\n"));
+        let parsed: Mapping = serde_yaml::from_str(&quoted).unwrap();
+        assert_eq!(parsed["environment"][1], "a: b");
+        assert_eq!(parsed["environment"][2], "*");
+        assert_eq!(parsed["environment"][3], "yes");
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use ts_rs::TS;
+
+use crate::cloud::CloudBackendKind;
+use crate::secret::SecretString;
+
+// =============================================================================
+// Constantes et réglages de l'application
+// =============================================================================
+//
+// Limites, URLs, nombres de tentatives et délais, regroupés ici plutôt qu'en
+// littéraux dans chaque module. Les valeurs modifiables (`Settings`) viennent, par
+// priorité croissante: des valeurs par défaut, de config_dir/jellysetup/settings.json,
+// puis des variables d'environnement JELLYSETUP_*.
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+/// Ports des services sur le Pi
+pub mod ports {
+    pub const JELLYFIN: u16 = 8096;
+    pub const JELLYSEERR: u16 = 5055;
+    pub const RADARR: u16 = 7878;
+    pub const SONARR: u16 = 8989;
+    pub const PROWLARR: u16 = 9696;
+    pub const LIDARR: u16 = 8686;
+    pub const READARR: u16 = 8787;
+    pub const AUDIOBOOKSHELF: u16 = 13378;
+    pub const BAZARR: u16 = 6767;
+    pub const SSH: u16 = 22;
+}
+
+/// URLs des services externes
+pub mod urls {
+    /// Dépôt des procédures (steps.json par version)
+    pub const PROCEDURES_BASE: &str = "https://raw.githubusercontent.com/nicolascleton/jellysetup/main/procedures";
+    pub const VERSION_CHECK: &str = "https://jellysetup.com/api/version";
+    pub const RPI_OS_INDEX: &str = "https://downloads.raspberrypi.com/raspios_lite_arm64/images/";
+    pub const DOCKER_INSTALL_SCRIPT: &str = "https://get.docker.com";
+}
+
+/// Délais (secondes)
+pub mod delays {
+    /// Intervalle de vérification du `docker compose pull` lancé en arrière-plan
+    pub const PULL_POLL_SECS: u64 = 10;
+    /// Attente du premier démarrage de Jellyfin après `docker compose up`
+    pub const JELLYFIN_BOOT_SECS: u64 = 30;
+    /// Durée maximale des étapes longues suivies en flux (apt upgrade, docker compose pull)
+    pub const LONG_STEP_TIMEOUT_SECS: u64 = 3600;
+    /// Intervalle des attentes de disponibilité (services, verrou APT, retour SSH)
+    pub const POLL_SECS: u64 = 5;
+    /// Intervalle de suivi des tâches lancées en arrière-plan (apt, docker compose up) et avant une nouvelle tentative
+    pub const LONG_POLL_SECS: u64 = 10;
+    /// Attente après une perte SSH pendant apt upgrade (redémarrage sur un nouveau noyau)
+    pub const REBOOT_GRACE_SECS: u64 = 60;
+    /// Intervalle de vérification pendant un redémarrage (délai initial du backoff)
+    pub const REBOOT_POLL_SECS: u64 = 2;
+    /// Pause après l'écriture d'une config ou le démarrage d'un service, avant ses appels d'API
+    pub const SETTLE_SECS: u64 = 3;
+    /// Pause avant de configurer le service suivant
+    pub const STEP_PAUSE_SECS: u64 = 2;
+    /// Pause entre deux appels d'API enchaînés
+    pub const API_PAUSE_SECS: u64 = 1;
+    /// Lecture du journal de `dd` pendant l'écriture de la carte SD (millisecondes)
+    #[cfg(target_os = "macos")]
+    pub const DD_LOG_POLL_MS: u64 = 200;
+    /// Intervalle de remontée de la progression de l'écriture
+    #[cfg(target_os = "macos")]
+    pub const FLASH_PROGRESS_SECS: u64 = 2;
+    /// Attente des partitions de la carte SD après remontage
+    #[cfg(target_os = "macos")]
+    pub const PARTITION_POLL_SECS: u64 = 1;
+    /// Pause avant de chercher la partition boot montée
+    pub const MOUNT_SETTLE_SECS: u64 = 2;
+}
+
+/// Réglages modifiables (fichier de réglages ou environnement)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct Settings {
+    /// Taille maximale d'un disque accepté comme carte SD (sécurité anti-SSD)
+    #[ts(type = "number")]
+    pub max_sd_size_bytes: u64,
+    #[ts(type = "number")]
+    pub min_sd_size_bytes: u64,
+    /// Index des images Raspberry Pi OS (miroir possible)
+    pub rpi_os_index_url: String,
+    pub procedures_base_url: String,
+    pub image_pull_attempts: u32,
+    /// Installation sans Internet côté ordinateur (bundle hors ligne, Supabase ignoré)
+    pub offline_mode: bool,
+    /// Destination des enregistrements d'installation (voir cloud.rs)
+    pub cloud_backend: CloudBackendKind,
+    /// Supabase auto-hébergé (cloud_backend = self_hosted)
+    pub self_hosted_url: Option<String>,
+    #[ts(type = "string | null")]
+    pub self_hosted_key: Option<SecretString>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_sd_size_bytes: 512 * GB,
+            min_sd_size_bytes: 4 * GB,
+            rpi_os_index_url: urls::RPI_OS_INDEX.to_string(),
+            procedures_base_url: urls::PROCEDURES_BASE.to_string(),
+            image_pull_attempts: 3,
+            offline_mode: false,
+            cloud_backend: CloudBackendKind::Hosted,
+            self_hosted_url: None,
+            self_hosted_key: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Applique les variables JELLYSETUP_* (valeurs invalides ignorées)
+    pub fn with_env(mut self, env: impl Fn(&str) -> Option<String>) -> Self {
+        if let Some(bytes) = env("JELLYSETUP_MAX_SD_SIZE_GB")
+            .and_then(|v| v.parse::<u64>().ok())
+            .and_then(|gb| gb.checked_mul(GB))
+        {
+            self.max_sd_size_bytes = bytes;
+        }
+        if let Some(bytes) = env("JELLYSETUP_MIN_SD_SIZE_GB")
+            .and_then(|v| v.parse::<u64>().ok())
+            .and_then(|gb| gb.checked_mul(GB))
+        {
+            self.min_sd_size_bytes = bytes;
+        }
+        if let Some(url) = env("JELLYSETUP_RPI_OS_MIRROR").filter(|v| v.starts_with("http")) {
+            self.rpi_os_index_url = if url.ends_with('/') { url } else { format!("{}/", url) };
+        }
+        if let Some(url) = env("JELLYSETUP_PROCEDURES_URL").filter(|v| v.starts_with("http")) {
+            self.procedures_base_url = url.trim_end_matches('/').to_string();
+        }
+        if let Some(attempts) = env("JELLYSETUP_PULL_ATTEMPTS").and_then(|v| v.parse::<u32>().ok()) {
+            self.image_pull_attempts = attempts.max(1);
+        }
+        if let Some(offline) = env("JELLYSETUP_OFFLINE") {
+            self.offline_mode = matches!(offline.as_str(), "1" | "true");
+        }
+        if let Some(kind) = env("JELLYSETUP_CLOUD_BACKEND").and_then(|v| serde_json::from_value(serde_json::Value::String(v)).ok()) {
+            self.cloud_backend = kind;
+        }
+        if let Some(url) = env("JELLYSETUP_CLOUD_URL").filter(|v| v.starts_with("http")) {
+            self.self_hosted_url = Some(url);
+        }
+        if let Some(key) = env("JELLYSETUP_CLOUD_KEY").filter(|v| !v.is_empty()) {
+            self.self_hosted_key = Some(key.into());
+        }
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.min_sd_size_bytes >= self.max_sd_size_bytes {
+            return Err(anyhow!("Taille minimale de carte SD supérieure à la taille maximale"));
+        }
+        if !self.rpi_os_index_url.starts_with("http") || !self.procedures_base_url.starts_with("http") {
+            return Err(anyhow!("URL invalide dans les réglages"));
+        }
+        if self.image_pull_attempts == 0 {
+            return Err(anyhow!("Au moins une tentative de téléchargement des images est requise"));
+        }
+        if self.cloud_backend == CloudBackendKind::SelfHosted {
+            if !self.self_hosted_url.as_deref().is_some_and(|url| url.starts_with("http")) {
+                return Err(anyhow!("URL du Supabase auto-hébergé manquante ou invalide"));
+            }
+            if self.self_hosted_key.as_ref().is_none_or(SecretString::is_empty) {
+                return Err(anyhow!("Clé du Supabase auto-hébergé manquante"));
+            }
+        }
+        Ok(())
+    }
+
+    /// URL de steps.json pour une version de procédure ("v1", ...)
+    pub fn procedure_url(&self, version: &str) -> String {
+        format!("{}/{}/steps.json", self.procedures_base_url, version)
+    }
+}
+
+static SETTINGS: Lazy<RwLock<Settings>> = Lazy::new(|| RwLock::new(load()));
+
+fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("jellysetup").join("settings.json"))
+}
+
+fn load() -> Settings {
+    let from_file: Settings = settings_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let settings = from_file.with_env(|key| std::env::var(key).ok());
+    match settings.validate() {
+        Ok(()) => settings,
+        Err(e) => {
+            tracing::warn!("⚠️ Invalid settings ({}), using defaults", e);
+            Settings::default()
+        }
+    }
+}
+
+/// Réglages courants
+pub fn settings() -> Settings {
+    SETTINGS.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// Enregistre de nouveaux réglages (fichier + valeurs courantes)
+pub fn save(settings: Settings) -> Result<()> {
+    settings.validate()?;
+    let path = settings_path().ok_or_else(|| anyhow!("Cannot determine config directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&settings)?)?;
+    if let Ok(mut current) = SETTINGS.write() {
+        tracing::info!("Settings updated: {:?}", settings);
+        *current = settings;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_overrides() {
+        let defaults = Settings::default();
+        assert_eq!(defaults.max_sd_size_bytes, 549_755_813_888);
+        assert!(defaults.validate().is_ok());
+        assert_eq!(
+            defaults.procedure_url("v1"),
+            "https://raw.githubusercontent.com/nicolascleton/jellysetup/main/procedures/v1/steps.json"
+        );
+
+        let partial: Settings = serde_json::from_str(r#"{ "image_pull_attempts": 5 }"#).unwrap();
+        assert_eq!(partial.image_pull_attempts, 5);
+        assert_eq!(partial.min_sd_size_bytes, 4 * GB);
+
+        let env = |key: &str| match key {
+            "JELLYSETUP_MAX_SD_SIZE_GB" => Some("2048".to_string()),
+            // Dépasserait u64 une fois converti en octets: ignoré
+            "JELLYSETUP_MIN_SD_SIZE_GB" => Some(u64::MAX.to_string()),
+            "JELLYSETUP_RPI_OS_MIRROR" => Some("https://mirror.example.org/raspios".to_string()),
+            "JELLYSETUP_PULL_ATTEMPTS" => Some("abc".to_string()),
+            "JELLYSETUP_OFFLINE" => Some("1".to_string()),
+            "JELLYSETUP_CLOUD_BACKEND" => Some("self_hosted".to_string()),
+            _ => None,
+        };
+        let overridden = Settings::default().with_env(env);
+        assert_eq!(overridden.max_sd_size_bytes, 2048 * GB);
+        assert_eq!(overridden.min_sd_size_bytes, 4 * GB);
+        assert_eq!(overridden.rpi_os_index_url, "https://mirror.example.org/raspios/");
+        assert_eq!(overridden.image_pull_attempts, 3);
+        assert!(overridden.offline_mode);
+        assert_eq!(overridden.cloud_backend, CloudBackendKind::SelfHosted);
+        assert!(overridden.validate().is_err());
+
+        let inverted = Settings { min_sd_size_bytes: 1024 * GB, ..Settings::default() };
+        assert!(inverted.validate().is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+use crate::ssh::SshTarget;
+use crate::supabase;
+use crate::template_engine::TemplateVars;
+
+// =============================================================================
+// Historique des configurations appliquées (audit et retour arrière)
+// =============================================================================
+//
+// Chaque version = un blob JSON { compose, services: { radarr: {...}, ... } }
+// - blob déposé dans Supabase Storage (config-history/<pi>/<sha256>.json)
+// - ligne config_versions (hash + référence du blob) dans le schéma du Pi
+// Le hash est calculé sur le Pi (sha256sum), une copie locale est gardée dans
+// ~/media-stack/.history/.
+// Les secrets (clé AllDebrid, clés API, TUNNEL_TOKEN, SUPABASE_SERVICE_KEY...) ne
+// quittent jamais le Pi: ils sont remplacés par {{VARIABLE}} ou {{REDACTED}} avant
+// l'envoi, et reconstitués depuis le Pi lors d'une ré-application.
+
+/// Services dont la ré-application demande des identifiants absents de l'historique
+const SKIPPED_ON_REAPPLY: [&str; 2] = ["jellyfin", "jellyseerr"];
+
+/// Valeur masquée sans variable de template correspondante
+const REDACTED: &str = "{{REDACTED}}";
+
+/// Version de configuration enregistrée
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ConfigVersion {
+    pub id: String,
+    pub created_at: String,
+    pub config_hash: String,
+    #[serde(default)]
+    pub master_config_id: Option<String>,
+    pub blob_path: String,
+}
+
+/// Différence entre deux versions (before/after absents: ajout/suppression)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ConfigChange {
+    pub path: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Contenu versionné
+pub fn build_blob(compose: &str, rendered: &[(String, Value)]) -> Value {
+    let services: serde_json::Map<String, Value> = rendered.iter().cloned().collect();
+    json!({ "compose": compose, "services": services })
+}
+
+/// Nom de champ ou de variable d'environnement porteur d'un secret
+fn is_secret_name(name: &str) -> bool {
+    let compact: String = name.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase();
+    name.to_ascii_uppercase().ends_with("_KEY")
+        || ["apikey", "servicekey", "password", "passkey", "token", "secret"].iter().any(|w| compact.contains(w))
+}
+
+fn is_placeholder(value: &str) -> bool {
+    value.starts_with("{{") || value.starts_with("${")
+}
+
+/// Masque les secrets d'un blob: valeurs des variables secrètes remplacées par
+/// {{VARIABLE}}, puis champs et variables d'environnement secrets restants par {{REDACTED}}
+pub fn redact_blob(blob: &Value, vars: &TemplateVars) -> Value {
+    let mut secrets: Vec<(&str, &str)> = vars
+        .names()
+        .filter(|name| is_secret_name(name))
+        .filter_map(|name| vars.get(name).filter(|v| v.len() >= 4).map(|v| (name, v)))
+        .collect();
+    // Les valeurs les plus longues d'abord (une clé peut en contenir une autre)
+    secrets.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+    let substitute = |text: &str| {
+        secrets.iter().fold(text.to_string(), |acc, (name, value)| acc.replace(value, &format!("{{{{{}}}}}", name)))
+    };
+
+    let env_line = regex::Regex::new(r#"^(\s*-?\s*"?([A-Za-z0-9_]+)"?\s*[=:]\s*)(\S.*)$"#).unwrap();
+    let compose = substitute(blob["compose"].as_str().unwrap_or_default())
+        .lines()
+        .map(|line| match env_line.captures(line) {
+            Some(caps) if is_secret_name(&caps[2]) && !is_placeholder(caps[3].trim_matches(|c| c == '"' || c == '\'')) => {
+                format!("{}{}", &caps[1], REDACTED)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    fn redact_value(value: &Value, secret_field: bool, substitute: &dyn Fn(&str) -> String) -> Value {
+        match value {
+            Value::Object(obj) => Value::Object(
+                obj.iter().map(|(k, v)| (k.clone(), redact_value(v, is_secret_name(k), substitute))).collect(),
+            ),
+            Value::Array(arr) => Value::Array(arr.iter().map(|v| redact_value(v, secret_field, substitute)).collect()),
+            Value::String(s) => {
+                let substituted = substitute(s);
+                if secret_field && !substituted.is_empty() && !is_placeholder(&substituted) {
+                    json!(REDACTED)
+                } else {
+                    json!(substituted)
+                }
+            }
+            other => other.clone(),
+        }
+    }
+
+    json!({ "compose": compose, "services": redact_value(&blob["services"], false, &substitute) })
+}
+
+/// Reconstitue les lignes masquées du compose avec celles, de même préfixe, du
+/// docker-compose.yml actuellement sur le Pi (None si une ligne reste introuvable)
+fn restore_compose(redacted: &str, live: &str) -> Option<String> {
+    redacted
+        .lines()
+        .map(|line| match line.find("{{") {
+            Some(index) if line[index..].contains("}}") && !live.lines().any(|l| l == line) => {
+                let prefix = &line[..index];
+                live.lines().find(|l| l.starts_with(prefix) && !l[prefix.len()..].starts_with("{{")).map(String::from)
+            }
+            _ => Some(line.to_string()),
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|lines| lines.join("\n"))
+}
+
+/// Variables {{VAR}} d'une config de service sans valeur connue
+fn unresolved_vars(config: &Value, vars: &TemplateVars) -> Vec<String> {
+    let re = regex::Regex::new(r"\{\{([A-Z_0-9]+)\}\}").unwrap();
+    let text = config.to_string();
+    let mut missing: Vec<String> = re
+        .captures_iter(&text)
+        .map(|caps| caps[1].to_string())
+        .filter(|name| vars.get(name).is_none())
+        .collect();
+    missing.sort();
+    missing.dedup();
+    missing
+}
+
+/// Aplatit un JSON en "chemin -> valeur" (/radarr/qualityProfiles/0/name)
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(obj) => {
+            for (key, v) in obj {
+                flatten(&format!("{}/{}", prefix, key), v, out);
+            }
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                flatten(&format!("{}/{}", prefix, i), v, out);
+            }
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+/// Compare deux blobs: lignes du compose ajoutées/supprimées, valeurs des services modifiées
+pub fn diff_blobs(before: &Value, after: &Value) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+
+    let lines = |blob: &Value| -> Vec<String> {
+        blob["compose"].as_str().unwrap_or_default().lines().map(String::from).collect()
+    };
+    let (before_lines, after_lines) = (lines(before), lines(after));
+    for line in before_lines.iter().filter(|l| !after_lines.contains(l)) {
+        changes.push(ConfigChange { path: "docker-compose.yml".to_string(), before: Some(line.clone()), after: None });
+    }
+    for line in after_lines.iter().filter(|l| !before_lines.contains(l)) {
+        changes.push(ConfigChange { path: "docker-compose.yml".to_string(), before: None, after: Some(line.clone()) });
+    }
+
+    let (mut before_values, mut after_values) = (BTreeMap::new(), BTreeMap::new());
+    flatten("", &before["services"], &mut before_values);
+    flatten("", &after["services"], &mut after_values);
+
+    for (path, value) in &before_values {
+        match after_values.get(path) {
+            Some(new_value) if new_value == value => {}
+            new_value => changes.push(ConfigChange {
+                path: path.clone(),
+                before: Some(value.clone()),
+                after: new_value.cloned(),
+            }),
+        }
+    }
+    for (path, value) in after_values.iter().filter(|(p, _)| !before_values.contains_key(*p)) {
+        changes.push(ConfigChange { path: path.clone(), before: None, after: Some(value.clone()) });
+    }
+
+    changes
+}
+
+/// Enregistre la configuration qui vient d'être appliquée (ignorée si identique à la dernière version)
+pub async fn record_version(
+    target: &SshTarget<'_>,
+    pi_name: &str,
+    master_config_id: Option<&str>,
+    compose: &str,
+    rendered: &[(String, Value)],
+    vars: &TemplateVars,
+) -> Result<()> {
+    let blob = redact_blob(&build_blob(compose, rendered), vars);
+
+    // Copie locale + hash calculé sur le Pi
+    let output = target
+        .exec(&format!(
+            "mkdir -p ~/media-stack/.history && cat > ~/media-stack/.history/pending.json << 'EOFHISTORY'\n{}\nEOFHISTORY\n\
+             HASH=$(sha256sum ~/media-stack/.history/pending.json | cut -d' ' -f1) && \
+             mv ~/media-stack/.history/pending.json ~/media-stack/.history/$HASH.json && echo $HASH",
+            serde_json::to_string_pretty(&blob)?
+        ))
+        .await?;
+    let hash = output.trim().lines().last().unwrap_or_default().to_string();
+    if hash.len() != 64 {
+        return Err(anyhow!("Hash de configuration invalide: {}", output.trim()));
+    }
+
+    let latest = list_versions(pi_name).await.unwrap_or_default();
+    if latest.first().map(|v| v.config_hash.as_str()) == Some(hash.as_str()) {
+        tracing::info!("Configuration unchanged ({})", &hash[..12]);
+        return Ok(());
+    }
+
+    let blob_path = supabase::config_blob_path(pi_name, &hash);
+    supabase::upload_config_blob(pi_name, &blob_path, &blob).await?;
+    supabase::save_config_version(
+        pi_name,
+        &json!({
+            "config_hash": hash,
+            "master_config_id": master_config_id,
+            "blob_path": blob_path
+        }),
+    )
+    .await?;
+
+    tracing::info!("✅ Version {} recorded for {}", &hash[..12], pi_name);
+    Ok(())
+}
+
+/// Historique d'un Pi, de la plus récente à la plus ancienne
+pub async fn list_versions(pi_name: &str) -> Result<Vec<ConfigVersion>> {
+    Ok(supabase::list_config_versions(pi_name)
+        .await?
+        .into_iter()
+        .filter_map(|row| serde_json::from_value(row).ok())
+        .collect())
+}
+
+async fn load_blob(pi_name: &str, version_id: &str) -> Result<Value> {
+    let version = list_versions(pi_name)
+        .await?
+        .into_iter()
+        .find(|v| v.id == version_id)
+        .ok_or_else(|| anyhow!("Version {} introuvable pour {}", version_id, pi_name))?;
+    supabase::download_config_blob(pi_name, &version.blob_path).await
+}
+
+/// Différences entre deux versions d'un Pi
+pub async fn diff_versions(pi_name: &str, from_id: &str, to_id: &str) -> Result<Vec<ConfigChange>> {
+    let before = load_blob(pi_name, from_id).await?;
+    let after = load_blob(pi_name, to_id).await?;
+    Ok(diff_blobs(&before, &after))
+}
+
+/// Ré-applique une version: docker-compose.yml, puis configs des services
+/// Retourne un compte rendu par service
+pub async fn reapply_version(target: SshTarget<'_>, pi_name: &str, version_id: &str) -> Result<Vec<String>> {
+    let blob = load_blob(pi_name, version_id).await?;
+    let redacted_compose = blob["compose"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Version {} sans docker-compose", version_id))?;
+    let live_compose = target.exec("cat ~/media-stack/docker-compose.yml").await?;
+    let compose = restore_compose(redacted_compose, &live_compose)
+        .ok_or_else(|| anyhow!("Secrets de la version {} introuvables dans le docker-compose.yml actuel", version_id))?;
+
+    crate::delta_sync::push_file(&target, "~/media-stack/docker-compose.yml", &compose).await?;
+    target.exec("cd ~/media-stack && docker compose up -d --remove-orphans").await?;
+    let mut report = vec!["docker-compose.yml restauré".to_string()];
+
+    // Variables minimales: les configs de l'historique sont déjà rendues
+    let mut vars = TemplateVars::new();
+    let host = match target {
+        SshTarget::Key { host, .. } | SshTarget::Password { host, .. } => host,
+    };
+    vars.set("PI_IP", host);
+    crate::services::harvest_api_keys(&target).await?.apply_to(&mut vars);
+    // Clé AllDebrid: reprise de la config Decypharr actuelle du Pi
+    let decypharr: Value = target
+        .exec("cat ~/media-stack/decypharr/config.json 2>/dev/null")
+        .await
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if let Some(key) = decypharr["debrids"][0]["api_key"].as_str().filter(|k| !k.is_empty()) {
+        vars.set("ALLDEBRID_API_KEY", key);
+    }
+
+    let services = blob["services"].as_object().cloned().unwrap_or_default();
+    for service in crate::services::SERVICE_ORDER.iter().filter(|s| services.contains_key(**s)) {
+        if SKIPPED_ON_REAPPLY.contains(service) {
+            report.push(format!("{}: ignoré (identifiants requis, relancer l'installation)", service));
+            continue;
+        }
+
+        let missing = unresolved_vars(&services[*service], &vars);
+        if !missing.is_empty() {
+            report.push(format!("{}: ignoré (secrets absents du Pi: {})", service, missing.join(", ")));
+            continue;
+        }
+
+        let config = vars.replace_in_json(&services[*service]);
+        let outcome = crate::services::reconfigure(target, service, &config, &vars).await;
+        report.push(match outcome {
+            Ok(()) => format!("{}: ré-appliqué", service),
+            Err(e) => format!("{}: échec ({})", service, e),
+        });
+    }
+
+    tracing::info!("Version {} re-applied on {}", version_id, pi_name);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_blobs() {
+        let before = build_blob(
+            "services:\n  radarr:\n    image: radarr:5",
+            &[("radarr".to_string(), json!({ "rootFolderPath": "/mnt/movies", "qualityProfiles": [{ "name": "HD" }] }))],
+        );
+        let after = build_blob(
+            "services:\n  radarr:\n    image: radarr:6",
+            &[("radarr".to_string(), json!({ "rootFolderPath": "/srv/movies", "naming": true }))],
+        );
+
+        let changes = diff_blobs(&before, &after);
+        let find = |path: &str| changes.iter().filter(|c| c.path == path).cloned().collect::<Vec<_>>();
+
+        assert_eq!(find("docker-compose.yml").len(), 2);
+        assert_eq!(
+            find("/radarr/rootFolderPath"),
+            vec![ConfigChange {
+                path: "/radarr/rootFolderPath".to_string(),
+                before: Some("\"/mnt/movies\"".to_string()),
+                after: Some("\"/srv/movies\"".to_string()),
+            }]
+        );
+        assert_eq!(find("/radarr/qualityProfiles/0/name")[0].after, None);
+        assert_eq!(find("/radarr/naming")[0].before, None);
+        assert!(diff_blobs(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_redact_blob() {
+        let mut vars = TemplateVars::new();
+        vars.set("ALLDEBRID_API_KEY", "ad-secret-123");
+        vars.set("PI_IP", "192.168.1.20");
+        let blob = build_blob(
+            "services:\n  cloudflared:\n    environment:\n      - TUNNEL_TOKEN=eyJhbGciOi\n      SUPABASE_SERVICE_KEY: \"srv\"\n      PI_IP: 192.168.1.20",
+            &[(
+                "decypharr".to_string(),
+                json!({ "debrids": [{ "api_key": "ad-secret-123", "download_api_keys": ["ad-secret-123"] }], "arrs": [{ "token": "abcd" }], "port": "8282" }),
+            )],
+        );
+
+        let redacted = redact_blob(&blob, &vars);
+        let text = redacted.to_string();
+        assert!(!text.contains("ad-secret-123") && !text.contains("eyJhbGciOi") && !text.contains("srv\\\""));
+        assert_eq!(redacted["services"]["decypharr"]["debrids"][0]["api_key"], "{{ALLDEBRID_API_KEY}}");
+        assert_eq!(redacted["services"]["decypharr"]["arrs"][0]["token"], REDACTED);
+        assert_eq!(redacted["services"]["decypharr"]["port"], "8282");
+        assert!(redacted["compose"].as_str().unwrap().contains("PI_IP: 192.168.1.20"));
+        assert_eq!(unresolved_vars(&redacted["services"]["decypharr"], &vars), vec!["REDACTED".to_string()]);
+
+        let live = blob["compose"].as_str().unwrap();
+        assert_eq!(restore_compose(redacted["compose"].as_str().unwrap(), live).as_deref(), Some(live));
+        assert_eq!(restore_compose("      - TUNNEL_TOKEN={{REDACTED}}", "services: {}"), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::secret::SecretString;
 use crate::SSHCredentials;
 use aes_gcm::{
     aead::{Aead, KeyInit},
@@ -6,8 +7,11 @@ use aes_gcm::{
 use anyhow::Result;
 use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use rand::{rngs::OsRng, RngCore};
+use rand::{rngs::OsRng, Rng, RngCore};
 use russh_keys::key::KeyPair;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use zeroize::Zeroizing;
 
 /// Génère une paire de clés SSH Ed25519
 pub async fn generate_ssh_keypair() -> Result<SSHCredentials> {
@@ -23,12 +27,12 @@ pub async fn generate_ssh_keypair() -> Result<SSHCredentials> {
 
     Ok(SSHCredentials {
         public_key,
-        private_key,
+        private_key: private_key.into(),
     })
 }
 
 /// Formate la clé publique en format OpenSSH
-fn format_public_key(keypair: &KeyPair) -> Result<String> {
+pub(crate) fn format_public_key(keypair: &KeyPair) -> Result<String> {
     let public_key = keypair.clone_public_key()?;
     let mut buffer = Vec::new();
     russh_keys::write_public_key_base64(&mut buffer, &public_key)?;
@@ -50,14 +54,16 @@ pub fn encrypt_private_key(private_key: &str, admin_password: &str) -> Result<St
 
     // Dériver la clé avec Argon2
     let argon2 = Argon2::default();
-    let password_hash = argon2
-        .hash_password(admin_password.as_bytes(), &salt)
-        .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?
-        .to_string();
+    let password_hash = Zeroizing::new(
+        argon2
+            .hash_password(admin_password.as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?
+            .to_string(),
+    );
 
     // Extraire le hash (les 32 premiers bytes)
     let hash_bytes = password_hash.as_bytes();
-    let key_bytes: [u8; 32] = hash_bytes[..32].try_into()?;
+    let key_bytes: Zeroizing<[u8; 32]> = Zeroizing::new(hash_bytes[..32].try_into()?);
 
     // Générer un nonce aléatoire
     let mut nonce_bytes = [0u8; 12];
@@ -65,7 +71,7 @@ pub fn encrypt_private_key(private_key: &str, admin_password: &str) -> Result<St
     let nonce = Nonce::from_slice(&nonce_bytes);
 
     // Chiffrer avec AES-256-GCM
-    let cipher = Aes256Gcm::new_from_slice(&key_bytes)?;
+    let cipher = Aes256Gcm::new_from_slice(key_bytes.as_slice())?;
     let ciphertext = cipher
         .encrypt(nonce, private_key.as_bytes())
         .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
@@ -81,7 +87,7 @@ pub fn encrypt_private_key(private_key: &str, admin_password: &str) -> Result<St
 }
 
 /// Déchiffre la clé privée (côté admin seulement)
-pub fn decrypt_private_key(encrypted: &str, admin_password: &str) -> Result<String> {
+pub fn decrypt_private_key(encrypted: &str, admin_password: &str) -> Result<SecretString> {
     // Décoder le base64
     let combined = BASE64.decode(encrypted)?;
 
@@ -98,23 +104,173 @@ pub fn decrypt_private_key(encrypted: &str, admin_password: &str) -> Result<Stri
 
     // Dériver la clé avec le même sel
     let argon2 = Argon2::default();
-    let password_hash = argon2
-        .hash_password(admin_password.as_bytes(), &salt)
-        .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?
-        .to_string();
+    let password_hash = Zeroizing::new(
+        argon2
+            .hash_password(admin_password.as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?
+            .to_string(),
+    );
 
     let hash_bytes = password_hash.as_bytes();
-    let key_bytes: [u8; 32] = hash_bytes[..32].try_into()?;
+    let key_bytes: Zeroizing<[u8; 32]> = Zeroizing::new(hash_bytes[..32].try_into()?);
 
     // Déchiffrer
-    let cipher = Aes256Gcm::new_from_slice(&key_bytes)?;
+    let cipher = Aes256Gcm::new_from_slice(key_bytes.as_slice())?;
     let nonce = Nonce::from_slice(&nonce_bytes);
 
     let plaintext = cipher
         .decrypt(nonce, ciphertext)
         .map_err(|e| anyhow::anyhow!("Decryption failed: {}", e))?;
 
-    Ok(String::from_utf8(plaintext)?)
+    Ok(SecretString::from(String::from_utf8(plaintext)?))
+}
+
+// =============================================================================
+// Chiffrement de secrets par mot de passe (clé dérivée par Argon2id)
+// =============================================================================
+//
+// Format: base64(sel (16 octets) + nonce (12 octets) + texte chiffré AES-256-GCM).
+// La clé AES est la sortie brute d'Argon2 (32 octets): un mauvais mot de passe
+// échoue au déchiffrement (tag GCM invalide).
+
+const SECRET_SALT_LEN: usize = 16;
+const SECRET_NONCE_LEN: usize = 12;
+
+fn derive_secret_key(password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, key.as_mut_slice())
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Chiffre un secret avec un mot de passe
+pub fn encrypt_secret(plaintext: &str, password: &str) -> Result<String> {
+    let mut salt = [0u8; SECRET_SALT_LEN];
+    let mut nonce_bytes = [0u8; SECRET_NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let cipher = Aes256Gcm::new_from_slice(derive_secret_key(password, &salt)?.as_slice())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+
+    let mut combined = Vec::with_capacity(SECRET_SALT_LEN + SECRET_NONCE_LEN + ciphertext.len());
+    combined.extend_from_slice(&salt);
+    combined.extend_from_slice(&nonce_bytes);
+    combined.extend_from_slice(&ciphertext);
+    Ok(BASE64.encode(&combined))
+}
+
+/// Déchiffre un secret produit par `encrypt_secret`
+pub fn decrypt_secret(encrypted: &str, password: &str) -> Result<SecretString> {
+    let combined = BASE64.decode(encrypted)?;
+    if combined.len() < SECRET_SALT_LEN + SECRET_NONCE_LEN {
+        return Err(anyhow::anyhow!("Invalid encrypted data"));
+    }
+    let (salt, rest) = combined.split_at(SECRET_SALT_LEN);
+    let (nonce_bytes, ciphertext) = rest.split_at(SECRET_NONCE_LEN);
+
+    let cipher = Aes256Gcm::new_from_slice(derive_secret_key(password, salt)?.as_slice())?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Decryption failed (wrong password?)"))?;
+    Ok(SecretString::from(String::from_utf8(plaintext)?))
+}
+
+/// Hash SHA-512 crypt ("$6$...") du mot de passe système, pour custom.toml et userconf.txt
+pub fn sha512_crypt(password: &str) -> Result<String> {
+    sha_crypt::sha512_simple(password, &sha_crypt::Sha512Params::default())
+        .map_err(|e| anyhow::anyhow!("Password hashing failed: {:?}", e))
+}
+
+/// PSK WPA2 (PBKDF2-HMAC-SHA1, SSID en sel, 4096 itérations) en hexadécimal.
+/// Une PSK déjà calculée (64 caractères hexadécimaux) est reprise telle quelle.
+pub fn wpa_psk(ssid: &str, passphrase: &str) -> Result<String> {
+    if passphrase.len() == 64 && passphrase.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(passphrase.to_ascii_lowercase());
+    }
+    if !passphrase.is_ascii() || !(8..=63).contains(&passphrase.len()) {
+        return Err(anyhow::anyhow!("Mot de passe WiFi invalide: 8 à 63 caractères ASCII"));
+    }
+    let psk = Zeroizing::new(pbkdf2::pbkdf2_hmac_array::<sha1::Sha1, 32>(
+        passphrase.as_bytes(),
+        ssid.as_bytes(),
+        4096,
+    ));
+    Ok(psk.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// =============================================================================
+// Génération de mots de passe sûrs pour tous les claviers
+// =============================================================================
+
+/// Lettres placées au même endroit en AZERTY, QWERTY et QWERTZ (sans a/q/w/z/m/y),
+/// hors caractères ambigus (l, I, o, O). Ni chiffres (Shift en AZERTY) ni symboles
+/// (échappement shell, TOML, custom.toml).
+const KEYBOARD_SAFE_LOWER: &str = "bcdefghijknprstuvx";
+const KEYBOARD_SAFE_UPPER: &str = "BCDEFGHJKLNPRSTUVX";
+
+pub const DEFAULT_PASSWORD_LENGTH: usize = 20;
+const MIN_PASSWORD_LENGTH: usize = 12;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Mot de passe généré et son évaluation
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct GeneratedPassword {
+    pub password: String,
+    pub length: u32,
+    pub alphabet_size: u32,
+    /// log2(alphabet) * longueur
+    pub entropy_bits: f64,
+    /// "faible", "correct" ou "fort"
+    pub strength: String,
+}
+
+/// Évaluation d'un mot de passe tiré uniformément dans un alphabet
+pub fn password_entropy(length: usize, alphabet_size: usize) -> (f64, &'static str) {
+    let bits = length as f64 * (alphabet_size as f64).log2();
+    let strength = match bits {
+        b if b >= 100.0 => "fort",
+        b if b >= 70.0 => "correct",
+        _ => "faible",
+    };
+    ((bits * 10.0).round() / 10.0, strength)
+}
+
+/// Génère un mot de passe tapable à l'identique en FR/EN, sans caractère spécial
+/// (au moins une minuscule et une majuscule)
+pub fn generate_strong_password(length: usize) -> Result<GeneratedPassword> {
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length) {
+        return Err(anyhow::anyhow!(
+            "Longueur {} invalide (entre {} et {} caractères)",
+            length,
+            MIN_PASSWORD_LENGTH,
+            MAX_PASSWORD_LENGTH
+        ));
+    }
+
+    let alphabet: Vec<char> = KEYBOARD_SAFE_LOWER.chars().chain(KEYBOARD_SAFE_UPPER.chars()).collect();
+
+    let password = loop {
+        let candidate: String = (0..length)
+            .map(|_| alphabet[OsRng.gen_range(0..alphabet.len())])
+            .collect();
+        if candidate.chars().any(|c| c.is_ascii_lowercase()) && candidate.chars().any(|c| c.is_ascii_uppercase()) {
+            break candidate;
+        }
+    };
+
+    let (entropy_bits, strength) = password_entropy(length, alphabet.len());
+    Ok(GeneratedPassword {
+        password,
+        length: length as u32,
+        alphabet_size: alphabet.len() as u32,
+        entropy_bits,
+        strength: strength.to_string(),
+    })
 }
 
 #[cfg(test)]
@@ -128,7 +284,7 @@ mod tests {
 
         let creds = result.unwrap();
         assert!(creds.public_key.contains("ssh-ed25519"));
-        assert!(creds.private_key.contains("-----BEGIN"));
+        assert!(creds.private_key.expose().contains("-----BEGIN"));
     }
 
     #[test]
@@ -139,6 +295,39 @@ mod tests {
         let encrypted = encrypt_private_key(private_key, password).unwrap();
         let decrypted = decrypt_private_key(&encrypted, password).unwrap();
 
-        assert_eq!(private_key, decrypted);
+        assert_eq!(private_key, decrypted.expose());
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_secret() {
+        let encrypted = encrypt_secret("{\"password\":\"raspberry\"}", "mot de passe").unwrap();
+        assert_eq!(decrypt_secret(&encrypted, "mot de passe").unwrap().expose(), "{\"password\":\"raspberry\"}");
+        assert!(decrypt_secret(&encrypted, "autre mot de passe").is_err());
+        assert!(decrypt_secret("AAAA", "mot de passe").is_err());
+    }
+
+    #[test]
+    fn test_boot_credential_hashes() {
+        let hash = sha512_crypt("raspberry").unwrap();
+        assert!(hash.starts_with("$6$"));
+        assert!(sha_crypt::sha512_check("raspberry", &hash).is_ok());
+        assert!(sha_crypt::sha512_check("autre", &hash).is_err());
+
+        // Vecteur de test IEEE 802.11i (annexe H.4)
+        let psk = wpa_psk("IEEE", "password").unwrap();
+        assert_eq!(psk, "f42c6fc52df0ebef9ebb4b90b38a5f902e83fe1b135a70e23aed762e9710a12e");
+        assert_eq!(wpa_psk("Autre SSID", &psk.to_uppercase()).unwrap(), psk);
+        assert!(wpa_psk("IEEE", "court").is_err());
+    }
+
+    #[test]
+    fn test_generate_strong_password() {
+        let generated = generate_strong_password(DEFAULT_PASSWORD_LENGTH).unwrap();
+        assert_eq!(generated.password.chars().count(), DEFAULT_PASSWORD_LENGTH);
+        assert!(generated.password.chars().all(|c| KEYBOARD_SAFE_LOWER.contains(c) || KEYBOARD_SAFE_UPPER.contains(c)));
+        assert_eq!(generated.alphabet_size, 36);
+        assert_eq!(generated.strength, "fort");
+        assert_eq!(password_entropy(12, 36).1, "faible");
+        assert!(generate_strong_password(6).is_err());
     }
 }
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::ssh::SshTarget;
+use crate::supabase::MediaDebridLink;
+
+// =============================================================================
+// Rafraîchissement des liens debrid du catalogue
+// =============================================================================
+//
+// Les liens débridés expirent (`debrid_link_expires`). Le job sélectionne ceux qui
+// expirent dans les REFRESH_WINDOW_HOURS prochaines heures, les re-débride via
+// AllDebrid (lien hébergeur d'origine `metadata.source_link` s'il est connu),
+// met à jour Supabase puis force Decypharr (rclone) à relire son arborescence.
+
+/// Fenêtre d'anticipation avant expiration
+pub const REFRESH_WINDOW_HOURS: i64 = 24;
+/// Validité supposée d'un lien AllDebrid (l'API ne la renvoie pas)
+const LINK_VALIDITY_HOURS: i64 = 72;
+
+/// Relit l'arborescence WebDAV dans le montage rclone de Decypharr
+const DECYPHARR_VFS_REFRESH: &str =
+    "docker exec decypharr sh -c 'wget -qO- --post-data \"\" \"http://localhost:5572/vfs/refresh?recursive=true\" || curl -fsS -X POST \"http://localhost:5572/vfs/refresh?recursive=true\"'";
+
+/// Bilan d'un passage du job
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct RefreshSummary {
+    pub checked: usize,
+    pub refreshed: usize,
+    pub failed: Vec<String>,
+    pub decypharr_refreshed: bool,
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Lien à rafraîchir: expiration inconnue, illisible ou dans la fenêtre
+pub fn needs_refresh(link: &MediaDebridLink, now: DateTime<Utc>) -> bool {
+    match link.debrid_link_expires.as_deref().map(DateTime::parse_from_rfc3339) {
+        Some(Ok(expires)) => expires.with_timezone(&Utc) <= now + Duration::hours(REFRESH_WINDOW_HOURS),
+        _ => true,
+    }
+}
+
+/// Lien à re-débrider: lien hébergeur d'origine, sinon le lien débridé lui-même
+pub fn source_link(link: &MediaDebridLink) -> &str {
+    link.metadata
+        .as_ref()
+        .and_then(|m| m["source_link"].as_str())
+        .filter(|s| !s.is_empty())
+        .unwrap_or(&link.debrid_link)
+}
+
+/// Rafraîchit les liens proches de l'expiration d'un Pi
+pub async fn refresh_links(pi_name: &str, api_key: &str, target: Option<&SshTarget<'_>>) -> Result<RefreshSummary> {
+    let now = Utc::now();
+    let before = timestamp(now + Duration::hours(REFRESH_WINDOW_HOURS));
+    let candidates = crate::supabase::list_expiring_debrid_links(pi_name, &before).await?;
+
+    let mut summary = RefreshSummary::default();
+    for link in candidates.iter().filter(|l| needs_refresh(l, now)) {
+        summary.checked += 1;
+        match crate::alldebrid::unlock_link(api_key, source_link(link)).await {
+            Ok(fresh) => {
+                let expires = timestamp(Utc::now() + Duration::hours(LINK_VALIDITY_HOURS));
+                match crate::supabase::update_media_debrid_link(pi_name, &link.id, &fresh, Some(&expires)).await {
+                    Ok(()) => summary.refreshed += 1,
+                    Err(e) => {
+                        tracing::warn!("⚠️ {}: Supabase update failed: {}", link.title, e);
+                        summary.failed.push(link.title.clone());
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("⚠️ {}: {}", link.title, e);
+                summary.failed.push(link.title.clone());
+            }
+        }
+    }
+
+    if summary.refreshed > 0 {
+        if let Some(target) = target {
+            match target.exec(DECYPHARR_VFS_REFRESH).await {
+                Ok(_) => summary.decypharr_refreshed = true,
+                Err(e) => tracing::warn!("⚠️ Decypharr VFS refresh failed: {}", e),
+            }
+        }
+    }
+
+    tracing::info!(
+        "✅ {}: {}/{} links refreshed ({} failed)",
+        pi_name,
+        summary.refreshed,
+        summary.checked,
+        summary.failed.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn link(expires: Option<&str>, metadata: Option<serde_json::Value>) -> MediaDebridLink {
+        MediaDebridLink {
+            id: "1".to_string(),
+            title: "Matrix".to_string(),
+            debrid_link: "https://abc.debrid.it/dl/xyz".to_string(),
+            debrid_link_expires: expires.map(String::from),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn test_link_selection() {
+        let now = DateTime::parse_from_rfc3339("2026-05-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        assert!(needs_refresh(&link(Some("2026-05-02T06:00:00Z"), None), now));
+        assert!(!needs_refresh(&link(Some("2026-05-04T12:00:00+00:00"), None), now));
+        assert!(needs_refresh(&link(None, None), now));
+        assert!(needs_refresh(&link(Some("demain"), None), now));
+
+        assert_eq!(source_link(&link(None, None)), "https://abc.debrid.it/dl/xyz");
+        let with_source = link(None, Some(json!({ "source_link": "https://1fichier.com/?abc" })));
+        assert_eq!(source_link(&with_source), "https://1fichier.com/?abc");
+    }
+}
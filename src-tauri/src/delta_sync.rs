@@ -0,0 +1,383 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::collections::HashMap;
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Mise à jour différentielle des fichiers de configuration (façon rsync)
+// =============================================================================
+//
+// Au lieu de renvoyer le fichier entier dans un heredoc, le Pi calcule la signature
+// de sa version (blocs fixes: somme faible d'Adler + FNV-1a 64 bits), l'ordinateur
+// cherche ces blocs dans la nouvelle version (somme glissante) et n'envoie que les
+// octets qui manquent, en base64 (aucun problème de quoting shell). Le Pi reconstruit
+// le fichier, vérifie son empreinte complète puis le remplace atomiquement.
+//
+// Le script Python du Pi est déposé une fois dans ~/.jellysetup; sans python3, ou si
+// la reconstruction ne correspond pas, le fichier est réécrit en entier.
+
+const BLOCK_SIZE: usize = 1024;
+const HELPER_PATH: &str = "~/.jellysetup/delta-v1.py";
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Script du Pi: `sig <fichier>` (signatures) ou `apply <fichier> <empreinte>` (opérations sur stdin)
+const HELPER_SCRIPT: &str = r#"import base64, os, sys
+
+BLOCK = 1024
+
+def weak(d):
+    a = sum(d) & 0xFFFF
+    b = sum((len(d) - i) * x for i, x in enumerate(d)) & 0xFFFF
+    return a | (b << 16)
+
+def strong(d):
+    h = 0xcbf29ce484222325
+    for x in d:
+        h = ((h ^ x) * 0x100000001b3) & 0xFFFFFFFFFFFFFFFF
+    return h
+
+def read(path):
+    try:
+        with open(path, 'rb') as f:
+            return f.read()
+    except FileNotFoundError:
+        return b''
+
+path = os.path.expanduser(sys.argv[2])
+old = read(path)
+if sys.argv[1] == 'sig':
+    for i in range(0, len(old), BLOCK):
+        block = old[i:i + BLOCK]
+        print('%08x %016x' % (weak(block), strong(block)))
+    print('SIG_END')
+else:
+    out = bytearray()
+    for line in sys.stdin.read().splitlines():
+        if len(line) < 2:
+            continue
+        kind, arg = line[0], line[2:]
+        if kind == 'C':
+            start, count = (int(v) for v in arg.split(','))
+            out += old[start * BLOCK:(start + count) * BLOCK]
+        elif kind == 'L':
+            out += base64.b64decode(arg)
+    if '%016x' % strong(bytes(out)) != sys.argv[3]:
+        print('DELTA_MISMATCH')
+        sys.exit(0)
+    os.makedirs(os.path.dirname(path) or '.', exist_ok=True)
+    with open(path + '.delta', 'wb') as f:
+        f.write(out)
+    os.replace(path + '.delta', path)
+    print('DELTA_OK')
+"#;
+
+/// Signature d'un bloc de la version du Pi
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockSignature {
+    pub weak: u32,
+    pub strong: u64,
+}
+
+/// Opération de reconstruction: blocs existants ou octets nouveaux
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeltaOp {
+    /// `count` blocs à partir du bloc `start` de l'ancienne version
+    Copy { start: usize, count: usize },
+    Literal(Vec<u8>),
+}
+
+/// Bilan d'un envoi
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushStats {
+    pub file_bytes: usize,
+    /// Octets réellement envoyés (opérations encodées)
+    pub sent_bytes: usize,
+    pub delta: bool,
+}
+
+/// Somme faible façon rsync (glissante)
+#[derive(Debug, Clone, Copy)]
+struct Rolling {
+    a: u32,
+    b: u32,
+    len: u32,
+}
+
+impl Rolling {
+    fn new(data: &[u8]) -> Self {
+        let len = data.len() as u32;
+        let (a, b) = data.iter().enumerate().fold((0u32, 0u32), |(a, b), (i, &x)| {
+            (a.wrapping_add(x as u32), b.wrapping_add((len - i as u32).wrapping_mul(x as u32)))
+        });
+        Rolling { a, b, len }
+    }
+
+    fn value(&self) -> u32 {
+        (self.a & 0xFFFF) | ((self.b & 0xFFFF) << 16)
+    }
+
+    /// Fait glisser la fenêtre d'un octet (`out` sort, `incoming` entre)
+    fn roll(&mut self, out: u8, incoming: u8) {
+        self.a = self.a.wrapping_sub(out as u32).wrapping_add(incoming as u32);
+        self.b = self.b.wrapping_sub(self.len.wrapping_mul(out as u32)).wrapping_add(self.a);
+    }
+}
+
+pub fn weak_checksum(data: &[u8]) -> u32 {
+    Rolling::new(data).value()
+}
+
+/// FNV-1a 64 bits (identique au script du Pi, sans dépendance supplémentaire)
+pub fn strong_hash(data: &[u8]) -> u64 {
+    data.iter().fold(FNV_OFFSET, |h, &x| (h ^ x as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Parse la sortie `sig` du script (None si la signature est incomplète)
+pub fn parse_signatures(output: &str) -> Option<Vec<BlockSignature>> {
+    if !output.lines().any(|l| l.trim() == "SIG_END") {
+        return None;
+    }
+    output
+        .lines()
+        .map(str::trim)
+        .take_while(|l| *l != "SIG_END")
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            let (weak, strong) = line.split_once(' ')?;
+            Some(BlockSignature {
+                weak: u32::from_str_radix(weak, 16).ok()?,
+                strong: u64::from_str_radix(strong, 16).ok()?,
+            })
+        })
+        .collect()
+}
+
+fn push_copy(ops: &mut Vec<DeltaOp>, block: usize) {
+    if let Some(DeltaOp::Copy { start, count }) = ops.last_mut() {
+        if *start + *count == block {
+            *count += 1;
+            return;
+        }
+    }
+    ops.push(DeltaOp::Copy { start: block, count: 1 });
+}
+
+fn push_literal(ops: &mut Vec<DeltaOp>, bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+    if let Some(DeltaOp::Literal(literal)) = ops.last_mut() {
+        literal.extend_from_slice(bytes);
+        return;
+    }
+    ops.push(DeltaOp::Literal(bytes.to_vec()));
+}
+
+/// Opérations transformant l'ancienne version (signatures) en `new`
+pub fn compute_delta(signatures: &[BlockSignature], new: &[u8]) -> Vec<DeltaOp> {
+    let mut by_weak: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, signature) in signatures.iter().enumerate() {
+        by_weak.entry(signature.weak).or_default().push(index);
+    }
+
+    let mut ops = Vec::new();
+    let mut literal_start = 0;
+    let mut pos = 0;
+    let mut rolling = (new.len() >= BLOCK_SIZE).then(|| Rolling::new(&new[..BLOCK_SIZE]));
+
+    while pos < new.len() {
+        let end = (pos + BLOCK_SIZE).min(new.len());
+        let window = &new[pos..end];
+        // La fenêtre glissante couvre les blocs pleins; le dernier bloc (court) est testé tel quel
+        let weak = match &rolling {
+            Some(r) if end - pos == BLOCK_SIZE => r.value(),
+            _ => weak_checksum(window),
+        };
+        let matched = by_weak.get(&weak).and_then(|candidates| {
+            let strong = strong_hash(window);
+            candidates.iter().copied().find(|&i| signatures[i].strong == strong)
+        });
+
+        match matched {
+            Some(block) => {
+                push_literal(&mut ops, &new[literal_start..pos]);
+                push_copy(&mut ops, block);
+                pos = end;
+                literal_start = pos;
+                rolling = (pos + BLOCK_SIZE <= new.len()).then(|| Rolling::new(&new[pos..pos + BLOCK_SIZE]));
+            }
+            None => {
+                if let Some(r) = rolling.as_mut() {
+                    if pos + BLOCK_SIZE < new.len() {
+                        r.roll(new[pos], new[pos + BLOCK_SIZE]);
+                    } else {
+                        rolling = None;
+                    }
+                }
+                pos += 1;
+            }
+        }
+    }
+    push_literal(&mut ops, &new[literal_start..]);
+    ops
+}
+
+/// Reconstruit la nouvelle version (miroir du script du Pi)
+pub fn apply_delta(old: &[u8], ops: &[DeltaOp]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for op in ops {
+        match op {
+            DeltaOp::Copy { start, count } => {
+                let from = (start * BLOCK_SIZE).min(old.len());
+                let to = ((start + count) * BLOCK_SIZE).min(old.len());
+                out.extend_from_slice(&old[from..to]);
+            }
+            DeltaOp::Literal(bytes) => out.extend_from_slice(bytes),
+        }
+    }
+    out
+}
+
+/// Une opération par ligne: "C start,count" ou "L base64"
+pub fn encode_ops(ops: &[DeltaOp]) -> String {
+    ops.iter()
+        .map(|op| match op {
+            DeltaOp::Copy { start, count } => format!("C {},{}", start, count),
+            DeltaOp::Literal(bytes) => format!("L {}", BASE64.encode(bytes)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn quoted_path(remote_path: &str) -> Result<String> {
+    if remote_path.is_empty() || remote_path.contains('\'') || remote_path.contains('\n') {
+        return Err(anyhow!("Chemin distant invalide: {}", remote_path));
+    }
+    Ok(format!("'{}'", remote_path))
+}
+
+async fn install_helper(target: &SshTarget<'_>) -> Result<()> {
+    target
+        .exec(&format!(
+            "mkdir -p ~/.jellysetup && cat > {} << 'EOFDELTAHELPER'\n{}EOFDELTAHELPER",
+            HELPER_PATH, HELPER_SCRIPT
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Signatures de la version du Pi (fichier absent: aucune)
+async fn remote_signatures(target: &SshTarget<'_>, path: &str) -> Result<Vec<BlockSignature>> {
+    let command = format!(
+        "if command -v python3 >/dev/null; then if [ -f {helper} ]; then python3 {helper} sig {path}; else echo NOHELPER; fi; else echo NOPYTHON; fi",
+        helper = HELPER_PATH,
+        path = path
+    );
+    let mut output = target.exec(&command).await?;
+    if output.contains("NOPYTHON") {
+        return Err(anyhow!("python3 absent du Pi"));
+    }
+    if output.contains("NOHELPER") {
+        install_helper(target).await?;
+        output = target.exec(&command).await?;
+    }
+    parse_signatures(&output).ok_or_else(|| anyhow!("Signature distante illisible"))
+}
+
+async fn apply_remote(target: &SshTarget<'_>, path: &str, ops: &[DeltaOp], expected: u64) -> Result<bool> {
+    let output = target
+        .exec(&format!(
+            "python3 {} apply {} {:016x} << 'EOFDELTA'\n{}\nEOFDELTA",
+            HELPER_PATH,
+            path,
+            expected,
+            encode_ops(ops)
+        ))
+        .await?;
+    Ok(output.contains("DELTA_OK"))
+}
+
+/// Écrit `content` dans `remote_path` sur le Pi en n'envoyant que les différences
+pub async fn push_file(target: &SshTarget<'_>, remote_path: &str, content: &str) -> Result<PushStats> {
+    let path = quoted_path(remote_path)?;
+    let new = content.as_bytes();
+    let expected = strong_hash(new);
+
+    match remote_signatures(target, &path).await {
+        Ok(signatures) => {
+            let ops = compute_delta(&signatures, new);
+            let sent_bytes = encode_ops(&ops).len();
+            if apply_remote(target, &path, &ops, expected).await? {
+                tracing::info!("✅ {} ({} bytes, {} sent)", remote_path, new.len(), sent_bytes);
+                return Ok(PushStats { file_bytes: new.len(), sent_bytes, delta: true });
+            }
+            // Version du Pi modifiée entre-temps: envoi complet par le même script
+            let full = vec![DeltaOp::Literal(new.to_vec())];
+            if apply_remote(target, &path, &full, expected).await? {
+                tracing::warn!("⚠️ {} delta mismatch, sent in full", remote_path);
+                return Ok(PushStats { file_bytes: new.len(), sent_bytes: encode_ops(&full).len(), delta: false });
+            }
+            Err(anyhow!("Écriture de {} échouée sur le Pi", remote_path))
+        }
+        Err(e) => {
+            tracing::warn!("⚠️ {}: {}, writing whole file", remote_path, e);
+            target
+                .exec(&format!(
+                    "mkdir -p \"$(dirname {path})\" && cat > {path} << 'EOFDELTAFULL'\n{content}\nEOFDELTAFULL",
+                    path = remote_path,
+                    content = content
+                ))
+                .await?;
+            Ok(PushStats { file_bytes: new.len(), sent_bytes: new.len(), delta: false })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signatures(old: &[u8]) -> Vec<BlockSignature> {
+        old.chunks(BLOCK_SIZE)
+            .map(|block| BlockSignature { weak: weak_checksum(block), strong: strong_hash(block) })
+            .collect()
+    }
+
+    #[test]
+    fn test_delta_roundtrip() {
+        let old: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let mut new = old.clone();
+        new.splice(1500..1500, b"\"max_downloads\": 4,".iter().copied());
+        new.truncate(4800);
+
+        let ops = compute_delta(&signatures(&old), &new);
+        assert_eq!(apply_delta(&old, &ops), new);
+        let literal: usize = ops.iter().map(|op| if let DeltaOp::Literal(b) = op { b.len() } else { 0 }).sum();
+        assert!(literal < BLOCK_SIZE * 2, "{} literal bytes", literal);
+
+        // Fichier absent sur le Pi: tout en littéral
+        assert_eq!(compute_delta(&[], b"abc"), vec![DeltaOp::Literal(b"abc".to_vec())]);
+        assert_eq!(apply_delta(&old, &compute_delta(&signatures(&old), &old)), old);
+
+        // La somme glissante reste égale à la somme recalculée
+        let mut rolling = Rolling::new(&new[..BLOCK_SIZE]);
+        rolling.roll(new[0], new[BLOCK_SIZE]);
+        assert_eq!(rolling.value(), weak_checksum(&new[1..BLOCK_SIZE + 1]));
+    }
+
+    #[test]
+    fn test_signatures_and_encoding() {
+        assert_eq!(strong_hash(b""), FNV_OFFSET);
+        assert_eq!(strong_hash(b"a"), 0xaf63dc4c8601ec8c);
+        let parsed = parse_signatures("0000ffff 00000000000000aa\nSIG_END\n").unwrap();
+        assert_eq!(parsed, vec![BlockSignature { weak: 0xffff, strong: 0xaa }]);
+        assert_eq!(parse_signatures("0000ffff 00000000000000aa\n"), None);
+        assert_eq!(parse_signatures("SIG_END").unwrap(), vec![]);
+
+        let ops = vec![DeltaOp::Copy { start: 0, count: 3 }, DeltaOp::Literal(b"hi".to_vec())];
+        assert_eq!(encode_ops(&ops), "C 0,3\nL aGk=");
+        assert!(quoted_path("~/x'; rm -rf ~").is_err());
+    }
+}
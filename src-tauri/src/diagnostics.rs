@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::preflight::{CheckStatus, HostOperation, PreflightCheck};
+
+// =============================================================================
+// Auto-diagnostic de l'application (ordinateur de l'utilisateur)
+// =============================================================================
+//
+// `run_app_diagnostics` vérifie ce dont JellySetup a besoin sur l'ordinateur:
+// outils système (xz ou 7z pour extraire l'image, ssh-keygen, ping), accès
+// disque (Full Disk Access sur macOS), accès réseau à raspberrypi.com, Supabase
+// et Docker Hub, espace libre du cache et version du webview. Le rapport
+// contient un résumé texte à coller tel quel dans un rapport de bug.
+
+const REACHABILITY_TIMEOUT_SECS: u64 = 10;
+
+/// Rapport de diagnostic de l'application
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct AppDiagnostics {
+    pub app_version: String,
+    /// Système et architecture ("macos aarch64")
+    pub os: String,
+    pub generated_at: String,
+    pub checks: Vec<PreflightCheck>,
+    pub has_failures: bool,
+    pub has_warnings: bool,
+    /// Rapport texte prêt à coller dans un ticket
+    pub summary: String,
+}
+
+impl AppDiagnostics {
+    fn new(checks: Vec<PreflightCheck>) -> Self {
+        let mut report = Self {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            has_failures: checks.iter().any(|c| c.status == CheckStatus::Fail),
+            has_warnings: checks.iter().any(|c| c.status == CheckStatus::Warn),
+            checks,
+            summary: String::new(),
+        };
+        report.summary = report.render_summary();
+        report
+    }
+
+    fn render_summary(&self) -> String {
+        let mut lines = vec![
+            format!("JellySetup {} - {}", self.app_version, self.os),
+            format!("Diagnostic du {}", self.generated_at),
+        ];
+        for c in &self.checks {
+            let status = match c.status {
+                CheckStatus::Pass => "OK  ",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+            };
+            let value = if c.value.is_empty() { String::new() } else { format!(" ({})", c.value) };
+            lines.push(format!("[{}] {}{}: {}", status, c.name, value, c.message));
+        }
+        lines.join("\n")
+    }
+}
+
+fn check(name: &str, status: CheckStatus, value: &str, message: &str) -> PreflightCheck {
+    PreflightCheck {
+        name: name.to_string(),
+        status,
+        value: value.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Cherche un exécutable dans le PATH, puis dans `extra_dirs`
+fn find_tool(name: &str, extra_dirs: &[&str]) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+    let path_dirs = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    path_dirs
+        .into_iter()
+        .chain(extra_dirs.iter().map(PathBuf::from))
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+fn tool_check(name: &str, extra_dirs: &[&str], missing: &str, missing_status: CheckStatus) -> PreflightCheck {
+    match find_tool(name, extra_dirs) {
+        Some(path) => check(name, CheckStatus::Pass, &path.to_string_lossy(), "Trouvé"),
+        None => check(name, missing_status, "", missing),
+    }
+}
+
+/// Outils système utilisés par le flash et les connexions SSH
+fn tool_checks() -> Vec<PreflightCheck> {
+    // Extraction de l'image: 7z sur Windows, xz ailleurs (mêmes chemins que flash.rs)
+    let extractor = if cfg!(windows) {
+        tool_check(
+            "7z",
+            &["C:\\Program Files\\7-Zip"],
+            "7-Zip introuvable: nécessaire pour extraire l'image Raspberry Pi OS",
+            CheckStatus::Fail,
+        )
+    } else {
+        tool_check(
+            "xz",
+            &["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin"],
+            "xz introuvable: nécessaire pour extraire l'image (brew install xz)",
+            CheckStatus::Fail,
+        )
+    };
+    vec![
+        extractor,
+        tool_check(
+            "ssh-keygen",
+            &["/usr/bin", "C:\\Windows\\System32\\OpenSSH"],
+            "ssh-keygen introuvable: le nettoyage de known_hosts sera ignoré",
+            CheckStatus::Warn,
+        ),
+        tool_check(
+            "ping",
+            &["/sbin", "/bin", "/usr/bin", "C:\\Windows\\System32"],
+            "ping introuvable: la découverte du Pi par IP sera plus lente",
+            CheckStatus::Warn,
+        ),
+    ]
+}
+
+/// Une réponse HTTP, quel que soit son code, prouve que l'hôte est joignable
+async fn reachability_check(client: &reqwest::Client, name: &str, url: &str) -> PreflightCheck {
+    let started = std::time::Instant::now();
+    match client.head(url).send().await {
+        Ok(response) => check(
+            name,
+            CheckStatus::Pass,
+            &format!("HTTP {} en {} ms", response.status().as_u16(), started.elapsed().as_millis()),
+            "Joignable",
+        ),
+        Err(e) if e.is_timeout() => check(name, CheckStatus::Fail, url, "Pas de réponse (délai dépassé)"),
+        Err(e) => check(name, CheckStatus::Fail, url, &format!("Injoignable: {}", e)),
+    }
+}
+
+async fn network_checks() -> Vec<PreflightCheck> {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(REACHABILITY_TIMEOUT_SECS))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => return vec![check("network", CheckStatus::Fail, "", &e.to_string())],
+    };
+
+    let supabase = match crate::cloud::endpoint() {
+        Some(endpoint) => reachability_check(&client, "supabase", &endpoint.url).await,
+        None => check("supabase", CheckStatus::Pass, "local", "Backend cloud désactivé"),
+    };
+    let mut checks = vec![
+        reachability_check(&client, "raspberrypi.com", "https://downloads.raspberrypi.com/").await,
+        supabase,
+        reachability_check(&client, "docker_hub", "https://registry-1.docker.io/v2/").await,
+    ];
+
+    // Hors ligne, l'absence de réseau est attendue
+    if crate::offline_bundle::active() {
+        for c in checks.iter_mut().filter(|c| c.status == CheckStatus::Fail) {
+            c.status = CheckStatus::Warn;
+            c.message = format!("{} (mode hors ligne)", c.message);
+        }
+    }
+    checks
+}
+
+/// Lance le diagnostic (`disk_access` et `webview_version` fournis par la commande Tauri)
+pub async fn run(disk_access: bool, webview_version: Option<String>) -> AppDiagnostics {
+    let mut checks = tool_checks();
+
+    checks.push(if disk_access {
+        check("disk_access", CheckStatus::Pass, "", "Accès aux disques autorisé")
+    } else {
+        check(
+            "disk_access",
+            CheckStatus::Fail,
+            "",
+            "Full Disk Access refusé: autorisez JellySetup dans Réglages > Confidentialité",
+        )
+    });
+
+    checks.extend(network_checks().await);
+
+    let cache_free = match dirs::cache_dir() {
+        Some(dir) => crate::preflight::free_space_bytes(&dir).await,
+        None => None,
+    };
+    checks.extend(
+        crate::preflight::evaluate_host(HostOperation::Flash, None, cache_free)
+            .checks
+            .into_iter()
+            .filter(|c| c.name == "cache_disk"),
+    );
+
+    checks.push(match webview_version {
+        Some(version) => check("webview", CheckStatus::Pass, &version, "Webview disponible"),
+        None => check("webview", CheckStatus::Warn, "", "Version du webview inconnue"),
+    });
+
+    let report = AppDiagnostics::new(checks);
+    tracing::info!(
+        "{} checks (failures: {}, warnings: {})",
+        report.checks.len(),
+        report.has_failures,
+        report.has_warnings
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics_summary() {
+        let report = AppDiagnostics::new(vec![
+            check("xz", CheckStatus::Pass, "/usr/bin/xz", "Trouvé"),
+            check("docker_hub", CheckStatus::Fail, "https://registry-1.docker.io/v2/", "Injoignable"),
+            check("webview", CheckStatus::Warn, "", "Version du webview inconnue"),
+        ]);
+        assert!(report.has_failures);
+        assert!(report.has_warnings);
+        assert!(report.summary.starts_with(&format!("JellySetup {}", env!("CARGO_PKG_VERSION"))));
+        assert!(report.summary.contains("\n[OK  ] xz (/usr/bin/xz): Trouvé"));
+        assert!(report.summary.contains("\n[FAIL] docker_hub (https://registry-1.docker.io/v2/): Injoignable"));
+        assert!(report.summary.ends_with("[WARN] webview: Version du webview inconnue"));
+
+        assert!(find_tool("jellysetup-outil-inexistant", &[]).is_none());
+    }
+}
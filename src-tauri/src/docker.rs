@@ -0,0 +1,162 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Gestion Docker à distance pour le tableau de bord post-installation
+// =============================================================================
+//
+// `docker ps` / `docker stats` en `--format '{{json .}}'` (un objet JSON par ligne),
+// logs et redémarrage d'un service du compose ~/media-stack.
+
+/// Lignes de logs maximum renvoyées au frontend
+pub const MAX_LOG_LINES: u32 = 2000;
+
+/// Conteneur tel que listé par `docker ps -a`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ContainerInfo {
+    pub name: String,
+    /// Service docker-compose (vide hors compose)
+    pub service: String,
+    pub image: String,
+    /// "running", "exited", "restarting", ...
+    pub state: String,
+    /// Texte Docker ("Up 2 hours (healthy)", ...)
+    pub status: String,
+    pub ports: String,
+}
+
+/// Consommation instantanée d'un conteneur (`docker stats --no-stream`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ContainerStats {
+    pub name: String,
+    pub cpu_percent: f64,
+    pub mem_percent: f64,
+    /// "512MiB / 7.6GiB"
+    pub mem_usage: String,
+    pub net_io: String,
+    pub block_io: String,
+}
+
+/// Noms de services/conteneurs acceptés (interpolés dans le shell)
+fn check_service_name(service: &str) -> Result<()> {
+    let valid = !service.is_empty()
+        && service.len() <= 64
+        && service.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Nom de service invalide: {}", service))
+    }
+}
+
+fn json_lines(output: &str) -> impl Iterator<Item = Value> + '_ {
+    output.lines().filter_map(|line| serde_json::from_str(line.trim()).ok())
+}
+
+/// "12.5%" -> 12.5
+fn percent(value: &Value) -> f64 {
+    value.as_str().and_then(|v| v.trim().trim_end_matches('%').parse().ok()).unwrap_or(0.0)
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+/// Valeur d'un label dans le champ `Labels` ("a=1,b=2")
+fn label<'a>(labels: &'a str, key: &str) -> Option<&'a str> {
+    labels.split(',').find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// Parse `docker ps -a --format '{{json .}}'`
+pub fn parse_ps(output: &str) -> Vec<ContainerInfo> {
+    let mut containers: Vec<ContainerInfo> = json_lines(output)
+        .map(|c| ContainerInfo {
+            name: text(&c["Names"]),
+            service: label(c["Labels"].as_str().unwrap_or_default(), "com.docker.compose.service")
+                .unwrap_or_default()
+                .to_string(),
+            image: text(&c["Image"]),
+            state: text(&c["State"]),
+            status: text(&c["Status"]),
+            ports: text(&c["Ports"]),
+        })
+        .collect();
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+    containers
+}
+
+/// Parse `docker stats --no-stream --format '{{json .}}'`
+pub fn parse_stats(output: &str) -> Vec<ContainerStats> {
+    let mut stats: Vec<ContainerStats> = json_lines(output)
+        .map(|s| ContainerStats {
+            name: text(&s["Name"]),
+            cpu_percent: percent(&s["CPUPerc"]),
+            mem_percent: percent(&s["MemPerc"]),
+            mem_usage: text(&s["MemUsage"]),
+            net_io: text(&s["NetIO"]),
+            block_io: text(&s["BlockIO"]),
+        })
+        .collect();
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    stats
+}
+
+pub async fn ps(target: &SshTarget<'_>) -> Result<Vec<ContainerInfo>> {
+    Ok(parse_ps(&target.exec("docker ps -a --format '{{json .}}'").await?))
+}
+
+pub async fn stats(target: &SshTarget<'_>) -> Result<Vec<ContainerStats>> {
+    Ok(parse_stats(&target.exec("docker stats --no-stream --format '{{json .}}'").await?))
+}
+
+/// Dernières lignes de logs d'un conteneur (horodatées)
+pub async fn logs(target: &SshTarget<'_>, service: &str, tail: u32) -> Result<Vec<String>> {
+    check_service_name(service)?;
+    let output = target
+        .exec(&format!("docker logs --timestamps --tail {} {} 2>&1", tail.clamp(1, MAX_LOG_LINES), service))
+        .await?;
+    Ok(output.lines().map(String::from).collect())
+}
+
+/// Redémarre un service du compose et renvoie son nouvel état
+pub async fn restart(target: &SshTarget<'_>, service: &str) -> Result<ContainerInfo> {
+    check_service_name(service)?;
+    tracing::info!("Restarting {}...", service);
+    target.exec(&format!("cd ~/media-stack && docker compose restart {}", service)).await?;
+
+    ps(target)
+        .await?
+        .into_iter()
+        .find(|c| c.service == service || c.name == service)
+        .ok_or_else(|| anyhow!("Conteneur {} introuvable après redémarrage", service))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker_output() {
+        let ps = r#"{"Names":"radarr","Image":"lscr.io/linuxserver/radarr:latest","State":"running","Status":"Up 2 hours","Ports":"0.0.0.0:7878->7878/tcp","Labels":"com.docker.compose.project=media-stack,com.docker.compose.service=radarr"}
+{"Names":"decypharr","Image":"cy01/blackhole:latest","State":"exited","Status":"Exited (1) 3 minutes ago","Ports":"","Labels":""}
+"#;
+        let containers = parse_ps(ps);
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].name, "decypharr");
+        assert_eq!(containers[0].service, "");
+        assert_eq!(containers[1].service, "radarr");
+
+        let stats = parse_stats(r#"{"Name":"jellyfin","CPUPerc":"12.50%","MemPerc":"6.71%","MemUsage":"512MiB / 7.6GiB","NetIO":"1.2MB / 800kB","BlockIO":"0B / 0B"}"#);
+        assert_eq!(stats[0].cpu_percent, 12.5);
+        assert_eq!(stats[0].mem_percent, 6.71);
+
+        assert!(check_service_name("jellyseerr").is_ok());
+        assert!(check_service_name("radarr; rm -rf ~").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+use crate::services::arr_client::{ArrClient, JellyseerrClient};
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Détection de dérive: état de référence (Supabase) vs Pi réel
+// =============================================================================
+//
+// L'état est un dictionnaire plat "clé -> valeur":
+//   file:docker-compose.yml       -> sha256 du fichier
+//   container:radarr              -> image du conteneur
+//   radarr:rootfolders            -> chemins triés
+//   prowlarr:indexers             -> noms triés
+//   jellyseerr:servers            -> nom@hôte:port des *arr enregistrés
+// Il est capturé en fin d'installation (référence) puis comparé à la demande.
+
+/// Fichiers suivis, relatifs à ~/media-stack
+const TRACKED_FILES: [&str; 3] = ["docker-compose.yml", "decypharr/config.json", "status/status.sh"];
+
+/// Valeur d'une clé dont le service ne répond pas
+const UNREACHABLE: &str = "<unreachable>";
+
+pub type Snapshot = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum DriftKind {
+    /// Présent sur le Pi, absent de la référence
+    Added,
+    /// Présent dans la référence, absent du Pi
+    Removed,
+    Changed,
+    /// Service injoignable: valeur réelle inconnue, pas une dérive
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct DriftItem {
+    pub key: String,
+    pub kind: DriftKind,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct DriftReport {
+    pub pi_name: String,
+    pub checked_at: String,
+    /// Faux si aucune référence n'a été enregistrée (installation antérieure)
+    pub has_baseline: bool,
+    pub items: Vec<DriftItem>,
+}
+
+impl DriftReport {
+    pub fn in_sync(&self) -> bool {
+        self.has_baseline && self.items.is_empty()
+    }
+}
+
+fn snapshot_script() -> String {
+    format!(
+        "cd ~/media-stack 2>/dev/null || exit 0\n\
+         for f in {files}; do [ -f \"$f\" ] && echo \"file:$f=$(sha256sum \"$f\" | cut -d' ' -f1)\"; done\n\
+         docker ps -a --format 'container:{{{{.Names}}}}={{{{.Image}}}}' 2>/dev/null",
+        files = TRACKED_FILES.join(" ")
+    )
+}
+
+/// Parse les lignes "clé=valeur" du script de capture
+pub fn parse_snapshot_lines(output: &str) -> Snapshot {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .filter(|(key, _)| key.starts_with("file:") || key.starts_with("container:"))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Noms triés, séparés par des virgules
+fn sorted_names(names: impl Iterator<Item = String>) -> String {
+    let mut names: Vec<String> = names.collect();
+    names.sort();
+    names.join(",")
+}
+
+/// Valeur d'un réglage lu via l'API, `<unreachable>` si le service ne répond pas
+fn api_value<E>(result: std::result::Result<String, E>) -> String {
+    result.unwrap_or_else(|_| UNREACHABLE.to_string())
+}
+
+/// Capture l'état réel du Pi: fichiers, conteneurs et réglages lus via les API typées
+pub async fn capture_snapshot(target: &SshTarget<'_>) -> Result<Snapshot> {
+    let mut snapshot = parse_snapshot_lines(&target.exec(&snapshot_script()).await?);
+
+    let (rootfolders, downloadclients) = match ArrClient::connect(target, "radarr").await {
+        Ok(radarr) => (
+            api_value(radarr.root_folders().await.map(|f| sorted_names(f.into_iter().map(|f| f.path)))),
+            api_value(radarr.download_clients().await.map(|c| sorted_names(c.into_iter().map(|c| c.name)))),
+        ),
+        Err(_) => (UNREACHABLE.to_string(), UNREACHABLE.to_string()),
+    };
+    snapshot.insert("radarr:rootfolders".to_string(), rootfolders);
+    snapshot.insert("radarr:downloadclients".to_string(), downloadclients);
+
+    let (rootfolders, downloadclients) = match ArrClient::connect(target, "sonarr").await {
+        Ok(sonarr) => (
+            api_value(sonarr.root_folders().await.map(|f| sorted_names(f.into_iter().map(|f| f.path)))),
+            api_value(sonarr.download_clients().await.map(|c| sorted_names(c.into_iter().map(|c| c.name)))),
+        ),
+        Err(_) => (UNREACHABLE.to_string(), UNREACHABLE.to_string()),
+    };
+    snapshot.insert("sonarr:rootfolders".to_string(), rootfolders);
+    snapshot.insert("sonarr:downloadclients".to_string(), downloadclients);
+
+    let (indexers, applications) = match ArrClient::connect(target, "prowlarr").await {
+        Ok(prowlarr) => (
+            api_value(prowlarr.indexers().await.map(|i| sorted_names(i.into_iter().map(|i| i.name)))),
+            api_value(prowlarr.applications().await.map(|a| sorted_names(a.into_iter().map(|a| a.name)))),
+        ),
+        Err(_) => (UNREACHABLE.to_string(), UNREACHABLE.to_string()),
+    };
+    snapshot.insert("prowlarr:indexers".to_string(), indexers);
+    snapshot.insert("prowlarr:applications".to_string(), applications);
+
+    let servers = match JellyseerrClient::connect(target).await {
+        Ok(jellyseerr) => match (jellyseerr.list_arr_servers("radarr").await, jellyseerr.list_arr_servers("sonarr").await) {
+            (Ok(radarr), Ok(sonarr)) => sorted_names(
+                radarr.into_iter().chain(sonarr).map(|s| format!("{}@{}:{}", s.name, s.hostname, s.port)),
+            ),
+            _ => UNREACHABLE.to_string(),
+        },
+        Err(_) => UNREACHABLE.to_string(),
+    };
+    snapshot.insert("jellyseerr:servers".to_string(), servers);
+
+    Ok(snapshot)
+}
+
+/// Compare la référence à l'état réel
+pub fn diff_snapshots(expected: &Snapshot, actual: &Snapshot) -> Vec<DriftItem> {
+    let mut items = Vec::new();
+
+    for (key, expected_value) in expected {
+        match actual.get(key) {
+            None => items.push(DriftItem {
+                key: key.clone(),
+                kind: DriftKind::Removed,
+                expected: Some(expected_value.clone()),
+                actual: None,
+            }),
+            Some(actual_value) if actual_value == UNREACHABLE || expected_value == UNREACHABLE => {
+                items.push(DriftItem {
+                    key: key.clone(),
+                    kind: DriftKind::Unknown,
+                    expected: Some(expected_value.clone()),
+                    actual: None,
+                })
+            }
+            Some(actual_value) if actual_value != expected_value => items.push(DriftItem {
+                key: key.clone(),
+                kind: DriftKind::Changed,
+                expected: Some(expected_value.clone()),
+                actual: Some(actual_value.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (key, actual_value) in actual.iter().filter(|(k, v)| !expected.contains_key(*k) && *v != UNREACHABLE) {
+        items.push(DriftItem {
+            key: key.clone(),
+            kind: DriftKind::Added,
+            expected: None,
+            actual: Some(actual_value.clone()),
+        });
+    }
+
+    items
+}
+
+/// Compare l'état de référence enregistré dans Supabase avec le Pi
+pub async fn detect_drift(target: &SshTarget<'_>, pi_name: &str) -> Result<DriftReport> {
+    let baseline = crate::supabase::get_desired_state(pi_name).await?;
+    let actual = capture_snapshot(target).await?;
+
+    let items = match &baseline {
+        Some(expected) => diff_snapshots(expected, &actual),
+        None => {
+            tracing::warn!("⚠️ No baseline stored for {}", pi_name);
+            Vec::new()
+        }
+    };
+
+    let report = DriftReport {
+        pi_name: pi_name.to_string(),
+        checked_at: chrono::Utc::now().to_rfc3339(),
+        has_baseline: baseline.is_some(),
+        items,
+    };
+
+    if report.in_sync() {
+        tracing::info!("✅ {} matches its stored state", pi_name);
+    } else if report.has_baseline {
+        let unknown = report.items.iter().filter(|i| i.kind == DriftKind::Unknown).count();
+        tracing::info!(
+            "{} differences detected on {} ({} unreachable)",
+            report.items.len() - unknown,
+            pi_name,
+            unknown
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_snapshots() {
+        let expected = parse_snapshot_lines(
+            "file:docker-compose.yml=abc\ncontainer:radarr=lscr.io/linuxserver/radarr:latest\ncontainer:bazarr=bazarr\n",
+        );
+        let mut actual = expected.clone();
+        actual.insert("file:docker-compose.yml".to_string(), "def".to_string());
+        actual.remove("container:bazarr");
+        actual.insert("container:extra-navidrome".to_string(), "deluan/navidrome".to_string());
+
+        let items = diff_snapshots(&expected, &actual);
+        let kind_of = |key: &str| items.iter().find(|i| i.key == key).map(|i| i.kind);
+        assert_eq!(items.len(), 3);
+        assert_eq!(kind_of("file:docker-compose.yml"), Some(DriftKind::Changed));
+        assert_eq!(kind_of("container:bazarr"), Some(DriftKind::Removed));
+        assert_eq!(kind_of("container:extra-navidrome"), Some(DriftKind::Added));
+        assert!(diff_snapshots(&expected, &expected).is_empty());
+
+        let mut expected = expected;
+        expected.insert("radarr:rootfolders".to_string(), "/mnt/media/movies".to_string());
+        let mut unreachable = expected.clone();
+        unreachable.insert("radarr:rootfolders".to_string(), UNREACHABLE.to_string());
+        unreachable.insert("sonarr:rootfolders".to_string(), UNREACHABLE.to_string());
+        let items = diff_snapshots(&expected, &unreachable);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, DriftKind::Unknown);
+        assert_eq!(items[0].actual, None);
+    }
+}
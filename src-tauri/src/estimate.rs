@@ -0,0 +1,277 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+use ts_rs::TS;
+
+use crate::install_state::InstallStep;
+
+// =============================================================================
+// Estimation de la durée et du volume téléchargé avant installation
+// =============================================================================
+//
+// - taille de l'image Raspberry Pi OS (HEAD sur le miroir officiel)
+// - débit mesuré en téléchargeant les premiers Mo de cette image
+// - durées des étapes des installations précédentes (benchmarks locaux)
+// - modèle du Pi (un Pi 4 met ~1.6x plus de temps qu'un Pi 5)
+
+/// Volume des images Docker de la stack (arm64, compressé)
+const DOCKER_IMAGES_BYTES: u64 = 2_300_000_000;
+
+/// Taille décompressée de l'image Raspberry Pi OS Lite (écriture sur la SD)
+const EXTRACTED_IMAGE_BYTES: u64 = 2_700_000_000;
+
+/// Débit d'écriture prudent d'une carte SD (octets/s)
+const SD_WRITE_BYTES_PER_SEC: u64 = 15_000_000;
+
+/// Échantillon téléchargé pour mesurer le débit
+const SPEED_SAMPLE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Durées de référence sur Pi 5 (secondes), hors téléchargement des images Docker
+const BASELINE_STEP_SECS: [(InstallStep, u64); 8] = [
+    (InstallStep::SystemUpdate, 420),
+    (InstallStep::Docker, 180),
+    (InstallStep::Structure, 10),
+    (InstallStep::Compose, 5),
+    (InstallStep::ImagePull, 120),
+    (InstallStep::Containers, 90),
+    (InstallStep::Configuration, 240),
+    (InstallStep::CloudSync, 15),
+];
+
+/// Premier démarrage du Pi après flash (resize, cloud-init, SSH)
+const FIRST_BOOT_SECS: u64 = 180;
+
+/// Modèle de Pi, pour pondérer les durées de référence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum PiModel {
+    Pi5,
+    Pi4,
+    Other,
+}
+
+impl PiModel {
+    /// Depuis /proc/device-tree/model ("Raspberry Pi 5 Model B Rev 1.0")
+    pub fn from_model_string(model: &str) -> Self {
+        if model.contains("Raspberry Pi 5") || model.eq_ignore_ascii_case("pi5") {
+            Self::Pi5
+        } else if model.contains("Raspberry Pi 4") || model.eq_ignore_ascii_case("pi4") {
+            Self::Pi4
+        } else {
+            Self::Other
+        }
+    }
+
+    fn slowdown(self) -> f64 {
+        match self {
+            Self::Pi5 => 1.0,
+            Self::Pi4 => 1.6,
+            Self::Other => 2.5,
+        }
+    }
+}
+
+/// Phase de l'estimation
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct EstimatePhase {
+    pub name: String,
+    #[ts(type = "number")]
+    pub seconds: u64,
+    /// Vrai si la durée vient d'installations précédentes
+    pub from_history: bool,
+}
+
+/// Estimation retournée au frontend
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct InstallEstimate {
+    pub pi_model: PiModel,
+    #[ts(type = "number")]
+    pub total_seconds: u64,
+    #[ts(type = "number")]
+    pub download_bytes: u64,
+    /// Débit mesuré (Mo/s), absent si la mesure a échoué
+    pub download_mbps: Option<f64>,
+    pub phases: Vec<EstimatePhase>,
+    /// "environ 35 minutes, 2.8 Go à télécharger"
+    pub summary: String,
+}
+
+/// Moyenne glissante des durées d'une étape
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StepBenchmark {
+    pub average_secs: f64,
+    pub samples: u32,
+}
+
+pub type Benchmarks = HashMap<InstallStep, StepBenchmark>;
+
+fn benchmarks_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("jellysetup").join("benchmarks.json"))
+}
+
+pub fn load_benchmarks() -> Benchmarks {
+    benchmarks_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Enregistre la durée d'une étape terminée (appelé par InstallState)
+pub fn record_step_duration(step: InstallStep, secs: u64) {
+    let mut benchmarks = load_benchmarks();
+    let entry = benchmarks.entry(step).or_default();
+    // Les 10 dernières installations pèsent le plus
+    let weight = entry.samples.min(9) as f64;
+    entry.average_secs = (entry.average_secs * weight + secs as f64) / (weight + 1.0);
+    entry.samples += 1;
+
+    let saved = benchmarks_path()
+        .ok_or_else(|| anyhow!("Cannot determine config directory"))
+        .and_then(|path| {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(&benchmarks)?)?;
+            Ok(())
+        });
+    if let Err(e) = saved {
+        tracing::warn!("⚠️ Could not save benchmark for {:?}: {}", step, e);
+    }
+}
+
+/// Mesure le débit (octets/s) en téléchargeant le début de l'image
+async fn measure_download_speed(client: &reqwest::Client, url: &str) -> Result<f64> {
+    let started = Instant::now();
+    let bytes = client
+        .get(url)
+        .header(reqwest::header::RANGE, format!("bytes=0-{}", SPEED_SAMPLE_BYTES - 1))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let elapsed = started.elapsed().as_secs_f64().max(0.001);
+    Ok(bytes.len() as f64 / elapsed)
+}
+
+/// Calcule l'estimation à partir des mesures (sans accès réseau)
+pub fn build_estimate(
+    pi_model: PiModel,
+    image_bytes: Option<u64>,
+    bytes_per_sec: Option<f64>,
+    benchmarks: &Benchmarks,
+) -> InstallEstimate {
+    let mut phases = Vec::new();
+    let transfer_secs = |bytes: u64| bytes_per_sec.map(|speed| (bytes as f64 / speed.max(1.0)) as u64);
+
+    if let Some(image_bytes) = image_bytes {
+        phases.push(EstimatePhase {
+            name: "download".to_string(),
+            seconds: transfer_secs(image_bytes).unwrap_or(300),
+            from_history: false,
+        });
+        phases.push(EstimatePhase {
+            name: "write".to_string(),
+            seconds: EXTRACTED_IMAGE_BYTES / SD_WRITE_BYTES_PER_SEC,
+            from_history: false,
+        });
+        phases.push(EstimatePhase {
+            name: "first_boot".to_string(),
+            seconds: FIRST_BOOT_SECS,
+            from_history: false,
+        });
+    }
+
+    for (step, baseline) in BASELINE_STEP_SECS {
+        let history = benchmarks.get(&step).filter(|b| b.samples > 0);
+        let mut seconds = match history {
+            Some(bench) => bench.average_secs as u64,
+            None => (baseline as f64 * pi_model.slowdown()) as u64,
+        };
+        // Sans historique, le pull dépend surtout du débit mesuré
+        if step == InstallStep::ImagePull && history.is_none() {
+            seconds += transfer_secs(DOCKER_IMAGES_BYTES).unwrap_or(600);
+        }
+        phases.push(EstimatePhase {
+            name: serde_json::to_value(step)
+                .ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default(),
+            seconds,
+            from_history: history.is_some(),
+        });
+    }
+
+    let total_seconds: u64 = phases.iter().map(|p| p.seconds).sum();
+    let download_bytes = DOCKER_IMAGES_BYTES + image_bytes.unwrap_or(0);
+    let summary = format!(
+        "environ {} minutes, {:.1} Go à télécharger",
+        total_seconds.div_ceil(60),
+        download_bytes as f64 / 1_000_000_000.0
+    );
+
+    InstallEstimate {
+        pi_model,
+        total_seconds,
+        download_bytes,
+        download_mbps: bytes_per_sec.map(|speed| (speed / 100_000.0).round() / 10.0),
+        phases,
+        summary,
+    }
+}
+
+/// Estimation complète: `include_flash` ajoute téléchargement, écriture SD et premier boot
+pub async fn estimate_install(pi_model: PiModel, include_flash: bool) -> Result<InstallEstimate> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+
+    let (url, _) = crate::flash::get_latest_rpi_os_url().await?;
+
+    let image_bytes = if include_flash {
+        client.head(&url).send().await.ok().and_then(|r| r.content_length())
+    } else {
+        None
+    };
+
+    let bytes_per_sec = match measure_download_speed(&client, &url).await {
+        Ok(speed) => Some(speed),
+        Err(e) => {
+            tracing::warn!("⚠️ Download speed measurement failed: {}", e);
+            None
+        }
+    };
+
+    let estimate = build_estimate(pi_model, image_bytes, bytes_per_sec, &load_benchmarks());
+    tracing::info!("{:?}: {}", pi_model, estimate.summary);
+    Ok(estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_estimate() {
+        let mut benchmarks = Benchmarks::new();
+        benchmarks.insert(InstallStep::SystemUpdate, StepBenchmark { average_secs: 600.0, samples: 3 });
+
+        let estimate = build_estimate(PiModel::Pi4, Some(500_000_000), Some(10_000_000.0), &benchmarks);
+        let phase = |name: &str| estimate.phases.iter().find(|p| p.name == name).unwrap().clone();
+
+        assert_eq!(phase("download").seconds, 50);
+        assert_eq!(phase("system_update").seconds, 600);
+        assert!(phase("system_update").from_history);
+        assert_eq!(phase("docker").seconds, 288);
+        assert_eq!(phase("image_pull").seconds, 192 + 230);
+        assert_eq!(estimate.download_bytes, 2_800_000_000);
+        assert_eq!(estimate.download_mbps, Some(10.0));
+        assert!(estimate.summary.ends_with("2.8 Go à télécharger"));
+        assert_eq!(PiModel::from_model_string("Raspberry Pi 5 Model B Rev 1.0"), PiModel::Pi5);
+    }
+}
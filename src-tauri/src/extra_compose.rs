@@ -0,0 +1,240 @@
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+
+// =============================================================================
+// Services personnalisés (extra-compose.yml) fusionnés dans le stack généré
+// =============================================================================
+//
+// Format accepté (YAML docker compose, seule la clé `services` est lue):
+//   services:
+//     navidrome:
+//       image: deluan/navidrome:latest
+//       ports:
+//         - 4533:4533
+//       volumes:
+//         - ./navidrome:/data
+//
+// Chaque service est renommé `extra-<nom>` (container_name imposé) pour ne jamais
+// écraser un service du stack, puis réécrit par serde_yaml (ancres et alias
+// résolus). La fusion est faite à la génération: les services sont conservés à
+// chaque réinstallation.
+//
+// Les options qui donnent accès à l'hôte sont refusées (privileged, cap_add,
+// devices, network_mode...), ainsi que les volumes montant un dossier système
+// (/, /etc, socket Docker...). Les ports sont lus sous toutes leurs formes
+// ("8080:80", liste `[..]`, `published`) pour détecter les conflits.
+
+pub const EXTRA_PREFIX: &str = "extra-";
+
+/// Options de service refusées (accès à l'hôte)
+const FORBIDDEN_KEYS: &[&str] = &[
+    "privileged", "network_mode", "pid", "ipc", "userns_mode", "cap_add", "devices", "device_cgroup_rules",
+    "security_opt", "sysctls", "<<",
+];
+
+/// Dossiers de l'hôte qui ne peuvent pas être montés
+const FORBIDDEN_MOUNTS: &[&str] = &[
+    "/", "/etc", "/proc", "/sys", "/dev", "/boot", "/root", "/run", "/var/run", "/var/lib/docker", "/usr", "/bin",
+    "/sbin", "/lib",
+];
+
+/// Service utilisateur validé
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraService {
+    pub name: String,
+    /// Définition d'origine, sans container_name
+    definition: Mapping,
+}
+
+impl ExtraService {
+    /// Nom du service dans le stack (namespacé)
+    pub fn namespaced_name(&self) -> String {
+        format!("{}{}", EXTRA_PREFIX, self.name)
+    }
+
+    fn render(&self) -> String {
+        let name = self.namespaced_name();
+        let mut node = Mapping::new();
+        node.insert("container_name".into(), name.clone().into());
+        node.extend(self.definition.clone());
+        let mut service = Mapping::new();
+        service.insert(name.into(), node.into());
+        format!(
+            "\n  # Service personnalisé (extra-compose.yml)\n{}",
+            crate::compose::to_yaml_indented(&service, 2)
+        )
+    }
+}
+
+/// Source d'un volume montée depuis un dossier système de l'hôte
+fn is_forbidden_mount(source: &str) -> bool {
+    if !source.starts_with('/') {
+        return false;
+    }
+    let path = match source.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    };
+    FORBIDDEN_MOUNTS
+        .iter()
+        .any(|dir| path == *dir || (*dir != "/" && path.starts_with(&format!("{}/", dir))))
+}
+
+/// Source côté hôte d'un volume: "/src:/dst[:ro]" ou syntaxe longue (`source`)
+fn volume_source(volume: &Value) -> Option<&str> {
+    match volume {
+        Value::String(volume) => volume.split(':').next(),
+        Value::Mapping(long) => long.get("source").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 40
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn parse_service(name: &str, definition: &Value) -> Result<ExtraService> {
+    let definition = definition
+        .as_mapping()
+        .ok_or_else(|| anyhow!("Service '{}': définition attendue", name))?;
+
+    let mut kept = Mapping::new();
+    for (key, value) in definition {
+        let key = key.as_str().ok_or_else(|| anyhow!("Service '{}': clé invalide", name))?;
+        match key {
+            // Imposé par le namespacing
+            "container_name" => continue,
+            key if FORBIDDEN_KEYS.contains(&key) => {
+                return Err(anyhow!("Service '{}': '{}' n'est pas autorisé", name, key));
+            }
+            "volumes" => {
+                let volumes = value.as_sequence().map(Vec::as_slice).unwrap_or_default();
+                if let Some(source) = volumes.iter().filter_map(volume_source).find(|s| is_forbidden_mount(s)) {
+                    return Err(anyhow!("Service '{}': montage de {} interdit", name, source));
+                }
+            }
+            _ => {}
+        }
+        kept.insert(key.into(), value.clone());
+    }
+
+    if !matches!(kept.get("image"), Some(Value::String(image)) if !image.is_empty()) {
+        return Err(anyhow!("Service '{}': 'image' obligatoire", name));
+    }
+    Ok(ExtraService { name: name.to_string(), definition: kept })
+}
+
+/// Parse et valide un extra-compose.yml
+pub fn parse_extra_compose(yaml: &str) -> Result<Vec<ExtraService>> {
+    let document: Value = serde_yaml::from_str(yaml).map_err(|e| anyhow!("extra-compose.yml invalide: {}", e))?;
+    let root = document
+        .as_mapping()
+        .ok_or_else(|| anyhow!("extra-compose.yml: section 'services' attendue"))?;
+    for key in root.keys() {
+        match key.as_str() {
+            Some("services") | Some("version") => {}
+            other => {
+                return Err(anyhow!("Clé '{}' non supportée (seul 'services' est accepté)", other.unwrap_or("?")));
+            }
+        }
+    }
+
+    let services = root
+        .get("services")
+        .and_then(Value::as_mapping)
+        .filter(|services| !services.is_empty())
+        .ok_or_else(|| anyhow!("Aucun service dans extra-compose.yml"))?;
+    services
+        .iter()
+        .map(|(name, definition)| {
+            let name = name
+                .as_str()
+                .filter(|name| is_valid_name(name))
+                .ok_or_else(|| anyhow!("Nom de service '{}' invalide", serde_yaml::to_string(name).unwrap_or_default().trim()))?;
+            parse_service(name, definition)
+        })
+        .collect()
+}
+
+/// Ports publiés sur l'hôte par un compose (`ports` des services, toutes syntaxes)
+pub fn host_ports(compose: &str) -> Vec<u16> {
+    crate::compose::parse_services(compose)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|(_, service)| crate::compose::service_host_ports(service))
+        .collect()
+}
+
+/// Fusionne les services utilisateur dans le compose généré (avant la section volumes)
+/// Refuse les ports déjà publiés par le stack
+pub fn merge_into(generated: String, extra_yaml: Option<&str>) -> Result<String> {
+    let Some(extra_yaml) = extra_yaml.filter(|y| !y.trim().is_empty()) else {
+        return Ok(generated);
+    };
+
+    let services = parse_extra_compose(extra_yaml)?;
+
+    let mut used_ports = host_ports(&generated);
+    used_ports.push(crate::status::STATUS_PORT);
+    let extra_ports = services
+        .iter()
+        .flat_map(|service| crate::compose::service_host_ports(&Value::Mapping(service.definition.clone())));
+    for port in extra_ports {
+        if used_ports.contains(&port) {
+            return Err(anyhow!("Port {} déjà utilisé par le stack (extra-compose.yml)", port));
+        }
+        used_ports.push(port);
+    }
+
+    let rendered: String = services.iter().map(ExtraService::render).collect();
+    let insert_at = generated
+        .rfind("\nvolumes:\n")
+        .ok_or_else(|| anyhow!("Section volumes absente du docker-compose généré"))?;
+
+    let mut merged = generated;
+    merged.insert_str(insert_at, &rendered);
+
+    tracing::info!(
+        "✅ Merged {} custom services: {}",
+        services.len(),
+        services.iter().map(|s| s.namespaced_name()).collect::<Vec<_>>().join(", ")
+    );
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENERATED: &str = "services:\n  jellyfin:\n    image: jellyfin/jellyfin\n    ports:\n      - 8096:8096\n\nvolumes:\n  supabazarr_data:\n";
+
+    #[test]
+    fn test_merge_extra_compose() {
+        let extra = "services:\n  navidrome:\n    image: deluan/navidrome:latest\n    container_name: mine\n    ports:\n      - \"4533:4533\"\n";
+        let merged = merge_into(GENERATED.to_string(), Some(extra)).unwrap();
+        assert!(merged.contains("  extra-navidrome:\n    container_name: extra-navidrome\n    image: deluan/navidrome:latest\n"));
+        assert!(!merged.contains("container_name: mine"));
+        assert!(merged.find("extra-navidrome").unwrap() < merged.find("\nvolumes:").unwrap());
+        assert_eq!(host_ports(&merged), vec![8096, 4533]);
+
+        let conflict = "services:\n  other:\n    image: x\n    ports:\n      - 8096:80\n";
+        assert!(merge_into(GENERATED.to_string(), Some(conflict)).is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    privileged: true\n").is_err());
+        assert!(parse_extra_compose("networks:\n  x:\n").is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    cap_add:\n      - SYS_ADMIN\n").is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    devices:\n      - /dev/sda:/dev/sda\n").is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    volumes:\n      - /:/host\n").is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    volumes:\n    - /:/host\n").is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    <<: {privileged: true}\n").is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    volumes: [\"/var/run/docker.sock:/sock\"]\n").is_err());
+        assert!(parse_extra_compose("services:\n  bad:\n    image: x\n    volumes:\n      - type: bind\n        source: /etc\n        target: /etc\n").is_err());
+        assert!(parse_extra_compose("services:\n  ok:\n    image: x\n    volumes:\n      - ./ok:/data\n      - /mnt/media:/media:ro\n").is_ok());
+        let flow = "services:\n  other:\n    image: x\n    ports: [\"8096:80\"]\n";
+        assert!(merge_into(GENERATED.to_string(), Some(flow)).is_err());
+        assert_eq!(host_ports("services:\n  a:\n    ports:\n      - target: 80\n        published: \"8080\"\n      - 9000-9001:9000-9001/udp\n"), vec![8080, 9000, 9001]);
+        assert_eq!(host_ports("services:\n  a:\n    ports:\n    - 127.0.0.1:8443:443\n"), vec![8443]);
+        assert_eq!(merge_into(GENERATED.to_string(), None).unwrap(), GENERATED);
+    }
+}
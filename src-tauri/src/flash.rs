@@ -1,10 +1,20 @@
 use crate::{FlashConfig, FlashProgress, InstallConfig, JellyfinAuth};
+use crate::hooks::HookPhase;
+use crate::install_state::{InstallState, InstallStep};
+use crate::install_progress::{compose_image_count, sub_percent, AptProgress, LineBuffer, PullProgress};
+use crate::config::ports::{BAZARR, JELLYFIN, JELLYSEERR, PROWLARR, RADARR, SONARR};
+use crate::master_config::MediaPaths;
+use crate::services::{jellyfin, jellyseerr};
+use crate::services::profiles;
+use crate::template_engine::TemplateVars;
 use anyhow::{anyhow, Result};
 use regex::Regex;
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufWriter, Write};
+use std::future::Future;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tauri::Window;
 use tokio::process::Command;
 
@@ -16,7 +26,7 @@ use std::os::unix::fs::PermissionsExt;
 
 /// Debug logging - écrit dans /tmp/jellysetup_debug.log
 fn debug_log(msg: &str) {
-    println!("{}", msg);
+    tracing::info!("{}", msg);
     if let Ok(mut file) = OpenOptions::new()
         .create(true)
         .append(true)
@@ -36,34 +46,200 @@ struct FlashGuard;
 impl Drop for FlashGuard {
     fn drop(&mut self) {
         FLASH_IN_PROGRESS.store(false, Ordering::SeqCst);
-        println!("[FLASH] Lock released - flash complete or failed");
+        tracing::info!("Lock released - flash complete or failed");
     }
 }
 
-// URL de base pour lister les versions de Raspberry Pi OS
-const RPI_OS_INDEX_URL: &str = "https://downloads.raspberrypi.com/raspios_lite_arm64/images/";
+// =============================================================================
+// Fonctions pures du flash (parsing, progression, fichiers de la partition boot)
+// =============================================================================
+
+/// Dossiers de l'index Raspberry Pi OS ("raspios_lite_arm64-YYYY-MM-DD/", date), du plus récent au plus ancien
+pub(crate) fn parse_rpi_os_versions(index_html: &str) -> Vec<(String, String)> {
+    let re = Regex::new(r#"href="(raspios_lite_arm64-(\d{4}-\d{2}-\d{2})/)""#).unwrap();
+    let mut versions: Vec<(String, String)> = re.captures_iter(index_html)
+        .map(|cap| (cap[1].to_string(), cap[2].to_string()))
+        .collect();
+    versions.sort_by(|a, b| b.1.cmp(&a.1));
+    versions.dedup();
+    versions
+}
+
+/// Image Bookworm d'un dossier de version (None si le dossier mentionne Trixie)
+pub(crate) fn find_bookworm_image(folder_html: &str) -> Option<String> {
+    if !folder_html.contains("bookworm") || folder_html.contains("trixie") {
+        return None;
+    }
+    let re = Regex::new(r#"href="([^"]*bookworm[^"]*\.img\.xz)""#).unwrap();
+    re.captures(folder_html).map(|cap| cap[1].to_string())
+}
+
+/// Première image .img.xz d'un dossier de version
+pub(crate) fn find_image(folder_html: &str) -> Option<String> {
+    let re = Regex::new(r#"href="([^"]+\.img\.xz)""#).unwrap();
+    re.captures(folder_html).map(|cap| cap[1].to_string())
+}
+
+/// Progression du téléchargement (0-30)
+fn download_percent(downloaded: u64, total_size: u64) -> u32 {
+    if total_size > 0 {
+        (downloaded.min(total_size) * 30 / total_size) as u32
+    } else {
+        0
+    }
+}
+
+/// Dernière progression rapportée par dd (SIGINFO ou fin d'exécution)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DdProgress {
+    pub bytes: u64,
+    pub bytes_per_sec: Option<f64>,
+}
+
+/// Parse "2841640960 bytes transferred in 997.746971 secs (2848058 bytes/sec)" (dernière ligne)
+pub(crate) fn parse_dd_progress(log: &str) -> Option<DdProgress> {
+    let line = log.lines().rev().find(|l| l.contains("bytes") && l.contains("transferred"))?;
+    let bytes = line.split_whitespace().next()?.parse::<u64>().ok()?;
+    let bytes_per_sec = line
+        .rfind('(')
+        .zip(line.rfind(" bytes/sec)"))
+        .filter(|(start, end)| start < end)
+        .and_then(|(start, end)| line[start + 1..end].parse::<f64>().ok());
+    Some(DdProgress { bytes, bytes_per_sec })
+}
+
+/// Issue d'un dd d'après son log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DdOutcome {
+    Completed,
+    /// Accès complet au disque refusé par macOS
+    PermissionDenied,
+    Unknown,
+}
+
+pub(crate) fn dd_log_outcome(log: &str) -> DdOutcome {
+    if log.contains("bytes transferred") {
+        DdOutcome::Completed
+    } else if log.contains("Operation not permitted") || log.contains("Permission denied") {
+        DdOutcome::PermissionDenied
+    } else {
+        DdOutcome::Unknown
+    }
+}
+
+/// Pourcentage réel de l'écriture (plafonné à 99 tant que dd n'a pas terminé)
+fn write_percent(written: u64, image_size: u64) -> u32 {
+    if image_size == 0 {
+        return 0;
+    }
+    ((written as f64 / image_size as f64) * 100.0).min(99.0) as u32
+}
+
+/// Temps restant estimé (secondes) à la vitesse courante en MB/s
+fn remaining_write_secs(written: u64, image_size: u64, speed_mbps: f64) -> u64 {
+    if speed_mbps > 0.1 {
+        (image_size.saturating_sub(written) as f64 / (speed_mbps * 1_000_000.0)) as u64
+    } else {
+        0
+    }
+}
+
+/// Progression globale: l'écriture occupe 25% à 75% de la barre
+fn overall_write_percent(write_percent: u32) -> u32 {
+    25 + (write_percent.min(100) * 50 / 100)
+}
+
+fn format_remaining(remaining_secs: u64) -> String {
+    let (minutes, seconds) = (remaining_secs / 60, remaining_secs % 60);
+    if minutes > 0 {
+        format!("~{}min{}s restant", minutes, seconds)
+    } else if remaining_secs > 0 {
+        format!("~{}s restant", remaining_secs)
+    } else {
+        "finalisation...".to_string()
+    }
+}
+
+/// custom.toml lu par raspberrypi-sys-mods au premier boot (méthode Bookworm 2024+).
+/// La partition boot (FAT) est lisible par tous: mot de passe système haché en
+/// SHA-512 crypt et WiFi sous forme de PSK, jamais en clair.
+pub(crate) fn custom_toml(config: &FlashConfig, ssh_public_key: &str) -> Result<String> {
+    // Réseau ouvert: pas de mot de passe à protéger
+    let (wifi_password, wifi_encrypted) = if config.wifi_password.is_empty() {
+        (String::new(), false)
+    } else {
+        (crate::crypto::wpa_psk(&config.wifi_ssid, config.wifi_password.expose())?, true)
+    };
+    Ok(format!(
+        r#"# Configuration JellySetup - Raspberry Pi OS Bookworm
+config_version = 1
+
+[system]
+hostname = "{hostname}"
+
+[user]
+name = "{username}"
+password = "{password}"
+password_encrypted = true
+
+[ssh]
+enabled = true
+password_authentication = true
+authorized_keys = [ {ssh_keys} ]
+
+[wlan]
+ssid = "{wifi_ssid}"
+password = "{wifi_password}"
+password_encrypted = {wifi_encrypted}
+hidden = {wifi_hidden}
+country = "{wifi_country}"
+
+[locale]
+keymap = "{keymap}"
+timezone = "{timezone}"
+"#,
+        hostname = config.hostname,
+        username = config.system_username,
+        password = crate::crypto::sha512_crypt(config.system_password.expose())?,
+        ssh_keys = std::iter::once(ssh_public_key)
+            .chain(config.extra_authorized_keys.iter().map(String::as_str))
+            .map(|key| format!("\"{}\"", key.trim()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        wifi_ssid = config.wifi_ssid,
+        wifi_password = wifi_password,
+        wifi_encrypted = wifi_encrypted,
+        wifi_hidden = config.wifi_hidden,
+        wifi_country = config.wifi_country,
+        keymap = config.keymap,
+        timezone = config.timezone,
+    ))
+}
+
+/// userconf.txt (anciennes versions): "username:hash SHA-512 crypt"
+pub(crate) fn userconf(config: &FlashConfig) -> Result<String> {
+    Ok(format!(
+        "{}:{}",
+        config.system_username,
+        crate::crypto::sha512_crypt(config.system_password.expose())?
+    ))
+}
 
 /// Récupère l'URL de la dernière version de Raspberry Pi OS Lite 64-bit (Bookworm)
 /// Note: On évite Trixie car custom.toml ne fonctionne pas (cloud-init requis)
-async fn get_latest_rpi_os_url() -> Result<(String, String)> {
+pub(crate) async fn get_latest_rpi_os_url() -> Result<(String, String)> {
     let client = reqwest::Client::new();
+    // URL de base pour lister les versions (miroir possible, voir config::Settings)
+    let index_url = crate::config::settings().rpi_os_index_url;
 
     // Récupérer la liste des versions
-    let index_html = client.get(RPI_OS_INDEX_URL)
+    let index_html = client.get(&index_url)
         .send()
         .await?
         .text()
         .await?;
 
-    // Trouver toutes les versions (format: raspios_lite_arm64-YYYY-MM-DD/)
-    let re = Regex::new(r#"href="(raspios_lite_arm64-(\d{4}-\d{2}-\d{2})/)""#)?;
-
-    let mut versions: Vec<(String, String)> = re.captures_iter(&index_html)
-        .map(|cap| (cap[1].to_string(), cap[2].to_string()))
-        .collect();
-
-    // Trier par date décroissante
-    versions.sort_by(|a, b| b.1.cmp(&a.1));
+    let versions = parse_rpi_os_versions(&index_html);
 
     // Chercher la dernière version BOOKWORM (pas Trixie)
     // On vérifie le contenu de chaque dossier jusqu'à trouver une version bookworm
@@ -71,18 +247,14 @@ async fn get_latest_rpi_os_url() -> Result<(String, String)> {
     let mut image_filename = String::new();
 
     for version in &versions {
-        let folder_url = format!("{}{}", RPI_OS_INDEX_URL, version.0);
+        let folder_url = format!("{}{}", index_url, version.0);
         if let Ok(resp) = client.get(&folder_url).send().await {
             if let Ok(folder_html) = resp.text().await {
-                // Chercher un fichier bookworm (pas trixie)
-                if folder_html.contains("bookworm") && !folder_html.contains("trixie") {
-                    let file_re = Regex::new(r#"href="([^"]*bookworm[^"]*\.img\.xz)""#)?;
-                    if let Some(cap) = file_re.captures(&folder_html) {
-                        image_filename = cap[1].to_string();
-                        latest_folder = Some(version);
-                        println!("[Flash] Found Bookworm version: {}", version.0);
-                        break;
-                    }
+                if let Some(filename) = find_bookworm_image(&folder_html) {
+                    image_filename = filename;
+                    latest_folder = Some(version);
+                    tracing::info!("Found Bookworm version: {}", version.0);
+                    break;
                 }
             }
         }
@@ -91,7 +263,7 @@ async fn get_latest_rpi_os_url() -> Result<(String, String)> {
     let latest_folder = latest_folder
         .ok_or_else(|| anyhow!("Aucune version Bookworm trouvée sur le serveur Raspberry Pi"))?;
 
-    let folder_url = format!("{}{}", RPI_OS_INDEX_URL, latest_folder.0);
+    let folder_url = format!("{}{}", index_url, latest_folder.0);
 
     // Si on n'a pas encore le nom du fichier, le récupérer
     let image_filename = if image_filename.is_empty() {
@@ -100,10 +272,7 @@ async fn get_latest_rpi_os_url() -> Result<(String, String)> {
             .await?
             .text()
             .await?;
-        let file_re = Regex::new(r#"href="([^"]+\.img\.xz)""#)?;
-        file_re.captures(&folder_html)
-            .ok_or_else(|| anyhow!("Fichier image non trouvé"))?[1]
-            .to_string()
+        find_image(&folder_html).ok_or_else(|| anyhow!("Fichier image non trouvé"))?
     } else {
         image_filename
     };
@@ -111,70 +280,37 @@ async fn get_latest_rpi_os_url() -> Result<(String, String)> {
     let full_url = format!("{}{}", folder_url, image_filename);
     let extracted_name = image_filename.trim_end_matches(".xz").to_string();
 
-    println!("[Flash] Using Raspberry Pi OS Bookworm: {}", latest_folder.1);
-    println!("[Flash] URL: {}", full_url);
+    tracing::info!("Using Raspberry Pi OS Bookworm: {}", latest_folder.1);
+    tracing::info!("URL: {}", full_url);
 
     Ok((full_url, extracted_name))
 }
 
-/// Récupère la taille d'un disque en bytes
-async fn get_disk_size(device_path: &str) -> Result<u64> {
-    #[cfg(target_os = "macos")]
-    {
-        let disk_path = device_path.replace("/dev/r", "/dev/");
-        let output = Command::new("diskutil")
-            .args(["info", &disk_path])
-            .output()
-            .await?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        // Chercher "Disk Size:" dans la sortie
-        for line in stdout.lines() {
-            if line.contains("Disk Size:") || line.contains("Total Size:") {
-                // Format: "Disk Size:                 31.9 GB (31914983424 Bytes)"
-                if let Some(start) = line.find('(') {
-                    if let Some(end) = line.find(" Bytes)") {
-                        let size_str = &line[start + 1..end];
-                        if let Ok(size) = size_str.parse::<u64>() {
-                            return Ok(size);
-                        }
-                    }
-                }
-            }
-        }
-        Err(anyhow!("Impossible de déterminer la taille du disque"))
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        // Pour les autres OS, retourner une valeur par défaut sûre
-        Ok(32 * 1024 * 1024 * 1024) // 32GB par défaut
-    }
-}
-
 /// Flash Raspberry Pi OS sur la carte SD
+#[tracing::instrument(name = "flash", skip_all, fields(sd_path = %config.sd_path, hostname = %config.hostname))]
 pub async fn flash_raspberry_pi_os(
     window: Window,
     config: FlashConfig,
     ssh_public_key: String,
 ) -> Result<()> {
-    println!("========================================");
-    println!("[FLASH] Starting flash_raspberry_pi_os");
-    println!("[FLASH] SD Path: {}", config.sd_path);
-    println!("[FLASH] Hostname: {}", config.hostname);
-    println!("========================================");
+    tracing::info!("========================================");
+    tracing::info!("Starting flash_raspberry_pi_os");
+    tracing::info!("SD Path: {}", config.sd_path);
+    tracing::info!("Hostname: {}", config.hostname);
+    tracing::info!("========================================");
 
     // Protection contre les lancements multiples
     if FLASH_IN_PROGRESS.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
-        println!("[FLASH] ERROR: Flash already in progress!");
+        tracing::error!("ERROR: Flash already in progress!");
         return Err(anyhow!("Un flash est déjà en cours. Veuillez patienter."));
     }
-    println!("[FLASH] Lock acquired - no other flash can start");
+    tracing::info!("Lock acquired - no other flash can start");
 
     // Garantir qu'on libère le lock même en cas d'erreur
     let _guard = FlashGuard;
 
+    crate::preflight::guard_host(crate::preflight::HostOperation::Flash, &config.host_overrides).await?;
+
     // Empêcher la mise en veille du Mac pendant le flash
     #[cfg(target_os = "macos")]
     let _caffeinate = {
@@ -183,11 +319,11 @@ pub async fn flash_raspberry_pi_os(
             .spawn()
         {
             Ok(child) => {
-                println!("[FLASH] caffeinate started (PID: {})", child.id());
+                tracing::info!("caffeinate started (PID: {})", child.id());
                 Some(child)
             }
             Err(e) => {
-                println!("[FLASH] Warning: could not start caffeinate: {}", e);
+                tracing::warn!("could not start caffeinate: {}", e);
                 None
             }
         }
@@ -197,137 +333,200 @@ pub async fn flash_raspberry_pi_os(
         .ok_or_else(|| anyhow!("Cannot find cache directory"))?
         .join("jellysetup");
 
-    println!("[FLASH] Cache dir: {:?}", cache_dir);
+    tracing::info!("Cache dir: {:?}", cache_dir);
 
     fs::create_dir_all(&cache_dir).map_err(|e| {
-        println!("[FLASH] ERROR creating cache dir: {:?}", e);
+        tracing::error!("ERROR creating cache dir: {:?}", e);
         anyhow!("Erreur création cache: {}", e)
     })?;
-    println!("[FLASH] Cache dir created OK");
+    tracing::info!("Cache dir created OK");
 
     // Étape 1: Récupérer la dernière version de Raspberry Pi OS
     // Étapes: Téléchargement (0-25%), Écriture (25-75%), Configuration (75-90%), Éjection (90-100%)
     emit_progress(&window, "download", 0, "Recherche de la dernière version...", None);
-    println!("[FLASH] Getting latest RPI OS URL...");
+    tracing::debug!("Getting latest RPI OS URL...");
 
-    let (download_url, image_name) = get_latest_rpi_os_url().await.map_err(|e| {
-        println!("[FLASH] ERROR getting RPI OS URL: {:?}", e);
-        e
-    })?;
-    println!("[FLASH] URL: {}", download_url);
-    println!("[FLASH] Image name: {}", image_name);
+    // Mode hors ligne: image du bundle importé, rien à télécharger
+    let (download_url, image_path, image_name) = if crate::offline_bundle::active() {
+        let (bundle_image, image_name) = crate::offline_bundle::os_image()?;
+        tracing::info!("Offline mode: using bundled image {:?}", bundle_image);
+        (String::new(), bundle_image, image_name)
+    } else {
+        let (download_url, image_name) = get_latest_rpi_os_url().await.map_err(|e| {
+            tracing::error!("ERROR getting RPI OS URL: {:?}", e);
+            e
+        })?;
+        let image_path = cache_dir.join(format!("{}.xz", &image_name));
+        (download_url, image_path, image_name)
+    };
+    tracing::info!("URL: {}", download_url);
+    tracing::info!("Image name: {}", image_name);
 
-    let image_path = cache_dir.join(format!("{}.xz", &image_name));
     let extracted_path = cache_dir.join(&image_name);
 
-    println!("[FLASH] Image path: {:?}", image_path);
-    println!("[FLASH] Extracted path: {:?}", extracted_path);
-    println!("[FLASH] Image exists: {}", image_path.exists());
-    println!("[FLASH] Extracted exists: {}", extracted_path.exists());
+    tracing::info!("Image path: {:?}", image_path);
+    tracing::info!("Extracted path: {:?}", extracted_path);
+    tracing::info!("Image exists: {}", image_path.exists());
+    tracing::info!("Extracted exists: {}", extracted_path.exists());
 
     // Télécharger l'image si nécessaire
     emit_progress(&window, "download", 5, "Téléchargement en cours...", None);  // 0-20% pour download
 
     if !image_path.exists() {
-        println!("[FLASH] Downloading image...");
+        tracing::debug!("Downloading image...");
         download_image(&window, &download_url, &image_path).await.map_err(|e| {
-            println!("[FLASH] ERROR downloading: {:?}", e);
+            tracing::error!("ERROR downloading: {:?}", e);
             e
         })?;
-        println!("[FLASH] Download complete");
+        tracing::info!("Download complete");
     } else {
-        println!("[FLASH] Image already cached, skipping download");
+        tracing::info!("Image already cached, skipping download");
     }
 
     emit_progress(&window, "download", 20, "Extraction de l'image...", None);  // Fin téléchargement
 
     // Étape 2: Extraire l'image XZ
     if !extracted_path.exists() {
-        println!("[FLASH] Extracting image...");
+        tracing::debug!("Extracting image...");
         extract_xz(&image_path, &extracted_path).await.map_err(|e| {
-            println!("[FLASH] ERROR extracting: {:?}", e);
+            tracing::error!("ERROR extracting: {:?}", e);
             e
         })?;
-        println!("[FLASH] Extraction complete");
+        tracing::info!("Extraction complete");
     } else {
-        println!("[FLASH] Image already extracted, skipping");
+        tracing::info!("Image already extracted, skipping");
     }
 
     // Vérifier que le fichier extrait existe
     if !extracted_path.exists() {
-        println!("[FLASH] ERROR: Extracted image not found at {:?}", extracted_path);
+        tracing::error!("ERROR: Extracted image not found at {:?}", extracted_path);
         return Err(anyhow!("Image extraite introuvable"));
     }
 
     let extracted_size = fs::metadata(&extracted_path).map(|m| m.len()).unwrap_or(0);
-    println!("[FLASH] Extracted image size: {} bytes ({:.2} GB)", extracted_size, extracted_size as f64 / 1_000_000_000.0);
+    tracing::info!("Extracted image size: {} bytes ({:.2} GB)", extracted_size, extracted_size as f64 / 1_000_000_000.0);
 
     // SÉCURITÉ: Vérification finale avant toute opération sur le disque
     emit_progress(&window, "download", 24, "Vérification de sécurité...", None);  // Presque fini téléchargement
-    println!("[FLASH] Security verification...");
+    tracing::debug!("Security verification...");
 
     // Récupérer la taille du disque sélectionné pour vérification
-    let sd_size = get_disk_size(&config.sd_path).await.unwrap_or(0);
-    println!("[FLASH] SD card size: {} bytes ({:.2} GB)", sd_size, sd_size as f64 / 1_000_000_000.0);
+    let sd_size = crate::sd_card::disk_size(&config.sd_path).await.unwrap_or(0);
+    tracing::info!("SD card size: {} bytes ({:.2} GB)", sd_size, sd_size as f64 / 1_000_000_000.0);
 
     crate::sd_card::verify_safe_to_flash(&config.sd_path, sd_size).map_err(|e| {
-        println!("[FLASH] ERROR in verify_safe_to_flash: {:?}", e);
+        tracing::error!("ERROR in verify_safe_to_flash: {:?}", e);
         e
     })?;
-    println!("[FLASH] Security verification OK");
+    tracing::info!("Security verification OK");
+
+    // Fichiers injectés dans la rootfs d'une copie de l'image (le cache reste intact)
+    let mut rootfs_files = config.rootfs_files();
+    if let Some(archive) = crate::offline_bundle::stack_images_archive().filter(|_| crate::offline_bundle::active()) {
+        // Hors ligne: images du bundle (pas de Docker Hub côté ordinateur ni côté Pi)
+        rootfs_files.push(crate::image_cache::rootfs_file(archive));
+    } else if config.preload_images {
+        emit_progress(&window, "download", 23, "Préparation des images Docker du stack...", None);
+        // Même définition que celle qui servira à l'installation (master_config, procédures, embarquée)
+        let images = crate::image_cache::stack_images(&crate::compose::resolve_stack().await);
+        let archive = crate::image_cache::build_archive(&images).await?;
+        rootfs_files.push(crate::image_cache::rootfs_file(archive));
+    }
+    let image_to_write = if rootfs_files.is_empty() {
+        extracted_path.clone()
+    } else {
+        emit_progress(&window, "download", 24, "Personnalisation de l'image...", None);
+        crate::rootfs::prepare_custom_image(&extracted_path, &rootfs_files).await?
+    };
 
     emit_progress(&window, "download", 25, "Démontage de la carte SD...", None);  // Fin téléchargement = 25%
-    println!("[FLASH] Unmounting disk...");
+    tracing::debug!("Unmounting disk...");
 
     // Étape 3: Démonter la carte SD
     crate::sd_card::unmount_disk(&config.sd_path).await.map_err(|e| {
-        println!("[FLASH] ERROR unmounting: {:?}", e);
+        tracing::error!("ERROR unmounting: {:?}", e);
         e
     })?;
-    println!("[FLASH] Unmount complete");
+    tracing::info!("Unmount complete");
 
     emit_progress(&window, "write", 25, "Écriture de l'image...", None);  // Début écriture = 25%
-    println!("[FLASH] ===== STARTING WRITE =====");
-    println!("[FLASH] Source: {:?}", extracted_path);
-    println!("[FLASH] Destination: {}", config.sd_path);
+    tracing::info!("===== STARTING WRITE =====");
+    tracing::info!("Source: {:?}", image_to_write);
+    tracing::info!("Destination: {}", config.sd_path);
 
     // Étape 4: Écrire l'image sur la carte SD (APRÈS vérification de sécurité)
-    write_image_to_sd(&window, &extracted_path, &config.sd_path).await.map_err(|e| {
-        println!("[FLASH] ERROR in write_image_to_sd: {:?}", e);
+    let written = write_image_to_sd(&window, &image_to_write, &config.sd_path).await;
+    if image_to_write != extracted_path {
+        fs::remove_file(&image_to_write).ok();
+    }
+    written.map_err(|e| {
+        tracing::error!("ERROR in write_image_to_sd: {:?}", e);
         e
     })?;
-    println!("[FLASH] Write complete!");
+    tracing::info!("Write complete!");
+
+    // Image écrite: si l'application s'arrête avant la configuration, le prochain lancement la reprend
+    crate::boot_recovery::mark_pending(&config);
+
+    configure_and_eject(&window, &config, &ssh_public_key).await
+}
+
+/// Termine la configuration d'une carte flashée mais jamais configurée (sans re-flasher)
+#[tracing::instrument(skip_all, fields(sd_path = %config.sd_path, hostname = %config.hostname))]
+pub async fn finish_boot_configuration(
+    window: Window,
+    config: FlashConfig,
+    ssh_public_key: String,
+) -> Result<()> {
+    tracing::info!("Finishing boot configuration only on {}", config.sd_path);
+
+    if FLASH_IN_PROGRESS.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+        return Err(anyhow!("Un flash est déjà en cours. Veuillez patienter."));
+    }
+    let _guard = FlashGuard;
 
-    emit_progress(&window, "configure", 75, "Configuration du système...", None);  // Configuration = 75-90%
-    println!("[FLASH] Configuring boot partition...");
+    configure_and_eject(&window, &config, &ssh_public_key).await
+}
+
+/// Étapes 5 et 6 du flash: configuration de la partition boot puis éjection
+async fn configure_and_eject(window: &Window, config: &FlashConfig, ssh_public_key: &str) -> Result<()> {
+    emit_progress(window, "configure", 75, "Configuration du système...", None);  // Configuration = 75-90%
+    tracing::debug!("Configuring boot partition...");
 
     // Étape 5: Configurer le boot (SSH, WiFi, hostname)
-    configure_boot_partition(&config, &ssh_public_key).await.map_err(|e| {
-        println!("[FLASH] ERROR configuring boot: {:?}", e);
+    configure_boot_partition(config, ssh_public_key).await.map_err(|e| {
+        tracing::error!("ERROR configuring boot: {:?}", e);
         e
     })?;
-    println!("[FLASH] Boot configured");
+    crate::boot_recovery::clear_pending();
+    crate::ssh::mark_freshly_flashed(&config.hostname);
+    tracing::info!("Boot configured");
 
-    emit_progress(&window, "eject", 90, "Éjection de la carte...", None);  // Éjection = 90-100%
-    println!("[FLASH] Ejecting disk...");
+    emit_progress(window, "eject", 90, "Éjection de la carte...", None);  // Éjection = 90-100%
+    tracing::debug!("Ejecting disk...");
 
     // Étape 6: Éjecter
     crate::sd_card::eject_disk(&config.sd_path).await.map_err(|e| {
-        println!("[FLASH] ERROR ejecting: {:?}", e);
+        tracing::error!("ERROR ejecting: {:?}", e);
         e
     })?;
-    println!("[FLASH] Eject complete");
+    tracing::info!("Eject complete");
+
+    // Profil réutilisable pour un prochain flash du même foyer (sans mots de passe)
+    if let Err(e) = crate::flash_profiles::remember(config) {
+        tracing::warn!("could not save flash profile: {}", e);
+    }
 
-    emit_progress(&window, "complete", 100, "Carte SD prête !", None);
-    println!("========================================");
-    println!("[FLASH] FLASH COMPLETE SUCCESS!");
-    println!("========================================");
+    emit_progress(window, "complete", 100, "Carte SD prête !", None);
+    tracing::info!("========================================");
+    tracing::info!("FLASH COMPLETE SUCCESS!");
+    tracing::info!("========================================");
 
     Ok(())
 }
 
 /// Télécharge l'image Raspberry Pi OS
-async fn download_image(window: &Window, url: &str, dest: &Path) -> Result<()> {
+pub(crate) async fn download_image(window: &Window, url: &str, dest: &Path) -> Result<()> {
     let client = reqwest::Client::new();
     let response = client.get(url).send().await?;
 
@@ -338,16 +537,24 @@ async fn download_image(window: &Window, url: &str, dest: &Path) -> Result<()> {
     let mut stream = response.bytes_stream();
 
     use futures_util::StreamExt;
-    while let Some(chunk) = stream.next().await {
+    let token = crate::operations::current_token();
+    loop {
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            _ = token.cancelled() => {
+                // Abandon du flux HTTP et du fichier partiel
+                drop(file);
+                let _ = std::fs::remove_file(dest);
+                tracing::info!("Cancelled, partial image removed");
+                return Err(crate::operations::Cancelled.into());
+            }
+        };
+        let Some(chunk) = chunk else { break };
         let chunk = chunk?;
         file.write_all(&chunk)?;
 
         downloaded += chunk.len() as u64;
-        let percent = if total_size > 0 {
-            (downloaded * 30 / total_size) as u32
-        } else {
-            0
-        };
+        let percent = download_percent(downloaded, total_size);
 
         let speed = format!("{:.1} MB/s", downloaded as f64 / 1_000_000.0);
         emit_progress(
@@ -379,7 +586,7 @@ async fn extract_xz(src: &Path, _dest: &Path) -> Result<()> {
         }
 
         let xz_path = xz_cmd.ok_or_else(|| anyhow!("xz not found. Install with: brew install xz"))?;
-        println!("[Extract] Using xz at: {}", xz_path);
+        tracing::info!("Using xz at: {}", xz_path);
 
         let output = Command::new(xz_path)
             .args(["-dk", src.to_str().unwrap()])
@@ -388,7 +595,7 @@ async fn extract_xz(src: &Path, _dest: &Path) -> Result<()> {
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            println!("[Extract] xz stderr: {}", stderr);
+            tracing::info!("xz stderr: {}", stderr);
             return Err(anyhow!("xz extraction failed: {}", stderr));
         }
     }
@@ -421,12 +628,12 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
             .trim_start_matches("/dev/r")
             .trim_start_matches("/dev/");
 
-        println!("[Flash] Writing image to {} (disk: {})", sd_path, disk_id);
-        println!("[Flash] Image: {}", image.display());
+        tracing::info!("Writing image to {} (disk: {})", sd_path, disk_id);
+        tracing::info!("Image: {}", image.display());
 
         // Taille de l'image pour calculer la progression
         let image_size = std::fs::metadata(&image)?.len();
-        println!("[Flash] Image size: {} bytes ({:.1} GB)", image_size, image_size as f64 / 1_000_000_000.0);
+        tracing::info!("Image size: {} bytes ({:.1} GB)", image_size, image_size as f64 / 1_000_000_000.0);
 
         // Utiliser le dossier cache pour le log (évite problèmes de permissions /tmp)
         let cache_dir = dirs::cache_dir()
@@ -435,7 +642,7 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
         let log_path = cache_dir.join("flash.log");
         let log_path_str = log_path.to_str().unwrap_or("/tmp/jellysetup_flash.log");
 
-        println!("[Flash] Log path: {}", log_path_str);
+        tracing::info!("Log path: {}", log_path_str);
 
         // Écrire un log initial
         match std::fs::write(&log_path, format!(
@@ -443,15 +650,15 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
             image.display(),
             sd_path
         )) {
-            Ok(_) => println!("[Flash] Initial log written OK"),
+            Ok(_) => tracing::info!("Initial log written OK"),
             Err(e) => {
-                println!("[Flash] ERROR writing initial log: {:?}", e);
+                tracing::error!("ERROR writing initial log: {:?}", e);
                 // On continue quand même, le log n'est pas critique
             }
         }
 
-        println!("[Flash] Using dd + authopen method...");
-        println!("[Flash] This will show a macOS authorization dialog");
+        tracing::debug!("Using dd + authopen method...");
+        tracing::info!("This will show a macOS authorization dialog");
 
         // Méthode qui fonctionne : dd pipe vers authopen
         // authopen gère l'autorisation et écrit sur le disque brut
@@ -471,21 +678,21 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
             .stderr(std::process::Stdio::piped())
             .spawn()
             .map_err(|e| {
-                println!("[Flash] ERROR spawning dd|authopen: {:?}", e);
+                tracing::error!("ERROR spawning dd|authopen: {:?}", e);
                 anyhow!("Impossible de lancer le flash: {}", e)
             })?;
 
-        println!("[Flash] dd|authopen spawned, PID: {}", child.id());
+        tracing::info!("dd|authopen spawned, PID: {}", child.id());
 
         // Écrire le début du log
         let _ = std::fs::write(&log_path, "=== Flash started ===\n");
 
         // Note: authopen va afficher un dialogue de mot de passe
         // Le processus va bloquer jusqu'à ce que l'utilisateur entre son mdp
-        println!("[Flash] Flash process started, waiting for authorization dialog...");
+        tracing::debug!("Flash process started, waiting for authorization dialog...");
 
         let child_pid = child.id();
-        println!("[Flash] PID: {}", child_pid);
+        tracing::info!("PID: {}", child_pid);
 
         // Monitorer la progression en lisant le log de dd
         let start_time = std::time::Instant::now();
@@ -496,68 +703,69 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
         loop {
             iteration += 1;
             if iteration % 10 == 1 {
-                println!("[Flash] Loop iteration {}, elapsed: {}s", iteration, start_time.elapsed().as_secs());
+                tracing::info!("Loop iteration {}, elapsed: {}s", iteration, start_time.elapsed().as_secs());
             }
 
             // Vérifier si le processus est terminé
             match child.try_wait() {
                 Ok(Some(status)) => {
-                    println!("[Flash] =============================================");
-                    println!("[Flash] Process finished with status: {:?}", status);
-                    println!("[Flash] Exit code: {:?}", status.code());
-                    println!("[Flash] Success: {}", status.success());
+                    tracing::info!("=============================================");
+                    tracing::info!("Process finished with status: {:?}", status);
+                    tracing::info!("Exit code: {:?}", status.code());
+                    tracing::info!("Success: {}", status.success());
 
                     // Lire stdout et stderr de osascript
                     if let Some(mut stdout) = child.stdout.take() {
                         let mut stdout_str = String::new();
                         use std::io::Read;
                         let _ = stdout.read_to_string(&mut stdout_str);
-                        println!("[Flash] Osascript STDOUT: '{}'", stdout_str);
+                        tracing::info!("Osascript STDOUT: '{}'", stdout_str);
                     }
                     if let Some(mut stderr) = child.stderr.take() {
                         let mut stderr_str = String::new();
                         use std::io::Read;
                         let _ = stderr.read_to_string(&mut stderr_str);
-                        println!("[Flash] Osascript STDERR: '{}'", stderr_str);
+                        tracing::info!("Osascript STDERR: '{}'", stderr_str);
                     }
 
                     // Lire le log final
-                    println!("[Flash] Reading log file: {:?}", log_path);
+                    tracing::info!("Reading log file: {:?}", log_path);
                     match std::fs::read_to_string(&log_path) {
                         Ok(log_content) => {
-                            println!("[Flash] Log file content ({} bytes):", log_content.len());
-                            println!("----------------------------------------");
-                            println!("{}", log_content);
-                            println!("----------------------------------------");
+                            tracing::info!("Log file content ({} bytes):", log_content.len());
+                            tracing::info!("----------------------------------------");
+                            tracing::info!("{}", log_content);
+                            tracing::info!("----------------------------------------");
 
                             // Vérifier si dd a réussi (méthode authopen)
                             // Le log contient la sortie stderr de dd: "XXXX bytes transferred"
-                            if log_content.contains("bytes transferred") && status.success() {
-                                println!("[Flash] SUCCESS: dd completed!");
+                            let outcome = dd_log_outcome(&log_content);
+                            if outcome == DdOutcome::Completed && status.success() {
+                                tracing::info!("SUCCESS: dd completed!");
                                 // Sync pour s'assurer que tout est écrit
                                 let _ = std::process::Command::new("sync").output();
                                 break;
-                            } else if log_content.contains("Operation not permitted") || log_content.contains("Permission denied") {
-                                println!("[Flash] FAILED: Permission denied in log");
+                            } else if outcome == DdOutcome::PermissionDenied {
+                                tracing::error!("FAILED: Permission denied in log");
                                 return Err(anyhow!(
                                     "macOS bloque l'écriture sur le disque.\n\n\
                                     Va dans Réglages Système > Confidentialité > Accès complet au disque\n\
                                     Ajoute JellySetup, puis quitte et relance l'app."
                                 ));
                             } else if !status.success() {
-                                println!("[Flash] FAILED: dd/authopen exit code non-zero");
+                                tracing::error!("FAILED: dd/authopen exit code non-zero");
                                 return Err(anyhow!(
                                     "Erreur lors du flash. Log:\n{}", log_content
                                 ));
                             }
                         }
                         Err(e) => {
-                            println!("[Flash] ERROR reading log file: {:?}", e);
+                            tracing::error!("ERROR reading log file: {:?}", e);
                         }
                     }
 
                     if !status.success() {
-                        println!("[Flash] FAILED: Flash process returned non-success status");
+                        tracing::error!("FAILED: Flash process returned non-success status");
                         return Err(anyhow!(
                             "Le flash a échoué (code: {:?}). L'utilisateur a peut-être annulé le dialogue de mot de passe.",
                             status.code()
@@ -587,31 +795,14 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
                     }
 
                     // Attendre un peu que dd écrive dans le log
-                    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                    tokio::time::sleep(std::time::Duration::from_millis(crate::config::delays::DD_LOG_POLL_MS)).await;
 
                     // Lire les dernières lignes du log dd
                     // Format SIGINFO: "2841640960 bytes transferred in 997.746971 secs (2848058 bytes/sec)"
-                    if let Ok(log_content) = std::fs::read_to_string(&log_path) {
-                        // Chercher la dernière ligne avec des bytes
-                        for line in log_content.lines().rev() {
-                            if line.contains("bytes") && line.contains("transferred") {
-                                // Parser: "2841640960 bytes transferred..."
-                                if let Some(bytes_str) = line.split_whitespace().next() {
-                                    if let Ok(bytes) = bytes_str.parse::<u64>() {
-                                        total_written = bytes;
-                                    }
-                                }
-                                // Parser vitesse: "... (2848058 bytes/sec)"
-                                if let Some(start) = line.rfind('(') {
-                                    if let Some(end) = line.rfind(" bytes/sec)") {
-                                        let speed_str = &line[start+1..end];
-                                        if let Ok(bytes_per_sec) = speed_str.parse::<f64>() {
-                                            current_speed = bytes_per_sec / 1_000_000.0; // Convertir en MB/s
-                                        }
-                                    }
-                                }
-                                break;
-                            }
+                    if let Some(progress) = std::fs::read_to_string(&log_path).ok().and_then(|log| parse_dd_progress(&log)) {
+                        total_written = progress.bytes;
+                        if let Some(bytes_per_sec) = progress.bytes_per_sec {
+                            current_speed = bytes_per_sec / 1_000_000.0; // Convertir en MB/s
                         }
                     }
 
@@ -620,40 +811,23 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
                         total_written = elapsed * (current_speed as u64 * 1_000_000);
                     }
 
-                    // Calculer le pourcentage RÉEL (pas de plafond artificiel)
-                    let percent = ((total_written as f64 / image_size as f64) * 100.0).min(99.0) as u32;
-
-                    // Calculer le temps restant estimé
-                    let remaining_bytes = image_size.saturating_sub(total_written);
-                    let remaining_secs = if current_speed > 0.1 {
-                        (remaining_bytes as f64 / (current_speed * 1_000_000.0)) as u64
-                    } else {
-                        0
-                    };
-                    let remaining_min = remaining_secs / 60;
-                    let remaining_sec = remaining_secs % 60;
+                    let percent = write_percent(total_written, image_size);
+                    let remaining_secs = remaining_write_secs(total_written, image_size, current_speed);
 
                     // Émettre la progression
                     if percent > last_percent || elapsed % 3 == 0 {
                         last_percent = percent;
-                        // Calculer progression totale: écriture = 25% à 75% (50% de la barre)
-                        let total_percent = 25 + (percent * 50 / 100);
-                        let time_str = if remaining_min > 0 {
-                            format!("~{}min{}s restant", remaining_min, remaining_sec)
-                        } else if remaining_secs > 0 {
-                            format!("~{}s restant", remaining_secs)
-                        } else {
-                            "finalisation...".to_string()
-                        };
+                        let total_percent = overall_write_percent(percent);
+                        let time_str = format_remaining(remaining_secs);
                         let speed_display = format!("{:.1} MB/s", current_speed);
                         emit_progress(_window, "write", total_percent,
                             &format!("Écriture: {}% - {}", percent, time_str), Some(&speed_display));
 
-                        println!("[Flash] Progress: {}% - Speed: {:.1} MB/s - Written: {:.1} GB",
+                        tracing::info!("Progress: {}% - Speed: {:.1} MB/s - Written: {:.1} GB",
                             percent, current_speed, total_written as f64 / 1_000_000_000.0);
                     }
 
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                    tokio::time::sleep(std::time::Duration::from_secs(crate::config::delays::FLASH_PROGRESS_SECS)).await;
                 }
                 Err(e) => {
                     return Err(anyhow!("Erreur lors du monitoring: {}", e));
@@ -665,7 +839,7 @@ async fn write_image_to_sd(_window: &Window, image: &Path, sd_path: &str) -> Res
         emit_progress(_window, "write", 74, "Synchronisation...", None);  // Fin écriture = ~75%
         let _ = Command::new("sync").output().await;
 
-        println!("[Flash] Write completed successfully!");
+        tracing::info!("Write completed successfully!");
     }
 
     #[cfg(target_os = "linux")]
@@ -716,7 +890,7 @@ async fn configure_boot_partition(config: &FlashConfig, ssh_public_key: &str) ->
             .trim_start_matches("/dev/r")
             .trim_start_matches("/dev/");
 
-        println!("[Config] Forcing partition table reload for: {}", disk_id);
+        tracing::info!("Forcing partition table reload for: {}", disk_id);
 
         // Méthode: utiliser diskutil repairDisk pour forcer la relecture de la table de partition
         // Cela nécessite des privilèges admin
@@ -725,40 +899,40 @@ async fn configure_boot_partition(config: &FlashConfig, ssh_public_key: &str) ->
             disk_id, disk_id
         );
 
-        println!("[Config] Running remount with admin privileges...");
+        tracing::debug!("Running remount with admin privileges...");
         let output = Command::new("osascript")
             .args(["-e", &script])
             .output()
             .await?;
 
-        println!("[Config] Remount stdout: {}", String::from_utf8_lossy(&output.stdout));
+        tracing::info!("Remount stdout: {}", String::from_utf8_lossy(&output.stdout));
         if !output.status.success() {
-            println!("[Config] Remount stderr: {}", String::from_utf8_lossy(&output.stderr));
+            tracing::info!("Remount stderr: {}", String::from_utf8_lossy(&output.stderr));
         }
 
         // Attendre que les partitions apparaissent
-        println!("[Config] Waiting for partitions to appear...");
+        tracing::debug!("Waiting for partitions to appear...");
         for i in 0..10 {
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            tokio::time::sleep(std::time::Duration::from_secs(crate::config::delays::PARTITION_POLL_SECS)).await;
 
             if Path::new("/Volumes/bootfs").exists() {
-                println!("[Config] bootfs found after {}s", i + 1);
+                tracing::info!("bootfs found after {}s", i + 1);
                 break;
             }
-            println!("[Config] Waiting... ({}s)", i + 1);
+            tracing::debug!("Waiting... ({}s)", i + 1);
         }
     }
 
-    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    tokio::time::sleep(std::time::Duration::from_secs(crate::config::delays::MOUNT_SETTLE_SECS)).await;
 
     // Trouver la partition boot montée
     #[cfg(target_os = "macos")]
     let boot_path = {
         // Lister les volumes disponibles pour debug
         if let Ok(entries) = std::fs::read_dir("/Volumes") {
-            println!("[Config] Available volumes:");
+            tracing::info!("Available volumes:");
             for entry in entries.flatten() {
-                println!("[Config]   - {}", entry.path().display());
+                tracing::info!("  - {}", entry.path().display());
             }
         }
 
@@ -770,7 +944,7 @@ async fn configure_boot_partition(config: &FlashConfig, ssh_public_key: &str) ->
             let path_str = format!("/Volumes/{}", name);
             let path = Path::new(&path_str);
             if path.exists() {
-                println!("[Config] Found boot partition at: {}", path_str);
+                tracing::info!("Found boot partition at: {}", path_str);
                 found_path = Some(Box::leak(path_str.into_boxed_str()) as &str).map(Path::new);
                 break;
             }
@@ -794,295 +968,168 @@ async fn configure_boot_partition(config: &FlashConfig, ssh_public_key: &str) ->
 
     // 1. Activer SSH (créer fichier vide - backup pour compatibilité)
     fs::write(boot_path.join("ssh"), "")?;
-    println!("[Config] Created ssh file");
+    tracing::info!("Created ssh file");
 
     // 2. Créer custom.toml (méthode Bookworm 2024+)
     // Ce fichier est lu par raspberrypi-sys-mods au premier boot
-    let custom_toml = format!(
-        r#"# Configuration JellySetup - Raspberry Pi OS Bookworm
-config_version = 1
+    fs::write(boot_path.join("custom.toml"), custom_toml(config, ssh_public_key)?)?;
+    tracing::info!("Created custom.toml with hostname={}, user={}", config.hostname, config.system_username);
+
+    // 3. userconf.txt en backup pour les versions qui ignorent custom.toml.
+    // Inutile (une copie du hash en moins) si l'image passe par le firstboot de raspberrypi-sys-mods
+    if !config.keep_userconf && crate::boot_recovery::supports_custom_toml(boot_path) {
+        let _ = fs::remove_file(boot_path.join("userconf.txt"));
+        tracing::info!("custom.toml supported, no userconf.txt");
+    } else {
+        fs::write(boot_path.join("userconf.txt"), userconf(config)?)?;
+        tracing::info!("Created userconf.txt backup");
+    }
 
-[system]
-hostname = "{hostname}"
+    Ok(())
+}
 
-[user]
-name = "{username}"
-password = "{password}"
-password_encrypted = false
+/// Génère le contenu du docker-compose.yml depuis la définition du stack
+/// (token Cloudflare, profils, Homepage et services désactivés lus dans `config`)
+fn generate_docker_compose(
+    stack: &crate::compose::StackDefinition,
+    hostname: &str,
+    config: &InstallConfig,
+    media_paths: &MediaPaths,
+    env: &crate::compose::ContainerEnv,
+) -> String {
+    let mut builder = crate::compose::ComposeBuilder::new(hostname)
+        .env(env)
+        .without(&config.disabled_services())
+        .var("SUPABASE_URL", &crate::supabase::get_supabase_url_public())
+        .var("SUPABASE_ANON_KEY", &crate::supabase::get_supabase_anon_key())
+        // Token limité au schéma du Pi: Supabazarr n'a jamais la clé service
+        .var("SUPABASE_PI_TOKEN", crate::supabase::pi_token(hostname).as_ref().map(|t| t.expose()).unwrap_or_default())
+        .var("CLOUDFLARE_TOKEN", crate::secret::expose_opt(&config.cloudflare_token).unwrap_or_default())
+        .profiles(&config.profiles())
+        // Racine des médias hors de /mnt: volume supplémentaire pour Jellyfin et les *arr
+        .media_volume(media_paths.compose_volume());
+
+    // Tableau de bord Homepage (optionnel)
+    if config.homepage {
+        builder = builder.fragment(crate::homepage::compose_service(&env.timezone));
+    }
+
+    // Page de statut agrégée (http://<pi>/status)
+    builder.fragment(crate::status::compose_service()).build(stack)
+}
 
-[ssh]
-enabled = true
-password_authentication = true
-authorized_keys = [ "{ssh_key}" ]
+/// TZ/PUID/PGID des conteneurs: valeurs de la config, complétées par celles du Pi
+async fn resolve_container_env(target: crate::ssh::SshTarget<'_>, config: &InstallConfig) -> crate::compose::ContainerEnv {
+    let detected = match target.exec(crate::compose::ContainerEnv::DETECT_COMMAND).await {
+        Ok(output) => output,
+        Err(e) => {
+            tracing::warn!("⚠️ Could not detect uid/gid/timezone on the Pi: {}", e);
+            String::new()
+        }
+    };
+    let env = crate::compose::ContainerEnv::resolve(config.timezone.as_deref(), config.puid, config.pgid, &detected);
+    tracing::info!("Containers: TZ={} PUID={} PGID={}", env.timezone, env.puid, env.pgid);
+    env
+}
 
-[wlan]
-ssid = "{wifi_ssid}"
-password = "{wifi_password}"
-password_encrypted = false
-hidden = false
-country = "{wifi_country}"
+/// Attend la fin d'un redémarrage du Pi
+///
+/// 1. Attend que le port SSH se ferme (le reboot a réellement commencé)
+/// 2. Sonde le Pi avec un backoff exponentiel jusqu'à son retour ou jusqu'à `deadline`
+///
+/// Le temps écoulé est remonté au frontend via les événements de progression.
+async fn wait_for_reboot<F, Fut>(
+    window: &Window,
+    host: &str,
+    percent: u32,
+    deadline: Duration,
+    mut probe: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    use crate::ssh;
 
-[locale]
-keymap = "{keymap}"
-timezone = "{timezone}"
-"#,
-        hostname = config.hostname,
-        username = config.system_username,
-        password = config.system_password,
-        ssh_key = ssh_public_key,
-        wifi_ssid = config.wifi_ssid,
-        wifi_password = config.wifi_password,
-        wifi_country = config.wifi_country,
-        keymap = config.keymap,
-        timezone = config.timezone,
-    );
-    fs::write(boot_path.join("custom.toml"), custom_toml)?;
-    println!("[Config] Created custom.toml with hostname={}, user={}", config.hostname, config.system_username);
+    let started = Instant::now();
+    let port_timeout = Duration::from_secs(3);
 
-    // 3. Créer aussi userconf.txt en backup (pour anciennes versions)
-    // Format: username:password (non chiffré pour simplifier, custom.toml prendra le relais)
-    let userconf = format!("{}:{}", config.system_username, config.system_password);
-    fs::write(boot_path.join("userconf.txt"), userconf)?;
-    println!("[Config] Created userconf.txt backup");
+    // Phase 1: attendre l'arrêt (max 90s, certains Pi coupent le réseau très tard)
+    let mut went_down = false;
+    while started.elapsed() < Duration::from_secs(90) {
+        if !ssh::is_ssh_port_open(host, port_timeout).await {
+            went_down = true;
+            break;
+        }
+        emit_progress(window, "reboot", percent,
+            &format!("Arrêt du Pi... ({}s)", started.elapsed().as_secs()), None);
+        crate::operations::sleep(Duration::from_secs(crate::config::delays::REBOOT_POLL_SECS)).await?;
+    }
 
-    Ok(())
+    if went_down {
+        tracing::info!("{} went down after {}s", host, started.elapsed().as_secs());
+    } else {
+        tracing::warn!("⚠️ SSH port on {} never closed, checking availability anyway", host);
+    }
+
+    // Phase 2: attendre le retour avec backoff exponentiel
+    let mut delay = Duration::from_secs(crate::config::delays::REBOOT_POLL_SECS);
+    loop {
+        let elapsed = started.elapsed();
+        if elapsed >= deadline {
+            return Err(anyhow!("Pi not responding after reboot ({}s)", elapsed.as_secs()));
+        }
+
+        emit_progress(window, "reboot", percent,
+            &format!("Redémarrage en cours... ({}s)", elapsed.as_secs()), None);
+
+        if ssh::is_ssh_port_open(host, port_timeout).await && probe().await {
+            tracing::info!("✅ {} back online after {}s", host, elapsed.as_secs());
+            return Ok(());
+        }
+
+        crate::operations::sleep(delay.min(deadline.saturating_sub(started.elapsed()))).await?;
+        delay = (delay * 2).min(Duration::from_secs(20));
+    }
 }
 
-/// Génère le contenu du docker-compose.yml avec tous les services
-fn generate_docker_compose(hostname: &str, cloudflare_token: Option<&str>) -> String {
-    let supabase_url = crate::supabase::get_supabase_url_public();
-    let supabase_service_key = crate::supabase::get_supabase_service_key();
-
-    let mut compose = format!(r#"---
-# =============================================================================
-# Docker Compose - Media Stack
-# Généré par JellySetup
-# Pi: {hostname}
-# =============================================================================
-
-services:
-  # Decypharr - Gestionnaire AllDebrid + montage WebDAV/Rclone
-  decypharr:
-    image: cy01/blackhole:latest
-    container_name: decypharr
-    restart: always
-    cap_add:
-      - SYS_ADMIN
-    security_opt:
-      - apparmor:unconfined
-    dns:
-      - 1.1.1.1
-      - 8.8.8.8
-    ports:
-      - 8282:8282
-    volumes:
-      - /mnt:/mnt:rshared
-      - /mnt/decypharr/qbit:/mnt/decypharr/qbit
-      - ./decypharr:/app
-    environment:
-      - TZ=Europe/Paris
-      - PUID=1000
-      - PGID=1000
-    devices:
-      - /dev/fuse:/dev/fuse:rwm
-
-  # Jellyfin - Serveur multimédia principal
-  jellyfin:
-    image: lscr.io/linuxserver/jellyfin:latest
-    container_name: jellyfin
-    restart: unless-stopped
-    ports:
-      - 8096:8096
-    environment:
-      - TZ=Europe/Paris
-      - PUID=1000
-      - PGID=1000
-      - JELLYFIN_FFmpeg__probesize=1G
-      - JELLYFIN_FFmpeg__analyzeduration=200M
-    volumes:
-      - ./jellyfin:/config
-      - /mnt:/mnt:rshared
-    devices:
-      - /dev/dri:/dev/dri
-    deploy:
-      resources:
-        limits:
-          memory: 4G
-        reservations:
-          memory: 1G
-    healthcheck:
-      test: ["CMD", "curl", "-f", "http://localhost:8096/health"]
-      interval: 30s
-      timeout: 10s
-      retries: 3
-      start_period: 30s
-
-  # Radarr - Gestionnaire de films
-  radarr:
-    image: lscr.io/linuxserver/radarr:latest
-    container_name: radarr
-    restart: unless-stopped
-    ports:
-      - 7878:7878
-    volumes:
-      - ./radarr:/config
-      - /mnt:/mnt:rslave
-    environment:
-      - TZ=Europe/Paris
-      - PUID=1000
-      - PGID=1000
-    deploy:
-      resources:
-        limits:
-          memory: 512M
-
-  # Sonarr - Gestionnaire de séries
-  sonarr:
-    image: lscr.io/linuxserver/sonarr:latest
-    container_name: sonarr
-    restart: unless-stopped
-    ports:
-      - 8989:8989
-    volumes:
-      - ./sonarr:/config
-      - /mnt:/mnt:rslave
-    environment:
-      - TZ=Europe/Paris
-      - PUID=1000
-      - PGID=1000
-    deploy:
-      resources:
-        limits:
-          memory: 512M
-
-  # Prowlarr - Gestionnaire d'indexeurs
-  prowlarr:
-    image: lscr.io/linuxserver/prowlarr:latest
-    container_name: prowlarr
-    restart: unless-stopped
-    ports:
-      - 9696:9696
-    volumes:
-      - ./prowlarr:/config
-    environment:
-      - TZ=Europe/Paris
-      - PUID=1000
-      - PGID=1000
-    deploy:
-      resources:
-        limits:
-          memory: 384M
-
-  # Jellyseerr - Interface de requêtes
-  jellyseerr:
-    image: fallenbagel/jellyseerr:latest
-    container_name: jellyseerr
-    restart: unless-stopped
-    ports:
-      - 5056:5055
-    volumes:
-      - ./jellyseerr:/app/config
-    environment:
-      - TZ=Europe/Paris
-    depends_on:
-      - jellyfin
-    extra_hosts:
-      - "host.docker.internal:host-gateway"
-
-  # Bazarr - Gestionnaire de sous-titres
-  bazarr:
-    image: lscr.io/linuxserver/bazarr:latest
-    container_name: bazarr
-    restart: unless-stopped
-    ports:
-      - 6767:6767
-    environment:
-      - TZ=Europe/Paris
-      - PUID=1000
-      - PGID=1000
-    volumes:
-      - ./bazarr:/config
-      - /mnt:/mnt:rslave
-
-  # FlareSolverr - Bypass Cloudflare pour les indexeurs
-  flaresolverr:
-    image: ghcr.io/flaresolverr/flaresolverr:latest
-    container_name: flaresolverr
-    restart: unless-stopped
-    ports:
-      - 8191:8191
-    environment:
-      - TZ=Europe/Paris
-      - LOG_LEVEL=info
-
-  # Supabazarr - Sauvegarde automatique vers Supabase
-  # Interface web: http://<pi-ip>:8383
-  supabazarr:
-    image: ghcr.io/nicolascleton/supabazarr:latest
-    container_name: supabazarr
-    restart: unless-stopped
-    ports:
-      - 8383:8383
-    environment:
-      - TZ=Europe/Paris
-      - PUID=1000
-      - PGID=1000
-      - SUPABASE_URL={supabase_url}
-      - SUPABASE_SERVICE_KEY={supabase_service_key}
-      - HOSTNAME={hostname}
-      - MEDIA_STACK_PATH=/media-stack
-      - BACKUP_HOUR=03:00
-    volumes:
-      - ./:/media-stack:ro
-      - supabazarr_data:/etc/supabazarr
-    deploy:
-      resources:
-        limits:
-          memory: 128M
-          cpus: '0.25'
-    logging:
-      driver: "json-file"
-      options:
-        max-size: "10m"
-        max-file: "3"
-    healthcheck:
-      test: ["CMD", "python", "-c", "import urllib.request; urllib.request.urlopen('http://localhost:8383/health')"]
-      interval: 30s
-      timeout: 10s
-      retries: 3
-      start_period: 10s
-"#);
-
-    // Ajouter Cloudflared si token fourni
-    if let Some(token) = cloudflare_token {
-        if !token.is_empty() {
-            compose.push_str(&format!(r#"
-  # Cloudflared - Tunnel Cloudflare pour accès distant
-  cloudflared:
-    image: cloudflare/cloudflared:latest
-    container_name: cloudflared
-    restart: unless-stopped
-    command: tunnel --no-autoupdate --protocol http2 run
-    environment:
-      - TUNNEL_TOKEN={token}
-"#));
-        }
-    }
-
-    // Ajouter les volumes et networks
-    compose.push_str(r#"
-volumes:
-  supabazarr_data:
-
-networks:
-  default:
-    name: media-network
-"#);
-
-    compose
+/// Options SSH des sondes post-reboot: une seule tentative courte, le backoff est géré par wait_for_reboot
+fn reboot_probe_options() -> crate::ssh::SshOptions {
+    crate::ssh::SshOptions {
+        connect_timeout_secs: 10,
+        retries: 1,
+        ..crate::ssh::default_options()
+    }
+}
+
+/// wait_for_reboot pour l'authentification par mot de passe (session persistante recréée au retour)
+async fn wait_for_reboot_password(
+    window: &Window,
+    host: &str,
+    username: &str,
+    password: &str,
+    percent: u32,
+    deadline: Duration,
+) -> Result<()> {
+    use crate::ssh;
+
+    // La session persistante ne survit pas au reboot
+    ssh::close_persistent_session().await;
+
+    let probe_options = reboot_probe_options();
+    let probe_options = &probe_options;
+    wait_for_reboot(window, host, percent, deadline, move || async move {
+        ssh::test_connection_password_with_options(host, username, password, probe_options).await.unwrap_or(false)
+    }).await?;
+
+    if let Err(e) = ssh::init_persistent_session(host, username, password).await {
+        tracing::warn!("could not re-init persistent SSH session: {}", e);
+    }
+    Ok(())
 }
 
 /// Exécute l'installation complète sur le Pi via SSH
+#[tracing::instrument(name = "install", skip_all, fields(host = %host, pi = %hostname))]
 pub async fn run_full_installation(
     window: Window,
     host: &str,
@@ -1091,376 +1138,472 @@ pub async fn run_full_installation(
     config: InstallConfig,
     hostname: &str,
 ) -> Result<()> {
-    use crate::ssh;
+    crate::preflight::guard_host(crate::preflight::HostOperation::Install, &config.host_overrides).await?;
+    let mut state = InstallState::start(host, false)?;
+    state.set_pi_name(hostname);
+    // Carte flashée par l'application: l'empreinte épinglée (TOFU/Supabase) n'est plus valable
+    if let Err(e) = crate::ssh::forget_if_freshly_flashed(hostname, host) {
+        tracing::warn!("could not forget pinned fingerprint: {}", e);
+    }
+    let notifier = crate::notifications::DiscordNotifier::from_webhook(crate::secret::expose_opt(&config.discord_webhook));
+    if let Some(notifier) = &notifier {
+        notifier.install_started(hostname, host).await;
+    }
+    state.set_notifier(notifier.clone());
+    state.set_resume_point(crate::remote_resume::ResumePublisher::new(host, username, None, Some(private_key), &config));
+    let result = run_full_installation_steps(window, host, username, private_key, config, hostname, &mut state).await;
+    state.finish(&result).await;
+    if let Some(notifier) = &notifier {
+        notifier.install_finished(&crate::ssh::SshTarget::Key { host, username, private_key }, &state, &result).await;
+    }
+    result
+}
 
-    // Générer le docker-compose.yml avec tous les services
-    let docker_compose = generate_docker_compose(
-        hostname,
-        config.cloudflare_token.as_deref()
-    );
+/// Reprend une installation interrompue (authentification par clé) à partir de l'étape en échec
+#[tracing::instrument(name = "resume_install", skip_all, fields(host = %host, pi = %hostname))]
+pub async fn resume_installation(
+    window: Window,
+    host: &str,
+    username: &str,
+    private_key: &str,
+    config: InstallConfig,
+    hostname: &str,
+) -> Result<()> {
+    crate::preflight::guard_host(crate::preflight::HostOperation::Install, &config.host_overrides).await?;
+    let mut state = InstallState::start(host, true)?;
+    state.set_pi_name(hostname);
+    let notifier = crate::notifications::DiscordNotifier::from_webhook(crate::secret::expose_opt(&config.discord_webhook));
+    if let Some(notifier) = &notifier {
+        notifier.install_started(hostname, host).await;
+    }
+    state.set_notifier(notifier.clone());
+    state.set_resume_point(crate::remote_resume::ResumePublisher::new(host, username, None, Some(private_key), &config));
+    let result = run_full_installation_steps(window, host, username, private_key, config, hostname, &mut state).await;
+    state.finish(&result).await;
+    if let Some(notifier) = &notifier {
+        notifier.install_finished(&crate::ssh::SshTarget::Key { host, username, private_key }, &state, &result).await;
+    }
+    result
+}
 
-    // Étape 1: Mise à jour système
-    emit_progress(&window, "update", 0, "Mise à jour système...", None);
-    ssh::execute_command(host, username, private_key,
-        "sudo DEBIAN_FRONTEND=noninteractive apt update && sudo DEBIAN_FRONTEND=noninteractive apt upgrade -y -o Dpkg::Options::='--force-confdef' -o Dpkg::Options::='--force-confold' && sudo apt install -y git curl"
-    ).await?;
-
-    // Étape 2: Installation Docker
-    emit_progress(&window, "docker", 15, "Installation Docker...", None);
-    ssh::execute_command(host, username, private_key,
-        "curl -fsSL https://get.docker.com | sh && sudo usermod -aG docker $USER"
-    ).await?;
-
-    // Étape 3: Redémarrage pour appliquer groupe docker
-    emit_progress(&window, "reboot", 30, "Redémarrage...", None);
-    ssh::execute_command(host, username, private_key, "sudo reboot").await.ok();
-    tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-
-    // Attendre que le Pi soit de nouveau accessible
-    for i in 0..30 {
-        if ssh::execute_command(host, username, private_key, "echo ok").await.is_ok() {
-            break;
-        }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-        if i == 29 {
-            return Err(anyhow!("Pi not responding after reboot"));
+async fn run_full_installation_steps(
+    window: Window,
+    host: &str,
+    username: &str,
+    private_key: &str,
+    mut config: InstallConfig,
+    hostname: &str,
+    state: &mut InstallState,
+) -> Result<()> {
+    use crate::ssh;
+
+    config.register_secrets();
+
+    // Bastion éventuel (Pi distant joignable uniquement via une machine intermédiaire)
+    ssh::set_jump_host(host, config.jump_host.clone());
+
+    // Arborescence des médias, partagée par compose, *arr, Jellyseerr et Jellyfin
+    // (celle déjà en place sur le Pi est conservée, y compris l'ancienne /mnt/media)
+    let paths_target = ssh::SshTarget::Key { host, username, private_key };
+    config.media_paths =
+        Some(crate::master_config::resolve_install_media_paths(&paths_target, config.media_paths.as_ref()).await);
+    let media_paths = config.media_paths();
+
+    // Fuseau horaire et PUID/PGID des conteneurs (config, sinon ceux du Pi)
+    let container_env = resolve_container_env(ssh::SshTarget::Key { host, username, private_key }, &config).await;
+    config.timezone = Some(container_env.timezone.clone());
+    config.puid = Some(container_env.puid);
+    config.pgid = Some(container_env.pgid);
+
+    // Tunnel Cloudflare provisionné via l'API (remplace le token collé à la main)
+    if let Some(setup) = config.cloudflare.clone() {
+        emit_progress(&window, "update", 0, "Création du tunnel Cloudflare...", None);
+        match crate::cloudflare::provision(&setup, hostname, config.service_enabled("jellyseerr")).await {
+            Ok(tunnel) => {
+                crate::cloudflare::write_config(&ssh::SshTarget::Key { host, username, private_key }, &tunnel).await?;
+                config.cloudflare_token = Some(tunnel.token.into());
+            }
+            Err(e) => tracing::error!("❌ Tunnel provisioning failed: {}", e),
         }
     }
 
-    // Étape 4: Création de la structure
-    emit_progress(&window, "structure", 40, "Création structure...", None);
-    ssh::execute_command(host, username, private_key,
-        "mkdir -p ~/media-stack/{decypharr,jellyfin,radarr,sonarr,prowlarr,jellyseerr,bazarr,logs} && \
-         sudo mkdir -p /mnt/decypharr /mnt/media && \
-         sudo chown $USER:$USER /mnt/decypharr /mnt/media && \
-         ln -sf /mnt/decypharr/qbit/radarr /mnt/media/movies && \
-         ln -sf /mnt/decypharr/qbit/tv-sonarr /mnt/media/series"
-    ).await?;
+    // Schéma Supabase initialisé avant le compose: Supabazarr reçoit le token du Pi
+    if let Err(e) = crate::supabase::ensure_schema_initialized(hostname).await {
+        tracing::warn!("⚠️ Schema init failed, Supabazarr without Pi token: {}", e);
+    }
 
-    // Étape 5: Écrire le docker-compose.yml
-    emit_progress(&window, "compose_write", 50, "Génération docker-compose.yml...", None);
-    let escaped_compose = docker_compose.replace("'", "'\\''");
-    let write_cmd = format!("cat > ~/media-stack/docker-compose.yml << 'EOFCOMPOSE'\n{}\nEOFCOMPOSE", docker_compose);
-    ssh::execute_command(host, username, private_key, &write_cmd).await?;
+    // Générer le docker-compose.yml avec tous les services
+    let stack = crate::compose::resolve_stack().await;
+    let docker_compose = generate_docker_compose(&stack, hostname, &config, &media_paths, &container_env);
+    // Services personnalisés de l'utilisateur (extra-compose.yml), validés et namespacés
+    let docker_compose = crate::extra_compose::merge_into(docker_compose, config.extra_compose.as_deref())?;
+    crate::workspace::write("docker-compose.yml", &docker_compose);
 
-    // Étape 6: Démarrer les services
-    emit_progress(&window, "compose_up", 60, "Démarrage des services Docker...", None);
-    ssh::execute_command(host, username, private_key,
-        "cd ~/media-stack && docker compose pull && docker compose up -d"
-    ).await?;
+    let hook_target = ssh::SshTarget::Key { host, username, private_key };
 
-    // Étape 7: Attendre que les services soient prêts
-    emit_progress(&window, "wait_services", 75, "Attente des services...", None);
-    tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+    // Journal d'installation (Pi, Supabase, panneau de logs du wizard)
+    use crate::logging::{InstallationLogger, LogLevel};
 
-    // Étape 8: Configuration des services via API
-    emit_progress(&window, "config", 85, "Configuration des services...", None);
-
-    // 8.1: Attendre que Jellyfin soit prêt (max 2 min)
-    emit_progress(&window, "config", 86, "Attente de Jellyfin...", None);
-    let mut jellyfin_ready = false;
-    for i in 0..24 {
-        let check = ssh::execute_command(host, username, private_key,
-            "curl -s -o /dev/null -w '%{http_code}' http://localhost:8096/health 2>/dev/null || echo 000"
-        ).await.unwrap_or_default();
-        if check.trim() == "200" {
-            jellyfin_ready = true;
-            println!("[Config] Jellyfin is ready");
-            break;
-        }
-        println!("[Config] Waiting for Jellyfin ({}/24)...", i + 1);
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    let logger = InstallationLogger::new_with_key(hostname, host, host, username, private_key, env!("CARGO_PKG_VERSION"))
+        .with_window(window.clone());
+    if let Err(e) = logger.initialize().await {
+        tracing::warn!("⚠️ Logger init failed: {}", e);
     }
+    logger.log_with_details(
+        LogLevel::Info,
+        "installation_start",
+        "Installation démarrée",
+        serde_json::json!({
+            "hostname": hostname,
+            "host": host,
+            "username": username,
+            "alldebrid_configured": !config.alldebrid_api_key.is_empty(),
+            "cloudflare_configured": config.cloudflare_token.is_some(),
+            "ygg_configured": config.ygg_passkey.is_some(),
+        })
+    ).await;
 
-    if jellyfin_ready {
-        // 8.2: Configurer Jellyfin via l'API Startup (compatible Jellyfin 10.11.x)
-        emit_progress(&window, "config", 87, "Configuration Jellyfin...", None);
+    // Procédure publiée (procedures/v2/steps.json): étapes exécutées entre les phases ci-dessous
+    let resolved_procedure = crate::procedure::Procedure::resolve_for(host, !state.completed.is_empty()).await;
+    // Hooks personnalisés (procédure et master_config), exécutés autour des étapes
+    let hooks = crate::hooks::step_hooks(&resolved_procedure).await;
+    let mut procedure_vars = base_template_vars(host, hostname, &config, &media_paths, &crate::services::ArrApiKeys::default());
+    procedure_vars.set("SUDO", "sudo");
+    let mut procedure = crate::procedure::ProcedureRunner::new(
+        resolved_procedure,
+        hook_target,
+        host,
+        procedure_vars,
+        Some(&logger),
+        !state.completed.is_empty(),
+    )?;
+    procedure.run_until(InstallStep::SystemUpdate).await?;
 
-        let jf_user = config.jellyfin_username.replace("\\", "\\\\").replace("\"", "\\\"");
-        let jf_pass = config.jellyfin_password.replace("\\", "\\\\").replace("\"", "\\\"");
+    // Langue du système (LANG, LC_ALL, police console), non bloquant
+    if state.should_run(InstallStep::SystemUpdate) {
+        if let Err(e) = crate::system_locale::apply(&hook_target, &config.system_locale(), None).await {
+            tracing::warn!("⚠️ Could not set system locale: {}", e);
+        }
+    }
 
-        // Étape 1: Initialiser l'utilisateur (GET /Startup/FirstUser créé l'utilisateur par défaut)
-        // En Jellyfin 10.11.x, il faut GET FirstUser avant de pouvoir POST User
-        let first_user_cmd = "curl -s 'http://localhost:8096/Startup/FirstUser'";
-        let first_user_result = ssh::execute_command(host, username, private_key, first_user_cmd).await.unwrap_or_default();
-        println!("[Config] Jellyfin FirstUser: {}", first_user_result);
+    // apt upgrade et docker compose pull dépassent largement le délai par défaut des commandes
+    let long_step_options = ssh::SshOptions {
+        command_timeout_secs: crate::config::delays::LONG_STEP_TIMEOUT_SECS,
+        ..ssh::default_options()
+    };
 
-        // Petite pause pour laisser Jellyfin créer l'utilisateur
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    if state.should_run(InstallStep::SystemUpdate) {
+        // Étape 1: Mise à jour système (progression lue dans la sortie apt)
+        logger.start_step("apt_update").await;
+        emit_progress(&window, "update", 0, "Mise à jour système...", None);
+        let mut apt = AptProgress::default();
+        let mut lines = LineBuffer::default();
+        let mut last_message = String::new();
+        hook_target.exec_streaming(
+            "sudo DEBIAN_FRONTEND=noninteractive apt update && sudo DEBIAN_FRONTEND=noninteractive apt upgrade -y -o Dpkg::Options::='--force-confdef' -o Dpkg::Options::='--force-confold' && sudo apt install -y git curl",
+            &long_step_options,
+            &mut |chunk| {
+                lines.push(chunk).iter().for_each(|line| apt.feed_line(line));
+                let message = apt.message();
+                if message != last_message {
+                    emit_progress(&window, "update", sub_percent(0, 14, apt.fraction()), &message, None);
+                    last_message = message;
+                }
+            },
+        ).await?;
+        logger.end_step("apt_update", true).await;
+        state.complete(InstallStep::SystemUpdate).await;
+    }
 
-        // Étape 2: Configuration initiale (langue, métadonnées)
-        let startup_config_cmd = r#"curl -s -X POST 'http://localhost:8096/Startup/Configuration' \
-            -H 'Content-Type: application/json' \
-            -d '{"UICulture":"fr","MetadataCountryCode":"FR","PreferredMetadataLanguage":"fr"}'"#;
-        ssh::execute_command(host, username, private_key, startup_config_cmd).await.ok();
+    procedure.run_until(InstallStep::Docker).await?;
 
-        // Étape 3: Mettre à jour l'utilisateur admin (POST /Startup/User)
-        let startup_user_cmd = format!(
-            r#"curl -s -X POST 'http://localhost:8096/Startup/User' \
-            -H 'Content-Type: application/json' \
-            -d '{{"Name":"{}","Password":"{}"}}'  "#,
-            jf_user, jf_pass
-        );
-        let user_result = ssh::execute_command(host, username, private_key, &startup_user_cmd).await;
-        match &user_result {
-            Ok(r) => println!("[Config] Jellyfin user updated: {}", r),
-            Err(e) => println!("[Config] Jellyfin user update warning: {}", e),
+    // Optimisation serveur sans écran (effective au redémarrage qui suit Docker)
+    if !state.is_done(InstallStep::Docker) {
+        if let Some(profile) = crate::headless::resolve_profile().await {
+            emit_progress(&window, "update", 15, "Optimisation du système (serveur sans écran)...", None);
+            if let Err(e) = crate::headless::optimize(&hook_target, &profile, "sudo").await {
+                tracing::warn!("⚠️ Optimization failed: {}", e);
+            }
         }
+    }
 
-        // Étape 4: Activer l'accès distant
-        let remote_access_cmd = r#"curl -s -X POST 'http://localhost:8096/Startup/RemoteAccess' \
-            -H 'Content-Type: application/json' \
-            -d '{"EnableRemoteAccess":true,"EnableAutomaticPortMapping":false}'"#;
-        ssh::execute_command(host, username, private_key, remote_access_cmd).await.ok();
+    if state.should_run(InstallStep::Docker) {
+        // Étape 2: Installation Docker
+        logger.start_step("docker_install").await;
+        emit_progress(&window, "docker", 15, "Installation Docker...", None);
+        ssh::execute_command(host, username, private_key,
+            &format!("curl -fsSL {} | sh && sudo usermod -aG docker $USER", crate::config::urls::DOCKER_INSTALL_SCRIPT)
+        ).await?;
 
-        // Étape 5: Compléter le wizard
-        ssh::execute_command(host, username, private_key, "curl -s -X POST 'http://localhost:8096/Startup/Complete'").await.ok();
-        println!("[Config] Jellyfin setup wizard completed");
+        // Étape 3: Redémarrage pour appliquer groupe docker
+        emit_progress(&window, "reboot", 30, "Redémarrage...", None);
+        ssh::execute_command(host, username, private_key, "sudo reboot").await.ok();
 
-        // S'authentifier pour créer les bibliothèques
-        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-        let auth_cmd = format!(r#"curl -s -X POST 'http://localhost:8096/Users/AuthenticateByName' \
-            -H 'Content-Type: application/json' \
-            -H 'X-Emby-Authorization: MediaBrowser Client="JellySetup", Device="RaspberryPi", DeviceId="jellysetup-install", Version="1.0.0"' \
-            -d '{{"Username":"{}","Pw":"{}"}}'  "#, jf_user, jf_pass);
-        let auth_result = ssh::execute_command(host, username, private_key, &auth_cmd).await.unwrap_or_default();
-
-        if let Some(token_start) = auth_result.find("\"AccessToken\":\"") {
-            let token_rest = &auth_result[token_start + 15..];
-            if let Some(token_end) = token_rest.find("\"") {
-                let jellyfin_token = &token_rest[..token_end];
-                println!("[Config] Jellyfin authenticated, creating libraries...");
-
-                // Créer bibliothèque Films avec LibraryOptions.PathInfos (OBLIGATOIRE pour avoir un ItemId!)
-                let movies_lib_cmd = format!(
-                    "curl -s -X POST 'http://localhost:8096/Library/VirtualFolders?name=Films&collectionType=movies&refreshLibrary=true' -H 'X-Emby-Token: {}' -H 'Content-Type: application/json' -d '{{\"LibraryOptions\":{{\"PathInfos\":[{{\"Path\":\"/mnt/media/movies\"}}]}}}}'",
-                    jellyfin_token
-                );
-                ssh::execute_command(host, username, private_key, &movies_lib_cmd).await.ok();
-                println!("[Config] Jellyfin: Movies library created");
+        // Attendre que le Pi soit de nouveau accessible
+        let probe_options = reboot_probe_options();
+        let probe_options = &probe_options;
+        wait_for_reboot(&window, host, 30, config.reboot_timeout(), move || async move {
+            ssh::test_connection_with_options(host, username, private_key, probe_options).await.unwrap_or(false)
+        }).await?;
+        logger.end_step("docker_install", true).await;
+        state.complete(InstallStep::Docker).await;
+    }
 
-                // Créer bibliothèque Séries avec LibraryOptions.PathInfos
-                let tv_lib_cmd = format!(
-                    "curl -s -X POST 'http://localhost:8096/Library/VirtualFolders?name=S%C3%A9ries&collectionType=tvshows&refreshLibrary=true' -H 'X-Emby-Token: {}' -H 'Content-Type: application/json' -d '{{\"LibraryOptions\":{{\"PathInfos\":[{{\"Path\":\"/mnt/media/series\"}}]}}}}'",
-                    jellyfin_token
-                );
-                ssh::execute_command(host, username, private_key, &tv_lib_cmd).await.ok();
-                println!("[Config] Jellyfin: TV library created");
-            }
-        }
-    }
-
-    // 8.3: Configurer Decypharr avec AllDebrid
-    emit_progress(&window, "config", 89, "Configuration Decypharr...", None);
-    if !config.alldebrid_api_key.is_empty() {
-        let ad_key = config.alldebrid_api_key.replace("\\", "\\\\").replace("\"", "\\\"");
-
-        let decypharr_config = format!(r#"{{
-  "url_base": "/",
-  "port": "8282",
-  "log_level": "info",
-  "debrids": [
-    {{
-      "name": "alldebrid",
-      "api_key": "{}",
-      "download_api_keys": ["{}"],
-      "folder": "/mnt/decypharr/alldebrid/__all__",
-      "rate_limit": "250/minute",
-      "unpack_rar": true,
-      "minimum_free_slot": 1,
-      "use_webdav": true,
-      "torrents_refresh_interval": "15s",
-      "download_links_refresh_interval": "40m",
-      "workers": 200,
-      "auto_expire_links_after": "3d",
-      "folder_naming": "arr"
-    }}
-  ],
-  "qbittorrent": {{
-    "download_folder": "/mnt/decypharr/qbit",
-    "refresh_interval": 15,
-    "skip_pre_cache": true
-  }},
-  "arrs": [],
-  "repair": {{
-    "enabled": true,
-    "auto_process": true,
-    "use_webdav": true,
-    "workers": 100,
-    "strategy": "per_torrent",
-    "reinsert": true,
-    "interval": "5m"
-  }},
-  "webdav": {{}},
-  "rclone": {{
-    "enabled": true,
-    "mount_path": "/mnt/decypharr",
-    "rc_port": "5572",
-    "vfs_cache_mode": "full",
-    "vfs_cache_max_size": "10G",
-    "vfs_cache_max_age": "2h",
-    "vfs_cache_poll_interval": "1m",
-    "vfs_read_chunk_size": "64M",
-    "vfs_read_chunk_size_limit": "128M",
-    "vfs_read_ahead": "512M",
-    "buffer_size": "64M",
-    "async_read": true,
-    "transfers": 2,
-    "uid": 1000,
-    "gid": 1000,
-    "attr_timeout": "1s",
-    "dir_cache_time": "10s",
-    "log_level": "INFO"
-  }},
-  "allowed_file_types": ["3gp","ac3","aiff","alac","amr","ape","asf","asx","avc","avi","bin","bivx","dat","divx","dts","dv","dvr-ms","flac","fli","flv","ifo","m2ts","m2v","m3u","m4a","m4p","m4v","mid","midi","mk3d","mka","mkv","mov","mp2","mp3","mp4","mpa","mpeg","mpg","nrg","nsv","nuv","ogg","ogm","ogv","pva","qt","ra","rm","rmvb","strm","svq3","ts","ty","viv","vob","voc","vp3","wav","webm","wma","wmv","wpl","wtv","wv","xvid"],
-  "use_auth": true
-}}"#, ad_key, ad_key);
-
-        let write_config_cmd = format!(
-            "cat > ~/media-stack/decypharr/config.json << 'EOFDECYPHARR'\n{}\nEOFDECYPHARR",
-            decypharr_config
-        );
-        ssh::execute_command(host, username, private_key, &write_config_cmd).await.ok();
-        // Redémarrer Decypharr en background (évite les timeouts)
-        ssh::execute_command(host, username, private_key, "nohup docker restart decypharr > /dev/null 2>&1 &").await.ok();
-        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-        println!("[Config] Decypharr configured with AllDebrid");
-    }
-
-    // 8.4: Configurer Radarr/Sonarr
-    emit_progress(&window, "config", 91, "Configuration Radarr/Sonarr...", None);
-    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-    let radarr_api = ssh::execute_command(host, username, private_key,
-        "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/radarr/config.xml 2>/dev/null || echo ''"
-    ).await.unwrap_or_default().trim().to_string();
-
-    let sonarr_api = ssh::execute_command(host, username, private_key,
-        "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/sonarr/config.xml 2>/dev/null || echo ''"
-    ).await.unwrap_or_default().trim().to_string();
-
-    let prowlarr_api = ssh::execute_command(host, username, private_key,
-        "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/prowlarr/config.xml 2>/dev/null || echo ''"
-    ).await.unwrap_or_default().trim().to_string();
-
-    // =============================================================================
-    // MASTER CONFIG - Fetch dynamique depuis Supabase
-    // =============================================================================
-    emit_progress(&window, "config", 89, "Récupération de la configuration master...", None);
-    println!("[MasterConfig] 🔄 Fetching configuration from Supabase...");
-
-    // Fetch master_config (type "streaming" par défaut, "storage" pour config NAS future)
-    let master_config_opt = crate::master_config::fetch_master_config(Some("streaming")).await.ok().flatten();
-
-    if let Some(master_cfg) = &master_config_opt {
-        println!("[MasterConfig] ✅ Master config loaded: {}", master_cfg.id);
-
-        // Préparer les variables pour le remplacement de templates
-        let mut template_vars = crate::template_engine::TemplateVars::new();
-        template_vars.set("PI_IP", host);
-        template_vars.set("PI_HOSTNAME", hostname);
-        template_vars.set("RADARR_API_KEY", &radarr_api);
-        template_vars.set("SONARR_API_KEY", &sonarr_api);
-        template_vars.set("PROWLARR_API_KEY", &prowlarr_api);
-        template_vars.set("JELLYFIN_USERNAME", &config.jellyfin_username);
-        template_vars.set("JELLYFIN_PASSWORD", &config.jellyfin_password);
-        template_vars.set("YGG_PASSKEY", config.admin_email.as_deref().unwrap_or(""));
-        template_vars.set("ALLDEBRID_API_KEY", &config.alldebrid_api_key);
-        template_vars.set("JELLYFIN_API_KEY", "PLACEHOLDER_WILL_BE_EXTRACTED");
-        template_vars.set("JELLYFIN_SERVER_ID", "PLACEHOLDER_WILL_BE_EXTRACTED");
-
-        // Appliquer la config pour chaque service depuis master_config
-        if let Some(jellyseerr_config) = &master_cfg.jellyseerr_config {
-            emit_progress(&window, "config", 90, "Configuration Jellyseerr...", None);
-            println!("[MasterConfig] Applying Jellyseerr config...");
-            crate::services::apply_service_config(
-                host, username, private_key,
-                "jellyseerr",
-                jellyseerr_config,
-                &template_vars
-            ).await?;
-        }
+    // Étape rapide et idempotente: toujours rejouée lors d'une reprise
+    // Étape 4: Création de la structure
+    procedure.run_until(InstallStep::Structure).await?;
+    state.begin(InstallStep::Structure);
+    emit_progress(&window, "structure", 40, "Création structure...", None);
+    let mkdir_cmd = structure_command(&media_paths, &ssh::sudo_prefix(None));
+    ssh::execute_command(host, username, private_key, &mkdir_cmd).await?;
+    crate::master_config::save_installed_media_paths(&hook_target, &media_paths).await?;
+    state.complete(InstallStep::Structure).await;
 
-        if let Some(radarr_config) = &master_cfg.radarr_config {
-            emit_progress(&window, "config", 91, "Configuration Radarr...", None);
-            println!("[MasterConfig] Applying Radarr config...");
-            crate::services::apply_service_config(
-                host, username, private_key,
-                "radarr",
-                radarr_config,
-                &template_vars
-            ).await?;
-        }
+    // Étape 5: Écrire le docker-compose.yml
+    procedure.run_until(InstallStep::Compose).await?;
+    if state.should_run(InstallStep::Compose) {
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Compose, HookPhase::Pre).await?;
+        emit_progress(&window, "compose_write", 50, "Génération docker-compose.yml...", None);
+        let escaped_compose = docker_compose.replace("'", "'\\''");
+        let write_cmd = format!("cat > ~/media-stack/docker-compose.yml << 'EOFCOMPOSE'\n{}\nEOFCOMPOSE", docker_compose);
+        ssh::execute_command(host, username, private_key, &write_cmd).await?;
+        ssh::execute_command(host, username, private_key, &crate::status::write_script_command(&docker_compose)).await?;
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Compose, HookPhase::Post).await?;
+        state.complete(InstallStep::Compose).await;
+    }
 
-        if let Some(sonarr_config) = &master_cfg.sonarr_config {
-            emit_progress(&window, "config", 92, "Configuration Sonarr...", None);
-            println!("[MasterConfig] Applying Sonarr config...");
-            crate::services::apply_service_config(
-                host, username, private_key,
-                "sonarr",
-                sonarr_config,
-                &template_vars
-            ).await?;
+    // Étape 6: Démarrer les services
+    procedure.run_until(InstallStep::ImagePull).await?;
+    if !state.is_done(InstallStep::ImagePull) {
+        match crate::image_cache::load_preloaded(&hook_target, &hook_target.sudo()).await {
+            Ok(Some(missing)) if missing.is_empty() => {
+                emit_progress(&window, "compose_up", 65, "Images Docker chargées depuis la carte SD", None);
+                state.begin(InstallStep::ImagePull);
+                state.complete(InstallStep::ImagePull).await;
+            }
+            Ok(Some(missing)) => {
+                tracing::info!("{} images missing from the SD card archive, pulling them", missing.len());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("⚠️ Preloaded images not loaded, pulling instead: {}", e),
+        }
+    }
+    if state.should_run(InstallStep::ImagePull) {
+        emit_progress(&window, "compose_up", 60, "Téléchargement des images Docker...", None);
+        let mut pull = PullProgress::new(compose_image_count(&docker_compose));
+        let mut lines = LineBuffer::default();
+        let mut last_message = String::new();
+        hook_target.exec_streaming(
+            "cd ~/media-stack && docker compose pull",
+            &long_step_options,
+            &mut |chunk| {
+                lines.push(chunk).iter().for_each(|line| pull.feed_line(line));
+                let message = pull.message();
+                if message != last_message {
+                    emit_progress(&window, "compose_up", sub_percent(60, 65, pull.fraction()), &message, None);
+                    last_message = message;
+                }
+            },
+        ).await?;
+        state.complete(InstallStep::ImagePull).await;
+    }
+
+    procedure.run_until(InstallStep::Containers).await?;
+    if state.should_run(InstallStep::Containers) {
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Containers, HookPhase::Pre).await?;
+        logger.start_step("docker_compose_up").await;
+        emit_progress(&window, "compose_up", 65, "Démarrage des services Docker...", None);
+        if let Err(e) = ssh::execute_command(host, username, private_key, "cd ~/media-stack && docker compose up -d").await {
+            logger.log_error("docker_compose_up", &format!("docker compose up -d FAILED: {}", e), None).await;
+            logger.end_step("docker_compose_up", false).await;
+            return Err(e);
         }
+        logger.end_step("docker_compose_up", true).await;
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Containers, HookPhase::Post).await?;
+        state.complete(InstallStep::Containers).await;
 
-        if let Some(prowlarr_config) = &master_cfg.prowlarr_config {
-            emit_progress(&window, "config", 93, "Configuration Prowlarr...", None);
-            println!("[MasterConfig] Applying Prowlarr config...");
-            crate::services::apply_service_config(
-                host, username, private_key,
-                "prowlarr",
-                prowlarr_config,
-                &template_vars
-            ).await?;
+        // Étape 7: Attendre que les services soient prêts
+        emit_progress(&window, "wait_services", 75, "Attente des services...", None);
+        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::JELLYFIN_BOOT_SECS)).await?;
+    }
+
+    // Étape 8: Configuration des services via API
+    // Clés *arr lues pendant la configuration (relues pour CloudSync si elle est sautée)
+    let mut harvested_keys: Option<crate::services::ArrApiKeys> = None;
+    // Infos d'auth Jellyfin (templates master_config et auto-login frontend)
+    let mut final_jellyfin_auth: Option<JellyfinAuth> = None;
+    procedure.run_until(InstallStep::Configuration).await?;
+    if state.should_run(InstallStep::Configuration) {
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Configuration, HookPhase::Pre).await?;
+        emit_progress(&window, "config", 85, "Configuration des services...", None);
+
+        // 8.1: Attendre que Jellyfin soit prêt (max 2 min)
+        emit_progress(&window, "config", 86, "Attente de Jellyfin...", None);
+        let mut jellyfin_ready = false;
+        for i in 0..24 {
+            let check = ssh::execute_command(host, username, private_key,
+                &format!("curl -s -o /dev/null -w '%{{http_code}}' http://localhost:{JELLYFIN}/health 2>/dev/null || echo 000")
+            ).await.unwrap_or_default();
+            if check.trim() == "200" {
+                jellyfin_ready = true;
+                tracing::info!("Jellyfin is ready");
+                break;
+            }
+            tracing::debug!("Waiting for Jellyfin ({}/24)...", i + 1);
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
+        }
+
+        if jellyfin_ready {
+            // 8.2: Assistant de premier démarrage, bibliothèques et transcodage via l'API REST
+            emit_progress(&window, "config", 87, "Configuration Jellyfin...", None);
+            let target = ssh::SshTarget::Key { host, username, private_key };
+            match jellyfin::first_run_setup(&target, &config, &media_paths, None).await {
+                Ok(session) => {
+                    tracing::info!("Jellyfin configured (ServerId={}, UserId={})", session.server_id, session.user_id);
+                    final_jellyfin_auth = Some(session.into());
+                }
+                Err(e) => tracing::warn!("⚠️ Jellyfin setup: {}", e),
+            }
+
+            // 8.2b: Auto-test du transcodage (informatif)
+            emit_progress(&window, "config", 88, "Test du transcodage 4K...", None);
+            match crate::transcode_test::run(&target).await {
+                Ok(result) => emit_progress(&window, "config", 88, &format!("Transcodage: {:.0} i/s - {}", result.fps, result.verdict), None),
+                Err(e) => tracing::warn!("⚠️ Transcode test: {}", e),
+            }
         }
 
-        if let Some(jellyfin_config) = &master_cfg.jellyfin_config {
-            emit_progress(&window, "config", 94, "Configuration Jellyfin...", None);
-            println!("[MasterConfig] Applying Jellyfin config...");
-            crate::services::apply_service_config(
-                host, username, private_key,
-                "jellyfin",
-                jellyfin_config,
-                &template_vars
-            ).await?;
+        // 8.3: Configurer Decypharr avec AllDebrid (les *arr sont ajoutés en 8.4c)
+        emit_progress(&window, "config", 89, "Configuration Decypharr...", None);
+        if !config.alldebrid_api_key.is_empty() {
+            let decypharr_vars = base_template_vars(host, hostname, &config, &media_paths, &crate::services::ArrApiKeys::default());
+            let decypharr_config = crate::services::decypharr::build_config(None, &decypharr_vars, &media_paths.root);
+            if let Err(e) = crate::services::decypharr::write_config(&hook_target, &decypharr_config).await {
+                tracing::warn!("⚠️ Decypharr: {}", e);
+            }
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::SETTLE_SECS)).await?;
+            tracing::info!("Decypharr configured with AllDebrid");
         }
 
-        println!("[MasterConfig] ✅ All service configurations applied from master_config");
-    } else {
-        println!("[MasterConfig] ⚠️  No master_config found - using default configuration");
-    }
-    // =============================================================================
+        // 8.4: Configurer Radarr/Sonarr
+        emit_progress(&window, "config", 91, "Configuration Radarr/Sonarr...", None);
+        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::STEP_PAUSE_SECS)).await?;
+
+        // Attendre que les config.xml soient générés puis lire les clés API
+        let api_keys = match crate::services::harvest_api_keys(&ssh::SshTarget::Key { host, username, private_key }).await {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::warn!("⚠️ Could not read *arr API keys: {}", e);
+                crate::services::ArrApiKeys::default()
+            }
+        };
+        harvested_keys = Some(api_keys.clone());
+        let radarr_api = api_keys.radarr.clone();
+        let sonarr_api = api_keys.sonarr.clone();
+        let prowlarr_api = api_keys.prowlarr.clone();
+
+        // =============================================================================
+        // MASTER CONFIG - Fetch dynamique depuis Supabase
+        // =============================================================================
+        emit_progress(&window, "config", 89, "Récupération de la configuration master...", None);
+        tracing::debug!("🔄 Fetching configuration from Supabase...");
+
+        // Fetch master_config (type "streaming" par défaut, "storage" pour config NAS future)
+        let master_config_opt = crate::cloud::backend().fetch_master_config(Some("streaming")).await.ok().flatten();
+
+        if let Some(master_cfg) = &master_config_opt {
+            tracing::info!("✅ Master config loaded: {}", master_cfg.id);
+
+            // Préparer les variables pour le remplacement de templates
+            let mut template_vars = base_template_vars(host, hostname, &config, &media_paths, &api_keys);
+            apply_jellyfin_vars(&mut template_vars, final_jellyfin_auth.as_ref());
+
+            // Rendre la config de chaque service, dans l'ordre des dépendances
+            let mut rendered = crate::services::render_service_configs(master_cfg, &template_vars);
+            // Services exclus par l'utilisateur: aucune configuration à appliquer
+            rendered.retain(|(service, _)| config.service_enabled(service));
+            if config.review_configs {
+                emit_progress(&window, "review", 90, "Relecture des configurations...", None);
+                rendered = crate::services::review::request_review(&window, rendered).await?;
+            }
+            for (service_name, service_config) in &rendered {
+                let json = serde_json::to_string_pretty(service_config).unwrap_or_default();
+                crate::workspace::write(&format!("configs/{}.json", service_name), &json);
+            }
+
+            // Puis l'appliquer (Merge si le Pi a déjà été configuré: aucune base supprimée)
+            let config_mode = crate::services::ConfigMode::detect(&hook_target).await;
+            for (index, (service_name, service_config)) in rendered.iter().enumerate() {
+                let display_name = crate::services::display_name(service_name);
+                emit_progress(&window, "config", 90 + index as u32, &format!("Configuration {}...", display_name), None);
+                tracing::debug!("Applying {} config...", display_name);
+
+                let outcome = crate::services::apply_service_config(
+                    host, username, private_key,
+                    crate::services::ServiceConfig {
+                        service_name,
+                        resolved_config: service_config,
+                        vars: &template_vars,
+                        mode: config_mode,
+                    },
+                ).await;
+
+                // Un service mal configuré n'interrompt pas l'installation (comme en mode mot de passe)
+                let log_line = match &outcome {
+                    Ok(()) => format!("{} configured from master_config", display_name),
+                    Err(e) => {
+                        tracing::warn!("⚠️  {} config error: {}", display_name, e);
+                        format!("ERROR - {} configuration failed: {}", display_name, e)
+                    }
+                };
+                ssh::execute_command(host, username, private_key,
+                    &format!("echo \"$(date): \"{} >> ~/jellysetup-logs/install.log", ssh::shell_quote(&log_line))
+                ).await.ok();
+            }
 
-    // Récupérer l'IP locale pour Decypharr
-    let pi_ip = ssh::execute_command(host, username, private_key, "hostname -I | awk '{print $1}'")
-        .await.unwrap_or_else(|_| host.to_string()).trim().to_string();
+            if let Err(e) = crate::services::ConfigMode::mark_configured(&hook_target).await {
+                tracing::warn!("⚠️  Configuration marker not written: {}", e);
+            }
+            if let Err(e) = crate::config_history::record_version(&hook_target, hostname, Some(&master_cfg.id), &docker_compose, &rendered, &template_vars).await {
+                tracing::warn!("⚠️  Version not recorded: {}", e);
+            }
+
+            tracing::info!("✅ All service configurations applied from master_config");
+        } else {
+            tracing::warn!("⚠️  No master_config found - using default configuration");
+        }
+        // =============================================================================
+
+        // Récupérer l'IP locale pour Decypharr
+        let pi_ip = ssh::execute_command(host, username, private_key, "hostname -I | awk '{print $1}'")
+            .await.unwrap_or_else(|_| host.to_string()).trim().to_string();
 
-    // Ajouter Decypharr à Radarr
-    if !radarr_api.is_empty() {
-        let radarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:7878/api/v3/downloadclient' \
+        // Ajouter Decypharr à Radarr
+        if !radarr_api.is_empty() {
+            let radarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:{RADARR}/api/v3/downloadclient' \
             -H 'X-Api-Key: {}' \
             -H 'Content-Type: application/json' \
             -d '{{"name": "Decypharr", "implementation": "QBittorrent", "configContract": "QBittorrentSettings", "enable": true, "priority": 1, "fields": [{{"name": "host", "value": "{}"}}, {{"name": "port", "value": 8282}}, {{"name": "useSsl", "value": false}}, {{"name": "movieCategory", "value": "radarr"}}]}}'"#, radarr_api, pi_ip);
-        ssh::execute_command(host, username, private_key, &radarr_client_cmd).await.ok();
-    }
+            ssh::execute_command(host, username, private_key, &radarr_client_cmd).await.ok();
+        }
 
-    // Ajouter Decypharr à Sonarr
-    if !sonarr_api.is_empty() {
-        let sonarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:8989/api/v3/downloadclient' \
+        // Ajouter Decypharr à Sonarr
+        if !sonarr_api.is_empty() {
+            let sonarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:{SONARR}/api/v3/downloadclient' \
             -H 'X-Api-Key: {}' \
             -H 'Content-Type: application/json' \
             -d '{{"name": "Decypharr", "implementation": "QBittorrent", "configContract": "QBittorrentSettings", "enable": true, "priority": 1, "fields": [{{"name": "host", "value": "{}"}}, {{"name": "port", "value": 8282}}, {{"name": "useSsl", "value": false}}, {{"name": "tvCategory", "value": "sonarr"}}]}}'"#, sonarr_api, pi_ip);
-        ssh::execute_command(host, username, private_key, &sonarr_client_cmd).await.ok();
-    }
+            ssh::execute_command(host, username, private_key, &sonarr_client_cmd).await.ok();
+        }
 
-    // 8.4c: Configurer Decypharr avec les arrs (Radarr/Sonarr)
-    if !radarr_api.is_empty() || !sonarr_api.is_empty() {
-        println!("[Config] Decypharr: Configuring arrs array...");
+        // 8.4c: Configurer Decypharr avec les arrs (Radarr/Sonarr)
+        if !radarr_api.is_empty() || !sonarr_api.is_empty() {
+            tracing::debug!("Decypharr: Configuring arrs array...");
 
-        let mut arrs_entries = Vec::new();
+            let mut arrs_entries = Vec::new();
 
-        if !radarr_api.is_empty() {
-            arrs_entries.push(format!(r#"{{
+            if !radarr_api.is_empty() {
+                arrs_entries.push(format!(r#"{{
       "name": "radarr",
       "host": "http://{}:7878",
       "token": "{}",
@@ -1468,10 +1611,10 @@ pub async fn run_full_installation(
       "flatten": true,
       "cleanup": true
     }}"#, pi_ip, radarr_api));
-        }
+            }
 
-        if !sonarr_api.is_empty() {
-            arrs_entries.push(format!(r#"{{
+            if !sonarr_api.is_empty() {
+                arrs_entries.push(format!(r#"{{
       "name": "tv-sonarr",
       "host": "http://{}:8989",
       "token": "{}",
@@ -1479,355 +1622,476 @@ pub async fn run_full_installation(
       "flatten": true,
       "cleanup": true
     }}"#, pi_ip, sonarr_api));
-        }
+            }
 
-        let arrs_json = format!("[{}]", arrs_entries.join(","));
+            let arrs_json = format!("[{}]", arrs_entries.join(","));
 
-        // Utiliser jq pour mettre à jour le champ arrs dans config.json
-        let update_arrs_cmd = format!(
-            r#"jq '.arrs = {}' ~/media-stack/decypharr/config.json > /tmp/decypharr_config_tmp.json && mv /tmp/decypharr_config_tmp.json ~/media-stack/decypharr/config.json"#,
-            arrs_json
-        );
+            // Utiliser jq pour mettre à jour le champ arrs dans config.json
+            let update_arrs_cmd = format!(
+                r#"jq '.arrs = {}' ~/media-stack/decypharr/config.json > /tmp/decypharr_config_tmp.json && mv /tmp/decypharr_config_tmp.json ~/media-stack/decypharr/config.json"#,
+                arrs_json
+            );
 
-        if let Err(e) = ssh::execute_command(host, username, private_key, &update_arrs_cmd).await {
-            println!("[Config] Decypharr: Failed to update arrs config: {}", e);
-        } else {
-            println!("[Config] Decypharr: arrs array configured successfully");
-            // Redémarrer Decypharr pour appliquer les changements
-            ssh::execute_command(host, username, private_key, "docker restart decypharr > /dev/null 2>&1 &").await.ok();
+            if let Err(e) = ssh::execute_command(host, username, private_key, &update_arrs_cmd).await {
+                tracing::warn!("Decypharr: Failed to update arrs config: {}", e);
+            } else {
+                tracing::info!("Decypharr: arrs array configured successfully");
+                // Redémarrer Decypharr pour appliquer les changements
+                ssh::execute_command(host, username, private_key, "docker restart decypharr > /dev/null 2>&1 &").await.ok();
+            }
         }
-    }
 
-    // 8.4b: Ajouter les Root Folders
-    if !radarr_api.is_empty() {
-        let radarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:7878/api/v3/rootfolder' \
+        // 8.4b: Ajouter les Root Folders
+        if !radarr_api.is_empty() {
+            let radarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:{RADARR}/api/v3/rootfolder' \
             -H 'X-Api-Key: {}' -H 'Content-Type: application/json' \
-            -d '{{"path": "/mnt/media/movies"}}'"#, radarr_api);
-        ssh::execute_command(host, username, private_key, &radarr_root_cmd).await.ok();
-    }
+            -d '{{"path": "{}"}}'"#, radarr_api, media_paths.movies_path());
+            ssh::execute_command(host, username, private_key, &radarr_root_cmd).await.ok();
+        }
 
-    if !sonarr_api.is_empty() {
-        let sonarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:8989/api/v3/rootfolder' \
+        if !sonarr_api.is_empty() {
+            let sonarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:{SONARR}/api/v3/rootfolder' \
             -H 'X-Api-Key: {}' -H 'Content-Type: application/json' \
-            -d '{{"path": "/mnt/media/series"}}'"#, sonarr_api);
-        ssh::execute_command(host, username, private_key, &sonarr_root_cmd).await.ok();
-    }
+            -d '{{"path": "{}"}}'"#, sonarr_api, media_paths.tv_path());
+            ssh::execute_command(host, username, private_key, &sonarr_root_cmd).await.ok();
+        }
 
-    // 8.5: Configurer Prowlarr avec YGG
-    emit_progress(&window, "config", 94, "Configuration Prowlarr...", None);
-    if let Some(ref ygg_passkey) = config.ygg_passkey {
-        if !ygg_passkey.is_empty() && !prowlarr_api.is_empty() {
-            let passkey = ygg_passkey.replace("\\", "\\\\").replace("\"", "\\\"");
+        // 8.5: Configurer Prowlarr avec YGG
+        emit_progress(&window, "config", 94, "Configuration Prowlarr...", None);
+        if let Some(ygg_passkey) = crate::secret::expose_opt(&config.ygg_passkey) {
+            if !ygg_passkey.is_empty() && !prowlarr_api.is_empty() {
+                let passkey = ygg_passkey.replace("\\", "\\\\").replace("\"", "\\\"");
 
-            let prowlarr_ygg_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/indexer' \
+                let prowlarr_ygg_cmd = format!(r#"curl -s -X POST 'http://localhost:{PROWLARR}/api/v1/indexer' \
                 -H 'X-Api-Key: {}' \
                 -H 'Content-Type: application/json' \
                 -d '{{"name": "YGGTorrent", "definitionName": "yggtorrent", "implementation": "YggTorrent", "configContract": "YggTorrentSettings", "enable": true, "protocol": "torrent", "priority": 1, "fields": [{{"name": "passkey", "value": "{}"}}]}}'"#, prowlarr_api, passkey);
-            ssh::execute_command(host, username, private_key, &prowlarr_ygg_cmd).await.ok();
+                ssh::execute_command(host, username, private_key, &prowlarr_ygg_cmd).await.ok();
+            }
+        }
 
-            // Ajouter FlareSolverr
-            let flaresolverr_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/indexerProxy' \
-                -H 'X-Api-Key: {}' \
-                -H 'Content-Type: application/json' \
-                -d '{{"name": "FlareSolverr", "configContract": "FlareSolverrSettings", "implementation": "FlareSolverr", "fields": [{{"name": "host", "value": "http://localhost:8191"}}]}}'"#, prowlarr_api);
-            ssh::execute_command(host, username, private_key, &flaresolverr_cmd).await.ok();
+        // 8.5b: FlareSolverr (requis par les indexers protégés par Cloudflare)
+        if !config.service_enabled("flaresolverr") {
+            tracing::info!("FlareSolverr disabled, skipping");
+        } else if let Err(issue) = crate::services::flaresolverr::setup(hook_target, None).await {
+            if issue == crate::services::flaresolverr::FlareSolverrIssue::Cancelled {
+                return Err(crate::operations::Cancelled.into());
+            }
+            let message = issue.message();
+            tracing::warn!("⚠️ {}", message);
+            emit_progress(&window, "config", 95, &format!("⚠️ {}", message), None);
+            ssh::execute_command(host, username, private_key,
+                &format!("echo \"$(date): WARNING - {}\" >> ~/jellysetup-logs/install.log", message.replace('"', "'"))
+            ).await.ok();
         }
-    }
 
-    // 8.6: Synchroniser Prowlarr avec Radarr/Sonarr
-    if !prowlarr_api.is_empty() {
-        emit_progress(&window, "config", 96, "Synchronisation Prowlarr...", None);
+        // 8.6: Synchroniser Prowlarr avec Radarr/Sonarr
+        if !prowlarr_api.is_empty() {
+            emit_progress(&window, "config", 96, "Synchronisation Prowlarr...", None);
 
-        if !radarr_api.is_empty() {
-            let sync_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/applications' \
+            if !radarr_api.is_empty() {
+                let sync_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{PROWLARR}/api/v1/applications' \
                 -H 'X-Api-Key: {}' \
                 -H 'Content-Type: application/json' \
-                -d '{{"enable": true, "name": "Radarr", "syncLevel": "fullSync", "implementation": "Radarr", "configContract": "RadarrSettings", "fields": [{{"name": "prowlarrUrl", "value": "http://localhost:9696"}}, {{"name": "baseUrl", "value": "http://localhost:7878"}}, {{"name": "apiKey", "value": "{}"}}]}}'"#, prowlarr_api, radarr_api);
-            ssh::execute_command(host, username, private_key, &sync_radarr_cmd).await.ok();
-        }
+                -d '{{"enable": true, "name": "Radarr", "syncLevel": "fullSync", "implementation": "Radarr", "configContract": "RadarrSettings", "fields": [{{"name": "prowlarrUrl", "value": "http://localhost:{PROWLARR}"}}, {{"name": "baseUrl", "value": "http://localhost:{RADARR}"}}, {{"name": "apiKey", "value": "{}"}}]}}'"#, prowlarr_api, radarr_api);
+                ssh::execute_command(host, username, private_key, &sync_radarr_cmd).await.ok();
+            }
 
-        if !sonarr_api.is_empty() {
-            let sync_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/applications' \
+            if !sonarr_api.is_empty() {
+                let sync_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{PROWLARR}/api/v1/applications' \
                 -H 'X-Api-Key: {}' \
                 -H 'Content-Type: application/json' \
-                -d '{{"enable": true, "name": "Sonarr", "syncLevel": "fullSync", "implementation": "Sonarr", "configContract": "SonarrSettings", "fields": [{{"name": "prowlarrUrl", "value": "http://localhost:9696"}}, {{"name": "baseUrl", "value": "http://localhost:8989"}}, {{"name": "apiKey", "value": "{}"}}]}}'"#, prowlarr_api, sonarr_api);
-            ssh::execute_command(host, username, private_key, &sync_sonarr_cmd).await.ok();
+                -d '{{"enable": true, "name": "Sonarr", "syncLevel": "fullSync", "implementation": "Sonarr", "configContract": "SonarrSettings", "fields": [{{"name": "prowlarrUrl", "value": "http://localhost:{PROWLARR}"}}, {{"name": "baseUrl", "value": "http://localhost:{SONARR}"}}, {{"name": "apiKey", "value": "{}"}}]}}'"#, prowlarr_api, sonarr_api);
+                ssh::execute_command(host, username, private_key, &sync_sonarr_cmd).await.ok();
+            }
         }
-    }
 
-    // 8.7: Configurer Bazarr
-    emit_progress(&window, "config", 97, "Configuration Bazarr...", None);
-    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        // 8.7: Configurer Bazarr
+        emit_progress(&window, "config", 97, "Configuration Bazarr...", None);
+        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::STEP_PAUSE_SECS)).await?;
 
-    let mut bazarr_ready = false;
-    for _ in 0..12 {
-        let check = ssh::execute_command(host, username, private_key,
-            "test -f ~/media-stack/bazarr/config/config.yaml && echo OK || echo WAIT"
-        ).await.unwrap_or_default();
-        if check.contains("OK") {
-            bazarr_ready = true;
-            break;
+        // Service désactivé: aucune attente, bazarr_ready reste faux
+        let bazarr_attempts = if config.service_enabled("bazarr") { 12 } else { 0 };
+        let mut bazarr_ready = false;
+        for _ in 0..bazarr_attempts {
+            let check = ssh::execute_command(host, username, private_key,
+                "test -f ~/media-stack/bazarr/config/config.yaml && echo OK || echo WAIT"
+            ).await.unwrap_or_default();
+            if check.contains("OK") {
+                bazarr_ready = true;
+                break;
+            }
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-    }
 
-    if bazarr_ready && !radarr_api.is_empty() && !sonarr_api.is_empty() {
-        let bazarr_api_check = ssh::execute_command(host, username, private_key,
-            "grep -oP '(?<=apikey: )[^\\s]+' ~/media-stack/bazarr/config/config.yaml 2>/dev/null || echo ''"
-        ).await.unwrap_or_default().trim().to_string();
+        if bazarr_ready && !radarr_api.is_empty() && !sonarr_api.is_empty() {
+            let bazarr_api_check = ssh::execute_command(host, username, private_key,
+                "grep -oP '(?<=apikey: )[^\\s]+' ~/media-stack/bazarr/config/config.yaml 2>/dev/null || echo ''"
+            ).await.unwrap_or_default().trim().to_string();
 
-        if !bazarr_api_check.is_empty() {
-            let bazarr_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:6767/api/system/settings' \
+            if !bazarr_api_check.is_empty() {
+                let bazarr_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{BAZARR}/api/system/settings' \
                 -H 'X-API-KEY: {}' -H 'Content-Type: application/json' \
                 -d '{{"settings": {{"radarr": {{"ip": "{}", "port": 7878, "apikey": "{}", "ssl": false, "base_url": ""}}}}}}"#,
-                bazarr_api_check, pi_ip, radarr_api);
-            ssh::execute_command(host, username, private_key, &bazarr_radarr_cmd).await.ok();
+                    bazarr_api_check, pi_ip, radarr_api);
+                ssh::execute_command(host, username, private_key, &bazarr_radarr_cmd).await.ok();
 
-            let bazarr_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:6767/api/system/settings' \
+                let bazarr_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{BAZARR}/api/system/settings' \
                 -H 'X-API-KEY: {}' -H 'Content-Type: application/json' \
                 -d '{{"settings": {{"sonarr": {{"ip": "{}", "port": 8989, "apikey": "{}", "ssl": false, "base_url": ""}}}}}}"#,
-                bazarr_api_check, pi_ip, sonarr_api);
-            ssh::execute_command(host, username, private_key, &bazarr_sonarr_cmd).await.ok();
-            println!("[Config] Bazarr: Configured");
+                    bazarr_api_check, pi_ip, sonarr_api);
+                ssh::execute_command(host, username, private_key, &bazarr_sonarr_cmd).await.ok();
+                tracing::info!("Bazarr: Configured");
+            }
         }
-    }
 
-    // 8.8: Configuration automatique de Jellyseerr via API
-    emit_progress(&window, "config", 96, "Configuration de Jellyseerr...", None);
-    println!("[Config] Jellyseerr: Starting automatic configuration...");
+        // 8.8: Configuration automatique de Jellyseerr via API
+        emit_progress(&window, "config", 96, "Configuration de Jellyseerr...", None);
+        tracing::debug!("Jellyseerr: Starting automatic configuration...");
 
-    // Attendre que Jellyseerr soit prêt (max 60 sec)
-    let mut jellyseerr_ready = false;
-    for i in 0..12 {
-        let check = ssh::execute_command(host, username, private_key,
-            "curl -s -o /dev/null -w '%{http_code}' 'http://localhost:5055/api/v1/status' 2>/dev/null || echo '000'"
-        ).await.unwrap_or_default();
+        // Attendre que Jellyseerr soit prêt (max 60 sec)
+        let jellyseerr_enabled = config.service_enabled("jellyseerr");
+        let jellyseerr_attempts = if jellyseerr_enabled { 12 } else { 0 };
+        let mut jellyseerr_ready = false;
+        for i in 0..jellyseerr_attempts {
+            let check = ssh::execute_command(host, username, private_key,
+                &format!("curl -s -o /dev/null -w '%{{http_code}}' 'http://localhost:{JELLYSEERR}/api/v1/status' 2>/dev/null || echo '000'")
+            ).await.unwrap_or_default();
 
-        if check.trim() == "200" || check.trim() == "403" {
-            jellyseerr_ready = true;
-            println!("[Config] Jellyseerr: Service ready after {} seconds", (i + 1) * 5);
-            break;
-        }
-        println!("[Config] Jellyseerr: Waiting... (attempt {}/12)", i + 1);
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-    }
+            if check.trim() == "200" || check.trim() == "403" {
+                jellyseerr_ready = true;
+                tracing::info!("Jellyseerr: Service ready after {} seconds", (i + 1) * 5);
+                break;
+            }
+            tracing::debug!("Jellyseerr: Waiting... (attempt {}/12)", i + 1);
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
+        }
+
+        if jellyseerr_ready {
+            // Petite pause pour s'assurer que Jellyseerr est complètement prêt
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::SETTLE_SECS)).await?;
+
+            // Étape 1: Authentifier avec Jellyfin et créer l'admin
+
+            // Essayer plusieurs hostnames jusqu'à ce qu'un fonctionne
+            // 1. host.docker.internal (avec extra_hosts configuré)
+            // 2. jellyfin (nom du service Docker sur le même réseau)
+            // 3. IP du Pi (passée en paramètre host)
+            let hostnames_to_try = vec![
+                "host.docker.internal".to_string(),
+                "jellyfin".to_string(),
+                host.to_string(),
+            ];
+
+            let mut auth_result = String::new();
+            for jellyfin_hostname in &hostnames_to_try {
+                tracing::info!("Jellyseerr: Trying hostname: {}", jellyfin_hostname);
+                // serverType: 2 = JELLYFIN (enum MediaServerType)
+                // urlBase: "" évite que JavaScript ajoute "undefined" à l'URL
+                let auth_body = serde_json::json!({
+                    "username": config.jellyfin_username,
+                    "password": config.jellyfin_password,
+                    "hostname": jellyfin_hostname,
+                    "port": crate::config::ports::JELLYFIN,
+                    "useSsl": false,
+                    "urlBase": "",
+                    "serverType": 2,
+                    "email": config.admin_email()
+                });
+                let auth_cmd = jellyseerr::curl_command("POST", "/auth/jellyfin", jellyseerr::CurlAuth::SaveCookies, Some(&auth_body));
+                auth_result = ssh::execute_command(host, username, private_key, &auth_cmd).await.unwrap_or_default();
+                tracing::info!("Jellyseerr: Auth result with {}: {}", jellyfin_hostname, &auth_result[..std::cmp::min(200, auth_result.len())]);
+
+                if jellyseerr::is_auth_success(&auth_result) {
+                    tracing::info!("Jellyseerr: Success with hostname: {}", jellyfin_hostname);
+                    break;
+                }
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::API_PAUSE_SECS)).await?;
+            }
+
+            // Vérifier si l'auth a réussi (l'utilisateur créé est renvoyé)
+            if jellyseerr::is_auth_success(&auth_result) {
+                tracing::info!("Jellyseerr: Admin user created successfully!");
+
+                // Étape 2: Sync des bibliothèques Jellyfin
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::STEP_PAUSE_SECS)).await?;
+                let sync_cmd = &format!("curl -s -X GET 'http://localhost:{JELLYSEERR}/api/v1/settings/jellyfin/library?sync=true' -b /tmp/jellyseerr_cookies.txt");
+                let sync_result = ssh::execute_command(host, username, private_key, sync_cmd).await.unwrap_or_default();
+                tracing::info!("Jellyseerr: Library sync result: {}", &sync_result[..std::cmp::min(300, sync_result.len())]);
+
+                // Extraire les IDs des bibliothèques (format: [{"id":"xxx","name":"Films",...}])
+                let library_ids = jellyseerr::parse_library_ids(&sync_result).unwrap_or_else(|e| {
+                    tracing::warn!("Jellyseerr: ⚠️ {}", e);
+                    Vec::new()
+                });
+
+                // Étape 3: Activer toutes les bibliothèques trouvées
+                if !library_ids.is_empty() {
+                    let ids_str = library_ids.join(",");
+                    let enable_cmd = format!(
+                        "curl -s -X GET 'http://localhost:{JELLYSEERR}/api/v1/settings/jellyfin/library?enable={}' -b /tmp/jellyseerr_cookies.txt",
+                        ids_str
+                    );
+                    ssh::execute_command(host, username, private_key, &enable_cmd).await.ok();
+                    tracing::info!("Jellyseerr: Enabled {} libraries: {}", library_ids.len(), ids_str);
+                }
 
-    if jellyseerr_ready {
-        // Petite pause pour s'assurer que Jellyseerr est complètement prêt
-        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+                // Étape 4: Finaliser le setup
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::API_PAUSE_SECS)).await?;
+                let init_cmd = &format!("curl -s -X POST 'http://localhost:{JELLYSEERR}/api/v1/settings/initialize' -b /tmp/jellyseerr_cookies.txt -H 'Content-Type: application/json'");
+                let init_result = ssh::execute_command(host, username, private_key, init_cmd).await.unwrap_or_default();
+                tracing::info!("Jellyseerr: Initialize result: {}", init_result);
+
+                // Configurer Radarr et Sonarr dans Jellyseerr
+                tracing::debug!("Jellyseerr: Configuring Radarr and Sonarr...");
+
+                // Clés API récupérées à l'étape 8.4
+                let radarr_api_key = radarr_api.clone();
+                let sonarr_api_key = sonarr_api.clone();
+
+                if !radarr_api_key.is_empty() && !sonarr_api_key.is_empty() {
+                    // Laisser Jellyseerr écrire settings.json après l'initialisation
+                    crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
+
+                    // Récupérer l'IP locale de l'hôte
+                    let host_ip = ssh::execute_command(host, username, private_key, "hostname -I | awk '{print $1}'"
+                    ).await.unwrap_or_default().trim().to_string();
+
+                    // Enregistrer Radarr/Sonarr sans créer de doublon si la config est rejouée
+                    match crate::services::arr_client::JellyseerrClient::connect(&ssh::SshTarget::Key { host, username, private_key }).await {
+                        Ok(client) => {
+                            let radarr = jellyseerr::arr_server_payload("radarr", &host_ip, &radarr_api_key, &media_paths.movies_path());
+                            let mut sonarr = jellyseerr::arr_server_payload("sonarr", &host_ip, &sonarr_api_key, &media_paths.tv_path());
+                            profiles::apply_anime_directory(&mut sonarr, &config);
+
+                            for (service, payload) in [("radarr", &radarr), ("sonarr", &sonarr)] {
+                                if let Err(e) = client.upsert_arr_server(service, payload).await {
+                                    tracing::warn!("Jellyseerr: ⚠️  Could not register {}: {}", service, e);
+                                }
+                            }
+                            tracing::info!("Jellyseerr: ✅ Radarr and Sonarr configured");
+                        }
+                        Err(e) => {
+                            tracing::warn!("Jellyseerr: ⚠️  Could not read Jellyseerr API key: {}", e);
+                        }
+                    }
+                } else {
+                    tracing::warn!("Jellyseerr: ⚠️  Could not get Radarr/Sonarr API keys");
+                }
 
-        // Étape 1: Authentifier avec Jellyfin et créer l'admin
-        // IMPORTANT: Échapper les caractères spéciaux pour JSON
-        let jf_user = config.jellyfin_username.replace("\\", "\\\\").replace("\"", "\\\"");
-        let jf_pass = config.jellyfin_password.replace("\\", "\\\\").replace("\"", "\\\"");
+                // Nettoyer les cookies
+                ssh::execute_command(host, username, private_key, "rm -f /tmp/jellyseerr_cookies.txt").await.ok();
 
-        // Essayer plusieurs hostnames jusqu'à ce qu'un fonctionne
-        // 1. host.docker.internal (avec extra_hosts configuré)
-        // 2. jellyfin (nom du service Docker sur le même réseau)
-        // 3. IP du Pi (passée en paramètre host)
-        let hostnames_to_try = vec![
-            "host.docker.internal".to_string(),
-            "jellyfin".to_string(),
-            host.to_string(),
-        ];
+                tracing::info!("Jellyseerr: Configuration completed successfully!");
+            } else {
+                tracing::warn!("Jellyseerr: Auth failed, manual setup required at http://<pi-ip>:5055");
+            }
+        } else if jellyseerr_enabled {
+            tracing::warn!("Jellyseerr: Service not ready after 60 seconds, manual setup required");
+        }
 
-        let mut auth_result = String::new();
-        for jellyfin_hostname in &hostnames_to_try {
-            println!("[Config] Jellyseerr: Trying hostname: {}", jellyfin_hostname);
-            // serverType: 2 = JELLYFIN (enum MediaServerType)
-            // urlBase: "" évite que JavaScript ajoute "undefined" à l'URL
-            let auth_cmd = format!(
-                r#"curl -s -X POST 'http://localhost:5055/api/v1/auth/jellyfin' \
-                   -H 'Content-Type: application/json' \
-                   -c /tmp/jellyseerr_cookies.txt \
-                   -d '{{"username":"{}","password":"{}","hostname":"{}","port":8096,"useSsl":false,"urlBase":"","serverType":2,"email":"admin@easyjelly.local"}}'"#,
-                jf_user, jf_pass, jellyfin_hostname
-            );
-            auth_result = ssh::execute_command(host, username, private_key, &auth_cmd).await.unwrap_or_default();
-            println!("[Config] Jellyseerr: Auth result with {}: {}", jellyfin_hostname, &auth_result[..std::cmp::min(200, auth_result.len())]);
+        // 8.8b: Profils optionnels (anime, musique, livres, livres audio)
+        if !config.profiles().is_empty() {
+            emit_progress(&window, "config", 97, "Configuration des profils (anime, musique, livres, livres audio)...", None);
+            let target = ssh::SshTarget::Key { host, username, private_key };
+            if let Err(e) = profiles::apply_profiles(&target, &config, &media_paths, master_config_opt.as_ref()).await {
+                tracing::info!("Profiles: {}", e);
+            }
+        }
 
-            if auth_result.contains("\"id\"") {
-                println!("[Config] Jellyseerr: Success with hostname: {}", jellyfin_hostname);
-                break;
+        // 8.8c: Politiques par défaut (quotas, téléchargements simultanés, monitoring)
+        if let Some(policies) = master_config_opt.as_ref().and_then(|m| m.policies.as_ref()) {
+            emit_progress(&window, "config", 97, "Application des politiques par défaut...", None);
+            let target = ssh::SshTarget::Key { host, username, private_key };
+            if let Err(e) = crate::services::policies::apply_policies(&target, policies).await {
+                tracing::info!("Policies: {}", e);
             }
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
 
-        // Vérifier si l'auth a réussi (contient "id" dans la réponse)
-        if auth_result.contains("\"id\"") {
-            println!("[Config] Jellyseerr: Admin user created successfully!");
+        // 8.8d: Tableau de bord Homepage (tuiles générées depuis le compose)
+        if config.homepage {
+            emit_progress(&window, "config", 97, "Configuration du tableau de bord...", None);
+            let target = ssh::SshTarget::Key { host, username, private_key };
+            let vars = base_template_vars(host, hostname, &config, &media_paths, &api_keys);
+            if let Err(e) = crate::homepage::apply_config(&target, &docker_compose, &vars).await {
+                tracing::info!("Homepage: {}", e);
+            }
+        }
 
-            // Étape 2: Sync des bibliothèques Jellyfin
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-            let sync_cmd = "curl -s -X GET 'http://localhost:5055/api/v1/settings/jellyfin/library?sync=true' -b /tmp/jellyseerr_cookies.txt";
-            let sync_result = ssh::execute_command(host, username, private_key, sync_cmd).await.unwrap_or_default();
-            println!("[Config] Jellyseerr: Library sync result: {}", &sync_result[..std::cmp::min(300, sync_result.len())]);
+        // 8.8e: Surveillance externe (alerte si le serveur ne répond plus)
+        if let Some(monitor) = &config.uptime_monitor {
+            emit_progress(&window, "config", 97, "Activation de la surveillance...", None);
+            let target = ssh::SshTarget::Key { host, username, private_key };
+            if let Err(e) = crate::uptime::install_monitor(&target, monitor).await {
+                tracing::info!("Uptime: {}", e);
+            }
+        }
 
-            // Extraire les IDs des bibliothèques
-            let mut library_ids: Vec<String> = Vec::new();
-            let mut search_pos = 0;
-            while let Some(id_start) = sync_result[search_pos..].find("\"id\":\"") {
-                let actual_start = search_pos + id_start + 6;
-                if let Some(id_end) = sync_result[actual_start..].find("\"") {
-                    let lib_id = &sync_result[actual_start..actual_start + id_end];
-                    library_ids.push(lib_id.to_string());
-                    search_pos = actual_start + id_end;
-                } else {
-                    break;
-                }
+        // 8.8f: Agent quotidien AllDebrid (expiration de l'abonnement, quotas)
+        if !config.alldebrid_api_key.is_empty() {
+            let target = ssh::SshTarget::Key { host, username, private_key };
+            if let Err(e) = crate::alldebrid::install_agent(&target, config.alldebrid_api_key.expose(), crate::secret::expose_opt(&config.discord_webhook)).await {
+                tracing::info!("AllDebrid agent: {}", e);
             }
+        }
 
-            // Étape 3: Activer toutes les bibliothèques trouvées
-            if !library_ids.is_empty() {
-                let ids_str = library_ids.join(",");
-                let enable_cmd = format!(
-                    "curl -s -X GET 'http://localhost:5055/api/v1/settings/jellyfin/library?enable={}' -b /tmp/jellyseerr_cookies.txt",
-                    ids_str
-                );
-                ssh::execute_command(host, username, private_key, &enable_cmd).await.ok();
-                println!("[Config] Jellyseerr: Enabled {} libraries: {}", library_ids.len(), ids_str);
+        // 8.8g: Surveillance de l'accès distant (redémarre cloudflared, alerte Discord)
+        if config.cloudflare_token.is_some() {
+            let target = ssh::SshTarget::Key { host, username, private_key };
+            if let Err(e) = crate::remote_access::install_watchdog(&target, crate::secret::expose_opt(&config.discord_webhook)).await {
+                tracing::info!("Remote access watchdog: {}", e);
             }
+        }
 
-            // Étape 4: Finaliser le setup
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-            let init_cmd = "curl -s -X POST 'http://localhost:5055/api/v1/settings/initialize' -b /tmp/jellyseerr_cookies.txt -H 'Content-Type: application/json'";
-            let init_result = ssh::execute_command(host, username, private_key, init_cmd).await.unwrap_or_default();
-            println!("[Config] Jellyseerr: Initialize result: {}", init_result);
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Configuration, HookPhase::Post).await?;
+        ssh::execute_command(host, username, private_key,
+            "echo \"$(date): Service configuration completed\" >> ~/jellysetup-logs/install.log"
+        ).await.ok();
+        state.complete(InstallStep::Configuration).await;
+    }
 
-            // Configurer Radarr et Sonarr dans Jellyseerr
-            println!("[Config] Jellyseerr: Configuring Radarr and Sonarr...");
+    // 8.9: Sauvegarder l'installation dans Supabase (centralisation des identifiants)
+    procedure.run_until(InstallStep::CloudSync).await?;
+    if state.should_run(InstallStep::CloudSync) {
+        emit_progress(&window, "supabase", 98, "Sauvegarde dans le cloud...", None);
+        let api_keys = match harvested_keys.take() {
+            Some(keys) => keys,
+            None => crate::services::harvest_api_keys(&hook_target).await.unwrap_or_default(),
+        };
 
-            // Récupérer les API keys de Radarr et Sonarr
-            let radarr_api_key = ssh::execute_command(host, username, private_key,
-                "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/radarr/config.xml 2>/dev/null || echo ''"
-            ).await.unwrap_or_default().trim().to_string();
+        // Récupérer le fingerprint SSH (capturé lors de la connexion)
+        let ssh_fingerprint = ssh::get_last_host_fingerprint();
+
+        // Sauvegarder dans Supabase (ne bloque pas en cas d'erreur)
+        // Note: Pour l'auth par clé, on pourrait aussi sauvegarder les clés SSH
+        // mais elles ne sont pas passées à cette fonction actuellement
+        let registration = crate::registration::InstallationRegistration::new(
+            hostname,
+            host,
+            // TODO: Ajouter les clés (publique, privée chiffrée) à InstallConfig
+            crate::registration::RegistrationAuth::Key { ssh_public_key: None, ssh_private_key_encrypted: None },
+            ssh_fingerprint.as_deref(),
+        );
+        crate::pi_registry::record_installation(&registration, username, Some(private_key)).await;
+        match crate::cloud::backend().save_installation(&registration).await {
+            Ok(config_id) => {
+                tracing::info!("Installation saved with ID: {}", config_id);
+
+                // Sauvegarder aussi les credentials de l'utilisateur
+                // Compte admin chiffré avec le mot de passe Jellyfin
+                let admin_account_encrypted = config.encrypted_admin_account().ok();
+                let credentials = crate::supabase::PiCredentials {
+                    alldebrid_api_key: Some(config.alldebrid_api_key.expose()),
+                    ygg_passkey: crate::secret::expose_opt(&config.ygg_passkey),
+                    cloudflare_token: crate::secret::expose_opt(&config.cloudflare_token),
+                    jellyfin_api_key: None,
+                    radarr_api_key: api_keys.get("radarr"),
+                    sonarr_api_key: api_keys.get("sonarr"),
+                    prowlarr_api_key: api_keys.get("prowlarr"),
+                    admin_account_encrypted: admin_account_encrypted.as_deref(),
+                };
+                if let Err(e) = crate::supabase::save_pi_config(hostname, &config_id, &credentials).await {
+                    tracing::warn!("could not save Pi config: {}", e);
+                }
 
-            let sonarr_api_key = ssh::execute_command(host, username, private_key,
-                "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/sonarr/config.xml 2>/dev/null || echo ''"
-            ).await.unwrap_or_default().trim().to_string();
+                // État de référence pour la détection de dérive (detect_drift)
+                match crate::drift::capture_snapshot(&hook_target).await {
+                    Ok(snapshot) => {
+                        if let Err(e) = crate::supabase::save_desired_state(hostname, &config_id, &snapshot).await {
+                            tracing::warn!("could not save desired state: {}", e);
+                        }
+                    }
+                    Err(e) => tracing::warn!("could not capture baseline: {}", e),
+                }
 
-            if !radarr_api_key.is_empty() && !sonarr_api_key.is_empty() {
-                let jellyseerr_config = format!(r#"
-# Récupérer l'API key de Jellyseerr depuis settings.json
-API_KEY=$(cat ~/media-stack/jellyseerr/settings.json | grep -o '"apiKey":"[^"]*"' | head -1 | cut -d'"' -f4)
-
-# Récupérer l'IP locale de l'hôte
-HOST_IP=$(hostname -I | awk '{{print $1}}')
-
-# Attendre que Jellyseerr soit prêt
-sleep 5
-
-# Configurer Radarr via l'API
-curl -s -X POST "http://localhost:5055/api/v1/settings/radarr" \
-  -H "X-Api-Key: $API_KEY" \
-  -H "Content-Type: application/json" \
-  -d '{{
-    "name": "Radarr",
-    "hostname": "'"$HOST_IP"'",
-    "port": 7878,
-    "apiKey": "{}",
-    "useSsl": false,
-    "activeProfileId": 4,
-    "activeProfileName": "HD-1080p",
-    "activeDirectory": "/mnt/media/movies",
-    "is4k": false,
-    "minimumAvailability": "released",
-    "isDefault": true,
-    "syncEnabled": true
-  }}'
-
-# Configurer Sonarr via l'API
-curl -s -X POST "http://localhost:5055/api/v1/settings/sonarr" \
-  -H "X-Api-Key: $API_KEY" \
-  -H "Content-Type: application/json" \
-  -d '{{
-    "name": "Sonarr",
-    "hostname": "'"$HOST_IP"'",
-    "port": 8989,
-    "apiKey": "{}",
-    "useSsl": false,
-    "activeProfileId": 4,
-    "activeProfileName": "HD-1080p",
-    "activeDirectory": "/mnt/media/series",
-    "is4k": false,
-    "enableSeasonFolders": true,
-    "isDefault": true,
-    "syncEnabled": true
-  }}'
-
-echo "✅ Radarr and Sonarr configured in Jellyseerr"
-"#, radarr_api_key, sonarr_api_key);
-
-                ssh::execute_command(host, username, private_key, &jellyseerr_config).await.ok();
-                println!("[Config] Jellyseerr: ✅ Radarr and Sonarr configured");
-            } else {
-                println!("[Config] Jellyseerr: ⚠️  Could not get Radarr/Sonarr API keys");
+                // Mettre à jour le statut à "completed"
+                if let Err(e) = crate::cloud::backend().update_status(hostname, &config_id, "completed", None).await {
+                    tracing::warn!("could not update status: {}", e);
+                }
             }
+            Err(e) => {
+                tracing::warn!("could not save installation: {}", e);
+            }
+        }
 
-            // Nettoyer les cookies
-            ssh::execute_command(host, username, private_key, "rm -f /tmp/jellyseerr_cookies.txt").await.ok();
+        state.complete(InstallStep::CloudSync).await;
+    }
+    procedure.finish().await?;
 
-            println!("[Config] Jellyseerr: Configuration completed successfully!");
-        } else {
-            println!("[Config] Jellyseerr: Auth failed, manual setup required at http://<pi-ip>:5055");
+    // Checklist post-installation pour le wizard (non bloquante)
+    match crate::verification::verify_and_persist(hook_target, hostname, &config.jellyfin_username, config.jellyfin_password.expose()).await {
+        Ok(report) => {
+            let _ = window.emit("install-health", &report);
         }
-    } else {
-        println!("[Config] Jellyseerr: Service not ready after 60 seconds, manual setup required");
+        Err(e) => tracing::warn!("post-install verification failed: {}", e),
     }
 
-    ssh::execute_command(host, username, private_key,
-        "echo \"$(date): Service configuration completed\" >> ~/jellysetup-logs/install.log"
-    ).await.ok();
+    emit_progress_with_auth(&window, "complete", 100, "Installation terminée !", None, final_jellyfin_auth);
 
-    // 8.9: Sauvegarder l'installation dans Supabase (centralisation des identifiants)
-    emit_progress(&window, "supabase", 98, "Sauvegarde dans le cloud...", None);
+    // Finaliser les logs et envoyer tout à Supabase
+    logger.finalize(true).await;
 
-    // Récupérer le fingerprint SSH (capturé lors de la connexion)
-    let ssh_fingerprint = ssh::get_last_host_fingerprint();
+    tracing::info!("Installation completed successfully on {}", host);
+    Ok(())
+}
 
-    // Sauvegarder dans Supabase (ne bloque pas en cas d'erreur)
-    // Note: Pour l'auth par clé, on pourrait aussi sauvegarder les clés SSH
-    // mais elles ne sont pas passées à cette fonction actuellement
-    match crate::supabase::save_installation(
-        hostname,
-        host,
-        None,  // TODO: Ajouter la clé publique à InstallConfig
-        None,  // TODO: Ajouter la clé privée chiffrée
-        ssh_fingerprint.as_deref(),
-        env!("CARGO_PKG_VERSION"),
-    ).await {
-        Ok(config_id) => {
-            println!("[Supabase] Installation saved with ID: {}", config_id);
-
-            // Sauvegarder aussi les credentials de l'utilisateur
-            if let Err(e) = crate::supabase::save_pi_config(
-                hostname,
-                &config_id,
-                Some(&config.alldebrid_api_key),
-                config.ygg_passkey.as_deref(),
-                config.cloudflare_token.as_deref(),
-                None, // jellyfin_api_key
-                None, // radarr_api_key
-                None, // sonarr_api_key
-                None, // prowlarr_api_key
-            ).await {
-                println!("[Supabase] Warning: could not save Pi config: {}", e);
-            }
+/// Variables de template communes aux sections master_config
+fn base_template_vars(
+    host: &str,
+    hostname: &str,
+    config: &InstallConfig,
+    media_paths: &MediaPaths,
+    api_keys: &crate::services::ArrApiKeys,
+) -> TemplateVars {
+    let mut vars = TemplateVars::new();
+    vars.set("PI_IP", host);
+    vars.set("PI_HOSTNAME", hostname);
+    media_paths.apply_to(&mut vars);
+    api_keys.apply_to(&mut vars);
+    config.container_env().apply_to(&mut vars);
+    vars.set("JELLYFIN_USERNAME", &config.jellyfin_username);
+    vars.set("JELLYFIN_PASSWORD", config.jellyfin_password.expose());
+    vars.set("YGG_PASSKEY", crate::secret::expose_opt(&config.ygg_passkey).unwrap_or(""));
+    vars.set("ALLDEBRID_API_KEY", config.alldebrid_api_key.expose());
+    vars.set(crate::services::flaresolverr::ENABLED_VAR, &config.service_enabled("flaresolverr").to_string());
+    vars
+}
 
-            // Mettre à jour le statut à "completed"
-            if let Err(e) = crate::supabase::update_status(hostname, &config_id, "completed", None).await {
-                println!("[Supabase] Warning: could not update status: {}", e);
-            }
+/// Jeton et ServerId Jellyfin pour les templates master_config (vides si l'assistant a échoué)
+fn apply_jellyfin_vars(vars: &mut TemplateVars, auth: Option<&JellyfinAuth>) {
+    match auth {
+        Some(auth) => {
+            vars.set("JELLYFIN_API_KEY", &auth.access_token);
+            vars.set("JELLYFIN_SERVER_ID", &auth.server_id);
         }
-        Err(e) => {
-            println!("[Supabase] Warning: could not save installation: {}", e);
+        None => {
+            tracing::warn!("⚠️ Jellyfin not authenticated, JELLYFIN_API_KEY and JELLYFIN_SERVER_ID left empty");
+            vars.set("JELLYFIN_API_KEY", "");
+            vars.set("JELLYFIN_SERVER_ID", "");
         }
     }
+}
 
-    emit_progress(&window, "complete", 100, "Installation terminée !", None);
+/// Dossiers du stack et des médias (identiques sur les deux chemins d'installation)
+/// `sudo` étant le préfixe d'élévation ("sudo" ou "echo 'pw' | sudo -S")
+fn structure_command(media_paths: &MediaPaths, sudo: &str) -> String {
+    let media_dirs: Vec<String> = media_paths.all_paths().iter().map(|p| crate::ssh::shell_quote(p)).collect();
+    let root = crate::ssh::shell_quote(&media_paths.root);
+    format!(
+        "mkdir -p ~/media-stack/{{decypharr,jellyfin,radarr,sonarr,prowlarr,jellyseerr,bazarr,logs}} && \
+         {sudo} mkdir -p /mnt/decypharr/qbit/downloads {root} {dirs} && \
+         {sudo} chown -R $USER:$USER /mnt/decypharr {root}",
+        dirs = media_dirs.join(" ")
+    )
+}
 
-    tracing::info!("Installation completed successfully on {}", host);
-    Ok(())
+/// Variables connues avant l'installation (aperçu `plan_installation`)
+pub async fn planning_vars(host: &str, config: &InstallConfig) -> TemplateVars {
+    let media_paths = crate::master_config::resolve_media_paths(config.media_paths.as_ref()).await;
+    let hostname = host.replace(".local", "");
+    let mut vars = base_template_vars(host, &hostname, config, &media_paths, &crate::services::ArrApiKeys::default());
+    vars.set("SUDO", "sudo");
+    vars
 }
 
 /// Émet un événement de progression vers le frontend
@@ -1837,6 +2101,7 @@ fn emit_progress(window: &Window, step: &str, percent: u32, message: &str, speed
 
 /// Émet un événement de progression avec données d'authentification Jellyfin optionnelles
 fn emit_progress_with_auth(window: &Window, step: &str, percent: u32, message: &str, speed: Option<&str>, jellyfin_auth: Option<JellyfinAuth>) {
+    crate::operations::report_progress(step, percent, message);
     let _ = window.emit(
         "flash-progress",
         FlashProgress {
@@ -1850,15 +2115,71 @@ fn emit_progress_with_auth(window: &Window, step: &str, percent: u32, message: &
 }
 
 /// Exécute l'installation complète sur le Pi via SSH (authentification par mot de passe)
+#[tracing::instrument(name = "install", skip_all, fields(host = %host))]
 pub async fn run_full_installation_password(
     window: Window,
     host: &str,
     username: &str,
     password: &str,
     config: InstallConfig,
+) -> Result<()> {
+    crate::preflight::guard_host(crate::preflight::HostOperation::Install, &config.host_overrides).await?;
+    let mut state = InstallState::start(host, false)?;
+    let notifier = crate::notifications::DiscordNotifier::from_webhook(crate::secret::expose_opt(&config.discord_webhook));
+    if let Some(notifier) = &notifier {
+        notifier.install_started(host, host).await;
+    }
+    state.set_notifier(notifier.clone());
+    state.set_resume_point(crate::remote_resume::ResumePublisher::new(host, username, Some(password), None, &config));
+    let result = run_full_installation_password_steps(window, host, username, password, config, &mut state).await;
+    state.finish(&result).await;
+    if let Some(notifier) = &notifier {
+        notifier.install_finished(&crate::ssh::SshTarget::Password { host, username, password }, &state, &result).await;
+    }
+    result
+}
+
+/// Reprend une installation interrompue (authentification par mot de passe) à partir de l'étape en échec
+#[tracing::instrument(name = "resume_install", skip_all, fields(host = %host))]
+pub async fn resume_installation_password(
+    window: Window,
+    host: &str,
+    username: &str,
+    password: &str,
+    config: InstallConfig,
+) -> Result<()> {
+    crate::preflight::guard_host(crate::preflight::HostOperation::Install, &config.host_overrides).await?;
+    let mut state = InstallState::start(host, true)?;
+    let notifier = crate::notifications::DiscordNotifier::from_webhook(crate::secret::expose_opt(&config.discord_webhook));
+    if let Some(notifier) = &notifier {
+        notifier.install_started(host, host).await;
+    }
+    state.set_notifier(notifier.clone());
+    state.set_resume_point(crate::remote_resume::ResumePublisher::new(host, username, Some(password), None, &config));
+    let result = run_full_installation_password_steps(window, host, username, password, config, &mut state).await;
+    state.finish(&result).await;
+    if let Some(notifier) = &notifier {
+        notifier.install_finished(&crate::ssh::SshTarget::Password { host, username, password }, &state, &result).await;
+    }
+    result
+}
+
+async fn run_full_installation_password_steps(
+    window: Window,
+    host: &str,
+    username: &str,
+    password: &str,
+    mut config: InstallConfig,
+    state: &mut InstallState,
 ) -> Result<()> {
     use crate::ssh;
 
+    config.register_secrets();
+    crate::redact::register(password);
+
+    // Bastion éventuel (Pi distant joignable uniquement via une machine intermédiaire)
+    ssh::set_jump_host(host, config.jump_host.clone());
+
     // Empêcher la mise en veille du Mac pendant l'installation
     #[cfg(target_os = "macos")]
     let caffeinate_process = {
@@ -1867,11 +2188,11 @@ pub async fn run_full_installation_password(
             .spawn()
         {
             Ok(child) => {
-                println!("[Install] caffeinate started (PID: {})", child.id());
+                tracing::info!("caffeinate started (PID: {})", child.id());
                 Some(child)
             }
             Err(e) => {
-                println!("[Install] Warning: could not start caffeinate: {}", e);
+                tracing::warn!("could not start caffeinate: {}", e);
                 None
             }
         }
@@ -1882,7 +2203,11 @@ pub async fn run_full_installation_password(
     // IMPORTANT: Nettoyer le known_hosts local pour cette IP
     // Cela permet de gérer les reflash de carte SD sans erreur de clé SSH
     if let Err(e) = ssh::clear_known_hosts_for_ip(host) {
-        println!("[Install] Warning: could not clear known_hosts: {}", e);
+        tracing::warn!("could not clear known_hosts: {}", e);
+    }
+    // Carte flashée par l'application: l'empreinte épinglée (TOFU/Supabase) n'est plus valable
+    if let Err(e) = ssh::forget_if_freshly_flashed(&host.replace(".local", ""), host) {
+        tracing::warn!("could not forget pinned fingerprint: {}", e);
     }
 
     // Faire une première connexion SSH pour capturer le fingerprint du serveur
@@ -1891,7 +2216,7 @@ pub async fn run_full_installation_password(
         Ok(true) => {
             // Récupérer le fingerprint capturé
             if let Some(fp) = ssh::get_last_host_fingerprint() {
-                println!("[Install] SSH host fingerprint captured: {}", fp);
+                tracing::info!("SSH host fingerprint captured: {}", fp);
                 // Le fingerprint sera sauvegardé dans Supabase avec les autres données
             }
         }
@@ -1905,9 +2230,9 @@ pub async fn run_full_installation_password(
 
     // Initialiser la session persistante
     if let Err(e) = ssh::init_persistent_session(host, username, password).await {
-        println!("[Install] Warning: could not init persistent SSH session: {}", e);
+        tracing::warn!("could not init persistent SSH session: {}", e);
     } else {
-        println!("[Install] ✅ Persistent SSH session initialized");
+        tracing::info!("✅ Persistent SSH session initialized");
     }
 
     // Notifier le frontend que la connexion SSH est OK
@@ -1922,21 +2247,56 @@ pub async fn run_full_installation_password(
         match ssh::execute_command_password(host, username, password, "hostname").await {
             Ok(h) => {
                 let h = h.trim().to_string();
-                println!("[Install] Hostname récupéré via SSH: {}", h);
+                tracing::info!("Hostname récupéré via SSH: {}", h);
                 h
             }
             Err(e) => {
-                println!("[Install] Warning: impossible de récupérer hostname: {}, utilisation de l'IP", e);
+                tracing::warn!("impossible de récupérer hostname: {}, utilisation de l'IP", e);
                 host.to_string()
             }
         }
     };
 
+    state.set_pi_name(&hostname);
+
+    // Arborescence des médias, partagée par compose, *arr, Jellyseerr et Jellyfin
+    // (celle déjà en place sur le Pi est conservée, y compris l'ancienne /mnt/media)
+    let paths_target = ssh::SshTarget::Password { host, username, password };
+    config.media_paths =
+        Some(crate::master_config::resolve_install_media_paths(&paths_target, config.media_paths.as_ref()).await);
+    let media_paths = config.media_paths();
+
+    // Fuseau horaire et PUID/PGID des conteneurs (config, sinon ceux du Pi)
+    let container_env = resolve_container_env(ssh::SshTarget::Password { host, username, password }, &config).await;
+    config.timezone = Some(container_env.timezone.clone());
+    config.puid = Some(container_env.puid);
+    config.pgid = Some(container_env.pgid);
+
+    // Tunnel Cloudflare provisionné via l'API (remplace le token collé à la main)
+    if let Some(setup) = config.cloudflare.clone() {
+        emit_progress(&window, "update", 0, "Création du tunnel Cloudflare...", None);
+        match crate::cloudflare::provision(&setup, &hostname, config.service_enabled("jellyseerr")).await {
+            Ok(tunnel) => {
+                crate::cloudflare::write_config(&ssh::SshTarget::Password { host, username, password }, &tunnel).await?;
+                config.cloudflare_token = Some(tunnel.token.into());
+            }
+            Err(e) => tracing::error!("❌ Tunnel provisioning failed: {}", e),
+        }
+    }
+
+    // Schéma Supabase initialisé avant le compose: Supabazarr reçoit le token du Pi
+    if let Err(e) = crate::supabase::ensure_schema_initialized(&hostname).await {
+        tracing::warn!("⚠️ Schema init failed, Supabazarr without Pi token: {}", e);
+    }
+
     // Générer le docker-compose.yml avec tous les services
-    let docker_compose = generate_docker_compose(
-        &hostname,
-        config.cloudflare_token.as_deref()
-    );
+    let stack = crate::compose::resolve_stack().await;
+    let docker_compose = generate_docker_compose(&stack, &hostname, &config, &media_paths, &container_env);
+    // Services personnalisés de l'utilisateur (extra-compose.yml), validés et namespacés
+    let docker_compose = crate::extra_compose::merge_into(docker_compose, config.extra_compose.as_deref())?;
+    crate::workspace::write("docker-compose.yml", &docker_compose);
+
+    let hook_target = ssh::SshTarget::Password { host, username, password };
 
     // ==========================================================================
     // MEGA SYSTÈME DE LOGS - Initialisation
@@ -1950,11 +2310,12 @@ pub async fn run_full_installation_password(
         username,            // ssh_username
         password,            // ssh_password
         env!("CARGO_PKG_VERSION"), // installer_version
-    );
+    )
+    .with_window(window.clone());
 
     // Initialiser le logger (crée dossier local + schéma Supabase)
     if let Err(e) = logger.initialize().await {
-        println!("[Install] ⚠️ Warning: logger init failed: {}", e);
+        tracing::warn!("⚠️ Logger init failed: {}", e);
     }
 
     logger.log_with_details(
@@ -1971,363 +2332,363 @@ pub async fn run_full_installation_password(
         })
     ).await;
 
-    // Étape 1: Mise à jour système (en background pour éviter timeout)
-    logger.start_step("apt_update").await;
-    emit_progress(&window, "update", 0, "Mise à jour système (peut prendre 10-15 min)...", None);
+    // Procédure publiée (procedures/v2/steps.json): étapes exécutées entre les phases ci-dessous
+    let mut procedure_vars = base_template_vars(host, &hostname, &config, &media_paths, &crate::services::ArrApiKeys::default());
+    let resolved_procedure = crate::procedure::Procedure::resolve_for(host, !state.completed.is_empty()).await;
+    // Hooks personnalisés (procédure et master_config), exécutés autour des étapes
+    let hooks = crate::hooks::step_hooks(&resolved_procedure).await;
+    procedure_vars.set("SUDO", &crate::ssh::sudo_prefix(Some(password)));
+    let mut procedure = crate::procedure::ProcedureRunner::new(
+        resolved_procedure,
+        hook_target,
+        host,
+        procedure_vars,
+        Some(&logger),
+        !state.completed.is_empty(),
+    )?;
+    procedure.run_until(InstallStep::SystemUpdate).await?;
 
-    // Lancer apt update/upgrade en background avec nohup
-    // IMPORTANT: DEBIAN_FRONTEND=noninteractive + --force-confdef/confold pour éviter les questions interactives
-    let update_cmd = format!(
-        "nohup sh -c 'export DEBIAN_FRONTEND=noninteractive && echo \"{}\" | sudo -S -E apt update && echo \"{}\" | sudo -S -E apt upgrade -y -o Dpkg::Options::=\"--force-confdef\" -o Dpkg::Options::=\"--force-confold\" && echo \"{}\" | sudo -S -E apt install -y git curl && touch /tmp/apt_done' > /tmp/apt.log 2>&1 &",
-        password, password, password
-    );
-    ssh::execute_command_password(host, username, password, &update_cmd).await.ok();
-
-    // Attendre que apt soit terminé (max 15 min)
-    let mut apt_completed = false;
-    for i in 0..90 {
-        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-
-        // Vérifier si apt est terminé et récupérer le paquet en cours
-        let status_cmd = r#"
-            if [ -f /tmp/apt_done ]; then
-                echo 'DONE'
-            elif pgrep -f 'apt|dpkg' > /dev/null; then
-                # Déterminer la phase et récupérer l'info pertinente
-                if grep -q 'Unpacking\|Setting up' /tmp/apt.log 2>/dev/null; then
-                    # Phase upgrade: afficher le paquet
-                    PKG=$(grep -oE '(Unpacking|Setting up) [^ ]+' /tmp/apt.log 2>/dev/null | tail -1 | awk '{print $2}' | cut -d: -f1)
-                    echo "UPGRADE:${PKG:-installing}"
-                elif grep -q 'Reading package lists\|Building dependency' /tmp/apt.log 2>/dev/null; then
-                    echo "UPDATE:refresh"
-                elif grep -q 'Hit:\|Get:' /tmp/apt.log 2>/dev/null; then
-                    REPO=$(grep -oE '(Hit|Get):[0-9]+ [^ ]+' /tmp/apt.log 2>/dev/null | tail -1 | awk '{print $2}' | cut -d'/' -f3)
-                    echo "FETCH:${REPO:-repos}"
+    // Langue du système (LANG, LC_ALL, police console), non bloquant
+    if state.should_run(InstallStep::SystemUpdate) {
+        if let Err(e) = crate::system_locale::apply(&hook_target, &config.system_locale(), Some(password)).await {
+            tracing::warn!("⚠️ Could not set system locale: {}", e);
+        }
+    }
+
+    if state.should_run(InstallStep::SystemUpdate) {
+        // Étape 1: Mise à jour système (en background pour éviter timeout)
+        logger.start_step("apt_update").await;
+        emit_progress(&window, "update", 0, "Mise à jour système (peut prendre 10-15 min)...", None);
+
+        // Lancer apt update/upgrade en background avec nohup
+        // IMPORTANT: DEBIAN_FRONTEND=noninteractive + --force-confdef/confold pour éviter les questions interactives
+        let update_cmd = format!(
+            "nohup sh -c 'export DEBIAN_FRONTEND=noninteractive && echo \"{}\" | sudo -S -E apt update && echo \"{}\" | sudo -S -E apt upgrade -y -o Dpkg::Options::=\"--force-confdef\" -o Dpkg::Options::=\"--force-confold\" && echo \"{}\" | sudo -S -E apt install -y git curl && touch /tmp/apt_done' > /tmp/apt.log 2>&1 &",
+            password, password, password
+        );
+        ssh::execute_command_password(host, username, password, &update_cmd).await.ok();
+
+        // Attendre que apt soit terminé (max 15 min)
+        let mut apt_completed = false;
+        for i in 0..90 {
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::LONG_POLL_SECS)).await?;
+
+            // Vérifier si apt est terminé (la progression est lue ensuite dans /tmp/apt.log)
+            let status_cmd = r#"
+                if [ -f /tmp/apt_done ]; then
+                    echo 'DONE'
+                elif pgrep -f 'apt|dpkg' > /dev/null; then
+                    echo 'RUNNING'
                 else
-                    echo "RUNNING:working"
+                    echo 'IDLE'
                 fi
-            else
-                echo 'IDLE'
-            fi
-        "#;
-        match ssh::execute_command_password(host, username, password, status_cmd).await {
-            Ok(output) => {
-                let output = output.trim();
-                if output.contains("DONE") {
-                    println!("[Install] apt upgrade completed!");
-                    apt_completed = true;
-                    break;
-                } else if output.starts_with("UPGRADE:") {
-                    // Phase upgrade: afficher le paquet
-                    let pkg = output.strip_prefix("UPGRADE:").unwrap_or("...");
-                    let progress_msg = format!("Installation: {} • ~{}min", pkg, (15 - i / 6).max(1));
-                    emit_progress(&window, "update", (i as u32).min(14), &progress_msg, None);
-                } else if output.starts_with("UPDATE:") {
-                    let progress_msg = format!("Analyse des paquets... • ~{}min", (15 - i / 6).max(1));
-                    emit_progress(&window, "update", (i as u32).min(14), &progress_msg, None);
-                } else if output.starts_with("FETCH:") {
-                    let repo = output.strip_prefix("FETCH:").unwrap_or("repos");
-                    let progress_msg = format!("Téléchargement: {} • ~{}min", repo, (15 - i / 6).max(1));
-                    emit_progress(&window, "update", (i as u32).min(14), &progress_msg, None);
-                } else if output.starts_with("RUNNING:") {
-                    let progress_msg = format!("Mise à jour en cours... • ~{}min", (15 - i / 6).max(1));
-                    emit_progress(&window, "update", (i as u32).min(14), &progress_msg, None);
-                } else {
-                    // IDLE = apt pas en cours, mais pas forcément terminé (peut avoir rebooté)
-                    println!("[Install] apt not running, checking if completed...");
-                    // Ne pas break ici, continuer à vérifier
-                }
-            }
-            Err(_) => {
-                // Pi probablement en train de rebooter (kernel update)
-                println!("[Install] SSH lost, waiting for Pi...");
-                emit_progress(&window, "update", 10, "Pi redémarre (kernel update)...", None);
-                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-
-                // Attendre que le Pi revienne
-                for _j in 0..30 {
-                    if ssh::execute_command_password(host, username, password, "echo ok").await.is_ok() {
+            "#;
+            match ssh::execute_command_password(host, username, password, status_cmd).await {
+                Ok(output) => {
+                    let output = output.trim();
+                    if output.contains("DONE") {
+                        tracing::info!("apt upgrade completed!");
+                        apt_completed = true;
                         break;
+                    } else if !output.starts_with("IDLE") {
+                        // Progression réelle lue dans le log apt (paquets téléchargés, dépaquetés, configurés)
+                        let apt_log = ssh::execute_command_password(host, username, password,
+                            "grep -E '^(Get:|Unpacking |Setting up )| upgraded, ' /tmp/apt.log 2>/dev/null"
+                        ).await.unwrap_or_default();
+                        let apt = AptProgress::from_log(&apt_log);
+                        let progress_msg = format!("{} • ~{}min", apt.message(), (15 - i / 6).max(1));
+                        emit_progress(&window, "update", sub_percent(0, 14, apt.fraction()), &progress_msg, None);
+                    } else {
+                        // IDLE = apt pas en cours, mais pas forcément terminé (peut avoir rebooté)
+                        tracing::debug!("apt not running, checking if completed...");
+                        // Ne pas break ici, continuer à vérifier
+                    }
+                }
+                Err(_) => {
+                    // Pi probablement en train de rebooter (kernel update)
+                    tracing::debug!("SSH lost, waiting for Pi...");
+                    emit_progress(&window, "update", 10, "Pi redémarre (kernel update)...", None);
+                    crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::REBOOT_GRACE_SECS)).await?;
+
+                    // Attendre que le Pi revienne
+                    for _j in 0..30 {
+                        if ssh::execute_command_password(host, username, password, "echo ok").await.is_ok() {
+                            break;
+                        }
+                        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
                     }
-                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    // Après reboot, continuer la boucle pour vérifier apt_done
                 }
-                // Après reboot, continuer la boucle pour vérifier apt_done
             }
-        }
 
-        if i == 89 {
-            println!("[Install] Warning: apt timeout, continuing anyway");
+            if i == 89 {
+                tracing::warn!("apt timeout, continuing anyway");
+            }
         }
-    }
 
-    // Si apt n'a pas terminé proprement (ex: reboot pendant upgrade), réparer et relancer
-    if !apt_completed {
-        println!("[Install] apt may have been interrupted, checking for broken packages...");
-        emit_progress(&window, "update", 12, "Vérification des paquets...", None);
+        // Si apt n'a pas terminé proprement (ex: reboot pendant upgrade), réparer et relancer
+        if !apt_completed {
+            tracing::debug!("apt may have been interrupted, checking for broken packages...");
+            emit_progress(&window, "update", 12, "Vérification des paquets...", None);
 
-        // Réparer les paquets potentiellement cassés
-        let repair_cmd = format!(
-            "echo '{}' | sudo -S DEBIAN_FRONTEND=noninteractive dpkg --configure -a && echo '{}' | sudo -S DEBIAN_FRONTEND=noninteractive apt --fix-broken install -y -o Dpkg::Options::='--force-confdef' -o Dpkg::Options::='--force-confold'",
-            password, password
-        );
-        ssh::execute_command_password(host, username, password, &repair_cmd).await.ok();
+            // Réparer les paquets potentiellement cassés
+            let repair_cmd = format!(
+                "echo '{}' | sudo -S DEBIAN_FRONTEND=noninteractive dpkg --configure -a && echo '{}' | sudo -S DEBIAN_FRONTEND=noninteractive apt --fix-broken install -y -o Dpkg::Options::='--force-confdef' -o Dpkg::Options::='--force-confold'",
+                password, password
+            );
+            ssh::execute_command_password(host, username, password, &repair_cmd).await.ok();
 
-        // Vérifier si on doit relancer l'upgrade
-        let check_upgrade = ssh::execute_command_password(host, username, password,
-            "apt list --upgradable 2>/dev/null | grep -c upgradable || echo 0"
-        ).await.unwrap_or_default();
+            // Vérifier si on doit relancer l'upgrade
+            let check_upgrade = ssh::execute_command_password(host, username, password,
+                "apt list --upgradable 2>/dev/null | grep -c upgradable || echo 0"
+            ).await.unwrap_or_default();
 
-        let upgradable_count: i32 = check_upgrade.trim().parse().unwrap_or(0);
-        if upgradable_count > 5 {
-            println!("[Install] {} packages still need upgrading, resuming...", upgradable_count);
-            emit_progress(&window, "update", 13, &format!("Reprise upgrade ({} paquets)...", upgradable_count), None);
+            let upgradable_count: i32 = check_upgrade.trim().parse().unwrap_or(0);
+            if upgradable_count > 5 {
+                tracing::debug!("{} packages still need upgrading, resuming...", upgradable_count);
+                emit_progress(&window, "update", 13, &format!("Reprise upgrade ({} paquets)...", upgradable_count), None);
 
-            let resume_cmd = format!(
-                "echo '{}' | sudo -S DEBIAN_FRONTEND=noninteractive apt upgrade -y -o Dpkg::Options::='--force-confdef' -o Dpkg::Options::='--force-confold'",
-                password
-            );
-            ssh::execute_command_password(host, username, password, &resume_cmd).await.ok();
+                let resume_cmd = format!(
+                    "echo '{}' | sudo -S DEBIAN_FRONTEND=noninteractive apt upgrade -y -o Dpkg::Options::='--force-confdef' -o Dpkg::Options::='--force-confold'",
+                    password
+                );
+                ssh::execute_command_password(host, username, password, &resume_cmd).await.ok();
+            }
         }
-    }
 
-    // Logger la fin de l'étape apt
-    logger.end_step("apt_update", apt_completed).await;
-    logger.log(LogLevel::Info, "apt_update", &format!(
-        "APT terminé: {}",
-        if apt_completed { "succès" } else { "avec récupération" }
-    )).await;
+        // Logger la fin de l'étape apt
+        logger.end_step("apt_update", apt_completed).await;
+        logger.log(LogLevel::Info, "apt_update", &format!(
+            "APT terminé: {}",
+            if apt_completed { "succès" } else { "avec récupération" }
+        )).await;
 
-    // IMPORTANT: Attendre que APT soit complètement libre avant Docker
-    // (évite "Could not get lock /var/lib/dpkg/lock-frontend")
-    emit_progress(&window, "docker", 14, "Attente fin des mises à jour...", None);
-    for wait_i in 0..60 {  // Max 5 minutes
-        let apt_free = ssh::execute_command_password(host, username, password,
-            "timeout 5 fuser /var/lib/dpkg/lock /var/lib/dpkg/lock-frontend /var/lib/apt/lists/lock /var/cache/apt/archives/lock 2>/dev/null; RC=$?; if [ $RC -eq 1 ] || [ $RC -eq 124 ]; then echo FREE; else echo LOCKED; fi"
-        ).await.unwrap_or_default();
+        // IMPORTANT: Attendre que APT soit complètement libre avant Docker
+        // (évite "Could not get lock /var/lib/dpkg/lock-frontend")
+        emit_progress(&window, "docker", 14, "Attente fin des mises à jour...", None);
+        for wait_i in 0..60 {  // Max 5 minutes
+            let apt_free = ssh::execute_command_password(host, username, password,
+                "timeout 5 fuser /var/lib/dpkg/lock /var/lib/dpkg/lock-frontend /var/lib/apt/lists/lock /var/cache/apt/archives/lock 2>/dev/null; RC=$?; if [ $RC -eq 1 ] || [ $RC -eq 124 ]; then echo FREE; else echo LOCKED; fi"
+            ).await.unwrap_or_default();
 
-        if apt_free.contains("FREE") {
-            println!("[Install] APT is free, proceeding with Docker install");
-            break;
-        }
-        println!("[Install] APT still locked (attempt {}/60), waiting 5s...", wait_i + 1);
-        if wait_i % 6 == 0 {
-            emit_progress(&window, "docker", 14, &format!("APT verrouillé, attente... (~{}s)", (60 - wait_i) * 5), None);
+            if apt_free.contains("FREE") {
+                tracing::info!("APT is free, proceeding with Docker install");
+                break;
+            }
+            tracing::debug!("APT still locked (attempt {}/60), waiting 5s...", wait_i + 1);
+            if wait_i % 6 == 0 {
+                emit_progress(&window, "docker", 14, &format!("APT verrouillé, attente... (~{}s)", (60 - wait_i) * 5), None);
+            }
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        state.complete(InstallStep::SystemUpdate).await;
     }
 
-    // Étape 2: Installation Docker
-    logger.start_step("docker_install").await;
-    emit_progress(&window, "docker", 15, "Vérification Docker...", None);
+    procedure.run_until(InstallStep::Docker).await?;
 
-    // Vérifier si Docker est déjà installé
-    let docker_check = ssh::execute_command_password(host, username, password, "docker --version 2>&1").await;
-    logger.log(LogLevel::Debug, "docker_install", &format!("Docker check: {:?}", docker_check)).await;
+    // Optimisation serveur sans écran (effective au redémarrage qui suit Docker)
+    if !state.is_done(InstallStep::Docker) {
+        if let Some(profile) = crate::headless::resolve_profile().await {
+            emit_progress(&window, "update", 15, "Optimisation du système (serveur sans écran)...", None);
+            if let Err(e) = crate::headless::optimize(&hook_target, &profile, &ssh::sudo_prefix(Some(password))).await {
+                tracing::warn!("⚠️ Optimization failed: {}", e);
+            }
+        }
+    }
 
-    let docker_output = docker_check.as_ref().map(|s| s.as_str()).unwrap_or("");
-    let docker_installed = docker_check.is_ok() && docker_output.contains("Docker");
-    println!("[Install] Docker installed: {}, output: '{}'", docker_installed, docker_output.trim());
+    if state.should_run(InstallStep::Docker) {
+        // Étape 2: Installation Docker
+        logger.start_step("docker_install").await;
+        emit_progress(&window, "docker", 15, "Vérification Docker...", None);
 
-    let mut needs_reboot = false;
+        // Vérifier si Docker est déjà installé
+        let docker_check = ssh::execute_command_password(host, username, password, "docker --version 2>&1").await;
+        logger.log(LogLevel::Debug, "docker_install", &format!("Docker check: {:?}", docker_check)).await;
 
-    if !docker_installed {
-        // Logger l'action
-        ssh::execute_command_password(host, username, password,
-            "echo \"$(date): Installing Docker...\" >> ~/jellysetup-logs/install.log"
-        ).await.ok();
+        let docker_output = docker_check.as_ref().map(|s| s.as_str()).unwrap_or("");
+        let docker_installed = docker_check.is_ok() && docker_output.contains("Docker");
+        tracing::info!("Docker installed: {}, output: '{}'", docker_installed, docker_output.trim());
 
-        let docker_cmd = format!(
-            "curl -fsSL https://get.docker.com -o /tmp/get-docker.sh && echo '{}' | sudo -S sh /tmp/get-docker.sh 2>&1 | tee -a ~/jellysetup-logs/docker-install.log && echo '{}' | sudo -S usermod -aG docker $USER",
-            password, password
-        );
-        match ssh::execute_command_password(host, username, password, &docker_cmd).await {
-            Ok(output) => {
-                println!("[Install] Docker install output: {}", &output[..output.len().min(500)]);
-                ssh::execute_command_password(host, username, password,
-                    "echo \"$(date): Docker install completed\" >> ~/jellysetup-logs/install.log"
-                ).await.ok();
-            }
-            Err(e) => {
-                let error_msg = format!("Docker install failed: {}", e);
-                println!("[Install] ERROR: {}", error_msg);
-                emit_progress(&window, "docker", 15, &format!("❌ Erreur: {}", e), None);
-                ssh::execute_command_password(host, username, password,
-                    &format!("echo \"$(date): ERROR - {}\" >> ~/jellysetup-logs/install.log", error_msg)
-                ).await.ok();
-                return Err(anyhow!(error_msg));
-            }
-        }
-        // Docker vient d'être installé, on doit rebooter pour le groupe docker
-        needs_reboot = true;
-    } else {
-        println!("[Install] Docker already installed, skipping");
-        ssh::execute_command_password(host, username, password,
-            "echo \"$(date): Docker already installed\" >> ~/jellysetup-logs/install.log"
-        ).await.ok();
+        let mut needs_reboot = false;
 
-        // Vérifier si l'utilisateur peut utiliser docker sans sudo (groupe docker appliqué)
-        let docker_test = ssh::execute_command_password(host, username, password,
-            "docker ps 2>&1"
-        ).await;
+        if !docker_installed {
+            // Logger l'action
+            ssh::execute_command_password(host, username, password,
+                "echo \"$(date): Installing Docker...\" >> ~/jellysetup-logs/install.log"
+            ).await.ok();
 
-        if let Ok(output) = &docker_test {
-            if output.contains("permission denied") || output.contains("Cannot connect") {
-                println!("[Install] User not in docker group yet, reboot needed");
-                needs_reboot = true;
-            } else {
-                println!("[Install] Docker works without sudo, no reboot needed");
-                needs_reboot = false;
+            let docker_cmd = format!(
+                "curl -fsSL {} -o /tmp/get-docker.sh && echo '{}' | sudo -S sh /tmp/get-docker.sh 2>&1 | tee -a ~/jellysetup-logs/docker-install.log && echo '{}' | sudo -S usermod -aG docker $USER",
+                crate::config::urls::DOCKER_INSTALL_SCRIPT, password, password
+            );
+            match ssh::execute_command_password(host, username, password, &docker_cmd).await {
+                Ok(output) => {
+                    tracing::info!("Docker install output: {}", &output[..output.len().min(500)]);
+                    ssh::execute_command_password(host, username, password,
+                        "echo \"$(date): Docker install completed\" >> ~/jellysetup-logs/install.log"
+                    ).await.ok();
+                }
+                Err(e) => {
+                    let error_msg = format!("Docker install failed: {}", e);
+                    tracing::error!("ERROR: {}", error_msg);
+                    emit_progress(&window, "docker", 15, &format!("❌ Erreur: {}", e), None);
+                    ssh::execute_command_password(host, username, password,
+                        &format!("echo \"$(date): ERROR - {}\" >> ~/jellysetup-logs/install.log", error_msg)
+                    ).await.ok();
+                    return Err(anyhow!(error_msg));
+                }
             }
-        } else {
-            println!("[Install] Docker test failed, reboot to be safe");
+            // Docker vient d'être installé, on doit rebooter pour le groupe docker
             needs_reboot = true;
-        }
-    }
+        } else {
+            tracing::info!("Docker already installed, skipping");
+            ssh::execute_command_password(host, username, password,
+                "echo \"$(date): Docker already installed\" >> ~/jellysetup-logs/install.log"
+            ).await.ok();
 
-    // Étape 3: Redémarrage pour appliquer groupe docker (seulement si nécessaire)
-    if needs_reboot {
-        println!("[Install] ========== REBOOT ==========");
-        emit_progress(&window, "reboot", 30, "Redémarrage...", None);
-        ssh::execute_command_password(host, username, password,
-            "echo \"$(date): Rebooting to apply docker group...\" >> ~/jellysetup-logs/install.log"
-        ).await.ok();
-        let reboot_cmd = format!("echo '{}' | sudo -S reboot", password);
-        ssh::execute_command_password(host, username, password, &reboot_cmd).await.ok();
-        println!("[Install] Reboot command sent, waiting 45s...");
-        tokio::time::sleep(std::time::Duration::from_secs(45)).await;
+            // Vérifier si l'utilisateur peut utiliser docker sans sudo (groupe docker appliqué)
+            let docker_test = ssh::execute_command_password(host, username, password,
+                "docker ps 2>&1"
+            ).await;
 
-        // Attendre que le Pi soit de nouveau accessible
-        println!("[Install] Waiting for Pi to come back online...");
-        let mut pi_back = false;
-        for i in 0..30 {
-            match ssh::execute_command_password(host, username, password, "echo ok").await {
-                Ok(_) => {
-                    println!("[Install] Pi is back online after {} attempts", i + 1);
-                    pi_back = true;
-                    break;
-                }
-                Err(e) => {
-                    println!("[Install] Pi not yet responding (attempt {}/30): {}", i + 1, e);
+            if let Ok(output) = &docker_test {
+                if output.contains("permission denied") || output.contains("Cannot connect") {
+                    tracing::info!("User not in docker group yet, reboot needed");
+                    needs_reboot = true;
+                } else {
+                    tracing::info!("Docker works without sudo, no reboot needed");
+                    needs_reboot = false;
                 }
+            } else {
+                tracing::warn!("Docker test failed, reboot to be safe");
+                needs_reboot = true;
             }
-            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
         }
-        if !pi_back {
-            return Err(anyhow!("Pi not responding after reboot (30 attempts)"));
+
+        // Étape 3: Redémarrage pour appliquer groupe docker (seulement si nécessaire)
+        if needs_reboot {
+            tracing::info!("========== REBOOT ==========");
+            emit_progress(&window, "reboot", 30, "Redémarrage...", None);
+            ssh::execute_command_password(host, username, password,
+                "echo \"$(date): Rebooting to apply docker group...\" >> ~/jellysetup-logs/install.log"
+            ).await.ok();
+            let reboot_cmd = format!("echo '{}' | sudo -S reboot", password);
+            ssh::execute_command_password(host, username, password, &reboot_cmd).await.ok();
+            tracing::debug!("Reboot command sent, waiting for Pi to come back online...");
+            wait_for_reboot_password(&window, host, username, password, 30, config.reboot_timeout()).await?;
+        } else {
+            tracing::info!("Skipping reboot - Docker already working");
+            emit_progress(&window, "reboot", 30, "Reboot non nécessaire", None);
         }
-    } else {
-        println!("[Install] Skipping reboot - Docker already working");
-        emit_progress(&window, "reboot", 30, "Reboot non nécessaire", None);
-    }
 
-    // Vérifier que Docker est bien installé après le reboot
-    println!("[Install] Checking Docker after reboot...");
-    let docker_verify = ssh::execute_command_password(host, username, password, "docker --version 2>&1").await;
-    println!("[Install] Docker verify result: {:?}", docker_verify);
+        // Vérifier que Docker est bien installé après le reboot
+        tracing::debug!("Checking Docker after reboot...");
+        let docker_verify = ssh::execute_command_password(host, username, password, "docker --version 2>&1").await;
+        tracing::info!("Docker verify result: {:?}", docker_verify);
 
-    let docker_verify_output = docker_verify.as_ref().map(|s| s.as_str()).unwrap_or("");
-    let docker_ok_after_reboot = docker_verify.is_ok() && docker_verify_output.contains("Docker");
-    println!("[Install] Docker OK after reboot: {}", docker_ok_after_reboot);
+        let docker_verify_output = docker_verify.as_ref().map(|s| s.as_str()).unwrap_or("");
+        let docker_ok_after_reboot = docker_verify.is_ok() && docker_verify_output.contains("Docker");
+        tracing::info!("Docker OK after reboot: {}", docker_ok_after_reboot);
 
-    if !docker_ok_after_reboot {
-        // Docker pas installé, réessayer
-        println!("[Install] Docker not found after reboot, attempting 2nd installation...");
-        emit_progress(&window, "docker", 20, "Installation Docker (2ème tentative)...", None);
-        ssh::execute_command_password(host, username, password,
-            "echo \"$(date): Docker not found after reboot, retrying...\" >> ~/jellysetup-logs/install.log"
-        ).await.ok();
+        if !docker_ok_after_reboot {
+            // Docker pas installé, réessayer
+            tracing::debug!("Docker not found after reboot, attempting 2nd installation...");
+            emit_progress(&window, "docker", 20, "Installation Docker (2ème tentative)...", None);
+            ssh::execute_command_password(host, username, password,
+                "echo \"$(date): Docker not found after reboot, retrying...\" >> ~/jellysetup-logs/install.log"
+            ).await.ok();
 
-        // IMPORTANT: Attendre que APT soit libre avant 2ème tentative
-        for wait_i in 0..60 {
-            let apt_free = ssh::execute_command_password(host, username, password,
-                "timeout 5 fuser /var/lib/dpkg/lock /var/lib/dpkg/lock-frontend /var/lib/apt/lists/lock /var/cache/apt/archives/lock 2>/dev/null; RC=$?; if [ $RC -eq 1 ] || [ $RC -eq 124 ]; then echo FREE; else echo LOCKED; fi"
-            ).await.unwrap_or_default();
-            if apt_free.contains("FREE") {
-                println!("[Install] APT is free for Docker retry");
-                break;
+            // IMPORTANT: Attendre que APT soit libre avant 2ème tentative
+            for wait_i in 0..60 {
+                let apt_free = ssh::execute_command_password(host, username, password,
+                    "timeout 5 fuser /var/lib/dpkg/lock /var/lib/dpkg/lock-frontend /var/lib/apt/lists/lock /var/cache/apt/archives/lock 2>/dev/null; RC=$?; if [ $RC -eq 1 ] || [ $RC -eq 124 ]; then echo FREE; else echo LOCKED; fi"
+                ).await.unwrap_or_default();
+                if apt_free.contains("FREE") {
+                    tracing::info!("APT is free for Docker retry");
+                    break;
+                }
+                tracing::info!("APT still locked before Docker retry (attempt {}/60)", wait_i + 1);
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
             }
-            println!("[Install] APT still locked before Docker retry (attempt {}/60)", wait_i + 1);
-            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-        }
 
-        let docker_cmd = format!(
-            "curl -fsSL https://get.docker.com -o /tmp/get-docker.sh && echo '{}' | sudo -S sh /tmp/get-docker.sh 2>&1 | tee -a ~/jellysetup-logs/docker-install-retry.log && echo '{}' | sudo -S usermod -aG docker $USER",
-            password, password
-        );
-        ssh::execute_command_password(host, username, password, &docker_cmd).await?;
+            let docker_cmd = format!(
+                "curl -fsSL {} -o /tmp/get-docker.sh && echo '{}' | sudo -S sh /tmp/get-docker.sh 2>&1 | tee -a ~/jellysetup-logs/docker-install-retry.log && echo '{}' | sudo -S usermod -aG docker $USER",
+                crate::config::urls::DOCKER_INSTALL_SCRIPT, password, password
+            );
+            ssh::execute_command_password(host, username, password, &docker_cmd).await?;
 
-        // Nouveau reboot après install Docker
-        let reboot_cmd = format!("echo '{}' | sudo -S reboot", password);
-        ssh::execute_command_password(host, username, password, &reboot_cmd).await.ok();
-        tokio::time::sleep(std::time::Duration::from_secs(45)).await;
+            // Nouveau reboot après install Docker
+            let reboot_cmd = format!("echo '{}' | sudo -S reboot", password);
+            ssh::execute_command_password(host, username, password, &reboot_cmd).await.ok();
 
-        // Attendre le Pi
-        for i in 0..30 {
-            if ssh::execute_command_password(host, username, password, "echo ok").await.is_ok() {
-                break;
-            }
-            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            if i == 29 {
-                return Err(anyhow!("Pi not responding after Docker reboot"));
-            }
+            // Attendre le Pi
+            wait_for_reboot_password(&window, host, username, password, 30, config.reboot_timeout()).await?;
         }
-    }
 
-    // VÉRIFICATION FINALE OBLIGATOIRE: Docker DOIT être installé avant de continuer
-    println!("[Install] ========== DOCKER FINAL VERIFICATION ==========");
-    emit_progress(&window, "docker", 35, "Vérification Docker...", None);
-    let final_docker_check = ssh::execute_command_password(host, username, password,
-        "docker --version 2>&1 && docker compose version 2>&1"
-    ).await;
+        // VÉRIFICATION FINALE OBLIGATOIRE: Docker DOIT être installé avant de continuer
+        tracing::info!("========== DOCKER FINAL VERIFICATION ==========");
+        emit_progress(&window, "docker", 35, "Vérification Docker...", None);
+        let final_docker_check = ssh::execute_command_password(host, username, password,
+            "docker --version 2>&1 && docker compose version 2>&1"
+        ).await;
 
-    println!("[Install] Final Docker check result: {:?}", final_docker_check);
+        tracing::info!("Final Docker check result: {:?}", final_docker_check);
 
-    match &final_docker_check {
-        Ok(output) if output.contains("Docker") && output.contains("Docker Compose") => {
-            println!("[Install] ✅ Docker et Docker Compose vérifiés: {}", output.lines().next().unwrap_or(""));
-            ssh::execute_command_password(host, username, password,
-                &format!("echo \"$(date): Docker verified - {}\" >> ~/jellysetup-logs/install.log",
-                    output.lines().next().unwrap_or("ok").replace('"', "'"))
-            ).await.ok();
-        }
-        Ok(output) => {
-            // Docker check returned but doesn't contain expected strings
-            println!("[Install] ❌ Docker check returned unexpected output: '{}'", output);
-            let error_msg = format!("❌ FATAL: Docker n'est pas installé correctement. Output: {}", output.chars().take(200).collect::<String>());
-            emit_progress(&window, "docker", 35, "❌ Docker non installé", None);
-            ssh::execute_command_password(host, username, password,
-                &format!("echo \"$(date): FATAL ERROR - Docker check failed: {}\" >> ~/jellysetup-logs/install.log",
-                    output.chars().take(100).collect::<String>().replace('"', "'"))
-            ).await.ok();
-            return Err(anyhow!(error_msg));
-        }
-        Err(e) => {
-            println!("[Install] ❌ Docker check failed with error: {}", e);
-            let error_msg = format!("❌ FATAL: Docker n'est pas installé. Erreur SSH: {}", e);
-            emit_progress(&window, "docker", 35, "❌ Docker non installé", None);
-            ssh::execute_command_password(host, username, password,
-                &format!("echo \"$(date): FATAL ERROR - Docker not installed, SSH error\" >> ~/jellysetup-logs/install.log")
-            ).await.ok();
-            return Err(anyhow!(error_msg));
+        match &final_docker_check {
+            Ok(output) if output.contains("Docker") && output.contains("Docker Compose") => {
+                tracing::info!("✅ Docker et Docker Compose vérifiés: {}", output.lines().next().unwrap_or(""));
+                ssh::execute_command_password(host, username, password,
+                    &format!("echo \"$(date): Docker verified - {}\" >> ~/jellysetup-logs/install.log",
+                        output.lines().next().unwrap_or("ok").replace('"', "'"))
+                ).await.ok();
+            }
+            Ok(output) => {
+                // Docker check returned but doesn't contain expected strings
+                tracing::error!("❌ Docker check returned unexpected output: '{}'", output);
+                let error_msg = format!("❌ FATAL: Docker n'est pas installé correctement. Output: {}", output.chars().take(200).collect::<String>());
+                emit_progress(&window, "docker", 35, "❌ Docker non installé", None);
+                ssh::execute_command_password(host, username, password,
+                    &format!("echo \"$(date): FATAL ERROR - Docker check failed: {}\" >> ~/jellysetup-logs/install.log",
+                        output.chars().take(100).collect::<String>().replace('"', "'"))
+                ).await.ok();
+                return Err(anyhow!(error_msg));
+            }
+            Err(e) => {
+                tracing::error!("❌ Docker check failed with error: {}", e);
+                let error_msg = format!("❌ FATAL: Docker n'est pas installé. Erreur SSH: {}", e);
+                emit_progress(&window, "docker", 35, "❌ Docker non installé", None);
+                ssh::execute_command_password(host, username, password,
+                    &format!("echo \"$(date): FATAL ERROR - Docker not installed, SSH error\" >> ~/jellysetup-logs/install.log")
+                ).await.ok();
+                return Err(anyhow!(error_msg));
+            }
         }
-    }
 
-    println!("[Install] ========== DOCKER OK - CONTINUING ==========");
+        tracing::info!("========== DOCKER OK - CONTINUING ==========");
+        state.complete(InstallStep::Docker).await;
+    }
 
+    // Étape rapide et idempotente: toujours rejouée lors d'une reprise
     // Étape 4: Création de la structure (y compris les dossiers media)
+    procedure.run_until(InstallStep::Structure).await?;
+    state.begin(InstallStep::Structure);
     emit_progress(&window, "structure", 40, "Création structure...", None);
-    let mkdir_cmd = format!(
-        "mkdir -p ~/media-stack/{{decypharr,jellyfin,radarr,sonarr,prowlarr,jellyseerr,bazarr,logs}} && \
-         echo '{}' | sudo -S mkdir -p /mnt/decypharr/{{movies,tv,qbit/downloads}} && \
-         echo '{}' | sudo -S chown -R $USER:$USER /mnt/decypharr",
-        password, password
-    );
+    let mkdir_cmd = structure_command(&media_paths, &ssh::sudo_prefix(Some(password)));
     ssh::execute_command_password(host, username, password, &mkdir_cmd).await?;
+    crate::master_config::save_installed_media_paths(&hook_target, &media_paths).await?;
+    state.complete(InstallStep::Structure).await;
 
     // Étape 5: Écrire le docker-compose.yml
-    emit_progress(&window, "compose_write", 50, "Génération docker-compose.yml...", None);
-    let write_cmd = format!("cat > ~/media-stack/docker-compose.yml << 'EOFCOMPOSE'\n{}\nEOFCOMPOSE", docker_compose);
-    ssh::execute_command_password(host, username, password, &write_cmd).await?;
+    procedure.run_until(InstallStep::Compose).await?;
+    if state.should_run(InstallStep::Compose) {
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Compose, HookPhase::Pre).await?;
+        emit_progress(&window, "compose_write", 50, "Génération docker-compose.yml...", None);
+        let write_cmd = format!("cat > ~/media-stack/docker-compose.yml << 'EOFCOMPOSE'\n{}\nEOFCOMPOSE", docker_compose);
+        ssh::execute_command_password(host, username, password, &write_cmd).await?;
+        ssh::execute_command_password(host, username, password, &crate::status::write_script_command(&docker_compose)).await?;
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Compose, HookPhase::Post).await?;
+        state.complete(InstallStep::Compose).await;
+    }
 
     // Étape 6: Démarrer les services (en background car pull peut être très long)
     emit_progress(&window, "compose_up", 60, "Téléchargement des images Docker (peut prendre 10-20 min)...", None);
@@ -2343,745 +2704,489 @@ pub async fn run_full_installation_password(
         return Err(anyhow!(error_msg));
     }
 
-    // Docker compose pull avec retry automatique en cas d'échec réseau
-    let mut pull_attempt = 0;
-    let max_pull_attempts = 3;
-
-    'pull_loop: loop {
-        pull_attempt += 1;
-        if pull_attempt > max_pull_attempts {
-            let error_msg = format!("Docker pull échoué après {} tentatives", max_pull_attempts);
-            emit_progress(&window, "compose_up", 60, &format!("❌ {}", error_msg), None);
-            return Err(anyhow!(error_msg));
+    procedure.run_until(InstallStep::ImagePull).await?;
+    if !state.is_done(InstallStep::ImagePull) {
+        match crate::image_cache::load_preloaded(&hook_target, &hook_target.sudo()).await {
+            Ok(Some(missing)) if missing.is_empty() => {
+                emit_progress(&window, "compose_up", 65, "Images Docker chargées depuis la carte SD", None);
+                state.begin(InstallStep::ImagePull);
+                state.complete(InstallStep::ImagePull).await;
+            }
+            Ok(Some(missing)) => {
+                tracing::info!("{} images missing from the SD card archive, pulling them", missing.len());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("⚠️ Preloaded images not loaded, pulling instead: {}", e),
         }
+    }
 
-        // Logger et lancer docker compose pull
-        ssh::execute_command_password(host, username, password,
-            &format!("echo \"$(date): Starting docker compose pull (attempt {}/{})...\" >> ~/jellysetup-logs/install.log", pull_attempt, max_pull_attempts)
-        ).await.ok();
+    if state.should_run(InstallStep::ImagePull) {
+        // Docker compose pull avec retry automatique en cas d'échec réseau
+        let mut pull_attempt = 0;
+        let max_pull_attempts = crate::config::settings().image_pull_attempts;
 
-        emit_progress(&window, "compose_up", 60, &format!("Téléchargement images (tentative {}/{})...", pull_attempt, max_pull_attempts), None);
+        'pull_loop: loop {
+            pull_attempt += 1;
+            if pull_attempt > max_pull_attempts {
+                let error_msg = format!("Docker pull échoué après {} tentatives", max_pull_attempts);
+                emit_progress(&window, "compose_up", 60, &format!("❌ {}", error_msg), None);
+                return Err(anyhow!(error_msg));
+            }
 
-        // Lancer docker compose pull avec fichier marker de fin (évite le bug pgrep/nohup)
-        ssh::execute_command_password(host, username, password,
-            "rm -f /tmp/docker_pull_done /tmp/docker_pull_failed && cd ~/media-stack && (docker compose pull > ~/jellysetup-logs/docker_pull.log 2>&1 && touch /tmp/docker_pull_done || touch /tmp/docker_pull_failed) &"
-        ).await?;
+            // Logger et lancer docker compose pull
+            ssh::execute_command_password(host, username, password,
+                &format!("echo \"$(date): Starting docker compose pull (attempt {}/{})...\" >> ~/jellysetup-logs/install.log", pull_attempt, max_pull_attempts)
+            ).await.ok();
 
-        // Attendre que le pull soit terminé (max 25 min par tentative)
-        for i in 0..150 {
-            tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+            emit_progress(&window, "compose_up", 60, &format!("Téléchargement images (tentative {}/{})...", pull_attempt, max_pull_attempts), None);
 
-            // Vérifier via fichiers markers (plus fiable que pgrep)
-            match ssh::execute_command_password(host, username, password,
-                "if [ -f /tmp/docker_pull_done ]; then echo DONE; elif [ -f /tmp/docker_pull_failed ]; then echo FAILED; elif grep -qi 'failed\\|error\\|timeout' ~/jellysetup-logs/docker_pull.log 2>/dev/null; then echo FAILED; else echo RUNNING; fi"
-            ).await {
-                Ok(output) => {
-                    let output = output.trim();
-                    if output.contains("DONE") {
-                        println!("[Install] Docker pull marker found, quick validation...");
+            // Lancer docker compose pull avec fichier marker de fin (évite le bug pgrep/nohup)
+            ssh::execute_command_password(host, username, password,
+                "rm -f /tmp/docker_pull_done /tmp/docker_pull_failed && cd ~/media-stack && (docker compose pull > ~/jellysetup-logs/docker_pull.log 2>&1 && touch /tmp/docker_pull_done || touch /tmp/docker_pull_failed) &"
+            ).await?;
 
-                        // VÉRIFICATION RAPIDE: Valider que docker-compose.yml est OK (2-5s au lieu de 60s+)
-                        let compose_check = ssh::execute_command_password(host, username, password,
-                            "cd ~/media-stack && docker compose config >/dev/null 2>&1 && echo OK || echo FAILED"
-                        ).await.unwrap_or_default();
+            // Attendre que le pull soit terminé (max 25 min par tentative)
+            for i in 0..150 {
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::PULL_POLL_SECS)).await?;
+
+                // Vérifier via fichiers markers (plus fiable que pgrep)
+                match ssh::execute_command_password(host, username, password,
+                    "if [ -f /tmp/docker_pull_done ]; then echo DONE; elif [ -f /tmp/docker_pull_failed ]; then echo FAILED; elif grep -qi 'failed\\|error\\|timeout' ~/jellysetup-logs/docker_pull.log 2>/dev/null; then echo FAILED; else echo RUNNING; fi"
+                ).await {
+                    Ok(output) => {
+                        let output = output.trim();
+                        if output.contains("DONE") {
+                            tracing::debug!("Docker pull marker found, quick validation...");
+
+                            // VÉRIFICATION RAPIDE: Valider que docker-compose.yml est OK (2-5s au lieu de 60s+)
+                            let compose_check = ssh::execute_command_password(host, username, password,
+                                "cd ~/media-stack && docker compose config >/dev/null 2>&1 && echo OK || echo FAILED"
+                            ).await.unwrap_or_default();
+
+                            if compose_check.trim() != "OK" {
+                                tracing::debug!("Docker compose config validation failed! Will retry pull...");
+                                ssh::execute_command_password(host, username, password,
+                                    "echo \"$(date): Docker compose config validation failed, retrying pull...\" >> ~/jellysetup-logs/install.log"
+                                ).await.ok();
+                                ssh::execute_command_password(host, username, password,
+                                    "rm -f /tmp/docker_pull_done"
+                                ).await.ok();
+                                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::SETTLE_SECS)).await?;
+                                continue 'pull_loop;  // Réessayer
+                            }
 
-                        if compose_check.trim() != "OK" {
-                            println!("[Install] Docker compose config validation failed! Will retry pull...");
+                            tracing::info!("Docker compose validated successfully!");
                             ssh::execute_command_password(host, username, password,
-                                "echo \"$(date): Docker compose config validation failed, retrying pull...\" >> ~/jellysetup-logs/install.log"
+                                "echo \"$(date): Docker pull completed and verified - all images present\" >> ~/jellysetup-logs/install.log"
                             ).await.ok();
+                            break 'pull_loop;  // Succès, sortir de la boucle principale
+                        } else if output.contains("FAILED") {
+                            tracing::debug!("Docker pull failed, will retry...");
                             ssh::execute_command_password(host, username, password,
-                                "rm -f /tmp/docker_pull_done"
+                                "echo \"$(date): Docker pull FAILED - retrying...\" >> ~/jellysetup-logs/install.log"
                             ).await.ok();
-                            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+                            // Attendre 10s avant de réessayer
+                            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::LONG_POLL_SECS)).await?;
                             continue 'pull_loop;  // Réessayer
                         }
-
-                        println!("[Install] Docker compose validated successfully!");
-                        ssh::execute_command_password(host, username, password,
-                            "echo \"$(date): Docker pull completed and verified - all images present\" >> ~/jellysetup-logs/install.log"
-                        ).await.ok();
-                        break 'pull_loop;  // Succès, sortir de la boucle principale
-                    } else if output.contains("FAILED") {
-                        println!("[Install] Docker pull failed, will retry...");
-                        ssh::execute_command_password(host, username, password,
-                            "echo \"$(date): Docker pull FAILED - retrying...\" >> ~/jellysetup-logs/install.log"
-                        ).await.ok();
-                        // Attendre 10s avant de réessayer
-                        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-                        continue 'pull_loop;  // Réessayer
+                        // RUNNING - progression lue dans le log du pull (images et couches terminées)
+                        let pull_log = ssh::execute_command_password(host, username, password,
+                            "grep -E 'Pull complete|Already exists|Download complete|Pulling fs layer|Pulled|Skipped' ~/jellysetup-logs/docker_pull.log 2>/dev/null"
+                        ).await.unwrap_or_default();
+                        let pull = PullProgress::from_log(compose_image_count(&docker_compose), &pull_log);
+                        emit_progress(&window, "compose_up", sub_percent(60, 74, pull.fraction()),
+                            &format!("{} (~{}min)", pull.message(), (150 - i) / 6), None);
+                    }
+                    Err(_) => {
+                        tracing::debug!("SSH check failed, retrying...");
                     }
-                    // RUNNING - afficher progression
-                    let progress = 60 + (i as u32 * 10 / 150).min(14);
-                    emit_progress(&window, "compose_up", progress,
-                        &format!("Téléchargement images... (~{}min)", (150 - i) / 6), None);
-                }
-                Err(_) => {
-                    println!("[Install] SSH check failed, retrying...");
                 }
             }
-        }
 
-        // Timeout atteint sans succès ni échec détecté - considérer comme échec
-        println!("[Install] Docker pull timeout, will retry...");
+            // Timeout atteint sans succès ni échec détecté - considérer comme échec
+            tracing::debug!("Docker pull timeout, will retry...");
+        }
+        state.complete(InstallStep::ImagePull).await;
     }
 
     // Lancer docker compose up - ÉTAPE CRITIQUE
-    logger.start_step("docker_compose_up").await;
-    emit_progress(&window, "compose_up", 74, "Démarrage des conteneurs...", None);
-
-    // Lancer en background car ça peut prendre 10+ minutes
-    ssh::execute_command_password(host, username, password,
-        "cd ~/media-stack && nohup docker compose up -d > /tmp/compose_up.log 2>&1 &"
-    ).await?;
-
-    // Attendre que la commande soit terminée (vérifier le fichier de lock Docker)
-    println!("[Install] Waiting for docker compose up to complete...");
-    for i in 0..60 {  // Max 10 minutes (60 * 10s = 600s)
-        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-
-        emit_progress(&window, "compose_up", 74 + (i as u32 / 3),
-            &format!("Démarrage des conteneurs... ({}s)", i * 10), None);
-
-        // Vérifier si docker compose est terminé en checkant les containers
-        match ssh::execute_command_password(host, username, password,
-            "docker ps --format '{{.Names}}' 2>/dev/null | grep -E 'jellyfin|radarr|sonarr' | wc -l"
-        ).await {
-            Ok(output) => {
-                if let Ok(count) = output.trim().parse::<i32>() {
-                    if count >= 3 {
-                        println!("[Install] Docker compose up completed ({} containers running)", count);
-                        break;
+    procedure.run_until(InstallStep::Containers).await?;
+    if state.should_run(InstallStep::Containers) {
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Containers, HookPhase::Pre).await?;
+        logger.start_step("docker_compose_up").await;
+        emit_progress(&window, "compose_up", 74, "Démarrage des conteneurs...", None);
+
+        // Lancer en background car ça peut prendre 10+ minutes
+        ssh::execute_command_password(host, username, password,
+            "cd ~/media-stack && nohup docker compose up -d > /tmp/compose_up.log 2>&1 &"
+        ).await?;
+
+        // Attendre que la commande soit terminée (vérifier le fichier de lock Docker)
+        tracing::debug!("Waiting for docker compose up to complete...");
+        for i in 0..60 {  // Max 10 minutes (60 * 10s = 600s)
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::LONG_POLL_SECS)).await?;
+
+            emit_progress(&window, "compose_up", 74 + (i as u32 / 3),
+                &format!("Démarrage des conteneurs... ({}s)", i * 10), None);
+
+            // Vérifier si docker compose est terminé en checkant les containers
+            match ssh::execute_command_password(host, username, password,
+                "docker ps --format '{{.Names}}' 2>/dev/null | grep -E 'jellyfin|radarr|sonarr' | wc -l"
+            ).await {
+                Ok(output) => {
+                    if let Ok(count) = output.trim().parse::<i32>() {
+                        if count >= 3 {
+                            tracing::info!("Docker compose up completed ({} containers running)", count);
+                            break;
+                        }
                     }
                 }
+                Err(_) => {}
             }
-            Err(_) => {}
         }
-    }
 
-    let compose_up_result = ssh::execute_command_password(host, username, password,
-        "cat /tmp/compose_up.log 2>/dev/null || echo 'Log not found'"
-    ).await;
+        let compose_up_result = ssh::execute_command_password(host, username, password,
+            "cat /tmp/compose_up.log 2>/dev/null || echo 'Log not found'"
+        ).await;
 
-    let compose_up_success = compose_up_result.is_ok();
+        let compose_up_success = compose_up_result.is_ok();
 
-    match &compose_up_result {
-        Ok(output) => {
-            // Vérifier si la sortie contient des erreurs même si la commande SSH a réussi
-            let output_lower = output.to_lowercase();
-            let has_error = output_lower.contains("error") ||
-                           output_lower.contains("failed") ||
-                           output_lower.contains("cannot") ||
-                           output_lower.contains("permission denied");
+        match &compose_up_result {
+            Ok(output) => {
+                // Vérifier si la sortie contient des erreurs même si la commande SSH a réussi
+                let output_lower = output.to_lowercase();
+                let has_error = output_lower.contains("error") ||
+                               output_lower.contains("failed") ||
+                               output_lower.contains("cannot") ||
+                               output_lower.contains("permission denied");
+
+                if has_error {
+                    logger.log_error(
+                        "docker_compose_up",
+                        "docker compose up -d a retourné des erreurs !",
+                        Some(serde_json::json!({
+                            "output": output.chars().take(1000).collect::<String>(),
+                            "command": "cd ~/media-stack && docker compose up -d",
+                            "error_detected": true
+                        }))
+                    ).await;
+                    return Err(anyhow::anyhow!("Docker compose up a échoué: {}", output.chars().take(500).collect::<String>()));
+                }
 
-            if has_error {
-                logger.log_error(
+                logger.log_with_details(
+                    LogLevel::Success,
                     "docker_compose_up",
-                    "docker compose up -d a retourné des erreurs !",
-                    Some(serde_json::json!({
-                        "output": output.chars().take(1000).collect::<String>(),
-                        "command": "cd ~/media-stack && docker compose up -d",
-                        "error_detected": true
-                    }))
+                    "docker compose up -d exécuté avec succès",
+                    serde_json::json!({
+                        "output": output.chars().take(500).collect::<String>(),
+                        "command": "cd ~/media-stack && docker compose up -d"
+                    })
                 ).await;
-                return Err(anyhow::anyhow!("Docker compose up a échoué: {}", output.chars().take(500).collect::<String>()));
             }
-
-            logger.log_with_details(
-                LogLevel::Success,
-                "docker_compose_up",
-                "docker compose up -d exécuté avec succès",
-                serde_json::json!({
-                    "output": output.chars().take(500).collect::<String>(),
+            Err(e) => {
+                logger.log_error("docker_compose_up", &format!("docker compose up -d FAILED: {}", e), Some(serde_json::json!({
+                    "error": e.to_string(),
                     "command": "cd ~/media-stack && docker compose up -d"
-                })
-            ).await;
-        }
-        Err(e) => {
-            logger.log_error("docker_compose_up", &format!("docker compose up -d FAILED: {}", e), Some(serde_json::json!({
-                "error": e.to_string(),
-                "command": "cd ~/media-stack && docker compose up -d"
-            }))).await;
-            return Err(anyhow::anyhow!("Docker compose up a échoué: {}", e));
+                }))).await;
+                return Err(anyhow::anyhow!("Docker compose up a échoué: {}", e));
+            }
         }
-    }
-
-    // VÉRIFICATION CRITIQUE: S'assurer que les containers tournent VRAIMENT
-    // Attendre un peu pour que les containers démarrent
-    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-
-    let containers_check = ssh::execute_command_password(host, username, password,
-        "docker ps --format '{{.Names}}: {{.Status}}' 2>&1"
-    ).await.unwrap_or_default();
-
-    let container_count = ssh::execute_command_password(host, username, password,
-        "docker ps -q | wc -l"
-    ).await.unwrap_or_default().trim().parse::<i32>().unwrap_or(0);
-
-    logger.log_with_details(
-        LogLevel::Info,
-        "docker_compose_up",
-        &format!("État des conteneurs après démarrage: {} containers actifs", container_count),
-        serde_json::json!({
-            "containers": containers_check.trim(),
-            "container_count": container_count
-        })
-    ).await;
 
-    // VÉRIFICATION STRICTE: On attend 9 containers minimum (10 avec Cloudflare)
-    let expected_min_containers = 9; // decypharr, jellyfin, radarr, sonarr, prowlarr, jellyseerr, bazarr, flaresolverr, supabazarr
+        // VÉRIFICATION CRITIQUE: S'assurer que les containers tournent VRAIMENT
+        // Attendre un peu pour que les containers démarrent
+        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
 
-    if container_count < expected_min_containers {
-        // Récupérer les logs docker compose pour debug
-        let compose_logs = ssh::execute_command_password(host, username, password,
-            "cd ~/media-stack && docker compose logs --tail=50 2>&1"
+        let containers_check = ssh::execute_command_password(host, username, password,
+            "docker ps --format '{{.Names}}: {{.Status}}' 2>&1"
         ).await.unwrap_or_default();
 
-        logger.log_error(
+        let container_count = ssh::execute_command_password(host, username, password,
+            "docker ps -q | wc -l"
+        ).await.unwrap_or_default().trim().parse::<i32>().unwrap_or(0);
+
+        logger.log_with_details(
+            LogLevel::Info,
             "docker_compose_up",
-            &format!("ERREUR CRITIQUE: Seulement {} containers sur {} attendus !", container_count, expected_min_containers),
-            Some(serde_json::json!({
-                "docker_ps_output": containers_check.trim(),
-                "expected_minimum": expected_min_containers,
-                "actual": container_count,
-                "compose_logs": compose_logs.chars().take(2000).collect::<String>()
-            }))
+            &format!("État des conteneurs après démarrage: {} containers actifs", container_count),
+            serde_json::json!({
+                "containers": containers_check.trim(),
+                "container_count": container_count
+            })
         ).await;
-        logger.end_step("docker_compose_up", false).await;
-
-        // Lister les images manquantes pour aider au debug
-        let missing_images = ssh::execute_command_password(host, username, password,
-            "cd ~/media-stack && docker compose config --images 2>/dev/null"
-        ).await.unwrap_or_default();
-
-        return Err(anyhow::anyhow!(
-            "Docker compose up a échoué: seulement {} containers sur {} attendus. Images requises: {}",
-            container_count, expected_min_containers, missing_images.trim()
-        ));
-    }
 
-    logger.end_step("docker_compose_up", true).await;
+        // VÉRIFICATION STRICTE: services requis + optionnels sélectionnés (hors Cloudflare)
+        // decypharr, jellyfin, radarr, sonarr, prowlarr, jellyseerr, bazarr, flaresolverr, supabazarr
+        let expected_min_containers = (crate::services::REQUIRED_SERVICES.len()
+            + crate::services::OPTIONAL_SERVICES.iter().filter(|s| config.service_enabled(s)).count()) as i32;
 
-    // Étape 7: Attendre que les services soient prêts
-    emit_progress(&window, "wait_services", 75, "Attente des services...", None);
-    tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        if container_count < expected_min_containers {
+            // Récupérer les logs docker compose pour debug
+            let compose_logs = ssh::execute_command_password(host, username, password,
+                "cd ~/media-stack && docker compose logs --tail=50 2>&1"
+            ).await.unwrap_or_default();
 
-    // Étape 8: Configuration des services via API
-    emit_progress(&window, "config", 85, "Configuration des services...", None);
-
-    // 8.1: Reset Jellyfin MAIS préserver le ServerId pour éviter "Incompatibilité du serveur"
-    emit_progress(&window, "config", 86, "Reset Jellyfin pour config propre...", None);
-    debug_log("[JELLYFIN] === Reset de Jellyfin avec préservation ServerId ===");
-
-    // 1. Sauvegarder TOUS les fichiers d'identité serveur
-    // Le ServerId peut être dans device.txt, deviceid, ou system.xml selon la version
-    let backup_cmd = ssh::execute_command_password(host, username, password,
-        "cd ~/media-stack && mkdir -p /tmp/jellyfin-backup && \
-         find jellyfin -name 'device*' -type f -exec cp {} /tmp/jellyfin-backup/ \\; 2>/dev/null || true && \
-         find jellyfin -name 'system.xml' -type f -exec cp {} /tmp/jellyfin-backup/ \\; 2>/dev/null || true && \
-         find jellyfin -name 'network.xml' -type f -exec cp {} /tmp/jellyfin-backup/ \\; 2>/dev/null || true && \
-         echo 'Backed up:' && ls -la /tmp/jellyfin-backup/ 2>/dev/null || echo 'No files found'"
-    ).await.unwrap_or_default();
-    debug_log(&format!("[JELLYFIN] Backup result: {}", backup_cmd));
-
-    // 2. Reset Jellyfin: stop, delete, clean
-    let reset_cmds = vec![
-        "cd ~/media-stack && docker compose stop jellyfin",
-        "cd ~/media-stack && docker compose rm -f jellyfin",
-        "cd ~/media-stack && rm -rf jellyfin/*",
-    ];
-    for cmd in &reset_cmds {
-        debug_log(&format!("[JELLYFIN] Executing: {}", cmd));
-        ssh::execute_command_password(host, username, password, cmd).await.ok();
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-    }
-
-    // 3. Restaurer les fichiers d'identité serveur AVANT de démarrer Jellyfin
-    let restore_result = ssh::execute_command_password(host, username, password,
-        "cd ~/media-stack && \
-         mkdir -p jellyfin/data jellyfin/config && \
-         for f in /tmp/jellyfin-backup/device*; do \
-           [ -f \"$f\" ] && cp -f \"$f\" jellyfin/data/ && echo \"Restored $(basename $f) to data/\"; \
-         done 2>/dev/null || true && \
-         [ -f /tmp/jellyfin-backup/system.xml ] && cp -f /tmp/jellyfin-backup/system.xml jellyfin/config/ && echo 'system.xml restored' || true && \
-         [ -f /tmp/jellyfin-backup/network.xml ] && cp -f /tmp/jellyfin-backup/network.xml jellyfin/config/ && echo 'network.xml restored' || true && \
-         echo 'Restored files:' && ls -la jellyfin/data/ jellyfin/config/ 2>/dev/null && \
-         rm -rf /tmp/jellyfin-backup"
-    ).await.unwrap_or_default();
-    debug_log(&format!("[JELLYFIN] Restore result: {}", restore_result));
-
-    // 4. Démarrer Jellyfin avec les fichiers d'identité restaurés
-    debug_log("[JELLYFIN] Starting Jellyfin with restored identity files...");
-    ssh::execute_command_password(host, username, password,
-        "cd ~/media-stack && docker compose up -d jellyfin"
-    ).await.ok();
-
-    // Attendre que Jellyfin soit prêt après le reset (max 90 sec)
-    debug_log("[JELLYFIN] Attente de Jellyfin après reset...");
-    emit_progress(&window, "config", 87, "Attente de Jellyfin...", None);
-
-    let mut jellyfin_ready = false;
-    for i in 0..18 {
-        let check = ssh::execute_command_password(host, username, password,
-            "curl -s 'http://localhost:8096/System/Info/Public' 2>/dev/null || echo 'CURL_ERROR'"
-        ).await.unwrap_or_default();
+            logger.log_error(
+                "docker_compose_up",
+                &format!("ERREUR CRITIQUE: Seulement {} containers sur {} attendus !", container_count, expected_min_containers),
+                Some(serde_json::json!({
+                    "docker_ps_output": containers_check.trim(),
+                    "expected_minimum": expected_min_containers,
+                    "actual": container_count,
+                    "compose_logs": compose_logs.chars().take(2000).collect::<String>()
+                }))
+            ).await;
+            logger.end_step("docker_compose_up", false).await;
 
-        debug_log(&format!("[JELLYFIN] Check {}/18: {}", i + 1, &check[..std::cmp::min(150, check.len())]));
+            // Lister les images manquantes pour aider au debug
+            let missing_images = ssh::execute_command_password(host, username, password,
+                "cd ~/media-stack && docker compose config --images 2>/dev/null"
+            ).await.unwrap_or_default();
 
-        if check.contains("ServerName") && check.contains("\"StartupWizardCompleted\":false") {
-            jellyfin_ready = true;
-            debug_log(&format!("[JELLYFIN] Jellyfin prêt, wizard NON complété ({})", i + 1));
-            break;
+            return Err(anyhow::anyhow!(
+                "Docker compose up a échoué: seulement {} containers sur {} attendus. Images requises: {}",
+                container_count, expected_min_containers, missing_images.trim()
+            ));
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-    }
 
-    if !jellyfin_ready {
-        debug_log("[JELLYFIN] ERREUR: Jellyfin non disponible après 90 sec!");
+        logger.end_step("docker_compose_up", true).await;
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Containers, HookPhase::Post).await?;
+        state.complete(InstallStep::Containers).await;
+
+        // Étape 7: Attendre que les services soient prêts
+        emit_progress(&window, "wait_services", 75, "Attente des services...", None);
+        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::JELLYFIN_BOOT_SECS)).await?;
     }
 
-    // Variable pour stocker les infos d'auth Jellyfin (pour auto-login frontend)
+    // Étape 8: Configuration des services via API
+    // Clés *arr lues pendant la configuration (relues pour CloudSync si elle est sautée)
+    let mut harvested_keys: Option<crate::services::ArrApiKeys> = None;
+    // Infos d'auth Jellyfin (pour auto-login frontend)
     let mut final_jellyfin_auth: Option<JellyfinAuth> = None;
-
-    if jellyfin_ready {
-        emit_progress(&window, "config", 88, "Configuration Jellyfin...", None);
-
-        // Échapper les caractères spéciaux pour JSON
-        let jf_user = config.jellyfin_username.replace("\\", "\\\\").replace("\"", "\\\"");
-        let jf_pass = config.jellyfin_password.replace("\\", "\\\\").replace("\"", "\\\"");
-        debug_log(&format!("[JELLYFIN] User: {}, Pass: [{}chars]", jf_user, jf_pass.len()));
-
-        // Configuration COMPLÈTE du wizard - ORDRE CORRECT selon gist officiel:
-        // 1. Configuration, 2. GET User, 3. POST User, 4. RemoteAccess, 5. Complete
-        debug_log("[JELLYFIN] Configuration du wizard startup...");
-
-        // Étape 1: Configuration langue/pays ET nom du serveur EN PREMIER
-        let jf_server_name = config.jellyfin_server_name.replace("\\", "\\\\").replace("\"", "\\\"");
-        let startup_config_json = format!(
-            r#"{{"UICulture":"fr","MetadataCountryCode":"FR","PreferredMetadataLanguage":"fr","ServerName":"{}"}}"#,
-            jf_server_name
-        );
-        let startup_config_cmd = format!(
-            "curl -s -X POST 'http://localhost:8096/Startup/Configuration' -H 'Content-Type: application/json' -d '{}'",
-            startup_config_json
-        );
-        let config_result = ssh::execute_command_password(host, username, password, &startup_config_cmd).await.unwrap_or_default();
-        debug_log(&format!("[JELLYFIN] 1. Startup/Configuration (ServerName={}): [{}]", jf_server_name, config_result));
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-        // Étape 2: GET /Startup/User pour initialiser l'état
-        let get_user_cmd = "curl -s 'http://localhost:8096/Startup/User'";
-        let get_user_result = ssh::execute_command_password(host, username, password, get_user_cmd).await.unwrap_or_default();
-        debug_log(&format!("[JELLYFIN] 2. GET Startup/User: [{}]", get_user_result));
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
-        // Étape 3: POST /Startup/User pour créer l'utilisateur
-        let write_json_cmd = format!(
-            r#"echo '{{"Name":"{}","Password":"{}"}}' > /tmp/jf_user.json"#,
-            jf_user, jf_pass
-        );
-        ssh::execute_command_password(host, username, password, &write_json_cmd).await.ok();
-        let create_user_cmd = "curl -s -X POST 'http://localhost:8096/Startup/User' -H 'Content-Type: application/json' -d @/tmp/jf_user.json";
-        debug_log(&format!("[JELLYFIN] 3. POST Startup/User: Creating {}", jf_user));
-        let user_result = ssh::execute_command_password(host, username, password, create_user_cmd).await.unwrap_or_default();
-        debug_log(&format!("[JELLYFIN] Startup/User result: [{}]", user_result));
-        ssh::execute_command_password(host, username, password, "rm -f /tmp/jf_user.json").await.ok();
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-        // Étape 4: Activer l'accès distant
-        let remote_access_cmd = r#"curl -s -X POST 'http://localhost:8096/Startup/RemoteAccess' -H 'Content-Type: application/json' -d '{"EnableRemoteAccess":true,"EnableAutomaticPortMapping":false}'"#;
-        let remote_result = ssh::execute_command_password(host, username, password, remote_access_cmd).await.unwrap_or_default();
-        debug_log(&format!("[JELLYFIN] 4. Startup/RemoteAccess: [{}]", remote_result));
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-        // Étape 5: Compléter le wizard
-        let complete_cmd = "curl -s -X POST 'http://localhost:8096/Startup/Complete'";
-        let complete_result = ssh::execute_command_password(host, username, password, complete_cmd).await.unwrap_or_default();
-        debug_log(&format!("[JELLYFIN] 5. Startup/Complete: [{}]", complete_result));
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-        // Étape 6: Vérifier que le wizard est complété
-        let verify_result = ssh::execute_command_password(host, username, password,
-            "curl -s 'http://localhost:8096/System/Info/Public'"
+    procedure.run_until(InstallStep::Configuration).await?;
+    if state.should_run(InstallStep::Configuration) {
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Configuration, HookPhase::Pre).await?;
+        emit_progress(&window, "config", 85, "Configuration des services...", None);
+
+        // 8.1: Reset Jellyfin MAIS préserver le ServerId pour éviter "Incompatibilité du serveur"
+        emit_progress(&window, "config", 86, "Reset Jellyfin pour config propre...", None);
+        debug_log("[JELLYFIN] === Reset de Jellyfin avec préservation ServerId ===");
+
+        // 1. Sauvegarder TOUS les fichiers d'identité serveur
+        // Le ServerId peut être dans device.txt, deviceid, ou system.xml selon la version
+        let backup_cmd = ssh::execute_command_password(host, username, password,
+            "cd ~/media-stack && mkdir -p /tmp/jellyfin-backup && \
+             find jellyfin -name 'device*' -type f -exec cp {} /tmp/jellyfin-backup/ \\; 2>/dev/null || true && \
+             find jellyfin -name 'system.xml' -type f -exec cp {} /tmp/jellyfin-backup/ \\; 2>/dev/null || true && \
+             find jellyfin -name 'network.xml' -type f -exec cp {} /tmp/jellyfin-backup/ \\; 2>/dev/null || true && \
+             echo 'Backed up:' && ls -la /tmp/jellyfin-backup/ 2>/dev/null || echo 'No files found'"
         ).await.unwrap_or_default();
-        if verify_result.contains("\"StartupWizardCompleted\":true") {
-            debug_log("[JELLYFIN] Wizard COMPLÉTÉ avec succès!");
-        } else {
-            debug_log(&format!("[JELLYFIN] ERREUR: Wizard pas complété! {}", &verify_result[..std::cmp::min(100, verify_result.len())]));
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-            ssh::execute_command_password(host, username, password, "curl -s -X POST 'http://localhost:8096/Startup/Complete'").await.ok();
-        }
+        debug_log(&format!("[JELLYFIN] Backup result: {}", backup_cmd));
 
-        // Étape 6: S'authentifier pour obtenir un token et créer les bibliothèques
-        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+        // 2. Reset Jellyfin: stop, delete, clean
+        let reset_cmds = vec![
+            "cd ~/media-stack && docker compose stop jellyfin",
+            "cd ~/media-stack && docker compose rm -f jellyfin",
+            "cd ~/media-stack && rm -rf jellyfin/*",
+        ];
+        for cmd in &reset_cmds {
+            debug_log(&format!("[JELLYFIN] Executing: {}", cmd));
+            ssh::execute_command_password(host, username, password, cmd).await.ok();
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::STEP_PAUSE_SECS)).await?;
+        }
+
+        // 3. Restaurer les fichiers d'identité serveur AVANT de démarrer Jellyfin
+        let restore_result = ssh::execute_command_password(host, username, password,
+            "cd ~/media-stack && \
+             mkdir -p jellyfin/data jellyfin/config && \
+             for f in /tmp/jellyfin-backup/device*; do \
+               [ -f \"$f\" ] && cp -f \"$f\" jellyfin/data/ && echo \"Restored $(basename $f) to data/\"; \
+             done 2>/dev/null || true && \
+             [ -f /tmp/jellyfin-backup/system.xml ] && cp -f /tmp/jellyfin-backup/system.xml jellyfin/config/ && echo 'system.xml restored' || true && \
+             [ -f /tmp/jellyfin-backup/network.xml ] && cp -f /tmp/jellyfin-backup/network.xml jellyfin/config/ && echo 'network.xml restored' || true && \
+             echo 'Restored files:' && ls -la jellyfin/data/ jellyfin/config/ 2>/dev/null && \
+             rm -rf /tmp/jellyfin-backup"
+        ).await.unwrap_or_default();
+        debug_log(&format!("[JELLYFIN] Restore result: {}", restore_result));
 
-        // Commande auth sur une seule ligne
-        let auth_cmd = format!(
-            "curl -s -X POST 'http://localhost:8096/Users/AuthenticateByName' -H 'Content-Type: application/json' -H 'X-Emby-Authorization: MediaBrowser Client=\"JellySetup\", Device=\"RaspberryPi\", DeviceId=\"jellysetup-install\", Version=\"1.0.0\"' -d '{{\"Username\":\"{}\",\"Pw\":\"{}\"}}'",
-            jf_user, jf_pass
-        );
-        debug_log(&format!("[JELLYFIN] Auth command: {}", &auth_cmd[..std::cmp::min(150, auth_cmd.len())]));
-        let auth_result = ssh::execute_command_password(host, username, password, &auth_cmd).await.unwrap_or_default();
-        debug_log(&format!("[JELLYFIN] Auth result: {}", &auth_result[..std::cmp::min(200, auth_result.len())]));
-
-        // Extraire le token et UserId de la réponse JSON
-        let mut jellyfin_auth_data: Option<JellyfinAuth> = None;
-
-        if let Some(token_start) = auth_result.find("\"AccessToken\":\"") {
-            let token_rest = &auth_result[token_start + 15..];
-            if let Some(token_end) = token_rest.find("\"") {
-                let jellyfin_token = token_rest[..token_end].to_string();
-                println!("[Config] Jellyfin authenticated, creating libraries...");
-
-                // Extraire UserId de la réponse d'auth
-                let mut user_id_from_auth = String::new();
-                if let Some(user_start) = auth_result.find("\"User\":{") {
-                    let user_json = &auth_result[user_start..];
-                    if let Some(id_start) = user_json.find("\"Id\":\"") {
-                        let id_rest = &user_json[id_start + 6..];
-                        if let Some(id_end) = id_rest.find("\"") {
-                            user_id_from_auth = id_rest[..id_end].to_string();
-                            debug_log(&format!("[JELLYFIN] UserId extracted: {}", user_id_from_auth));
-                        }
-                    }
-                }
+        // 4. Démarrer Jellyfin avec les fichiers d'identité restaurés
+        debug_log("[JELLYFIN] Starting Jellyfin with restored identity files...");
+        ssh::execute_command_password(host, username, password,
+            "cd ~/media-stack && docker compose up -d jellyfin"
+        ).await.ok();
 
-                // Récupérer le ServerId depuis /System/Info/Public
-                let server_info = ssh::execute_command_password(host, username, password,
-                    "curl -s 'http://localhost:8096/System/Info/Public'"
-                ).await.unwrap_or_default();
-                debug_log(&format!("[JELLYFIN] Server info: {}", &server_info[..std::cmp::min(200, server_info.len())]));
-
-                let mut server_id = String::new();
-                if let Some(sid_start) = server_info.find("\"Id\":\"") {
-                    let sid_rest = &server_info[sid_start + 6..];
-                    if let Some(sid_end) = sid_rest.find("\"") {
-                        server_id = sid_rest[..sid_end].to_string();
-                        println!("[Config] Jellyfin ServerId: {}", server_id);
-                    }
-                }
+        // Attendre que Jellyfin soit prêt après le reset (max 90 sec)
+        debug_log("[JELLYFIN] Attente de Jellyfin après reset...");
+        emit_progress(&window, "config", 87, "Attente de Jellyfin...", None);
 
-                // Créer la bibliothèque Films avec LibraryOptions.PathInfos (format correct!)
-                // Le secret: il FAUT passer PathInfos dans le body JSON sinon la lib n'a pas d'ItemId
-                let movies_lib_cmd = format!(
-                    "curl -s -X POST 'http://localhost:8096/Library/VirtualFolders?name=Films&collectionType=movies&refreshLibrary=true' -H 'X-Emby-Token: {}' -H 'Content-Type: application/json' -d '{{\"LibraryOptions\":{{\"PathInfos\":[{{\"Path\":\"/mnt/decypharr/movies\"}}]}}}}'",
-                    jellyfin_token
-                );
-                let movies_result = ssh::execute_command_password(host, username, password, &movies_lib_cmd).await.unwrap_or_default();
-                debug_log(&format!("[JELLYFIN] Movies library result: {}", movies_result));
-                println!("[Config] Jellyfin: Movies library created");
-
-                // Créer la bibliothèque Séries avec LibraryOptions.PathInfos
-                let tv_lib_cmd = format!(
-                    "curl -s -X POST 'http://localhost:8096/Library/VirtualFolders?name=S%C3%A9ries&collectionType=tvshows&refreshLibrary=true' -H 'X-Emby-Token: {}' -H 'Content-Type: application/json' -d '{{\"LibraryOptions\":{{\"PathInfos\":[{{\"Path\":\"/mnt/decypharr/tv\"}}]}}}}'",
-                    jellyfin_token
-                );
-                let tv_result = ssh::execute_command_password(host, username, password, &tv_lib_cmd).await.unwrap_or_default();
-                debug_log(&format!("[JELLYFIN] TV library result: {}", tv_result));
-                println!("[Config] Jellyfin: TV Shows library created");
-
-                // Vérifier que les bibliothèques ont bien un ItemId (sinon elles sont invisibles!)
-                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                let libs_check = ssh::execute_command_password(host, username, password,
-                    &format!("curl -s 'http://localhost:8096/Library/VirtualFolders' -H 'X-Emby-Token: {}'", jellyfin_token)
-                ).await.unwrap_or_default();
-                debug_log(&format!("[JELLYFIN] Libraries check: {}", &libs_check[..std::cmp::min(500, libs_check.len())]));
+        let mut jellyfin_ready = false;
+        for i in 0..18 {
+            let check = ssh::execute_command_password(host, username, password,
+                &format!("curl -s 'http://localhost:{JELLYFIN}/System/Info/Public' 2>/dev/null || echo 'CURL_ERROR'")
+            ).await.unwrap_or_default();
 
-                // Vérifier que les deux libs ont un ItemId
-                let films_ok = libs_check.contains("Films") && libs_check.contains("\"ItemId\"");
-                let series_ok = libs_check.contains("ries") && libs_check.matches("\"ItemId\"").count() >= 2;
-                if films_ok && series_ok {
-                    println!("[Config] Jellyfin: Both libraries created with ItemId - SUCCESS!");
-                } else {
-                    println!("[Config] Jellyfin: Warning - libraries might not have ItemId: Films={}, Séries={}", films_ok, series_ok);
-                }
+            debug_log(&format!("[JELLYFIN] Check {}/18: {}", i + 1, &check[..std::cmp::min(150, check.len())]));
 
-                // Note: ServerName et langue déjà configurés via /Startup/Configuration
-                // NE PAS appeler /System/Configuration ici car ça reset IsStartupWizardCompleted !
-                println!("[Config] Jellyfin: Server already configured via Startup API");
+            if check.contains("ServerName") && check.contains("\"StartupWizardCompleted\":false") {
+                jellyfin_ready = true;
+                debug_log(&format!("[JELLYFIN] Jellyfin prêt, wizard NON complété ({})", i + 1));
+                break;
+            }
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
+        }
 
-                // Configurer les préférences utilisateur en Français (langue UI + sous-titres + audio)
-                if !user_id_from_auth.is_empty() {
-                    let user_config_cmd = format!(
-                        "curl -s -X POST 'http://localhost:8096/Users/{}/Configuration' -H 'X-Emby-Token: {}' -H 'Content-Type: application/json' -d '{{\"SubtitleLanguagePreference\":\"fre\",\"AudioLanguagePreference\":\"fra\"}}'",
-                        user_id_from_auth, jellyfin_token
-                    );
-                    ssh::execute_command_password(host, username, password, &user_config_cmd).await.ok();
+        if !jellyfin_ready {
+            debug_log("[JELLYFIN] ERREUR: Jellyfin non disponible après 90 sec!");
+        }
 
-                    // Configurer aussi la langue d'affichage (DisplayLanguage) via User Policy
-                    let display_lang_cmd = format!(
-                        "curl -s -X POST 'http://localhost:8096/Users/{}/Policy' -H 'X-Emby-Token: {}' -H 'Content-Type: application/json' -d '{{\"IsAdministrator\":true,\"EnableAllFolders\":true}}'",
-                        user_id_from_auth, jellyfin_token
-                    );
-                    ssh::execute_command_password(host, username, password, &display_lang_cmd).await.ok();
-
-                    // Forcer la langue française dans system.xml (au cas où le wizard l'aurait pas appliqué)
-                    let force_french_cmd = r#"cd ~/media-stack && \
-                        if [ -f jellyfin/config/system.xml ]; then \
-                            sed -i 's|<UICulture>[^<]*</UICulture>|<UICulture>fr</UICulture>|g' jellyfin/config/system.xml && \
-                            sed -i 's|<PreferredMetadataLanguage>[^<]*</PreferredMetadataLanguage>|<PreferredMetadataLanguage>fr</PreferredMetadataLanguage>|g' jellyfin/config/system.xml && \
-                            sed -i 's|<MetadataCountryCode>[^<]*</MetadataCountryCode>|<MetadataCountryCode>FR</MetadataCountryCode>|g' jellyfin/config/system.xml && \
-                            echo 'French language forced in system.xml'; \
-                        fi"#;
-                    ssh::execute_command_password(host, username, password, force_french_cmd).await.ok();
-                    println!("[Config] Jellyfin: User preferences set to French (UI + subtitles + audio)");
+        if jellyfin_ready {
+            // Assistant de premier démarrage, bibliothèques, transcodage et plugins via l'API REST
+            emit_progress(&window, "config", 88, "Configuration Jellyfin...", None);
+            let target = ssh::SshTarget::Password { host, username, password };
+            match jellyfin::first_run_setup(&target, &config, &media_paths, None).await {
+                Ok(session) => {
+                    tracing::info!("Jellyfin configured (ServerId={}, UserId={})", session.server_id, session.user_id);
+                    final_jellyfin_auth = Some(session.into());
                 }
-
-                // Stocker les infos d'authentification pour le frontend
-                if !server_id.is_empty() && !user_id_from_auth.is_empty() {
-                    jellyfin_auth_data = Some(JellyfinAuth {
-                        server_id: server_id.clone(),
-                        access_token: jellyfin_token.clone(),
-                        user_id: user_id_from_auth.clone(),
-                    });
-                    println!("[Config] Jellyfin auth data saved for auto-login: ServerId={}, UserId={}", server_id, user_id_from_auth);
+                Err(e) => {
+                    tracing::warn!("⚠️ Jellyfin setup: {}", e);
+                    logger.log_error("jellyfin_config", &format!("Configuration Jellyfin échouée: {}", e), None).await;
                 }
             }
-        }
-
-        // Sauvegarder jellyfin_auth_data pour l'utiliser à la fin
-        // On le stocke dans une variable qui sera utilisée par emit_progress_with_auth
-        final_jellyfin_auth = jellyfin_auth_data;
-    } else {
-        // ERREUR CRITIQUE: Si Jellyfin n'est pas prêt après 2 min, c'est que l'installation a échoué !
-        logger.log_error(
-            "jellyfin_config",
-            "ERREUR CRITIQUE: Jellyfin n'est pas accessible après 2 minutes d'attente !",
-            Some(serde_json::json!({
-                "timeout_seconds": 120,
-                "attempts": 24,
-                "expected_url": "http://localhost:8096/health",
-                "expected_response": "200"
-            }))
-        ).await;
-
-        // Vérifier les logs Docker pour comprendre le problème
-        let docker_logs = ssh::execute_command_password(host, username, password,
-            "docker logs jellyfin 2>&1 | tail -50"
-        ).await.unwrap_or_default();
-
-        logger.log_with_details(
-            LogLevel::Error,
-            "jellyfin_config",
-            "Logs Jellyfin pour debug",
-            serde_json::json!({
-                "docker_logs": docker_logs.chars().take(2000).collect::<String>()
-            })
-        ).await;
-
-        return Err(anyhow::anyhow!("Jellyfin n'est pas accessible après 2 minutes d'attente. Les containers Docker ne fonctionnent pas correctement."));
-    }
-
-    // 8.3: Configurer Decypharr avec AllDebrid
-    emit_progress(&window, "config", 89, "Configuration Decypharr...", None);
-    if !config.alldebrid_api_key.is_empty() {
-        let ad_key = config.alldebrid_api_key.replace("\\", "\\\\").replace("\"", "\\\"");
-
-        // Créer le config.json pour Decypharr
-        let decypharr_config = format!(r#"{{
-  "url_base": "/",
-  "port": "8282",
-  "log_level": "info",
-  "debrids": [
-    {{
-      "name": "alldebrid",
-      "api_key": "{}",
-      "download_api_keys": ["{}"],
-      "folder": "/mnt/decypharr/alldebrid/__all__",
-      "rate_limit": "250/minute",
-      "unpack_rar": true,
-      "minimum_free_slot": 1,
-      "use_webdav": true,
-      "torrents_refresh_interval": "15s",
-      "download_links_refresh_interval": "40m",
-      "workers": 200,
-      "auto_expire_links_after": "3d",
-      "folder_naming": "arr"
-    }}
-  ],
-  "qbittorrent": {{
-    "download_folder": "/mnt/decypharr/qbit",
-    "refresh_interval": 15,
-    "skip_pre_cache": true
-  }},
-  "arrs": [],
-  "repair": {{
-    "enabled": true,
-    "auto_process": true,
-    "use_webdav": true,
-    "workers": 100,
-    "strategy": "per_torrent",
-    "reinsert": true,
-    "interval": "5m"
-  }},
-  "webdav": {{}},
-  "rclone": {{
-    "enabled": true,
-    "mount_path": "/mnt/decypharr",
-    "rc_port": "5572",
-    "vfs_cache_mode": "full",
-    "vfs_cache_max_size": "10G",
-    "vfs_cache_max_age": "2h",
-    "vfs_cache_poll_interval": "1m",
-    "vfs_read_chunk_size": "64M",
-    "vfs_read_chunk_size_limit": "128M",
-    "vfs_read_ahead": "512M",
-    "buffer_size": "64M",
-    "async_read": true,
-    "transfers": 2,
-    "uid": 1000,
-    "gid": 1000,
-    "attr_timeout": "1s",
-    "dir_cache_time": "10s",
-    "log_level": "INFO"
-  }},
-  "allowed_file_types": ["3gp","ac3","aiff","alac","amr","ape","asf","asx","avc","avi","bin","bivx","dat","divx","dts","dv","dvr-ms","flac","fli","flv","ifo","m2ts","m2v","m3u","m4a","m4p","m4v","mid","midi","mk3d","mka","mkv","mov","mp2","mp3","mp4","mpa","mpeg","mpg","nrg","nsv","nuv","ogg","ogm","ogv","pva","qt","ra","rm","rmvb","strm","svq3","ts","ty","viv","vob","voc","vp3","wav","webm","wma","wmv","wpl","wtv","wv","xvid"],
-  "use_auth": true
-}}"#, ad_key, ad_key);
-
-        let write_config_cmd = format!(
-            "cat > ~/media-stack/decypharr/config.json << 'EOFDECYPHARR'\n{}\nEOFDECYPHARR",
-            decypharr_config
-        );
-        ssh::execute_command_password(host, username, password, &write_config_cmd).await.ok();
-
-        // Redémarrer Decypharr en background (évite les timeouts SSH)
-        ssh::execute_command_password(host, username, password,
-            "nohup docker restart decypharr > /dev/null 2>&1 &"
-        ).await.ok();
-        // Attendre quelques secondes pour laisser le restart démarrer
-        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-        debug_log("[DECYPHARR] Config updated with port as string");
-        println!("[Config] Decypharr configured with AllDebrid");
-    }
-
-    // 8.4: Attendre que Radarr et Sonarr soient prêts
-    emit_progress(&window, "config", 91, "Configuration Radarr/Sonarr...", None);
-    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
 
-    // Récupérer les API keys de Radarr et Sonarr depuis leurs config.xml
-    let radarr_api = ssh::execute_command_password(host, username, password,
-        "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/radarr/config.xml 2>/dev/null || echo ''"
-    ).await.unwrap_or_default().trim().to_string();
-
-    let sonarr_api = ssh::execute_command_password(host, username, password,
-        "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/sonarr/config.xml 2>/dev/null || echo ''"
-    ).await.unwrap_or_default().trim().to_string();
+            // Auto-test du transcodage (informatif)
+            match crate::transcode_test::run(&target).await {
+                Ok(result) => logger.log(LogLevel::Info, "transcode_test", &format!("{:.1} fps (x{:.2}, {}): {}", result.fps, result.speed, result.encoder, result.verdict)).await,
+                Err(e) => tracing::warn!("⚠️ Transcode test: {}", e),
+            }
+        } else {
+            // ERREUR CRITIQUE: Si Jellyfin n'est pas prêt après 2 min, c'est que l'installation a échoué !
+            logger.log_error(
+                "jellyfin_config",
+                "ERREUR CRITIQUE: Jellyfin n'est pas accessible après 2 minutes d'attente !",
+                Some(serde_json::json!({
+                    "timeout_seconds": 120,
+                    "attempts": 24,
+                    "expected_url": format!("http://localhost:{JELLYFIN}/health"),
+                    "expected_response": "200"
+                }))
+            ).await;
 
-    let prowlarr_api = ssh::execute_command_password(host, username, password,
-        "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/prowlarr/config.xml 2>/dev/null || echo ''"
-    ).await.unwrap_or_default().trim().to_string();
+            // Vérifier les logs Docker pour comprendre le problème
+            let docker_logs = ssh::execute_command_password(host, username, password,
+                "docker logs jellyfin 2>&1 | tail -50"
+            ).await.unwrap_or_default();
 
-    println!("[Config] API Keys - Radarr: {}..., Sonarr: {}..., Prowlarr: {}...",
-        radarr_api.chars().take(8).collect::<String>(),
-        sonarr_api.chars().take(8).collect::<String>(),
-        prowlarr_api.chars().take(8).collect::<String>()
-    );
+            logger.log_with_details(
+                LogLevel::Error,
+                "jellyfin_config",
+                "Logs Jellyfin pour debug",
+                serde_json::json!({
+                    "docker_logs": docker_logs.chars().take(2000).collect::<String>()
+                })
+            ).await;
 
-    // =============================================================================
-    // MASTER CONFIG - Fetch dynamique depuis Supabase
-    // =============================================================================
-    emit_progress(&window, "config", 89, "Récupération de la configuration master...", None);
-    println!("[MasterConfig] 🔄 Fetching configuration from Supabase...");
-
-    let master_config_opt = crate::master_config::fetch_master_config(Some("streaming")).await.ok().flatten();
-
-    if let Some(master_cfg) = &master_config_opt {
-        println!("[MasterConfig] ✅ Master config loaded: {}", master_cfg.id);
-
-        let mut template_vars = crate::template_engine::TemplateVars::new();
-        template_vars.set("PI_IP", host);
-        template_vars.set("PI_HOSTNAME", &hostname);
-        template_vars.set("RADARR_API_KEY", &radarr_api);
-        template_vars.set("SONARR_API_KEY", &sonarr_api);
-        template_vars.set("PROWLARR_API_KEY", &prowlarr_api);
-        template_vars.set("JELLYFIN_USERNAME", &config.jellyfin_username);
-        template_vars.set("JELLYFIN_PASSWORD", &config.jellyfin_password);
-        template_vars.set("YGG_PASSKEY", config.admin_email.as_deref().unwrap_or(""));
-        template_vars.set("ALLDEBRID_API_KEY", &config.alldebrid_api_key);
-
-        if let Some(jf_auth) = &final_jellyfin_auth {
-            template_vars.set("JELLYFIN_API_KEY", &jf_auth.access_token);
-            template_vars.set("JELLYFIN_SERVER_ID", &jf_auth.server_id);
-        } else {
-            template_vars.set("JELLYFIN_API_KEY", "PLACEHOLDER");
-            template_vars.set("JELLYFIN_SERVER_ID", "PLACEHOLDER");
-        }
-
-        if let Some(jellyseerr_config) = &master_cfg.jellyseerr_config {
-            emit_progress(&window, "config", 90, "Configuration Jellyseerr...", None);
-            println!("[MasterConfig] Applying Jellyseerr config...");
-            if let Err(e) = crate::services::apply_service_config_password(
-                host, username, password, "jellyseerr", jellyseerr_config, &template_vars,
-                &config.jellyfin_username,
-                &config.jellyfin_password,
-                config.admin_email.as_deref().unwrap_or("admin@jellyseerr.local")
-            ).await {
-                println!("[MasterConfig] ⚠️  Jellyseerr config error: {}", e);
-            }
+            return Err(anyhow::anyhow!("Jellyfin n'est pas accessible après 2 minutes d'attente. Les containers Docker ne fonctionnent pas correctement."));
         }
 
-        if let Some(radarr_config) = &master_cfg.radarr_config {
-            emit_progress(&window, "config", 91, "Configuration Radarr...", None);
-            println!("[MasterConfig] Applying Radarr config...");
-            if let Err(e) = crate::services::apply_service_config_password(
-                host, username, password, "radarr", radarr_config, &template_vars,
-                &config.jellyfin_username,
-                &config.jellyfin_password,
-                config.admin_email.as_deref().unwrap_or("admin@jellyseerr.local")
-            ).await {
-                println!("[MasterConfig] ⚠️  Radarr config error: {}", e);
+        // 8.3: Configurer Decypharr avec AllDebrid (les *arr sont ajoutés en 8.4c)
+        emit_progress(&window, "config", 89, "Configuration Decypharr...", None);
+        if !config.alldebrid_api_key.is_empty() {
+            let decypharr_vars = base_template_vars(host, &hostname, &config, &media_paths, &crate::services::ArrApiKeys::default());
+            let decypharr_config = crate::services::decypharr::build_config(None, &decypharr_vars, &media_paths.root);
+            if let Err(e) = crate::services::decypharr::write_config(&hook_target, &decypharr_config).await {
+                tracing::warn!("⚠️ Decypharr: {}", e);
             }
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::SETTLE_SECS)).await?;
+            tracing::info!("Decypharr configured with AllDebrid");
         }
 
-        if let Some(sonarr_config) = &master_cfg.sonarr_config {
-            emit_progress(&window, "config", 92, "Configuration Sonarr...", None);
-            println!("[MasterConfig] Applying Sonarr config...");
-            if let Err(e) = crate::services::apply_service_config_password(
-                host, username, password, "sonarr", sonarr_config, &template_vars,
-                &config.jellyfin_username,
-                &config.jellyfin_password,
-                config.admin_email.as_deref().unwrap_or("admin@jellyseerr.local")
-            ).await {
-                println!("[MasterConfig] ⚠️  Sonarr config error: {}", e);
+        // 8.4: Attendre que Radarr et Sonarr soient prêts
+        emit_progress(&window, "config", 91, "Configuration Radarr/Sonarr...", None);
+        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::STEP_PAUSE_SECS)).await?;
+
+        // Attendre que les config.xml soient générés puis lire les clés API
+        let api_keys = match crate::services::harvest_api_keys(&ssh::SshTarget::Password { host, username, password }).await {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::warn!("⚠️ Could not read *arr API keys: {}", e);
+                crate::services::ArrApiKeys::default()
+            }
+        };
+        harvested_keys = Some(api_keys.clone());
+        let radarr_api = api_keys.radarr.clone();
+        let sonarr_api = api_keys.sonarr.clone();
+        let prowlarr_api = api_keys.prowlarr.clone();
+
+        // =============================================================================
+        // MASTER CONFIG - Fetch dynamique depuis Supabase
+        // =============================================================================
+        emit_progress(&window, "config", 89, "Récupération de la configuration master...", None);
+        tracing::debug!("🔄 Fetching configuration from Supabase...");
+
+        let master_config_opt = crate::cloud::backend().fetch_master_config(Some("streaming")).await.ok().flatten();
+
+        if let Some(master_cfg) = &master_config_opt {
+            tracing::info!("✅ Master config loaded: {}", master_cfg.id);
+
+            let mut template_vars = base_template_vars(host, &hostname, &config, &media_paths, &api_keys);
+            apply_jellyfin_vars(&mut template_vars, final_jellyfin_auth.as_ref());
+
+            // Rendre la config de chaque service, dans l'ordre des dépendances
+            let mut rendered = crate::services::render_service_configs(master_cfg, &template_vars);
+            // Services exclus par l'utilisateur: aucune configuration à appliquer
+            rendered.retain(|(service, _)| config.service_enabled(service));
+            if config.review_configs {
+                emit_progress(&window, "review", 90, "Relecture des configurations...", None);
+                rendered = crate::services::review::request_review(&window, rendered).await?;
+            }
+            for (service_name, service_config) in &rendered {
+                let json = serde_json::to_string_pretty(service_config).unwrap_or_default();
+                crate::workspace::write(&format!("configs/{}.json", service_name), &json);
             }
-        }
 
-        if let Some(prowlarr_config) = &master_cfg.prowlarr_config {
-            emit_progress(&window, "config", 93, "Configuration Prowlarr...", None);
-            println!("[MasterConfig] Applying Prowlarr config...");
-            if let Err(e) = crate::services::apply_service_config_password(
-                host, username, password, "prowlarr", prowlarr_config, &template_vars,
-                &config.jellyfin_username,
-                &config.jellyfin_password,
-                config.admin_email.as_deref().unwrap_or("admin@jellyseerr.local")
-            ).await {
-                println!("[MasterConfig] ⚠️  Prowlarr config error: {}", e);
+            // Puis l'appliquer (Merge si le Pi a déjà été configuré: aucune base supprimée)
+            let config_mode = crate::services::ConfigMode::detect(&hook_target).await;
+            for (index, (service_name, service_config)) in rendered.iter().enumerate() {
+                let display_name = crate::services::display_name(service_name);
+                emit_progress(&window, "config", 90 + index as u32, &format!("Configuration {}...", display_name), None);
+                tracing::debug!("Applying {} config...", display_name);
+
+                match crate::services::apply_service_config_password(
+                    host, username, password,
+                    crate::services::ServiceConfig {
+                        service_name,
+                        resolved_config: service_config,
+                        vars: &template_vars,
+                        mode: config_mode,
+                    },
+                    &config,
+                ).await {
+                    Ok(()) => {
+                        logger.log(LogLevel::Success, "master_config", &format!("{} configured from master_config", display_name)).await;
+                    }
+                    Err(e) => {
+                        tracing::warn!("⚠️  {} config error: {}", display_name, e);
+                        logger.log_error("master_config", &format!("{} configuration failed: {}", display_name, e), None).await;
+                    }
+                }
             }
-        }
 
-        if let Some(jellyfin_config) = &master_cfg.jellyfin_config {
-            emit_progress(&window, "config", 94, "Configuration Jellyfin...", None);
-            println!("[MasterConfig] Applying Jellyfin config...");
-            if let Err(e) = crate::services::apply_service_config_password(
-                host, username, password, "jellyfin", jellyfin_config, &template_vars,
-                &config.jellyfin_username,
-                &config.jellyfin_password,
-                config.admin_email.as_deref().unwrap_or("admin@jellyseerr.local")
-            ).await {
-                println!("[MasterConfig] ⚠️  Jellyfin config error: {}", e);
+            if let Err(e) = crate::services::ConfigMode::mark_configured(&hook_target).await {
+                tracing::warn!("⚠️  Configuration marker not written: {}", e);
+            }
+            if let Err(e) = crate::config_history::record_version(&hook_target, &hostname, Some(&master_cfg.id), &docker_compose, &rendered, &template_vars).await {
+                tracing::warn!("⚠️  Version not recorded: {}", e);
             }
-        }
 
-        println!("[MasterConfig] ✅ All service configurations applied from master_config");
-    } else {
-        println!("[MasterConfig] ⚠️  No master_config found - using default configuration");
-    }
-    // =============================================================================
+            tracing::info!("✅ All service configurations applied from master_config");
+        } else {
+            tracing::warn!("⚠️  No master_config found - using default configuration");
+        }
+        // =============================================================================
 
-    // Ajouter Decypharr comme client de téléchargement à Radarr
-    if !radarr_api.is_empty() {
-        let radarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:7878/api/v3/downloadclient' \
+        // Ajouter Decypharr comme client de téléchargement à Radarr
+        if !radarr_api.is_empty() {
+            let radarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:{RADARR}/api/v3/downloadclient' \
             -H 'X-Api-Key: {}' \
             -H 'Content-Type: application/json' \
             -d '{{
@@ -3097,13 +3202,13 @@ pub async fn run_full_installation_password(
                     {{"name": "movieCategory", "value": "radarr"}}
                 ]
             }}'"#, radarr_api);
-        let result = ssh::execute_command_password(host, username, password, &radarr_client_cmd).await;
-        println!("[Config] Radarr: Decypharr download client result: {:?}", result);
-    }
+            let result = ssh::execute_command_password(host, username, password, &radarr_client_cmd).await;
+            tracing::info!("Radarr: Decypharr download client result: {:?}", result);
+        }
 
-    // Ajouter Decypharr comme client de téléchargement à Sonarr
-    if !sonarr_api.is_empty() {
-        let sonarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:8989/api/v3/downloadclient' \
+        // Ajouter Decypharr comme client de téléchargement à Sonarr
+        if !sonarr_api.is_empty() {
+            let sonarr_client_cmd = format!(r#"curl -s -X POST 'http://localhost:{SONARR}/api/v3/downloadclient' \
             -H 'X-Api-Key: {}' \
             -H 'Content-Type: application/json' \
             -d '{{
@@ -3119,38 +3224,38 @@ pub async fn run_full_installation_password(
                     {{"name": "tvCategory", "value": "sonarr"}}
                 ]
             }}'"#, sonarr_api);
-        let result = ssh::execute_command_password(host, username, password, &sonarr_client_cmd).await;
-        println!("[Config] Sonarr: Decypharr download client result: {:?}", result);
-    }
+            let result = ssh::execute_command_password(host, username, password, &sonarr_client_cmd).await;
+            tracing::info!("Sonarr: Decypharr download client result: {:?}", result);
+        }
 
-    // 8.4b: Ajouter les Root Folders pour Radarr et Sonarr
-    if !radarr_api.is_empty() {
-        let radarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:7878/api/v3/rootfolder' \
+        // 8.4b: Ajouter les Root Folders pour Radarr et Sonarr
+        if !radarr_api.is_empty() {
+            let radarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:{RADARR}/api/v3/rootfolder' \
             -H 'X-Api-Key: {}' \
             -H 'Content-Type: application/json' \
-            -d '{{"path": "/mnt/decypharr/movies"}}'"#, radarr_api);
-        ssh::execute_command_password(host, username, password, &radarr_root_cmd).await.ok();
-        println!("[Config] Radarr: Root folder /mnt/decypharr/movies added");
-    }
+            -d '{{"path": "{}"}}'"#, radarr_api, media_paths.movies_path());
+            ssh::execute_command_password(host, username, password, &radarr_root_cmd).await.ok();
+            tracing::info!("Radarr: Root folder {} added", media_paths.movies_path());
+        }
 
-    if !sonarr_api.is_empty() {
-        let sonarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:8989/api/v3/rootfolder' \
+        if !sonarr_api.is_empty() {
+            let sonarr_root_cmd = format!(r#"curl -s -X POST 'http://localhost:{SONARR}/api/v3/rootfolder' \
             -H 'X-Api-Key: {}' \
             -H 'Content-Type: application/json' \
-            -d '{{"path": "/mnt/decypharr/tv"}}'"#, sonarr_api);
-        ssh::execute_command_password(host, username, password, &sonarr_root_cmd).await.ok();
-        println!("[Config] Sonarr: Root folder /mnt/decypharr/tv added");
-    }
+            -d '{{"path": "{}"}}'"#, sonarr_api, media_paths.tv_path());
+            ssh::execute_command_password(host, username, password, &sonarr_root_cmd).await.ok();
+            tracing::info!("Sonarr: Root folder {} added", media_paths.tv_path());
+        }
 
-    // 8.5: Configurer Prowlarr avec YGG (si passkey fournie)
-    emit_progress(&window, "config", 94, "Configuration Prowlarr...", None);
-    if let Some(ref ygg_passkey) = config.ygg_passkey {
-        if !ygg_passkey.is_empty() && !prowlarr_api.is_empty() {
-            let passkey = ygg_passkey.replace("\\", "\\\\").replace("\"", "\\\"");
+        // 8.5: Configurer Prowlarr avec YGG (si passkey fournie)
+        emit_progress(&window, "config", 94, "Configuration Prowlarr...", None);
+        if let Some(ygg_passkey) = crate::secret::expose_opt(&config.ygg_passkey) {
+            if !ygg_passkey.is_empty() && !prowlarr_api.is_empty() {
+                let passkey = ygg_passkey.replace("\\", "\\\\").replace("\"", "\\\"");
 
-            // D'abord, récupérer le schema de l'indexer YGG
-            // Puis ajouter l'indexer avec le passkey
-            let prowlarr_ygg_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/indexer' \
+                // D'abord, récupérer le schema de l'indexer YGG
+                // Puis ajouter l'indexer avec le passkey
+                let prowlarr_ygg_cmd = format!(r#"curl -s -X POST 'http://localhost:{PROWLARR}/api/v1/indexer' \
                 -H 'X-Api-Key: {}' \
                 -H 'Content-Type: application/json' \
                 -d '{{
@@ -3165,32 +3270,33 @@ pub async fn run_full_installation_password(
                         {{"name": "passkey", "value": "{}"}}
                     ]
                 }}'"#, prowlarr_api, passkey);
-            ssh::execute_command_password(host, username, password, &prowlarr_ygg_cmd).await.ok();
-            println!("[Config] Prowlarr: YGG indexer configured");
+                ssh::execute_command_password(host, username, password, &prowlarr_ygg_cmd).await.ok();
+                tracing::info!("Prowlarr: YGG indexer configured");
+            }
+        }
 
-            // Ajouter FlareSolverr à Prowlarr
-            let flaresolverr_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/indexerProxy' \
-                -H 'X-Api-Key: {}' \
-                -H 'Content-Type: application/json' \
-                -d '{{
-                    "name": "FlareSolverr",
-                    "configContract": "FlareSolverrSettings",
-                    "implementation": "FlareSolverr",
-                    "fields": [
-                        {{"name": "host", "value": "http://localhost:8191"}}
-                    ]
-                }}'"#, prowlarr_api);
-            ssh::execute_command_password(host, username, password, &flaresolverr_cmd).await.ok();
+        // 8.5b: FlareSolverr (requis par les indexers protégés par Cloudflare)
+        if !config.service_enabled("flaresolverr") {
+            tracing::info!("FlareSolverr disabled, skipping");
+        } else if let Err(issue) = crate::services::flaresolverr::setup(hook_target, None).await {
+            if issue == crate::services::flaresolverr::FlareSolverrIssue::Cancelled {
+                return Err(crate::operations::Cancelled.into());
+            }
+            let message = issue.message();
+            tracing::warn!("⚠️ {}", message);
+            emit_progress(&window, "config", 95, &format!("⚠️ {}", message), None);
+            ssh::execute_command_password(host, username, password,
+                &format!("echo \"$(date): WARNING - {}\" >> ~/jellysetup-logs/install.log", message.replace('"', "'"))
+            ).await.ok();
         }
-    }
 
-    // 8.6: Synchroniser Prowlarr avec Radarr et Sonarr
-    if !prowlarr_api.is_empty() {
-        emit_progress(&window, "config", 96, "Synchronisation Prowlarr...", None);
+        // 8.6: Synchroniser Prowlarr avec Radarr et Sonarr
+        if !prowlarr_api.is_empty() {
+            emit_progress(&window, "config", 96, "Synchronisation Prowlarr...", None);
 
-        // Ajouter Radarr comme application dans Prowlarr
-        if !radarr_api.is_empty() {
-            let sync_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/applications' \
+            // Ajouter Radarr comme application dans Prowlarr
+            if !radarr_api.is_empty() {
+                let sync_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{PROWLARR}/api/v1/applications' \
                 -H 'X-Api-Key: {}' \
                 -H 'Content-Type: application/json' \
                 -d '{{
@@ -3200,18 +3306,18 @@ pub async fn run_full_installation_password(
                     "implementation": "Radarr",
                     "configContract": "RadarrSettings",
                     "fields": [
-                        {{"name": "prowlarrUrl", "value": "http://localhost:9696"}},
-                        {{"name": "baseUrl", "value": "http://localhost:7878"}},
+                        {{"name": "prowlarrUrl", "value": "http://localhost:{PROWLARR}"}},
+                        {{"name": "baseUrl", "value": "http://localhost:{RADARR}"}},
                         {{"name": "apiKey", "value": "{}"}}
                     ]
                 }}'"#, prowlarr_api, radarr_api);
-            ssh::execute_command_password(host, username, password, &sync_radarr_cmd).await.ok();
-            println!("[Config] Prowlarr: Radarr sync configured");
-        }
+                ssh::execute_command_password(host, username, password, &sync_radarr_cmd).await.ok();
+                tracing::info!("Prowlarr: Radarr sync configured");
+            }
 
-        // Ajouter Sonarr comme application dans Prowlarr
-        if !sonarr_api.is_empty() {
-            let sync_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:9696/api/v1/applications' \
+            // Ajouter Sonarr comme application dans Prowlarr
+            if !sonarr_api.is_empty() {
+                let sync_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{PROWLARR}/api/v1/applications' \
                 -H 'X-Api-Key: {}' \
                 -H 'Content-Type: application/json' \
                 -d '{{
@@ -3221,293 +3327,343 @@ pub async fn run_full_installation_password(
                     "implementation": "Sonarr",
                     "configContract": "SonarrSettings",
                     "fields": [
-                        {{"name": "prowlarrUrl", "value": "http://localhost:9696"}},
-                        {{"name": "baseUrl", "value": "http://localhost:8989"}},
+                        {{"name": "prowlarrUrl", "value": "http://localhost:{PROWLARR}"}},
+                        {{"name": "baseUrl", "value": "http://localhost:{SONARR}"}},
                         {{"name": "apiKey", "value": "{}"}}
                     ]
                 }}'"#, prowlarr_api, sonarr_api);
-            ssh::execute_command_password(host, username, password, &sync_sonarr_cmd).await.ok();
-            println!("[Config] Prowlarr: Sonarr sync configured");
+                ssh::execute_command_password(host, username, password, &sync_sonarr_cmd).await.ok();
+                tracing::info!("Prowlarr: Sonarr sync configured");
+            }
         }
-    }
 
-    // 8.7: Configurer Bazarr avec Radarr et Sonarr
-    emit_progress(&window, "config", 97, "Configuration Bazarr...", None);
-    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        // 8.7: Configurer Bazarr avec Radarr et Sonarr
+        emit_progress(&window, "config", 97, "Configuration Bazarr...", None);
+        crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::STEP_PAUSE_SECS)).await?;
 
-    // Attendre que Bazarr génère son config.ini
-    let mut bazarr_ready = false;
-    for _ in 0..12 {
-        let check = ssh::execute_command_password(host, username, password,
-            "test -f ~/media-stack/bazarr/config/config.yaml && echo OK || echo WAIT"
-        ).await.unwrap_or_default();
-        if check.contains("OK") {
-            bazarr_ready = true;
-            break;
+        // Attendre que Bazarr génère son config.ini
+        // Service désactivé: aucune attente, bazarr_ready reste faux
+        let bazarr_attempts = if config.service_enabled("bazarr") { 12 } else { 0 };
+        let mut bazarr_ready = false;
+        for _ in 0..bazarr_attempts {
+            let check = ssh::execute_command_password(host, username, password,
+                "test -f ~/media-stack/bazarr/config/config.yaml && echo OK || echo WAIT"
+            ).await.unwrap_or_default();
+            if check.contains("OK") {
+                bazarr_ready = true;
+                break;
+            }
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-    }
 
-    if bazarr_ready && !radarr_api.is_empty() && !sonarr_api.is_empty() {
-        // Bazarr utilise config.yaml depuis les versions récentes
-        // On peut modifier directement les settings via son API après le premier démarrage
-        let bazarr_api_check = ssh::execute_command_password(host, username, password,
-            "grep -oP '(?<=apikey: )[^\\s]+' ~/media-stack/bazarr/config/config.yaml 2>/dev/null || echo ''"
-        ).await.unwrap_or_default().trim().to_string();
+        if bazarr_ready && !radarr_api.is_empty() && !sonarr_api.is_empty() {
+            // Bazarr utilise config.yaml depuis les versions récentes
+            // On peut modifier directement les settings via son API après le premier démarrage
+            let bazarr_api_check = ssh::execute_command_password(host, username, password,
+                "grep -oP '(?<=apikey: )[^\\s]+' ~/media-stack/bazarr/config/config.yaml 2>/dev/null || echo ''"
+            ).await.unwrap_or_default().trim().to_string();
 
-        if !bazarr_api_check.is_empty() {
-            // Récupérer l'IP du Pi pour Bazarr
-            let pi_ip = ssh::execute_command_password(host, username, password, "hostname -I | awk '{print $1}'")
-                .await.unwrap_or_else(|_| host.to_string()).trim().to_string();
+            if !bazarr_api_check.is_empty() {
+                // Récupérer l'IP du Pi pour Bazarr
+                let pi_ip = ssh::execute_command_password(host, username, password, "hostname -I | awk '{print $1}'")
+                    .await.unwrap_or_else(|_| host.to_string()).trim().to_string();
 
-            // Configurer Radarr dans Bazarr
-            let bazarr_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:6767/api/system/settings' \
+                // Configurer Radarr dans Bazarr
+                let bazarr_radarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{BAZARR}/api/system/settings' \
                 -H 'X-API-KEY: {}' \
                 -H 'Content-Type: application/json' \
                 -d '{{"settings": {{"radarr": {{"ip": "{}", "port": 7878, "apikey": "{}", "ssl": false, "base_url": ""}}}}}}"#,
-                bazarr_api_check, pi_ip, radarr_api);
-            ssh::execute_command_password(host, username, password, &bazarr_radarr_cmd).await.ok();
+                    bazarr_api_check, pi_ip, radarr_api);
+                ssh::execute_command_password(host, username, password, &bazarr_radarr_cmd).await.ok();
 
-            // Configurer Sonarr dans Bazarr
-            let bazarr_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:6767/api/system/settings' \
+                // Configurer Sonarr dans Bazarr
+                let bazarr_sonarr_cmd = format!(r#"curl -s -X POST 'http://localhost:{BAZARR}/api/system/settings' \
                 -H 'X-API-KEY: {}' \
                 -H 'Content-Type: application/json' \
                 -d '{{"settings": {{"sonarr": {{"ip": "{}", "port": 8989, "apikey": "{}", "ssl": false, "base_url": ""}}}}}}"#,
-                bazarr_api_check, pi_ip, sonarr_api);
-            ssh::execute_command_password(host, username, password, &bazarr_sonarr_cmd).await.ok();
-            println!("[Config] Bazarr: Radarr and Sonarr configured");
-        }
-    }
-
-    // 8.8: Configuration automatique de Jellyseerr via API
-    emit_progress(&window, "config", 96, "Configuration de Jellyseerr...", None);
-    println!("[Config] Jellyseerr: Starting automatic configuration...");
-
-    // Attendre que Jellyseerr soit prêt (max 60 sec)
-    let mut jellyseerr_ready = false;
-    for i in 0..12 {
-        let check = ssh::execute_command_password(host, username, password,
-            "curl -s -o /dev/null -w '%{http_code}' 'http://localhost:5055/api/v1/status' 2>/dev/null || echo '000'"
-        ).await.unwrap_or_default();
-
-        if check.trim() == "200" || check.trim() == "403" {
-            jellyseerr_ready = true;
-            println!("[Config] Jellyseerr: Service ready after {} seconds", (i + 1) * 5);
-            break;
+                    bazarr_api_check, pi_ip, sonarr_api);
+                ssh::execute_command_password(host, username, password, &bazarr_sonarr_cmd).await.ok();
+                tracing::info!("Bazarr: Radarr and Sonarr configured");
+            }
         }
-        println!("[Config] Jellyseerr: Waiting... (attempt {}/12)", i + 1);
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-    }
-
-    if jellyseerr_ready {
-        // Petite pause pour s'assurer que Jellyseerr est complètement prêt
-        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-
-        // Étape 1: Authentifier avec Jellyfin et créer l'admin
-        // IMPORTANT: Échapper les caractères spéciaux pour JSON
-        let jf_user = config.jellyfin_username.replace("\\", "\\\\").replace("\"", "\\\"");
-        let jf_pass = config.jellyfin_password.replace("\\", "\\\\").replace("\"", "\\\"");
 
-        // Essayer plusieurs hostnames jusqu'à ce qu'un fonctionne
-        // 1. host.docker.internal (avec extra_hosts configuré)
-        // 2. jellyfin (nom du service Docker sur le même réseau)
-        // 3. IP du Pi (passée en paramètre host)
-        let hostnames_to_try = vec![
-            "host.docker.internal".to_string(),
-            "jellyfin".to_string(),
-            host.to_string(),
-        ];
+        // 8.8: Configuration automatique de Jellyseerr via API
+        emit_progress(&window, "config", 96, "Configuration de Jellyseerr...", None);
+        tracing::debug!("Jellyseerr: Starting automatic configuration...");
 
-        let mut auth_result = String::new();
-        for jellyfin_hostname in &hostnames_to_try {
-            println!("[Config] Jellyseerr: Trying hostname: {}", jellyfin_hostname);
-            // serverType: 2 = JELLYFIN (enum MediaServerType)
-            // urlBase: "" évite que JavaScript ajoute "undefined" à l'URL
-            let auth_cmd = format!(
-                r#"curl -s -X POST 'http://localhost:5055/api/v1/auth/jellyfin' \
-                   -H 'Content-Type: application/json' \
-                   -c /tmp/jellyseerr_cookies.txt \
-                   -d '{{"username":"{}","password":"{}","hostname":"{}","port":8096,"useSsl":false,"urlBase":"","serverType":2,"email":"admin@easyjelly.local"}}'"#,
-                jf_user, jf_pass, jellyfin_hostname
-            );
-            auth_result = ssh::execute_command_password(host, username, password, &auth_cmd).await.unwrap_or_default();
-            println!("[Config] Jellyseerr: Auth result with {}: {}", jellyfin_hostname, &auth_result[..std::cmp::min(200, auth_result.len())]);
+        // Attendre que Jellyseerr soit prêt (max 60 sec)
+        let jellyseerr_enabled = config.service_enabled("jellyseerr");
+        let jellyseerr_attempts = if jellyseerr_enabled { 12 } else { 0 };
+        let mut jellyseerr_ready = false;
+        for i in 0..jellyseerr_attempts {
+            let check = ssh::execute_command_password(host, username, password,
+                &format!("curl -s -o /dev/null -w '%{{http_code}}' 'http://localhost:{JELLYSEERR}/api/v1/status' 2>/dev/null || echo '000'")
+            ).await.unwrap_or_default();
 
-            if auth_result.contains("\"id\"") {
-                println!("[Config] Jellyseerr: Success with hostname: {}", jellyfin_hostname);
+            if check.trim() == "200" || check.trim() == "403" {
+                jellyseerr_ready = true;
+                tracing::info!("Jellyseerr: Service ready after {} seconds", (i + 1) * 5);
                 break;
             }
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-        }
-
-        // Vérifier si l'auth a réussi (contient "id" dans la réponse)
-        if auth_result.contains("\"id\"") {
-            println!("[Config] Jellyseerr: Admin user created successfully!");
+            tracing::debug!("Jellyseerr: Waiting... (attempt {}/12)", i + 1);
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
+        }
+
+        if jellyseerr_ready {
+            // Petite pause pour s'assurer que Jellyseerr est complètement prêt
+            crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::SETTLE_SECS)).await?;
+
+            // Étape 1: Authentifier avec Jellyfin et créer l'admin
+
+            // Essayer plusieurs hostnames jusqu'à ce qu'un fonctionne
+            // 1. host.docker.internal (avec extra_hosts configuré)
+            // 2. jellyfin (nom du service Docker sur le même réseau)
+            // 3. IP du Pi (passée en paramètre host)
+            let hostnames_to_try = vec![
+                "host.docker.internal".to_string(),
+                "jellyfin".to_string(),
+                host.to_string(),
+            ];
+
+            let mut auth_result = String::new();
+            for jellyfin_hostname in &hostnames_to_try {
+                tracing::info!("Jellyseerr: Trying hostname: {}", jellyfin_hostname);
+                // serverType: 2 = JELLYFIN (enum MediaServerType)
+                // urlBase: "" évite que JavaScript ajoute "undefined" à l'URL
+                let auth_body = serde_json::json!({
+                    "username": config.jellyfin_username,
+                    "password": config.jellyfin_password,
+                    "hostname": jellyfin_hostname,
+                    "port": crate::config::ports::JELLYFIN,
+                    "useSsl": false,
+                    "urlBase": "",
+                    "serverType": 2,
+                    "email": config.admin_email()
+                });
+                let auth_cmd = jellyseerr::curl_command("POST", "/auth/jellyfin", jellyseerr::CurlAuth::SaveCookies, Some(&auth_body));
+                auth_result = ssh::execute_command_password(host, username, password, &auth_cmd).await.unwrap_or_default();
+                tracing::info!("Jellyseerr: Auth result with {}: {}", jellyfin_hostname, &auth_result[..std::cmp::min(200, auth_result.len())]);
+
+                if jellyseerr::is_auth_success(&auth_result) {
+                    tracing::info!("Jellyseerr: Success with hostname: {}", jellyfin_hostname);
+                    break;
+                }
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::API_PAUSE_SECS)).await?;
+            }
 
-            // Étape 2: Sync des bibliothèques Jellyfin
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-            let sync_cmd = "curl -s -X GET 'http://localhost:5055/api/v1/settings/jellyfin/library?sync=true' -b /tmp/jellyseerr_cookies.txt";
-            let sync_result = ssh::execute_command_password(host, username, password, sync_cmd).await.unwrap_or_default();
-            println!("[Config] Jellyseerr: Library sync result: {}", &sync_result[..std::cmp::min(300, sync_result.len())]);
+            // Vérifier si l'auth a réussi (l'utilisateur créé est renvoyé)
+            if jellyseerr::is_auth_success(&auth_result) {
+                tracing::info!("Jellyseerr: Admin user created successfully!");
+
+                // Étape 2: Sync des bibliothèques Jellyfin
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::STEP_PAUSE_SECS)).await?;
+                let sync_cmd = &format!("curl -s -X GET 'http://localhost:{JELLYSEERR}/api/v1/settings/jellyfin/library?sync=true' -b /tmp/jellyseerr_cookies.txt");
+                let sync_result = ssh::execute_command_password(host, username, password, sync_cmd).await.unwrap_or_default();
+                tracing::info!("Jellyseerr: Library sync result: {}", &sync_result[..std::cmp::min(300, sync_result.len())]);
+
+                // Extraire les IDs des bibliothèques (format: [{"id":"xxx","name":"Films",...}])
+                let library_ids = jellyseerr::parse_library_ids(&sync_result).unwrap_or_else(|e| {
+                    tracing::warn!("Jellyseerr: ⚠️ {}", e);
+                    Vec::new()
+                });
+
+                // Étape 3: Activer toutes les bibliothèques trouvées
+                if !library_ids.is_empty() {
+                    let ids_str = library_ids.join(",");
+                    let enable_cmd = format!(
+                        "curl -s -X GET 'http://localhost:{JELLYSEERR}/api/v1/settings/jellyfin/library?enable={}' -b /tmp/jellyseerr_cookies.txt",
+                        ids_str
+                    );
+                    ssh::execute_command_password(host, username, password, &enable_cmd).await.ok();
+                    tracing::info!("Jellyseerr: Enabled {} libraries: {}", library_ids.len(), ids_str);
+                }
 
-            // Extraire les IDs des bibliothèques (format: [{"id":"xxx","name":"Films",...}])
-            let mut library_ids: Vec<String> = Vec::new();
-            let mut search_pos = 0;
-            while let Some(id_start) = sync_result[search_pos..].find("\"id\":\"") {
-                let actual_start = search_pos + id_start + 6;
-                if let Some(id_end) = sync_result[actual_start..].find("\"") {
-                    let lib_id = &sync_result[actual_start..actual_start + id_end];
-                    library_ids.push(lib_id.to_string());
-                    search_pos = actual_start + id_end;
+                // Étape 4: Finaliser le setup
+                crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::API_PAUSE_SECS)).await?;
+                let init_cmd = &format!("curl -s -X POST 'http://localhost:{JELLYSEERR}/api/v1/settings/initialize' -b /tmp/jellyseerr_cookies.txt -H 'Content-Type: application/json'");
+                let init_result = ssh::execute_command_password(host, username, password, init_cmd).await.unwrap_or_default();
+                tracing::info!("Jellyseerr: Initialize result: {}", init_result);
+
+                // Configurer Radarr et Sonarr dans Jellyseerr
+                tracing::debug!("Jellyseerr: Configuring Radarr and Sonarr...");
+
+                // Clés API récupérées à l'étape 8.4
+                let radarr_api_key = radarr_api.clone();
+                let sonarr_api_key = sonarr_api.clone();
+
+                if !radarr_api_key.is_empty() && !sonarr_api_key.is_empty() {
+                    // Laisser Jellyseerr écrire settings.json après l'initialisation
+                    crate::operations::sleep(std::time::Duration::from_secs(crate::config::delays::POLL_SECS)).await?;
+
+                    // Récupérer l'IP locale de l'hôte
+                    let host_ip = ssh::execute_command_password(host, username, password, "hostname -I | awk '{print $1}'"
+                    ).await.unwrap_or_default().trim().to_string();
+
+                    // Enregistrer Radarr/Sonarr sans créer de doublon si la config est rejouée
+                    match crate::services::arr_client::JellyseerrClient::connect(&ssh::SshTarget::Password { host, username, password }).await {
+                        Ok(client) => {
+                            let radarr = jellyseerr::arr_server_payload("radarr", &host_ip, &radarr_api_key, &media_paths.movies_path());
+                            let mut sonarr = jellyseerr::arr_server_payload("sonarr", &host_ip, &sonarr_api_key, &media_paths.tv_path());
+                            profiles::apply_anime_directory(&mut sonarr, &config);
+
+                            for (service, payload) in [("radarr", &radarr), ("sonarr", &sonarr)] {
+                                if let Err(e) = client.upsert_arr_server(service, payload).await {
+                                    tracing::warn!("Jellyseerr: ⚠️  Could not register {}: {}", service, e);
+                                }
+                            }
+                            tracing::info!("Jellyseerr: ✅ Radarr and Sonarr configured");
+                        }
+                        Err(e) => {
+                            tracing::warn!("Jellyseerr: ⚠️  Could not read Jellyseerr API key: {}", e);
+                        }
+                    }
                 } else {
-                    break;
+                    tracing::warn!("Jellyseerr: ⚠️  Could not get Radarr/Sonarr API keys");
                 }
-            }
 
-            // Étape 3: Activer toutes les bibliothèques trouvées
-            if !library_ids.is_empty() {
-                let ids_str = library_ids.join(",");
-                let enable_cmd = format!(
-                    "curl -s -X GET 'http://localhost:5055/api/v1/settings/jellyfin/library?enable={}' -b /tmp/jellyseerr_cookies.txt",
-                    ids_str
-                );
-                ssh::execute_command_password(host, username, password, &enable_cmd).await.ok();
-                println!("[Config] Jellyseerr: Enabled {} libraries: {}", library_ids.len(), ids_str);
-            }
+                // Nettoyer les cookies
+                ssh::execute_command_password(host, username, password, "rm -f /tmp/jellyseerr_cookies.txt").await.ok();
 
-            // Étape 4: Finaliser le setup
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-            let init_cmd = "curl -s -X POST 'http://localhost:5055/api/v1/settings/initialize' -b /tmp/jellyseerr_cookies.txt -H 'Content-Type: application/json'";
-            let init_result = ssh::execute_command_password(host, username, password, init_cmd).await.unwrap_or_default();
-            println!("[Config] Jellyseerr: Initialize result: {}", init_result);
+                tracing::info!("Jellyseerr: Configuration completed successfully!");
+            } else {
+                tracing::warn!("Jellyseerr: Auth failed, manual setup required at http://<pi-ip>:5055");
+            }
+        } else if jellyseerr_enabled {
+            tracing::warn!("Jellyseerr: Service not ready after 60 seconds, manual setup required");
+        }
 
-            // Configurer Radarr et Sonarr dans Jellyseerr
-            println!("[Config] Jellyseerr: Configuring Radarr and Sonarr...");
+        // Log la configuration effectuée
+        // 8.8b: Profils optionnels (anime, musique, livres, livres audio)
+        if !config.profiles().is_empty() {
+            emit_progress(&window, "config", 97, "Configuration des profils (anime, musique, livres, livres audio)...", None);
+            let target = ssh::SshTarget::Password { host, username, password };
+            if let Err(e) = profiles::apply_profiles(&target, &config, &media_paths, master_config_opt.as_ref()).await {
+                tracing::info!("Profiles: {}", e);
+            }
+        }
 
-            // Récupérer les API keys de Radarr et Sonarr
-            let radarr_api_key = ssh::execute_command_password(host, username, password,
-                "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/radarr/config.xml 2>/dev/null || echo ''"
-            ).await.unwrap_or_default().trim().to_string();
+        // 8.8c: Politiques par défaut (quotas, téléchargements simultanés, monitoring)
+        if let Some(policies) = master_config_opt.as_ref().and_then(|m| m.policies.as_ref()) {
+            emit_progress(&window, "config", 97, "Application des politiques par défaut...", None);
+            let target = ssh::SshTarget::Password { host, username, password };
+            if let Err(e) = crate::services::policies::apply_policies(&target, policies).await {
+                tracing::info!("Policies: {}", e);
+            }
+        }
 
-            let sonarr_api_key = ssh::execute_command_password(host, username, password,
-                "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/sonarr/config.xml 2>/dev/null || echo ''"
-            ).await.unwrap_or_default().trim().to_string();
+        // 8.8d: Tableau de bord Homepage (tuiles générées depuis le compose)
+        if config.homepage {
+            emit_progress(&window, "config", 97, "Configuration du tableau de bord...", None);
+            let target = ssh::SshTarget::Password { host, username, password };
+            let vars = base_template_vars(host, &hostname, &config, &media_paths, &api_keys);
+            if let Err(e) = crate::homepage::apply_config(&target, &docker_compose, &vars).await {
+                tracing::info!("Homepage: {}", e);
+            }
+        }
 
-            if !radarr_api_key.is_empty() && !sonarr_api_key.is_empty() {
-                let jellyseerr_config = format!(r#"
-# Récupérer l'API key de Jellyseerr depuis settings.json
-API_KEY=$(cat ~/media-stack/jellyseerr/settings.json | grep -o '"apiKey":"[^"]*"' | head -1 | cut -d'"' -f4)
-
-# Récupérer l'IP locale de l'hôte
-HOST_IP=$(hostname -I | awk '{{print $1}}')
-
-# Attendre que Jellyseerr soit prêt
-sleep 5
-
-# Configurer Radarr via l'API
-curl -s -X POST "http://localhost:5055/api/v1/settings/radarr" \
-  -H "X-Api-Key: $API_KEY" \
-  -H "Content-Type: application/json" \
-  -d '{{
-    "name": "Radarr",
-    "hostname": "'"$HOST_IP"'",
-    "port": 7878,
-    "apiKey": "{}",
-    "useSsl": false,
-    "activeProfileId": 4,
-    "activeProfileName": "HD-1080p",
-    "activeDirectory": "/mnt/media/movies",
-    "is4k": false,
-    "minimumAvailability": "released",
-    "isDefault": true,
-    "syncEnabled": true
-  }}'
-
-# Configurer Sonarr via l'API
-curl -s -X POST "http://localhost:5055/api/v1/settings/sonarr" \
-  -H "X-Api-Key: $API_KEY" \
-  -H "Content-Type: application/json" \
-  -d '{{
-    "name": "Sonarr",
-    "hostname": "'"$HOST_IP"'",
-    "port": 8989,
-    "apiKey": "{}",
-    "useSsl": false,
-    "activeProfileId": 4,
-    "activeProfileName": "HD-1080p",
-    "activeDirectory": "/mnt/media/series",
-    "is4k": false,
-    "enableSeasonFolders": true,
-    "isDefault": true,
-    "syncEnabled": true
-  }}'
-
-echo "✅ Radarr and Sonarr configured in Jellyseerr"
-"#, radarr_api_key, sonarr_api_key);
-
-                ssh::execute_command_password(host, username, password, &jellyseerr_config).await.ok();
-                println!("[Config] Jellyseerr: ✅ Radarr and Sonarr configured");
-            } else {
-                println!("[Config] Jellyseerr: ⚠️  Could not get Radarr/Sonarr API keys");
+        // 8.8e: Surveillance externe (alerte si le serveur ne répond plus)
+        if let Some(monitor) = &config.uptime_monitor {
+            emit_progress(&window, "config", 97, "Activation de la surveillance...", None);
+            let target = ssh::SshTarget::Password { host, username, password };
+            if let Err(e) = crate::uptime::install_monitor(&target, monitor).await {
+                tracing::info!("Uptime: {}", e);
             }
+        }
 
-            // Nettoyer les cookies
-            ssh::execute_command_password(host, username, password, "rm -f /tmp/jellyseerr_cookies.txt").await.ok();
+        // 8.8f: Agent quotidien AllDebrid (expiration de l'abonnement, quotas)
+        if !config.alldebrid_api_key.is_empty() {
+            let target = ssh::SshTarget::Password { host, username, password };
+            if let Err(e) = crate::alldebrid::install_agent(&target, config.alldebrid_api_key.expose(), crate::secret::expose_opt(&config.discord_webhook)).await {
+                tracing::info!("AllDebrid agent: {}", e);
+            }
+        }
 
-            println!("[Config] Jellyseerr: Configuration completed successfully!");
-        } else {
-            println!("[Config] Jellyseerr: Auth failed, manual setup required at http://<pi-ip>:5055");
+        // 8.8g: Surveillance de l'accès distant (redémarre cloudflared, alerte Discord)
+        if config.cloudflare_token.is_some() {
+            let target = ssh::SshTarget::Password { host, username, password };
+            if let Err(e) = crate::remote_access::install_watchdog(&target, crate::secret::expose_opt(&config.discord_webhook)).await {
+                tracing::info!("Remote access watchdog: {}", e);
+            }
         }
-    } else {
-        println!("[Config] Jellyseerr: Service not ready after 60 seconds, manual setup required");
-    }
 
-    // Log la configuration effectuée
-    ssh::execute_command_password(host, username, password,
-        "echo \"$(date): Service configuration completed\" >> ~/jellysetup-logs/install.log"
-    ).await.ok();
+        crate::hooks::run_hooks(&hook_target, &hooks, InstallStep::Configuration, HookPhase::Post).await?;
+        ssh::execute_command_password(host, username, password,
+            "echo \"$(date): Service configuration completed\" >> ~/jellysetup-logs/install.log"
+        ).await.ok();
+        state.complete(InstallStep::Configuration).await;
+    }
 
     // 8.9: Sauvegarder l'installation dans Supabase (centralisation des identifiants)
-    emit_progress(&window, "supabase", 98, "Sauvegarde dans le cloud...", None);
+    procedure.run_until(InstallStep::CloudSync).await?;
+    if state.should_run(InstallStep::CloudSync) {
+        emit_progress(&window, "supabase", 98, "Sauvegarde dans le cloud...", None);
+        let api_keys = match harvested_keys.take() {
+            Some(keys) => keys,
+            None => crate::services::harvest_api_keys(&hook_target).await.unwrap_or_default(),
+        };
 
-    // Récupérer le fingerprint SSH capturé au début
-    let ssh_fingerprint = ssh::get_last_host_fingerprint();
+        // Récupérer le fingerprint SSH capturé au début
+        let ssh_fingerprint = ssh::get_last_host_fingerprint();
 
-    // Sauvegarder dans Supabase (ne bloque pas en cas d'erreur)
-    match crate::supabase::save_installation(
-        &hostname,
-        host,
-        None,  // Pas de clé publique pour auth par mot de passe
-        None,  // Pas de clé privée pour auth par mot de passe
-        ssh_fingerprint.as_deref(),
-        env!("CARGO_PKG_VERSION"),
-    ).await {
-        Ok(config_id) => {
-            println!("[Supabase] Installation saved with ID: {}", config_id);
-
-            // Sauvegarder aussi les credentials de l'utilisateur
-            if let Err(e) = crate::supabase::save_pi_config(
-                &hostname,
-                &config_id,
-                Some(&config.alldebrid_api_key),
-                config.ygg_passkey.as_deref(),
-                config.cloudflare_token.as_deref(),
-                None, // jellyfin_api_key
-                None, // radarr_api_key
-                None, // sonarr_api_key
-                None, // prowlarr_api_key
-            ).await {
-                println!("[Supabase] Warning: could not save Pi config: {}", e);
-            }
+        // Sauvegarder dans Supabase (ne bloque pas en cas d'erreur)
+        let registration = crate::registration::InstallationRegistration::new(
+            &hostname,
+            host,
+            crate::registration::RegistrationAuth::Password,
+            ssh_fingerprint.as_deref(),
+        );
+        crate::pi_registry::record_installation(&registration, username, None).await;
+        match crate::cloud::backend().save_installation(&registration).await {
+            Ok(config_id) => {
+                tracing::info!("Installation saved with ID: {}", config_id);
+
+                // Sauvegarder aussi les credentials de l'utilisateur
+                // Compte admin chiffré avec le mot de passe Jellyfin
+                let admin_account_encrypted = config.encrypted_admin_account().ok();
+                let credentials = crate::supabase::PiCredentials {
+                    alldebrid_api_key: Some(config.alldebrid_api_key.expose()),
+                    ygg_passkey: crate::secret::expose_opt(&config.ygg_passkey),
+                    cloudflare_token: crate::secret::expose_opt(&config.cloudflare_token),
+                    jellyfin_api_key: None,
+                    radarr_api_key: api_keys.get("radarr"),
+                    sonarr_api_key: api_keys.get("sonarr"),
+                    prowlarr_api_key: api_keys.get("prowlarr"),
+                    admin_account_encrypted: admin_account_encrypted.as_deref(),
+                };
+                if let Err(e) = crate::supabase::save_pi_config(&hostname, &config_id, &credentials).await {
+                    tracing::warn!("could not save Pi config: {}", e);
+                }
 
-            // Mettre à jour le statut à "completed"
-            if let Err(e) = crate::supabase::update_status(&hostname, &config_id, "completed", None).await {
-                println!("[Supabase] Warning: could not update status: {}", e);
+                // État de référence pour la détection de dérive (detect_drift)
+                match crate::drift::capture_snapshot(&hook_target).await {
+                    Ok(snapshot) => {
+                        if let Err(e) = crate::supabase::save_desired_state(&hostname, &config_id, &snapshot).await {
+                            tracing::warn!("could not save desired state: {}", e);
+                        }
+                    }
+                    Err(e) => tracing::warn!("could not capture baseline: {}", e),
+                }
+
+                // Mettre à jour le statut à "completed"
+                if let Err(e) = crate::cloud::backend().update_status(&hostname, &config_id, "completed", None).await {
+                    tracing::warn!("could not update status: {}", e);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("could not save installation: {}", e);
             }
         }
-        Err(e) => {
-            println!("[Supabase] Warning: could not save installation: {}", e);
+
+        state.complete(InstallStep::CloudSync).await;
+    }
+    procedure.finish().await?;
+
+    // Checklist post-installation pour le wizard (non bloquante)
+    match crate::verification::verify_and_persist(hook_target, &hostname, &config.jellyfin_username, config.jellyfin_password.expose()).await {
+        Ok(report) => {
+            let _ = window.emit("install-health", &report);
         }
+        Err(e) => tracing::warn!("post-install verification failed: {}", e),
     }
 
     // Émettre l'événement de fin avec les données d'auth Jellyfin pour auto-login
@@ -3519,7 +3675,7 @@ echo "✅ Radarr and Sonarr configured in Jellyseerr"
     // Arrêter caffeinate maintenant que l'installation est terminée
     #[cfg(target_os = "macos")]
     if let Some(mut process) = caffeinate_process {
-        println!("[Install] Stopping caffeinate...");
+        tracing::debug!("Stopping caffeinate...");
         let _ = process.kill();
     }
 
@@ -3529,3 +3685,97 @@ echo "✅ Radarr and Sonarr configured in Jellyseerr"
     tracing::info!("Installation (password auth) completed successfully on {}", host);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RPI_OS_INDEX: &str = include_str!("../tests/fixtures/rpi_os_index.html");
+    const FOLDER_BOOKWORM: &str = include_str!("../tests/fixtures/rpi_os_folder_bookworm.html");
+    const FOLDER_TRIXIE: &str = include_str!("../tests/fixtures/rpi_os_folder_trixie.html");
+    const DD_SIGINFO: &str = include_str!("../tests/fixtures/dd_siginfo.log");
+    const DD_PERMISSION_DENIED: &str = include_str!("../tests/fixtures/dd_permission_denied.log");
+
+    fn flash_config() -> FlashConfig {
+        serde_json::from_value(serde_json::json!({
+            "sdPath": "/dev/rdisk4",
+            "hostname": "jellypi",
+            "systemUsername": "pi",
+            "systemPassword": "secret",
+            "wifiSsid": "Maison",
+            "wifiPassword": "wifi-pass",
+            "wifiCountry": "FR",
+            "timezone": "Europe/Paris",
+            "keymap": "fr",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rpi_os_index_parsing() {
+        let versions = parse_rpi_os_versions(RPI_OS_INDEX);
+        let dates: Vec<&str> = versions.iter().map(|(_, date)| date.as_str()).collect();
+        assert_eq!(dates, vec!["2025-10-02", "2025-05-13", "2024-11-19", "2023-12-06"]);
+        assert_eq!(versions[0].0, "raspios_lite_arm64-2025-10-02/");
+
+        assert_eq!(find_bookworm_image(FOLDER_TRIXIE), None);
+        assert_eq!(find_bookworm_image(FOLDER_BOOKWORM).as_deref(), Some("2025-05-13-raspios-bookworm-arm64-lite.img.xz"));
+        assert_eq!(find_image(FOLDER_TRIXIE).as_deref(), Some("2025-10-01-raspios-trixie-arm64-lite.img.xz"));
+        assert_eq!(find_image("<html></html>"), None);
+    }
+
+    #[test]
+    fn test_dd_log_parsing() {
+        let progress = parse_dd_progress(DD_SIGINFO).unwrap();
+        assert_eq!(progress.bytes, 1_195_376_640);
+        assert_eq!(progress.bytes_per_sec, Some(2_848_083.0));
+        assert_eq!(dd_log_outcome(DD_SIGINFO), DdOutcome::Completed);
+
+        assert_eq!(parse_dd_progress(DD_PERMISSION_DENIED), None);
+        assert_eq!(dd_log_outcome(DD_PERMISSION_DENIED), DdOutcome::PermissionDenied);
+        assert_eq!(dd_log_outcome(""), DdOutcome::Unknown);
+        assert_eq!(
+            parse_dd_progress("1024 bytes transferred in 0.5 secs"),
+            Some(DdProgress { bytes: 1024, bytes_per_sec: None })
+        );
+    }
+
+    #[test]
+    fn test_progress_math() {
+        assert_eq!(download_percent(50, 100), 15);
+        assert_eq!(download_percent(150, 100), 30);
+        assert_eq!(download_percent(10, 0), 0);
+
+        assert_eq!(write_percent(2_000_000_000, 2_000_000_000), 99);
+        assert_eq!(write_percent(500, 1000), 50);
+        assert_eq!(write_percent(1, 0), 0);
+        assert_eq!(overall_write_percent(0), 25);
+        assert_eq!(overall_write_percent(99), 74);
+
+        assert_eq!(remaining_write_secs(0, 90_000_000, 1.0), 90);
+        assert_eq!(remaining_write_secs(0, 90_000_000, 0.0), 0);
+        assert_eq!(format_remaining(90), "~1min30s restant");
+        assert_eq!(format_remaining(42), "~42s restant");
+        assert_eq!(format_remaining(0), "finalisation...");
+    }
+
+    #[test]
+    fn test_boot_partition_files() {
+        let config = flash_config();
+        let toml = custom_toml(&config, "ssh-ed25519 AAAA jellysetup").unwrap();
+        assert!(toml.starts_with("# Configuration JellySetup"));
+        assert!(toml.contains("hostname = \"jellypi\""));
+        assert!(toml.contains("authorized_keys = [ \"ssh-ed25519 AAAA jellysetup\" ]"));
+        assert!(toml.contains("hidden = false"));
+        assert!(toml.contains("ssid = \"Maison\""));
+        assert!(toml.contains("country = \"FR\""));
+        assert!(toml.contains("timezone = \"Europe/Paris\""));
+        // Aucun mot de passe en clair sur la partition FAT
+        assert!(!toml.contains("secret") && !toml.contains("wifi-pass"));
+        assert!(!toml.contains("password_encrypted = false"));
+        assert!(toml.contains(&crate::crypto::wpa_psk("Maison", "wifi-pass").unwrap()));
+        let userconf = userconf(&config).unwrap();
+        assert!(userconf.starts_with("pi:$6$"));
+        assert!(!userconf.contains("secret"));
+    }
+}
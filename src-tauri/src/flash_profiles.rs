@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use ts_rs::TS;
+
+use crate::FlashConfig;
+
+// =============================================================================
+// Profils de flash réutilisables (hostname, WiFi, locale d'un foyer)
+// =============================================================================
+//
+// Enregistrés après chaque flash réussi dans config_dir/jellysetup/flash_profiles.json.
+// Les mots de passe (système, WiFi) ne sont jamais écrits: le wizard les redemande.
+
+/// Profils conservés au-delà desquels les plus anciens sont oubliés
+const MAX_PROFILES: usize = 20;
+
+/// Configuration de flash sans secrets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct FlashProfile {
+    /// Nom affiché (hostname par défaut)
+    pub name: String,
+    pub hostname: String,
+    pub system_username: String,
+    pub wifi_ssid: String,
+    pub wifi_country: String,
+    pub timezone: String,
+    pub keymap: String,
+    /// RFC 3339
+    pub last_used: String,
+}
+
+impl FlashProfile {
+    pub fn from_config(name: &str, config: &FlashConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            hostname: config.hostname.clone(),
+            system_username: config.system_username.clone(),
+            wifi_ssid: config.wifi_ssid.clone(),
+            wifi_country: config.wifi_country.clone(),
+            timezone: config.timezone.clone(),
+            keymap: config.keymap.clone(),
+            last_used: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+fn profiles_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("jellysetup").join("flash_profiles.json"))
+}
+
+fn load_profiles() -> Vec<FlashProfile> {
+    profiles_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_profiles(profiles: &[FlashProfile]) -> Result<()> {
+    let path = profiles_path().ok_or_else(|| anyhow!("Cannot determine config directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(profiles)?)?;
+    Ok(())
+}
+
+/// Remplace le profil de même nom, place le plus récent en tête et borne la liste
+pub fn upsert_profile(profiles: &mut Vec<FlashProfile>, profile: FlashProfile) {
+    profiles.retain(|p| p.name != profile.name);
+    profiles.insert(0, profile);
+    profiles.truncate(MAX_PROFILES);
+}
+
+/// Profils du plus récent au plus ancien
+pub fn list_profiles() -> Vec<FlashProfile> {
+    let mut profiles = load_profiles();
+    profiles.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+    profiles
+}
+
+/// Mémorise la configuration d'un flash réussi (nommée d'après le hostname)
+pub fn remember(config: &FlashConfig) -> Result<()> {
+    let mut profiles = load_profiles();
+    upsert_profile(&mut profiles, FlashProfile::from_config(&config.hostname, config));
+    save_profiles(&profiles)?;
+    tracing::info!("✅ Profile '{}' saved", config.hostname);
+    Ok(())
+}
+
+/// Profil à pré-remplir dans le wizard (marqué comme utilisé)
+pub fn apply_profile(name: &str) -> Result<FlashProfile> {
+    let mut profiles = load_profiles();
+    let mut profile = profiles
+        .iter()
+        .find(|p| p.name == name)
+        .cloned()
+        .ok_or_else(|| anyhow!("Profil '{}' introuvable", name))?;
+    profile.last_used = chrono::Utc::now().to_rfc3339();
+    upsert_profile(&mut profiles, profile.clone());
+    save_profiles(&profiles)?;
+    Ok(profile)
+}
+
+/// Supprime un profil
+pub fn delete_profile(name: &str) -> Result<()> {
+    let mut profiles = load_profiles();
+    profiles.retain(|p| p.name != name);
+    save_profiles(&profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, last_used: &str) -> FlashProfile {
+        FlashProfile {
+            name: name.to_string(),
+            hostname: name.to_string(),
+            system_username: "pi".to_string(),
+            wifi_ssid: "Maison".to_string(),
+            wifi_country: "FR".to_string(),
+            timezone: "Europe/Paris".to_string(),
+            keymap: "fr".to_string(),
+            last_used: last_used.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_upsert_profile() {
+        let mut profiles = vec![profile("salon", "2026-01-01"), profile("chalet", "2026-02-01")];
+        upsert_profile(&mut profiles, profile("chalet", "2026-03-01"));
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "chalet");
+        assert_eq!(profiles[0].last_used, "2026-03-01");
+
+        for i in 0..MAX_PROFILES {
+            upsert_profile(&mut profiles, profile(&format!("pi-{}", i), "2026-04-01"));
+        }
+        assert_eq!(profiles.len(), MAX_PROFILES);
+        assert!(!profiles.iter().any(|p| p.name == "salon"));
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Optimisation serveur sans écran (profil matériel de la master_config)
+// =============================================================================
+//
+// Désactive Bluetooth, audio, détection caméra/écran et les restes du bureau,
+// réduit gpu_mem et masque les services inutiles, pour laisser la RAM à Jellyfin.
+// Le script tourne en root sur le Pi; les changements de config.txt prennent effet
+// au redémarrage qui suit l'installation de Docker.
+
+const SCRIPT_PATH: &str = "/tmp/jellysetup-headless.sh";
+
+fn default_mask_services() -> Vec<String> {
+    ["bluetooth", "hciuart", "triggerhappy", "ModemManager", "cups", "cups-browsed", "lightdm", "pulseaudio"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Section `hardware_profile` de la master_config
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct HardwareProfile {
+    pub disable_bluetooth: bool,
+    pub disable_audio: bool,
+    /// camera_auto_detect=0, display_auto_detect=0
+    pub disable_camera_display: bool,
+    /// Démarrage en console (multi-user.target) si un bureau est installé
+    pub disable_desktop: bool,
+    /// Mémoire réservée au GPU en Mo (ignorée sur Pi 5, qui la gère dynamiquement)
+    pub gpu_mem: Option<u32>,
+    /// Services systemd masqués s'ils existent
+    pub mask_services: Vec<String>,
+}
+
+impl Default for HardwareProfile {
+    fn default() -> Self {
+        Self {
+            disable_bluetooth: true,
+            disable_audio: true,
+            disable_camera_display: true,
+            disable_desktop: true,
+            gpu_mem: Some(16),
+            mask_services: default_mask_services(),
+        }
+    }
+}
+
+/// RAM disponible avant/après (les services masqués sont arrêtés immédiatement)
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessReport {
+    pub available_before_mb: u64,
+    pub available_after_mb: u64,
+    pub masked: Vec<String>,
+}
+
+impl HeadlessReport {
+    pub fn freed_mb(&self) -> i64 {
+        self.available_after_mb as i64 - self.available_before_mb as i64
+    }
+}
+
+/// Script shell (root) appliquant le profil
+pub fn script(profile: &HardwareProfile) -> String {
+    let mut lines = vec![
+        "CFG=/boot/firmware/config.txt; [ -f \"$CFG\" ] || CFG=/boot/config.txt".to_string(),
+        "set_cfg() { if grep -q \"^$1=\" \"$CFG\"; then sed -i \"s|^$1=.*|$1=$2|\" \"$CFG\"; else echo \"$1=$2\" >> \"$CFG\"; fi; }"
+            .to_string(),
+        "add_line() { grep -qx \"$1\" \"$CFG\" || echo \"$1\" >> \"$CFG\"; }".to_string(),
+        "echo \"MEM_BEFORE=$(awk '/MemAvailable/ {print int($2/1024)}' /proc/meminfo)\"".to_string(),
+    ];
+
+    if profile.disable_bluetooth {
+        lines.push("add_line dtoverlay=disable-bt".to_string());
+    }
+    if profile.disable_audio {
+        lines.push("set_cfg dtparam=audio off".to_string());
+    }
+    if profile.disable_camera_display {
+        lines.push("set_cfg camera_auto_detect 0".to_string());
+        lines.push("set_cfg display_auto_detect 0".to_string());
+    }
+    if let Some(gpu_mem) = profile.gpu_mem {
+        lines.push(format!("grep -q 'Raspberry Pi 5' /proc/device-tree/model || set_cfg gpu_mem {}", gpu_mem));
+    }
+    if profile.disable_desktop {
+        lines.push(
+            "if systemctl list-unit-files display-manager.service lightdm.service | grep -q enabled; then systemctl set-default multi-user.target; fi"
+                .to_string(),
+        );
+    }
+
+    // Noms de services restreints aux caractères d'unités systemd (injectés dans le script)
+    for service in profile
+        .mask_services
+        .iter()
+        .filter(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "-_@.".contains(c)))
+    {
+        lines.push(format!(
+            "if systemctl list-unit-files {s}.service | grep -q '^{s}.service'; then systemctl disable --now {s}.service 2>/dev/null; systemctl mask {s}.service && echo MASKED={s}; fi",
+            s = service
+        ));
+    }
+
+    lines.push("sync; echo 1 > /proc/sys/vm/drop_caches".to_string());
+    lines.push("echo \"MEM_AFTER=$(awk '/MemAvailable/ {print int($2/1024)}' /proc/meminfo)\"".to_string());
+    lines.join("\n")
+}
+
+/// Lit MEM_BEFORE / MEM_AFTER / MASKED dans la sortie du script
+pub fn parse_report(output: &str) -> HeadlessReport {
+    let mut report = HeadlessReport { available_before_mb: 0, available_after_mb: 0, masked: Vec::new() };
+    for line in output.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("MEM_BEFORE=") {
+            report.available_before_mb = value.parse().unwrap_or(0);
+        } else if let Some(value) = line.strip_prefix("MEM_AFTER=") {
+            report.available_after_mb = value.parse().unwrap_or(0);
+        } else if let Some(service) = line.strip_prefix("MASKED=") {
+            report.masked.push(service.to_string());
+        }
+    }
+    report
+}
+
+/// Profil matériel de la master_config (aucune optimisation s'il est absent)
+pub async fn resolve_profile() -> Option<HardwareProfile> {
+    crate::cloud::backend().fetch_master_config(Some("streaming"))
+        .await
+        .ok()
+        .flatten()
+        .and_then(|m| m.hardware_profile)
+}
+
+/// Applique le profil (`sudo`: "sudo" ou "echo 'pw' | sudo -S")
+pub async fn optimize(target: &SshTarget<'_>, profile: &HardwareProfile, sudo: &str) -> Result<HeadlessReport> {
+    let cmd = format!(
+        "cat > {path} << 'EOFHEADLESS'\n{script}\nEOFHEADLESS\n{sudo} sh {path}; rm -f {path}",
+        path = SCRIPT_PATH,
+        script = script(profile),
+        sudo = sudo
+    );
+    let report = parse_report(&target.exec(&cmd).await?);
+    tracing::info!(
+        "✅ {} services masked, {} Mo de RAM libérés ({} -> {} Mo disponibles)",
+        report.masked.len(),
+        report.freed_mb(),
+        report.available_before_mb,
+        report.available_after_mb
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_script() {
+        let profile = HardwareProfile {
+            disable_audio: false,
+            mask_services: vec!["cups".to_string(), "evil; rm -rf /".to_string()],
+            ..HardwareProfile::default()
+        };
+        let script = script(&profile);
+        assert!(script.contains("add_line dtoverlay=disable-bt"));
+        assert!(!script.contains("dtparam=audio"));
+        assert!(script.contains("set_cfg gpu_mem 16"));
+        assert!(script.contains("systemctl mask cups.service"));
+        assert!(!script.contains("evil"));
+
+        let report = parse_report("MEM_BEFORE=612\nMASKED=cups\nMASKED=bluetooth\nMEM_AFTER=688\n");
+        assert_eq!(report.masked, vec!["cups", "bluetooth"]);
+        assert_eq!(report.freed_mb(), 76);
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+
+use crate::ssh::SshTarget;
+use crate::status::StatusTarget;
+use crate::template_engine::TemplateVars;
+
+// =============================================================================
+// Tableau de bord Homepage (gethomepage.dev) - optionnel
+// =============================================================================
+//
+// Les tuiles sont déduites des services du docker-compose.yml généré, puis les
+// variables ({{PI_IP}}, {{RADARR_API_KEY}}, ...) sont remplacées par le moteur de
+// templates une fois les clés API récupérées.
+
+pub const HOMEPAGE_PORT: u16 = 3000;
+
+/// Tuile Homepage d'un service
+struct Tile {
+    group: &'static str,
+    title: &'static str,
+    icon: &'static str,
+    description: &'static str,
+    /// Variable de template de la clé API (active le widget Homepage du service)
+    api_key_var: Option<&'static str>,
+}
+
+fn tile(service: &str) -> Option<Tile> {
+    let (group, title, icon, description, api_key_var) = match service {
+        "jellyfin" => ("Médias", "Jellyfin", "jellyfin.png", "Films et séries", None),
+        "jellyseerr" => ("Médias", "Jellyseerr", "jellyseerr.png", "Demandes de films et séries", None),
+        "audiobookshelf" => ("Médias", "Audiobookshelf", "audiobookshelf.png", "Livres audio", None),
+        "radarr" => ("Gestion", "Radarr", "radarr.png", "Films", Some("RADARR_API_KEY")),
+        "sonarr" => ("Gestion", "Sonarr", "sonarr.png", "Séries", Some("SONARR_API_KEY")),
+        "lidarr" => ("Gestion", "Lidarr", "lidarr.png", "Musique", None),
+        "readarr" => ("Gestion", "Readarr", "readarr.png", "Livres", None),
+        "prowlarr" => ("Gestion", "Prowlarr", "prowlarr.png", "Indexeurs", Some("PROWLARR_API_KEY")),
+        "bazarr" => ("Gestion", "Bazarr", "bazarr.png", "Sous-titres", None),
+        "decypharr" => ("Système", "Decypharr", "mdi-download", "AllDebrid", None),
+        "flaresolverr" => ("Système", "FlareSolverr", "flaresolverr.png", "Bypass Cloudflare", None),
+        "supabazarr" => ("Système", "Supabazarr", "mdi-cloud-upload", "Sauvegardes", None),
+        _ => return None,
+    };
+    Some(Tile { group, title, icon, description, api_key_var })
+}
+
+/// Génère services.yaml (avec variables {{...}}) à partir des services du compose
+pub fn services_yaml_template(targets: &[StatusTarget]) -> String {
+    let mut groups: Vec<(&str, String)> = Vec::new();
+
+    for target in targets {
+        let Some(tile) = tile(&target.name) else { continue };
+
+        let mut entry = format!(
+            "    - {}:\n        icon: {}\n        href: http://{{{{PI_IP}}}}:{}\n        description: {}\n",
+            tile.title, tile.icon, target.port, tile.description
+        );
+        if let Some(var) = tile.api_key_var {
+            entry.push_str(&format!(
+                "        widget:\n          type: {}\n          url: http://{{{{PI_IP}}}}:{}\n          key: \"{{{{{}}}}}\"\n",
+                target.name, target.port, var
+            ));
+        }
+
+        match groups.iter_mut().find(|(g, _)| *g == tile.group) {
+            Some((_, entries)) => entries.push_str(&entry),
+            None => groups.push((tile.group, entry)),
+        }
+    }
+
+    let mut yaml = String::from("---\n# Généré par JellySetup\n");
+    for (group, entries) in groups {
+        yaml.push_str(&format!("- {}:\n{}", group, entries));
+    }
+    yaml
+}
+
+/// Service docker-compose de Homepage
+pub fn compose_service(timezone: &str) -> String {
+    format!(r#"
+  # Homepage - Tableau de bord des services
+  # Interface web: http://<pi-ip>:{port}
+  homepage:
+    image: ghcr.io/gethomepage/homepage:latest
+    container_name: homepage
+    restart: unless-stopped
+    ports:
+      - {port}:3000
+    volumes:
+      - ./homepage:/app/config
+      - /var/run/docker.sock:/var/run/docker.sock:ro
+    environment:
+      - TZ={timezone}
+      # Accès LAN par IP ou hostname.local
+      - HOMEPAGE_ALLOWED_HOSTS=*
+    deploy:
+      resources:
+        limits:
+          memory: 256M
+"#, port = HOMEPAGE_PORT, timezone = timezone)
+}
+
+/// Écrit services.yaml et settings.yaml (Homepage recharge sa config à chaud)
+pub async fn apply_config(target: &SshTarget<'_>, compose: &str, vars: &TemplateVars) -> Result<()> {
+    let targets = crate::status::status_targets(compose);
+    let services_yaml = vars.replace(&services_yaml_template(&targets));
+    let settings_yaml = vars.replace("---\ntitle: JellySetup - {{PI_HOSTNAME}}\ntheme: dark\ncolor: slate\n");
+
+    let cmd = format!(
+        "mkdir -p ~/media-stack/homepage && cat > ~/media-stack/homepage/services.yaml << 'EOFHOMEPAGE'\n{}\nEOFHOMEPAGE\ncat > ~/media-stack/homepage/settings.yaml << 'EOFHOMEPAGE'\n{}\nEOFHOMEPAGE",
+        services_yaml, settings_yaml
+    );
+    target.exec(&cmd).await?;
+
+    tracing::info!("✅ Dashboard configured ({} services)", targets.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_services_yaml_template() {
+        let targets = vec![
+            StatusTarget { name: "jellyfin".into(), port: 8096, path: "/health" },
+            StatusTarget { name: "radarr".into(), port: 7878, path: "/ping" },
+            StatusTarget { name: "homepage".into(), port: 3000, path: "/" },
+        ];
+        let yaml = services_yaml_template(&targets);
+        assert!(yaml.contains("- Médias:\n    - Jellyfin:"));
+        assert!(yaml.contains("href: http://{{PI_IP}}:8096"));
+        assert!(yaml.contains("type: radarr"));
+        assert!(yaml.contains("key: \"{{RADARR_API_KEY}}\""));
+        assert!(!yaml.contains("Homepage"));
+    }
+}
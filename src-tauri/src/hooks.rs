@@ -0,0 +1,175 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::install_state::InstallStep;
+use crate::procedure::Procedure;
+use crate::ssh::{self, SshTarget};
+
+// =============================================================================
+// Hooks d'étapes (pre/post) définis dans la procédure et dans master_config
+// =============================================================================
+//
+// Exemple (clé `hooks` de steps.json ou master_configs.hooks):
+//   [{ "step": "containers", "when": "post", "name": "Mon script",
+//      "command": "~/scripts/after-up.sh", "timeout": 120, "critical": false }]
+//
+// Les commandes sont exécutées sur le Pi depuis ~/media-stack, avec les variables
+// JELLYSETUP_STEP et JELLYSETUP_PHASE. La sortie est ajoutée à ~/jellysetup-logs/hooks.log.
+// Les hooks d'une étape déjà complétée ne sont pas rejoués lors d'une reprise.
+
+/// Étapes exposant des points d'accroche (les autres sont ignorées avec un avertissement)
+pub const HOOKABLE_STEPS: [InstallStep; 3] = [InstallStep::Compose, InstallStep::Containers, InstallStep::Configuration];
+
+const EXIT_MARKER: &str = "__HOOK_EXIT=";
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// Moment d'exécution du hook par rapport à l'étape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookPhase {
+    Pre,
+    Post,
+}
+
+/// Commande personnalisée attachée à une étape d'installation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepHook {
+    pub step: InstallStep,
+    pub when: HookPhase,
+    pub command: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Timeout en secondes (même convention que procedures/steps.json)
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Un hook critique en échec interrompt l'installation (sinon simple avertissement)
+    #[serde(default)]
+    pub critical: bool,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+impl StepHook {
+    fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.command)
+    }
+
+    /// Commande shell complète: script via heredoc (aucun échappement), code de sortie en fin de sortie
+    fn shell_command(&self) -> String {
+        format!(
+            "mkdir -p ~/jellysetup-logs; cd ~/media-stack 2>/dev/null || cd ~\n\
+             echo \"$(date): [hook {step:?}/{phase:?}] {label}\" >> ~/jellysetup-logs/hooks.log\n\
+             JELLYSETUP_STEP={step:?} JELLYSETUP_PHASE={phase:?} bash -s <<'JELLYSETUP_HOOK' 2>&1 | tee -a ~/jellysetup-logs/hooks.log\n\
+             {command}\n\
+             JELLYSETUP_HOOK\n\
+             echo \"{marker}${{PIPESTATUS[0]}}\"",
+            step = self.step,
+            phase = self.when,
+            label = self.label().replace('"', "'"),
+            command = self.command,
+            marker = EXIT_MARKER
+        )
+    }
+}
+
+/// Sépare la sortie du hook de son code de sortie (None si le marqueur est absent)
+pub fn parse_hook_output(output: &str) -> (String, Option<i32>) {
+    match output.rfind(EXIT_MARKER) {
+        Some(pos) => {
+            let code = output[pos + EXIT_MARKER.len()..].trim().parse().ok();
+            (output[..pos].trim_end().to_string(), code)
+        }
+        None => (output.trim_end().to_string(), None),
+    }
+}
+
+/// Hooks à exécuter pour une étape et une phase, dans l'ordre de déclaration
+pub fn hooks_for(hooks: &[StepHook], step: InstallStep, phase: HookPhase) -> Vec<&StepHook> {
+    hooks.iter().filter(|h| h.step == step && h.when == phase).collect()
+}
+
+/// Hooks de la procédure puis ceux de la master_config de son type (`configType`)
+pub async fn step_hooks(procedure: &Procedure) -> Vec<StepHook> {
+    let master_hooks = match crate::cloud::backend().fetch_master_config(procedure.config_type.as_deref()).await {
+        Ok(Some(master)) => master.hooks,
+        _ => Vec::new(),
+    };
+    if !procedure.hooks.is_empty() || !master_hooks.is_empty() {
+        tracing::info!(
+            "{} step hooks loaded ({} from the procedure, {} from master_config)",
+            procedure.hooks.len() + master_hooks.len(),
+            procedure.hooks.len(),
+            master_hooks.len()
+        );
+    }
+
+    let hooks: Vec<StepHook> = procedure.hooks.iter().cloned().chain(master_hooks).collect();
+    for hook in hooks.iter().filter(|h| !HOOKABLE_STEPS.contains(&h.step)) {
+        tracing::warn!("⚠️ Step {:?} has no hook point, ignoring {}", hook.step, hook.label());
+    }
+    hooks
+}
+
+async fn run_hook(target: &SshTarget<'_>, hook: &StepHook) -> Result<()> {
+    let options = ssh::SshOptions { command_timeout_secs: hook.timeout, ..ssh::default_options() };
+    let output = target.exec_with_options(&hook.shell_command(), &options).await?;
+
+    let (output, exit_code) = parse_hook_output(&output);
+    if !output.is_empty() {
+        tracing::info!("{}: {}", hook.label(), output);
+    }
+
+    match exit_code {
+        Some(0) => Ok(()),
+        Some(code) => Err(anyhow!("code de sortie {}", code)),
+        None => Err(anyhow!("code de sortie inconnu")),
+    }
+}
+
+/// Exécute les hooks d'une étape; seul l'échec d'un hook critique est une erreur
+pub async fn run_hooks(target: &SshTarget<'_>, hooks: &[StepHook], step: InstallStep, phase: HookPhase) -> Result<()> {
+    for hook in hooks_for(hooks, step, phase) {
+        tracing::info!("Running {:?}/{:?} hook: {}", step, phase, hook.label());
+
+        match run_hook(target, hook).await {
+            Ok(()) => tracing::info!("✅ {} completed", hook.label()),
+            Err(e) if hook.critical => {
+                tracing::error!("❌ {} failed: {}", hook.label(), e);
+                return Err(anyhow!("Hook {} en échec ({:?}/{:?}): {}", hook.label(), step, phase, e));
+            }
+            Err(e) => tracing::warn!("⚠️ {} failed (non critique): {}", hook.label(), e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks_parsing_and_output() {
+        let hooks: Vec<StepHook> = serde_json::from_str(
+            r#"[
+                { "step": "containers", "when": "post", "command": "docker ps" },
+                { "step": "configuration", "when": "pre", "command": "./before.sh", "timeout": 30, "critical": true }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(hooks[0].timeout, DEFAULT_TIMEOUT_SECS);
+        assert!(!hooks[0].critical);
+        assert_eq!(hooks_for(&hooks, InstallStep::Configuration, HookPhase::Pre).len(), 1);
+        assert!(hooks_for(&hooks, InstallStep::Containers, HookPhase::Pre).is_empty());
+
+        assert_eq!(parse_hook_output("ok\n__HOOK_EXIT=0\n"), ("ok".to_string(), Some(0)));
+        assert_eq!(parse_hook_output("boom\n__HOOK_EXIT=2"), ("boom".to_string(), Some(2)));
+        assert_eq!(parse_hook_output("killed"), ("killed".to_string(), None));
+
+        let mut procedure: serde_json::Value = serde_json::from_str(crate::compose::EMBEDDED_PROCEDURE).unwrap();
+        procedure["hooks"] = serde_json::json!([{ "step": "compose", "when": "pre", "command": "./check.sh" }]);
+        let procedure = Procedure::parse(&procedure.to_string()).unwrap();
+        assert_eq!(hooks_for(&procedure.hooks, InstallStep::Compose, HookPhase::Pre)[0].command, "./check.sh");
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use tokio::process::Command;
+
+use crate::compose::StackDefinition;
+use crate::rootfs::RootfsFile;
+use crate::ssh::{self, SshTarget};
+
+// =============================================================================
+// Images Docker pré-chargées sur la carte SD
+// =============================================================================
+//
+// Au flash (option `preload_images`), les images arm64 du stack sont tirées par
+// le Docker de l'ordinateur puis sauvées dans une archive (`docker save`, couches
+// partagées dédupliquées), gardée en cache et injectée dans la rootfs. Pendant
+// l'installation, `docker load` remplace alors le `docker compose pull` si toutes
+// les images du compose sont présentes (sinon le pull complète): utile pour
+// flasher plusieurs Pis ou quand la connexion du foyer est lente.
+
+/// Emplacement de l'archive sur le Pi (supprimée après chargement)
+pub const PRELOADED_ARCHIVE: &str = "/opt/jellysetup/images/stack-images.tar";
+const PLATFORM: &str = "linux/arm64";
+
+/// Images des services de base du stack (hors profils et services conditionnels)
+pub fn stack_images(stack: &StackDefinition) -> Vec<String> {
+    let mut images: Vec<String> = stack
+        .services
+        .iter()
+        .filter(|s| s.profile.is_none() && s.requires.is_none() && !s.image.contains("{{"))
+        .map(|s| s.image.clone())
+        .collect();
+    images.sort();
+    images.dedup();
+    images
+}
+
+fn archive_path(images: &[String]) -> Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    images.hash(&mut hasher);
+    let dir = dirs::cache_dir()
+        .ok_or_else(|| anyhow!("Cannot find cache directory"))?
+        .join("jellysetup")
+        .join("images");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("stack-{:016x}.tar", hasher.finish())))
+}
+
+async fn docker(args: &[&str]) -> Result<()> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| anyhow!("Docker introuvable sur cet ordinateur ({}): requis pour pré-charger les images", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("docker {}: {}", args[0], String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(())
+}
+
+/// Tire les images arm64 avec le Docker local et les sauve dans une archive (réutilisée si présente)
+pub async fn build_archive(images: &[String]) -> Result<PathBuf> {
+    let path = archive_path(images)?;
+    if path.exists() {
+        tracing::info!("Reusing {:?}", path);
+        return Ok(path);
+    }
+
+    for image in images {
+        tracing::info!("Pulling {} ({})...", image, PLATFORM);
+        docker(&["pull", "--platform", PLATFORM, image]).await?;
+    }
+    let partial = path.with_extension("partial");
+    let partial_str = partial.to_string_lossy().to_string();
+    let mut args = vec!["save", "-o", partial_str.as_str()];
+    args.extend(images.iter().map(String::as_str));
+    docker(&args).await?;
+    std::fs::rename(&partial, &path)?;
+
+    tracing::info!("✅ {} images saved to {:?}", images.len(), path);
+    Ok(path)
+}
+
+/// Fichier rootfs de l'archive (copié depuis le disque, pas chargé en mémoire)
+pub fn rootfs_file(archive: PathBuf) -> RootfsFile {
+    RootfsFile {
+        path: PRELOADED_ARCHIVE.to_string(),
+        content: Vec::new(),
+        mode: Some(0o644),
+        uid: None,
+        gid: None,
+        source: Some(archive),
+    }
+}
+
+/// Images du docker-compose.yml absentes du Pi (une ligne MISSING= par image)
+const MISSING_IMAGES_SCRIPT: &str = r#"cd ~/media-stack && for image in $(docker compose config --images); do
+  docker image inspect "$image" >/dev/null 2>&1 || echo "MISSING=$image"
+done"#;
+
+/// Charge l'archive si elle a été injectée au flash
+///
+/// None: aucune archive sur la carte; sinon les images du compose encore à tirer
+pub async fn load_preloaded(target: &SshTarget<'_>, sudo: &str) -> Result<Option<Vec<String>>> {
+    let cmd = format!(
+        "if [ -f {archive} ]; then docker load -i {archive} >/dev/null && {sudo} rm -f {archive} && echo PRELOADED; fi",
+        archive = PRELOADED_ARCHIVE,
+        sudo = sudo
+    );
+    // Plusieurs Go à décompresser sur la carte SD: bien au-delà du délai par défaut
+    let options = ssh::SshOptions {
+        command_timeout_secs: crate::config::delays::LONG_STEP_TIMEOUT_SECS,
+        ..ssh::default_options()
+    };
+    if !target.exec_with_options(&cmd, &options).await?.contains("PRELOADED") {
+        return Ok(None);
+    }
+
+    let missing: Vec<String> = target
+        .exec(MISSING_IMAGES_SCRIPT)
+        .await?
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("MISSING="))
+        .map(String::from)
+        .collect();
+    tracing::info!("✅ Docker images loaded from SD card ({} still to pull)", missing.len());
+    Ok(Some(missing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack_images() {
+        let stack = StackDefinition::embedded();
+        let images = stack_images(&stack);
+        assert!(images.contains(&"lscr.io/linuxserver/jellyfin:latest".to_string()));
+        assert!(!images.iter().any(|i| i.contains("lidarr")));
+        assert!(images.windows(2).all(|w| w[0] < w[1]));
+    }
+}
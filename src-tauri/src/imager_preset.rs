@@ -0,0 +1,213 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::FlashConfig;
+
+// =============================================================================
+// Préréglages Raspberry Pi Imager (personnalisation de l'OS)
+// =============================================================================
+//
+// Même clés que la section [imagecustomization] d'Imager (hostname, sshUserName,
+// wifiSSID, ...), exportées en JSON. Imager stocke le mot de passe utilisateur
+// haché (sha256-crypt "$5$...") et le Wi-Fi sous forme de PSK (64 caractères
+// hexadécimaux). La PSK est reprise telle quelle dans custom.toml; le mot de
+// passe utilisateur est redemandé (JellySetup s'en sert pour sudo pendant
+// l'installation). À l'export, les mots de passe ne sont jamais écrits.
+
+/// Préréglage tel que lu ou écrit par Imager
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagerPreset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_enabled: Option<bool>,
+    #[serde(default, alias = "sshUsername", skip_serializing_if = "Option::is_none")]
+    pub ssh_user_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_user_password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_use_password: Option<bool>,
+    /// Clés publiques, une par ligne
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_authorized_keys: Option<String>,
+    #[serde(default, rename = "wifiSSID", skip_serializing_if = "Option::is_none")]
+    pub wifi_ssid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wifi_password: Option<String>,
+    #[serde(default, rename = "wifiSSIDHidden", skip_serializing_if = "Option::is_none")]
+    pub wifi_ssid_hidden: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wifi_country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyboard_layout: Option<String>,
+}
+
+/// Champs du wizard pré-remplis depuis un préréglage Imager
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ImagerImport {
+    pub hostname: Option<String>,
+    pub system_username: Option<String>,
+    /// Seulement si le préréglage le contient en clair
+    pub system_password: Option<String>,
+    pub wifi_ssid: Option<String>,
+    pub wifi_password: Option<String>,
+    pub wifi_hidden: bool,
+    pub wifi_country: Option<String>,
+    pub timezone: Option<String>,
+    pub keymap: Option<String>,
+    /// Ajoutées à la clé JellySetup dans authorized_keys
+    pub ssh_authorized_keys: Vec<String>,
+    /// Valeurs ignorées, à ressaisir dans le wizard
+    pub warnings: Vec<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(String::from)
+}
+
+/// Mot de passe haché façon crypt(3) ("$5$sel$hash", "$6$...", "$y$...")
+fn is_crypt_hash(password: &str) -> bool {
+    password.starts_with('$') && password.matches('$').count() >= 3
+}
+
+impl ImagerPreset {
+    pub fn parse(json: &str) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| anyhow!("Préréglage Imager illisible: {}", e))?;
+        // Export complet: personnalisation sous "imagecustomization"
+        let customization = value.get("imagecustomization").cloned().unwrap_or(value);
+        if !customization.is_object() {
+            return Err(anyhow!("Préréglage Imager illisible: objet JSON attendu"));
+        }
+        Ok(serde_json::from_value(customization)?)
+    }
+
+    /// Valeurs utilisables par le wizard (secrets hachés écartés)
+    pub fn to_import(&self) -> ImagerImport {
+        let mut warnings = Vec::new();
+
+        let system_password = non_empty(&self.ssh_user_password).filter(|password| {
+            let plain = !is_crypt_hash(password);
+            if !plain {
+                warnings.push("Mot de passe utilisateur haché par Imager: à ressaisir".to_string());
+            }
+            plain
+        });
+        let wifi_password = non_empty(&self.wifi_password);
+        if self.ssh_enabled == Some(false) {
+            warnings.push("SSH désactivé dans Imager: JellySetup l'active toujours (installation à distance)".to_string());
+        }
+
+        ImagerImport {
+            hostname: non_empty(&self.hostname),
+            system_username: non_empty(&self.ssh_user_name),
+            system_password,
+            wifi_ssid: non_empty(&self.wifi_ssid),
+            wifi_password,
+            wifi_hidden: self.wifi_ssid_hidden.unwrap_or(false),
+            wifi_country: non_empty(&self.wifi_country).map(|c| c.to_uppercase()),
+            timezone: non_empty(&self.timezone),
+            keymap: non_empty(&self.keyboard_layout),
+            ssh_authorized_keys: self
+                .ssh_authorized_keys
+                .as_deref()
+                .unwrap_or_default()
+                .lines()
+                .map(str::trim)
+                .filter(|k| k.starts_with("ssh-") || k.starts_with("ecdsa-"))
+                .map(String::from)
+                .collect(),
+            warnings,
+        }
+    }
+
+    /// Préréglage équivalent à une configuration de flash (sans mots de passe)
+    pub fn from_flash_config(config: &FlashConfig, ssh_public_key: Option<&str>) -> Self {
+        let keys: Vec<&str> = ssh_public_key
+            .into_iter()
+            .chain(config.extra_authorized_keys.iter().map(String::as_str))
+            .filter(|k| !k.trim().is_empty())
+            .collect();
+        let text = |value: &str| Some(value.to_string()).filter(|v| !v.is_empty());
+        Self {
+            hostname: text(&config.hostname),
+            ssh_enabled: Some(true),
+            ssh_user_name: text(&config.system_username),
+            ssh_user_password: None,
+            ssh_use_password: Some(keys.is_empty()),
+            ssh_authorized_keys: Some(keys.join("\n")).filter(|k| !k.is_empty()),
+            wifi_ssid: text(&config.wifi_ssid),
+            wifi_password: None,
+            wifi_ssid_hidden: Some(config.wifi_hidden),
+            wifi_country: text(&config.wifi_country),
+            timezone: text(&config.timezone),
+            keyboard_layout: text(&config.keymap),
+        }
+    }
+}
+
+/// Lit un préréglage Imager (contenu du .json choisi dans le wizard)
+pub fn import_preset(content: &str) -> Result<ImagerImport> {
+    let import = ImagerPreset::parse(content)?.to_import();
+    tracing::info!("✅ Preset imported ({} warnings)", import.warnings.len());
+    Ok(import)
+}
+
+/// Configuration courante au format Imager (JSON à enregistrer)
+pub fn export_preset(config: &FlashConfig, ssh_public_key: Option<&str>) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&ImagerPreset::from_flash_config(config, ssh_public_key))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imager_preset() {
+        let preset = ImagerPreset::parse(
+            r#"{ "imagecustomization": {
+                "hostname": "salon", "sshEnabled": true, "sshUserName": "nico",
+                "sshUserPassword": "$5$abcdefgh$0123456789abcdef", "sshUsePassword": false,
+                "sshAuthorizedKeys": "ssh-ed25519 AAAA nico@mac\n\nnot-a-key",
+                "wifiSSID": "Maison", "wifiPassword": "c1d4f0e3b2a9c1d4f0e3b2a9c1d4f0e3b2a9c1d4f0e3b2a9c1d4f0e3b2a9c1d4",
+                "wifiSSIDHidden": true, "wifiCountry": "fr", "timezone": "Europe/Paris", "keyboardLayout": "fr"
+            } }"#,
+        )
+        .unwrap();
+        let import = preset.to_import();
+        assert_eq!(import.hostname.as_deref(), Some("salon"));
+        assert_eq!(import.system_username.as_deref(), Some("nico"));
+        assert_eq!(import.system_password, None);
+        // PSK reprise telle quelle (custom.toml accepte les PSK)
+        assert_eq!(
+            import.wifi_password.as_deref(),
+            Some("c1d4f0e3b2a9c1d4f0e3b2a9c1d4f0e3b2a9c1d4f0e3b2a9c1d4f0e3b2a9c1d4")
+        );
+        assert!(import.wifi_hidden);
+        assert_eq!(import.wifi_country.as_deref(), Some("FR"));
+        assert_eq!(import.ssh_authorized_keys, vec!["ssh-ed25519 AAAA nico@mac"]);
+        assert_eq!(import.warnings.len(), 1);
+
+        let plain = ImagerPreset::parse(r#"{ "wifiSSID": "Maison", "wifiPassword": "motdepasse" }"#).unwrap().to_import();
+        assert_eq!(plain.wifi_password.as_deref(), Some("motdepasse"));
+        assert!(ImagerPreset::parse("[1, 2]").is_err());
+
+        let config: FlashConfig = serde_json::from_value(serde_json::json!({
+            "sdPath": "/dev/rdisk4", "hostname": "jellypi", "systemUsername": "pi", "systemPassword": "secret",
+            "wifiSsid": "Maison", "wifiPassword": "wifi-pass", "wifiCountry": "FR", "timezone": "Europe/Paris",
+            "keymap": "fr", "extraAuthorizedKeys": ["ssh-ed25519 BBBB nico@mac"]
+        }))
+        .unwrap();
+        let exported = serde_json::to_value(ImagerPreset::from_flash_config(&config, Some("ssh-ed25519 AAAA jellysetup"))).unwrap();
+        assert_eq!(exported["hostname"], "jellypi");
+        assert_eq!(exported["wifiSSID"], "Maison");
+        assert_eq!(exported["sshAuthorizedKeys"], "ssh-ed25519 AAAA jellysetup\nssh-ed25519 BBBB nico@mac");
+        assert!(exported.get("sshUserPassword").is_none());
+        assert!(exported.get("wifiPassword").is_none());
+    }
+}
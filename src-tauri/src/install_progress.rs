@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+
+// =============================================================================
+// Progression fine des étapes longues (apt upgrade, docker compose pull)
+// =============================================================================
+//
+// Les sorties sont lues au fil de l'eau (flux SSH, clé privée) ou relues depuis
+// leur fichier de log (installation par mot de passe, commandes en arrière-plan).
+// Chaque tracker convertit les lignes en fraction 0.0..=1.0 de l'étape, que
+// `sub_percent` projette sur la plage de pourcentage de l'étape.
+
+/// Projette une fraction d'étape sur une plage de la barre globale
+pub fn sub_percent(start: u32, end: u32, fraction: f64) -> u32 {
+    start + ((end - start) as f64 * fraction.clamp(0.0, 1.0)).floor() as u32
+}
+
+/// Découpe un flux en lignes complètes (les chunks SSH coupent les lignes n'importe où)
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    pending: String,
+}
+
+impl LineBuffer {
+    pub fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.pending.push_str(&chunk.replace('\r', "\n"));
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=pos).collect();
+            let line = line.trim();
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        lines
+    }
+}
+
+// -----------------------------------------------------------------------------
+// apt update / upgrade
+// -----------------------------------------------------------------------------
+
+/// Progression d'un `apt update && apt upgrade`
+#[derive(Debug, Default)]
+pub struct AptProgress {
+    /// Paquets à mettre à jour/installer ("37 upgraded, 2 newly installed, ...")
+    total: Option<u32>,
+    /// Archives téléchargées après l'annonce du total
+    fetched: u32,
+    unpacked: u32,
+    configured: u32,
+    current: Option<String>,
+}
+
+impl AptProgress {
+    pub fn from_log(log: &str) -> Self {
+        let mut progress = Self::default();
+        log.lines().for_each(|line| progress.feed_line(line));
+        progress
+    }
+
+    pub fn feed_line(&mut self, line: &str) {
+        let line = line.trim();
+        if let Some(total) = parse_upgrade_summary(line) {
+            self.total = Some(self.total.unwrap_or(0) + total);
+        } else if line.starts_with("Get:") {
+            if self.total.is_some() {
+                self.fetched += 1;
+            }
+        } else if let Some(rest) = line.strip_prefix("Unpacking ") {
+            self.unpacked += 1;
+            self.current = package_name(rest);
+        } else if let Some(rest) = line.strip_prefix("Setting up ") {
+            self.configured += 1;
+            self.current = package_name(rest);
+        }
+    }
+
+    /// 10% pour apt update, 30% téléchargement, 60% dépaquetage + configuration
+    pub fn fraction(&self) -> f64 {
+        match self.total {
+            None => 0.05,
+            Some(0) => 1.0,
+            Some(total) => {
+                let total = total as f64;
+                let download = (self.fetched as f64 / total).min(1.0);
+                let install = ((self.unpacked + self.configured) as f64 / (2.0 * total)).min(1.0);
+                0.1 + 0.3 * download + 0.6 * install
+            }
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match (self.total, &self.current) {
+            (Some(total), Some(package)) => {
+                format!("Installation: {} ({}/{})", package, self.configured.min(total), total)
+            }
+            (Some(total), None) => format!("Téléchargement des paquets ({}/{})", self.fetched.min(total), total),
+            (None, _) => "Analyse des paquets...".to_string(),
+        }
+    }
+}
+
+/// "37 upgraded, 2 newly installed, 0 to remove and 0 not upgraded." -> 39
+fn parse_upgrade_summary(line: &str) -> Option<u32> {
+    if !line.contains(" upgraded, ") || !line.contains(" newly installed") {
+        return None;
+    }
+    let mut numbers = line.split(", ").filter_map(|part| part.split_whitespace().next()?.parse::<u32>().ok());
+    Some(numbers.next()? + numbers.next()?)
+}
+
+/// "libc6:arm64 (2.36-9) over (2.36-8) ..." -> "libc6"
+fn package_name(rest: &str) -> Option<String> {
+    let name = rest.split_whitespace().next()?.split(':').next()?;
+    Some(name.to_string()).filter(|n| !n.is_empty())
+}
+
+// -----------------------------------------------------------------------------
+// docker compose pull
+// -----------------------------------------------------------------------------
+
+/// Progression d'un `docker compose pull` (sortie non-TTY)
+#[derive(Debug, Default)]
+pub struct PullProgress {
+    total_images: usize,
+    /// Couche -> avancement (0.0 vue, 0.5 téléchargée, 1.0 extraite/déjà présente)
+    layers: HashMap<String, f64>,
+    pulled: HashSet<String>,
+}
+
+impl PullProgress {
+    pub fn new(total_images: usize) -> Self {
+        Self { total_images, ..Self::default() }
+    }
+
+    pub fn from_log(total_images: usize, log: &str) -> Self {
+        let mut progress = Self::new(total_images);
+        log.lines().for_each(|line| progress.feed_line(line));
+        progress
+    }
+
+    pub fn feed_line(&mut self, line: &str) {
+        let line = line.trim().trim_start_matches(['✔', '⠿', ' ']);
+        let Some((id, status)) = line.split_once(' ') else { return };
+        let status = status.trim();
+
+        let is_layer = id.len() == 12 && id.chars().all(|c| c.is_ascii_hexdigit());
+        if is_layer {
+            let step = match status {
+                s if s.starts_with("Pull complete") || s.starts_with("Already exists") => 1.0,
+                s if s.starts_with("Download complete") || s.starts_with("Extracting") => 0.5,
+                _ => 0.0,
+            };
+            let layer = self.layers.entry(id.to_string()).or_insert(0.0);
+            *layer = layer.max(step);
+        } else if status.starts_with("Pulled") || status.starts_with("Skipped") {
+            self.pulled.insert(id.to_string());
+        }
+    }
+
+    /// Moitié images terminées, moitié couches (le nombre de couches n'est connu qu'au fil du pull)
+    pub fn fraction(&self) -> f64 {
+        let images = if self.total_images == 0 {
+            0.0
+        } else {
+            (self.pulled.len() as f64 / self.total_images as f64).min(1.0)
+        };
+        if self.layers.is_empty() {
+            return images;
+        }
+        let layers = self.layers.values().sum::<f64>() / self.layers.len() as f64;
+        0.5 * images + 0.5 * layers
+    }
+
+    pub fn message(&self) -> String {
+        let done_layers = self.layers.values().filter(|s| **s >= 1.0).count();
+        format!(
+            "Images: {}/{} • couches {}/{}",
+            self.pulled.len().min(self.total_images),
+            self.total_images,
+            done_layers,
+            self.layers.len()
+        )
+    }
+}
+
+/// Nombre d'images du compose (une par service)
+pub fn compose_image_count(compose: &str) -> usize {
+    compose.lines().filter(|l| l.trim_start().starts_with("image:")).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_parsing() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.push("Reading package li").is_empty());
+        assert_eq!(buffer.push("sts...\r\nDone\n"), vec!["Reading package lists...", "Done"]);
+
+        let apt = AptProgress::from_log(
+            "Get:1 http://deb.debian.org bookworm InRelease\n\
+             2 upgraded, 0 newly installed, 0 to remove and 0 not upgraded.\n\
+             Get:2 http://deb.debian.org bookworm/main arm64 libc6\n\
+             Get:3 http://deb.debian.org bookworm/main arm64 curl\n\
+             Unpacking libc6:arm64 (2.36-9) over (2.36-8) ...\n\
+             Setting up libc6:arm64 (2.36-9) ...\n",
+        );
+        assert!((apt.fraction() - (0.1 + 0.3 + 0.6 * 0.5)).abs() < 1e-9);
+        assert_eq!(apt.message(), "Installation: libc6 (1/2)");
+        assert_eq!(sub_percent(0, 15, apt.fraction()), 10);
+
+        let pull = PullProgress::from_log(
+            2,
+            " jellyfin Pulling \n\
+             7264a8db6415 Pulling fs layer \n\
+             7264a8db6415 Pull complete \n\
+             a1b2c3d4e5f6 Download complete \n\
+             jellyfin Pulled \n",
+        );
+        assert!((pull.fraction() - (0.5 * 0.5 + 0.5 * 0.75)).abs() < 1e-9);
+        assert_eq!(pull.message(), "Images: 1/2 • couches 1/2");
+        assert_eq!(compose_image_count("services:\n  a:\n    image: x\n  b:\n    image: y\n"), 2);
+    }
+}
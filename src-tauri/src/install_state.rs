@@ -0,0 +1,301 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::notifications::DiscordNotifier;
+use crate::remote_resume::ResumePublisher;
+
+// =============================================================================
+// État d'installation (reprise après échec)
+// =============================================================================
+
+/// Étapes de l'installation, dans l'ordre d'exécution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallStep {
+    SystemUpdate,
+    Docker,
+    Structure,
+    Compose,
+    ImagePull,
+    Containers,
+    Configuration,
+    CloudSync,
+}
+
+impl InstallStep {
+    pub const ALL: [InstallStep; 8] = [
+        InstallStep::SystemUpdate,
+        InstallStep::Docker,
+        InstallStep::Structure,
+        InstallStep::Compose,
+        InstallStep::ImagePull,
+        InstallStep::Containers,
+        InstallStep::Configuration,
+        InstallStep::CloudSync,
+    ];
+
+    /// Libellé affiché (notifications)
+    pub fn label(&self) -> &'static str {
+        match self {
+            InstallStep::SystemUpdate => "Mise à jour système",
+            InstallStep::Docker => "Installation de Docker",
+            InstallStep::Structure => "Création des dossiers",
+            InstallStep::Compose => "Génération du docker-compose",
+            InstallStep::ImagePull => "Téléchargement des images",
+            InstallStep::Containers => "Démarrage des conteneurs",
+            InstallStep::Configuration => "Configuration des services",
+            InstallStep::CloudSync => "Synchronisation cloud",
+        }
+    }
+}
+
+/// Progression d'une installation, sauvegardée localement et dans Supabase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallState {
+    pub host: String,
+    pub pi_name: Option<String>,
+    pub completed: Vec<InstallStep>,
+    pub current_step: Option<InstallStep>,
+    pub failed_step: Option<InstallStep>,
+    pub last_error: Option<String>,
+    pub finished: bool,
+    pub updated_at: String,
+    /// Début de l'étape en cours (durées enregistrées pour `estimate_install`)
+    #[serde(skip)]
+    step_started: Option<Instant>,
+    /// Webhook Discord notifié à la fin de chaque étape
+    #[serde(skip)]
+    notifier: Option<DiscordNotifier>,
+    /// Point de reprise chiffré publié dans Supabase (reprise depuis un autre ordinateur)
+    #[serde(skip)]
+    resume_point: Option<ResumePublisher>,
+}
+
+impl InstallState {
+    fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            pi_name: None,
+            completed: Vec::new(),
+            current_step: None,
+            failed_step: None,
+            last_error: None,
+            finished: false,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            step_started: None,
+            notifier: None,
+            resume_point: None,
+        }
+    }
+
+    /// Démarre une installation: nouvel état, ou reprise de l'état précédent
+    pub fn start(host: &str, resume: bool) -> Result<Self> {
+        if !resume {
+            return Ok(Self::new(host));
+        }
+
+        match Self::load(host) {
+            Some(state) if !state.finished => {
+                tracing::info!(
+                    "Resuming installation on {} ({} steps already done, failed at {:?})",
+                    host,
+                    state.completed.len(),
+                    state.failed_step
+                );
+                Ok(state)
+            }
+            _ => Err(anyhow!("Aucune installation interrompue à reprendre pour {}", host)),
+        }
+    }
+
+    /// Charge l'état local d'une installation
+    pub fn load(host: &str) -> Option<Self> {
+        let content = std::fs::read_to_string(state_path(host)?).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Oublie l'état local d'un Pi (après désinstallation)
+    pub fn forget(host: &str) {
+        if let Some(path) = state_path(host) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    pub fn set_pi_name(&mut self, pi_name: &str) {
+        self.pi_name = Some(pi_name.to_string());
+    }
+
+    pub fn set_notifier(&mut self, notifier: Option<DiscordNotifier>) {
+        self.notifier = notifier;
+    }
+
+    pub fn set_resume_point(&mut self, publisher: Option<ResumePublisher>) {
+        self.resume_point = publisher;
+    }
+
+    pub fn is_done(&self, step: InstallStep) -> bool {
+        self.completed.contains(&step)
+    }
+
+    /// Commence une étape rejouée à chaque fois (rapide et idempotente)
+    pub fn begin(&mut self, step: InstallStep) {
+        self.current_step = Some(step);
+        self.step_started = Some(Instant::now());
+    }
+
+    /// Commence une étape coûteuse; retourne false si elle est déjà terminée (à sauter)
+    pub fn should_run(&mut self, step: InstallStep) -> bool {
+        if self.is_done(step) {
+            tracing::info!("⏭️ Skipping {:?} (already completed)", step);
+            return false;
+        }
+        self.begin(step);
+        true
+    }
+
+    /// Marque une étape comme terminée et sauvegarde
+    pub async fn complete(&mut self, step: InstallStep) {
+        if !self.is_done(step) {
+            self.completed.push(step);
+            if let Some(notifier) = &self.notifier {
+                notifier.phase_completed(step, self.completed.len()).await;
+            }
+        }
+        if let Ok(snapshot) = serde_json::to_string_pretty(&*self) {
+            let name = serde_json::to_value(step).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+            crate::workspace::write(&format!("snapshots/{}.json", name), &snapshot);
+        }
+        if self.current_step == Some(step) {
+            self.current_step = None;
+            if let Some(started) = self.step_started.take() {
+                crate::estimate::record_step_duration(step, started.elapsed().as_secs());
+            }
+        }
+        self.persist().await;
+    }
+
+    /// Enregistre l'issue de l'installation (succès, ou étape en échec)
+    pub async fn finish(&mut self, result: &Result<()>) {
+        match result {
+            Ok(()) => {
+                self.finished = true;
+                self.failed_step = None;
+                self.last_error = None;
+                tracing::info!("✅ Installation on {} finished", self.host);
+            }
+            Err(e) => {
+                self.failed_step = self.current_step;
+                self.last_error = Some(e.to_string());
+                tracing::error!("❌ Installation on {} failed at {:?}", self.host, self.failed_step);
+            }
+        }
+        self.current_step = None;
+        self.persist().await;
+    }
+
+    async fn persist(&mut self) {
+        self.updated_at = chrono::Utc::now().to_rfc3339();
+
+        if let Err(e) = self.save_local() {
+            tracing::warn!("⚠️ Could not save local state: {}", e);
+        }
+
+        if let Some(pi_name) = self.pi_name.clone() {
+            let state = serde_json::to_value(&*self).unwrap_or_default();
+            if let Err(e) = crate::cloud::backend().save_install_state(&pi_name, &state).await {
+                tracing::warn!("⚠️ Could not sync state to Supabase: {}", e);
+            }
+        }
+
+        if let Some(publisher) = &self.resume_point {
+            if let Err(e) = publisher.publish(self).await {
+                tracing::warn!("⚠️ Could not publish resume point: {}", e);
+            }
+        }
+    }
+
+    /// Écrit l'état local (aussi utilisé pour importer un point de reprise distant)
+    pub(crate) fn save_local(&self) -> Result<()> {
+        let path = state_path(&self.host).ok_or_else(|| anyhow!("Cannot determine config directory"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Fichier d'état local pour un host
+fn state_path(host: &str) -> Option<PathBuf> {
+    let file_name: String = host
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+
+    dirs::config_dir().map(|d| {
+        d.join("jellysetup")
+            .join("installs")
+            .join(format!("{}.json", file_name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Host propre au test: l'état local est écrit sous config_dir/jellysetup/installs
+    fn test_host(name: &str) -> String {
+        format!("test-{}-{}", name, uuid::Uuid::new_v4())
+    }
+
+    #[test]
+    fn test_should_run_skips_completed_steps() {
+        let mut state = InstallState::new("pi.local");
+        state.completed = vec![InstallStep::SystemUpdate, InstallStep::Docker];
+
+        assert!(!state.should_run(InstallStep::Docker));
+        assert_eq!(state.current_step, None);
+        assert!(state.should_run(InstallStep::Structure));
+        assert_eq!(state.current_step, Some(InstallStep::Structure));
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_failure() {
+        let host = test_host("resume");
+        let mut state = InstallState::new(&host);
+        state.completed = vec![InstallStep::SystemUpdate];
+        state.begin(InstallStep::Docker);
+        state.finish(&Err(anyhow!("apt indisponible"))).await;
+        assert_eq!(state.failed_step, Some(InstallStep::Docker));
+        assert_eq!(state.current_step, None);
+        assert!(!state.finished);
+
+        let mut resumed = InstallState::start(&host, true).unwrap();
+        InstallState::forget(&host);
+        assert_eq!(resumed.failed_step, Some(InstallStep::Docker));
+        assert_eq!(resumed.last_error.as_deref(), Some("apt indisponible"));
+        assert!(!resumed.should_run(InstallStep::SystemUpdate));
+        assert!(resumed.should_run(InstallStep::Docker));
+    }
+
+    #[test]
+    fn test_nothing_to_resume() {
+        let host = test_host("none");
+        let err = InstallState::start(&host, true).unwrap_err();
+        assert!(err.to_string().contains("Aucune installation interrompue"));
+
+        // Installation terminée: rien à reprendre non plus
+        let mut state = InstallState::new(&host);
+        state.finished = true;
+        state.save_local().unwrap();
+        let result = InstallState::start(&host, true);
+        InstallState::forget(&host);
+        assert!(result.is_err());
+
+        // Sans reprise: nouvel état vide
+        let fresh = InstallState::start(&host, false).unwrap();
+        assert!(fresh.completed.is_empty());
+    }
+}
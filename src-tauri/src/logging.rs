@@ -5,6 +5,7 @@
 // - Logs locaux sur le Pi (~/jellysetup-logs/)
 // - Logs Supabase dans le schéma dédié au Pi
 // - Support batch pour performance optimale
+// - Événement `install-log` vers le wizard (panneau de logs en direct)
 // - Niveaux de log: DEBUG, INFO, WARN, ERROR, SUCCESS, CRITICAL
 // =============================================================================
 
@@ -14,16 +15,21 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::sync::Arc;
 use std::time::Instant;
+use tauri::Window;
 use tokio::sync::Mutex;
+use ts_rs::TS;
 use uuid::Uuid;
 
+use crate::secret::SecretString;
+
 // =============================================================================
 // TYPES ET STRUCTURES
 // =============================================================================
 
 /// Niveaux de log supportés
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
 #[serde(rename_all = "UPPERCASE")]
+#[ts(export, export_to = "../src/bindings/")]
 pub enum LogLevel {
     Debug,
     Info,
@@ -121,12 +127,59 @@ impl LogEntry {
         self.tags = tags.iter().map(|s| s.to_string()).collect();
         self
     }
+
+    /// Masque les secrets (message, commande, sortie, détails) avant toute écriture
+    pub fn redact(&mut self) {
+        use crate::redact::{redact, redact_json};
+        self.message = redact(&self.message);
+        self.substep = self.substep.as_deref().map(redact);
+        self.ssh_command = self.ssh_command.as_deref().map(redact);
+        self.ssh_output = self.ssh_output.as_deref().map(redact);
+        if let Some(details) = self.details.as_mut() {
+            redact_json(details);
+        }
+    }
+}
+
+/// Entrée envoyée au wizard par l'événement `install-log` (déjà masquée)
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct InstallLogEvent {
+    /// RFC 3339
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub step: String,
+    pub substep: Option<String>,
+    pub message: String,
+    pub progress_percent: Option<i32>,
+    pub ssh_exit_code: Option<i32>,
+}
+
+impl From<&LogEntry> for InstallLogEvent {
+    fn from(entry: &LogEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp.to_rfc3339(),
+            level: entry.level,
+            step: entry.step.clone(),
+            substep: entry.substep.clone(),
+            message: entry.message.clone(),
+            progress_percent: entry.progress_percent,
+            ssh_exit_code: entry.ssh_exit_code,
+        }
+    }
 }
 
 // =============================================================================
 // INSTALLATION LOGGER - Logger principal pour une installation
 // =============================================================================
 
+/// Authentification SSH du logger (les deux chemins d'installation)
+#[derive(Clone)]
+enum SshAuth {
+    Key(SecretString),
+    Password(SecretString),
+}
+
 /// Logger pour une installation spécifique
 pub struct InstallationLogger {
     /// Nom du Pi (utilisé pour le schéma Supabase)
@@ -137,8 +190,8 @@ pub struct InstallationLogger {
     pub ssh_host: String,
     /// Username SSH
     pub ssh_username: String,
-    /// Password SSH
-    pub ssh_password: String,
+    /// Clé privée ou mot de passe SSH
+    ssh_auth: SshAuth,
     /// Session ID unique pour cette installation
     pub session_id: String,
     /// Version de l'installateur
@@ -149,10 +202,12 @@ pub struct InstallationLogger {
     step_timer: Arc<Mutex<Option<Instant>>>,
     /// Étape courante
     current_step: Arc<Mutex<String>>,
+    /// Fenêtre du wizard qui reçoit les événements `install-log`
+    window: Option<Window>,
 }
 
 impl InstallationLogger {
-    /// Crée un nouveau logger pour une installation
+    /// Crée un nouveau logger pour une installation (authentification par mot de passe)
     pub fn new(
         pi_name: &str,
         pi_ip: &str,
@@ -160,21 +215,59 @@ impl InstallationLogger {
         ssh_username: &str,
         ssh_password: &str,
         installer_version: &str,
+    ) -> Self {
+        crate::redact::register(ssh_password);
+        let auth = SshAuth::Password(ssh_password.into());
+        Self::with_auth(pi_name, pi_ip, ssh_host, ssh_username, auth, installer_version)
+    }
+
+    /// Crée un nouveau logger pour une installation (authentification par clé)
+    pub fn new_with_key(
+        pi_name: &str,
+        pi_ip: &str,
+        ssh_host: &str,
+        ssh_username: &str,
+        private_key: &str,
+        installer_version: &str,
+    ) -> Self {
+        let auth = SshAuth::Key(private_key.into());
+        Self::with_auth(pi_name, pi_ip, ssh_host, ssh_username, auth, installer_version)
+    }
+
+    fn with_auth(
+        pi_name: &str,
+        pi_ip: &str,
+        ssh_host: &str,
+        ssh_username: &str,
+        ssh_auth: SshAuth,
+        installer_version: &str,
     ) -> Self {
         Self {
             pi_name: pi_name.to_string(),
             pi_ip: pi_ip.to_string(),
             ssh_host: ssh_host.to_string(),
             ssh_username: ssh_username.to_string(),
-            ssh_password: ssh_password.to_string(),
+            ssh_auth,
             session_id: Uuid::new_v4().to_string(),
             installer_version: installer_version.to_string(),
             log_buffer: Arc::new(Mutex::new(Vec::new())),
             step_timer: Arc::new(Mutex::new(None)),
             current_step: Arc::new(Mutex::new(String::new())),
+            window: None,
         }
     }
 
+    /// Diffuse chaque entrée au wizard (événement `install-log`)
+    pub fn with_window(mut self, window: Window) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Cible SSH du Pi
+    pub fn ssh_target(&self) -> crate::ssh::SshTarget<'_> {
+        ssh_target(&self.ssh_host, &self.ssh_username, &self.ssh_auth)
+    }
+
     /// Initialise le système de logs (crée le dossier local + schéma Supabase)
     pub async fn initialize(&self) -> Result<()> {
         // 1. Créer le dossier de logs sur le Pi
@@ -183,18 +276,13 @@ impl InstallationLogger {
             self.session_id
         );
 
-        if let Err(e) = crate::ssh::execute_command_password(
-            &self.ssh_host,
-            &self.ssh_username,
-            &self.ssh_password,
-            &init_cmd,
-        ).await {
-            println!("[Logger] Warning: could not create log dir on Pi: {}", e);
+        if let Err(e) = self.ssh_target().exec(&init_cmd).await {
+            tracing::warn!("could not create log dir on Pi: {}", e);
         }
 
         // 2. Initialiser le schéma Supabase
         if let Err(e) = crate::supabase::ensure_schema_initialized(&self.pi_name).await {
-            println!("[Logger] Warning: could not init Supabase schema: {}", e);
+            tracing::warn!("could not init Supabase schema: {}", e);
         }
 
         // 3. Logger le début de session
@@ -273,22 +361,29 @@ impl InstallationLogger {
 
     /// Log une entrée complète
     pub async fn log_entry(&self, mut entry: LogEntry) {
+        // Rien ne sort en clair: console, install.log, Pi et Supabase
+        entry.redact();
+
         // Ajouter les métadonnées
         entry.installer_version = Some(self.installer_version.clone());
         if entry.session_id.is_none() {
             entry.session_id = Some(self.session_id.clone());
         }
 
-        // Afficher dans la console
-        let emoji = match entry.level {
-            LogLevel::Debug => "🔍",
-            LogLevel::Info => "ℹ️",
-            LogLevel::Warn => "⚠️",
-            LogLevel::Error => "❌",
-            LogLevel::Success => "✅",
-            LogLevel::Critical => "🚨",
-        };
-        println!("{} [{}] [{}] {}", emoji, entry.level, entry.step, entry.message);
+        // Afficher dans la console (niveau tracing correspondant)
+        match entry.level {
+            LogLevel::Debug => tracing::debug!("[{}] {}", entry.step, entry.message),
+            LogLevel::Info => tracing::info!("[{}] {}", entry.step, entry.message),
+            LogLevel::Success => tracing::info!("✅ [{}] {}", entry.step, entry.message),
+            LogLevel::Warn => tracing::warn!("⚠️ [{}] {}", entry.step, entry.message),
+            LogLevel::Error => tracing::error!("❌ [{}] {}", entry.step, entry.message),
+            LogLevel::Critical => tracing::error!("🚨 [{}] {}", entry.step, entry.message),
+        }
+
+        // Panneau de logs du wizard
+        if let Some(window) = &self.window {
+            let _ = window.emit("install-log", InstallLogEvent::from(&entry));
+        }
 
         // Log local sur le Pi (non-bloquant)
         let local_log = format!(
@@ -298,17 +393,18 @@ impl InstallationLogger {
             entry.step,
             entry.message
         );
+        crate::workspace::append("install.log", &local_log);
 
         let ssh_host = self.ssh_host.clone();
         let ssh_user = self.ssh_username.clone();
-        let ssh_pass = self.ssh_password.clone();
+        let ssh_auth = self.ssh_auth.clone();
 
         tokio::spawn(async move {
             let cmd = format!(
                 "echo '{}' >> ~/jellysetup-logs/install.log",
                 local_log.replace("'", "'\\''")
             );
-            crate::ssh::execute_command_password(&ssh_host, &ssh_user, &ssh_pass, &cmd).await.ok();
+            ssh_target(&ssh_host, &ssh_user, &ssh_auth).exec(&cmd).await.ok();
         });
 
         // Ajouter au buffer pour envoi batch à Supabase
@@ -332,13 +428,6 @@ impl InstallationLogger {
         let logs: Vec<LogEntry> = buffer.drain(..).collect();
         drop(buffer);
 
-        // Envoyer à Supabase via l'Edge Function SÉCURISÉE (clé ANON uniquement)
-        let client = reqwest::Client::new();
-        let supabase_url = crate::supabase::get_supabase_url_public();
-        // SÉCURITÉ: On utilise la clé ANON (publique) et PAS la SERVICE_KEY
-        // L'Edge Function jellysetup-logs vérifie le token et utilise ses propres droits
-        let anon_key = crate::supabase::get_supabase_anon_key();
-
         let body = json!({
             "logs": logs.iter().map(|l| json!({
                 "level": l.level.to_string(),
@@ -357,36 +446,13 @@ impl InstallationLogger {
             })).collect::<Vec<_>>()
         });
 
-        // Utiliser le hostname (pi_name) pour le schéma, pas l'IP
-        let schema_name = self.pi_name.to_lowercase()
-            .chars()
-            .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
-            .collect::<String>();
-
-        // Nouvelle Edge Function sécurisée qui accepte la clé ANON
-        let url = format!("{}/functions/v1/jellysetup-logs?hostname={}", supabase_url, schema_name);
-        println!("[Logger] Sending {} logs to: {}", logs.len(), url);
-
-        match client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", anon_key))
-            .header("Content-Type", "application/json")
-            .header("X-Pi-Hostname", &self.pi_name)
-            .json(&body)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let status = response.status();
-                if status.is_success() {
-                    println!("[Logger] ✅ Logs sent successfully ({} logs)", logs.len());
-                } else {
-                    let error_text = response.text().await.unwrap_or_default();
-                    println!("[Logger] ❌ Supabase returned error {}: {}", status, error_text);
-                }
-            }
+        tracing::info!("Sending {} logs for {}", logs.len(), self.pi_name);
+        match send_logs(&self.pi_name, &body).await {
+            Ok(()) => tracing::info!("✅ Logs sent successfully ({} logs)", logs.len()),
             Err(e) => {
-                println!("[Logger] ❌ Network error sending logs: {}", e);
+                tracing::error!("❌ Error sending logs: {}", e);
+                let write = crate::sync_outbox::PendingWrite::InstallLogs { pi_name: self.pi_name.clone(), body };
+                crate::sync_outbox::queue_if_retryable(write, &e);
             }
         }
     }
@@ -404,6 +470,40 @@ impl InstallationLogger {
     }
 }
 
+/// Envoie un lot de logs à l'Edge Function jellysetup-logs
+pub(crate) async fn send_logs(pi_name: &str, body: &serde_json::Value) -> Result<()> {
+    // Envoyer à Supabase via l'Edge Function SÉCURISÉE (clé ANON uniquement)
+    let client = crate::supabase::http_client()?;
+    let supabase_url = crate::supabase::get_supabase_url_public();
+    // SÉCURITÉ: On utilise la clé ANON (publique) et PAS la SERVICE_KEY
+    // L'Edge Function jellysetup-logs vérifie le token du Pi et utilise ses propres droits
+
+    // Utiliser le hostname (pi_name) pour le schéma, pas l'IP
+    let schema_name = pi_name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect::<String>();
+
+    // Nouvelle Edge Function sécurisée qui accepte la clé ANON
+    let url = format!("{}/functions/v1/jellysetup-logs?hostname={}", supabase_url, schema_name);
+    let request = client
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .header("X-Pi-Hostname", pi_name)
+        .json(body);
+    let response = crate::supabase::with_pi_auth(request, pi_name).send().await?;
+
+    let status = response.status();
+    let error_text = if status.is_success() { String::new() } else { response.text().await.unwrap_or_default() };
+    if status.is_server_error() {
+        return Err(crate::supabase::Unavailable(format!("{} - {}", status, error_text)).into());
+    }
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("Supabase returned error {}: {}", status, error_text));
+    }
+    Ok(())
+}
+
 // =============================================================================
 // MACROS UTILITAIRES
 // =============================================================================
@@ -441,6 +541,13 @@ macro_rules! log_debug {
 // HELPER FUNCTIONS
 // =============================================================================
 
+fn ssh_target<'a>(host: &'a str, username: &'a str, auth: &'a SshAuth) -> crate::ssh::SshTarget<'a> {
+    match auth {
+        SshAuth::Key(private_key) => crate::ssh::SshTarget::Key { host, username, private_key: private_key.expose() },
+        SshAuth::Password(password) => crate::ssh::SshTarget::Password { host, username, password: password.expose() },
+    }
+}
+
 /// Exécute une commande SSH et log automatiquement le résultat
 pub async fn execute_and_log(
     logger: &InstallationLogger,
@@ -449,12 +556,7 @@ pub async fn execute_and_log(
 ) -> Result<String> {
     let start = Instant::now();
 
-    match crate::ssh::execute_command_password(
-        &logger.ssh_host,
-        &logger.ssh_username,
-        &logger.ssh_password,
-        command,
-    ).await {
+    match logger.ssh_target().exec(command).await {
         Ok(output) => {
             let duration = start.elapsed().as_millis() as i64;
             logger.log_with_details(
@@ -490,12 +592,7 @@ pub async fn execute_and_log_full(
     // On va parser le code de sortie depuis la commande
     let wrapped_cmd = format!("{}; echo \"EXIT_CODE:$?\"", command);
 
-    match crate::ssh::execute_command_password(
-        &logger.ssh_host,
-        &logger.ssh_username,
-        &logger.ssh_password,
-        &wrapped_cmd,
-    ).await {
+    match logger.ssh_target().exec(&wrapped_cmd).await {
         Ok(output) => {
             let duration = start.elapsed().as_millis() as i64;
 
@@ -11,18 +11,80 @@ mod logging;
 mod master_config;
 mod template_engine;
 mod services;
+mod install_state;
+mod operations;
+mod preflight;
+mod status;
+mod homepage;
+mod uptime;
+mod hooks;
+mod extra_compose;
+mod drift;
+mod config_history;
+mod estimate;
+mod terminal;
+mod tunnels;
+mod compose;
+mod flash_profiles;
+mod registration;
+mod install_progress;
+mod verification;
+mod cloudflare;
+mod system_locale;
+mod notifications;
+mod headless;
+mod alldebrid;
+mod debrid_refresh;
+mod uninstall;
+mod requests_sync;
+mod stack_update;
+mod sync_play;
+mod transcode_test;
+mod docker;
+mod rootfs;
+mod monitoring;
+mod image_cache;
+mod pi_metrics;
+mod delta_sync;
+mod pi_registry;
+mod adopt;
+mod config;
+mod backup;
+mod mdns;
+mod procedure;
+mod remote_access;
+mod remote_resume;
+mod imager_preset;
+mod offline_bundle;
+mod sync_outbox;
+mod secret;
+mod cloud;
+mod workspace;
+mod diagnostics;
+mod secrets;
+mod boot_recovery;
+mod vault;
+mod addons;
+mod ssh_keys;
+mod redact;
+mod app_logs;
+mod support_bundle;
+mod account;
 
 use serde::{Deserialize, Serialize};
 use tauri::{Manager, Window};
+use ts_rs::TS;
 
 // =============================================================================
 // Types
 // =============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
 pub struct SDCard {
     pub path: String,
     pub name: String,
+    #[ts(type = "number")]
     pub size: u64,
     pub removable: bool,
 }
@@ -34,52 +96,250 @@ pub struct FlashConfig {
     // Système
     pub hostname: String,
     pub system_username: String,
-    pub system_password: String,
+    pub system_password: secret::SecretString,
     // WiFi
     pub wifi_ssid: String,
-    pub wifi_password: String,
+    pub wifi_password: secret::SecretString,
     pub wifi_country: String,
+    #[serde(default)]
+    pub wifi_hidden: bool,
     // Locale
     pub timezone: String,
     pub keymap: String,
+    /// Démarrer malgré une batterie faible / un cache presque plein
+    #[serde(default)]
+    pub host_overrides: preflight::HostOverrides,
+    /// Fichiers déposés dans la rootfs avant le flash (docker-compose, agent, ...)
+    #[serde(default)]
+    pub rootfs_files: Vec<rootfs::RootfsFile>,
+    /// Durcissement sshd injecté dans la rootfs
+    #[serde(default)]
+    pub harden_sshd: bool,
+    /// Images Docker du stack pré-chargées sur la carte (Docker requis sur l'ordinateur)
+    #[serde(default)]
+    pub preload_images: bool,
+    /// Clés publiques autorisées en plus de celle de JellySetup (import Raspberry Pi Imager)
+    #[serde(default)]
+    pub extra_authorized_keys: Vec<String>,
+    /// Écrire userconf.txt même si l'image lit custom.toml
+    #[serde(default)]
+    pub keep_userconf: bool,
+}
+
+impl FlashConfig {
+    /// Fichiers à injecter dans la rootfs (vide: image flashée telle quelle)
+    pub fn rootfs_files(&self) -> Vec<rootfs::RootfsFile> {
+        let mut files = self.rootfs_files.clone();
+        if self.harden_sshd {
+            files.push(rootfs::sshd_hardening());
+        }
+        files
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallConfig {
-    pub alldebrid_api_key: String,
+    pub alldebrid_api_key: secret::SecretString,
     pub jellyfin_username: String,
-    pub jellyfin_password: String,
+    pub jellyfin_password: secret::SecretString,
     pub jellyfin_server_name: String,
     pub admin_email: Option<String>,
-    pub ygg_passkey: Option<String>,
-    pub discord_webhook: Option<String>,
-    pub cloudflare_token: Option<String>,
+    #[serde(default)]
+    pub admin_display_name: Option<String>,
+    #[serde(default)]
+    pub preferred_language: Option<String>,
+    pub ygg_passkey: Option<secret::SecretString>,
+    /// Webhook Discord notifié du déroulement de l'installation
+    pub discord_webhook: Option<secret::SecretString>,
+    pub cloudflare_token: Option<secret::SecretString>,
+    /// Provisionnement automatique du tunnel (prioritaire sur un token collé à la main)
+    #[serde(default)]
+    pub cloudflare: Option<cloudflare::CloudflareSetup>,
+    #[serde(default)]
+    pub jump_host: Option<ssh::JumpHost>,
+    /// Surcharge de l'arborescence des médias (sinon master_config, sinon défaut)
+    #[serde(default)]
+    pub media_paths: Option<master_config::MediaPaths>,
+    /// Délai maximum d'attente d'un redémarrage du Pi (secondes, 600 par défaut)
+    #[serde(default)]
+    pub reboot_timeout_secs: Option<u64>,
+    /// Tableau de bord Homepage (http://<pi>:3000)
+    #[serde(default)]
+    pub homepage: bool,
+    /// Surveillance externe (ping healthchecks.io / Uptime Kuma quand le serveur répond)
+    #[serde(default)]
+    pub uptime_monitor: Option<uptime::UptimeMonitor>,
+    /// Relecture des configurations master_config avant application (événement "config-review")
+    #[serde(default)]
+    pub review_configs: bool,
+    /// Contenu d'un extra-compose.yml: services personnalisés ajoutés au stack
+    #[serde(default)]
+    pub extra_compose: Option<String>,
+    #[serde(default)]
+    pub host_overrides: preflight::HostOverrides,
+    /// Services optionnels à installer (vide = tous), les services requis sont toujours installés
+    /// Les profils de bibliothèques (anime, lidarr, readarr, audiobookshelf) ne sont
+    /// installés que s'ils sont cochés
+    #[serde(default)]
+    pub enabled_services: Vec<String>,
+    /// Fuseau horaire des conteneurs (sinon celui du Pi, sinon Europe/Paris)
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Propriétaire des fichiers créés par les conteneurs (sinon uid/gid de l'utilisateur SSH)
+    #[serde(default)]
+    pub puid: Option<u32>,
+    #[serde(default)]
+    pub pgid: Option<u32>,
+    /// Locale du système du Pi (sinon déduite de la langue préférée)
+    #[serde(default)]
+    pub system_locale: Option<system_locale::SystemLocale>,
+    /// Chiffre le point de reprise publié dans Supabase (reprise depuis un autre ordinateur)
+    #[serde(default)]
+    pub resume_password: Option<secret::SecretString>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl InstallConfig {
+    /// Enregistre les secrets saisis pour les masquer dans les journaux
+    pub fn register_secrets(&self) {
+        let secrets = [Some(&self.alldebrid_api_key), Some(&self.jellyfin_password)]
+            .into_iter()
+            .chain(
+                [&self.ygg_passkey, &self.discord_webhook, &self.cloudflare_token, &self.resume_password]
+                    .map(Option::as_ref),
+            )
+            .chain([self.cloudflare.as_ref().map(|setup| &setup.api_token)]);
+        for secret in secrets.flatten() {
+            redact::register(secret.expose());
+        }
+    }
+
+    /// Le service fait-il partie du stack sélectionné ?
+    pub fn service_enabled(&self, service: &str) -> bool {
+        if services::profiles::PROFILE_SERVICES.contains(&service) {
+            return self.enabled_services.iter().any(|s| s == service);
+        }
+        !services::OPTIONAL_SERVICES.contains(&service)
+            || self.enabled_services.is_empty()
+            || self.enabled_services.iter().any(|s| s == service)
+    }
+
+    /// Profils de bibliothèques cochés parmi les services
+    pub fn profiles(&self) -> Vec<services::profiles::MediaProfile> {
+        services::profiles::MediaProfile::ALL.into_iter().filter(|p| self.service_enabled(p.service())).collect()
+    }
+
+    /// Services optionnels exclus par l'utilisateur
+    pub fn disabled_services(&self) -> Vec<String> {
+        services::OPTIONAL_SERVICES
+            .iter()
+            .filter(|s| !self.service_enabled(s))
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    /// Email de l'admin (Jellyseerr, notifications)
+    pub fn admin_email(&self) -> &str {
+        self.admin_email
+            .as_deref()
+            .filter(|e| !e.trim().is_empty())
+            .unwrap_or("admin@easyjelly.local")
+    }
+
+    /// Nom affiché de l'admin (par défaut le nom d'utilisateur Jellyfin)
+    pub fn admin_display_name(&self) -> &str {
+        self.admin_display_name
+            .as_deref()
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or(&self.jellyfin_username)
+    }
+
+    /// Arborescence des médias effective (résolue en début d'installation)
+    pub fn media_paths(&self) -> master_config::MediaPaths {
+        self.media_paths.clone().unwrap_or_default()
+    }
+
+    /// TZ/PUID/PGID effectifs (résolus en début d'installation)
+    pub fn container_env(&self) -> compose::ContainerEnv {
+        compose::ContainerEnv::resolve(self.timezone.as_deref(), self.puid, self.pgid, "")
+    }
+
+    /// Délai maximum d'attente d'un redémarrage (cartes SD lentes: augmenter)
+    pub fn reboot_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.reboot_timeout_secs.unwrap_or(600))
+    }
+
+    /// Langue préférée (code ISO 639-1, "fr" par défaut, ou si le code est invalide)
+    pub fn preferred_language(&self) -> &str {
+        self.preferred_language
+            .as_deref()
+            .filter(|l| (2..=3).contains(&l.len()) && l.chars().all(|c| c.is_ascii_lowercase()))
+            .unwrap_or("fr")
+    }
+
+    /// Locale système du Pi (LANG, LC_ALL, police console)
+    pub fn system_locale(&self) -> system_locale::SystemLocale {
+        self.system_locale
+            .clone()
+            .unwrap_or_else(|| system_locale::SystemLocale::for_language(self.preferred_language()))
+    }
+
+    /// Pays des métadonnées déduit de la langue
+    pub fn metadata_country(&self) -> &'static str {
+        system_locale::country_for_language(self.preferred_language())
+    }
+
+    /// Langue audio/sous-titres au format ISO 639-2 (utilisé par Jellyfin)
+    pub fn media_language(&self) -> &str {
+        match self.preferred_language() {
+            "fr" => "fre",
+            "en" => "eng",
+            "es" => "spa",
+            "de" => "ger",
+            "it" => "ita",
+            "pt" => "por",
+            lang => lang,
+        }
+    }
+
+    /// Paramètres du compte admin, chiffrés avec le mot de passe Jellyfin pour Supabase
+    pub fn encrypted_admin_account(&self) -> anyhow::Result<String> {
+        let account = serde_json::json!({
+            "email": self.admin_email(),
+            "display_name": self.admin_display_name(),
+            "preferred_language": self.preferred_language(),
+        });
+        crypto::encrypt_private_key(&account.to_string(), self.jellyfin_password.expose())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
 pub struct JellyfinAuth {
     pub server_id: String,
     pub access_token: String,
     pub user_id: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
 pub struct FlashProgress {
     pub step: String,
     pub percent: u32,
     pub message: String,
     pub speed: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
     pub jellyfin_auth: Option<JellyfinAuth>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SSHCredentials {
     pub public_key: String,
-    pub private_key: String,
+    pub private_key: secret::SecretString,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
 pub struct PiInfo {
     pub ip: String,
     pub hostname: String,
@@ -146,41 +406,69 @@ fn open_disk_access_settings() {
     }
 }
 
-/// Génère une paire de clés SSH
+/// Génère une paire de clés SSH (Ed25519 par défaut, RSA ou ECDSA; phrase de passe optionnelle)
+#[tauri::command]
+async fn generate_ssh_keys(
+    key_type: Option<ssh_keys::SshKeyType>,
+    passphrase: Option<secret::SecretString>,
+) -> Result<SSHCredentials, String> {
+    ssh_keys::generate(key_type.unwrap_or_default(), passphrase)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Importe une clé SSH existante (OpenSSH ou PEM, éventuellement protégée par une phrase de passe)
 #[tauri::command]
-async fn generate_ssh_keys() -> Result<SSHCredentials, String> {
-    crypto::generate_ssh_keypair()
+async fn import_ssh_key(
+    private_key: secret::SecretString,
+    passphrase: Option<secret::SecretString>,
+) -> Result<ssh_keys::ImportedSshKey, String> {
+    ssh_keys::import(private_key.expose(), passphrase)
         .await
         .map_err(|e| e.to_string())
 }
 
-/// Flash la carte SD avec Raspberry Pi OS
+/// Génère un mot de passe système/Jellyfin tapable sur tous les claviers FR/EN
+#[tauri::command]
+fn generate_strong_password(length: Option<usize>) -> Result<crypto::GeneratedPassword, String> {
+    crypto::generate_strong_password(length.unwrap_or(crypto::DEFAULT_PASSWORD_LENGTH))
+        .map_err(|e| e.to_string())
+}
+
+/// Carte flashée mais jamais configurée (application arrêtée avant custom.toml)
+#[tauri::command]
+fn detect_unconfigured_card() -> Option<boot_recovery::UnconfiguredCard> {
+    boot_recovery::detect()
+}
+
+/// Termine la configuration de la partition boot sans re-flasher la carte
 #[tauri::command]
-async fn flash_sd_card(
+async fn finish_boot_configuration(
     window: Window,
     config: FlashConfig,
     ssh_public_key: String,
 ) -> Result<(), String> {
-    flash::flash_raspberry_pi_os(window, config, ssh_public_key)
+    flash::finish_boot_configuration(window, config, ssh_public_key)
         .await
         .map_err(|e| e.to_string())
 }
 
-/// Découvre le Raspberry Pi sur le réseau
+/// Profils de flash enregistrés (hostname, WiFi, locale), du plus récent au plus ancien
 #[tauri::command]
-async fn discover_pi(hostname: String, timeout_secs: u64) -> Result<Option<PiInfo>, String> {
-    // Log dans un fichier car stdout/stderr sont avalés sur macOS GUI
-    use std::io::Write;
-    let _ = std::fs::write("/tmp/jellysetup_discovery.log",
-        format!("discover_pi CALLED: hostname={}, timeout={}s\n", hostname, timeout_secs));
-    let result = network::discover_raspberry_pi(&hostname, timeout_secs)
-        .await
-        .map_err(|e| {
-            println!("[CMD discover_pi] Error: {}", e);
-            e.to_string()
-        });
-    println!("[CMD discover_pi] Result: {:?}", result);
-    result
+fn list_profiles() -> Vec<flash_profiles::FlashProfile> {
+    flash_profiles::list_profiles()
+}
+
+/// Retourne un profil pour pré-remplir le formulaire de flash (mots de passe à ressaisir)
+#[tauri::command]
+fn apply_profile(name: String) -> Result<flash_profiles::FlashProfile, String> {
+    flash_profiles::apply_profile(&name).map_err(|e| e.to_string())
+}
+
+/// Supprime un profil de flash
+#[tauri::command]
+fn delete_profile(name: String) -> Result<(), String> {
+    flash_profiles::delete_profile(&name).map_err(|e| e.to_string())
 }
 
 /// Vérifie la connexion SSH au Pi (clé privée)
@@ -220,58 +508,90 @@ async fn ssh_exec(
         .map_err(|e| e.to_string())
 }
 
-/// Exécute une série de commandes d'installation (clé SSH)
+/// Reprend une installation interrompue à partir de la dernière étape en échec (clé SSH)
 #[tauri::command]
-async fn run_installation(
+async fn resume_installation(
     window: Window,
     host: String,
     username: String,
     private_key: String,
     config: InstallConfig,
 ) -> Result<(), String> {
-    // Extraire le hostname depuis l'adresse (comme pour la version password)
     let hostname = host.replace(".local", "");
-    flash::run_full_installation(window, &host, &username, &private_key, config, &hostname)
+    flash::resume_installation(window, &host, &username, &private_key, config, &hostname)
         .await
         .map_err(|e| e.to_string())
 }
 
-/// Exécute une série de commandes d'installation (mot de passe)
+/// Reprend une installation interrompue à partir de la dernière étape en échec (mot de passe)
 #[tauri::command]
-async fn run_installation_password(
+async fn resume_installation_password(
     window: Window,
     host: String,
     username: String,
     password: String,
     config: InstallConfig,
 ) -> Result<(), String> {
-    flash::run_full_installation_password(window, &host, &username, &password, config)
+    flash::resume_installation_password(window, &host, &username, &password, config)
         .await
         .map_err(|e| e.to_string())
 }
 
-/// Sauvegarde les credentials dans Supabase (ne bloque jamais)
+/// Connexion au compte JellySetup (points de reprise, registre, modèles Jellyfin)
 #[tauri::command]
-async fn save_to_supabase(
-    pi_name: String,
-    pi_ip: String,
-    ssh_public_key: String,
-    ssh_private_key_encrypted: String,
-    ssh_host_fingerprint: Option<String>,
-    installer_version: String,
-) -> Result<String, String> {
-    match supabase::save_installation(
-        &pi_name,
-        &pi_ip,
-        Some(&ssh_public_key),
-        Some(&ssh_private_key_encrypted),
-        ssh_host_fingerprint.as_deref(),
-        &installer_version,
-    )
-    .await {
+async fn account_sign_in(email: String, password: String) -> Result<account::AccountInfo, String> {
+    account::sign_in(&email, &password).await.map_err(|e| e.to_string())
+}
+
+/// Création du compte JellySetup
+#[tauri::command]
+async fn account_sign_up(email: String, password: String) -> Result<account::AccountInfo, String> {
+    account::sign_up(&email, &password).await.map_err(|e| e.to_string())
+}
+
+/// Déconnexion du compte JellySetup
+#[tauri::command]
+async fn account_sign_out() -> Result<(), String> {
+    account::sign_out().await.map_err(|e| e.to_string())
+}
+
+/// Compte JellySetup connecté (None si aucune session)
+#[tauri::command]
+async fn get_account() -> Option<account::AccountInfo> {
+    account::current().await
+}
+
+/// Installation interrompue publiée par un autre ordinateur pour ce Pi
+#[tauri::command]
+async fn get_remote_resume_point(pi_name: String) -> Result<Option<remote_resume::ResumePointInfo>, String> {
+    remote_resume::resume_point_info(&pi_name).await.map_err(|e| e.to_string())
+}
+
+/// Reprend sur cet ordinateur une installation interrompue ailleurs (point de reprise Supabase)
+#[tauri::command]
+async fn resume_remote_installation(window: Window, pi_name: String, resume_password: String) -> Result<(), String> {
+    remote_resume::resume(window, &pi_name, &resume_password)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Retourne l'état local de la dernière installation sur ce host (étapes terminées, échec)
+#[tauri::command]
+fn get_install_state(host: String) -> Option<install_state::InstallState> {
+    install_state::InstallState::load(&host)
+}
+
+/// Enregistre l'installation dans Supabase (ne bloque jamais)
+///
+/// `payload`: `InstallationRegistration` (v2) ou arguments de l'ancien `save_to_supabase` (v1, migrés)
+#[tauri::command]
+async fn register_installation(payload: serde_json::Value) -> Result<String, String> {
+    let registration = registration::parse_payload(payload).map_err(|e| e.to_string())?;
+
+    match cloud::backend().save_installation(&registration).await {
         Ok(id) => Ok(id),
         Err(e) => {
-            println!("[Supabase] Warning: save_installation failed: {}", e);
+            tracing::warn!("save_installation failed: {}", e);
             // Ne pas bloquer l'installation - retourner un ID local
             Ok("local".to_string())
         }
@@ -281,10 +601,7 @@ async fn save_to_supabase(
 /// Récupère la procédure depuis GitHub
 #[tauri::command]
 async fn fetch_procedure(version: String) -> Result<String, String> {
-    let url = format!(
-        "https://raw.githubusercontent.com/nicolascleton/jellysetup/main/procedures/{}/steps.json",
-        version
-    );
+    let url = config::settings().procedure_url(&version);
 
     reqwest::get(&url)
         .await
@@ -297,7 +614,7 @@ async fn fetch_procedure(version: String) -> Result<String, String> {
 /// Vérifie les mises à jour de l'application
 #[tauri::command]
 async fn check_for_updates() -> Result<Option<String>, String> {
-    let url = "https://jellysetup.com/api/version";
+    let url = config::urls::VERSION_CHECK;
 
     let response = reqwest::get(url)
         .await
@@ -339,38 +656,1032 @@ fn clear_known_hosts(ip: String) -> Result<(), String> {
     ssh::clear_known_hosts_for_ip(&ip).map_err(|e| e.to_string())
 }
 
-// =============================================================================
-// Main
-// =============================================================================
+/// Épingle le fingerprint attendu d'un Pi depuis Supabase (s'il existe)
+/// Carte que l'application vient de flasher: l'ancienne empreinte est oubliée à la place
+#[tauri::command]
+async fn load_expected_fingerprint(pi_name: String, host: String) -> Result<Option<String>, String> {
+    if ssh::forget_if_freshly_flashed(&pi_name, &host).map_err(|e| e.to_string())? {
+        return Ok(None);
+    }
 
-fn main() {
-    tracing_subscriber::fmt::init();
+    let fingerprint = supabase::get_host_fingerprint(&pi_name)
+        .await
+        .map_err(|e| e.to_string())?;
 
-    tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![
-            list_sd_cards,
-            generate_ssh_keys,
-            flash_sd_card,
-            discover_pi,
-            test_ssh_connection,
-            test_ssh_connection_password,
-            ssh_exec,
-            run_installation,
-            run_installation_password,
-            save_to_supabase,
-            fetch_procedure,
-            check_for_updates,
-            check_disk_access,
-            open_disk_access_settings,
-            restart_app,
-            get_ssh_host_fingerprint,
-            clear_known_hosts,
-        ])
-        .setup(|app| {
-            let window = app.get_window("main").unwrap();
+    if let Some(ref fp) = fingerprint {
+        ssh::pin_host_fingerprint(&host, fp).map_err(|e| e.to_string())?;
+    }
 
-            // Centrer la fenêtre
-            window.center().unwrap();
+    Ok(fingerprint)
+}
+
+/// Oublie le fingerprint épinglé d'un host (Pi reflashé volontairement)
+#[tauri::command]
+fn forget_ssh_host_fingerprint(host: String) -> Result<(), String> {
+    ssh::forget_host_fingerprint(&host).map_err(|e| e.to_string())
+}
+
+/// Configure un bastion (ProxyJump) pour joindre un Pi distant
+#[tauri::command]
+fn set_ssh_jump_host(host: String, jump_host: Option<ssh::JumpHost>) {
+    ssh::set_jump_host(&host, jump_host);
+}
+
+/// Récupère les options SSH courantes (timeouts, retries, keepalive)
+#[tauri::command]
+fn get_ssh_options() -> ssh::SshOptions {
+    ssh::default_options()
+}
+
+/// Met à jour les options SSH (ex: WiFi instable)
+#[tauri::command]
+fn set_ssh_options(options: ssh::SshOptions) {
+    ssh::set_default_options(options);
+}
+
+/// Ouvre un terminal interactif (PTY) vers le Pi, retourne l'ID du shell
+#[tauri::command]
+async fn ssh_open_shell(
+    window: Window,
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    cols: u32,
+    rows: u32,
+) -> Result<String, String> {
+    terminal::open_shell(
+        window,
+        &host,
+        &username,
+        password.as_deref(),
+        private_key.as_deref(),
+        cols,
+        rows,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Ferme un terminal ouvert
+#[tauri::command]
+fn ssh_close_shell(id: String) -> Result<(), String> {
+    terminal::close_shell(&id).map_err(|e| e.to_string())
+}
+
+/// Cible SSH depuis les identifiants passés par le frontend (clé privée prioritaire)
+fn ssh_target<'a>(
+    host: &'a str,
+    username: &'a str,
+    password: Option<&'a str>,
+    private_key: Option<&'a str>,
+) -> Result<ssh::SshTarget<'a>, String> {
+    match (private_key, password) {
+        (Some(private_key), _) => Ok(ssh::SshTarget::Key { host, username, private_key }),
+        (None, Some(password)) => Ok(ssh::SshTarget::Password { host, username, password }),
+        (None, None) => Err("Aucune clé ni mot de passe fourni".to_string()),
+    }
+}
+
+/// Vérifie que le Pi peut accueillir la stack (RAM, disque, architecture, réseau)
+#[tauri::command]
+async fn preflight_check(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+) -> Result<preflight::PreflightReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    preflight::run_preflight(&target)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// État de l'abonnement AllDebrid, vérifié dès la saisie de la clé dans le wizard
+/// (avertissement in-app avant expiration)
+#[tauri::command]
+async fn check_alldebrid_account(api_key: String) -> Result<preflight::PreflightCheck, String> {
+    Ok(alldebrid::check_account(&api_key).await)
+}
+
+/// Estime la durée et le volume téléchargé avant de lancer l'installation
+/// `pi_model`: modèle lu par le pré-check ("Raspberry Pi 5 Model B...") ou "pi4"/"pi5"
+#[tauri::command]
+async fn estimate_install(pi_model: Option<String>, include_flash: bool) -> Result<estimate::InstallEstimate, String> {
+    let model = pi_model
+        .as_deref()
+        .map(estimate::PiModel::from_model_string)
+        .unwrap_or(estimate::PiModel::Pi5);
+
+    estimate::estimate_install(model, include_flash)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Vérifie l'ordinateur (batterie, espace du cache) avant un flash ou une installation
+#[tauri::command]
+async fn host_preflight_check(operation: preflight::HostOperation) -> Result<preflight::PreflightReport, String> {
+    Ok(preflight::check_host(operation).await)
+}
+
+/// Désinstalle le stack du Pi (compose down, ~/media-stack, options: volumes et /mnt/decypharr)
+#[tauri::command]
+async fn uninstall_stack(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+    options: uninstall::UninstallOptions,
+) -> Result<uninstall::UninstallReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    let sudo = ssh::sudo_prefix(password.as_deref());
+
+    uninstall::uninstall_stack(&target, &host, &pi_name, &options, &sudo)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Met à jour les images du stack puis ré-applique la master_config (rollback automatique si le stack est KO)
+#[tauri::command]
+async fn update_stack(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+    discord_webhook: Option<String>,
+) -> Result<stack_update::StackUpdateReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    let notifier = notifications::DiscordNotifier::from_webhook(discord_webhook.as_deref());
+
+    stack_update::update_stack(target, &pi_name, notifier.as_ref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Synchronise les demandes Jellyseerr (en attente/approuvées) dans Supabase et les renvoie
+#[tauri::command]
+async fn sync_jellyseerr_requests(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+) -> Result<Vec<requests_sync::HouseholdRequest>, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    requests_sync::sync_requests(target, &pi_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Re-débride les liens du catalogue proches de l'expiration (Supabase + Decypharr)
+#[tauri::command]
+async fn refresh_debrid_links(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+    alldebrid_api_key: String,
+) -> Result<debrid_refresh::RefreshSummary, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    debrid_refresh::refresh_links(&pi_name, &alldebrid_api_key, Some(&target))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Compare l'état de référence du Pi (Supabase) avec son état réel
+#[tauri::command]
+async fn detect_drift(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+) -> Result<drift::DriftReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    drift::detect_drift(&target, &pi_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Historique des configurations appliquées sur un Pi (plus récente en premier)
+#[tauri::command]
+async fn list_config_history(pi_name: String) -> Result<Vec<config_history::ConfigVersion>, String> {
+    config_history::list_versions(&pi_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Différences entre deux versions de configuration d'un Pi
+#[tauri::command]
+async fn diff_config_versions(
+    pi_name: String,
+    from_id: String,
+    to_id: String,
+) -> Result<Vec<config_history::ConfigChange>, String> {
+    config_history::diff_versions(&pi_name, &from_id, &to_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Ré-applique une version antérieure (compose + configs des services)
+#[tauri::command]
+async fn reapply_config_version(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+    version_id: String,
+) -> Result<Vec<String>, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    config_history::reapply_version(target, &pi_name, &version_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Réglage post-installation: active/désactive SyncPlay (visionnage groupé) pour tous les utilisateurs
+#[tauri::command]
+async fn set_sync_play(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    jellyfin_username: String,
+    jellyfin_password: String,
+    enabled: bool,
+) -> Result<sync_play::SyncPlaySettings, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    sync_play::set_sync_play(target, &jellyfin_username, &jellyfin_password, enabled)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Auto-test de transcodage 4K -> 1080p (i/s obtenus avec l'accélération configurée)
+#[tauri::command]
+async fn run_transcode_test(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+) -> Result<transcode_test::TranscodeTestResult, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    transcode_test::run(&target)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tableau de bord: conteneurs du Pi (docker ps -a)
+#[tauri::command]
+async fn docker_ps(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+) -> Result<Vec<docker::ContainerInfo>, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    docker::ps(&target)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tableau de bord: dernières lignes de logs d'un service
+#[tauri::command]
+async fn docker_logs(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    service: String,
+    tail: Option<u32>,
+) -> Result<Vec<String>, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    docker::logs(&target, &service, tail.unwrap_or(200))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tableau de bord: redémarre un service et renvoie son nouvel état
+#[tauri::command]
+async fn docker_restart(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    service: String,
+) -> Result<docker::ContainerInfo, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    docker::restart(&target, &service)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tableau de bord: CPU/mémoire/réseau de chaque conteneur
+#[tauri::command]
+async fn docker_stats(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+) -> Result<Vec<docker::ContainerStats>, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    docker::stats(&target)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Surveillance périodique des Pis (événements "monitoring-heartbeat" et "monitoring-alert")
+#[tauri::command]
+fn start_monitoring(
+    window: Window,
+    pis: Vec<monitoring::MonitoredPi>,
+    interval_secs: Option<u64>,
+) -> Result<(), String> {
+    monitoring::start(window, pis, interval_secs).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn stop_monitoring() {
+    monitoring::stop();
+}
+
+/// Tableau de bord: température, throttling, disque, RAM et uptime du Pi
+#[tauri::command]
+async fn get_pi_metrics(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+) -> Result<pi_metrics::PiMetrics, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    pi_metrics::collect(&target)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pis installés connus de l'application (local + Supabase)
+#[tauri::command]
+async fn list_pis() -> Vec<pi_registry::RegisteredPi> {
+    pi_registry::list().await
+}
+
+/// Ajoute un Pi au registre (ou met à jour son entrée)
+#[tauri::command]
+async fn add_pi(pi: pi_registry::RegisteredPi) -> Result<(), String> {
+    pi_registry::add(pi).await.map_err(|e| e.to_string())
+}
+
+/// Retire un Pi du registre
+#[tauri::command]
+async fn remove_pi(pi_name: String) -> Result<(), String> {
+    pi_registry::remove(&pi_name).await.map_err(|e| e.to_string())
+}
+
+/// Sélectionne le Pi des écrans post-installation (mise à jour, surveillance, logs)
+#[tauri::command]
+fn select_pi(pi_name: String) -> Result<pi_registry::RegisteredPi, String> {
+    pi_registry::select(&pi_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_selected_pi() -> Option<pi_registry::RegisteredPi> {
+    pi_registry::selected()
+}
+
+/// Adopte un Pi déjà équipé d'un stack Docker installé à la main (sans reflasher)
+#[tauri::command]
+async fn adopt_pi(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+) -> Result<adopt::AdoptionReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    adopt::adopt(&target, &pi_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sauvegarde les configurations des services du Pi (archives locales, envoi cloud optionnel)
+#[tauri::command]
+async fn backup_now(
+    window: tauri::Window,
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+    upload: bool,
+) -> Result<backup::BackupManifest, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    let sudo = ssh::sudo_prefix(password.as_deref());
+
+    backup::backup_now(&window, &target, &pi_name, &sudo, upload)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Restaure une sauvegarde (locale ou cloud) sur un Pi réinstallé, puis vérifie le stack
+#[tauri::command]
+async fn restore_backup(
+    window: tauri::Window,
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+    backup_id: String,
+) -> Result<backup::RestoreReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    let sudo = ssh::sudo_prefix(password.as_deref());
+
+    backup::restore_backup(&window, &target, &pi_name, &backup_id, &sudo)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Machines annoncées en mDNS sur le réseau local (cache du navigateur permanent)
+#[tauri::command]
+fn list_network_hosts() -> Result<Vec<mdns::NetworkHost>, String> {
+    mdns::start().map_err(|e| e.to_string())?;
+    Ok(mdns::hosts())
+}
+
+/// Capture la configuration Jellyfin du Pi comme modèle du compte pour les prochaines installations
+#[tauri::command]
+async fn capture_jellyfin_template(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+) -> Result<services::jellyfin_template::JellyfinTemplate, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    services::jellyfin_template::capture(&target)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Machines du réseau local (SSH/Jellyfin/Jellyseerr) pour choisir un Pi manuellement
+#[tauri::command]
+async fn scan_network_devices() -> Result<Vec<network::NetworkDevice>, String> {
+    network::scan_network_devices().await.map_err(|e| e.to_string())
+}
+
+/// Vérifie l'accès distant (tunnel Cloudflare) et tente de le réparer si demandé
+#[tauri::command]
+async fn check_remote_access(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    hostnames: Vec<String>,
+    cloudflare: Option<cloudflare::CloudflareSetup>,
+    repair: bool,
+) -> Result<remote_access::RemoteAccessReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    remote_access::check_and_repair(&target, &hostnames, cloudflare.as_ref(), repair)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Remplace le token du tunnel Cloudflare (régénéré dans le dashboard)
+#[tauri::command]
+async fn update_tunnel_token(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    token: String,
+) -> Result<remote_access::RemoteAccessReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    remote_access::update_tunnel_token(&target, &token).await.map_err(|e| e.to_string())?;
+    remote_access::check_and_repair(&target, &[], None, false).await.map_err(|e| e.to_string())
+}
+
+/// Aperçu des actions de l'installation, sans rien exécuter
+///
+/// `procedure`: contenu d'un steps.json à examiner (sinon la procédure publiée)
+#[tauri::command]
+async fn plan_installation(
+    procedure: Option<String>,
+    config: InstallConfig,
+    host: Option<String>,
+) -> Result<procedure::InstallationPlan, String> {
+    let procedure = match procedure {
+        Some(json) => procedure::Procedure::parse(&json).map_err(|e| e.to_string())?,
+        None => procedure::Procedure::resolve().await,
+    };
+    let vars = flash::planning_vars(host.as_deref().unwrap_or("jellypi.local"), &config).await;
+    Ok(procedure.plan(&vars))
+}
+
+/// Pré-remplit le wizard depuis un préréglage Raspberry Pi Imager (contenu du .json)
+#[tauri::command]
+fn import_imager_preset(content: String) -> Result<imager_preset::ImagerImport, String> {
+    imager_preset::import_preset(&content).map_err(|e| e.to_string())
+}
+
+/// Exporte la configuration de flash au format Raspberry Pi Imager (sans mots de passe)
+#[tauri::command]
+fn export_imager_preset(config: FlashConfig, ssh_public_key: Option<String>) -> Result<String, String> {
+    imager_preset::export_preset(&config, ssh_public_key.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Prépare un bundle hors ligne dans un dossier (image Raspberry Pi OS, procédure, images Docker)
+#[tauri::command]
+async fn create_offline_bundle(
+    window: Window,
+    destination: String,
+    include_images: bool,
+) -> Result<offline_bundle::OfflineBundleInfo, String> {
+    offline_bundle::create(&window, std::path::Path::new(&destination), include_images)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Importe un bundle hors ligne et active le mode hors ligne
+#[tauri::command]
+async fn import_offline_bundle(path: String) -> Result<offline_bundle::OfflineBundleInfo, String> {
+    offline_bundle::import(std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Bundle hors ligne importé sur cet ordinateur
+#[tauri::command]
+fn get_offline_bundle() -> Option<offline_bundle::OfflineBundleInfo> {
+    offline_bundle::installed()
+}
+
+/// Renvoie à Supabase les écritures mises en attente faute de connexion
+#[tauri::command]
+async fn flush_pending_sync() -> Result<sync_outbox::PendingSyncReport, String> {
+    sync_outbox::flush(true).await.map_err(|e| e.to_string())
+}
+
+/// Ouvre le dossier de session d'une opération (compose, configs, logs, snapshots)
+#[tauri::command]
+fn open_session_folder(id: String) -> Result<(), String> {
+    workspace::open(&id).map_err(|e| e.to_string())
+}
+
+/// Auto-diagnostic de l'ordinateur (outils, accès disque, réseau, cache, webview)
+#[tauri::command]
+async fn run_app_diagnostics() -> diagnostics::AppDiagnostics {
+    let disk_access = check_disk_access().unwrap_or(false);
+    diagnostics::run(disk_access, tauri::webview_version().ok()).await
+}
+
+/// Range un secret dans le coffre de l'OS (portée: nom du Pi, "default" si absente)
+#[tauri::command]
+fn store_secret(kind: secrets::SecretKind, scope: Option<String>, value: secret::SecretString) -> Result<(), String> {
+    let scope = scope.unwrap_or_else(|| secrets::DEFAULT_SCOPE.to_string());
+    secrets::store(kind, &scope, &value).map_err(|e| e.to_string())
+}
+
+/// Lit un secret du coffre de l'OS (null s'il n'existe pas)
+#[tauri::command]
+fn get_secret(kind: secrets::SecretKind, scope: Option<String>) -> Result<Option<secret::SecretString>, String> {
+    let scope = scope.unwrap_or_else(|| secrets::DEFAULT_SCOPE.to_string());
+    secrets::get(kind, &scope).map_err(|e| e.to_string())
+}
+
+/// Supprime un secret du coffre de l'OS
+#[tauri::command]
+fn delete_secret(kind: secrets::SecretKind, scope: Option<String>) -> Result<(), String> {
+    let scope = scope.unwrap_or_else(|| secrets::DEFAULT_SCOPE.to_string());
+    secrets::delete(kind, &scope).map_err(|e| e.to_string())
+}
+
+/// État du coffre local (existe, déverrouillé, Pi enregistrés)
+#[tauri::command]
+fn vault_status() -> vault::VaultStatus {
+    vault::status()
+}
+
+/// Déverrouille le coffre local (créé au premier déverrouillage)
+#[tauri::command]
+fn unlock_vault(master_password: secret::SecretString) -> Result<vault::VaultStatus, String> {
+    vault::unlock(master_password).map_err(|e| e.to_string())
+}
+
+/// Verrouille le coffre local
+#[tauri::command]
+fn lock_vault() {
+    vault::lock()
+}
+
+/// Change le mot de passe maître du coffre
+#[tauri::command]
+fn change_vault_password(
+    current_password: secret::SecretString,
+    new_password: secret::SecretString,
+) -> Result<(), String> {
+    vault::change_password(current_password, new_password).map_err(|e| e.to_string())
+}
+
+/// Identifiants d'un Pi enregistrés dans le coffre (coffre déverrouillé)
+#[tauri::command]
+fn get_vault_credentials(hostname: String) -> Result<Option<vault::PiCredentials>, String> {
+    vault::get(&hostname).map_err(|e| e.to_string())
+}
+
+/// Enregistre les identifiants d'un Pi dans le coffre
+#[tauri::command]
+fn save_vault_credentials(credentials: vault::PiCredentials) -> Result<(), String> {
+    vault::put(credentials).map_err(|e| e.to_string())
+}
+
+/// Retire un Pi du coffre
+#[tauri::command]
+fn delete_vault_credentials(hostname: String) -> Result<(), String> {
+    vault::remove(&hostname).map_err(|e| e.to_string())
+}
+
+/// Catalogue d'applications optionnelles, avec celles déjà installées sur le Pi
+#[tauri::command]
+async fn list_addons(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+) -> Result<Vec<addons::AddonInfo>, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    Ok(addons::list(&target).await)
+}
+
+/// Installe une application du catalogue (ressources, compose, santé, configuration)
+#[tauri::command]
+async fn install_addon(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    name: String,
+) -> Result<addons::AddonInstallResult, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    addons::install(&target, &host, &name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Désinstalle une application du catalogue (données archivées sur le Pi si keep_data)
+#[tauri::command]
+async fn uninstall_addon(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    name: String,
+    keep_data: bool,
+) -> Result<addons::AddonUninstallReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+    let sudo = ssh::sudo_prefix(password.as_deref());
+
+    addons::uninstall(&target, &name, keep_data, &sudo)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Derniers événements des journaux de l'application (secrets masqués), pour le support
+#[tauri::command]
+fn get_app_logs(limit: Option<usize>, min_level: Option<String>) -> Result<Vec<app_logs::AppLogEntry>, String> {
+    app_logs::recent(limit.unwrap_or(app_logs::DEFAULT_RECENT_LIMIT), min_level.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Zip de support (journaux, configuration masquée, versions), renvoie son chemin
+#[tauri::command]
+fn export_support_bundle(config: Option<serde_json::Value>) -> Result<String, String> {
+    support_bundle::export(config)
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Réglages de l'application (limites de taille SD, miroir Raspberry Pi OS, tentatives)
+#[tauri::command]
+fn get_app_settings() -> config::Settings {
+    config::settings()
+}
+
+#[tauri::command]
+fn save_app_settings(settings: config::Settings) -> Result<(), String> {
+    config::save(settings).map_err(|e| e.to_string())
+}
+
+/// Vérification post-installation: conteneurs, APIs, indexers, bibliothèques, montage, recherche
+#[tauri::command]
+async fn verify_installation(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    pi_name: String,
+    jellyfin_username: String,
+    jellyfin_password: String,
+) -> Result<verification::HealthReport, String> {
+    let target = ssh_target(&host, &username, password.as_deref(), private_key.as_deref())?;
+
+    verification::verify_and_persist(target, &pi_name, &jellyfin_username, &jellyfin_password)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// État des services du Pi, lu depuis sa page de statut (http://<pi>/status)
+#[tauri::command]
+async fn get_stack_status(host: String) -> Result<status::StackStatus, String> {
+    status::fetch_stack_status(&host)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Valide un extra-compose.yml et retourne les noms des services tels que déployés
+#[tauri::command]
+fn validate_extra_compose(content: String) -> Result<Vec<String>, String> {
+    extra_compose::parse_extra_compose(&content)
+        .map(|services| services.iter().map(|s| s.namespaced_name()).collect())
+        .map_err(|e| e.to_string())
+}
+
+/// Config d'un service en attente de relecture, secrets inclus
+#[tauri::command]
+fn reveal_config_review(review_id: String, service: String) -> Result<serde_json::Value, String> {
+    services::review::reveal(&review_id, &service).map_err(|e| e.to_string())
+}
+
+/// Valide (avec éditions éventuelles) ou refuse les configurations en relecture
+#[tauri::command]
+fn submit_config_review(
+    review_id: String,
+    approved: bool,
+    edits: Option<std::collections::HashMap<String, serde_json::Value>>,
+) -> Result<(), String> {
+    services::review::submit(&review_id, approved, edits.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Ouvre un tunnel local vers un port du Pi (ex: localhost:8096 -> pi:8096)
+#[tauri::command]
+async fn ssh_forward_port(
+    host: String,
+    username: String,
+    password: Option<String>,
+    private_key: Option<String>,
+    local_port: u16,
+    remote_port: u16,
+    remote_host: Option<String>,
+) -> Result<tunnels::TunnelInfo, String> {
+    tunnels::open_tunnel(
+        &host,
+        &username,
+        password.as_deref(),
+        private_key.as_deref(),
+        local_port,
+        remote_host.as_deref().unwrap_or("localhost"),
+        remote_port,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Liste les tunnels actifs
+#[tauri::command]
+fn list_tunnels() -> Vec<tunnels::TunnelInfo> {
+    tunnels::list_tunnels()
+}
+
+/// Ferme un tunnel
+#[tauri::command]
+fn stop_tunnel(id: String) -> Result<(), String> {
+    tunnels::stop_tunnel(&id).map_err(|e| e.to_string())
+}
+
+// =============================================================================
+// Opérations longues (ID + polling + annulation)
+// =============================================================================
+
+/// Lance le flash en tâche de fond et retourne l'ID de l'opération
+#[tauri::command]
+fn start_flash(window: Window, config: FlashConfig, ssh_public_key: String) -> String {
+    operations::start(
+        operations::OperationKind::Flash,
+        flash::flash_raspberry_pi_os(window, config, ssh_public_key),
+    )
+}
+
+/// Lance l'installation (clé SSH) en tâche de fond
+#[tauri::command]
+fn start_installation(
+    window: Window,
+    host: String,
+    username: String,
+    private_key: String,
+    config: InstallConfig,
+) -> String {
+    operations::start(operations::OperationKind::Install, async move {
+        let hostname = host.replace(".local", "");
+        flash::run_full_installation(window, &host, &username, &private_key, config, &hostname).await
+    })
+}
+
+/// Lance l'installation (mot de passe) en tâche de fond
+#[tauri::command]
+fn start_installation_password(
+    window: Window,
+    host: String,
+    username: String,
+    password: String,
+    config: InstallConfig,
+) -> String {
+    operations::start(operations::OperationKind::Install, async move {
+        flash::run_full_installation_password(window, &host, &username, &password, config).await
+    })
+}
+
+/// Lance la découverte du Pi en tâche de fond
+#[tauri::command]
+fn start_discovery(hostname: String, timeout_secs: u64) -> String {
+    operations::start(operations::OperationKind::Discovery, async move {
+        network::discover_raspberry_pi(&hostname, timeout_secs).await
+    })
+}
+
+/// Retourne l'état d'une opération
+#[tauri::command]
+fn get_operation(id: String) -> Option<operations::Operation> {
+    operations::get(&id)
+}
+
+/// Liste les opérations connues
+#[tauri::command]
+fn list_operations() -> Vec<operations::Operation> {
+    operations::list()
+}
+
+/// Demande l'annulation d'une opération
+#[tauri::command]
+fn cancel_operation(id: String) -> Result<(), String> {
+    operations::cancel(&id).map_err(|e| e.to_string())
+}
+
+// =============================================================================
+// Main
+// =============================================================================
+
+fn main() {
+    app_logs::init();
+
+    tauri::Builder::default()
+        .invoke_handler(tauri::generate_handler![
+            list_sd_cards,
+            generate_ssh_keys,
+            generate_strong_password,
+            verify_installation,
+            list_profiles,
+            apply_profile,
+            delete_profile,
+            test_ssh_connection,
+            test_ssh_connection_password,
+            ssh_exec,
+            resume_installation,
+            resume_installation_password,
+            account_sign_in,
+            account_sign_up,
+            account_sign_out,
+            get_account,
+            get_remote_resume_point,
+            resume_remote_installation,
+            get_install_state,
+            register_installation,
+            fetch_procedure,
+            check_for_updates,
+            check_disk_access,
+            open_disk_access_settings,
+            restart_app,
+            get_ssh_host_fingerprint,
+            clear_known_hosts,
+            load_expected_fingerprint,
+            forget_ssh_host_fingerprint,
+            get_ssh_options,
+            set_ssh_options,
+            set_ssh_jump_host,
+            start_flash,
+            start_installation,
+            start_installation_password,
+            start_discovery,
+            get_operation,
+            list_operations,
+            cancel_operation,
+            ssh_open_shell,
+            ssh_close_shell,
+            ssh_forward_port,
+            preflight_check,
+            check_alldebrid_account,
+            refresh_debrid_links,
+            uninstall_stack,
+            sync_jellyseerr_requests,
+            update_stack,
+            set_sync_play,
+            run_transcode_test,
+            docker_ps,
+            docker_logs,
+            docker_restart,
+            docker_stats,
+            start_monitoring,
+            stop_monitoring,
+            get_pi_metrics,
+            list_pis,
+            add_pi,
+            remove_pi,
+            select_pi,
+            get_selected_pi,
+            adopt_pi,
+            backup_now,
+            restore_backup,
+            list_network_hosts,
+            capture_jellyfin_template,
+            scan_network_devices,
+            check_remote_access,
+            update_tunnel_token,
+            plan_installation,
+            import_imager_preset,
+            export_imager_preset,
+            create_offline_bundle,
+            import_offline_bundle,
+            get_offline_bundle,
+            flush_pending_sync,
+            open_session_folder,
+            run_app_diagnostics,
+            store_secret,
+            get_secret,
+            delete_secret,
+            detect_unconfigured_card,
+            finish_boot_configuration,
+            vault_status,
+            unlock_vault,
+            lock_vault,
+            change_vault_password,
+            get_vault_credentials,
+            save_vault_credentials,
+            delete_vault_credentials,
+            list_addons,
+            install_addon,
+            uninstall_addon,
+            import_ssh_key,
+            get_app_logs,
+            export_support_bundle,
+            get_app_settings,
+            save_app_settings,
+            host_preflight_check,
+            estimate_install,
+            detect_drift,
+            list_config_history,
+            diff_config_versions,
+            reapply_config_version,
+            get_stack_status,
+            validate_extra_compose,
+            reveal_config_review,
+            submit_config_review,
+            list_tunnels,
+            stop_tunnel,
+        ])
+        .setup(|app| {
+            let window = app.get_window("main").unwrap();
+
+            // Centrer la fenêtre
+            window.center().unwrap();
+
+            // Navigateur mDNS permanent (découverte du Pi, sélecteur d'appareil)
+            if let Err(e) = mdns::start() {
+                tracing::warn!("⚠️ Could not start browser: {}", e);
+            }
+
+            // Rejeu des écritures Supabase mises en attente hors connexion
+            sync_outbox::start();
+
+            // Rétention des dossiers de session
+            workspace::cleanup();
+
+            // Verrouillage automatique du coffre local après inactivité
+            vault::start_auto_lock();
 
             Ok(())
         })
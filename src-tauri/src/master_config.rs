@@ -1,6 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use crate::supabase;
+use crate::template_engine::TemplateVars;
 
 /// Type de configuration pour évolution future
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -22,6 +23,179 @@ pub struct MasterConfig {
     pub jellyfin_config: Option<serde_json::Value>,
     pub jellyseerr_config: Option<serde_json::Value>,
     pub decypharr_config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub anime_config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub lidarr_config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub readarr_config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub audiobookshelf_config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub media_paths: Option<MediaPaths>,
+    #[serde(default)]
+    pub policies: Option<Policies>,
+    /// Commandes personnalisées exécutées avant/après certaines étapes
+    #[serde(default)]
+    pub hooks: Vec<crate::hooks::StepHook>,
+    /// Définition déclarative du stack docker-compose (voir compose.rs)
+    #[serde(default)]
+    pub stack: Option<serde_json::Value>,
+    /// Optimisations serveur sans écran (voir headless.rs)
+    #[serde(default)]
+    pub hardware_profile: Option<crate::headless::HardwareProfile>,
+    /// Catalogue d'applications optionnelles installables après le stack (voir addons.rs)
+    #[serde(default)]
+    pub addons: Option<serde_json::Value>,
+}
+
+/// Quota de requêtes Jellyseerr: `limit` demandes tous les `days` jours
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Quota {
+    pub limit: u32,
+    pub days: u32,
+}
+
+/// Politiques par défaut appliquées à tous les services (foyers non techniques)
+/// Un champ absent laisse le réglage du service inchangé
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Policies {
+    /// Téléchargements simultanés côté Decypharr
+    pub max_concurrent_downloads: Option<u32>,
+    /// Quota films par utilisateur Jellyseerr
+    pub movie_quota: Option<Quota>,
+    /// Quota séries par utilisateur Jellyseerr
+    pub tv_quota: Option<Quota>,
+    /// Radarr: ne plus surveiller un film une fois téléchargé
+    pub radarr_unmonitor_downloaded: Option<bool>,
+    /// Sonarr: ne plus surveiller un épisode une fois téléchargé
+    pub sonarr_unmonitor_downloaded: Option<bool>,
+}
+
+impl MasterConfig {
+    /// Section de configuration d'un service (None si absente de la master_config)
+    pub fn service_config(&self, service_name: &str) -> Option<&serde_json::Value> {
+        match service_name {
+            "radarr" => self.radarr_config.as_ref(),
+            "sonarr" => self.sonarr_config.as_ref(),
+            "prowlarr" => self.prowlarr_config.as_ref(),
+            "bazarr" => self.bazarr_config.as_ref(),
+            "jellyfin" => self.jellyfin_config.as_ref(),
+            "jellyseerr" => self.jellyseerr_config.as_ref(),
+            "decypharr" => self.decypharr_config.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Arborescence des médias sur le Pi (volumes Docker, root folders *arr, Jellyseerr, Jellyfin)
+///
+/// Les sous-dossiers relatifs sont résolus depuis `root`, les chemins absolus sont gardés tels quels.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MediaPaths {
+    pub root: String,
+    pub movies: String,
+    pub tv: String,
+    pub anime: String,
+    pub music: String,
+    pub books: String,
+    pub audiobooks: String,
+}
+
+impl Default for MediaPaths {
+    fn default() -> Self {
+        Self {
+            root: "/mnt/decypharr".to_string(),
+            movies: "movies".to_string(),
+            tv: "tv".to_string(),
+            anime: "anime".to_string(),
+            music: "music".to_string(),
+            books: "books".to_string(),
+            audiobooks: "audiobooks".to_string(),
+        }
+    }
+}
+
+impl MediaPaths {
+    /// Résout l'arborescence: surcharge utilisateur > master_config > défaut
+    pub fn resolve(master: Option<&MasterConfig>, user_override: Option<&MediaPaths>) -> Self {
+        user_override
+            .or_else(|| master.and_then(|m| m.media_paths.as_ref()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn full_path(&self, folder: &str) -> String {
+        if folder.starts_with('/') {
+            folder.to_string()
+        } else {
+            format!("{}/{}", self.root.trim_end_matches('/'), folder)
+        }
+    }
+
+    pub fn movies_path(&self) -> String {
+        self.full_path(&self.movies)
+    }
+
+    pub fn tv_path(&self) -> String {
+        self.full_path(&self.tv)
+    }
+
+    pub fn anime_path(&self) -> String {
+        self.full_path(&self.anime)
+    }
+
+    pub fn music_path(&self) -> String {
+        self.full_path(&self.music)
+    }
+
+    pub fn books_path(&self) -> String {
+        self.full_path(&self.books)
+    }
+
+    pub fn audiobooks_path(&self) -> String {
+        self.full_path(&self.audiobooks)
+    }
+
+    /// Tous les dossiers médias à créer sur le Pi
+    pub fn all_paths(&self) -> Vec<String> {
+        vec![
+            self.movies_path(),
+            self.tv_path(),
+            self.anime_path(),
+            self.music_path(),
+            self.books_path(),
+            self.audiobooks_path(),
+        ]
+    }
+
+    /// Volume Docker supplémentaire si la racine n'est pas déjà couverte par /mnt:/mnt
+    pub fn compose_volume(&self) -> Option<String> {
+        let root = self.root.trim_end_matches('/');
+        if root == "/mnt" || root.starts_with("/mnt/") {
+            None
+        } else {
+            Some(format!("{root}:{root}:rslave"))
+        }
+    }
+
+    /// Arborescence des premières installations par clé SSH (/mnt/media/{movies,series})
+    pub fn legacy() -> Self {
+        Self { root: LEGACY_MEDIA_ROOT.to_string(), tv: "series".to_string(), ..Self::default() }
+    }
+
+    /// Expose les chemins aux templates master_config ({{MOVIES_PATH}}, ...)
+    pub fn apply_to(&self, vars: &mut TemplateVars) {
+        vars.set("MEDIA_ROOT", &self.root);
+        vars.set("MOVIES_PATH", &self.movies_path());
+        vars.set("TV_PATH", &self.tv_path());
+        vars.set("ANIME_PATH", &self.anime_path());
+        vars.set("MUSIC_PATH", &self.music_path());
+        vars.set("BOOKS_PATH", &self.books_path());
+        vars.set("AUDIOBOOKS_PATH", &self.audiobooks_path());
+    }
 }
 
 /// Récupère la master_config depuis Supabase
@@ -31,11 +205,11 @@ pub struct MasterConfig {
 /// # Arguments
 /// * `config_type` - Optionnel: "streaming" ou "storage" pour filtrer par type
 pub async fn fetch_master_config(config_type: Option<&str>) -> Result<Option<MasterConfig>> {
-    let client = reqwest::Client::new();
+    let client = supabase::http_client()?;
     let supabase_url = supabase::get_supabase_url_public();
-    let service_key = supabase::get_supabase_service_key();
+    let rest_key = supabase::get_supabase_rest_key();
 
-    println!("[MasterConfig] 🔄 Fetching master_config from Supabase (type: {:?})...", config_type);
+    tracing::info!("🔄 Fetching master_config from Supabase (type: {:?})...", config_type);
 
     // Construire la query avec filtres
     let mut query_params = vec![
@@ -52,24 +226,109 @@ pub async fn fetch_master_config(config_type: Option<&str>) -> Result<Option<Mas
     let response = client
         .get(format!("{}/rest/v1/master_configs", supabase_url))
         .query(&query_params)
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
+        .header("apikey", &rest_key)
+        .header("Authorization", format!("Bearer {}", rest_key))
         .send()
         .await?;
 
     if !response.status().is_success() {
-        println!("[MasterConfig] ⚠️  Failed to fetch master_config: {}", response.status());
+        tracing::warn!("⚠️  Failed to fetch master_config: {}", response.status());
         return Ok(None);
     }
 
     let configs: Vec<MasterConfig> = response.json().await?;
 
     if let Some(config) = configs.first() {
-        println!("[MasterConfig] ✅ Loaded master_config: {} (type: {:?})",
+        tracing::info!("✅ Loaded master_config: {} (type: {:?})",
                  config.id, config.config_type);
         Ok(Some(config.clone()))
     } else {
-        println!("[MasterConfig] ⚠️  No active master_config found");
+        tracing::warn!("⚠️  No active master_config found");
         Ok(None)
     }
 }
+
+/// Arborescence des médias pour une installation (surcharge utilisateur > master_config > défaut)
+pub async fn resolve_media_paths(user_override: Option<&MediaPaths>) -> MediaPaths {
+    if let Some(paths) = user_override {
+        return paths.clone();
+    }
+    let master = crate::cloud::backend().fetch_master_config(Some("streaming")).await.ok().flatten();
+    MediaPaths::resolve(master.as_ref(), None)
+}
+
+/// Arborescence retenue à l'installation, gardée sur le Pi pour les opérations ultérieures
+const INSTALLED_PATHS_FILE: &str = "~/media-stack/.media-paths.json";
+
+/// Racine des installations antérieures à l'arborescence configurable
+const LEGACY_MEDIA_ROOT: &str = "/mnt/media";
+
+/// Arborescence mémorisée sur le Pi, sinon l'ancienne /mnt/media si elle existe
+async fn existing_media_paths(target: &crate::ssh::SshTarget<'_>) -> Option<MediaPaths> {
+    let saved = target
+        .exec(&format!("cat {} 2>/dev/null", INSTALLED_PATHS_FILE))
+        .await
+        .ok()
+        .and_then(|content| serde_json::from_str::<MediaPaths>(content.trim()).ok());
+    if saved.is_some() {
+        return saved;
+    }
+
+    let legacy = MediaPaths::legacy();
+    let probe = format!("test -e {} && echo legacy", crate::ssh::shell_quote(&legacy.movies_path()));
+    let found = target.exec(&probe).await.map(|out| out.trim() == "legacy").unwrap_or(false);
+    if found {
+        tracing::warn!("⚠️ Legacy media layout found on the Pi, keeping {}", LEGACY_MEDIA_ROOT);
+    }
+    found.then_some(legacy)
+}
+
+/// Arborescence d'une installation: surcharge utilisateur, sinon celle déjà en place
+/// sur le Pi (réinstallation, ancienne /mnt/media), sinon master_config/défaut
+pub async fn resolve_install_media_paths(
+    target: &crate::ssh::SshTarget<'_>,
+    user_override: Option<&MediaPaths>,
+) -> MediaPaths {
+    if let Some(paths) = user_override {
+        return paths.clone();
+    }
+    match existing_media_paths(target).await {
+        Some(paths) => paths,
+        None => resolve_media_paths(None).await,
+    }
+}
+
+/// Mémorise sur le Pi l'arborescence de l'installation
+pub async fn save_installed_media_paths(target: &crate::ssh::SshTarget<'_>, paths: &MediaPaths) -> Result<()> {
+    crate::delta_sync::push_file(target, INSTALLED_PATHS_FILE, &serde_json::to_string_pretty(paths)?).await?;
+    Ok(())
+}
+
+/// Arborescence configurée sur un Pi installé (repli: master_config, puis défaut)
+pub async fn installed_media_paths(target: &crate::ssh::SshTarget<'_>) -> MediaPaths {
+    resolve_install_media_paths(target, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_paths_resolution() {
+        let defaults = MediaPaths::default();
+        assert_eq!(defaults.movies_path(), "/mnt/decypharr/movies");
+        assert_eq!(defaults.compose_volume(), None);
+        assert_eq!(MediaPaths { root: "/mnt/".to_string(), ..MediaPaths::default() }.compose_volume(), None);
+        assert_eq!(
+            MediaPaths { root: "/mntdata".to_string(), ..MediaPaths::default() }.compose_volume().as_deref(),
+            Some("/mntdata:/mntdata:rslave")
+        );
+        assert_eq!(MediaPaths::legacy().tv_path(), "/mnt/media/series");
+
+        let user: MediaPaths = serde_json::from_str(r#"{"root": "/srv/media/", "music": "/data/music"}"#).unwrap();
+        let resolved = MediaPaths::resolve(None, Some(&user));
+        assert_eq!(resolved.tv_path(), "/srv/media/tv");
+        assert_eq!(resolved.music_path(), "/data/music");
+        assert_eq!(resolved.compose_volume().as_deref(), Some("/srv/media:/srv/media:rslave"));
+    }
+}
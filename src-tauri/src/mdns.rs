@@ -0,0 +1,238 @@
+use anyhow::Result;
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use ts_rs::TS;
+
+// =============================================================================
+// Navigateur mDNS permanent (cache des machines du réseau local)
+// =============================================================================
+//
+// Lancé au démarrage de l'application, il écoute `_ssh._tcp` et
+// `_workstation._tcp` (Avahi, publié par Raspberry Pi OS) et tient à jour un
+// cache des machines: découverte instantanée du Pi après le flash, détection
+// d'un nom d'hôte déjà pris et liste pour le sélecteur d'appareil
+// (`list_network_hosts`).
+
+const SERVICE_TYPES: [&str; 2] = ["_ssh._tcp.local.", "_workstation._tcp.local."];
+/// Machines sans annonce depuis ce délai ignorées (pas de paquet d'au revoir reçu)
+const STALE_AFTER_SECS: i64 = 15 * 60;
+/// Attente du cache quand le navigateur vient de démarrer
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Machine annoncée sur le réseau local
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct NetworkHost {
+    /// Nom sans ".local."
+    pub hostname: String,
+    /// IPv4 d'abord
+    pub addresses: Vec<String>,
+    /// "ssh", "workstation"
+    pub services: Vec<String>,
+    pub ssh_port: Option<u16>,
+    /// Annoncée par `_workstation._tcp` ("jellypi [dc:a6:32:..]")
+    pub mac_address: Option<String>,
+    /// RFC 3339
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// Cache des annonces (instance mDNS -> machine)
+#[derive(Debug, Default)]
+struct HostCache {
+    hosts: HashMap<String, NetworkHost>,
+    /// Nom complet d'instance -> (hostname, service)
+    instances: HashMap<String, (String, String)>,
+}
+
+/// "_ssh._tcp.local." -> "ssh"
+fn service_name(service_type: &str) -> String {
+    service_type.trim_start_matches('_').split('.').next().unwrap_or_default().to_string()
+}
+
+/// "jellypi.local." -> "jellypi"
+fn short_hostname(hostname: &str) -> String {
+    hostname.trim_end_matches('.').trim_end_matches(".local").to_lowercase()
+}
+
+/// MAC publiée dans le nom d'instance `_workstation`: "jellypi [dc:a6:32:01:02:03]._workstation._tcp.local."
+pub fn parse_workstation_mac(fullname: &str) -> Option<String> {
+    let start = fullname.find('[')?;
+    let end = start + fullname[start..].find(']')?;
+    let mac = &fullname[start + 1..end];
+    let valid = mac.split(':').count() == 6 && mac.split(':').all(|b| b.len() == 2 && b.chars().all(|c| c.is_ascii_hexdigit()));
+    valid.then(|| mac.to_lowercase())
+}
+
+impl HostCache {
+    fn resolved(&mut self, fullname: &str, service_type: &str, hostname: &str, addresses: &[IpAddr], port: u16, now: &str) {
+        let hostname = short_hostname(hostname);
+        if hostname.is_empty() {
+            return;
+        }
+        let service = service_name(service_type);
+        let host = self.hosts.entry(hostname.clone()).or_insert_with(|| NetworkHost {
+            hostname: hostname.clone(),
+            addresses: Vec::new(),
+            services: Vec::new(),
+            ssh_port: None,
+            mac_address: None,
+            first_seen: now.to_string(),
+            last_seen: now.to_string(),
+        });
+
+        let mut addresses = addresses.to_vec();
+        addresses.sort_by_key(|a| (a.is_ipv6(), *a));
+        if !addresses.is_empty() {
+            host.addresses = addresses.iter().map(IpAddr::to_string).collect();
+        }
+        if !host.services.contains(&service) {
+            host.services.push(service.clone());
+            host.services.sort();
+        }
+        if service == "ssh" {
+            host.ssh_port = Some(port);
+        }
+        if let Some(mac) = parse_workstation_mac(fullname) {
+            host.mac_address = Some(mac);
+        }
+        host.last_seen = now.to_string();
+        self.instances.insert(fullname.to_string(), (hostname, service));
+    }
+
+    fn removed(&mut self, fullname: &str) {
+        let Some((hostname, service)) = self.instances.remove(fullname) else {
+            return;
+        };
+        let still_announced = self.instances.values().any(|(h, s)| *h == hostname && *s == service);
+        if let Some(host) = self.hosts.get_mut(&hostname) {
+            if !still_announced {
+                host.services.retain(|s| *s != service);
+                if service == "ssh" {
+                    host.ssh_port = None;
+                }
+            }
+            if host.services.is_empty() {
+                self.hosts.remove(&hostname);
+            }
+        }
+    }
+
+    /// Machines vues récemment, triées par nom
+    fn hosts(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<NetworkHost> {
+        let mut hosts: Vec<NetworkHost> = self
+            .hosts
+            .values()
+            .filter(|h| {
+                chrono::DateTime::parse_from_rfc3339(&h.last_seen)
+                    .map(|seen| (now - seen.with_timezone(&chrono::Utc)).num_seconds() < STALE_AFTER_SECS)
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+        hosts.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+        hosts
+    }
+}
+
+static CACHE: Lazy<Mutex<HostCache>> = Lazy::new(|| Mutex::new(HostCache::default()));
+/// Démon mDNS du navigateur (None tant qu'il n'est pas démarré)
+static BROWSER: Lazy<Mutex<Option<ServiceDaemon>>> = Lazy::new(|| Mutex::new(None));
+
+fn handle_event(event: ServiceEvent) {
+    let Ok(mut cache) = CACHE.lock() else { return };
+    match event {
+        ServiceEvent::ServiceResolved(info) => {
+            let addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+            let now = chrono::Utc::now().to_rfc3339();
+            cache.resolved(info.get_fullname(), info.get_type(), info.get_hostname(), &addresses, info.get_port(), &now);
+        }
+        ServiceEvent::ServiceRemoved(_, fullname) => cache.removed(&fullname),
+        _ => {}
+    }
+}
+
+/// Démarre le navigateur (sans effet s'il tourne déjà)
+pub fn start() -> Result<()> {
+    let mut browser = BROWSER.lock().map_err(|_| anyhow::anyhow!("mDNS browser lock poisoned"))?;
+    if browser.is_some() {
+        return Ok(());
+    }
+    let daemon = ServiceDaemon::new()?;
+    for service_type in SERVICE_TYPES {
+        let receiver = daemon.browse(service_type)?;
+        tauri::async_runtime::spawn(async move {
+            while let Ok(event) = receiver.recv_async().await {
+                handle_event(event);
+            }
+            tracing::info!("Browser for {} stopped", service_type);
+        });
+    }
+    *browser = Some(daemon);
+    tracing::info!("✅ Browsing {}", SERVICE_TYPES.join(", "));
+    Ok(())
+}
+
+/// Machines actuellement connues
+pub fn hosts() -> Vec<NetworkHost> {
+    CACHE.lock().map(|cache| cache.hosts(chrono::Utc::now())).unwrap_or_default()
+}
+
+/// Machine annoncée sous ce nom ("jellypi" ou "jellypi.local"), si présente
+pub fn find(hostname: &str) -> Option<NetworkHost> {
+    let wanted = short_hostname(hostname);
+    hosts().into_iter().find(|h| h.hostname == wanted)
+}
+
+/// Attend jusqu'à `timeout` qu'une machine soit annoncée (navigateur tout juste démarré)
+pub async fn wait_for(hostname: &str, timeout: Duration) -> Option<NetworkHost> {
+    let start = std::time::Instant::now();
+    loop {
+        if let Some(host) = find(hostname) {
+            return Some(host);
+        }
+        if start.elapsed() >= timeout {
+            return None;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_cache() {
+        assert_eq!(parse_workstation_mac("jellypi [DC:A6:32:01:02:03]._workstation._tcp.local.").as_deref(), Some("dc:a6:32:01:02:03"));
+        assert_eq!(parse_workstation_mac("jellypi._ssh._tcp.local."), None);
+
+        let now = "2026-01-01T12:00:00+00:00";
+        let ipv4: IpAddr = "192.168.1.20".parse().unwrap();
+        let ipv6: IpAddr = "fe80::1".parse().unwrap();
+        let mut cache = HostCache::default();
+        cache.resolved("jellypi._ssh._tcp.local.", "_ssh._tcp.local.", "JellyPi.local.", &[ipv6, ipv4], 22, now);
+        cache.resolved("jellypi [dc:a6:32:01:02:03]._workstation._tcp.local.", "_workstation._tcp.local.", "jellypi.local.", &[], 9, now);
+
+        let at = |time: &str| chrono::DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&chrono::Utc);
+        let hosts = cache.hosts(at("2026-01-01T12:05:00+00:00"));
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].hostname, "jellypi");
+        assert_eq!(hosts[0].addresses, vec!["192.168.1.20", "fe80::1"]);
+        assert_eq!(hosts[0].services, vec!["ssh", "workstation"]);
+        assert_eq!(hosts[0].ssh_port, Some(22));
+        assert_eq!(hosts[0].mac_address.as_deref(), Some("dc:a6:32:01:02:03"));
+        assert!(cache.hosts(at("2026-01-01T13:00:00+00:00")).is_empty());
+
+        cache.removed("jellypi._ssh._tcp.local.");
+        assert_eq!(cache.hosts["jellypi"].services, vec!["workstation"]);
+        assert_eq!(cache.hosts["jellypi"].ssh_port, None);
+        cache.removed("jellypi [dc:a6:32:01:02:03]._workstation._tcp.local.");
+        assert!(cache.hosts.is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Window;
+use tauri::async_runtime::JoinHandle;
+use ts_rs::TS;
+
+use crate::secret::SecretString;
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Surveillance périodique des Pis (tant que l'app est ouverte)
+// =============================================================================
+//
+// Toutes les `interval` secondes, chaque Pi connu est interrogé en SSH (une seule
+// commande: conteneurs, disque, température, mémoire). Le battement de cœur est
+// écrit dans le schéma Supabase du Pi et renvoyé au frontend (événement
+// "monitoring-heartbeat"); un service qui tombe ou revient, ou un Pi injoignable,
+// déclenche l'événement "monitoring-alert".
+
+pub const DEFAULT_INTERVAL_SECS: u64 = 300;
+const MIN_INTERVAL_SECS: u64 = 30;
+/// Un Pi qui ne répond pas en 30 s est considéré injoignable pour ce tour
+const COLLECT_TIMEOUT_SECS: u64 = 30;
+
+/// Tâche de surveillance en cours (une seule à la fois)
+static MONITOR: Lazy<Mutex<Option<JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Une ligne par mesure: C:<conteneur>=<état>, DISK=<%>, TEMP=<millidegrés>, MEM=<%>
+const METRICS_COMMAND: &str = "docker ps -a --format 'C:{{.Names}}={{.State}}'; \
+     echo \"DISK=$(df --output=pcent / | tail -1 | tr -dc '0-9')\"; \
+     echo \"TEMP=$(cat /sys/class/thermal/thermal_zone0/temp 2>/dev/null)\"; \
+     echo \"MEM=$(free | awk '/^Mem:/ {printf \"%.1f\", $3 * 100 / $2}')\"";
+
+/// Pi à surveiller (identifiants fournis par le frontend, jamais persistés)
+#[derive(Debug, Clone, Deserialize)]
+pub struct MonitoredPi {
+    pub host: String,
+    pub username: String,
+    pub pi_name: String,
+    pub password: Option<SecretString>,
+    pub private_key: Option<SecretString>,
+}
+
+impl MonitoredPi {
+    fn target(&self) -> Result<SshTarget<'_>> {
+        let (host, username) = (self.host.as_str(), self.username.as_str());
+        match (&self.private_key, &self.password) {
+            (Some(private_key), _) => Ok(SshTarget::Key { host, username, private_key: private_key.expose() }),
+            (None, Some(password)) => Ok(SshTarget::Password { host, username, password: password.expose() }),
+            (None, None) => Err(anyhow!("{}: aucune clé ni mot de passe", self.pi_name)),
+        }
+    }
+}
+
+/// Battement de cœur d'un Pi
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct PiHeartbeat {
+    pub pi_name: String,
+    pub host: String,
+    pub checked_at: String,
+    pub reachable: bool,
+    pub running: Vec<String>,
+    pub down: Vec<String>,
+    pub disk_percent: Option<f64>,
+    pub cpu_temp_c: Option<f64>,
+    pub mem_percent: Option<f64>,
+}
+
+/// Alerte envoyée au frontend
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct MonitoringAlert {
+    pub pi_name: String,
+    /// "service_down", "service_recovered", "unreachable", "reachable"
+    pub kind: String,
+    pub service: Option<String>,
+    pub message: String,
+}
+
+/// Parse la sortie de METRICS_COMMAND
+pub fn parse_metrics(pi: &MonitoredPi, output: &str) -> PiHeartbeat {
+    let mut heartbeat = PiHeartbeat {
+        pi_name: pi.pi_name.clone(),
+        host: pi.host.clone(),
+        checked_at: chrono::Utc::now().to_rfc3339(),
+        reachable: true,
+        ..Default::default()
+    };
+    for line in output.lines().map(str::trim) {
+        if let Some((name, state)) = line.strip_prefix("C:").and_then(|c| c.split_once('=')) {
+            match state {
+                "running" => heartbeat.running.push(name.to_string()),
+                _ => heartbeat.down.push(name.to_string()),
+            }
+        } else if let Some((key, value)) = line.split_once('=') {
+            let value: Option<f64> = value.trim().parse().ok();
+            match key {
+                "DISK" => heartbeat.disk_percent = value,
+                "TEMP" => heartbeat.cpu_temp_c = value.map(|millis| millis / 1000.0),
+                "MEM" => heartbeat.mem_percent = value,
+                _ => {}
+            }
+        }
+    }
+    heartbeat.running.sort();
+    heartbeat.down.sort();
+    heartbeat
+}
+
+/// Alertes entre deux battements successifs d'un même Pi
+pub fn alerts(previous: Option<&PiHeartbeat>, current: &PiHeartbeat) -> Vec<MonitoringAlert> {
+    let alert = |kind: &str, service: Option<&str>, message: String| MonitoringAlert {
+        pi_name: current.pi_name.clone(),
+        kind: kind.to_string(),
+        service: service.map(String::from),
+        message,
+    };
+    let was_reachable = previous.is_none_or(|p| p.reachable);
+
+    if !current.reachable {
+        return if was_reachable {
+            vec![alert("unreachable", None, format!("{} ne répond plus", current.pi_name))]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let mut alerts = Vec::new();
+    if !was_reachable {
+        alerts.push(alert("reachable", None, format!("{} répond à nouveau", current.pi_name)));
+    }
+    let before: BTreeSet<&String> = previous.filter(|p| p.reachable).map(|p| p.down.iter().collect()).unwrap_or_default();
+    let now: BTreeSet<&String> = current.down.iter().collect();
+    for service in now.difference(&before) {
+        alerts.push(alert("service_down", Some(service), format!("{} est arrêté sur {}", service, current.pi_name)));
+    }
+    for service in before.difference(&now) {
+        alerts.push(alert("service_recovered", Some(service), format!("{} fonctionne à nouveau sur {}", service, current.pi_name)));
+    }
+    alerts
+}
+
+/// Interroge un Pi (Pi injoignable: battement `reachable: false`)
+pub async fn collect(pi: &MonitoredPi) -> PiHeartbeat {
+    let output = match pi.target() {
+        Ok(target) => {
+            target
+                .exec_with_options(METRICS_COMMAND, &crate::ssh::SshOptions::fail_fast(COLLECT_TIMEOUT_SECS))
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match output {
+        Ok(output) => parse_metrics(pi, &output),
+        Err(e) => {
+            tracing::warn!("⚠️ {} unreachable: {}", pi.pi_name, e);
+            PiHeartbeat {
+                pi_name: pi.pi_name.clone(),
+                host: pi.host.clone(),
+                checked_at: chrono::Utc::now().to_rfc3339(),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+async fn monitor_loop(window: Window, pis: Vec<MonitoredPi>, interval: Duration) {
+    let mut last: HashMap<String, PiHeartbeat> = HashMap::new();
+    loop {
+        for pi in &pis {
+            let heartbeat = collect(pi).await;
+            if heartbeat.reachable {
+                crate::pi_registry::touch(&pi.pi_name);
+            }
+            if let Err(e) = crate::supabase::save_heartbeat(&pi.pi_name, &serde_json::to_value(&heartbeat).unwrap_or_default()).await {
+                tracing::warn!("could not save heartbeat for {}: {}", pi.pi_name, e);
+            }
+            for alert in alerts(last.get(&pi.pi_name), &heartbeat) {
+                tracing::info!("{}", alert.message);
+                let _ = window.emit("monitoring-alert", &alert);
+            }
+            let _ = window.emit("monitoring-heartbeat", &heartbeat);
+            last.insert(pi.pi_name.clone(), heartbeat);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Démarre (ou redémarre) la surveillance des Pis donnés
+pub fn start(window: Window, pis: Vec<MonitoredPi>, interval_secs: Option<u64>) -> Result<()> {
+    let interval = Duration::from_secs(interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS).max(MIN_INTERVAL_SECS));
+    tracing::info!("Watching {} Pis every {}s", pis.len(), interval.as_secs());
+
+    let mut monitor = MONITOR.lock().map_err(|_| anyhow!("Monitoring state poisoned"))?;
+    if let Some(previous) = monitor.take() {
+        previous.abort();
+    }
+    *monitor = Some(tauri::async_runtime::spawn(monitor_loop(window, pis, interval)));
+    Ok(())
+}
+
+/// Arrête la surveillance
+pub fn stop() {
+    if let Ok(mut monitor) = MONITOR.lock() {
+        if let Some(task) = monitor.take() {
+            task.abort();
+            tracing::info!("Stopped");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_and_alerts() {
+        let pi = MonitoredPi {
+            host: "192.168.1.20".to_string(),
+            username: "pi".to_string(),
+            pi_name: "salon".to_string(),
+            password: None,
+            private_key: None,
+        };
+        let first = parse_metrics(&pi, "C:jellyfin=running\nC:radarr=running\nDISK=42\nTEMP=51234\nMEM=37.5\n");
+        assert_eq!(first.running, vec!["jellyfin", "radarr"]);
+        assert_eq!(first.disk_percent, Some(42.0));
+        assert_eq!(first.cpu_temp_c, Some(51.234));
+        assert!(alerts(None, &first).is_empty());
+
+        let second = parse_metrics(&pi, "C:jellyfin=running\nC:radarr=exited\nDISK=42\nTEMP=\nMEM=38\n");
+        assert_eq!(second.cpu_temp_c, None);
+        let down = alerts(Some(&first), &second);
+        assert_eq!(down.len(), 1);
+        assert_eq!(down[0].kind, "service_down");
+        assert_eq!(down[0].service.as_deref(), Some("radarr"));
+
+        let unreachable = PiHeartbeat { pi_name: "salon".to_string(), ..Default::default() };
+        assert_eq!(alerts(Some(&second), &unreachable)[0].kind, "unreachable");
+        assert!(alerts(Some(&unreachable), &unreachable).is_empty());
+        assert_eq!(alerts(Some(&unreachable), &first)[0].kind, "reachable");
+    }
+}
@@ -1,9 +1,13 @@
 use crate::PiInfo;
 use anyhow::Result;
-use std::net::{IpAddr, SocketAddr, TcpStream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
 use std::time::Duration;
+use ts_rs::TS;
 
 /// Découvre le Raspberry Pi sur le réseau local
+#[tracing::instrument(skip_all, fields(hostname = %hostname))]
 pub async fn discover_raspberry_pi(hostname: &str, timeout_secs: u64) -> Result<Option<PiInfo>> {
     let timeout = Duration::from_secs(timeout_secs);
     let start = std::time::Instant::now();
@@ -115,9 +119,9 @@ async fn discover_via_mdns(hostname: &str) -> Result<Option<PiInfo>> {
             for addr in addrs {
                 if let IpAddr::V4(ipv4) = addr.ip() {
                     let ip_str = ipv4.to_string();
-                    println!("[Discovery] Resolved {} to {}", full_hostname, ip_str);
+                    tracing::info!("Resolved {} to {}", full_hostname, ip_str);
                     if is_ssh_available(&ip_str).await {
-                        println!("[Discovery] SSH available on {}", ip_str);
+                        tracing::info!("SSH available on {}", ip_str);
                         return Ok(Some(PiInfo {
                             ip: ip_str,
                             hostname: hostname.to_string(),
@@ -129,39 +133,18 @@ async fn discover_via_mdns(hostname: &str) -> Result<Option<PiInfo>> {
         }
     }
 
-    // Méthode 2: mDNS service discovery (backup)
-    use mdns_sd::{ServiceDaemon, ServiceEvent};
-
-    if let Ok(mdns) = ServiceDaemon::new() {
-        let service_type = "_ssh._tcp.local.";
-        if let Ok(receiver) = mdns.browse(service_type) {
-            let timeout = Duration::from_secs(5);
-            let start = std::time::Instant::now();
-
-            while start.elapsed() < timeout {
-                match receiver.recv_timeout(Duration::from_secs(1)) {
-                    Ok(ServiceEvent::ServiceResolved(info)) => {
-                        println!("[Discovery] mDNS found: {}", info.get_hostname());
-                        if info.get_hostname().starts_with(hostname) {
-                            let ip = info
-                                .get_addresses()
-                                .iter()
-                                .find(|addr| addr.is_ipv4())
-                                .map(|addr| addr.to_string());
-
-                            if let Some(ip) = ip {
-                                return Ok(Some(PiInfo {
-                                    ip,
-                                    hostname: hostname.to_string(),
-                                    mac_address: None,
-                                }));
-                            }
-                        }
-                    }
-                    Ok(_) => {}
-                    Err(_) => break,
-                }
-            }
+    // Méthode 2: cache du navigateur mDNS permanent (annonces _ssh._tcp / _workstation._tcp)
+    if let Err(e) = crate::mdns::start() {
+        tracing::warn!("⚠️ mDNS browser unavailable: {}", e);
+    }
+    if let Some(host) = crate::mdns::wait_for(hostname, Duration::from_secs(5)).await {
+        tracing::info!("mDNS found: {} ({:?})", host.hostname, host.addresses);
+        if let Some(ip) = host.addresses.iter().find(|a| a.parse::<std::net::Ipv4Addr>().is_ok()) {
+            return Ok(Some(PiInfo {
+                ip: ip.clone(),
+                hostname: hostname.to_string(),
+                mac_address: host.mac_address.clone(),
+            }));
         }
     }
 
@@ -267,3 +250,168 @@ pub async fn ping(ip: &str) -> bool {
         output.map(|o| o.status.success()).unwrap_or(false)
     }
 }
+
+// =============================================================================
+// Sélecteur d'appareil: machines du réseau local avec indices d'identification
+// =============================================================================
+//
+// Pour les Pi dont le nom d'hôte diffère de celui attendu (renommé, DHCP), toutes
+// les machines du /24 local exposant SSH, Jellyfin ou Jellyseerr sont listées avec
+// leur MAC (table ARP remplie par le scan), le constructeur (OUI) et le nom mDNS.
+
+/// Ports sondés: SSH, Jellyfin, Jellyseerr
+pub const SCANNED_PORTS: [u16; 3] = [22, 8096, 5055];
+const PORT_TIMEOUT: Duration = Duration::from_millis(600);
+
+/// Préfixes OUI connus (Raspberry Pi Foundation / Raspberry Pi Trading)
+const VENDOR_PREFIXES: [(&str, &str); 6] = [
+    ("b8:27:eb", "Raspberry Pi Foundation"),
+    ("dc:a6:32", "Raspberry Pi Trading"),
+    ("e4:5f:01", "Raspberry Pi Trading"),
+    ("d8:3a:dd", "Raspberry Pi Trading"),
+    ("28:cd:c1", "Raspberry Pi Trading"),
+    ("2c:cf:67", "Raspberry Pi Trading"),
+];
+
+/// Machine trouvée par `scan_network_devices`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct NetworkDevice {
+    pub ip: String,
+    /// Nom annoncé en mDNS
+    pub hostname: Option<String>,
+    pub mac_address: Option<String>,
+    /// Constructeur déduit de la MAC ("Raspberry Pi Trading", "Adresse privée")
+    pub vendor: Option<String>,
+    pub open_ports: Vec<u16>,
+    /// Raspberry Pi probable (MAC Raspberry Pi ou Jellyfin en écoute)
+    pub likely_pi: bool,
+}
+
+/// "dc-a6-32-1-2-3" / "DC:A6:32:01:02:03" -> "dc:a6:32:01:02:03"
+pub fn normalize_mac(mac: &str) -> Option<String> {
+    let bytes: Vec<String> = mac
+        .split([':', '-'])
+        .map(|b| format!("{:0>2}", b.to_lowercase()))
+        .collect();
+    let valid = bytes.len() == 6 && bytes.iter().all(|b| b.len() == 2 && b.chars().all(|c| c.is_ascii_hexdigit()));
+    (valid && bytes.iter().any(|b| b != "00" && b != "ff")).then(|| bytes.join(":"))
+}
+
+/// Table ARP (`arp -an` macOS/Linux, `arp -a` Windows) -> IP -> MAC
+pub fn parse_arp_table(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            // "? (192.168.1.20) at dc:a6:32:1:2:3 on en0" ou "192.168.1.20   dc-a6-32-01-02-03   dynamique"
+            let ip = words.iter().find_map(|w| w.trim_matches(['(', ')']).parse::<Ipv4Addr>().ok())?;
+            let mac = words.iter().find_map(|w| normalize_mac(w))?;
+            Some((ip.to_string(), mac))
+        })
+        .collect()
+}
+
+/// Constructeur d'après le préfixe OUI (bit "administré localement": MAC aléatoire)
+pub fn mac_vendor(mac: &str) -> Option<String> {
+    if let Some((_, vendor)) = VENDOR_PREFIXES.iter().find(|(prefix, _)| mac.starts_with(prefix)) {
+        return Some(vendor.to_string());
+    }
+    let first = u8::from_str_radix(mac.get(..2)?, 16).ok()?;
+    (first & 0x02 != 0).then(|| "Adresse privée (aléatoire)".to_string())
+}
+
+async fn open_ports(ip: Ipv4Addr) -> Vec<u16> {
+    let mut open = Vec::new();
+    for port in SCANNED_PORTS {
+        let connect = tokio::net::TcpStream::connect(SocketAddr::new(IpAddr::V4(ip), port));
+        if matches!(tokio::time::timeout(PORT_TIMEOUT, connect).await, Ok(Ok(_))) {
+            open.push(port);
+        }
+    }
+    open
+}
+
+async fn arp_table() -> HashMap<String, String> {
+    #[cfg(target_os = "windows")]
+    let output = tokio::process::Command::new("arp").arg("-a").output().await;
+    #[cfg(target_os = "macos")]
+    let output = tokio::process::Command::new("/usr/sbin/arp").arg("-an").output().await;
+    #[cfg(target_os = "linux")]
+    let output = tokio::process::Command::new("arp").arg("-an").output().await;
+
+    match output {
+        Ok(output) => parse_arp_table(&String::from_utf8_lossy(&output.stdout)),
+        Err(e) => {
+            tracing::warn!("⚠️ arp unavailable: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Scanne le /24 local (SSH, Jellyfin, Jellyseerr) et identifie les machines trouvées
+#[tracing::instrument]
+pub async fn scan_network_devices() -> Result<Vec<NetworkDevice>> {
+    let local_ip: Ipv4Addr = get_local_ip()?.parse()?;
+    let [a, b, c, _] = local_ip.octets();
+    if let Err(e) = crate::mdns::start() {
+        tracing::warn!("⚠️ mDNS browser unavailable: {}", e);
+    }
+
+    let handles: Vec<_> = (1..=254u8)
+        .map(|i| Ipv4Addr::new(a, b, c, i))
+        .filter(|ip| *ip != local_ip)
+        .map(|ip| tokio::spawn(async move { (ip, open_ports(ip).await) }))
+        .collect();
+    let mut found = Vec::new();
+    for handle in handles {
+        if let Ok((ip, ports)) = handle.await {
+            if !ports.is_empty() {
+                found.push((ip, ports));
+            }
+        }
+    }
+
+    // Les connexions du scan ont rempli la table ARP
+    let arp = arp_table().await;
+    let mdns_hosts = crate::mdns::hosts();
+    let devices: Vec<NetworkDevice> = found
+        .into_iter()
+        .map(|(ip, open_ports)| {
+            let ip = ip.to_string();
+            let mdns_host = mdns_hosts.iter().find(|h| h.addresses.contains(&ip));
+            let mac_address = arp.get(&ip).cloned().or_else(|| mdns_host.and_then(|h| h.mac_address.clone()));
+            let vendor = mac_address.as_deref().and_then(mac_vendor);
+            let likely_pi = vendor.as_deref().map(|v| v.starts_with("Raspberry Pi")).unwrap_or(false) || open_ports.contains(&8096);
+            NetworkDevice { hostname: mdns_host.map(|h| h.hostname.clone()), ip, mac_address, vendor, open_ports, likely_pi }
+        })
+        .collect();
+
+    tracing::info!("✅ {} devices found on {}.{}.{}.0/24", devices.len(), a, b, c);
+    Ok(devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arp_table_and_vendor() {
+        let macos = "? (192.168.1.1) at 0:24:d4:a:b:c on en0 ifscope [ethernet]\n\
+                     ? (192.168.1.20) at dc:a6:32:1:2:3 on en0 ifscope [ethernet]\n\
+                     ? (192.168.1.30) at (incomplete) on en0 ifscope [ethernet]\n\
+                     ? (192.168.1.255) at ff:ff:ff:ff:ff:ff on en0 ifscope [ethernet]\n";
+        let table = parse_arp_table(macos);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table["192.168.1.1"], "00:24:d4:0a:0b:0c");
+        assert_eq!(table["192.168.1.20"], "dc:a6:32:01:02:03");
+
+        let windows = "Interface : 192.168.1.10 --- 0x5\n  Adresse Internet      Adresse physique      Type\n  192.168.1.21          d8-3a-dd-01-02-03     dynamique\n";
+        assert_eq!(parse_arp_table(windows)["192.168.1.21"], "d8:3a:dd:01:02:03");
+
+        assert_eq!(mac_vendor("dc:a6:32:01:02:03").as_deref(), Some("Raspberry Pi Trading"));
+        assert_eq!(mac_vendor("b8:27:eb:01:02:03").as_deref(), Some("Raspberry Pi Foundation"));
+        assert_eq!(mac_vendor("da:a1:19:01:02:03").as_deref(), Some("Adresse privée (aléatoire)"));
+        assert_eq!(mac_vendor("00:24:d4:0a:0b:0c"), None);
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::install_state::{InstallState, InstallStep};
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Notifications Discord (webhook) du cycle de vie de l'installation
+// =============================================================================
+//
+// Début, fin de chaque étape, échec (étape + dernières lignes du journal du Pi)
+// et succès (URLs locales des services). Un webhook injoignable n'interrompt
+// jamais l'installation: les erreurs sont seulement journalisées.
+
+const COLOR_INFO: u32 = 0x5865F2;
+const COLOR_SUCCESS: u32 = 0x57F287;
+const COLOR_ERROR: u32 = 0xED4245;
+
+/// Limite Discord d'une valeur de champ d'embed
+const FIELD_MAX_CHARS: usize = 1024;
+
+const LOG_TAIL_COMMAND: &str = "tail -n 15 ~/jellysetup-logs/install.log 2>/dev/null";
+
+/// Embed Discord (titre, description, couleur, champs nom/valeur)
+pub fn embed(title: &str, description: &str, color: u32, fields: &[(String, String)]) -> Value {
+    let fields: Vec<Value> = fields
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": truncate(value, FIELD_MAX_CHARS), "inline": false }))
+        .collect();
+    json!({
+        "username": "JellySetup",
+        "embeds": [{
+            "title": title,
+            "description": description,
+            "color": color,
+            "fields": fields,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        }]
+    })
+}
+
+/// Tronque par la fin en gardant les dernières lignes (les plus utiles d'un journal)
+fn truncate(value: &str, max_chars: usize) -> String {
+    let count = value.chars().count();
+    if count <= max_chars {
+        return value.to_string();
+    }
+    let tail: String = value.chars().skip(count - (max_chars - 1)).collect();
+    format!("…{}", tail)
+}
+
+/// Journal dans un bloc de code (les ``` internes casseraient le rendu)
+fn code_block(log: &str) -> String {
+    let log = truncate(log.trim(), FIELD_MAX_CHARS - 8).replace("```", "'''");
+    format!("```\n{}\n```", log)
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscordNotifier {
+    webhook_url: String,
+    client: reqwest::Client,
+}
+
+impl DiscordNotifier {
+    /// Notifier du webhook configuré (aucun si absent ou pas une URL de webhook Discord)
+    pub fn from_webhook(webhook: Option<&str>) -> Option<Self> {
+        let webhook = webhook.map(str::trim).filter(|w| !w.is_empty())?;
+        let valid = ["https://discord.com/api/webhooks/", "https://discordapp.com/api/webhooks/"]
+            .iter()
+            .any(|prefix| webhook.starts_with(prefix));
+        if !valid {
+            tracing::warn!("⚠️ Ignoring invalid Discord webhook URL");
+            return None;
+        }
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().ok()?;
+        Some(Self { webhook_url: webhook.to_string(), client })
+    }
+
+    async fn post(&self, payload: &Value) -> Result<()> {
+        let response = self.client.post(&self.webhook_url).json(payload).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Discord webhook HTTP {}", response.status()));
+        }
+        Ok(())
+    }
+
+    async fn send(&self, payload: Value) {
+        if let Err(e) = self.post(&payload).await {
+            tracing::warn!("⚠️ Discord notification failed: {}", e);
+        }
+    }
+
+    pub async fn install_started(&self, pi_name: &str, host: &str) {
+        let description = format!("Installation démarrée sur **{}** ({})", pi_name, host);
+        self.send(embed("🚀 Installation démarrée", &description, COLOR_INFO, &[])).await;
+    }
+
+    pub async fn phase_completed(&self, step: InstallStep, done: usize) {
+        let description = format!("Étape {}/{} terminée", done, InstallStep::ALL.len());
+        self.send(embed(&format!("✅ {}", step.label()), &description, COLOR_INFO, &[])).await;
+    }
+
+    /// Succès (URLs locales des services) ou échec (étape + fin du journal du Pi)
+    pub async fn install_finished(&self, target: &SshTarget<'_>, state: &InstallState, result: &Result<()>) {
+        let pi_name = state.pi_name.as_deref().unwrap_or(&state.host);
+        let payload = match result {
+            Ok(()) => {
+                let compose = target.exec("cat ~/media-stack/docker-compose.yml").await.unwrap_or_default();
+                let urls: Vec<String> = crate::status::status_targets(&compose)
+                    .iter()
+                    .map(|t| format!("• {}: http://{}:{}", t.name, state.host, t.port))
+                    .collect();
+                let fields = if urls.is_empty() { Vec::new() } else { vec![("Services".to_string(), urls.join("\n"))] };
+                embed("🎉 Installation terminée", &format!("**{}** est prêt !", pi_name), COLOR_SUCCESS, &fields)
+            }
+            Err(e) => {
+                let step = state.failed_step.map_or("inconnue", |s| s.label());
+                let mut fields = vec![("Étape".to_string(), step.to_string()), ("Erreur".to_string(), e.to_string())];
+                let log = target.exec(LOG_TAIL_COMMAND).await.unwrap_or_default();
+                if !log.trim().is_empty() {
+                    fields.push(("Dernières lignes du journal".to_string(), code_block(&log)));
+                }
+                embed("❌ Installation échouée", &format!("Échec sur **{}**", pi_name), COLOR_ERROR, &fields)
+            }
+        };
+        self.send(payload).await;
+    }
+
+    /// Mise à jour annulée: checks en échec et services revenus à l'image précédente
+    pub async fn update_rolled_back(&self, pi_name: &str, failed_checks: &[String], services: &[String]) {
+        let fields = vec![
+            ("Vérifications en échec".to_string(), failed_checks.join("\n")),
+            ("Services restaurés".to_string(), services.join(", ")),
+        ];
+        let description = format!("La mise à jour de **{}** a échoué: version précédente restaurée", pi_name);
+        self.send(embed("↩️ Mise à jour annulée", &description, COLOR_ERROR, &fields)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_payload() {
+        let log = "x".repeat(2000);
+        let payload = embed("❌ Échec", "pi", COLOR_ERROR, &[("Journal".to_string(), code_block(&log))]);
+        let field = payload["embeds"][0]["fields"][0]["value"].as_str().unwrap();
+        assert!(field.chars().count() <= FIELD_MAX_CHARS);
+        assert!(field.starts_with("```\n…") && field.ends_with("x\n```"));
+        assert_eq!(payload["embeds"][0]["color"], COLOR_ERROR);
+
+        assert!(DiscordNotifier::from_webhook(Some("https://discord.com/api/webhooks/1/abc")).is_some());
+        assert!(DiscordNotifier::from_webhook(Some("http://example.com/hook")).is_none());
+        assert!(DiscordNotifier::from_webhook(Some("  ")).is_none());
+    }
+}
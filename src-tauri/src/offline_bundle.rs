@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::Window;
+use ts_rs::TS;
+
+use crate::compose::StackDefinition;
+use crate::procedure::{Procedure, PROCEDURE_VERSION};
+
+// =============================================================================
+// Installation hors ligne (bundle pré-téléchargé)
+// =============================================================================
+//
+// Un bundle est un dossier préparé sur un ordinateur connecté: image Raspberry Pi
+// OS (.img.xz), steps.json (procédure + définition du stack), templates de la
+// procédure et, en option, l'archive `docker save` des images du stack. Importé
+// dans cache_dir/jellysetup/offline, il active le mode hors ligne (Settings):
+// le flash écrit l'image du bundle, procédure/stack/templates sont lus localement,
+// les images Docker sont chargées depuis la carte SD et les écritures Supabase
+// attendent le retour de la connexion (sync_outbox).
+// Le Pi n'a alors besoin d'Internet que pour les paquets système (apt, Docker).
+
+const MANIFEST_FILE: &str = "manifest.json";
+const PROCEDURE_FILE: &str = "steps.json";
+const TEMPLATES_DIR: &str = "templates";
+const STACK_IMAGES_FILE: &str = "stack-images.tar";
+
+/// Contenu d'un bundle (manifest.json)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct OfflineBundleInfo {
+    pub installer_version: String,
+    /// RFC 3339
+    pub created_at: String,
+    /// Image Raspberry Pi OS compressée (.img.xz)
+    pub os_image: String,
+    pub procedure_version: String,
+    pub templates: Vec<String>,
+    /// Archive des images Docker (None: images tirées par le Pi)
+    pub stack_images: Option<String>,
+    #[ts(type = "number")]
+    pub size_bytes: u64,
+}
+
+/// Nom de fichier simple (pas de chemin dans un manifest importé)
+fn plain_name(name: &str) -> Result<&str> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(anyhow!("Nom de fichier invalide dans le bundle: {}", name));
+    }
+    Ok(name)
+}
+
+impl OfflineBundleInfo {
+    /// Vérifie les fichiers du bundle et la compatibilité de sa procédure
+    fn check(&self, dir: &Path) -> Result<()> {
+        if !plain_name(&self.os_image)?.ends_with(".img.xz") {
+            return Err(anyhow!("Image du bundle inattendue: {} (.img.xz attendu)", self.os_image));
+        }
+        let mut files = vec![self.os_image.clone(), PROCEDURE_FILE.to_string()];
+        for template in &self.templates {
+            files.push(format!("{}/{}", TEMPLATES_DIR, plain_name(template)?));
+        }
+        if let Some(archive) = &self.stack_images {
+            files.push(plain_name(archive)?.to_string());
+        }
+        if let Some(missing) = files.iter().find(|file| !dir.join(file).is_file()) {
+            return Err(anyhow!("Bundle incomplet: {} manquant", missing));
+        }
+
+        let json = std::fs::read_to_string(dir.join(PROCEDURE_FILE))?;
+        Procedure::parse(&json)?;
+        StackDefinition::from_procedure(&serde_json::from_str(&json)?)?;
+        Ok(())
+    }
+}
+
+fn offline_dir() -> Result<PathBuf> {
+    Ok(dirs::cache_dir()
+        .ok_or_else(|| anyhow!("Cannot find cache directory"))?
+        .join("jellysetup")
+        .join("offline"))
+}
+
+fn read_manifest(dir: &Path) -> Result<OfflineBundleInfo> {
+    let content = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|_| anyhow!("Aucun bundle hors ligne dans {}", dir.display()))?;
+    serde_json::from_str(&content).map_err(|e| anyhow!("manifest.json invalide: {}", e))
+}
+
+/// Mode hors ligne activé dans les réglages
+pub fn active() -> bool {
+    crate::config::settings().offline_mode
+}
+
+/// Bundle importé sur cet ordinateur
+pub fn installed() -> Option<OfflineBundleInfo> {
+    read_manifest(&offline_dir().ok()?).ok()
+}
+
+/// Image du bundle et nom de l'image extraite
+pub fn os_image() -> Result<(PathBuf, String)> {
+    let bundle = installed().ok_or_else(|| anyhow!("Mode hors ligne: aucun bundle importé"))?;
+    let extracted = bundle.os_image.trim_end_matches(".xz").to_string();
+    Ok((offline_dir()?.join(&bundle.os_image), extracted))
+}
+
+/// Archive des images Docker du bundle, si elle a été incluse
+pub fn stack_images_archive() -> Option<PathBuf> {
+    let archive = installed()?.stack_images?;
+    Some(offline_dir().ok()?.join(archive))
+}
+
+fn procedure_json() -> Result<String> {
+    Ok(std::fs::read_to_string(offline_dir()?.join(PROCEDURE_FILE))?)
+}
+
+pub fn procedure() -> Result<Procedure> {
+    Procedure::parse(&procedure_json()?)
+}
+
+pub fn stack() -> Result<StackDefinition> {
+    StackDefinition::from_procedure(&serde_json::from_str(&procedure_json()?)?)
+}
+
+pub fn template(template: &str) -> Result<String> {
+    let path = offline_dir()?.join(TEMPLATES_DIR).join(plain_name(template)?);
+    std::fs::read_to_string(&path).map_err(|_| anyhow!("Template {} absent du bundle hors ligne", template))
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Prépare un bundle dans `dest` (ordinateur connecté à Internet)
+pub async fn create(window: &Window, dest: &Path, include_images: bool) -> Result<OfflineBundleInfo> {
+    if active() {
+        return Err(anyhow!("Désactivez le mode hors ligne pour préparer un bundle"));
+    }
+    tokio::fs::create_dir_all(dest.join(TEMPLATES_DIR)).await?;
+
+    // Image: réutilise celle du cache du flash si elle y est déjà
+    let (url, image_name) = crate::flash::get_latest_rpi_os_url().await?;
+    let os_image = format!("{}.xz", image_name);
+    let cached = dirs::cache_dir().map(|d| d.join("jellysetup").join(&os_image)).filter(|p| p.exists());
+    match cached {
+        Some(cached) => {
+            tokio::fs::copy(&cached, dest.join(&os_image)).await?;
+        }
+        None => crate::flash::download_image(window, &url, &dest.join(&os_image)).await?,
+    }
+
+    let json = match crate::procedure::fetch_remote_json(PROCEDURE_VERSION).await {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::warn!("⚠️ Remote procedure unavailable ({}), bundling embedded copy", e);
+            crate::compose::EMBEDDED_PROCEDURE.to_string()
+        }
+    };
+    let procedure = Procedure::parse(&json)?;
+    tokio::fs::write(dest.join(PROCEDURE_FILE), &json).await?;
+
+    let templates = procedure.templates();
+    for template in &templates {
+        let content = crate::procedure::fetch_template(template).await?;
+        tokio::fs::write(dest.join(TEMPLATES_DIR).join(plain_name(template)?), content).await?;
+    }
+
+    let stack_images = if include_images {
+        let stack = StackDefinition::from_procedure(&serde_json::from_str(&json)?)?;
+        let archive = crate::image_cache::build_archive(&crate::image_cache::stack_images(&stack)).await?;
+        tokio::fs::copy(&archive, dest.join(STACK_IMAGES_FILE)).await?;
+        Some(STACK_IMAGES_FILE.to_string())
+    } else {
+        None
+    };
+
+    let mut bundle = OfflineBundleInfo {
+        installer_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        os_image,
+        procedure_version: procedure.version.clone(),
+        templates,
+        stack_images,
+        size_bytes: 0,
+    };
+    bundle.size_bytes = file_size(&dest.join(&bundle.os_image))
+        + file_size(&dest.join(PROCEDURE_FILE))
+        + bundle.stack_images.as_ref().map(|a| file_size(&dest.join(a))).unwrap_or(0);
+    tokio::fs::write(dest.join(MANIFEST_FILE), serde_json::to_string_pretty(&bundle)?).await?;
+
+    tracing::info!("✅ Bundle created in {:?} ({} MB)", dest, bundle.size_bytes / 1_000_000);
+    Ok(bundle)
+}
+
+/// Importe un bundle (copié dans le cache) et active le mode hors ligne
+pub async fn import(src: &Path) -> Result<OfflineBundleInfo> {
+    let bundle = read_manifest(src)?;
+    bundle.check(src)?;
+
+    let dir = offline_dir()?;
+    if dir.exists() {
+        tokio::fs::remove_dir_all(&dir).await?;
+    }
+    tokio::fs::create_dir_all(dir.join(TEMPLATES_DIR)).await?;
+    let mut files = vec![MANIFEST_FILE.to_string(), PROCEDURE_FILE.to_string(), bundle.os_image.clone()];
+    files.extend(bundle.templates.iter().map(|t| format!("{}/{}", TEMPLATES_DIR, t)));
+    files.extend(bundle.stack_images.clone());
+    for file in &files {
+        tokio::fs::copy(src.join(file), dir.join(file)).await?;
+    }
+
+    crate::config::save(crate::config::Settings { offline_mode: true, ..crate::config::settings() })?;
+    tracing::info!(
+        "✅ Bundle imported: {} (procedure {}, {} templates, images Docker: {})",
+        bundle.os_image,
+        bundle.procedure_version,
+        bundle.templates.len(),
+        bundle.stack_images.is_some()
+    );
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_check() {
+        let dir = std::env::temp_dir().join(format!("jellysetup-bundle-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join(TEMPLATES_DIR)).unwrap();
+        let mut bundle = OfflineBundleInfo {
+            installer_version: "1.1.0".to_string(),
+            created_at: "2026-01-01T12:00:00+00:00".to_string(),
+            os_image: "2024-11-19-raspios-bookworm-arm64-lite.img.xz".to_string(),
+            procedure_version: "1.0.0".to_string(),
+            templates: vec!["docker-compose.yml.j2".to_string()],
+            stack_images: None,
+            size_bytes: 0,
+        };
+        std::fs::write(dir.join(&bundle.os_image), b"xz").unwrap();
+        std::fs::write(dir.join(PROCEDURE_FILE), crate::compose::EMBEDDED_PROCEDURE).unwrap();
+        assert!(bundle.check(&dir).unwrap_err().to_string().contains("templates/docker-compose.yml.j2 manquant"));
+
+        std::fs::write(dir.join(TEMPLATES_DIR).join("docker-compose.yml.j2"), "services: {}").unwrap();
+        assert!(bundle.check(&dir).is_ok());
+
+        bundle.stack_images = Some("../../etc/passwd".to_string());
+        assert!(bundle.check(&dir).is_err());
+        bundle.stack_images = None;
+        bundle.os_image = "raspios.iso".to_string();
+        assert!(bundle.check(&dir).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
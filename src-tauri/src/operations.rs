@@ -0,0 +1,396 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use ts_rs::TS;
+
+// =============================================================================
+// Opérations longues (flash, installation, découverte)
+// =============================================================================
+
+// Annulation: `cancel` déclenche le CancellationToken de l'opération. Les couches
+// réseau (canaux SSH, téléchargements, boucles d'attente des services) le
+// surveillent via `current_token()` et s'arrêtent d'elles-mêmes, en fermant
+// canal et connexion; la tâche n'est interrompue de force qu'après un délai de
+// grâce si elle ne s'est pas terminée.
+
+/// Délai laissé à une opération annulée pour s'arrêter proprement
+const CANCEL_GRACE_SECS: u64 = 10;
+
+/// Durée pendant laquelle une opération terminée reste consultable (get_operation)
+const FINISHED_RETENTION: Duration = Duration::from_secs(3600);
+
+// Registre global des opérations en cours ou terminées (élagué à chaque `start`)
+static OPERATIONS: Lazy<Mutex<HashMap<String, OperationEntry>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+tokio::task_local! {
+    // ID de l'opération exécutée par la tâche courante
+    static CURRENT_OPERATION: String;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum OperationKind {
+    Flash,
+    Install,
+    Discovery,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum OperationStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// État d'une opération, renvoyé au frontend par get_operation
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct Operation {
+    pub id: String,
+    pub kind: OperationKind,
+    pub status: OperationStatus,
+    pub step: String,
+    pub percent: u32,
+    pub message: String,
+    #[ts(type = "unknown")]
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    /// Dossier de session (artefacts de l'installation, voir workspace.rs)
+    pub workspace: Option<String>,
+}
+
+struct OperationEntry {
+    operation: Operation,
+    handle: Option<JoinHandle<()>>,
+    token: CancellationToken,
+    /// Fin (ou annulation) de l'opération, pour l'élagage du registre
+    finished: Option<Instant>,
+}
+
+/// Retire les opérations terminées depuis plus de FINISHED_RETENTION
+/// (une opération annulée dont la tâche tourne encore est gardée: elle lit son token).
+/// Le handle est rangé après le spawn: une tâche déjà finie peut en garder un, d'où
+/// `is_finished` plutôt que sa seule présence
+fn prune(ops: &mut HashMap<String, OperationEntry>, now: Instant) {
+    ops.retain(|_, entry| {
+        entry.handle.as_ref().is_some_and(|handle| !handle.inner().is_finished())
+            || entry.operation.status == OperationStatus::Running
+            || entry.finished.is_none_or(|at| now.duration_since(at) < FINISHED_RETENTION)
+    });
+}
+
+/// Erreur renvoyée par une étape interrompue par `cancel`
+#[derive(Debug, thiserror::Error)]
+#[error("Opération annulée")]
+pub struct Cancelled;
+
+/// Lance une opération en tâche de fond et retourne son ID
+pub fn start<F, T>(kind: OperationKind, future: F) -> String
+where
+    F: Future<Output = anyhow::Result<T>> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    let id = uuid::Uuid::new_v4().to_string();
+
+    // Dossier de session pour les opérations qui modifient la carte SD ou le Pi
+    let workspace = match kind {
+        OperationKind::Flash | OperationKind::Install => match crate::workspace::create(&id) {
+            Ok(dir) => Some(dir.to_string_lossy().to_string()),
+            Err(e) => {
+                tracing::warn!("⚠️ Could not create session folder: {}", e);
+                None
+            }
+        },
+        OperationKind::Discovery => None,
+    };
+
+    let operation = Operation {
+        id: id.clone(),
+        kind,
+        status: OperationStatus::Running,
+        step: "starting".to_string(),
+        percent: 0,
+        message: String::new(),
+        result: None,
+        error: None,
+        started_at: chrono::Utc::now().to_rfc3339(),
+        finished_at: None,
+        workspace,
+    };
+
+    // Insérer avant le spawn pour que la tâche trouve son entrée
+    if let Ok(mut ops) = OPERATIONS.lock() {
+        prune(&mut ops, Instant::now());
+        ops.insert(
+            id.clone(),
+            OperationEntry { operation, handle: None, token: CancellationToken::new(), finished: None },
+        );
+    }
+
+    let task_id = id.clone();
+    let handle = tauri::async_runtime::spawn(CURRENT_OPERATION.scope(id.clone(), async move {
+        let outcome = future.await;
+        finish(&task_id, outcome.map(|value| serde_json::to_value(value).ok()));
+    }));
+
+    if let Ok(mut ops) = OPERATIONS.lock() {
+        if let Some(entry) = ops.get_mut(&id) {
+            entry.handle = Some(handle);
+        }
+    }
+
+    tracing::info!("Started {:?} operation {}", kind, id);
+    id
+}
+
+/// Met à jour la progression de l'opération de la tâche courante (no-op hors opération)
+pub fn report_progress(step: &str, percent: u32, message: &str) {
+    let _ = CURRENT_OPERATION.try_with(|id| {
+        if let Ok(mut ops) = OPERATIONS.lock() {
+            if let Some(entry) = ops.get_mut(id) {
+                entry.operation.step = step.to_string();
+                entry.operation.percent = percent;
+                entry.operation.message = message.to_string();
+            }
+        }
+    });
+}
+
+/// ID de l'opération de la tâche courante
+pub fn current_id() -> Option<String> {
+    CURRENT_OPERATION.try_with(|id| id.clone()).ok()
+}
+
+/// Token d'annulation de l'opération de la tâche courante
+/// (token jamais déclenché hors opération)
+pub fn current_token() -> CancellationToken {
+    CURRENT_OPERATION
+        .try_with(|id| {
+            OPERATIONS
+                .lock()
+                .ok()
+                .and_then(|ops| ops.get(id).map(|entry| entry.token.clone()))
+        })
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// L'opération de la tâche courante a été annulée
+pub fn is_cancelled() -> bool {
+    current_token().is_cancelled()
+}
+
+/// Exécute `future` jusqu'à son terme ou jusqu'à l'annulation de l'opération courante
+pub async fn cancellable<F, T>(future: F) -> anyhow::Result<T>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let token = current_token();
+    tokio::select! {
+        outcome = future => outcome,
+        _ = token.cancelled() => Err(Cancelled.into()),
+    }
+}
+
+/// Pause interrompue par l'annulation de l'opération courante
+pub async fn sleep(duration: Duration) -> anyhow::Result<()> {
+    cancellable(async {
+        tokio::time::sleep(duration).await;
+        Ok(())
+    })
+    .await
+}
+
+fn finish(id: &str, outcome: anyhow::Result<Option<serde_json::Value>>) {
+    if let Ok(mut ops) = OPERATIONS.lock() {
+        if let Some(entry) = ops.get_mut(id) {
+            entry.handle = None;
+            // Une opération annulée reste annulée
+            if entry.operation.status != OperationStatus::Running {
+                tracing::info!("Cancelled operation {} stopped", id);
+                return;
+            }
+            match outcome {
+                Ok(result) => {
+                    entry.operation.status = OperationStatus::Completed;
+                    entry.operation.percent = 100;
+                    entry.operation.result = result;
+                    tracing::info!("✅ Operation {} completed", id);
+                }
+                Err(e) => {
+                    entry.operation.status = OperationStatus::Failed;
+                    entry.operation.error = Some(e.to_string());
+                    tracing::error!("❌ Operation {} failed: {}", id, e);
+                }
+            }
+            entry.operation.finished_at = Some(chrono::Utc::now().to_rfc3339());
+            entry.finished = Some(Instant::now());
+        }
+    }
+}
+
+/// Retourne l'état d'une opération
+pub fn get(id: &str) -> Option<Operation> {
+    OPERATIONS
+        .lock()
+        .ok()
+        .and_then(|ops| ops.get(id).map(|entry| entry.operation.clone()))
+}
+
+/// Liste toutes les opérations connues (plus récentes en premier)
+pub fn list() -> Vec<Operation> {
+    let mut operations: Vec<Operation> = OPERATIONS
+        .lock()
+        .map(|ops| ops.values().map(|entry| entry.operation.clone()).collect())
+        .unwrap_or_default();
+    operations.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    operations
+}
+
+/// Demande l'annulation d'une opération en cours
+pub fn cancel(id: &str) -> anyhow::Result<()> {
+    let mut ops = OPERATIONS
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Operations registry poisoned"))?;
+
+    let entry = ops
+        .get_mut(id)
+        .ok_or_else(|| anyhow::anyhow!("Opération inconnue: {}", id))?;
+
+    if entry.operation.status != OperationStatus::Running {
+        return Err(anyhow::anyhow!("L'opération {} n'est plus en cours", id));
+    }
+
+    entry.token.cancel();
+
+    // Filet de sécurité: interruption forcée si l'opération ignore le token
+    let task_id = id.to_string();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(CANCEL_GRACE_SECS)).await;
+        let handle = OPERATIONS
+            .lock()
+            .ok()
+            .and_then(|mut ops| ops.get_mut(&task_id).and_then(|entry| entry.handle.take()));
+        if let Some(handle) = handle {
+            tracing::warn!("⚠️ Operation {} still running, aborting", task_id);
+            handle.abort();
+        }
+    });
+
+    entry.operation.status = OperationStatus::Cancelled;
+    entry.operation.finished_at = Some(chrono::Utc::now().to_rfc3339());
+    entry.finished = Some(Instant::now());
+    tracing::warn!("⚠️ Operation {} cancelled", id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(id: &str) -> Operation {
+        Operation {
+            id: id.to_string(),
+            kind: OperationKind::Install,
+            status: OperationStatus::Running,
+            step: String::new(),
+            percent: 0,
+            message: String::new(),
+            result: None,
+            error: None,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            workspace: None,
+        }
+    }
+
+    #[test]
+    fn test_prune_finished_operations() {
+        // Dans le futur: `now - age` reste valide pour tous les âges testés
+        let now = Instant::now() + FINISHED_RETENTION * 2;
+        let entry = |status, finished: Option<Duration>| {
+            let mut operation = operation("prune");
+            operation.status = status;
+            OperationEntry {
+                operation,
+                handle: None,
+                token: CancellationToken::new(),
+                finished: finished.map(|age| now - age),
+            }
+        };
+
+        let mut ops = HashMap::new();
+        ops.insert("running".to_string(), entry(OperationStatus::Running, None));
+        ops.insert("recent".to_string(), entry(OperationStatus::Completed, Some(Duration::from_secs(60))));
+        ops.insert("old".to_string(), entry(OperationStatus::Failed, Some(FINISHED_RETENTION * 2)));
+        ops.insert("cancelled".to_string(), entry(OperationStatus::Cancelled, Some(FINISHED_RETENTION * 2)));
+        prune(&mut ops, now);
+
+        let mut kept: Vec<&str> = ops.keys().map(String::as_str).collect();
+        kept.sort();
+        assert_eq!(kept, vec!["recent", "running"]);
+    }
+
+    #[tokio::test]
+    async fn test_prune_ignores_finished_handle() {
+        // Handle rangé après la fin de la tâche: l'entrée doit quand même être élaguée
+        let handle = tauri::async_runtime::spawn(async {});
+        while !handle.inner().is_finished() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let mut operation = operation("stale");
+        operation.status = OperationStatus::Completed;
+        let now = Instant::now() + FINISHED_RETENTION * 2;
+
+        let mut ops = HashMap::new();
+        ops.insert(
+            "stale".to_string(),
+            OperationEntry {
+                operation,
+                handle: Some(handle),
+                token: CancellationToken::new(),
+                finished: Some(now - FINISHED_RETENTION * 2),
+            },
+        );
+        prune(&mut ops, now);
+        assert!(ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_token() {
+        // Hors opération: jamais annulé
+        assert!(!is_cancelled());
+        assert!(sleep(Duration::from_millis(1)).await.is_ok());
+
+        let id = "test-cancel".to_string();
+        let token = CancellationToken::new();
+        OPERATIONS.lock().unwrap().insert(
+            id.clone(),
+            OperationEntry { operation: operation(&id), handle: None, token: token.clone(), finished: None },
+        );
+
+        CURRENT_OPERATION
+            .scope(id.clone(), async {
+                token.cancel();
+                assert!(is_cancelled());
+                let error = sleep(Duration::from_secs(3600)).await.unwrap_err();
+                assert!(error.is::<Cancelled>());
+            })
+            .await;
+        OPERATIONS.lock().unwrap().remove(&id);
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Métriques système du Pi (température, throttling, disque, RAM, uptime)
+// =============================================================================
+//
+// Une seule commande SSH; `vcgencmd` (firmware Raspberry Pi) donne la température
+// et l'état de throttling, avec repli sur thermal_zone0 hors Raspberry Pi OS.
+
+/// Température à partir de laquelle on prévient l'utilisateur (°C)
+const HOT_TEMP_C: f64 = 75.0;
+
+/// Lecture rapide: au-delà, le Pi est jugé injoignable
+const METRICS_TIMEOUT_SECS: u64 = 30;
+
+/// Une ligne par mesure: TEMP=, ZONE=, THROTTLED=, DISK=size,used,avail, MEM=total,used,available, UPTIME=
+const METRICS_COMMAND: &str = "echo \"TEMP=$(vcgencmd measure_temp 2>/dev/null)\"; \
+     echo \"ZONE=$(cat /sys/class/thermal/thermal_zone0/temp 2>/dev/null)\"; \
+     echo \"THROTTLED=$(vcgencmd get_throttled 2>/dev/null)\"; \
+     df -B1 --output=size,used,avail / | tail -1 | awk '{print \"DISK=\"$1\",\"$2\",\"$3}'; \
+     free -b | awk '/^Mem:/ {print \"MEM=\"$2\",\"$3\",\"$7}'; \
+     echo \"UPTIME=$(cut -d' ' -f1 /proc/uptime)\"";
+
+/// Bits de `vcgencmd get_throttled` (actuel / depuis le démarrage)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ThrottleStatus {
+    /// Valeur brute ("0x50005")
+    pub raw: String,
+    pub under_voltage: bool,
+    pub freq_capped: bool,
+    pub throttled: bool,
+    pub soft_temp_limit: bool,
+    pub under_voltage_occurred: bool,
+    pub freq_capped_occurred: bool,
+    pub throttled_occurred: bool,
+    pub soft_temp_limit_occurred: bool,
+}
+
+impl ThrottleStatus {
+    /// "throttled=0x50005" -> bits décodés
+    pub fn parse(value: &str) -> Option<Self> {
+        let raw = value.trim().trim_start_matches("throttled=");
+        let bits = u32::from_str_radix(raw.trim_start_matches("0x"), 16).ok()?;
+        let bit = |n: u32| bits & (1 << n) != 0;
+        Some(ThrottleStatus {
+            raw: raw.to_string(),
+            under_voltage: bit(0),
+            freq_capped: bit(1),
+            throttled: bit(2),
+            soft_temp_limit: bit(3),
+            under_voltage_occurred: bit(16),
+            freq_capped_occurred: bit(17),
+            throttled_occurred: bit(18),
+            soft_temp_limit_occurred: bit(19),
+        })
+    }
+}
+
+/// Métriques système du Pi
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct PiMetrics {
+    pub cpu_temp_c: Option<f64>,
+    /// Absent hors Raspberry Pi OS (pas de vcgencmd)
+    pub throttle: Option<ThrottleStatus>,
+    #[ts(type = "number")]
+    pub disk_total_bytes: u64,
+    #[ts(type = "number")]
+    pub disk_used_bytes: u64,
+    #[ts(type = "number")]
+    pub disk_available_bytes: u64,
+    #[ts(type = "number")]
+    pub mem_total_bytes: u64,
+    #[ts(type = "number")]
+    pub mem_used_bytes: u64,
+    #[ts(type = "number")]
+    pub mem_available_bytes: u64,
+    #[ts(type = "number")]
+    pub uptime_secs: u64,
+    /// Avertissements à afficher (sous-tension, throttling, disque plein, ...)
+    pub warnings: Vec<String>,
+}
+
+fn triple(value: &str) -> (u64, u64, u64) {
+    let mut parts = value.split(',').map(|v| v.trim().parse().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+fn warnings(metrics: &PiMetrics) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(throttle) = &metrics.throttle {
+        if throttle.under_voltage {
+            warnings.push("Sous-tension: utiliser l'alimentation officielle (5V/5A pour un Pi 5)".to_string());
+        }
+        if throttle.throttled || throttle.soft_temp_limit {
+            warnings.push("Le Pi réduit sa fréquence (surchauffe): transcodages ralentis, ajouter un refroidissement".to_string());
+        } else if throttle.throttled_occurred || throttle.soft_temp_limit_occurred {
+            warnings.push("Le Pi a réduit sa fréquence depuis le démarrage: vérifier le refroidissement".to_string());
+        }
+    }
+    if let Some(temp) = metrics.cpu_temp_c.filter(|t| *t >= HOT_TEMP_C) {
+        warnings.push(format!("Température élevée: {:.1}°C", temp));
+    }
+    if metrics.disk_total_bytes > 0 && metrics.disk_available_bytes * 10 < metrics.disk_total_bytes {
+        warnings.push("Moins de 10% d'espace disque libre".to_string());
+    }
+    warnings
+}
+
+/// Parse la sortie de METRICS_COMMAND
+pub fn parse_metrics(output: &str) -> PiMetrics {
+    let mut metrics = PiMetrics::default();
+    let mut zone_temp = None;
+    for (key, value) in output.lines().filter_map(|line| line.trim().split_once('=')) {
+        match key {
+            // "temp=51.0'C"
+            "TEMP" => {
+                metrics.cpu_temp_c = value.trim_start_matches("temp=").trim_end_matches("'C").trim().parse().ok();
+            }
+            "ZONE" => zone_temp = value.trim().parse::<f64>().ok().map(|millis| millis / 1000.0),
+            "THROTTLED" => metrics.throttle = ThrottleStatus::parse(value),
+            "DISK" => {
+                (metrics.disk_total_bytes, metrics.disk_used_bytes, metrics.disk_available_bytes) = triple(value);
+            }
+            "MEM" => {
+                (metrics.mem_total_bytes, metrics.mem_used_bytes, metrics.mem_available_bytes) = triple(value);
+            }
+            "UPTIME" => metrics.uptime_secs = value.trim().parse::<f64>().map(|s| s as u64).unwrap_or(0),
+            _ => {}
+        }
+    }
+    metrics.cpu_temp_c = metrics.cpu_temp_c.or(zone_temp);
+    metrics.warnings = warnings(&metrics);
+    metrics
+}
+
+pub async fn collect(target: &SshTarget<'_>) -> Result<PiMetrics> {
+    let options = crate::ssh::SshOptions::fail_fast(METRICS_TIMEOUT_SECS);
+    let metrics = parse_metrics(&target.exec_with_options(METRICS_COMMAND, &options).await?);
+    for warning in &metrics.warnings {
+        tracing::warn!("⚠️ {}", warning);
+    }
+    Ok(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pi_metrics() {
+        let output = "TEMP=temp=78.4'C\nZONE=78400\nTHROTTLED=throttled=0x50005\n\
+            DISK=62000000000,58000000000,4000000000\nMEM=8000000000,3000000000,4500000000\nUPTIME=86400.52\n";
+        let metrics = parse_metrics(output);
+        assert_eq!(metrics.cpu_temp_c, Some(78.4));
+        let throttle = metrics.throttle.clone().unwrap();
+        assert!(throttle.under_voltage && throttle.throttled && throttle.under_voltage_occurred);
+        assert!(!throttle.freq_capped && !throttle.soft_temp_limit_occurred);
+        assert_eq!(metrics.disk_available_bytes, 4_000_000_000);
+        assert_eq!(metrics.mem_used_bytes, 3_000_000_000);
+        assert_eq!(metrics.uptime_secs, 86400);
+        assert_eq!(metrics.warnings.len(), 4);
+
+        // Sans vcgencmd: température de thermal_zone0, pas d'état de throttling
+        let generic = parse_metrics("TEMP=\nZONE=45000\nTHROTTLED=\n");
+        assert_eq!(generic.cpu_temp_c, Some(45.0));
+        assert_eq!(generic.throttle, None);
+        assert!(generic.warnings.is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use ts_rs::TS;
+
+use crate::registration::InstallationRegistration;
+
+// =============================================================================
+// Registre des Pis installés (gestion de plusieurs Pis)
+// =============================================================================
+//
+// Chaque installation réussie est mémorisée localement dans
+// config_dir/jellysetup/pi_registry.json et copiée dans Supabase (best effort),
+// pour que mise à jour, surveillance et logs fonctionnent sur tous les Pis du
+// foyer, y compris après une réinstallation de l'application. Les lignes cloud
+// appartiennent au compte connecté (account.rs, RLS sur owner_id). La clé privée
+// SSH n'en fait jamais partie: elle reste dans le coffre de l'OS (secrets.rs,
+// portée = nom du Pi).
+
+/// Pi connu de l'application
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct RegisteredPi {
+    pub pi_name: String,
+    pub host: String,
+    pub username: String,
+    /// "key" ou "password"
+    pub auth_method: String,
+    #[serde(default)]
+    pub ssh_host_fingerprint: Option<String>,
+    pub installed_version: String,
+    /// RFC 3339
+    pub added_at: String,
+    #[serde(default)]
+    pub last_seen: Option<String>,
+}
+
+impl RegisteredPi {
+    pub fn from_registration(registration: &InstallationRegistration, username: &str) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            pi_name: registration.pi_name.clone(),
+            host: registration.pi_ip.clone(),
+            username: username.to_string(),
+            auth_method: registration.auth_method().to_string(),
+            ssh_host_fingerprint: registration.ssh_host_fingerprint.clone(),
+            installed_version: registration.installer_version.clone(),
+            added_at: now.clone(),
+            last_seen: Some(now),
+        }
+    }
+}
+
+/// Contenu du fichier local
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Registry {
+    #[serde(default)]
+    selected: Option<String>,
+    #[serde(default)]
+    pis: Vec<RegisteredPi>,
+}
+
+impl Registry {
+    /// Ajoute ou remplace un Pi (la date d'ajout d'origine est conservée)
+    fn upsert(&mut self, mut pi: RegisteredPi) {
+        if let Some(existing) = self.pis.iter().find(|p| p.pi_name == pi.pi_name) {
+            pi.added_at = existing.added_at.clone();
+        }
+        self.pis.retain(|p| p.pi_name != pi.pi_name);
+        self.pis.push(pi);
+        self.pis.sort_by(|a, b| a.pi_name.cmp(&b.pi_name));
+        if self.selected.is_none() {
+            self.selected = self.pis.first().map(|p| p.pi_name.clone());
+        }
+    }
+
+    fn remove(&mut self, pi_name: &str) -> bool {
+        let before = self.pis.len();
+        self.pis.retain(|p| p.pi_name != pi_name);
+        if self.selected.as_deref() == Some(pi_name) {
+            self.selected = self.pis.first().map(|p| p.pi_name.clone());
+        }
+        self.pis.len() != before
+    }
+
+    /// Ajoute les Pis connus du cloud absents localement (réinstallation de l'app)
+    fn merge_remote(&mut self, remote: Vec<RegisteredPi>) -> usize {
+        let missing: Vec<RegisteredPi> = remote
+            .into_iter()
+            .filter(|r| !self.pis.iter().any(|p| p.pi_name == r.pi_name))
+            .collect();
+        let count = missing.len();
+        missing.into_iter().for_each(|pi| self.upsert(pi));
+        count
+    }
+}
+
+fn registry_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("jellysetup").join("pi_registry.json"))
+}
+
+fn load() -> Registry {
+    registry_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(registry: &Registry) -> Result<()> {
+    let path = registry_path().ok_or_else(|| anyhow!("Cannot determine config directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(registry)?)?;
+    Ok(())
+}
+
+/// Pis connus, complétés par ceux du cloud si Supabase répond
+pub async fn list() -> Vec<RegisteredPi> {
+    let mut registry = load();
+    match crate::supabase::list_registered_pis().await {
+        Ok(rows) => {
+            let remote: Vec<RegisteredPi> = rows.into_iter().filter_map(|row| serde_json::from_value(row).ok()).collect();
+            if registry.merge_remote(remote) > 0 {
+                if let Err(e) = save(&registry) {
+                    tracing::warn!("⚠️ Could not save merged registry: {}", e);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("⚠️ Cloud registry unavailable: {}", e),
+    }
+    registry.pis
+}
+
+/// Ajoute (ou met à jour) un Pi, localement puis dans le cloud
+pub async fn add(pi: RegisteredPi) -> Result<()> {
+    let mut registry = load();
+    registry.upsert(pi.clone());
+    save(&registry)?;
+    if let Err(e) = crate::supabase::save_registered_pi(&serde_json::to_value(&pi)?).await {
+        tracing::warn!("⚠️ Could not sync {} to Supabase: {}", pi.pi_name, e);
+    }
+    tracing::info!("✅ {} ({}) registered", pi.pi_name, pi.host);
+    Ok(())
+}
+
+/// Oublie un Pi (l'installation elle-même n'est pas touchée)
+pub async fn remove(pi_name: &str) -> Result<()> {
+    let mut registry = load();
+    if !registry.remove(pi_name) {
+        return Err(anyhow!("Pi '{}' inconnu", pi_name));
+    }
+    save(&registry)?;
+    if let Err(e) = crate::supabase::remove_registered_pi(pi_name).await {
+        tracing::warn!("⚠️ Could not remove {} from Supabase: {}", pi_name, e);
+    }
+    Ok(())
+}
+
+/// Pi sur lequel portent les écrans post-installation
+pub fn select(pi_name: &str) -> Result<RegisteredPi> {
+    let mut registry = load();
+    let pi = registry
+        .pis
+        .iter()
+        .find(|p| p.pi_name == pi_name)
+        .cloned()
+        .ok_or_else(|| anyhow!("Pi '{}' inconnu", pi_name))?;
+    registry.selected = Some(pi.pi_name.clone());
+    save(&registry)?;
+    Ok(pi)
+}
+
+pub fn selected() -> Option<RegisteredPi> {
+    let registry = load();
+    let name = registry.selected?;
+    registry.pis.into_iter().find(|p| p.pi_name == name)
+}
+
+/// Met à jour la date de dernier contact (surveillance, connexion réussie)
+pub fn touch(pi_name: &str) {
+    let mut registry = load();
+    if let Some(pi) = registry.pis.iter_mut().find(|p| p.pi_name == pi_name) {
+        pi.last_seen = Some(chrono::Utc::now().to_rfc3339());
+        save(&registry).ok();
+    }
+}
+
+/// Range la clé privée SSH d'un Pi dans le coffre de l'OS (jamais dans le cloud)
+pub fn store_private_key(pi_name: &str, private_key: &str) -> Result<()> {
+    crate::secrets::store(crate::secrets::SecretKind::SshPrivateKey, pi_name, &private_key.into())
+}
+
+/// Mémorise le Pi en fin d'installation (ne bloque jamais)
+pub async fn record_installation(registration: &InstallationRegistration, username: &str, private_key: Option<&str>) {
+    if let Some(private_key) = private_key {
+        if let Err(e) = store_private_key(&registration.pi_name, private_key) {
+            tracing::warn!("⚠️ Could not store SSH key of {} in the keychain: {}", registration.pi_name, e);
+        }
+    }
+    if let Err(e) = add(RegisteredPi::from_registration(registration, username)).await {
+        tracing::warn!("⚠️ Could not register {}: {}", registration.pi_name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registration::RegistrationAuth;
+
+    fn pi(name: &str) -> RegisteredPi {
+        let registration = InstallationRegistration::new(name, "192.168.1.20", RegistrationAuth::Password, None);
+        RegisteredPi::from_registration(&registration, "pi")
+    }
+
+    #[test]
+    fn test_registry_operations() {
+        let mut registry = Registry::default();
+        registry.upsert(pi("salon"));
+        assert_eq!(registry.selected.as_deref(), Some("salon"));
+
+        let mut salon = pi("salon");
+        salon.added_at = "2030-01-01T00:00:00Z".to_string();
+        salon.host = "192.168.1.21".to_string();
+        let original_added = registry.pis[0].added_at.clone();
+        registry.upsert(salon);
+        assert_eq!(registry.pis.len(), 1);
+        assert_eq!(registry.pis[0].added_at, original_added);
+        assert_eq!(registry.pis[0].host, "192.168.1.21");
+
+        assert_eq!(registry.merge_remote(vec![pi("chalet"), pi("salon")]), 1);
+        assert_eq!(registry.pis[0].pi_name, "chalet");
+
+        assert!(registry.remove("salon"));
+        assert_eq!(registry.selected.as_deref(), Some("chalet"));
+        assert!(!registry.remove("salon"));
+    }
+}
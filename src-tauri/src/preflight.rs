@@ -0,0 +1,405 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ts_rs::TS;
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Vérifications avant installation (modèle, RAM, disque, réseau)
+// =============================================================================
+
+// RAM minimum: un Pi 4 Go remonte ~3.7 Go dans MemTotal
+const MIN_MEM_KB: u64 = 3_500_000;
+const MIN_DISK_FREE_KB: u64 = 10 * 1024 * 1024;
+const RECOMMENDED_DISK_FREE_KB: u64 = 20 * 1024 * 1024;
+
+/// Collecte toutes les infos en une seule commande SSH (une ligne CLE=valeur par mesure)
+const PREFLIGHT_SCRIPT: &str = r#"
+echo "MODEL=$(tr -d '\0' < /proc/device-tree/model 2>/dev/null)"
+echo "MEM_KB=$(awk '/MemTotal/ {print $2}' /proc/meminfo)"
+echo "DISK_FREE_KB=$(df -Pk / | awk 'NR==2 {print $4}')"
+echo "ARCH=$(uname -m)"
+echo "KERNEL=$(uname -r)"
+echo "DNS=$(getent hosts get.docker.com >/dev/null 2>&1 && echo ok || echo fail)"
+echo "INTERNET=$(curl -s -o /dev/null -m 10 -w '%{http_code}' https://registry-1.docker.io/v2/ 2>/dev/null || echo 000)"
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Résultat d'une vérification
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct PreflightCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub value: String,
+    pub message: String,
+}
+
+/// Rapport complet: l'UI bloque si `can_install` est faux, avertit s'il reste des Warn
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct PreflightReport {
+    pub checks: Vec<PreflightCheck>,
+    pub can_install: bool,
+    pub has_warnings: bool,
+}
+
+fn check(name: &str, status: CheckStatus, value: &str, message: &str) -> PreflightCheck {
+    PreflightCheck {
+        name: name.to_string(),
+        status,
+        value: value.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Lance les vérifications sur le Pi
+pub async fn run_preflight(target: &SshTarget<'_>) -> Result<PreflightReport> {
+    let output = target.exec(PREFLIGHT_SCRIPT).await?;
+    let report = parse_preflight_output(&output);
+
+    tracing::info!(
+        "{} (can_install: {}, warnings: {})",
+        if report.can_install { "✅" } else { "❌" },
+        report.can_install,
+        report.has_warnings
+    );
+    Ok(report)
+}
+
+/// Construit le rapport depuis la sortie de PREFLIGHT_SCRIPT
+pub fn parse_preflight_output(output: &str) -> PreflightReport {
+    let values: HashMap<&str, &str> = output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let get = |key: &str| values.get(key).copied().unwrap_or("");
+
+    let mut checks = Vec::new();
+
+    // Modèle
+    let model = get("MODEL");
+    checks.push(if model.contains("Raspberry Pi 5") || model.contains("Raspberry Pi 4") {
+        check("model", CheckStatus::Pass, model, "Modèle supporté")
+    } else if model.contains("Raspberry Pi") {
+        check("model", CheckStatus::Warn, model, "Modèle ancien: Raspberry Pi 4 ou 5 recommandé")
+    } else {
+        check("model", CheckStatus::Warn, model, "Modèle non reconnu (pas un Raspberry Pi ?)")
+    });
+
+    // RAM
+    let mem_kb: u64 = get("MEM_KB").parse().unwrap_or(0);
+    let mem_value = format!("{:.1} Go", mem_kb as f64 / 1024.0 / 1024.0);
+    checks.push(if mem_kb >= MIN_MEM_KB {
+        check("memory", CheckStatus::Pass, &mem_value, "Mémoire suffisante")
+    } else {
+        check("memory", CheckStatus::Fail, &mem_value, "La stack Jellyfin nécessite au moins 4 Go de RAM")
+    });
+
+    // Espace disque
+    let disk_kb: u64 = get("DISK_FREE_KB").parse().unwrap_or(0);
+    let disk_value = format!("{:.1} Go libres", disk_kb as f64 / 1024.0 / 1024.0);
+    checks.push(if disk_kb >= RECOMMENDED_DISK_FREE_KB {
+        check("disk", CheckStatus::Pass, &disk_value, "Espace disque suffisant")
+    } else if disk_kb >= MIN_DISK_FREE_KB {
+        check("disk", CheckStatus::Warn, &disk_value, "Espace disque limité: 20 Go libres recommandés")
+    } else {
+        check("disk", CheckStatus::Fail, &disk_value, "Au moins 10 Go libres sont nécessaires pour les images Docker")
+    });
+
+    // Architecture
+    let arch = get("ARCH");
+    checks.push(match arch {
+        "aarch64" | "arm64" => check("architecture", CheckStatus::Pass, arch, "OS 64 bits"),
+        "x86_64" => check("architecture", CheckStatus::Warn, arch, "Machine x86_64: installation possible mais non testée"),
+        _ => check("architecture", CheckStatus::Fail, arch, "Un OS 64 bits (aarch64) est requis"),
+    });
+
+    // Noyau
+    let kernel = get("KERNEL");
+    let major: u32 = kernel.split('.').next().and_then(|m| m.parse().ok()).unwrap_or(0);
+    checks.push(match major {
+        m if m >= 6 => check("kernel", CheckStatus::Pass, kernel, "Noyau récent"),
+        5 => check("kernel", CheckStatus::Warn, kernel, "Noyau ancien: mettre à jour Raspberry Pi OS est recommandé"),
+        _ => check("kernel", CheckStatus::Fail, kernel, "Noyau trop ancien ou inconnu"),
+    });
+
+    // DNS
+    let dns = get("DNS");
+    checks.push(if dns == "ok" {
+        check("dns", CheckStatus::Pass, dns, "Résolution DNS fonctionnelle")
+    } else {
+        check("dns", CheckStatus::Fail, dns, "Le Pi ne résout pas les noms de domaine")
+    });
+
+    // Internet (le registry Docker répond 401 sans authentification)
+    let http_code = get("INTERNET");
+    checks.push(if !http_code.is_empty() && http_code != "000" {
+        check("internet", CheckStatus::Pass, http_code, "Registry Docker joignable")
+    } else {
+        check("internet", CheckStatus::Fail, http_code, "Pas d'accès Internet (registry Docker injoignable)")
+    });
+
+    let can_install = checks.iter().all(|c| c.status != CheckStatus::Fail);
+    let has_warnings = checks.iter().any(|c| c.status == CheckStatus::Warn);
+
+    PreflightReport { checks, can_install, has_warnings }
+}
+
+// =============================================================================
+// Vérifications côté ordinateur (batterie, espace du cache) avant flash/installation
+// =============================================================================
+
+/// Sur batterie, en dessous de ce niveau on refuse de démarrer
+const MIN_BATTERY_PERCENT: u8 = 30;
+/// Image compressée + image extraite + marge
+const MIN_CACHE_FREE_BYTES: u64 = 6 * 1024 * 1024 * 1024;
+const RECOMMENDED_CACHE_FREE_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Opération longue à protéger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum HostOperation {
+    Flash,
+    Install,
+}
+
+/// Passe outre les refus (l'utilisateur a confirmé dans l'UI)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HostOverrides {
+    /// Démarrer malgré une batterie faible
+    #[serde(default)]
+    pub ignore_battery: bool,
+    #[serde(default)]
+    pub ignore_low_disk: bool,
+}
+
+/// Alimentation de l'ordinateur
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    /// None: pas de batterie (ordinateur fixe)
+    pub percent: Option<u8>,
+}
+
+/// Sortie de `pmset -g batt` (macOS)
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn parse_pmset_output(output: &str) -> PowerStatus {
+    let percent = output
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .find_map(|word| word.strip_suffix('%')?.parse().ok());
+    PowerStatus {
+        on_battery: output.contains("'Battery Power'"),
+        percent,
+    }
+}
+
+/// Lit l'état de la batterie via les outils de l'OS (None si illisible)
+async fn read_power_status() -> Option<PowerStatus> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = tokio::process::Command::new("pmset").args(["-g", "batt"]).output().await.ok()?;
+        Some(parse_pmset_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let mut status = PowerStatus { on_battery: false, percent: None };
+        for entry in std::fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+            let path = entry.path();
+            let read = |file: &str| std::fs::read_to_string(path.join(file)).unwrap_or_default().trim().to_string();
+            if read("type") == "Battery" {
+                status.percent = read("capacity").parse().ok();
+                status.on_battery = read("status") == "Discharging";
+            }
+        }
+        Some(status)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // BatteryStatus 1 = sur batterie
+        let output = tokio::process::Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-CimInstance Win32_Battery | Select-Object -First 1 | ForEach-Object { \"$($_.BatteryStatus) $($_.EstimatedChargeRemaining)\" }",
+            ])
+            .output()
+            .await
+            .ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut parts = stdout.split_whitespace();
+        Some(match (parts.next(), parts.next()) {
+            (Some(battery_status), Some(percent)) => PowerStatus {
+                on_battery: battery_status == "1",
+                percent: percent.parse().ok(),
+            },
+            _ => PowerStatus { on_battery: false, percent: None },
+        })
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Espace libre (octets) du disque contenant `path`
+pub(crate) async fn free_space_bytes(path: &std::path::Path) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        let output = tokio::process::Command::new("df").arg("-Pk").arg(path).output().await.ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let kb: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+
+    #[cfg(windows)]
+    {
+        let drive = path.to_string_lossy().chars().next()?;
+        let output = tokio::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", &format!("(Get-PSDrive {}).Free", drive)])
+            .output()
+            .await
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    }
+}
+
+/// Construit le rapport côté ordinateur (sans accès à l'OS)
+pub fn evaluate_host(operation: HostOperation, power: Option<&PowerStatus>, cache_free: Option<u64>) -> PreflightReport {
+    let mut checks = Vec::new();
+
+    checks.push(match power {
+        Some(PowerStatus { on_battery: true, percent: Some(p) }) if *p < MIN_BATTERY_PERCENT => check(
+            "battery",
+            CheckStatus::Fail,
+            &format!("{}%", p),
+            "Batterie faible: branchez l'ordinateur, une coupure pendant l'opération peut corrompre la carte SD",
+        ),
+        Some(PowerStatus { on_battery: true, percent }) => check(
+            "battery",
+            CheckStatus::Warn,
+            &percent.map(|p| format!("{}%", p)).unwrap_or_default(),
+            "Sur batterie: brancher l'ordinateur est recommandé",
+        ),
+        Some(_) => check("battery", CheckStatus::Pass, "secteur", "Ordinateur branché"),
+        None => check("battery", CheckStatus::Warn, "", "État de la batterie inconnu"),
+    });
+
+    // Seul le flash utilise le cache (image téléchargée puis extraite)
+    if operation == HostOperation::Flash {
+        let value = cache_free
+            .map(|b| format!("{:.1} Go libres", b as f64 / 1024.0 / 1024.0 / 1024.0))
+            .unwrap_or_default();
+        checks.push(match cache_free {
+            Some(b) if b >= RECOMMENDED_CACHE_FREE_BYTES => {
+                check("cache_disk", CheckStatus::Pass, &value, "Espace suffisant pour l'image")
+            }
+            Some(b) if b >= MIN_CACHE_FREE_BYTES => {
+                check("cache_disk", CheckStatus::Warn, &value, "Espace limité: 10 Go libres recommandés")
+            }
+            Some(_) => check(
+                "cache_disk",
+                CheckStatus::Fail,
+                &value,
+                "Au moins 6 Go libres sont nécessaires pour télécharger et extraire l'image",
+            ),
+            None => check("cache_disk", CheckStatus::Warn, &value, "Espace disque inconnu"),
+        });
+    }
+
+    let can_install = checks.iter().all(|c| c.status != CheckStatus::Fail);
+    let has_warnings = checks.iter().any(|c| c.status == CheckStatus::Warn);
+    PreflightReport { checks, can_install, has_warnings }
+}
+
+/// Vérifie la batterie et le disque du cache de l'ordinateur
+pub async fn check_host(operation: HostOperation) -> PreflightReport {
+    let power = read_power_status().await;
+    let cache_free = match dirs::cache_dir() {
+        Some(dir) => free_space_bytes(&dir).await,
+        None => None,
+    };
+    evaluate_host(operation, power.as_ref(), cache_free)
+}
+
+/// Refuse de démarrer une opération longue si un contrôle échoue et n'est pas ignoré
+pub async fn guard_host(operation: HostOperation, overrides: &HostOverrides) -> Result<()> {
+    let report = check_host(operation).await;
+
+    for c in &report.checks {
+        let ignored = match c.name.as_str() {
+            "battery" => overrides.ignore_battery,
+            "cache_disk" => overrides.ignore_low_disk,
+            _ => false,
+        };
+        match c.status {
+            CheckStatus::Fail if !ignored => {
+                tracing::error!("❌ {:?} refused: {} ({})", operation, c.message, c.value);
+                return Err(anyhow!("{} ({})", c.message, c.value));
+            }
+            CheckStatus::Fail | CheckStatus::Warn => {
+                tracing::warn!("⚠️ {}: {} ({})", c.name, c.message, c.value);
+            }
+            CheckStatus::Pass => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_of(report: &PreflightReport, name: &str) -> CheckStatus {
+        report.checks.iter().find(|c| c.name == name).unwrap().status
+    }
+
+    #[test]
+    fn test_preflight_pi4_ok() {
+        let output = "MODEL=Raspberry Pi 4 Model B Rev 1.4\nMEM_KB=3884400\nDISK_FREE_KB=52428800\nARCH=aarch64\nKERNEL=6.6.31+rpt-rpi-v8\nDNS=ok\nINTERNET=401\n";
+        let report = parse_preflight_output(output);
+        assert!(report.can_install);
+        assert!(!report.has_warnings);
+    }
+
+    #[test]
+    fn test_preflight_blocks_small_pi() {
+        let output = "MODEL=Raspberry Pi 3 Model B Rev 1.2\nMEM_KB=944000\nDISK_FREE_KB=5242880\nARCH=armv7l\nKERNEL=5.10.103-v7+\nDNS=fail\nINTERNET=000\n";
+        let report = parse_preflight_output(output);
+        assert!(!report.can_install);
+        assert_eq!(status_of(&report, "model"), CheckStatus::Warn);
+        assert_eq!(status_of(&report, "memory"), CheckStatus::Fail);
+        assert_eq!(status_of(&report, "architecture"), CheckStatus::Fail);
+        assert_eq!(status_of(&report, "internet"), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_host_guards() {
+        let power = parse_pmset_output("Now drawing from 'Battery Power'\n -InternalBattery-0 (id=123)\t18%; discharging; 1:02 remaining present: true");
+        assert_eq!(power, PowerStatus { on_battery: true, percent: Some(18) });
+
+        let report = evaluate_host(HostOperation::Flash, Some(&power), Some(4 * 1024 * 1024 * 1024));
+        assert!(!report.can_install);
+        assert_eq!(status_of(&report, "battery"), CheckStatus::Fail);
+        assert_eq!(status_of(&report, "cache_disk"), CheckStatus::Fail);
+
+        let plugged = parse_pmset_output("Now drawing from 'AC Power'\n -InternalBattery-0 (id=123)\t18%; charging");
+        let report = evaluate_host(HostOperation::Install, Some(&plugged), None);
+        assert!(report.can_install);
+        assert_eq!(report.checks.len(), 1);
+    }
+}
@@ -0,0 +1,1074 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::install_state::InstallStep;
+use crate::logging::{InstallationLogger, LogLevel};
+use crate::ssh::{self, SshTarget};
+use crate::template_engine::TemplateVars;
+
+// =============================================================================
+// Moteur de procédures (procedures/<version>/steps.json)
+// =============================================================================
+//
+// La séquence d'installation est décrite par les `steps` de la procédure publiée
+// sur GitHub (bundle importé en mode hors ligne; repli: copie embarquée). Les étapes `builtin` désignent les phases
+// codées en Rust (InstallStep, dans leur ordre); les autres types s'exécutent sur
+// le Pi et peuvent être ajoutés, retirés ou placés entre deux phases sans nouvelle
+// version de l'application:
+//   commands      commandes shell (bash -e), `outputs`: variables capturées
+//   file          écriture d'un fichier (`content`)
+//   template      fichier procedures/<version>/templates/<template> rendu vers `output`
+//   wait          attente de services HTTP (`services`: name/port/path)
+//   json_patch    remplacements JSON pointer dans un fichier, puis `postCommands`
+//   api_calls     appels REST locaux (`baseUrl`, `apiKeyVar`, `calls`)
+//   service_config  configuration master_config d'un service (`configType` de la procédure)
+//   summary       récapitulatif des services (journal)
+// La clé `hooks` attache des commandes avant/après les phases builtin (voir hooks.rs).
+// `condition` ("VAR", "!VAR", "VAR==valeur") conditionne une étape. Les valeurs
+// acceptent {{VAR}} (template engine) et ${VAR} (variables connues uniquement, les
+// variables shell sont laissées intactes). Dans `commands` et `postCommands`,
+// chaque valeur est insérée entre quotes (sauf {{SUDO}}, fragment de commande);
+// les chemins `path`/`output`/`file` sont quotés à l'écriture (`~/` conservé).
+//
+// Ce format est publié sous procedures/v2; procedures/v1 reste dans son format
+// d'origine pour les installeurs déjà distribués.
+//
+// Le fichier est validé avant toute exécution: `schemaVersion` (format compris par
+// l'application), `minInstallerVersion`, type de chaque étape, phases builtin
+// complètes et ordonnées, puis variables {{VAR}} définies au lancement du runner.
+// Une procédure invalide échoue avant la première commande, jamais en cours de route.
+// `plan` détaille sans rien exécuter les actions concrètes (secrets masqués).
+
+const EXIT_MARKER: &str = "__STEP_EXIT=";
+const OUTPUT_MARKER: &str = "__STEP_OUTPUT_";
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+/// Version de procédure utilisée par l'installation
+pub const PROCEDURE_VERSION: &str = "v2";
+/// Version du format de steps.json comprise par cette application
+pub const SCHEMA_VERSION: u32 = 1;
+/// Valeurs acceptées pour le champ "type" d'une étape
+const ACTIONS: [&str; 9] = [
+    "builtin",
+    "commands",
+    "file",
+    "template",
+    "wait",
+    "json_patch",
+    "api_calls",
+    "service_config",
+    "summary",
+];
+
+fn default_critical() -> bool {
+    true
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+/// Service attendu par une étape `wait` / listé par `summary`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceCheck {
+    pub name: String,
+    pub port: u16,
+    #[serde(default = "default_path")]
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonPatch {
+    /// JSON pointer ("/arrs/0/token")
+    pub path: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiCall {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+/// Action d'une étape (champ "type", "commands" si absent)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepAction {
+    Builtin {
+        phase: InstallStep,
+    },
+    Commands {
+        commands: Vec<String>,
+        #[serde(default)]
+        outputs: Vec<String>,
+    },
+    File {
+        path: String,
+        content: String,
+        #[serde(default)]
+        mode: Option<String>,
+    },
+    Template {
+        template: String,
+        output: String,
+    },
+    Wait {
+        services: Vec<ServiceCheck>,
+    },
+    JsonPatch {
+        file: String,
+        patches: Vec<JsonPatch>,
+        #[serde(default, rename = "postCommands")]
+        post_commands: Vec<String>,
+    },
+    ApiCalls {
+        #[serde(rename = "baseUrl")]
+        base_url: String,
+        #[serde(default, rename = "apiKeyVar")]
+        api_key_var: Option<String>,
+        calls: Vec<ApiCall>,
+    },
+    ServiceConfig {
+        service: String,
+    },
+    Summary {
+        services: Vec<ServiceCheck>,
+    },
+}
+
+/// Étape de procédure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcedureStep {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub condition: Option<String>,
+    /// Une étape critique en échec interrompt l'installation
+    #[serde(default = "default_critical")]
+    pub critical: bool,
+    /// Secondes
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(flatten)]
+    pub action: StepAction,
+}
+
+impl ProcedureStep {
+    fn builtin_phase(&self) -> Option<InstallStep> {
+        match self.action {
+            StepAction::Builtin { phase } => Some(phase),
+            _ => None,
+        }
+    }
+
+    fn ssh_options(&self) -> ssh::SshOptions {
+        ssh::SshOptions { command_timeout_secs: self.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS), ..ssh::default_options() }
+    }
+}
+
+/// steps.json (les clés "stack" et "addons" sont lues par `compose` et `addons`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Procedure {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+    /// Version minimale de JellySetup ("1.1.0")
+    #[serde(default, rename = "minInstallerVersion")]
+    pub min_installer_version: Option<String>,
+    pub version: String,
+    pub name: String,
+    /// Type de master_config lu par les étapes `service_config` (défaut: config active)
+    #[serde(default, rename = "configType")]
+    pub config_type: Option<String>,
+    pub steps: Vec<ProcedureStep>,
+    /// Hooks pre/post des phases builtin (exécutés avant ceux de la master_config)
+    #[serde(default)]
+    pub hooks: Vec<crate::hooks::StepHook>,
+}
+
+/// "v1.2" -> [1, 2] (suffixes "-beta" ignorés)
+fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// `current` >= `minimum` (composantes manquantes = 0)
+pub fn version_at_least(current: &str, minimum: &str) -> bool {
+    let (mut current, mut minimum) = (version_numbers(current), version_numbers(minimum));
+    let len = current.len().max(minimum.len());
+    current.resize(len, 0);
+    minimum.resize(len, 0);
+    current >= minimum
+}
+
+/// schemaVersion et minInstallerVersion, vérifiés avant la lecture des étapes
+fn check_compatibility(value: &Value, installer_version: &str) -> Result<()> {
+    let schema = value
+        .get("schemaVersion")
+        .ok_or_else(|| anyhow!("steps.json sans champ schemaVersion"))?
+        .as_u64()
+        .ok_or_else(|| anyhow!("schemaVersion doit être un entier"))?;
+    if schema == 0 || schema > u64::from(SCHEMA_VERSION) {
+        return Err(anyhow!(
+            "Procédure au format {} non supporté (format {} attendu): mettez à jour JellySetup",
+            schema,
+            SCHEMA_VERSION
+        ));
+    }
+    if let Some(minimum) = value.get("minInstallerVersion").and_then(Value::as_str) {
+        if !version_at_least(installer_version, minimum) {
+            return Err(anyhow!(
+                "Cette procédure nécessite JellySetup {} ou plus récent (version installée: {})",
+                minimum,
+                installer_version
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Variables {{VAR}} référencées dans une valeur JSON
+fn referenced_vars(value: &Value, found: &mut Vec<String>) {
+    match value {
+        Value::String(text) => {
+            let re = Regex::new(r"\{\{([A-Z_0-9]+)\}\}").unwrap();
+            for caps in re.captures_iter(text) {
+                if !found.contains(&caps[1].to_string()) {
+                    found.push(caps[1].to_string());
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| referenced_vars(v, found)),
+        Value::Object(map) => map.values().for_each(|v| referenced_vars(v, found)),
+        _ => {}
+    }
+}
+
+impl Procedure {
+    /// Analyse et valide un steps.json (étapes sans "type": commandes)
+    pub fn parse(json: &str) -> Result<Self> {
+        Self::parse_for(json, env!("CARGO_PKG_VERSION"))
+    }
+
+    fn parse_for(json: &str, installer_version: &str) -> Result<Self> {
+        let mut value: Value = serde_json::from_str(json).map_err(|e| anyhow!("steps.json n'est pas un JSON valide: {}", e))?;
+        check_compatibility(&value, installer_version)?;
+
+        let steps = value
+            .get_mut("steps")
+            .and_then(|s| s.as_array_mut())
+            .ok_or_else(|| anyhow!("steps.json sans liste \"steps\""))?;
+        for (index, step) in steps.iter_mut().enumerate() {
+            let object = step.as_object_mut().ok_or_else(|| anyhow!("Étape n°{}: objet attendu", index + 1))?;
+            let id = object.get("id").and_then(Value::as_str).map(String::from).unwrap_or_else(|| format!("n°{}", index + 1));
+            let action = object.entry("type").or_insert_with(|| Value::from("commands"));
+            if !action.as_str().is_some_and(|a| ACTIONS.contains(&a)) {
+                return Err(anyhow!("Étape {}: action {} inconnue (attendu: {})", id, action, ACTIONS.join(", ")));
+            }
+            serde_json::from_value::<ProcedureStep>(step.clone()).map_err(|e| anyhow!("Étape {}: {}", id, e))?;
+        }
+
+        let procedure: Self = serde_json::from_value(value)?;
+        procedure.check_structure()?;
+        Ok(procedure)
+    }
+
+    /// Identifiants uniques, phases builtin toutes présentes et dans l'ordre d'InstallStep
+    fn check_structure(&self) -> Result<()> {
+        let mut ids: Vec<&str> = Vec::new();
+        for step in &self.steps {
+            if ids.contains(&step.id.as_str()) {
+                return Err(anyhow!("Étape {} définie deux fois", step.id));
+            }
+            ids.push(&step.id);
+        }
+        let phases: Vec<InstallStep> = self.steps.iter().filter_map(ProcedureStep::builtin_phase).collect();
+        if phases != InstallStep::ALL {
+            let missing: Vec<String> = InstallStep::ALL
+                .iter()
+                .filter(|p| !phases.contains(p))
+                .map(|p| format!("{:?}", p))
+                .collect();
+            return Err(if missing.is_empty() {
+                anyhow!("Phases builtin en double ou dans le désordre")
+            } else {
+                anyhow!("Phases builtin absentes: {}", missing.join(", "))
+            });
+        }
+        Ok(())
+    }
+
+    /// Variables {{VAR}} non définies au lancement ni capturées par une étape précédente
+    pub fn missing_variables(&self, vars: &TemplateVars) -> Vec<(String, String)> {
+        let mut produced: Vec<String> = Vec::new();
+        let mut missing = Vec::new();
+        for step in &self.steps {
+            let mut used = Vec::new();
+            referenced_vars(&serde_json::to_value(&step.action).unwrap_or_default(), &mut used);
+            if let StepAction::ApiCalls { api_key_var: Some(var), .. } = &step.action {
+                used.push(var.clone());
+            }
+            for var in used {
+                if vars.get(&var).is_none() && !produced.contains(&var) {
+                    missing.push((step.id.clone(), var));
+                }
+            }
+            if let StepAction::Commands { outputs, .. } = &step.action {
+                produced.extend(outputs.iter().cloned());
+            }
+        }
+        missing
+    }
+
+    /// Validation avant exécution (variables disponibles pour cette installation)
+    pub fn validate(&self, vars: &TemplateVars) -> Result<()> {
+        let missing = self.missing_variables(vars);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = missing.iter().map(|(step, var)| format!("{} ({})", var, step)).collect();
+        Err(anyhow!("Procédure {}: variables non définies: {}", self.version, details.join(", ")))
+    }
+
+    /// Templates (procedures/<version>/templates) utilisés par les étapes
+    pub fn templates(&self) -> Vec<String> {
+        let mut templates: Vec<String> = Vec::new();
+        for step in &self.steps {
+            if let StepAction::Template { template, .. } = &step.action {
+                if !templates.contains(template) {
+                    templates.push(template.clone());
+                }
+            }
+        }
+        templates
+    }
+
+    /// Procédure embarquée dans le binaire
+    pub fn embedded() -> Self {
+        Self::parse(crate::compose::EMBEDDED_PROCEDURE).expect("procedures/v2/steps.json embarqué invalide")
+    }
+
+    /// Reprise: procédure épinglée au démarrage de l'installation, sinon `resolve`
+    pub async fn resolve_for(host: &str, resume: bool) -> Self {
+        if let Some(procedure) = load_progress(host).and_then(|p| p.procedure).filter(|_| resume) {
+            tracing::info!("Using pinned {} {} (resumed installation)", procedure.name, procedure.version);
+            return procedure;
+        }
+        Self::resolve().await
+    }
+
+    /// Procédure publiée sur GitHub (bundle en mode hors ligne), sinon la copie embarquée
+    pub async fn resolve() -> Self {
+        if crate::offline_bundle::active() {
+            return match crate::offline_bundle::procedure() {
+                Ok(procedure) => {
+                    tracing::info!("Using offline bundle {} {}", procedure.name, procedure.version);
+                    procedure
+                }
+                Err(e) => {
+                    tracing::warn!("⚠️ Offline bundle procedure unavailable ({}), using embedded copy", e);
+                    Self::embedded()
+                }
+            };
+        }
+        match fetch_remote(PROCEDURE_VERSION).await {
+            Ok(procedure) => {
+                tracing::info!("Using {} {} ({} steps)", procedure.name, procedure.version, procedure.steps.len());
+                procedure
+            }
+            Err(e) => {
+                tracing::warn!("⚠️ Remote procedure unavailable ({}), using embedded copy", e);
+                Self::embedded()
+            }
+        }
+    }
+}
+
+async fn fetch_remote(version: &str) -> Result<Procedure> {
+    Procedure::parse(&fetch_remote_json(version).await?)
+}
+
+/// steps.json publié, tel quel (procédure + définition du stack)
+pub(crate) async fn fetch_remote_json(version: &str) -> Result<String> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .get(crate::config::settings().procedure_url(version))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?)
+}
+
+/// Contenu d'un template de procédure (publié, ou copie du bundle hors ligne)
+pub(crate) async fn fetch_template(template: &str) -> Result<String> {
+    if crate::offline_bundle::active() {
+        return crate::offline_bundle::template(template);
+    }
+    let url = format!("{}/{}/templates/{}", crate::config::settings().procedures_base_url, PROCEDURE_VERSION, template);
+    Ok(reqwest::get(&url).await?.error_for_status()?.text().await?)
+}
+
+/// Une condition d'étape est-elle remplie ? ("VAR", "!VAR", "VAR==valeur", noms insensibles à la casse)
+pub fn condition_met(condition: &str, vars: &TemplateVars) -> bool {
+    let value = |name: &str| vars.get(&name.trim().to_uppercase()).unwrap_or_default().to_string();
+    let condition = condition.trim();
+    if let Some((name, expected)) = condition.split_once("==") {
+        return value(name) == expected.trim();
+    }
+    match condition.strip_prefix('!') {
+        Some(name) => value(name).is_empty(),
+        None => !value(condition).is_empty(),
+    }
+}
+
+/// Remplace {{VAR}} puis ${VAR} (variables connues uniquement)
+pub fn expand(text: &str, vars: &TemplateVars) -> String {
+    expand_with(text, vars, |_, value| value.to_string())
+}
+
+/// Variables qui sont des fragments de commande, insérées telles quelles dans le shell
+const SHELL_FRAGMENT_VARS: &[&str] = &["SUDO"];
+
+/// Comme `expand`, pour une commande shell: chaque valeur est insérée entre quotes
+pub fn expand_quoted(text: &str, vars: &TemplateVars) -> String {
+    expand_with(text, vars, |name, value| {
+        if SHELL_FRAGMENT_VARS.contains(&name) {
+            value.to_string()
+        } else {
+            ssh::shell_quote(value)
+        }
+    })
+}
+
+fn expand_with(text: &str, vars: &TemplateVars, render: impl Fn(&str, &str) -> String) -> String {
+    let re = Regex::new(r"\{\{([A-Z_0-9]+)\}\}|\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+    re.replace_all(text, |caps: &regex::Captures| {
+        let name = caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str()).unwrap_or_default();
+        match vars.get(name) {
+            Some(value) => render(name, value),
+            // {{VAR}} inconnue: vide (comme le template engine); ${VAR}: variable shell
+            None if caps.get(1).is_some() => String::new(),
+            None => caps[0].to_string(),
+        }
+    })
+    .to_string()
+}
+
+/// Chemin distant inséré dans une commande: `~/` reste développé par le shell
+pub fn quote_path(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("\"$HOME\"/{}", ssh::shell_quote(rest)),
+        None if path == "~" => "\"$HOME\"".to_string(),
+        None => ssh::shell_quote(path),
+    }
+}
+
+/// Écriture d'un fichier distant par heredoc (délimiteur absent du contenu)
+fn write_file_command(path: &str, content: &str) -> String {
+    let mut delimiter = "JELLYSETUP_FILE".to_string();
+    while content.lines().any(|line| line == delimiter) {
+        delimiter.push('_');
+    }
+    format!(
+        "mkdir -p \"$(dirname {path})\" && cat > {path} <<'{delimiter}'\n{content}\n{delimiter}",
+        path = path,
+        content = content,
+        delimiter = delimiter
+    )
+}
+
+fn expand_json(value: &Value, vars: &TemplateVars) -> Value {
+    match value {
+        Value::String(s) => Value::String(expand(s, vars)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| expand_json(v, vars)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), expand_json(v, vars))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Script exécuté pour une étape `commands`: bash -e, variables capturées puis code de sortie
+pub fn commands_script(commands: &[String], outputs: &[String]) -> String {
+    let echo_outputs: String = outputs
+        .iter()
+        .map(|name| format!("echo \"{}{}=${}\"\n", OUTPUT_MARKER, name, name))
+        .collect();
+    format!(
+        "bash -es <<'JELLYSETUP_STEP' 2>&1\n{}\n{}JELLYSETUP_STEP\necho \"{}$?\"",
+        commands.join("\n"),
+        echo_outputs,
+        EXIT_MARKER
+    )
+}
+
+/// Sortie d'une étape `commands`: (texte, variables capturées, code de sortie)
+pub fn parse_commands_output(output: &str) -> (String, Vec<(String, String)>, Option<i32>) {
+    let (body, exit_code) = match output.rfind(EXIT_MARKER) {
+        Some(pos) => (&output[..pos], output[pos + EXIT_MARKER.len()..].trim().parse().ok()),
+        None => (output, None),
+    };
+    let mut text = Vec::new();
+    let mut captured = Vec::new();
+    for line in body.lines() {
+        match line.strip_prefix(OUTPUT_MARKER).and_then(|l| l.split_once('=')) {
+            Some((name, value)) => captured.push((name.to_string(), value.trim().to_string())),
+            None => text.push(line),
+        }
+    }
+    (text.join("\n").trim_end().to_string(), captured, exit_code)
+}
+
+/// Applique des remplacements JSON pointer (le parent doit exister)
+pub fn apply_json_patches(document: &mut Value, patches: &[JsonPatch]) -> Result<()> {
+    for patch in patches {
+        let target = document
+            .pointer_mut(&patch.path)
+            .ok_or_else(|| anyhow!("Chemin {} absent du document", patch.path))?;
+        *target = patch.value.clone();
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Plan d'installation (aperçu sans exécution)
+// =============================================================================
+
+/// Variables dont la valeur est masquée dans un plan (en plus des valeurs enregistrées dans `redact`)
+const SECRET_MARKERS: [&str; 5] = ["PASSWORD", "PASSKEY", "TOKEN", "SECRET", "API_KEY"];
+const MASK: &str = "••••••";
+
+/// Action concrète d'une étape, telle qu'elle serait exécutée
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct PlannedAction {
+    pub step_id: String,
+    pub name: String,
+    /// Type de l'étape ("builtin", "commands", ...)
+    pub kind: String,
+    pub critical: bool,
+    /// Condition non remplie: l'étape sera sautée
+    pub skipped: bool,
+    /// Commandes, fichiers, appels (secrets masqués)
+    pub details: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct InstallationPlan {
+    pub procedure_name: String,
+    pub procedure_version: String,
+    pub actions: Vec<PlannedAction>,
+    /// "VAR (étape)": variables {{VAR}} non définies
+    pub missing_variables: Vec<String>,
+}
+
+fn is_secret(name: &str) -> bool {
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+impl Procedure {
+    /// Variables d'affichage: secrets masqués, valeurs connues seulement pendant l'installation en <VAR>
+    fn display_vars(&self, vars: &TemplateVars) -> TemplateVars {
+        let mut display = TemplateVars::new();
+        for name in vars.names() {
+            let value = vars.get(name).unwrap_or_default();
+            let shown = if value.is_empty() {
+                format!("<{}>", name)
+            } else if is_secret(name) || crate::redact::redact(value) != value {
+                MASK.to_string()
+            } else {
+                value.to_string()
+            };
+            display.set(name, &shown);
+        }
+        let pending = self
+            .steps
+            .iter()
+            .flat_map(|step| match &step.action {
+                StepAction::Commands { outputs, .. } => outputs.clone(),
+                _ => Vec::new(),
+            })
+            .chain(self.missing_variables(vars).into_iter().map(|(_, var)| var));
+        for name in pending {
+            if display.get(&name).is_none() {
+                display.set(&name, &format!("<{}>", name));
+            }
+        }
+        display
+    }
+
+    /// Actions concrètes de la procédure, dans l'ordre, sans rien exécuter
+    pub fn plan(&self, vars: &TemplateVars) -> InstallationPlan {
+        let display = self.display_vars(vars);
+        let actions = self
+            .steps
+            .iter()
+            .map(|step| {
+                let kind = serde_json::to_value(&step.action)
+                    .ok()
+                    .and_then(|v| v["type"].as_str().map(String::from))
+                    .unwrap_or_default();
+                PlannedAction {
+                    step_id: step.id.clone(),
+                    name: step.name.clone(),
+                    kind,
+                    critical: step.critical,
+                    skipped: step.condition.as_deref().is_some_and(|c| !condition_met(c, vars)),
+                    details: action_details(&step.action, &display),
+                }
+            })
+            .collect();
+        InstallationPlan {
+            procedure_name: self.name.clone(),
+            procedure_version: self.version.clone(),
+            actions,
+            missing_variables: self
+                .missing_variables(vars)
+                .into_iter()
+                .map(|(step, var)| format!("{} ({})", var, step))
+                .collect(),
+        }
+    }
+}
+
+/// Détail lisible d'une action (valeurs déjà masquées dans `display`)
+fn action_details(action: &StepAction, display: &TemplateVars) -> Vec<String> {
+    match action {
+        StepAction::Builtin { phase } => vec![format!("Phase intégrée: {}", phase.label())],
+        StepAction::Commands { commands, outputs } => {
+            let mut details: Vec<String> = commands.iter().map(|c| expand(c, display)).collect();
+            if !outputs.is_empty() {
+                details.push(format!("Variables capturées: {}", outputs.join(", ")));
+            }
+            details
+        }
+        StepAction::File { path, content, mode } => {
+            let mode = mode.as_ref().map(|m| format!(" (mode {})", m)).unwrap_or_default();
+            vec![format!("Écrire {}{}:\n{}", expand(path, display), mode, expand(content, display))]
+        }
+        StepAction::Template { template, output } => {
+            vec![format!("Rendre le modèle {} vers {}", template, expand(output, display))]
+        }
+        StepAction::Wait { services } => services
+            .iter()
+            .map(|s| format!("Attendre {} (http://localhost:{}{})", s.name, s.port, s.path))
+            .collect(),
+        StepAction::JsonPatch { file, patches, post_commands } => patches
+            .iter()
+            .map(|p| format!("{}: {} = {}", expand(file, display), p.path, expand_json(&p.value, display)))
+            .chain(post_commands.iter().map(|c| expand(c, display)))
+            .collect(),
+        StepAction::ApiCalls { base_url, api_key_var, calls } => {
+            let mut details: Vec<String> = api_key_var.iter().map(|_| format!("En-tête X-Api-Key: {}", MASK)).collect();
+            for call in calls {
+                let mut line = format!("{} {}{}", call.method, expand(base_url, display), expand(&call.path, display));
+                if let Some(body) = &call.body {
+                    line.push_str(&format!(" {}", expand_json(body, display)));
+                }
+                details.push(line);
+            }
+            details
+        }
+        StepAction::ServiceConfig { service } => vec![format!("Configurer {} (master_config)", service)],
+        StepAction::Summary { services } => {
+            vec![format!("Récapitulatif: {}", services.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(", "))]
+        }
+    }
+}
+
+/// Étapes terminées d'une procédure (reprise après échec)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProcedureProgress {
+    version: String,
+    completed: Vec<String>,
+    /// Procédure épinglée: une reprise rejoue exactement la même séquence
+    #[serde(default)]
+    procedure: Option<Procedure>,
+}
+
+fn progress_path(host: &str) -> Option<PathBuf> {
+    let file_name: String = host
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    dirs::config_dir().map(|d| d.join("jellysetup").join("procedures").join(format!("{}.json", file_name)))
+}
+
+fn load_progress(host: &str) -> Option<ProcedureProgress> {
+    let content = std::fs::read_to_string(progress_path(host)?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Progression locale (point de reprise publié pour un autre ordinateur)
+pub(crate) fn progress_snapshot(host: &str) -> Option<Value> {
+    load_progress(host).and_then(|progress| serde_json::to_value(progress).ok())
+}
+
+/// Restaure la progression d'une installation reprise depuis un autre ordinateur
+pub(crate) fn restore_progress(host: &str, snapshot: &Value) -> Result<()> {
+    let progress: ProcedureProgress = serde_json::from_value(snapshot.clone())?;
+    let path = progress_path(host).ok_or_else(|| anyhow!("Cannot determine config directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&progress)?)?;
+    Ok(())
+}
+
+/// Exécute les étapes d'une procédure autour des phases Rust de l'installation
+pub struct ProcedureRunner<'a> {
+    procedure: Procedure,
+    target: SshTarget<'a>,
+    host: String,
+    pub vars: TemplateVars,
+    logger: Option<&'a InstallationLogger>,
+    progress: ProcedureProgress,
+    /// Prochaine étape à examiner
+    cursor: usize,
+}
+
+impl<'a> ProcedureRunner<'a> {
+    /// `resume`: les étapes déjà terminées sont sautées (sauf celles qui capturent des variables).
+    /// Échoue si la procédure référence des variables inconnues.
+    pub fn new(procedure: Procedure, target: SshTarget<'a>, host: &str, vars: TemplateVars, logger: Option<&'a InstallationLogger>, resume: bool) -> Result<Self> {
+        procedure.validate(&vars)?;
+        let mut progress = load_progress(host)
+            .filter(|progress| resume && progress.version == procedure.version)
+            .unwrap_or_else(|| ProcedureProgress { version: procedure.version.clone(), ..Default::default() });
+        progress.procedure = Some(procedure.clone());
+        let runner = Self { procedure, target, host: host.to_string(), vars, logger, progress, cursor: 0 };
+        runner.save_progress();
+        Ok(runner)
+    }
+
+    fn save_progress(&self) {
+        let Some(path) = progress_path(&self.host) else { return };
+        let saved = path
+            .parent()
+            .map(std::fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(&self.progress).unwrap_or_default()));
+        if let Err(e) = saved {
+            tracing::warn!("⚠️ Could not save progress: {}", e);
+        }
+    }
+
+    /// Exécute les étapes placées avant la phase `phase`
+    pub async fn run_until(&mut self, phase: InstallStep) -> Result<()> {
+        let order = |p: InstallStep| InstallStep::ALL.iter().position(|s| *s == p);
+        while let Some(step) = self.procedure.steps.get(self.cursor).cloned() {
+            match step.builtin_phase() {
+                Some(builtin) if builtin == phase => {
+                    self.cursor += 1;
+                    return Ok(());
+                }
+                Some(builtin) if order(builtin) > order(phase) => return Ok(()),
+                Some(_) => {}
+                None => self.run_step(&step).await?,
+            }
+            self.cursor += 1;
+        }
+        Ok(())
+    }
+
+    /// Exécute les étapes restantes (après la dernière phase)
+    pub async fn finish(&mut self) -> Result<()> {
+        while let Some(step) = self.procedure.steps.get(self.cursor).cloned() {
+            if step.builtin_phase().is_none() {
+                self.run_step(&step).await?;
+            }
+            self.cursor += 1;
+        }
+        if let Some(path) = progress_path(&self.host) {
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    async fn run_step(&mut self, step: &ProcedureStep) -> Result<()> {
+        if let Some(condition) = &step.condition {
+            if !condition_met(condition, &self.vars) {
+                tracing::info!("⏭️ {} skipped (condition {} not met)", step.id, condition);
+                return Ok(());
+            }
+        }
+        let captures = matches!(&step.action, StepAction::Commands { outputs, .. } if !outputs.is_empty());
+        if self.progress.completed.contains(&step.id) && !captures {
+            tracing::info!("⏭️ {} already completed", step.id);
+            return Ok(());
+        }
+
+        tracing::info!("▶️ {}", step.name);
+        if let Some(logger) = self.logger {
+            logger.start_step(&step.id).await;
+        }
+        let mut result = self.execute(step).await;
+        for attempt in 1..=step.retries {
+            if result.is_ok() {
+                break;
+            }
+            tracing::info!("Retrying {} ({}/{})", step.id, attempt, step.retries);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            result = self.execute(step).await;
+        }
+        if let Some(logger) = self.logger {
+            logger.end_step(&step.id, result.is_ok()).await;
+        }
+
+        match result {
+            Ok(()) => {
+                tracing::info!("✅ {}", step.name);
+                if !self.progress.completed.contains(&step.id) {
+                    self.progress.completed.push(step.id.clone());
+                }
+                self.save_progress();
+                Ok(())
+            }
+            Err(e) => {
+                if let Some(logger) = self.logger {
+                    logger.log_error(&step.id, &e.to_string(), None).await;
+                }
+                if step.critical {
+                    tracing::error!("❌ {} failed: {}", step.name, e);
+                    Err(anyhow!("Étape {} en échec: {}", step.name, e))
+                } else {
+                    tracing::warn!("⚠️ {} failed (non critique): {}", step.name, e);
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    async fn execute(&mut self, step: &ProcedureStep) -> Result<()> {
+        match &step.action {
+            StepAction::Builtin { .. } => Ok(()),
+            StepAction::Commands { commands, outputs } => {
+                let commands: Vec<String> = commands.iter().map(|c| expand_quoted(c, &self.vars)).collect();
+                self.run_commands(step, &commands, outputs).await
+            }
+            StepAction::File { path, content, mode } => {
+                let path = quote_path(&expand(path, &self.vars));
+                let mut command = write_file_command(&path, &expand(content, &self.vars));
+                if let Some(mode) = mode {
+                    if mode.is_empty() || !mode.chars().all(|c| c.is_digit(8)) {
+                        return Err(anyhow!("Mode {} invalide", mode));
+                    }
+                    command.push_str(&format!("\nchmod {} {}", mode, path));
+                }
+                self.run_commands(step, &[command], &[]).await
+            }
+            StepAction::Template { template, output } => {
+                let content = expand(&fetch_template(template).await?, &self.vars);
+                let command = write_file_command(&quote_path(&expand(output, &self.vars)), &content);
+                self.run_commands(step, &[command], &[]).await
+            }
+            StepAction::Wait { services } => {
+                let deadline = std::time::Instant::now() + Duration::from_secs(step.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+                for service in services {
+                    let check = format!(
+                        "curl -s -o /dev/null -w '%{{http_code}}' 'http://localhost:{}{}' 2>/dev/null || echo 000",
+                        service.port, service.path
+                    );
+                    loop {
+                        let code = self.target.exec(&check).await.unwrap_or_default();
+                        if matches!(code.trim().chars().next(), Some('2' | '3')) || code.trim() == "401" {
+                            tracing::info!("{} is ready", service.name);
+                            break;
+                        }
+                        if std::time::Instant::now() >= deadline {
+                            return Err(anyhow!("{} ne répond pas", service.name));
+                        }
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+                Ok(())
+            }
+            StepAction::JsonPatch { file, patches, post_commands } => {
+                let path = quote_path(&expand(file, &self.vars));
+                let mut document: Value = serde_json::from_str(self.target.exec(&format!("cat {}", path)).await?.trim())
+                    .map_err(|e| anyhow!("{} n'est pas un JSON valide: {}", file, e))?;
+                let patches: Vec<JsonPatch> = patches
+                    .iter()
+                    .map(|p| JsonPatch { path: p.path.clone(), value: expand_json(&p.value, &self.vars) })
+                    .collect();
+                apply_json_patches(&mut document, &patches)?;
+                let mut commands = vec![write_file_command(&path, &serde_json::to_string_pretty(&document)?)];
+                commands.extend(post_commands.iter().map(|c| expand_quoted(c, &self.vars)));
+                self.run_commands(step, &commands, &[]).await
+            }
+            StepAction::ApiCalls { base_url, api_key_var, calls } => {
+                let mut headers = Vec::new();
+                if let Some(var) = api_key_var {
+                    let key = self.vars.get(var).ok_or_else(|| anyhow!("Variable {} non définie", var))?;
+                    headers.push(format!("X-Api-Key: {}", key));
+                }
+                for call in calls {
+                    let url = format!("{}{}", expand(base_url, &self.vars), expand(&call.path, &self.vars));
+                    let body = call.body.as_ref().map(|b| expand_json(b, &self.vars));
+                    let command = crate::services::local_curl(&call.method, &url, &headers, body.as_ref());
+                    let response = self.target.exec_with_options(&command, &step.ssh_options()).await?;
+                    if let Some(logger) = self.logger {
+                        logger.log_ssh(&step.id, &format!("{} {}", call.method, url), &response, 0).await;
+                    }
+                }
+                Ok(())
+            }
+            StepAction::ServiceConfig { service } => {
+                let master = crate::cloud::backend().fetch_master_config(self.procedure.config_type.as_deref())
+                    .await?
+                    .ok_or_else(|| anyhow!("Aucune master_config active"))?;
+                let config = master
+                    .service_config(service)
+                    .ok_or_else(|| anyhow!("Aucune configuration {} dans master_config", service))?;
+                let resolved = self.vars.replace_in_json(config);
+                crate::services::reconfigure(self.target, service, &resolved, &self.vars).await
+            }
+            StepAction::Summary { services } => {
+                let ip = self.vars.get("PI_IP").unwrap_or("localhost").to_string();
+                let lines: Vec<String> = services.iter().map(|s| format!("{}: http://{}:{}", s.name, ip, s.port)).collect();
+                tracing::info!("{}", lines.join(" | "));
+                if let Some(logger) = self.logger {
+                    logger.log_with_details(LogLevel::Info, &step.id, "Services installés", serde_json::json!(lines)).await;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn run_commands(&mut self, step: &ProcedureStep, commands: &[String], outputs: &[String]) -> Result<()> {
+        let script = commands_script(commands, outputs);
+        let output = self.target.exec_with_options(&script, &step.ssh_options()).await?;
+        let (text, captured, exit_code) = parse_commands_output(&output);
+        if let Some(logger) = self.logger {
+            logger.log_ssh(&step.id, &commands.join("\n"), &text, exit_code.unwrap_or(-1)).await;
+        }
+        for (name, value) in captured {
+            self.vars.set(&name, &value);
+        }
+        match exit_code {
+            Some(0) => Ok(()),
+            Some(code) => Err(anyhow!("code de sortie {}: {}", code, text.lines().last().unwrap_or_default())),
+            None => Err(anyhow!("code de sortie inconnu")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Procédure embarquée + étapes supplémentaires en tête
+    fn with_steps(extra: Value) -> String {
+        let mut value: Value = serde_json::from_str(crate::compose::EMBEDDED_PROCEDURE).unwrap();
+        let steps = value["steps"].as_array_mut().unwrap();
+        for (i, step) in extra.as_array().unwrap().iter().enumerate() {
+            steps.insert(i, step.clone());
+        }
+        value.to_string()
+    }
+
+    #[test]
+    fn test_installation_plan() {
+        crate::redact::register("plan-household-secret");
+        let mut vars = TemplateVars::new();
+        vars.set("PI_IP", "192.168.1.20");
+        vars.set("JELLYFIN_PASSWORD", "hunter22");
+        vars.set("HOUSEHOLD", "plan-household-secret");
+        vars.set("YGG_PASSKEY", "");
+        let procedure = Procedure::parse(&with_steps(serde_json::json!([
+            { "id": "login", "name": "Login", "commands": ["curl -u admin:{{JELLYFIN_PASSWORD}} http://{{PI_IP}}:8096 && T=$(cat /tmp/t)"], "outputs": ["T"] },
+            { "id": "use", "name": "Use", "condition": "YGG_PASSKEY", "type": "file", "path": "/tmp/ygg", "content": "{{T}} {{YGG_PASSKEY}}" },
+            { "id": "home", "name": "Home", "commands": ["echo {{HOUSEHOLD}}"] }
+        ])))
+        .unwrap();
+        let plan = procedure.plan(&vars);
+        assert_eq!(plan.actions.len(), procedure.steps.len());
+        assert_eq!(plan.actions[0].kind, "commands");
+        assert_eq!(plan.actions[0].details[0], "curl -u admin:•••••• http://192.168.1.20:8096 && T=$(cat /tmp/t)");
+        assert!(plan.actions[1].skipped);
+        assert_eq!(plan.actions[1].details[0], "Écrire /tmp/ygg:\n<T> <YGG_PASSKEY>");
+        assert_eq!(plan.actions[2].details[0], "echo ••••••");
+        assert_eq!(plan.actions[3].details[0], "Phase intégrée: Mise à jour système");
+        assert!(plan.missing_variables.is_empty());
+    }
+
+    #[test]
+    fn test_procedure_parsing_and_helpers() {
+        let procedure = Procedure::embedded();
+        let phases: Vec<InstallStep> = procedure.steps.iter().filter_map(|s| s.builtin_phase()).collect();
+        assert_eq!(phases, InstallStep::ALL.to_vec());
+
+        let procedure = Procedure::parse(&with_steps(serde_json::json!([
+            { "id": "legacy", "name": "Legacy", "commands": ["echo ok"], "outputs": ["TOKEN"] },
+            { "id": "ygg", "name": "YGG", "condition": "ygg_passkey", "critical": false, "type": "api_calls",
+              "baseUrl": "http://localhost:9696/api/v1", "apiKeyVar": "PROWLARR_API", "calls": [{ "path": "/indexer", "body": { "t": "{{TOKEN}}" } }] }
+        ])))
+        .unwrap();
+        assert!(matches!(&procedure.steps[0].action, StepAction::Commands { commands, .. } if commands == &["echo ok"]));
+        assert!(procedure.steps[0].critical);
+        assert!(matches!(&procedure.steps[1].action, StepAction::ApiCalls { calls, .. } if calls[0].method == "POST"));
+        assert_eq!(procedure.missing_variables(&TemplateVars::new()), vec![("ygg".to_string(), "PROWLARR_API".to_string())]);
+
+        let error = Procedure::parse(&with_steps(serde_json::json!([{ "id": "a", "name": "a", "type": "reboot" }]))).unwrap_err();
+        assert!(error.to_string().starts_with("Étape a: action \"reboot\" inconnue"));
+        let error = Procedure::parse(&with_steps(serde_json::json!([{ "id": "b", "name": "b", "type": "file", "path": "/tmp/x" }]))).unwrap_err();
+        assert!(error.to_string().contains("content"));
+        assert!(Procedure::parse(&with_steps(serde_json::json!([{ "id": "docker", "name": "x", "commands": [] }]))).is_err());
+        assert!(Procedure::parse(r#"{ "schemaVersion": 1, "version": "1", "name": "x", "steps": [] }"#).is_err());
+
+        let mut value: Value = serde_json::from_str(crate::compose::EMBEDDED_PROCEDURE).unwrap();
+        value["minInstallerVersion"] = "1.2".into();
+        assert!(Procedure::parse_for(&value.to_string(), "1.1.0").unwrap_err().to_string().contains("JellySetup 1.2"));
+        assert!(Procedure::parse_for(&value.to_string(), "1.2.0").is_ok());
+        value["schemaVersion"] = (SCHEMA_VERSION + 1).into();
+        assert!(Procedure::parse_for(&value.to_string(), "9.0.0").is_err());
+        assert!(version_at_least("v1.10.0", "1.9.3"));
+
+        assert!(!version_at_least("1.1.0-beta", "1.1.1"));
+
+        let mut vars = TemplateVars::new();
+        vars.set("RADARR_API", "abc");
+        vars.set("YGG_PASSKEY", "");
+        assert_eq!(expand("key=${RADARR_API} user=$USER home=${HOME} ip={{RADARR_API}}", &vars), "key=abc user=$USER home=${HOME} ip=abc");
+        vars.set("NAME", "it's; rm -rf ~");
+        vars.set("SUDO", "sudo -n");
+        assert_eq!(expand_quoted("{{SUDO}} echo ${NAME} {{NAME}} $HOME {{UNKNOWN}}", &vars), "sudo -n echo 'it'\\''s; rm -rf ~' 'it'\\''s; rm -rf ~' $HOME ");
+        assert_eq!(quote_path("~/media stack/a.json"), "\"$HOME\"/'media stack/a.json'");
+        assert_eq!(quote_path("/tmp/$(x)"), "'/tmp/$(x)'");
+        assert!(write_file_command("'/tmp/a'", "x\nJELLYSETUP_FILE\ny").ends_with("\nJELLYSETUP_FILE_"));
+        assert!(condition_met("radarr_api", &vars));
+        assert!(!condition_met("ygg_passkey", &vars));
+        assert!(condition_met("!ygg_passkey", &vars));
+        assert!(condition_met("RADARR_API==abc", &vars));
+
+        let (text, captured, code) = parse_commands_output("ligne 1\n__STEP_OUTPUT_RADARR_API=abc\nligne 2\n__STEP_EXIT=0\n");
+        assert_eq!(text, "ligne 1\nligne 2");
+        assert_eq!(captured, vec![("RADARR_API".to_string(), "abc".to_string())]);
+        assert_eq!(code, Some(0));
+        assert!(commands_script(&["A=$(echo x)".to_string()], &["A".to_string()]).contains("echo \"__STEP_OUTPUT_A=$A\""));
+
+        let mut document = serde_json::json!({ "arrs": [{ "token": "" }] });
+        apply_json_patches(&mut document, &[JsonPatch { path: "/arrs/0/token".into(), value: "abc".into() }]).unwrap();
+        assert_eq!(document["arrs"][0]["token"], "abc");
+        assert!(apply_json_patches(&mut document, &[JsonPatch { path: "/arrs/3/token".into(), value: "x".into() }]).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+// =============================================================================
+// Enregistrement d'une installation dans Supabase (payload versionné)
+// =============================================================================
+//
+// v1 (ancien `save_to_supabase`): arguments camelCase à plat, clés SSH obligatoires
+//     (chaîne vide = absente), pas de méthode d'authentification.
+// v2: `InstallationRegistration`, commun aux flows clé privée et mot de passe.
+// Un payload sans champ `version` est traité comme v1 et migré.
+
+pub const REGISTRATION_VERSION: u32 = 2;
+
+/// Authentification SSH utilisée par l'installation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "method", rename_all = "lowercase")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum RegistrationAuth {
+    Key {
+        ssh_public_key: Option<String>,
+        ssh_private_key_encrypted: Option<String>,
+    },
+    Password,
+}
+
+/// Payload de `register_installation` (version courante)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct InstallationRegistration {
+    pub version: u32,
+    pub pi_name: String,
+    pub pi_ip: String,
+    pub auth: RegistrationAuth,
+    #[serde(default)]
+    pub ssh_host_fingerprint: Option<String>,
+    pub installer_version: String,
+}
+
+impl InstallationRegistration {
+    pub fn new(pi_name: &str, pi_ip: &str, auth: RegistrationAuth, ssh_host_fingerprint: Option<&str>) -> Self {
+        Self {
+            version: REGISTRATION_VERSION,
+            pi_name: pi_name.to_string(),
+            pi_ip: pi_ip.to_string(),
+            auth,
+            ssh_host_fingerprint: ssh_host_fingerprint.map(String::from),
+            installer_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// "key" ou "password"
+    pub fn auth_method(&self) -> &'static str {
+        match self.auth {
+            RegistrationAuth::Key { .. } => "key",
+            RegistrationAuth::Password => "password",
+        }
+    }
+
+    pub fn ssh_public_key(&self) -> Option<&str> {
+        match &self.auth {
+            RegistrationAuth::Key { ssh_public_key, .. } => ssh_public_key.as_deref(),
+            RegistrationAuth::Password => None,
+        }
+    }
+
+    pub fn ssh_private_key_encrypted(&self) -> Option<&str> {
+        match &self.auth {
+            RegistrationAuth::Key { ssh_private_key_encrypted, .. } => ssh_private_key_encrypted.as_deref(),
+            RegistrationAuth::Password => None,
+        }
+    }
+}
+
+/// Arguments de l'ancien `save_to_supabase`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyRegistration {
+    pi_name: String,
+    pi_ip: String,
+    #[serde(default)]
+    ssh_public_key: String,
+    #[serde(default)]
+    ssh_private_key_encrypted: String,
+    #[serde(default)]
+    ssh_host_fingerprint: Option<String>,
+    installer_version: String,
+}
+
+impl From<LegacyRegistration> for InstallationRegistration {
+    fn from(legacy: LegacyRegistration) -> Self {
+        let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+        let public_key = non_empty(legacy.ssh_public_key);
+        let private_key = non_empty(legacy.ssh_private_key_encrypted);
+        // v1 ne distinguait pas les flows: sans aucune clé, c'était une installation par mot de passe
+        let auth = if public_key.is_none() && private_key.is_none() {
+            RegistrationAuth::Password
+        } else {
+            RegistrationAuth::Key { ssh_public_key: public_key, ssh_private_key_encrypted: private_key }
+        };
+
+        Self {
+            version: REGISTRATION_VERSION,
+            pi_name: legacy.pi_name,
+            pi_ip: legacy.pi_ip,
+            auth,
+            ssh_host_fingerprint: legacy.ssh_host_fingerprint,
+            installer_version: legacy.installer_version,
+        }
+    }
+}
+
+/// Lit un payload de n'importe quelle version et le migre vers la version courante
+pub fn parse_payload(payload: Value) -> Result<InstallationRegistration> {
+    match payload.get("version").and_then(|v| v.as_u64()) {
+        None | Some(1) => {
+            let legacy: LegacyRegistration = serde_json::from_value(payload)?;
+            tracing::info!("Migrating v1 payload for {}", legacy.pi_name);
+            Ok(legacy.into())
+        }
+        Some(2) => Ok(serde_json::from_value(payload)?),
+        Some(other) => Err(anyhow!("Version de payload non supportée: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_payload() {
+        let legacy = parse_payload(json!({
+            "piName": "pi-salon",
+            "piIp": "192.168.1.20",
+            "sshPublicKey": "ssh-ed25519 AAAA",
+            "sshPrivateKeyEncrypted": "",
+            "installerVersion": "1.0.0"
+        }))
+        .unwrap();
+        assert_eq!(legacy.version, REGISTRATION_VERSION);
+        assert_eq!(legacy.auth_method(), "key");
+        assert_eq!(legacy.ssh_public_key(), Some("ssh-ed25519 AAAA"));
+        assert_eq!(legacy.ssh_private_key_encrypted(), None);
+
+        let no_keys = parse_payload(json!({
+            "version": 1, "piName": "pi", "piIp": "10.0.0.2", "installerVersion": "1.0.0"
+        }))
+        .unwrap();
+        assert_eq!(no_keys.auth, RegistrationAuth::Password);
+
+        let current = InstallationRegistration::new("pi", "10.0.0.2", RegistrationAuth::Password, Some("SHA256:abc"));
+        assert_eq!(parse_payload(serde_json::to_value(&current).unwrap()).unwrap(), current);
+
+        assert!(parse_payload(json!({ "version": 9 })).is_err());
+    }
+}
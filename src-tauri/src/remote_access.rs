@@ -0,0 +1,394 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use ts_rs::TS;
+
+use crate::cloudflare::CloudflareSetup;
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Surveillance et réparation de l'accès distant (tunnel Cloudflare)
+// =============================================================================
+//
+// L'accès distant casse sans bruit: token du tunnel régénéré dans le dashboard,
+// cloudflared arrêté, connexion perdue après une coupure. Le diagnostic combine
+// les routes publiques (réponses 52x/530 = tunnel absent) et les logs de
+// cloudflared. Réparation par paliers:
+// 1. redémarrage de cloudflared
+// 2. avec un token API: tunnel, routes DNS et token ré-émis (cloudflare::provision)
+// 3. sinon l'utilisateur est guidé pour coller un nouveau token
+// Un agent sur le Pi fait les mêmes vérifications toutes les 10 minutes.
+
+const COMPOSE_DIR: &str = "~/media-stack";
+const AGENT_DIR: &str = "~/jellysetup-agent";
+const CRON_MARKER: &str = "# jellysetup-remote-access";
+/// Laisse cloudflared rétablir ses connexions avant de revérifier
+const RECONNECT_DELAY: Duration = Duration::from_secs(20);
+
+/// État du conteneur cloudflared déduit de ses logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum TunnelState {
+    Connected,
+    /// Token refusé par Cloudflare (régénéré ou tunnel supprimé)
+    TokenRejected,
+    Disconnected,
+    NotRunning,
+    Unknown,
+}
+
+/// Réponse d'une route publique
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct RouteCheck {
+    pub hostname: String,
+    pub reachable: bool,
+    /// Code HTTP (None: pas de réponse)
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct RemoteAccessReport {
+    pub routes: Vec<RouteCheck>,
+    pub tunnel: TunnelState,
+    /// Réparation automatique effectuée avec succès
+    pub repaired: bool,
+    /// Étapes tentées ("cloudflared redémarré", ...)
+    pub actions: Vec<String>,
+    /// Nouveau token à coller depuis le dashboard Cloudflare
+    pub needs_new_token: bool,
+    pub message: String,
+}
+
+impl RemoteAccessReport {
+    fn reachable(&self) -> bool {
+        if self.routes.is_empty() {
+            self.tunnel == TunnelState::Connected
+        } else {
+            self.routes.iter().all(|r| r.reachable)
+        }
+    }
+}
+
+/// Dernier événement significatif des logs cloudflared
+pub fn classify_logs(logs: &str, running: bool) -> TunnelState {
+    if !running {
+        return TunnelState::NotRunning;
+    }
+    let mut state = TunnelState::Unknown;
+    for line in logs.lines() {
+        let line = line.to_lowercase();
+        if line.contains("registered tunnel connection") || line.contains("connection registered") {
+            state = TunnelState::Connected;
+        } else if line.contains("unauthorized")
+            || line.contains("invalid tunnel secret")
+            || line.contains("token is not valid")
+            || line.contains("tunnel not found")
+        {
+            state = TunnelState::TokenRejected;
+        } else if line.contains("failed to connect")
+            || line.contains("connection terminated")
+            || line.contains("no more connections active")
+            || line.contains("serve tunnel error")
+        {
+            state = TunnelState::Disconnected;
+        }
+    }
+    state
+}
+
+/// Remplace TUNNEL_TOKEN dans docker-compose.yml (None: variable absente)
+pub fn replace_tunnel_token(compose: &str, token: &str) -> Option<String> {
+    let mut found = false;
+    let lines: Vec<String> = compose
+        .lines()
+        .map(|line| match line.find("TUNNEL_TOKEN=") {
+            Some(start) => {
+                found = true;
+                let value_start = start + "TUNNEL_TOKEN=".len();
+                let quote = line[value_start..]
+                    .chars()
+                    .rev()
+                    .take_while(|c| *c == '"' || *c == '\'')
+                    .collect::<String>();
+                format!("{}{}{}", &line[..value_start], token, quote)
+            }
+            None => line.to_string(),
+        })
+        .collect();
+    found.then(|| lines.join("\n") + if compose.ends_with('\n') { "\n" } else { "" })
+}
+
+/// Token de tunnel plausible (base64 d'un JSON, collé depuis le dashboard)
+fn validate_token(token: &str) -> Result<()> {
+    let valid = token.len() >= 32
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_'));
+    if !valid {
+        return Err(anyhow!("Token de tunnel invalide: copiez la valeur complète affichée par Cloudflare"));
+    }
+    Ok(())
+}
+
+async fn probe(hostname: &str) -> RouteCheck {
+    let client = match reqwest::Client::builder().timeout(Duration::from_secs(15)).build() {
+        Ok(client) => client,
+        Err(e) => {
+            return RouteCheck { hostname: hostname.to_string(), reachable: false, status: None, message: e.to_string() }
+        }
+    };
+    match client.get(format!("https://{}/", hostname)).send().await {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let reachable = !(520..=530).contains(&status);
+            let message = if reachable {
+                format!("HTTP {}", status)
+            } else {
+                format!("HTTP {} (tunnel non connecté)", status)
+            };
+            RouteCheck { hostname: hostname.to_string(), reachable, status: Some(status), message }
+        }
+        Err(e) => RouteCheck { hostname: hostname.to_string(), reachable: false, status: None, message: e.to_string() },
+    }
+}
+
+async fn probe_all(hostnames: &[String]) -> Vec<RouteCheck> {
+    let mut routes = Vec::new();
+    for hostname in hostnames {
+        routes.push(probe(hostname).await);
+    }
+    routes
+}
+
+async fn tunnel_state(target: &SshTarget<'_>) -> TunnelState {
+    let running = target
+        .exec("docker inspect -f '{{.State.Running}}' cloudflared 2>/dev/null")
+        .await
+        .map(|out| out.trim() == "true")
+        .unwrap_or(false);
+    let logs = target.exec("docker logs --tail 50 cloudflared 2>&1").await.unwrap_or_default();
+    classify_logs(&logs, running)
+}
+
+/// Hostnames publics déclarés dans config.yml (tunnel provisionné par l'app)
+async fn configured_hostnames(target: &SshTarget<'_>) -> Vec<String> {
+    let config = target
+        .exec(&format!("cat {}/config.yml 2>/dev/null", crate::cloudflare::REMOTE_CONFIG_DIR))
+        .await
+        .unwrap_or_default();
+    crate::cloudflare::ingress_hostnames(&config)
+}
+
+/// Écrit le nouveau token dans docker-compose.yml puis recrée cloudflared
+pub async fn update_tunnel_token(target: &SshTarget<'_>, token: &str) -> Result<()> {
+    let token = token.trim();
+    validate_token(token)?;
+    let compose = target.exec(&format!("cat {}/docker-compose.yml", COMPOSE_DIR)).await?;
+    let updated = replace_tunnel_token(&compose, token)
+        .ok_or_else(|| anyhow!("Service cloudflared absent du docker-compose.yml"))?;
+    let cmd = format!(
+        "cat > {dir}/docker-compose.yml << 'EOFCOMPOSE'\n{compose}\nEOFCOMPOSE\ncd {dir} && docker compose up -d cloudflared 2>&1",
+        dir = COMPOSE_DIR,
+        compose = updated.trim_end()
+    );
+    target.exec(&cmd).await?;
+    tracing::info!("✅ Tunnel token updated, cloudflared recreated");
+    Ok(())
+}
+
+async fn restart_cloudflared(target: &SshTarget<'_>, state: TunnelState) -> Result<()> {
+    let cmd = match state {
+        TunnelState::NotRunning => format!("cd {} && docker compose up -d cloudflared 2>&1", COMPOSE_DIR),
+        _ => "docker restart cloudflared 2>&1".to_string(),
+    };
+    target.exec(&cmd).await?;
+    Ok(())
+}
+
+/// Diagnostique l'accès distant et, si demandé, tente de le réparer
+pub async fn check_and_repair(
+    target: &SshTarget<'_>,
+    hostnames: &[String],
+    cloudflare: Option<&CloudflareSetup>,
+    repair: bool,
+) -> Result<RemoteAccessReport> {
+    let hostnames = if hostnames.is_empty() { configured_hostnames(target).await } else { hostnames.to_vec() };
+    let mut report = RemoteAccessReport {
+        routes: probe_all(&hostnames).await,
+        tunnel: tunnel_state(target).await,
+        repaired: false,
+        actions: Vec::new(),
+        needs_new_token: false,
+        message: String::new(),
+    };
+    if report.reachable() {
+        report.message = "Accès distant opérationnel".to_string();
+        tracing::info!("✅ {} route(s) reachable", report.routes.len());
+        return Ok(report);
+    }
+    tracing::warn!("⚠️ Remote access broken (tunnel: {:?})", report.tunnel);
+    if !repair {
+        report.needs_new_token = report.tunnel == TunnelState::TokenRejected && cloudflare.is_none();
+        report.message = "Accès distant interrompu".to_string();
+        return Ok(report);
+    }
+
+    // 1. Redémarrage simple (coupure réseau, conteneur arrêté)
+    if report.tunnel != TunnelState::TokenRejected {
+        restart_cloudflared(target, report.tunnel).await?;
+        report.actions.push("cloudflared redémarré".to_string());
+        tokio::time::sleep(RECONNECT_DELAY).await;
+        report.routes = probe_all(&hostnames).await;
+        report.tunnel = tunnel_state(target).await;
+        if report.reachable() {
+            report.repaired = true;
+            report.message = "Accès distant rétabli après redémarrage de cloudflared".to_string();
+            tracing::info!("✅ Repaired by restarting cloudflared");
+            return Ok(report);
+        }
+    }
+
+    // 2. Tunnel, routes et token ré-émis via l'API
+    if let Some(setup) = cloudflare {
+        let pi_name = target.exec("hostname").await?.trim().to_string();
+        let compose = target.exec(&format!("cat {}/docker-compose.yml", COMPOSE_DIR)).await.unwrap_or_default();
+        let tunnel = crate::cloudflare::provision(setup, &pi_name, compose.contains("\n  jellyseerr:")).await?;
+        crate::cloudflare::write_config(target, &tunnel).await?;
+        update_tunnel_token(target, &tunnel.token).await?;
+        report.actions.push("Tunnel et routes DNS ré-émis".to_string());
+
+        let hostnames: Vec<String> = tunnel.routes.iter().map(|r| r.hostname.clone()).collect();
+        tokio::time::sleep(RECONNECT_DELAY).await;
+        report.routes = probe_all(&hostnames).await;
+        report.tunnel = tunnel_state(target).await;
+        if report.reachable() {
+            report.repaired = true;
+            report.message = "Accès distant rétabli (tunnel ré-émis)".to_string();
+            tracing::info!("✅ Repaired by re-issuing the tunnel");
+            return Ok(report);
+        }
+    }
+
+    // 3. Intervention de l'utilisateur
+    report.needs_new_token = cloudflare.is_none()
+        && matches!(report.tunnel, TunnelState::TokenRejected | TunnelState::Disconnected | TunnelState::Unknown);
+    report.message = if report.needs_new_token {
+        "Le tunnel ne se connecte pas: générez un nouveau token dans Cloudflare Zero Trust \
+         (Networks > Tunnels > Configure) et collez-le ici"
+            .to_string()
+    } else {
+        "Accès distant toujours interrompu: vérifiez la connexion Internet du Pi".to_string()
+    };
+    tracing::error!("❌ Repair failed ({:?})", report.tunnel);
+    Ok(report)
+}
+
+// =============================================================================
+// Agent de surveillance sur le Pi
+// =============================================================================
+
+/// Vérifie les routes, redémarre cloudflared et prévient Discord (une fois par panne)
+const AGENT_SCRIPT: &str = r#"import json, os, subprocess, time, urllib.error, urllib.request
+
+base = os.path.expanduser("~/jellysetup-agent")
+with open(os.path.join(base, "remote-access.json")) as f:
+    conf = json.load(f)
+state_path = os.path.join(base, "remote-access-status.json")
+
+def routes_ok():
+    if not conf["hostnames"]:
+        logs = subprocess.run(["docker", "logs", "--tail", "50", "cloudflared"], capture_output=True, text=True)
+        return "Registered tunnel connection" in (logs.stdout + logs.stderr)
+    for hostname in conf["hostnames"]:
+        try:
+            urllib.request.urlopen("https://%s/" % hostname, timeout=15)
+        except urllib.error.HTTPError as e:
+            if 520 <= e.code <= 530:
+                return False
+        except Exception:
+            return False
+    return True
+
+def token_rejected():
+    logs = subprocess.run(["docker", "logs", "--tail", "50", "cloudflared"], capture_output=True, text=True)
+    text = (logs.stdout + logs.stderr).lower()
+    return "unauthorized" in text or "token is not valid" in text or "tunnel not found" in text
+
+try:
+    with open(state_path) as f:
+        previous = json.load(f)
+except Exception:
+    previous = {}
+
+ok = routes_ok()
+problem = None
+if not ok:
+    subprocess.run(["docker", "restart", "cloudflared"], capture_output=True)
+    time.sleep(30)
+    ok = routes_ok()
+    if not ok:
+        problem = ("Le token du tunnel Cloudflare est refusé: ouvrez JellySetup > Accès distant pour en coller un nouveau."
+                   if token_rejected() else "Accès distant interrompu malgré le redémarrage de cloudflared.")
+
+with open(state_path, "w") as f:
+    json.dump({"checked_at": int(time.time()), "ok": ok, "problem": problem}, f)
+
+if problem and previous.get("ok", True) and conf.get("discord_webhook"):
+    body = json.dumps({"username": "JellySetup", "embeds": [{"title": "⚠️ Accès distant", "description": problem, "color": 15548997}]}).encode()
+    req = urllib.request.Request(conf["discord_webhook"], data=body, headers={"Content-Type": "application/json", "User-Agent": "jellysetup"})
+    urllib.request.urlopen(req, timeout=20)
+print(problem or "OK")
+"#;
+
+/// Installe l'agent (cron toutes les 10 minutes); hostnames lus dans config.yml
+pub async fn install_watchdog(target: &SshTarget<'_>, discord_webhook: Option<&str>) -> Result<()> {
+    let conf = json!({
+        "hostnames": configured_hostnames(target).await,
+        "discord_webhook": discord_webhook.unwrap_or_default(),
+    });
+    let cmd = format!(
+        "mkdir -p {dir} && umask 077 && cat > {dir}/remote-access.json << 'EOFREMOTE'\n{conf}\nEOFREMOTE\n\
+         cat > {dir}/remote_access_check.py << 'EOFREMOTE'\n{script}EOFREMOTE\n\
+         (crontab -l 2>/dev/null | grep -v '{marker}'; echo \"*/10 * * * * python3 {dir}/remote_access_check.py >/dev/null 2>&1 {marker}\") | crontab -",
+        dir = AGENT_DIR,
+        conf = conf,
+        script = AGENT_SCRIPT,
+        marker = CRON_MARKER
+    );
+    target.exec(&cmd).await?;
+    tracing::info!("✅ Watchdog installed ({} routes)", conf["hostnames"].as_array().map_or(0, Vec::len));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tunnel_diagnosis() {
+        let logs = "2026-01-01T10:00:00Z INF Registered tunnel connection connIndex=0\n\
+                    2026-01-01T11:00:00Z ERR Connection terminated error=\"timeout\"\n";
+        assert_eq!(classify_logs(logs, true), TunnelState::Disconnected);
+        assert_eq!(classify_logs(&format!("{}INF Registered tunnel connection connIndex=1\n", logs), true), TunnelState::Connected);
+        assert_eq!(
+            classify_logs("ERR Register tunnel error from server side error=\"Unauthorized: Invalid tunnel secret\"", true),
+            TunnelState::TokenRejected
+        );
+        assert_eq!(classify_logs("", true), TunnelState::Unknown);
+        assert_eq!(classify_logs(logs, false), TunnelState::NotRunning);
+
+        let compose = "services:\n  cloudflared:\n    environment:\n      - \"TUNNEL_TOKEN=old\"\n";
+        assert_eq!(
+            replace_tunnel_token(compose, "eyJhIjoiYiJ9").unwrap(),
+            "services:\n  cloudflared:\n    environment:\n      - \"TUNNEL_TOKEN=eyJhIjoiYiJ9\"\n"
+        );
+        assert_eq!(replace_tunnel_token("      - TUNNEL_TOKEN=", "new").unwrap(), "      - TUNNEL_TOKEN=new");
+        assert!(replace_tunnel_token("services: {}\n", "new").is_none());
+
+        assert!(validate_token("eyJhIjoiMTIzIiwidCI6IjQ1NiIsInMiOiI3ODkifQ==").is_ok());
+        assert!(validate_token("abc def").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::Window;
+use ts_rs::TS;
+
+use crate::install_state::{InstallState, InstallStep};
+use crate::secret::SecretString;
+use crate::InstallConfig;
+
+// =============================================================================
+// Reprise d'une installation depuis un autre ordinateur
+// =============================================================================
+//
+// À chaque étape terminée, l'installation publie dans Supabase Storage un point
+// de reprise: métadonnées en clair (Pi, étapes, erreur) et contenu chiffré par le
+// mot de passe de reprise (crypto::encrypt_secret, Argon2id + AES-256-GCM):
+// identifiants SSH, InstallConfig, état d'installation et procédure épinglée.
+// Le point est rangé dans le dossier du compte JellySetup connecté (account.rs),
+// lisible par lui seul: sans session, rien n'est publié.
+// Sur un autre ordinateur, après connexion au même compte,
+// `resume_remote_installation` déchiffre ce point, restaure l'état local puis
+// reprend l'installation comme après un échec.
+
+/// Métadonnées lisibles sans mot de passe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ResumePointInfo {
+    pub pi_name: String,
+    pub host: String,
+    pub installer_version: String,
+    /// RFC 3339
+    pub saved_at: String,
+    pub completed: Vec<String>,
+    pub failed_step: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// Point de reprise tel que stocké (métadonnées + contenu chiffré)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ResumeEnvelope {
+    #[serde(flatten)]
+    info: ResumePointInfo,
+    encrypted: String,
+}
+
+/// Contenu chiffré du point de reprise
+#[derive(Clone, Serialize, Deserialize)]
+struct ResumePayload {
+    host: String,
+    username: String,
+    password: Option<SecretString>,
+    private_key: Option<SecretString>,
+    /// Sans le mot de passe de reprise
+    config: InstallConfig,
+    state: Value,
+    /// Progression de la procédure (procédure épinglée incluse)
+    procedure: Option<Value>,
+}
+
+fn step_name(step: InstallStep) -> String {
+    serde_json::to_value(step).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default()
+}
+
+impl ResumePayload {
+    fn seal(&self, pi_name: &str, state: &InstallState, resume_password: &str) -> Result<ResumeEnvelope> {
+        let info = ResumePointInfo {
+            pi_name: pi_name.to_string(),
+            host: self.host.clone(),
+            installer_version: env!("CARGO_PKG_VERSION").to_string(),
+            saved_at: state.updated_at.clone(),
+            completed: state.completed.iter().copied().map(step_name).collect(),
+            failed_step: state.failed_step.map(step_name),
+            last_error: state.last_error.clone(),
+        };
+        let encrypted = crate::crypto::encrypt_secret(&serde_json::to_string(self)?, resume_password)?;
+        Ok(ResumeEnvelope { info, encrypted })
+    }
+
+    fn open(envelope: &ResumeEnvelope, resume_password: &str) -> Result<Self> {
+        let json = crate::crypto::decrypt_secret(&envelope.encrypted, resume_password)
+            .map_err(|_| anyhow!("Mot de passe de reprise incorrect"))?;
+        Ok(serde_json::from_str(json.expose())?)
+    }
+}
+
+/// Publie le point de reprise à chaque sauvegarde de l'état (porté par InstallState)
+#[derive(Clone)]
+pub struct ResumePublisher {
+    payload: ResumePayload,
+    resume_password: SecretString,
+}
+
+impl std::fmt::Debug for ResumePublisher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResumePublisher").field("host", &self.payload.host).finish_non_exhaustive()
+    }
+}
+
+impl ResumePublisher {
+    /// None si l'utilisateur n'a pas choisi de mot de passe de reprise
+    pub fn new(host: &str, username: &str, password: Option<&str>, private_key: Option<&str>, config: &InstallConfig) -> Option<Self> {
+        let resume_password = config.resume_password.clone().filter(|p| !p.is_empty())?;
+        let mut config = config.clone();
+        config.resume_password = None;
+        Some(Self {
+            payload: ResumePayload {
+                host: host.to_string(),
+                username: username.to_string(),
+                password: password.map(SecretString::from),
+                private_key: private_key.map(SecretString::from),
+                config,
+                state: Value::Null,
+                procedure: None,
+            },
+            resume_password,
+        })
+    }
+
+    /// Met à jour le point de reprise (supprimé une fois l'installation terminée)
+    pub async fn publish(&self, state: &InstallState) -> Result<()> {
+        let Some(pi_name) = state.pi_name.as_deref() else {
+            return Ok(());
+        };
+        if state.finished {
+            return crate::supabase::delete_resume_point(pi_name).await;
+        }
+
+        let mut payload = self.payload.clone();
+        payload.state = serde_json::to_value(state)?;
+        payload.procedure = crate::procedure::progress_snapshot(&payload.host);
+        let envelope = payload.seal(pi_name, state, self.resume_password.expose())?;
+        crate::supabase::upload_resume_point(pi_name, &serde_json::to_value(&envelope)?).await
+    }
+}
+
+async fn fetch_envelope(pi_name: &str) -> Result<Option<ResumeEnvelope>> {
+    let point = crate::supabase::download_resume_point(pi_name).await?;
+    point.map(|p| serde_json::from_value(p).map_err(|e| anyhow!("Point de reprise illisible: {}", e))).transpose()
+}
+
+/// Installation interrompue publiée pour ce Pi, s'il y en a une
+pub async fn resume_point_info(pi_name: &str) -> Result<Option<ResumePointInfo>> {
+    Ok(fetch_envelope(pi_name).await?.map(|envelope| envelope.info))
+}
+
+/// Restaure l'état publié sur cet ordinateur puis reprend l'installation
+pub async fn resume(window: Window, pi_name: &str, resume_password: &str) -> Result<()> {
+    let envelope = fetch_envelope(pi_name)
+        .await?
+        .ok_or_else(|| anyhow!("Aucune installation interrompue à reprendre pour {}", pi_name))?;
+    let payload = ResumePayload::open(&envelope, resume_password)?;
+    tracing::info!(
+        "Resuming {} from another computer ({} steps done, saved {})",
+        pi_name,
+        envelope.info.completed.len(),
+        envelope.info.saved_at
+    );
+
+    let state: InstallState = serde_json::from_value(payload.state.clone())?;
+    state.save_local()?;
+    if let Some(procedure) = &payload.procedure {
+        crate::procedure::restore_progress(&payload.host, procedure)?;
+    }
+    let mut config = payload.config.clone();
+    config.resume_password = Some(resume_password.into());
+
+    let host = payload.host.as_str();
+    match (&payload.private_key, &payload.password) {
+        (Some(private_key), _) => {
+            // Nom du Pi publié avec le point (hostname réel, même si `host` est une IP)
+            let hostname = envelope.info.pi_name.as_str();
+            crate::flash::resume_installation(window, host, &payload.username, private_key.expose(), config, hostname).await
+        }
+        (None, Some(password)) => {
+            crate::flash::resume_installation_password(window, host, &payload.username, password.expose(), config).await
+        }
+        (None, None) => Err(anyhow!("Point de reprise sans identifiants SSH")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_point_roundtrip() {
+        let payload = ResumePayload {
+            host: "jellypi.local".to_string(),
+            username: "pi".to_string(),
+            password: Some("secret".into()),
+            private_key: None,
+            config: serde_json::from_value(serde_json::json!({
+                "alldebrid_api_key": "key", "jellyfin_username": "admin", "jellyfin_password": "pw", "jellyfin_server_name": "Jelly"
+            }))
+            .unwrap(),
+            state: Value::Null,
+            procedure: None,
+        };
+        let mut state: InstallState = serde_json::from_value(serde_json::json!({
+            "host": "jellypi.local", "pi_name": "jellypi", "completed": ["system_update", "docker"],
+            "current_step": null, "failed_step": "image_pull", "last_error": "timeout",
+            "finished": false, "updated_at": "2026-01-01T12:00:00+00:00"
+        }))
+        .unwrap();
+        state.set_pi_name("jellypi");
+
+        let envelope = payload.seal("jellypi", &state, "mot de passe").unwrap();
+        assert_eq!(envelope.info.completed, vec!["system_update", "docker"]);
+        assert_eq!(envelope.info.failed_step.as_deref(), Some("image_pull"));
+        assert!(!envelope.encrypted.contains("secret"));
+
+        let stored: ResumeEnvelope = serde_json::from_value(serde_json::to_value(&envelope).unwrap()).unwrap();
+        let opened = ResumePayload::open(&stored, "mot de passe").unwrap();
+        assert_eq!(crate::secret::expose_opt(&opened.password), Some("secret"));
+        assert_eq!(opened.config.jellyfin_username, "admin");
+        assert_eq!(opened.config.jellyfin_password.expose(), "pw");
+        assert!(matches!(ResumePayload::open(&stored, "autre"), Err(e) if e.to_string() == "Mot de passe de reprise incorrect"));
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::services::arr_client::JellyseerrClient;
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Demandes Jellyseerr du foyer, synchronisées dans Supabase (table `requests`)
+// =============================================================================
+//
+// Les demandes en attente et approuvées sont lues via l'API Jellyseerr (sur le Pi)
+// puis upsertées par `jellyseerr_id` dans le schéma du Pi, pour que l'app affiche
+// "ce que le foyer a demandé" à côté de la progression des téléchargements.
+
+/// Demandes lues par filtre (les plus récentes)
+const REQUESTS_PER_FILTER: usize = 50;
+
+/// Demande d'un membre du foyer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct HouseholdRequest {
+    #[ts(type = "number")]
+    pub jellyseerr_id: u64,
+    /// "movie" ou "tv"
+    pub media_type: String,
+    #[ts(type = "number")]
+    pub tmdb_id: u64,
+    pub title: String,
+    /// "pending", "approved", "declined"
+    pub status: String,
+    /// "pending", "processing", "partially_available", "available", "unknown"
+    pub media_status: String,
+    pub requested_by: String,
+    pub requested_at: String,
+    pub is_4k: bool,
+}
+
+fn request_status(code: u64) -> &'static str {
+    match code {
+        1 => "pending",
+        2 => "approved",
+        3 => "declined",
+        _ => "unknown",
+    }
+}
+
+fn media_status(code: u64) -> &'static str {
+    match code {
+        2 => "pending",
+        3 => "processing",
+        4 => "partially_available",
+        5 => "available",
+        _ => "unknown",
+    }
+}
+
+/// Parse une page de /api/v1/request (titre vide: résolu ensuite via TMDB)
+pub fn parse_requests(page: &Value) -> Vec<HouseholdRequest> {
+    page["results"]
+        .as_array()
+        .map(|results| {
+            results
+                .iter()
+                .filter_map(|r| {
+                    Some(HouseholdRequest {
+                        jellyseerr_id: r["id"].as_u64()?,
+                        media_type: r["type"].as_str().or_else(|| r["media"]["mediaType"].as_str())?.to_string(),
+                        tmdb_id: r["media"]["tmdbId"].as_u64()?,
+                        title: String::new(),
+                        status: request_status(r["status"].as_u64().unwrap_or(0)).to_string(),
+                        media_status: media_status(r["media"]["status"].as_u64().unwrap_or(0)).to_string(),
+                        requested_by: r["requestedBy"]["displayName"]
+                            .as_str()
+                            .or_else(|| r["requestedBy"]["email"].as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                        requested_at: r["createdAt"].as_str().unwrap_or_default().to_string(),
+                        is_4k: r["is4k"].as_bool().unwrap_or(false),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Lit les demandes en attente/approuvées et les synchronise dans Supabase
+pub async fn sync_requests(target: SshTarget<'_>, pi_name: &str) -> Result<Vec<HouseholdRequest>> {
+    let client = JellyseerrClient::connect(&target).await?;
+
+    let mut requests: Vec<HouseholdRequest> = Vec::new();
+    for filter in ["pending", "approved"] {
+        let page = client.list_requests(filter, REQUESTS_PER_FILTER).await?;
+        for request in parse_requests(&page) {
+            if !requests.iter().any(|r| r.jellyseerr_id == request.jellyseerr_id) {
+                requests.push(request);
+            }
+        }
+    }
+
+    for request in &mut requests {
+        request.title = client
+            .media_title(&request.media_type, request.tmdb_id)
+            .await
+            .unwrap_or_else(|_| format!("TMDB {}", request.tmdb_id));
+    }
+
+    let rows: Vec<Value> = requests.iter().filter_map(|r| serde_json::to_value(r).ok()).collect();
+    crate::supabase::upsert_requests(pi_name, &rows).await?;
+
+    tracing::info!("✅ {} Jellyseerr requests synced for {}", requests.len(), pi_name);
+    Ok(requests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_requests() {
+        let page = json!({
+            "pageInfo": { "pages": 1, "results": 2 },
+            "results": [
+                {
+                    "id": 12, "status": 2, "type": "movie", "is4k": false, "createdAt": "2026-03-01T10:00:00.000Z",
+                    "media": { "tmdbId": 603, "mediaType": "movie", "status": 3 },
+                    "requestedBy": { "displayName": "Léa", "email": "lea@example.com" }
+                },
+                { "id": 13, "status": 1, "media": { "mediaType": "tv", "status": 2 } }
+            ]
+        });
+        let requests = parse_requests(&page);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].status, "approved");
+        assert_eq!(requests[0].media_status, "processing");
+        assert_eq!(requests[0].requested_by, "Léa");
+        assert_eq!(requests[0].tmdb_id, 603);
+    }
+}
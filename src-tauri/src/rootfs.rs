@@ -0,0 +1,323 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+// =============================================================================
+// Injection de fichiers dans la partition rootfs (ext4) de l'image, avant le flash
+// =============================================================================
+//
+// La partition boot (FAT) est écrite après le flash (custom.toml, ssh, ...).
+// Pour la rootfs, on passe par `debugfs` (e2fsprogs) qui écrit dans un système
+// ext4 en espace utilisateur, sans montage ni chroot: la partition est localisée
+// via la table MBR de l'image et ouverte avec l'option `offset=` de debugfs.
+//
+// L'image en cache n'est jamais modifiée: les fichiers sont injectés dans une
+// copie (clone APFS instantané sur macOS), supprimée après le flash. Pour les gros
+// fichiers (archive d'images Docker), la rootfs de la copie est agrandie d'abord
+// (`resize2fs`); l'extension au premier démarrage reste possible car elle est la
+// dernière partition.
+
+const SECTOR_SIZE: u64 = 512;
+/// Marge ajoutée à la taille des fichiers sources lors de l'agrandissement
+const GROW_MARGIN_BYTES: u64 = 256 * 1024 * 1024;
+/// Type MBR des partitions Linux
+const LINUX_PARTITION: u8 = 0x83;
+
+/// Fichier à déposer dans la rootfs (chemin absolu sur le Pi)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootfsFile {
+    pub path: String,
+    /// Contenu brut (binaires compris)
+    #[serde(default)]
+    pub content: Vec<u8>,
+    /// Permissions octales (0o644 par défaut)
+    #[serde(default)]
+    pub mode: Option<u32>,
+    /// Propriétaire (root par défaut; 1000 pour un fichier du compte créé au flash)
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    /// Fichier local copié tel quel à la place de `content` (gros fichiers)
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+/// Partition de la table MBR
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    pub kind: u8,
+    pub start_sector: u64,
+    pub sectors: u64,
+}
+
+impl Partition {
+    pub fn offset_bytes(&self) -> u64 {
+        self.start_sector * SECTOR_SIZE
+    }
+}
+
+/// Durcissement sshd (les connexions par clé et mot de passe restent possibles)
+pub fn sshd_hardening() -> RootfsFile {
+    RootfsFile {
+        path: "/etc/ssh/sshd_config.d/90-jellysetup.conf".to_string(),
+        content: b"PermitRootLogin no\nMaxAuthTries 4\nLoginGraceTime 30\nX11Forwarding no\nAllowAgentForwarding no\n"
+            .to_vec(),
+        mode: Some(0o644),
+        uid: None,
+        gid: None,
+        source: None,
+    }
+}
+
+/// Parse les 4 entrées de la table MBR (secteur 0 de l'image)
+pub fn parse_mbr(sector: &[u8]) -> Result<Vec<Partition>> {
+    if sector.len() < 512 || sector[510] != 0x55 || sector[511] != 0xAA {
+        return Err(anyhow!("Table de partitions MBR absente de l'image"));
+    }
+    let le32 = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as u64;
+
+    Ok((0..4)
+        .map(|i| &sector[446 + i * 16..446 + (i + 1) * 16])
+        .filter(|entry| entry[4] != 0)
+        .map(|entry| Partition { kind: entry[4], start_sector: le32(&entry[8..12]), sectors: le32(&entry[12..16]) })
+        .collect())
+}
+
+/// Première partition Linux (rootfs de Raspberry Pi OS)
+pub fn rootfs_partition(partitions: &[Partition]) -> Result<&Partition> {
+    partitions
+        .iter()
+        .find(|p| p.kind == LINUX_PARTITION)
+        .ok_or_else(|| anyhow!("Partition rootfs (ext4) introuvable dans l'image"))
+}
+
+/// Secteur MBR avec la taille (en secteurs) de la partition `index` remplacée
+pub fn resize_partition_entry(sector: &[u8], index: usize, sectors: u64) -> Result<Vec<u8>> {
+    let sectors = u32::try_from(sectors).map_err(|_| anyhow!("Partition trop grande pour une table MBR"))?;
+    let mut sector = sector.to_vec();
+    let offset = 446 + index * 16 + 12;
+    sector[offset..offset + 4].copy_from_slice(&sectors.to_le_bytes());
+    Ok(sector)
+}
+
+fn find_e2fs_tool(name: &str) -> Result<String> {
+    [
+        "/opt/homebrew/opt/e2fsprogs/sbin",
+        "/usr/local/opt/e2fsprogs/sbin",
+        "/sbin",
+        "/usr/sbin",
+    ]
+    .into_iter()
+    .map(|dir| format!("{}/{}", dir, name))
+    .find(|p| Path::new(p).exists())
+    .ok_or_else(|| anyhow!("{} introuvable. Installer e2fsprogs (macOS: brew install e2fsprogs)", name))
+}
+
+/// Agrandit la dernière partition (rootfs) de l'image de `extra_bytes`, puis son système ext4
+async fn grow_rootfs(image: &Path, extra_bytes: u64) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut file = fs::OpenOptions::new().read(true).write(true).open(image)?;
+    let mut sector = vec![0u8; 512];
+    file.read_exact(&mut sector)?;
+    let partitions = parse_mbr(&sector)?;
+    let index = partitions
+        .iter()
+        .position(|p| p.kind == LINUX_PARTITION)
+        .ok_or_else(|| anyhow!("Partition rootfs (ext4) introuvable dans l'image"))?;
+    let rootfs = &partitions[index];
+    if partitions.iter().any(|p| p.start_sector > rootfs.start_sector) {
+        return Err(anyhow!("La rootfs n'est pas la dernière partition: agrandissement impossible"));
+    }
+
+    let extra_sectors = extra_bytes.div_ceil(SECTOR_SIZE);
+    let new_sectors = rootfs.sectors + extra_sectors;
+    file.set_len((rootfs.start_sector + new_sectors) * SECTOR_SIZE)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&resize_partition_entry(&sector, index, new_sectors)?)?;
+    file.sync_all()?;
+
+    // Taille explicite: sans elle, resize2fs prendrait celle du fichier image entier
+    // (partition boot comprise) et déborderait de la partition
+    let device = format!("{}?offset={}", image.display(), rootfs.offset_bytes());
+    let size = format!("{}K", new_sectors * SECTOR_SIZE / 1024);
+    let output = Command::new(find_e2fs_tool("resize2fs")?).args(["-f", &device, &size]).output().await?;
+    if !output.status.success() {
+        return Err(anyhow!("resize2fs: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+    tracing::info!("Rootfs grown by {} MB", extra_bytes / (1024 * 1024));
+    Ok(())
+}
+
+/// Répertoires parents à créer ("/opt/a/b.txt" -> ["/opt", "/opt/a"])
+fn parent_dirs(path: &str) -> Vec<String> {
+    let parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    (1..parts.len()).map(|n| format!("/{}", parts[..n].join("/"))).collect()
+}
+
+/// Script debugfs: répertoires, fichier (remplacé s'il existe), propriétaire, permissions
+pub fn debugfs_script(files: &[(RootfsFile, PathBuf)]) -> String {
+    let mut lines = Vec::new();
+    for (file, local) in files {
+        for dir in parent_dirs(&file.path) {
+            // Échoue sans conséquence si le répertoire existe déjà
+            lines.push(format!("mkdir {}", dir));
+        }
+        lines.push(format!("rm {}", file.path));
+        lines.push(format!("write {} {}", local.display(), file.path));
+        lines.push(format!("sif {} mode 0{:o}", file.path, 0o100000 | file.mode.unwrap_or(0o644)));
+        lines.push(format!("sif {} uid {}", file.path, file.uid.unwrap_or(0)));
+        lines.push(format!("sif {} gid {}", file.path, file.gid.or(file.uid).unwrap_or(0)));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Erreurs signalées par debugfs sur stderr (son code de sortie reste 0 avec `-f`)
+/// Ignorées: la bannière de version, `mkdir` d'un répertoire existant, `rm` d'un fichier absent
+pub fn debugfs_errors(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("debugfs "))
+        .filter(|line| !((line.starts_with("mkdir:") || line.starts_with("ext2fs_mkdir:")) && line.contains("already exists")))
+        .filter(|line| !(line.starts_with("rm:") && line.contains("not found")))
+        .map(String::from)
+        .collect()
+}
+
+fn validate(file: &RootfsFile) -> Result<()> {
+    let valid = file.path.starts_with('/')
+        && !file.path.ends_with('/')
+        && !file.path.split('/').any(|part| part == "..")
+        && !file.path.contains(char::is_whitespace);
+    if !valid {
+        return Err(anyhow!("Chemin rootfs invalide: {}", file.path));
+    }
+    Ok(())
+}
+
+/// Copie l'image et y injecte les fichiers; renvoie le chemin de la copie à flasher
+pub async fn prepare_custom_image(image: &Path, files: &[RootfsFile]) -> Result<PathBuf> {
+    files.iter().try_for_each(validate)?;
+    let debugfs = find_e2fs_tool("debugfs")?;
+
+    let mut sector = vec![0u8; 512];
+    {
+        use std::io::Read;
+        fs::File::open(image)?.read_exact(&mut sector)?;
+    }
+    let rootfs = rootfs_partition(&parse_mbr(&sector)?)?.clone();
+
+    let custom = image.with_extension("custom.img");
+    let staging = image.with_extension("rootfs-files");
+    fs::create_dir_all(&staging)?;
+    // std::fs::copy clone le fichier sur APFS (aucune copie des ~5 Go)
+    fs::copy(image, &custom)?;
+
+    let source_bytes: u64 = files
+        .iter()
+        .filter_map(|f| f.source.as_ref())
+        .map(|source| fs::metadata(source).map(|m| m.len()))
+        .sum::<std::io::Result<u64>>()?;
+    if source_bytes > 0 {
+        if let Err(e) = grow_rootfs(&custom, source_bytes + GROW_MARGIN_BYTES).await {
+            fs::remove_file(&custom).ok();
+            return Err(e);
+        }
+    }
+
+    let mut staged = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        let local = match &file.source {
+            Some(source) => source.clone(),
+            None => {
+                let local = staging.join(format!("{}", index));
+                fs::write(&local, &file.content)?;
+                local
+            }
+        };
+        staged.push((file.clone(), local));
+    }
+    let script_path = staging.join("debugfs.cmd");
+    fs::write(&script_path, debugfs_script(&staged))?;
+
+    let device = format!("{}?offset={}", custom.display(), rootfs.offset_bytes());
+    let output = Command::new(debugfs)
+        .args(["-w", "-f"])
+        .arg(&script_path)
+        .arg(&device)
+        .output()
+        .await?;
+    fs::remove_dir_all(&staging).ok();
+
+    let errors = debugfs_errors(&String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() || !errors.is_empty() {
+        fs::remove_file(&custom).ok();
+        return Err(anyhow!("Injection rootfs échouée: {}", errors.join("; ")));
+    }
+
+    tracing::info!("✅ {} files injected into {:?}", files.len(), custom);
+    Ok(custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mbr_and_debugfs_script() {
+        let mut sector = vec![0u8; 512];
+        // boot FAT32 LBA à 8192, rootfs Linux à 1056768
+        sector[446 + 4] = 0x0C;
+        sector[446 + 8..446 + 12].copy_from_slice(&8192u32.to_le_bytes());
+        sector[462 + 4] = 0x83;
+        sector[462 + 8..462 + 12].copy_from_slice(&1_056_768u32.to_le_bytes());
+        sector[462 + 12..462 + 16].copy_from_slice(&8_388_608u32.to_le_bytes());
+        sector[510] = 0x55;
+        sector[511] = 0xAA;
+
+        let partitions = parse_mbr(&sector).unwrap();
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(rootfs_partition(&partitions).unwrap().offset_bytes(), 541_065_216);
+        assert!(parse_mbr(&[0u8; 512]).is_err());
+
+        let script = debugfs_script(&[(sshd_hardening(), PathBuf::from("/tmp/0"))]);
+        assert!(script.starts_with("mkdir /etc\nmkdir /etc/ssh\nmkdir /etc/ssh/sshd_config.d\n"));
+        assert!(script.contains("write /tmp/0 /etc/ssh/sshd_config.d/90-jellysetup.conf\n"));
+        assert!(script.contains("mode 0100644\n"));
+        assert!(script.contains("sif /etc/ssh/sshd_config.d/90-jellysetup.conf uid 0\n"));
+        let compose = RootfsFile {
+            path: "/home/pi/media-stack/docker-compose.yml".to_string(),
+            content: b"services: {}\n".to_vec(),
+            mode: Some(0o600),
+            uid: Some(1000),
+            gid: None,
+            source: None,
+        };
+        let script = debugfs_script(&[(compose, PathBuf::from("/tmp/1"))]);
+        assert!(script.contains("mode 0100600\n"));
+        assert!(script.contains("docker-compose.yml uid 1000\n"));
+        assert!(script.contains("docker-compose.yml gid 1000\n"));
+
+        let stderr = "debugfs 1.47.0 (5-Feb-2023)\n\
+            ext2fs_mkdir: Ext2 directory already exists while creating directory \"etc\"\n\
+            mkdir: Ext2 directory already exists \n\
+            rm: File not found by ext2_lookup while trying to resolve filename\n\
+            write: Could not allocate block in ext2 filesystem \n\
+            /etc/zz: File not found by ext2_lookup \n";
+        assert_eq!(
+            debugfs_errors(stderr),
+            vec!["write: Could not allocate block in ext2 filesystem", "/etc/zz: File not found by ext2_lookup"]
+        );
+
+        let resized = resize_partition_entry(&sector, 1, 9_000_000).unwrap();
+        assert_eq!(parse_mbr(&resized).unwrap()[1].sectors, 9_000_000);
+
+        let escape = RootfsFile { path: "/opt/../etc/shadow".to_string(), ..sshd_hardening() };
+        assert!(validate(&escape).is_err());
+    }
+}
@@ -1,11 +1,9 @@
 use crate::SDCard;
 use anyhow::{anyhow, Result};
+use serde::Deserialize;
 use std::process::Command;
 
-// Taille max pour une carte SD (512 GB) - sécurité pour ne pas formater un SSD
-const MAX_SD_SIZE_BYTES: u64 = 512 * 1024 * 1024 * 1024;
-// Taille min pour une carte SD utilisable (4 GB)
-const MIN_SD_SIZE_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+// Tailles min/max d'une carte SD: config::Settings (sécurité pour ne pas formater un SSD)
 
 /// Liste les cartes SD disponibles
 pub async fn list_removable_drives() -> Result<Vec<SDCard>> {
@@ -25,125 +23,177 @@ pub async fn list_removable_drives() -> Result<Vec<SDCard>> {
     }
 }
 
-/// Liste les cartes SD sur macOS - approche ultra-simple
-#[cfg(target_os = "macos")]
-async fn list_sd_cards_macos() -> Result<Vec<SDCard>> {
-    let mut sd_cards = Vec::new();
+// =============================================================================
+// Sorties structurées de diskutil (-plist, lues par le crate plist)
+// =============================================================================
+
+/// `diskutil info -plist <disque>` (champs utiles)
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct DiskInfo {
+    pub device_identifier: String,
+    pub size: u64,
+    pub total_size: u64,
+    pub internal: bool,
+    pub removable_media: bool,
+    pub ejectable: bool,
+    /// Disque système du Mac
+    #[serde(rename = "OSInternalMedia")]
+    pub os_internal_media: bool,
+    /// "Physical", "Virtual" (APFS, images disque) ou "Unknown"
+    pub virtual_or_physical: String,
+    /// "Secure Digital", "USB", "PCI-Express", ...
+    pub bus_protocol: String,
+    pub media_name: String,
+    /// Faux si le verrou d'écriture de la carte est engagé
+    pub writable_media: bool,
+}
 
-    // Lister tous les disques
-    let output = Command::new("diskutil")
-        .args(["list"])
-        .output()?;
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    println!("[SD Detection] diskutil list output:");
-    println!("{}", stdout);
-
-    // Chercher les disques physiques (pas synthesized)
-    // Inclure aussi "external" pour les cartes SD dans lecteur USB
-    for line in stdout.lines() {
-        // Format: /dev/disk11 (internal, physical): ou /dev/disk11 (external, physical):
-        if line.starts_with("/dev/disk") && (line.contains("physical") || line.contains("external")) && !line.contains("synthesized") {
-            // Extraire disk id: "/dev/disk11 (internal, physical):" -> "disk11"
-            if let Some(disk_part) = line.split_whitespace().next() {
-                let disk_id = disk_part.trim_start_matches("/dev/");
-
-                println!("[SD Detection] Found physical disk: {}", disk_id);
-
-                // Ignorer disk0-3 (disques système)
-                if disk_id == "disk0" || disk_id == "disk1" || disk_id == "disk2" || disk_id == "disk3" {
-                    println!("[SD Detection] Skipping system disk: {}", disk_id);
-                    continue;
-                }
-
-                // Récupérer les infos du disque
-                if let Some(sd) = get_disk_info(disk_id).await {
-                    println!("[SD Detection] Valid SD card found: {} ({} GB)", sd.name, sd.size / 1024 / 1024 / 1024);
-                    sd_cards.push(sd);
-                } else {
-                    println!("[SD Detection] Disk {} rejected after info check", disk_id);
-                }
-            }
-        }
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+impl DiskInfo {
+    pub fn size_bytes(&self) -> u64 {
+        if self.size > 0 { self.size } else { self.total_size }
     }
+}
 
-    println!("[SD Detection] Total SD cards found: {}", sd_cards.len());
-    Ok(sd_cards)
+/// Partition listée par `diskutil list -plist`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ListedPartition {
+    pub volume_name: String,
 }
 
-/// Récupère les infos d'un disque
-#[cfg(target_os = "macos")]
-async fn get_disk_info(disk_id: &str) -> Option<SDCard> {
-    // D'abord récupérer la taille du disque entier
-    let output = Command::new("diskutil")
-        .args(["info", disk_id])
-        .output()
-        .ok()?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut size: u64 = 0;
-
-    for line in stdout.lines() {
-        if line.contains("Disk Size:") || line.contains("Total Size:") {
-            if let Some(start_idx) = line.find('(') {
-                if let Some(end_idx) = line.find(" Bytes)") {
-                    let bytes_str = &line[start_idx + 1..end_idx];
-                    size = bytes_str.parse().unwrap_or(0);
-                }
-            }
-        }
-    }
+/// Disque listé par `diskutil list -plist`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ListedDisk {
+    pub device_identifier: String,
+    pub partitions: Vec<ListedPartition>,
+}
 
-    // Vérifier la taille
-    if size < MIN_SD_SIZE_BYTES || size > MAX_SD_SIZE_BYTES {
-        println!("[SD Detection] Disk {} size {} out of range", disk_id, size);
-        return None;
+/// `diskutil list -plist physical`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct DiskList {
+    pub whole_disks: Vec<String>,
+    pub all_disks_and_partitions: Vec<ListedDisk>,
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+impl DiskList {
+    /// Nom du premier volume nommé du disque (ex: "bootfs" d'une carte déjà flashée)
+    pub fn volume_name(&self, disk_id: &str) -> Option<String> {
+        self.all_disks_and_partitions
+            .iter()
+            .find(|d| d.device_identifier == disk_id)?
+            .partitions
+            .iter()
+            .map(|p| p.volume_name.trim())
+            .find(|name| !name.is_empty())
+            .map(String::from)
     }
+}
 
-    // Chercher le nom du volume sur la première partition (disk11s1)
-    let partition_id = format!("{}s1", disk_id);
-    let volume_name = get_volume_name(&partition_id).unwrap_or_default();
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn parse_diskutil_info(xml: &[u8]) -> Result<DiskInfo> {
+    plist::from_bytes(xml).map_err(|e| anyhow!("Sortie de diskutil info illisible: {}", e))
+}
 
-    // Nom final: nom du volume si disponible, sinon indication claire
-    let size_gb = size / 1024 / 1024 / 1024;
-    let display_name = if volume_name.is_empty() || volume_name == "Not applicable (no file system)" {
-        // Carte flashée avec Linux ou vide - afficher la taille pour aider l'utilisateur
-        format!("Carte SD {}GB ({})", size_gb, disk_id)
-    } else {
-        format!("{} - {}GB ({})", volume_name, size_gb, disk_id)
-    };
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn parse_diskutil_list(xml: &[u8]) -> Result<DiskList> {
+    plist::from_bytes(xml).map_err(|e| anyhow!("Sortie de diskutil list illisible: {}", e))
+}
+
+/// disk0 à disk3: disques système, jamais proposés
+fn is_system_disk(disk_id: &str) -> bool {
+    matches!(disk_id, "disk0" | "disk1" | "disk2" | "disk3")
+}
 
-    println!("[SD Detection] {} -> {} ({} GB)", disk_id, display_name, size / 1024 / 1024 / 1024);
+/// Nom affiché: nom du volume si disponible, sinon la taille pour aider l'utilisateur
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn display_name(volume_name: Option<&str>, size: u64, disk_id: &str) -> String {
+    let size_gb = size / 1024 / 1024 / 1024;
+    match volume_name.filter(|v| !v.is_empty() && *v != "Not applicable (no file system)") {
+        Some(volume) => format!("{} - {}GB ({})", volume, size_gb, disk_id),
+        // Carte flashée avec Linux ou vide
+        None => format!("Carte SD {}GB ({})", size_gb, disk_id),
+    }
+}
 
+/// Carte SD candidate d'après `diskutil info` (None: disque système, virtuel, verrouillé ou hors limites)
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn sd_card_from_disk_info(info: &DiskInfo, volume_name: Option<&str>, settings: &crate::config::Settings) -> Option<SDCard> {
+    let disk_id = info.device_identifier.as_str();
+    if is_system_disk(disk_id) || info.os_internal_media || info.virtual_or_physical == "Virtual" {
+        tracing::info!("Skipping system/virtual disk: {}", disk_id);
+        return None;
+    }
+    if !info.writable_media {
+        tracing::info!("Disk {} is write-protected (lock switch?)", disk_id);
+        return None;
+    }
+    let size = info.size_bytes();
+    if size < settings.min_sd_size_bytes || size > settings.max_sd_size_bytes {
+        tracing::info!("Disk {} size {} out of range", disk_id, size);
+        return None;
+    }
     Some(SDCard {
         path: format!("/dev/r{}", disk_id),
-        name: display_name,
+        name: display_name(volume_name, size, disk_id),
         size,
-        removable: true,
+        removable: info.removable_media || info.ejectable,
     })
 }
 
-/// Récupère le nom du volume d'une partition
+// =============================================================================
+// Détection par OS
+// =============================================================================
+
+#[cfg(target_os = "macos")]
+fn diskutil_info(disk_id: &str) -> Result<DiskInfo> {
+    // Option -plist avant l'identifiant du disque
+    let output = Command::new("diskutil").args(["info", "-plist", disk_id]).output()?;
+    if !output.status.success() {
+        return Err(anyhow!("diskutil info {} a échoué: {}", disk_id, String::from_utf8_lossy(&output.stderr)));
+    }
+    parse_diskutil_info(&output.stdout)
+}
+
+/// Liste les cartes SD sur macOS (disques physiques, infos typées de diskutil)
 #[cfg(target_os = "macos")]
-fn get_volume_name(partition_id: &str) -> Option<String> {
-    let output = Command::new("diskutil")
-        .args(["info", partition_id])
-        .output()
-        .ok()?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    for line in stdout.lines() {
-        if line.contains("Volume Name:") {
-            if let Some(value) = line.split(':').last() {
-                let name = value.trim().to_string();
-                if !name.is_empty() && name != "Not applicable (no file system)" {
-                    return Some(name);
-                }
+async fn list_sd_cards_macos() -> Result<Vec<SDCard>> {
+    let output = Command::new("diskutil").args(["list", "-plist", "physical"]).output()?;
+    let list = parse_diskutil_list(&output.stdout)?;
+    let settings = crate::config::settings();
+
+    let mut sd_cards = Vec::new();
+    for disk_id in &list.whole_disks {
+        let info = match diskutil_info(disk_id) {
+            Ok(info) => info,
+            Err(e) => {
+                tracing::warn!("⚠️ diskutil info {} failed: {}", disk_id, e);
+                continue;
             }
+        };
+        tracing::info!(
+            "{}: {} bytes, {} ({}), internal={}, removable={}",
+            disk_id, info.size_bytes(), info.media_name, info.bus_protocol, info.internal, info.removable_media
+        );
+        match sd_card_from_disk_info(&info, list.volume_name(disk_id).as_deref(), &settings) {
+            Some(sd) => {
+                tracing::info!("Valid SD card found: {} ({} GB)", sd.name, sd.size / 1024 / 1024 / 1024);
+                sd_cards.push(sd);
+            }
+            None => tracing::info!("Disk {} rejected after info check", disk_id),
         }
     }
-    None
+
+    tracing::info!("Total SD cards found: {}", sd_cards.len());
+    Ok(sd_cards)
 }
 
 #[cfg(target_os = "windows")]
@@ -156,6 +206,26 @@ async fn list_sd_cards_linux() -> Result<Vec<SDCard>> {
     Ok(Vec::new())
 }
 
+/// Taille d'un disque en octets (/dev/rdiskN, /dev/sdX)
+pub async fn disk_size(device_path: &str) -> Result<u64> {
+    #[cfg(target_os = "macos")]
+    {
+        let disk_path = device_path.replace("/dev/r", "/dev/");
+        let size = diskutil_info(&disk_path)?.size_bytes();
+        if size == 0 {
+            return Err(anyhow!("Impossible de déterminer la taille du disque"));
+        }
+        Ok(size)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        // Pour les autres OS, retourner une valeur par défaut sûre
+        let _ = device_path;
+        Ok(32 * 1024 * 1024 * 1024) // 32GB par défaut
+    }
+}
+
 /// Vérifie une dernière fois avant le flash que c'est bien une carte SD
 pub fn verify_safe_to_flash(device_path: &str, expected_size: u64) -> Result<()> {
     // Extraire le disk id du path (ex: /dev/rdisk11 -> disk11)
@@ -164,19 +234,23 @@ pub fn verify_safe_to_flash(device_path: &str, expected_size: u64) -> Result<()>
         .trim_start_matches("/dev/");
 
     // Vérifier que ce n'est pas un disque système (disk0, disk1, disk2, disk3)
-    let is_system_disk = disk_id == "disk0" || disk_id == "disk1"
-        || disk_id == "disk2" || disk_id == "disk3";
-
-    if is_system_disk {
+    if is_system_disk(disk_id) {
         return Err(anyhow!("SECURITE: Impossible de flasher le disque systeme!"));
     }
 
-    if expected_size > MAX_SD_SIZE_BYTES {
-        return Err(anyhow!("SECURITE: Disque trop grand pour etre une carte SD (max 512GB)"));
+    let settings = crate::config::settings();
+    if expected_size > settings.max_sd_size_bytes {
+        return Err(anyhow!(
+            "SECURITE: Disque trop grand pour etre une carte SD (max {}GB)",
+            settings.max_sd_size_bytes / 1024 / 1024 / 1024
+        ));
     }
 
-    if expected_size < MIN_SD_SIZE_BYTES {
-        return Err(anyhow!("SECURITE: Disque trop petit (min 4GB requis)"));
+    if expected_size < settings.min_sd_size_bytes {
+        return Err(anyhow!(
+            "SECURITE: Disque trop petit (min {}GB requis)",
+            settings.min_sd_size_bytes / 1024 / 1024 / 1024
+        ));
     }
 
     Ok(())
@@ -191,7 +265,7 @@ pub async fn unmount_disk(device_path: &str) -> Result<()> {
             .trim_start_matches("/dev/r")
             .trim_start_matches("/dev/");
 
-        println!("[SD] Unmounting disk: {}", disk_id);
+        tracing::info!("Unmounting disk: {}", disk_id);
 
         // Force unmount de toutes les partitions
         let output = Command::new("diskutil")
@@ -200,7 +274,7 @@ pub async fn unmount_disk(device_path: &str) -> Result<()> {
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            println!("[SD] Unmount warning: {}", stderr);
+            tracing::info!("Unmount warning: {}", stderr);
         }
 
         // Attendre un peu que le système libère le disque
@@ -249,3 +323,35 @@ pub async fn eject_disk(device_path: &str) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISKUTIL_INFO_SD: &[u8] = include_bytes!("../tests/fixtures/diskutil_info_sd.plist");
+    const DISKUTIL_LIST: &[u8] = include_bytes!("../tests/fixtures/diskutil_list.plist");
+
+    #[test]
+    fn test_structured_disk_detection() {
+        let settings = crate::config::Settings::default();
+
+        let info = parse_diskutil_info(DISKUTIL_INFO_SD).unwrap();
+        assert_eq!(info.device_identifier, "disk4");
+        assert_eq!(info.size_bytes(), 31_914_983_424);
+        assert_eq!(info.bus_protocol, "Secure Digital");
+        assert!(info.removable_media && info.writable_media && !info.os_internal_media);
+
+        let list = parse_diskutil_list(DISKUTIL_LIST).unwrap();
+        assert_eq!(list.whole_disks, vec!["disk0", "disk4"]);
+        assert_eq!(list.volume_name("disk4").as_deref(), Some("bootfs"));
+        assert_eq!(list.volume_name("disk0"), None);
+
+        let card = sd_card_from_disk_info(&info, list.volume_name("disk4").as_deref(), &settings).unwrap();
+        assert_eq!(card.path, "/dev/rdisk4");
+        assert_eq!(card.name, "bootfs - 29GB (disk4)");
+        let locked = DiskInfo { writable_media: false, ..info.clone() };
+        assert!(sd_card_from_disk_info(&locked, None, &settings).is_none());
+        let system = DiskInfo { device_identifier: "disk0".to_string(), ..info };
+        assert!(sd_card_from_disk_info(&system, None, &settings).is_none());
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroize;
+
+// =============================================================================
+// Secrets en mémoire (mots de passe, clés privées, tokens)
+// =============================================================================
+//
+// `SecretString` remplace `String` pour les secrets conservés pendant la vie de
+// l'application (InstallConfig, session SSH persistante, bastion, reprise): le
+// contenu est effacé (zeroize) à la libération et n'apparaît jamais dans un
+// `{:?}`. Il n'implémente pas Display: la valeur ne sort que par `expose()`, au
+// moment de l'utiliser. La sérialisation reste transparente (IPC, fichiers
+// chiffrés); le masquage dans les journaux est assuré par le module redact.
+
+/// Chaîne secrète effacée de la mémoire à la libération
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Valeur en clair, à n'utiliser qu'au moment de s'en servir
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretString(\"••••••\")")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// `Option<SecretString>` en `Option<&str>` (équivalent de `as_deref`)
+pub fn expose_opt(secret: &Option<SecretString>) -> Option<&str> {
+    secret.as_ref().map(SecretString::expose)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_string() {
+        let secret = SecretString::from("hunter2");
+        assert_eq!(secret.expose(), "hunter2");
+        assert_eq!(format!("{:?}", secret), "SecretString(\"••••••\")");
+        assert!(!format!("{:?}", Some(secret.clone())).contains("hunter2"));
+
+        let json = serde_json::to_string(&secret).unwrap();
+        assert_eq!(json, "\"hunter2\"");
+        let parsed: Option<SecretString> = serde_json::from_str(&json).unwrap();
+        assert_eq!(expose_opt(&parsed), Some("hunter2"));
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::secret::SecretString;
+
+// =============================================================================
+// Coffre du système d'exploitation (clés SSH, clés API, mots de passe)
+// =============================================================================
+//
+// Les secrets saisis dans l'assistant ou générés pour un Pi sont rangés dans le
+// coffre de l'OS: Trousseau sur macOS, Gestionnaire d'identification sous
+// Windows, Secret Service (GNOME Keyring, KWallet) sous Linux. Le frontend ne
+// les persiste plus dans localStorage: il les relit ici au démarrage.
+// Une entrée = service "JellySetup", compte "<type>:<portée>" (portée: nom du Pi
+// pour la clé SSH, "default" pour les réglages de l'assistant).
+
+const KEYCHAIN_SERVICE: &str = "JellySetup";
+pub const DEFAULT_SCOPE: &str = "default";
+
+/// Type de secret rangé dans le coffre
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "../src/bindings/")]
+pub enum SecretKind {
+    SshPrivateKey,
+    AlldebridKey,
+    YggPasskey,
+    CloudflareToken,
+    CloudflareApiToken,
+    SystemPassword,
+    WifiPassword,
+    JellyfinPassword,
+    /// Jeton de rafraîchissement du compte JellySetup (account.rs)
+    AccountRefreshToken,
+    /// Token délivré par jellysetup-init, portée = schéma du Pi (supabase.rs)
+    PiToken,
+}
+
+impl SecretKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SecretKind::SshPrivateKey => "ssh_private_key",
+            SecretKind::AlldebridKey => "alldebrid_key",
+            SecretKind::YggPasskey => "ygg_passkey",
+            SecretKind::CloudflareToken => "cloudflare_token",
+            SecretKind::CloudflareApiToken => "cloudflare_api_token",
+            SecretKind::SystemPassword => "system_password",
+            SecretKind::WifiPassword => "wifi_password",
+            SecretKind::JellyfinPassword => "jellyfin_password",
+            SecretKind::AccountRefreshToken => "account_refresh_token",
+            SecretKind::PiToken => "pi_token",
+        }
+    }
+}
+
+/// Compte de l'entrée dans le coffre
+fn account(kind: SecretKind, scope: &str) -> Result<String> {
+    if scope.is_empty() || !scope.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return Err(anyhow!("Portée de secret invalide: {}", scope));
+    }
+    Ok(format!("{}:{}", kind.as_str(), scope))
+}
+
+fn entry(kind: SecretKind, scope: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &account(kind, scope)?)
+        .map_err(|e| anyhow!("Coffre du système indisponible: {}", e))
+}
+
+/// Range un secret (remplace la valeur existante; vide: supprime l'entrée)
+pub fn store(kind: SecretKind, scope: &str, value: &SecretString) -> Result<()> {
+    if value.is_empty() {
+        return delete(kind, scope);
+    }
+    crate::redact::register(value.expose());
+    entry(kind, scope)?
+        .set_password(value.expose())
+        .map_err(|e| anyhow!("Impossible d'enregistrer {} dans le coffre: {}", kind.as_str(), e))?;
+    tracing::info!("✅ {} stored in OS keychain ({})", kind.as_str(), scope);
+    Ok(())
+}
+
+/// Lit un secret (None s'il n'a jamais été rangé)
+pub fn get(kind: SecretKind, scope: &str) -> Result<Option<SecretString>> {
+    match entry(kind, scope)?.get_password() {
+        Ok(value) => {
+            crate::redact::register(&value);
+            Ok(Some(value.into()))
+        }
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!("Impossible de lire {} dans le coffre: {}", kind.as_str(), e)),
+    }
+}
+
+/// Supprime un secret (sans erreur s'il n'existe pas)
+pub fn delete(kind: SecretKind, scope: &str) -> Result<()> {
+    match entry(kind, scope)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("Impossible de supprimer {} du coffre: {}", kind.as_str(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_account() {
+        assert_eq!(account(SecretKind::SshPrivateKey, "jellypi").unwrap(), "ssh_private_key:jellypi");
+        assert_eq!(account(SecretKind::AlldebridKey, DEFAULT_SCOPE).unwrap(), "alldebrid_key:default");
+        assert!(account(SecretKind::YggPasskey, "").is_err());
+        assert!(account(SecretKind::YggPasskey, "pi salon/../x").is_err());
+        assert_eq!(serde_json::to_value(SecretKind::CloudflareApiToken).unwrap(), "cloudflare_api_token");
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+
+use crate::ssh::SshTarget;
+use crate::template_engine::TemplateVars;
+
+// =============================================================================
+// Récupération des clés API des *arr (générées dans config.xml au 1er démarrage)
+// =============================================================================
+
+const HARVEST_ATTEMPTS: u32 = 24;
+const HARVEST_INTERVAL_SECS: u64 = 5;
+
+/// Affiche les config.xml des trois services, chacun précédé d'un marqueur "== service"
+const READ_CONFIGS_COMMAND: &str =
+    "for s in radarr sonarr prowlarr; do echo \"== $s\"; cat ~/media-stack/$s/config.xml 2>/dev/null; echo; done";
+
+/// Clés API Radarr/Sonarr/Prowlarr (chaîne vide si pas encore générée)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArrApiKeys {
+    pub radarr: String,
+    pub sonarr: String,
+    pub prowlarr: String,
+}
+
+impl ArrApiKeys {
+    pub fn is_complete(&self) -> bool {
+        !self.radarr.is_empty() && !self.sonarr.is_empty() && !self.prowlarr.is_empty()
+    }
+
+    /// Clé d'un service, None si absente (pratique pour save_pi_config)
+    pub fn get(&self, service: &str) -> Option<&str> {
+        let key = match service {
+            "radarr" => &self.radarr,
+            "sonarr" => &self.sonarr,
+            "prowlarr" => &self.prowlarr,
+            _ => return None,
+        };
+        Some(key.as_str()).filter(|k| !k.is_empty())
+    }
+
+    /// Expose les clés aux templates master_config ({{RADARR_API_KEY}}, ...)
+    pub fn apply_to(&self, vars: &mut TemplateVars) {
+        vars.set("RADARR_API_KEY", &self.radarr);
+        vars.set("SONARR_API_KEY", &self.sonarr);
+        vars.set("PROWLARR_API_KEY", &self.prowlarr);
+    }
+}
+
+/// Extrait <ApiKey> d'un config.xml *arr
+pub fn parse_config_xml_api_key(xml: &str) -> Option<String> {
+    let start = xml.find("<ApiKey>")? + "<ApiKey>".len();
+    let end = xml[start..].find("</ApiKey>")? + start;
+    Some(xml[start..end].trim().to_string()).filter(|k| !k.is_empty())
+}
+
+/// Découpe la sortie de READ_CONFIGS_COMMAND et extrait chaque clé
+pub fn parse_harvest_output(output: &str) -> ArrApiKeys {
+    let mut keys = ArrApiKeys::default();
+
+    for section in output.split("== ").skip(1) {
+        let (service, xml) = section.split_once('\n').unwrap_or((section, ""));
+        let key = parse_config_xml_api_key(xml).unwrap_or_default();
+        match service.trim() {
+            "radarr" => keys.radarr = key,
+            "sonarr" => keys.sonarr = key,
+            "prowlarr" => keys.prowlarr = key,
+            _ => {}
+        }
+    }
+
+    keys
+}
+
+/// Lit les clés API des *arr, en réessayant tant que les config.xml ne sont pas générés
+///
+/// Retourne les clés partielles si le délai expire, une erreur si aucune n'a été trouvée.
+/// L'appelant les expose aux templates (`apply_to`) et les sauvegarde dans Supabase (save_pi_config).
+pub async fn harvest_api_keys(target: &SshTarget<'_>) -> Result<ArrApiKeys> {
+    let mut keys = ArrApiKeys::default();
+
+    for attempt in 1..=HARVEST_ATTEMPTS {
+        match target.exec(READ_CONFIGS_COMMAND).await {
+            Ok(output) => keys = parse_harvest_output(&output),
+            Err(e) => tracing::warn!("SSH read failed (attempt {}/{}): {}", attempt, HARVEST_ATTEMPTS, e),
+        }
+
+        if keys.is_complete() {
+            break;
+        }
+
+        tracing::debug!("Waiting for config.xml files (attempt {}/{})...", attempt, HARVEST_ATTEMPTS);
+        crate::operations::sleep(std::time::Duration::from_secs(HARVEST_INTERVAL_SECS)).await?;
+    }
+
+    tracing::debug!(
+        "Radarr: {}..., Sonarr: {}..., Prowlarr: {}...",
+        keys.radarr.chars().take(8).collect::<String>(),
+        keys.sonarr.chars().take(8).collect::<String>(),
+        keys.prowlarr.chars().take(8).collect::<String>()
+    );
+
+    if keys == ArrApiKeys::default() {
+        return Err(anyhow!(
+            "Aucune clé API trouvée après {}s",
+            HARVEST_ATTEMPTS as u64 * HARVEST_INTERVAL_SECS
+        ));
+    }
+    if !keys.is_complete() {
+        tracing::warn!("⚠️ Some API keys are still missing");
+    }
+
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_harvest_output() {
+        let output = "== radarr\n<Config>\n  <ApiKey>abc123</ApiKey>\n</Config>\n\n== sonarr\n\n== prowlarr\n<Config><ApiKey> def456 </ApiKey></Config>\n";
+        let keys = parse_harvest_output(output);
+        assert_eq!(keys.radarr, "abc123");
+        assert_eq!(keys.sonarr, "");
+        assert_eq!(keys.prowlarr, "def456");
+        assert!(!keys.is_complete());
+        assert_eq!(keys.get("sonarr"), None);
+    }
+}
@@ -0,0 +1,70 @@
+use serde_json::{json, Value};
+
+// =============================================================================
+// Payloads et réponses communs aux *arr (client: `arr_client::ArrClient`)
+// =============================================================================
+
+/// Payload du client qBittorrent exposé par Decypharr
+/// `category_field`: "movieCategory" (Radarr) ou "tvCategory" (Sonarr)
+pub fn decypharr_client_payload(overrides: Option<&Value>, default_host: &str, category_field: &str, default_category: &str) -> Value {
+    let get = |key: &str| overrides.and_then(|o| o.get(key));
+
+    json!({
+        "name": get("name").and_then(|v| v.as_str()).unwrap_or("Decypharr"),
+        "implementation": "QBittorrent",
+        "configContract": "QBittorrentSettings",
+        "protocol": "torrent",
+        "enable": true,
+        "priority": 1,
+        "fields": [
+            { "name": "host", "value": get("host").and_then(|v| v.as_str()).unwrap_or(default_host) },
+            { "name": "port", "value": get("port").and_then(|v| v.as_u64()).unwrap_or(8282) },
+            { "name": "useSsl", "value": false },
+            { "name": category_field, "value": get("category").and_then(|v| v.as_str()).unwrap_or(default_category) }
+        ]
+    })
+}
+
+/// Copie les champs de `overrides` dans `base` (premier niveau seulement)
+pub fn merge_fields(base: &Value, overrides: &Value) -> Value {
+    let mut merged = base.clone();
+    if let (Some(target), Some(source)) = (merged.as_object_mut(), overrides.as_object()) {
+        for (key, value) in source {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+    merged
+}
+
+/// Messages d'une liste d'erreurs de validation *arr (`[{propertyName, errorMessage}]`)
+pub fn validation_messages(response: &Value) -> Vec<String> {
+    response
+        .as_array()
+        .map(|items| items.iter().filter_map(|e| e.get("errorMessage")?.as_str().map(String::from)).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decypharr_client_payload() {
+        let payload = decypharr_client_payload(None, "192.168.1.20", "movieCategory", "radarr");
+        assert_eq!(payload["name"], "Decypharr");
+        assert_eq!(payload["fields"][0]["value"], "192.168.1.20");
+        assert_eq!(payload["fields"][3]["name"], "movieCategory");
+
+        let overrides = json!({ "host": "decypharr", "category": "films" });
+        let payload = decypharr_client_payload(Some(&overrides), "192.168.1.20", "movieCategory", "radarr");
+        assert_eq!(payload["fields"][0]["value"], "decypharr");
+        assert_eq!(payload["fields"][3]["value"], "films");
+    }
+
+    #[test]
+    fn test_validation_messages() {
+        let errors = json!([{ "propertyName": "Path", "errorMessage": "Path is already configured" }]);
+        assert_eq!(validation_messages(&errors), vec!["Path is already configured"]);
+        assert!(validation_messages(&json!([{ "id": 1, "path": "/mnt" }])).is_empty());
+    }
+}
@@ -0,0 +1,517 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::ssh::SshTarget;
+use crate::tunnels;
+
+use super::arr::{merge_fields, validation_messages};
+use super::jellyseerr::{self, ArrServer};
+use super::read_arr_api_key;
+
+// =============================================================================
+// Clients HTTP typés (reqwest) pour les *arr et Jellyseerr
+// =============================================================================
+//
+// Les requêtes partent de l'ordinateur via un port forward SSH (localhost:<port
+// libre> -> Pi:<port service>): pas de JSON dans des scripts shell, réponses
+// désérialisées dans des modèles serde. Le tunnel est fermé quand le client est détruit.
+
+/// Erreurs des clients typés
+#[derive(Debug, thiserror::Error)]
+pub enum ArrClientError {
+    #[error("{service}: port forward SSH impossible ({reason})")]
+    Tunnel { service: &'static str, reason: String },
+    #[error("{service}: API key introuvable ({reason})")]
+    MissingApiKey { service: &'static str, reason: String },
+    #[error("{service}: requête {endpoint} échouée ({source})")]
+    Transport {
+        service: &'static str,
+        endpoint: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{service}: {endpoint} a répondu {status}: {body}")]
+    Http { service: &'static str, endpoint: String, status: u16, body: String },
+    #[error("{service}: {endpoint} refusé: {}", .messages.join("; "))]
+    Validation { service: &'static str, endpoint: String, messages: Vec<String> },
+    #[error("{service}: {reason}")]
+    Unexpected { service: &'static str, reason: String },
+    #[error("{service}: réponse {endpoint} invalide ({source})")]
+    Decode {
+        service: &'static str,
+        endpoint: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+pub type ClientResult<T> = std::result::Result<T, ArrClientError>;
+
+// -----------------------------------------------------------------------------
+// Modèles
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemStatus {
+    pub version: String,
+    #[serde(default)]
+    pub instance_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootFolder {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub path: String,
+}
+
+/// Champ de configuration d'un provider (`fields: [{name, value}]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadClient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub name: String,
+    pub implementation: String,
+    pub config_contract: String,
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    /// Autres propriétés (priority, protocol, tags...), renvoyées telles quelles au PUT
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Indexer {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub tags: Vec<u64>,
+}
+
+/// Application synchronisée par Prowlarr (Radarr, Sonarr...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Application {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub sync_level: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: u64,
+    pub label: String,
+}
+
+// -----------------------------------------------------------------------------
+// Transport commun
+// -----------------------------------------------------------------------------
+
+/// Tunnel localhost -> Pi, fermé à la destruction
+struct PortForward {
+    id: String,
+    local_port: u16,
+}
+
+impl PortForward {
+    async fn open(target: &SshTarget<'_>, service: &'static str, remote_port: u16) -> ClientResult<Self> {
+        let (host, username, password, private_key) = match *target {
+            SshTarget::Key { host, username, private_key } => (host, username, None, Some(private_key)),
+            SshTarget::Password { host, username, password } => (host, username, Some(password), None),
+        };
+
+        let info = tunnels::open_tunnel(host, username, password, private_key, 0, "127.0.0.1", remote_port)
+            .await
+            .map_err(|e| ArrClientError::Tunnel { service, reason: e.to_string() })?;
+        Ok(Self { id: info.id, local_port: info.local_port })
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        let _ = tunnels::stop_tunnel(&self.id);
+    }
+}
+
+/// Client HTTP d'un service, via son tunnel
+struct ApiTransport {
+    service: &'static str,
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    _forward: PortForward,
+}
+
+impl ApiTransport {
+    async fn open(
+        target: &SshTarget<'_>,
+        service: &'static str,
+        port: u16,
+        api_prefix: &str,
+        api_key: String,
+    ) -> ClientResult<Self> {
+        let forward = PortForward::open(target, service, port).await?;
+        Ok(Self {
+            service,
+            http: reqwest::Client::new(),
+            base_url: format!("http://127.0.0.1:{}{}", forward.local_port, api_prefix),
+            api_key,
+            _forward: forward,
+        })
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder, endpoint: &str) -> ClientResult<T> {
+        let transport = |source| ArrClientError::Transport { service: self.service, endpoint: endpoint.to_string(), source };
+
+        let response = request
+            .header("X-Api-Key", &self.api_key)
+            .send()
+            .await
+            .map_err(transport)?;
+        let status = response.status();
+        let body = response.text().await.map_err(transport)?;
+
+        if !status.is_success() {
+            let messages = serde_json::from_str::<Value>(&body)
+                .map(|value| validation_messages(&value))
+                .unwrap_or_default();
+            return Err(if messages.is_empty() {
+                ArrClientError::Http { service: self.service, endpoint: endpoint.to_string(), status: status.as_u16(), body }
+            } else {
+                ArrClientError::Validation { service: self.service, endpoint: endpoint.to_string(), messages }
+            });
+        }
+
+        // Certaines routes (PUT /config, POST /settings/main...) répondent sans corps
+        let body = if body.trim().is_empty() { "null" } else { body.as_str() };
+        serde_json::from_str(body).map_err(|source| ArrClientError::Decode {
+            service: self.service,
+            endpoint: endpoint.to_string(),
+            source,
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> ClientResult<T> {
+        let request = self.http.get(format!("{}{}", self.base_url, endpoint));
+        self.send(request, endpoint).await
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> ClientResult<T> {
+        let request = self.http.post(format!("{}{}", self.base_url, endpoint)).json(body);
+        self.send(request, endpoint).await
+    }
+
+    async fn put<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> ClientResult<T> {
+        let request = self.http.put(format!("{}{}", self.base_url, endpoint)).json(body);
+        self.send(request, endpoint).await
+    }
+}
+
+/// Ouvre le transport d'un *arr (API key lue dans config.xml)
+async fn open_arr(target: &SshTarget<'_>, service: &'static str, port: u16, api_prefix: &str) -> ClientResult<ApiTransport> {
+    let api_key = read_arr_api_key(target, service)
+        .await
+        .map_err(|e| ArrClientError::MissingApiKey { service, reason: e.to_string() })?;
+    let transport = ApiTransport::open(target, service, port, api_prefix, api_key).await?;
+
+    let status: SystemStatus = transport.get("/system/status").await?;
+    tracing::info!("✅ {} {} reachable via port forward", super::display_name(service), status.version);
+    Ok(transport)
+}
+
+// -----------------------------------------------------------------------------
+// Clients
+// -----------------------------------------------------------------------------
+
+/// Port et préfixe d'API d'un *arr (Radarr/Sonarr: /api/v3, Prowlarr/Lidarr: /api/v1)
+fn arr_endpoint(service: &str) -> (u16, &'static str) {
+    match service {
+        "radarr" => (crate::config::ports::RADARR, "/api/v3"),
+        "sonarr" => (crate::config::ports::SONARR, "/api/v3"),
+        "prowlarr" => (crate::config::ports::PROWLARR, "/api/v1"),
+        "lidarr" => (crate::config::ports::LIDARR, "/api/v1"),
+        _ => (crate::config::ports::READARR, "/api/v1"),
+    }
+}
+
+/// Client commun aux *arr ("radarr", "sonarr", "prowlarr", "lidarr", "readarr")
+pub struct ArrClient {
+    service: &'static str,
+    api: ApiTransport,
+}
+
+impl ArrClient {
+    /// Ouvre le port forward et vérifie /system/status (API key lue dans config.xml)
+    pub async fn connect(target: &SshTarget<'_>, service: &'static str) -> ClientResult<Self> {
+        let (port, api_prefix) = arr_endpoint(service);
+        Ok(Self { service, api: open_arr(target, service, port, api_prefix).await? })
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> ClientResult<T> {
+        self.api.get(endpoint).await
+    }
+
+    pub async fn post<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> ClientResult<T> {
+        self.api.post(endpoint, body).await
+    }
+
+    pub async fn put<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> ClientResult<T> {
+        self.api.put(endpoint, body).await
+    }
+
+    fn unexpected(&self, reason: String) -> ArrClientError {
+        ArrClientError::Unexpected { service: self.service, reason }
+    }
+
+    pub async fn root_folders(&self) -> ClientResult<Vec<RootFolder>> {
+        self.get("/rootfolder").await
+    }
+
+    /// Crée le root folder s'il n'existe pas encore
+    pub async fn ensure_root_folder(&self, path: &str) -> ClientResult<()> {
+        self.ensure_root_folder_with(&json!({ "path": path })).await
+    }
+
+    /// Idem avec un payload complet (Lidarr/Readarr: profils par défaut du dossier)
+    pub async fn ensure_root_folder_with(&self, payload: &Value) -> ClientResult<()> {
+        let path = payload.get("path").and_then(|v| v.as_str()).unwrap_or_default();
+        if self.root_folders().await?.iter().any(|f| f.path.trim_end_matches('/') == path.trim_end_matches('/')) {
+            tracing::info!("[{}] Root folder {} already exists", self.service, path);
+            return Ok(());
+        }
+
+        self.post::<_, Value>("/rootfolder", payload).await?;
+        tracing::info!("[{}] Root folder {} created", self.service, path);
+        Ok(())
+    }
+
+    /// Met à jour les profils de qualité par nom, crée ceux qui n'existent pas (depuis le schéma)
+    pub async fn apply_quality_profiles(&self, profiles: &[Value]) -> ClientResult<()> {
+        self.apply_profiles("/qualityprofile", profiles).await
+    }
+
+    /// Met à jour ou crée des profils nommés (`/qualityprofile`, `/languageprofile`)
+    pub async fn apply_profiles(&self, endpoint: &str, profiles: &[Value]) -> ClientResult<()> {
+        let existing: Vec<Value> = self.get(endpoint).await?;
+
+        for profile in profiles {
+            let name = profile
+                .get("name")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| self.unexpected(format!("profil {} sans nom", endpoint)))?;
+
+            match existing.iter().find(|p| p.get("name").and_then(|v| v.as_str()) == Some(name)) {
+                Some(current) => {
+                    let id = current.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+                    self.put::<_, Value>(&format!("{}/{}", endpoint, id), &merge_fields(current, profile)).await?;
+                    tracing::info!("[{}] Profile {} updated ({})", self.service, name, endpoint);
+                }
+                None => {
+                    let schema: Value = self.get(&format!("{}/schema", endpoint)).await?;
+                    self.post::<_, Value>(endpoint, &merge_fields(&schema, profile)).await?;
+                    tracing::info!("[{}] Profile {} created ({})", self.service, name, endpoint);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fusionne des réglages dans une section /config (naming, mediamanagement, ...)
+    pub async fn update_config(&self, section: &str, overrides: &Value) -> ClientResult<()> {
+        let current: Value = self.get(&format!("/config/{}", section)).await?;
+        let id = current
+            .get("id")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| self.unexpected(format!("ID config/{} absent", section)))?;
+
+        self.put::<_, Value>(&format!("/config/{}/{}", section, id), &merge_fields(&current, overrides)).await?;
+        tracing::info!("[{}] config/{} updated", self.service, section);
+        Ok(())
+    }
+
+    /// Crée ou met à jour (même nom) une ressource: /indexer, /applications, /indexerProxy...
+    pub async fn upsert_by_name(&self, endpoint: &str, payload: &Value) -> ClientResult<()> {
+        let name = payload.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        let existing: Vec<Value> = self.get(endpoint).await?;
+        let existing_id = existing
+            .iter()
+            .find(|c| c.get("name").and_then(|v| v.as_str()) == Some(name))
+            .and_then(|c| c.get("id")?.as_u64());
+
+        match existing_id {
+            Some(id) => {
+                let mut payload = payload.clone();
+                payload["id"] = json!(id);
+                self.put::<_, Value>(&format!("{}/{}", endpoint, id), &payload).await?;
+                tracing::info!("[{}] {} updated ({})", self.service, name, endpoint);
+            }
+            None => {
+                self.post::<_, Value>(endpoint, payload).await?;
+                tracing::info!("[{}] {} created ({})", self.service, name, endpoint);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// ID d'un tag, créé s'il n'existe pas
+    pub async fn ensure_tag(&self, label: &str) -> ClientResult<u64> {
+        let tags: Vec<Tag> = self.get("/tag").await?;
+        if let Some(tag) = tags.iter().find(|t| t.label == label) {
+            return Ok(tag.id);
+        }
+
+        let tag: Tag = self.post("/tag", &json!({ "label": label })).await?;
+        Ok(tag.id)
+    }
+
+    pub async fn download_clients(&self) -> ClientResult<Vec<DownloadClient>> {
+        self.get("/downloadclient").await
+    }
+
+    /// Crée le client de téléchargement, ou le remplace s'il existe déjà (même nom)
+    pub async fn upsert_download_client(&self, client: &DownloadClient) -> ClientResult<DownloadClient> {
+        let existing = self.download_clients().await?.into_iter().find(|c| c.name == client.name);
+        match existing.and_then(|c| c.id) {
+            Some(id) => {
+                let mut client = client.clone();
+                client.id = Some(id);
+                self.put(&format!("/downloadclient/{}", id), &client).await
+            }
+            None => self.post("/downloadclient", client).await,
+        }
+    }
+
+    /// Indexers (Prowlarr)
+    pub async fn indexers(&self) -> ClientResult<Vec<Indexer>> {
+        self.get("/indexer").await
+    }
+
+    /// Applications synchronisées (Prowlarr)
+    pub async fn applications(&self) -> ClientResult<Vec<Application>> {
+        self.get("/applications").await
+    }
+}
+
+pub struct JellyseerrClient {
+    api: ApiTransport,
+}
+
+impl JellyseerrClient {
+    /// API key lue dans settings.json
+    pub async fn connect(target: &SshTarget<'_>) -> ClientResult<Self> {
+        let missing = |e: anyhow::Error| ArrClientError::MissingApiKey { service: "jellyseerr", reason: e.to_string() };
+        let settings = target.exec(jellyseerr::READ_SETTINGS_COMMAND).await.map_err(missing)?;
+        let api_key = jellyseerr::parse_settings_api_key(&settings).map_err(missing)?;
+        Ok(Self { api: ApiTransport::open(target, "jellyseerr", crate::config::ports::JELLYSEERR, "/api/v1", api_key).await? })
+    }
+
+    /// Nombre de résultats d'une recherche (vérifie l'accès à TMDB)
+    pub async fn search_results(&self, query: &str) -> ClientResult<usize> {
+        let endpoint = format!("/search?{}", form_query(&[("query", query)]));
+        let response: Value = self.api.get(&endpoint).await?;
+        Ok(response.get("results").and_then(|r| r.as_array()).map(|r| r.len()).unwrap_or(0))
+    }
+
+    /// Demandes des utilisateurs (`filter`: pending, approved, processing, available, all)
+    pub async fn list_requests(&self, filter: &str, take: usize) -> ClientResult<Value> {
+        self.api.get(&format!("/request?take={}&skip=0&sort=added&filter={}", take, filter)).await
+    }
+
+    /// Titre d'un film ("movie") ou d'une série ("tv") depuis son id TMDB
+    pub async fn media_title(&self, media_type: &str, tmdb_id: u64) -> ClientResult<String> {
+        let value: Value = self.api.get(&format!("/{}/{}", media_type, tmdb_id)).await?;
+        value["title"]
+            .as_str()
+            .or_else(|| value["name"].as_str())
+            .map(String::from)
+            .ok_or_else(|| ArrClientError::Unexpected {
+                service: "jellyseerr",
+                reason: format!("titre introuvable pour {} {}", media_type, tmdb_id),
+            })
+    }
+
+    /// Serveurs enregistrés ("radarr" ou "sonarr")
+    pub async fn list_arr_servers(&self, service: &str) -> ClientResult<Vec<ArrServer>> {
+        self.api.get(&format!("/settings/{}", service)).await
+    }
+
+    /// Enregistre un serveur, ou met à jour celui qui existe déjà (pas de doublon)
+    pub async fn upsert_arr_server(&self, service: &str, payload: &Value) -> ClientResult<()> {
+        let existing = self.list_arr_servers(service).await?;
+
+        match jellyseerr::find_existing_server(&existing, payload) {
+            Some(id) => {
+                tracing::info!("{} server already registered (id {}), updating", service, id);
+                self.api.put::<_, Value>(&format!("/settings/{}/{}", service, id), payload).await?;
+            }
+            None => {
+                tracing::info!("Registering {} server", service);
+                self.api.post::<_, Value>(&format!("/settings/{}", service), payload).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Met à jour les réglages généraux (mise à jour partielle)
+    pub async fn update_main_settings(&self, payload: &Value) -> ClientResult<()> {
+        self.api.post::<_, Value>("/settings/main", payload).await?;
+        Ok(())
+    }
+}
+
+/// Paramètres de requête encodés (`a=b&c=d`)
+fn form_query(params: &[(&str, &str)]) -> String {
+    let mut query = reqwest::Url::parse("http://localhost/").expect("URL constante");
+    query.query_pairs_mut().extend_pairs(params);
+    query.query().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models() {
+        let clients: Vec<DownloadClient> = serde_json::from_str(
+            r#"[{"id": 1, "name": "Decypharr", "implementation": "QBittorrent", "configContract": "QBittorrentSettings",
+                "enable": true, "priority": 1, "fields": [{"name": "host", "value": "decypharr"}, {"name": "port", "value": 8282}]}]"#,
+        )
+        .unwrap();
+        assert_eq!(clients[0].fields[1].value, 8282);
+
+        let mut client = clients[0].clone();
+        client.id = None;
+        let payload = serde_json::to_value(&client).unwrap();
+        assert!(payload.get("id").is_none());
+        assert_eq!(payload["configContract"], "QBittorrentSettings");
+        assert_eq!(payload["priority"], 1);
+
+        let folder: RootFolder = serde_json::from_str(r#"{"id": 2, "path": "/mnt/decypharr/movies", "freeSpace": 1024}"#).unwrap();
+        assert_eq!(serde_json::to_value(&folder).unwrap(), serde_json::json!({ "id": 2, "path": "/mnt/decypharr/movies" }));
+
+        assert_eq!(arr_endpoint("prowlarr"), (9696, "/api/v1"));
+        assert_eq!(arr_endpoint("sonarr"), (8989, "/api/v3"));
+        assert_eq!(arr_endpoint("readarr"), (8787, "/api/v1"));
+        assert_eq!(form_query(&[("query", "Le Parrain & co")]), "query=Le+Parrain+%26+co");
+    }
+}
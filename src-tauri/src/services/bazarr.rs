@@ -0,0 +1,234 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::ssh::SshTarget;
+use crate::template_engine::TemplateVars;
+
+// =============================================================================
+// Bazarr: liaison Radarr/Sonarr, langues et fournisseurs de sous-titres
+// =============================================================================
+//
+// Section master_config (bazarr_config):
+//   {
+//     "languages": ["fr", "en"],
+//     "profile_name": "Français",
+//     "providers": ["opensubtitlescom", "podnapisi"],
+//     "provider_settings": { "opensubtitlescom": { "username": "...", "password": "..." } },
+//     "radarr": { "ip": "...", "port": 7878 },
+//     "sonarr": { "ip": "...", "port": 8989 }
+//   }
+//
+// L'API settings de Bazarr attend un formulaire (settings-<section>-<clé>),
+// les listes étant envoyées en répétant la clé.
+
+const BAZARR_PORT: u16 = crate::config::ports::BAZARR;
+
+fn bazarr_url() -> String {
+    format!("http://localhost:{}", BAZARR_PORT)
+}
+
+/// Lit l'API key de Bazarr depuis config.yaml
+async fn read_api_key(target: &SshTarget<'_>) -> Result<String> {
+    Ok(target
+        .exec("grep -oP '(?<=apikey: )[^\\s]+' ~/media-stack/bazarr/config/config.yaml 2>/dev/null || echo ''")
+        .await?
+        .trim()
+        .to_string())
+}
+
+/// Attend que Bazarr réponde puis lit l'API key générée au premier démarrage (max 2 min)
+pub async fn wait_for_api_key(target: &SshTarget<'_>) -> Result<String> {
+    super::wait_for_api(target, "bazarr", BAZARR_PORT, "/", |body| !body.trim().is_empty(), Duration::from_secs(120))
+        .await?;
+
+    let api_key = read_api_key(target).await?;
+    if api_key.is_empty() {
+        return Err(anyhow!("API key Bazarr introuvable dans config.yaml"));
+    }
+    Ok(api_key)
+}
+
+/// Champs du formulaire de liaison d'un *arr (`section`: "radarr" ou "sonarr")
+fn arr_fields(config: &Value, section: &str, default_ip: &str, default_port: u64, api_key: &str) -> Vec<(String, String)> {
+    let overrides = config.get(section);
+    let ip = overrides.and_then(|o| o.get("ip")?.as_str()).unwrap_or(default_ip);
+    let port = overrides.and_then(|o| o.get("port")?.as_u64()).unwrap_or(default_port);
+
+    vec![
+        (format!("settings-general-use_{}", section), "true".to_string()),
+        (format!("settings-{}-ip", section), ip.to_string()),
+        (format!("settings-{}-port", section), port.to_string()),
+        (format!("settings-{}-apikey", section), api_key.to_string()),
+        (format!("settings-{}-base_url", section), String::new()),
+        (format!("settings-{}-ssl", section), "false".to_string()),
+    ]
+}
+
+/// Profil de langues unique, utilisé par défaut pour les films et les séries
+fn language_profile(name: &str, languages: &[&str]) -> Value {
+    let items: Vec<Value> = languages
+        .iter()
+        .enumerate()
+        .map(|(i, code)| {
+            json!({
+                "id": i + 1,
+                "language": code,
+                "audio_exclude": "False",
+                "hi": "False",
+                "forced": "False"
+            })
+        })
+        .collect();
+
+    json!([{
+        "profileId": 1,
+        "name": name,
+        "items": items,
+        "cutoff": null,
+        "mustContain": [],
+        "mustNotContain": [],
+        "originalFormat": false
+    }])
+}
+
+/// Formulaire settings complet: *arr liés (clés récoltées), langues et fournisseurs
+pub fn settings_form(config: &Value, vars: &TemplateVars) -> Vec<(String, String)> {
+    let pi_ip = vars.get("PI_IP").unwrap_or("localhost");
+    let mut form = Vec::new();
+
+    for (section, port, key_var) in [("radarr", crate::config::ports::RADARR, "RADARR_API_KEY"), ("sonarr", crate::config::ports::SONARR, "SONARR_API_KEY")] {
+        match vars.get(key_var).filter(|k| !k.is_empty()) {
+            Some(api_key) => form.extend(arr_fields(config, section, pi_ip, port.into(), api_key)),
+            None => tracing::warn!("⚠️ {} not set, {} not linked", key_var, section),
+        }
+    }
+
+    let languages: Vec<&str> = config
+        .get("languages")
+        .and_then(|v| v.as_array())
+        .map(|langs| langs.iter().filter_map(|l| l.as_str()).collect())
+        .unwrap_or_default();
+    if !languages.is_empty() {
+        let profile_name = config.get("profile_name").and_then(|v| v.as_str()).unwrap_or("Default");
+        for code in &languages {
+            form.push(("languages-enabled".to_string(), code.to_string()));
+        }
+        form.push(("languages-profiles".to_string(), language_profile(profile_name, &languages).to_string()));
+        for kind in ["serie", "movie"] {
+            form.push((format!("settings-general-{}_default_enabled", kind), "true".to_string()));
+            form.push((format!("settings-general-{}_default_profile", kind), "1".to_string()));
+        }
+    }
+
+    if let Some(providers) = config.get("providers").and_then(|v| v.as_array()) {
+        for provider in providers.iter().filter_map(|p| p.as_str()) {
+            form.push(("settings-general-enabled_providers".to_string(), provider.to_string()));
+        }
+    }
+
+    if let Some(provider_settings) = config.get("provider_settings").and_then(|v| v.as_object()) {
+        for (provider, settings) in provider_settings {
+            for (key, value) in settings.as_object().into_iter().flatten() {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                form.push((format!("settings-{}-{}", provider, key), value));
+            }
+        }
+    }
+
+    form
+}
+
+/// Encode le formulaire (application/x-www-form-urlencoded)
+fn encode_form(form: &[(String, String)]) -> Result<String> {
+    let url = reqwest::Url::parse_with_params(&bazarr_url(), form)?;
+    Ok(url.query().unwrap_or_default().to_string())
+}
+
+/// Applique la configuration Bazarr depuis master_config (avec clé privée)
+pub async fn apply_config(
+    host: &str,
+    username: &str,
+    private_key: &str,
+    config: &Value,
+    vars: &TemplateVars,
+) -> Result<()> {
+    tracing::debug!("Applying master configuration...");
+
+    configure(SshTarget::Key { host, username, private_key }, config, vars).await
+}
+
+/// Applique la configuration Bazarr depuis master_config (avec mot de passe)
+pub async fn apply_config_password(
+    host: &str,
+    username: &str,
+    password: &str,
+    config: &Value,
+    vars: &TemplateVars,
+) -> Result<()> {
+    tracing::debug!("Applying master configuration...");
+
+    configure(SshTarget::Password { host, username, password }, config, vars).await
+}
+
+pub(super) async fn configure(target: SshTarget<'_>, config: &Value, vars: &TemplateVars) -> Result<()> {
+    let api_key = wait_for_api_key(&target).await?;
+
+    let form = settings_form(config, vars);
+    if form.is_empty() {
+        tracing::info!("Nothing to configure");
+        return Ok(());
+    }
+
+    // Corps URL-encodé: aucun caractère spécial pour le shell
+    let cmd = format!(
+        "curl -s -o /dev/null -w '%{{http_code}}' -X POST '{}/api/system/settings' -H 'X-API-KEY: {}' \
+         -H 'Content-Type: application/x-www-form-urlencoded' --data '{}'",
+        bazarr_url(),
+        api_key,
+        encode_form(&form)?
+    );
+    let status = target.exec(&cmd).await?;
+    if !matches!(status.trim(), "200" | "204") {
+        return Err(anyhow!("Bazarr a refusé les réglages (HTTP {})", status.trim()));
+    }
+
+    tracing::info!("✅ Configuration applied ({} settings)", form.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_form() {
+        let mut vars = TemplateVars::new();
+        vars.set("PI_IP", "192.168.1.20");
+        vars.set("RADARR_API_KEY", "radarr-key");
+        vars.set("SONARR_API_KEY", "");
+
+        let config = json!({
+            "languages": ["fr", "en"],
+            "providers": ["opensubtitlescom"],
+            "provider_settings": { "opensubtitlescom": { "username": "jelly" } },
+            "radarr": { "ip": "radarr" }
+        });
+        let form = settings_form(&config, &vars);
+        let get = |key: &str| form.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(get("settings-radarr-ip"), ["radarr"]);
+        assert_eq!(get("settings-radarr-apikey"), ["radarr-key"]);
+        assert!(get("settings-sonarr-apikey").is_empty());
+        assert_eq!(get("languages-enabled"), ["fr", "en"]);
+        assert_eq!(get("settings-general-enabled_providers"), ["opensubtitlescom"]);
+        assert_eq!(get("settings-opensubtitlescom-username"), ["jelly"]);
+
+        let encoded = encode_form(&form).unwrap();
+        assert!(encoded.contains("settings-radarr-port=7878"));
+        assert!(!encoded.contains('\''));
+    }
+}
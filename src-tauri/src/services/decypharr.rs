@@ -0,0 +1,264 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::ssh::SshTarget;
+use crate::template_engine::TemplateVars;
+
+// =============================================================================
+// Decypharr: AllDebrid, endpoint compatible qBittorrent, montage WebDAV/rclone
+// =============================================================================
+//
+// La config par défaut est complétée par la section master_config (decypharr_config,
+// fusion récursive, les tableaux sont remplacés). La clé AllDebrid et les *arr
+// viennent des variables de template (ALLDEBRID_API_KEY, RADARR_API_KEY, ...).
+// Le montage rclone est la racine des médias de l'installation (MediaPaths::root).
+
+const QBIT_PORT: u64 = 8282;
+
+const ALLOWED_FILE_TYPES: [&str; 66] = [
+    "3gp", "ac3", "aiff", "alac", "amr", "ape", "asf", "asx", "avc", "avi", "bin", "bivx", "dat", "divx", "dts", "dv",
+    "dvr-ms", "flac", "fli", "flv", "ifo", "m2ts", "m2v", "m3u", "m4a", "m4p", "m4v", "mid", "midi", "mk3d", "mka",
+    "mkv", "mov", "mp2", "mp3", "mp4", "mpa", "mpeg", "mpg", "nrg", "nsv", "nuv", "ogg", "ogm", "ogv", "pva", "qt",
+    "ra", "rm", "rmvb", "strm", "svq3", "ts", "ty", "viv", "vob", "voc", "vp3", "wav", "webm", "wma", "wmv", "wpl",
+    "wtv", "wv", "xvid",
+];
+
+/// Config Decypharr par défaut (AllDebrid + rclone sur la racine des médias)
+fn default_config(alldebrid_api_key: &str, mount_path: &str) -> Value {
+    json!({
+        "url_base": "/",
+        "port": QBIT_PORT.to_string(),
+        "log_level": "info",
+        "debrids": [{
+            "name": "alldebrid",
+            "api_key": alldebrid_api_key,
+            "download_api_keys": [alldebrid_api_key],
+            "folder": format!("{}/alldebrid/__all__", mount_path),
+            "rate_limit": "250/minute",
+            "unpack_rar": true,
+            "minimum_free_slot": 1,
+            "use_webdav": true,
+            "torrents_refresh_interval": "15s",
+            "download_links_refresh_interval": "40m",
+            "workers": 200,
+            "auto_expire_links_after": "3d",
+            "folder_naming": "arr"
+        }],
+        "qbittorrent": {
+            "download_folder": format!("{}/qbit", mount_path),
+            "refresh_interval": 15,
+            "skip_pre_cache": true
+        },
+        "arrs": [],
+        "repair": {
+            "enabled": true,
+            "auto_process": true,
+            "use_webdav": true,
+            "workers": 100,
+            "strategy": "per_torrent",
+            "reinsert": true,
+            "interval": "5m"
+        },
+        "webdav": {},
+        "rclone": {
+            "enabled": true,
+            "mount_path": mount_path,
+            "rc_port": "5572",
+            "vfs_cache_mode": "full",
+            "vfs_cache_max_size": "10G",
+            "vfs_cache_max_age": "2h",
+            "vfs_cache_poll_interval": "1m",
+            "vfs_read_chunk_size": "64M",
+            "vfs_read_chunk_size_limit": "128M",
+            "vfs_read_ahead": "512M",
+            "buffer_size": "64M",
+            "async_read": true,
+            "transfers": 2,
+            "uid": 1000,
+            "gid": 1000,
+            "attr_timeout": "1s",
+            "dir_cache_time": "10s",
+            "log_level": "INFO"
+        },
+        "allowed_file_types": ALLOWED_FILE_TYPES.as_slice(),
+        "use_auth": true
+    })
+}
+
+/// Fusion récursive des objets; les autres valeurs (tableaux compris) sont remplacées
+fn deep_merge(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                deep_merge(base.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides.clone(),
+    }
+}
+
+/// Entrées `arrs` (Radarr/Sonarr) dont les clés API sont connues
+fn arr_entries(vars: &TemplateVars) -> Vec<Value> {
+    let pi_ip = vars.get("PI_IP").unwrap_or("localhost");
+
+    [("radarr", crate::config::ports::RADARR, "RADARR_API_KEY"), ("tv-sonarr", crate::config::ports::SONARR, "SONARR_API_KEY")]
+        .iter()
+        .filter_map(|(name, port, key_var)| {
+            let token = vars.get(key_var).filter(|k| !k.is_empty())?;
+            Some(json!({
+                "name": name,
+                "host": format!("http://{}:{}", pi_ip, port),
+                "token": token,
+                "download_uncached": false,
+                "flatten": true,
+                "cleanup": true
+            }))
+        })
+        .collect()
+}
+
+/// Config complète: défaut, surcharges master_config, puis clé AllDebrid et *arr injectés
+pub fn build_config(overrides: Option<&Value>, vars: &TemplateVars, mount_path: &str) -> Value {
+    let alldebrid_api_key = vars.get("ALLDEBRID_API_KEY").unwrap_or_default();
+    let mut config = default_config(alldebrid_api_key, mount_path.trim_end_matches('/'));
+
+    // Fichiers du montage rclone au même propriétaire que les conteneurs (PUID/PGID)
+    for (field, var) in [("uid", "PUID"), ("gid", "PGID")] {
+        if let Some(id) = vars.get(var).and_then(|v| v.parse::<u32>().ok()) {
+            config["rclone"][field] = json!(id);
+        }
+    }
+
+    if let Some(overrides) = overrides {
+        deep_merge(&mut config, overrides);
+    }
+
+    // La clé de l'utilisateur prime sur un éventuel placeholder vide de la master_config
+    if !alldebrid_api_key.is_empty() {
+        if let Some(debrid) = config["debrids"]
+            .as_array_mut()
+            .and_then(|debrids| debrids.iter_mut().find(|d| d["name"] == "alldebrid"))
+        {
+            if debrid["api_key"].as_str().unwrap_or_default().is_empty() {
+                debrid["api_key"] = json!(alldebrid_api_key);
+                debrid["download_api_keys"] = json!([alldebrid_api_key]);
+            }
+        }
+    }
+
+    let arrs = arr_entries(vars);
+    if !arrs.is_empty() {
+        config["arrs"] = Value::Array(arrs);
+    }
+
+    config
+}
+
+/// Écrit config.json et redémarre le conteneur (en arrière-plan, évite les timeouts SSH)
+pub async fn write_config(target: &SshTarget<'_>, config: &Value) -> Result<()> {
+    let content = serde_json::to_string_pretty(config)?;
+    crate::delta_sync::push_file(target, "~/media-stack/decypharr/config.json", &content).await?;
+    target.exec("nohup docker restart decypharr > /dev/null 2>&1 &").await?;
+    tracing::info!("config.json written, container restarting");
+    Ok(())
+}
+
+/// Le montage rclone est-il visible sur l'hôte ?
+pub async fn mount_present(target: &SshTarget<'_>, mount_path: &str) -> bool {
+    let mount_path = mount_path.trim_end_matches('/');
+    let check = format!(
+        "grep -qsF -- {mount} /proc/mounts && ls -d {dir} >/dev/null 2>&1 && echo MOUNTED || echo WAIT",
+        mount = crate::ssh::shell_quote(&format!(" {} ", mount_path)),
+        dir = crate::ssh::shell_quote(&format!("{}/alldebrid", mount_path))
+    );
+    target.exec(&check).await.unwrap_or_default().contains("MOUNTED")
+}
+
+/// Attend que le montage rclone apparaisse sur l'hôte (max 2 min)
+pub async fn verify_mounts(target: &SshTarget<'_>, mount_path: &str) -> Result<()> {
+    for attempt in 1..=24 {
+        if mount_present(target, mount_path).await {
+            tracing::info!("✅ {} mounted", mount_path);
+            return Ok(());
+        }
+        tracing::debug!("Waiting for {} mount ({}/24)...", mount_path, attempt);
+        crate::operations::sleep(std::time::Duration::from_secs(5)).await?;
+    }
+
+    Err(anyhow!("Montage {} absent après 2 minutes (clé AllDebrid invalide ?)", mount_path))
+}
+
+/// Applique la configuration Decypharr depuis master_config (avec clé privée)
+pub async fn apply_config(
+    host: &str,
+    username: &str,
+    private_key: &str,
+    config: &Value,
+    vars: &TemplateVars,
+) -> Result<()> {
+    tracing::debug!("Applying master configuration...");
+
+    configure(SshTarget::Key { host, username, private_key }, config, vars).await
+}
+
+/// Applique la configuration Decypharr depuis master_config (avec mot de passe)
+pub async fn apply_config_password(
+    host: &str,
+    username: &str,
+    password: &str,
+    config: &Value,
+    vars: &TemplateVars,
+) -> Result<()> {
+    tracing::debug!("Applying master configuration...");
+
+    configure(SshTarget::Password { host, username, password }, config, vars).await
+}
+
+pub(super) async fn configure(target: SshTarget<'_>, config: &Value, vars: &TemplateVars) -> Result<()> {
+    if vars.get("ALLDEBRID_API_KEY").unwrap_or_default().is_empty() {
+        return Err(anyhow!("Clé API AllDebrid manquante"));
+    }
+
+    let mount_path = crate::master_config::installed_media_paths(&target).await.root;
+    write_config(&target, &build_config(Some(config), vars, &mount_path)).await?;
+
+    // Le montage dépend d'AllDebrid: son absence n'empêche pas la suite de la configuration
+    if let Err(e) = verify_mounts(&target, &mount_path).await {
+        tracing::warn!("⚠️ {}", e);
+    }
+
+    tracing::info!("✅ Configuration applied");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_config() {
+        let mut vars = TemplateVars::new();
+        vars.set("PI_IP", "192.168.1.20");
+        vars.set("ALLDEBRID_API_KEY", "ad-key");
+        vars.set("RADARR_API_KEY", "radarr-key");
+        vars.set("PUID", "1001");
+
+        let overrides = json!({
+            "debrids": [{ "name": "alldebrid", "api_key": "", "folder": "/mnt/decypharr/alldebrid/__all__" }],
+            "rclone": { "vfs_cache_max_size": "4G" }
+        });
+        let config = build_config(Some(&overrides), &vars, "/mnt/decypharr");
+
+        assert_eq!(config["debrids"][0]["api_key"], "ad-key");
+        assert_eq!(config["rclone"]["vfs_cache_max_size"], "4G");
+        assert_eq!(config["rclone"]["mount_path"], "/mnt/decypharr");
+        assert_eq!(config["rclone"]["uid"], 1001);
+        assert_eq!(config["rclone"]["gid"], 1000);
+        assert_eq!(config["arrs"].as_array().unwrap().len(), 1);
+        assert_eq!(config["arrs"][0]["host"], "http://192.168.1.20:7878");
+
+        let config = build_config(None, &vars, "/srv/media/");
+        assert_eq!(config["rclone"]["mount_path"], "/srv/media");
+        assert_eq!(config["qbittorrent"]["download_folder"], "/srv/media/qbit");
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::ssh::SshTarget;
+
+use super::arr_client::ArrClient;
+use super::ServiceNotReady;
+
+// =============================================================================
+// FlareSolverr: vérification et branchement dans Prowlarr (proxy d'indexers)
+// =============================================================================
+//
+// Les indexers protégés par Cloudflare (YGG...) ne répondent qu'à travers
+// FlareSolverr: chaque échec est remonté avec sa cause pour que l'utilisateur
+// sache pourquoi ses recherches échouent.
+
+/// URL vue depuis le conteneur Prowlarr (réseau media-network)
+pub const DEFAULT_URL: &str = "http://flaresolverr:8191/";
+pub const TAG: &str = "flaresolverr";
+/// Variable de template: "true" si FlareSolverr fait partie du stack du Pi
+pub const ENABLED_VAR: &str = "FLARESOLVERR_ENABLED";
+
+/// Cause d'un échec FlareSolverr
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlareSolverrIssue {
+    /// Le conteneur n'existe pas ou est arrêté
+    ContainerDown,
+    /// Le conteneur tourne mais l'API ne répond pas (Chromium en échec, RAM...)
+    NotAnswering,
+    /// FlareSolverr répond mais Prowlarr refuse le proxy
+    ProxyRegistrationFailed(String),
+    /// Attente interrompue par l'annulation de l'opération (à propager, pas un avertissement)
+    Cancelled,
+}
+
+impl FlareSolverrIssue {
+    /// Message destiné à l'utilisateur
+    pub fn message(&self) -> String {
+        match self {
+            Self::ContainerDown => {
+                "FlareSolverr n'est pas démarré: les recherches YGG échoueront (docker compose up -d flaresolverr)".to_string()
+            }
+            Self::NotAnswering => {
+                "FlareSolverr ne répond pas sur le port 8191: les indexers protégés par Cloudflare (YGG) échoueront".to_string()
+            }
+            Self::ProxyRegistrationFailed(e) => {
+                format!("FlareSolverr fonctionne mais n'a pas pu être ajouté à Prowlarr ({}): YGG ne l'utilisera pas", e)
+            }
+            Self::Cancelled => "Vérification de FlareSolverr annulée".to_string(),
+        }
+    }
+}
+
+/// Version annoncée par la page d'accueil de FlareSolverr ("FlareSolverr is ready!")
+pub fn parse_ready_response(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body.trim()).ok()?;
+    if !value.get("msg")?.as_str()?.contains("ready") {
+        return None;
+    }
+    Some(value.get("version").and_then(|v| v.as_str()).unwrap_or("?").to_string())
+}
+
+/// Vérifie que le conteneur tourne et que l'API répond (max 1 min, Chromium est lent à démarrer)
+pub async fn check_health(target: &SshTarget<'_>) -> std::result::Result<String, FlareSolverrIssue> {
+    let running = target
+        .exec("docker inspect -f '{{.State.Running}}' flaresolverr 2>/dev/null || echo false")
+        .await
+        .unwrap_or_default();
+    if running.trim() != "true" {
+        return Err(FlareSolverrIssue::ContainerDown);
+    }
+
+    let body = super::wait_for_api(
+        target,
+        "flaresolverr",
+        8191,
+        "/",
+        |body| parse_ready_response(body).is_some(),
+        Duration::from_secs(60),
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceNotReady::Timeout { .. } => FlareSolverrIssue::NotAnswering,
+        ServiceNotReady::ContainerExited { .. } | ServiceNotReady::CrashLoop { .. } => FlareSolverrIssue::ContainerDown,
+        ServiceNotReady::Cancelled { .. } => FlareSolverrIssue::Cancelled,
+    })?;
+
+    let version = parse_ready_response(&body).unwrap_or_default();
+    tracing::info!("✅ Ready (v{})", version);
+    Ok(version)
+}
+
+/// Enregistre FlareSolverr comme proxy Prowlarr, appliqué aux indexers tagués "flaresolverr"
+/// Retourne l'ID du tag
+pub async fn register_proxy(client: &ArrClient, url: &str) -> Result<u64> {
+    let tag_id = client.ensure_tag(TAG).await?;
+    client
+        .upsert_by_name(
+            "/indexerProxy",
+            &json!({
+                "name": "FlareSolverr",
+                "implementation": "FlareSolverr",
+                "configContract": "FlareSolverrSettings",
+                "tags": [tag_id],
+                "fields": [
+                    { "name": "host", "value": url },
+                    { "name": "requestTimeout", "value": 60 }
+                ]
+            }),
+        )
+        .await?;
+    Ok(tag_id)
+}
+
+/// Étape complète: santé de FlareSolverr puis branchement dans Prowlarr
+pub async fn setup(target: SshTarget<'_>, url: Option<&str>) -> std::result::Result<u64, FlareSolverrIssue> {
+    check_health(&target).await?;
+
+    let registration = async {
+        let client = ArrClient::connect(&target, "prowlarr").await?;
+        register_proxy(&client, url.unwrap_or(DEFAULT_URL)).await
+    };
+
+    match registration.await {
+        Ok(tag_id) => {
+            tracing::info!("✅ Registered in Prowlarr (tag {})", tag_id);
+            Ok(tag_id)
+        }
+        Err(e) => Err(FlareSolverrIssue::ProxyRegistrationFailed(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ready_response() {
+        let ready = r#"{"msg": "FlareSolverr is ready!", "version": "3.3.21", "userAgent": "Mozilla/5.0"}"#;
+        assert_eq!(parse_ready_response(ready), Some("3.3.21".to_string()));
+        assert_eq!(parse_ready_response("curl: (7) Failed to connect"), None);
+        assert!(FlareSolverrIssue::NotAnswering.message().contains("8191"));
+    }
+}
@@ -1,5 +1,282 @@
-use anyhow::Result;
-use crate::ssh;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::master_config::MediaPaths;
+use crate::ssh::SshTarget;
+use crate::template_engine::TemplateVars;
+use crate::InstallConfig;
+
+use super::arr::merge_fields;
+use super::local_curl;
+
+// =============================================================================
+// Jellyfin: assistant de premier démarrage, bibliothèques et transcodage (API REST)
+// =============================================================================
+//
+// L'assistant est entièrement piloté par l'API Startup (aucune étape manuelle):
+//   GET /Startup/FirstUser -> POST /Startup/Configuration -> POST /Startup/User
+//   -> POST /Startup/RemoteAccess -> POST /Startup/Complete
+
+const AUTH_HEADER: &str = r#"X-Emby-Authorization: MediaBrowser Client="JellySetup", Device="RaspberryPi", DeviceId="jellysetup-install", Version="1.0.0""#;
+
+/// Session admin obtenue après l'assistant
+#[derive(Debug, Clone)]
+pub struct JellyfinSession {
+    pub access_token: String,
+    pub user_id: String,
+    pub server_id: String,
+}
+
+impl From<JellyfinSession> for crate::JellyfinAuth {
+    fn from(session: JellyfinSession) -> Self {
+        Self { server_id: session.server_id, access_token: session.access_token, user_id: session.user_id }
+    }
+}
+
+pub(super) async fn request(target: &SshTarget<'_>, method: &str, endpoint: &str, token: Option<&str>, body: Option<&Value>) -> Result<String> {
+    let mut headers = vec![AUTH_HEADER.to_string()];
+    if let Some(token) = token {
+        headers.push(format!("X-Emby-Token: {}", token));
+    }
+    let url = format!("http://localhost:8096{}", endpoint);
+    target.exec(&local_curl(method, &url, &headers, body)).await
+}
+
+pub(super) async fn request_json(target: &SshTarget<'_>, method: &str, endpoint: &str, token: Option<&str>, body: Option<&Value>) -> Result<Value> {
+    let response = request(target, method, endpoint, token, body).await?;
+    serde_json::from_str(response.trim()).map_err(|e| anyhow!("Réponse Jellyfin {} invalide: {}", endpoint, e))
+}
+
+/// L'assistant de premier démarrage a-t-il déjà été complété ?
+pub async fn startup_wizard_completed(target: &SshTarget<'_>) -> Result<bool> {
+    let info = request_json(target, "GET", "/System/Info/Public", None, None).await?;
+    Ok(info.get("StartupWizardCompleted").and_then(|v| v.as_bool()).unwrap_or(false))
+}
+
+/// Complète l'assistant: langue/métadonnées, compte admin, accès distant
+/// Sans effet si l'assistant est déjà complété (réinstallation, reprise)
+pub async fn run_startup_wizard(target: &SshTarget<'_>, config: &InstallConfig) -> Result<()> {
+    if startup_wizard_completed(target).await? {
+        tracing::info!("Startup wizard already completed");
+        return Ok(());
+    }
+
+    // Jellyfin 10.11: GET FirstUser crée l'utilisateur par défaut, requis avant POST User
+    request(target, "GET", "/Startup/FirstUser", None, None).await?;
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    request(
+        target,
+        "POST",
+        "/Startup/Configuration",
+        None,
+        Some(&json!({
+            "ServerName": config.jellyfin_server_name,
+            "UICulture": config.preferred_language(),
+            "MetadataCountryCode": config.metadata_country(),
+            "PreferredMetadataLanguage": config.preferred_language()
+        })),
+    )
+    .await?;
+
+    request(
+        target,
+        "POST",
+        "/Startup/User",
+        None,
+        Some(&json!({ "Name": config.jellyfin_username, "Password": config.jellyfin_password })),
+    )
+    .await?;
+
+    request(
+        target,
+        "POST",
+        "/Startup/RemoteAccess",
+        None,
+        Some(&json!({ "EnableRemoteAccess": true, "EnableAutomaticPortMapping": false })),
+    )
+    .await?;
+
+    request(target, "POST", "/Startup/Complete", None, None).await?;
+
+    if !startup_wizard_completed(target).await? {
+        return Err(anyhow!("L'assistant Jellyfin n'a pas été marqué comme complété"));
+    }
+
+    tracing::info!("✅ Startup wizard completed via API");
+    Ok(())
+}
+
+/// Authentifie l'admin
+pub async fn authenticate(target: &SshTarget<'_>, username: &str, password: &str) -> Result<JellyfinSession> {
+    let response = request_json(
+        target,
+        "POST",
+        "/Users/AuthenticateByName",
+        None,
+        Some(&json!({ "Username": username, "Pw": password })),
+    )
+    .await?;
+
+    let field = |pointer: &str| response.pointer(pointer).and_then(|v| v.as_str()).map(String::from);
+    Ok(JellyfinSession {
+        access_token: field("/AccessToken").ok_or_else(|| anyhow!("AccessToken absent de la réponse Jellyfin"))?,
+        user_id: field("/User/Id").unwrap_or_default(),
+        server_id: field("/ServerId").unwrap_or_default(),
+    })
+}
+
+/// Noms des bibliothèques visibles par l'admin
+pub async fn library_names(target: &SshTarget<'_>, token: &str) -> Result<Vec<String>> {
+    let folders = request_json(target, "GET", "/Library/VirtualFolders", Some(token), None).await?;
+    Ok(folders
+        .as_array()
+        .map(|folders| folders.iter().filter_map(|f| f.get("Name")?.as_str().map(String::from)).collect())
+        .unwrap_or_default())
+}
+
+/// Crée les bibliothèques Films/Séries si elles n'existent pas encore
+pub async fn ensure_libraries(target: &SshTarget<'_>, token: &str, media_paths: &MediaPaths) -> Result<()> {
+    ensure_library(target, token, "Films", "movies", &media_paths.movies_path()).await?;
+    ensure_library(target, token, "Séries", "tvshows", &media_paths.tv_path()).await
+}
+
+/// Crée une bibliothèque si aucune ne porte déjà ce nom
+pub async fn ensure_library(
+    target: &SshTarget<'_>,
+    token: &str,
+    name: &str,
+    collection_type: &str,
+    path: &str,
+) -> Result<()> {
+    if library_names(target, token).await?.iter().any(|n| n == name) {
+        tracing::info!("Library {} already exists", name);
+        return Ok(());
+    }
+
+    // PathInfos obligatoire pour que la bibliothèque ait un ItemId
+    let url = reqwest::Url::parse_with_params(
+        "http://localhost:8096/Library/VirtualFolders",
+        &[("name", name), ("collectionType", collection_type), ("refreshLibrary", "true")],
+    )?;
+    let endpoint = format!("{}?{}", url.path(), url.query().unwrap_or_default());
+    request(
+        target,
+        "POST",
+        &endpoint,
+        Some(token),
+        Some(&json!({ "LibraryOptions": { "PathInfos": [{ "Path": path }] } })),
+    )
+    .await?;
+    tracing::info!("Library {} created ({})", name, path);
+    Ok(())
+}
+
+/// Utilisateurs Jellyfin (id, nom, policy)
+pub async fn list_users(target: &SshTarget<'_>, token: &str) -> Result<Vec<Value>> {
+    let users = request_json(target, "GET", "/Users", Some(token), None).await?;
+    Ok(users.as_array().cloned().unwrap_or_default())
+}
+
+/// Remplace la policy d'un utilisateur (POST /Users/{id}/Policy)
+pub async fn update_user_policy(target: &SshTarget<'_>, token: &str, user_id: &str, policy: &Value) -> Result<()> {
+    request(target, "POST", &format!("/Users/{}/Policy", user_id), Some(token), Some(policy)).await?;
+    Ok(())
+}
+
+/// Langue audio/sous-titres préférée de l'utilisateur (GET puis POST /Users/{id}/Configuration)
+pub async fn configure_user_languages(target: &SshTarget<'_>, token: &str, user_id: &str, language: &str) -> Result<()> {
+    let user = request_json(target, "GET", &format!("/Users/{}", user_id), Some(token), None).await?;
+    let current = user.get("Configuration").cloned().unwrap_or_else(|| json!({}));
+    let configuration =
+        merge_fields(&current, &json!({ "SubtitleLanguagePreference": language, "AudioLanguagePreference": language }));
+    request(target, "POST", &format!("/Users/{}/Configuration", user_id), Some(token), Some(&configuration)).await?;
+    Ok(())
+}
+
+/// Redémarre Jellyfin et attend que son API réponde à nouveau
+pub async fn restart(target: &SshTarget<'_>) -> Result<()> {
+    target.exec("cd ~/media-stack && docker compose restart jellyfin").await?;
+    super::readiness::wait_for_api(
+        target,
+        "jellyfin",
+        crate::config::ports::JELLYFIN,
+        "/health",
+        |body| body.trim() == "Healthy",
+        std::time::Duration::from_secs(120),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Options de transcodage adaptées au Pi, fusionnées dans la config actuelle
+///
+/// Jellyfin 10.11 ne gère plus l'accélération V4L2 du Pi et le Pi 5 n'a pas d'encodeur
+/// H.264 matériel: transcodage logiciel allégé, throttling et suppression des segments.
+/// La section `encoding` de master_config peut surcharger chaque option.
+pub fn pi_encoding_options(current: &Value, overrides: Option<&Value>) -> Value {
+    let defaults = json!({
+        "HardwareAccelerationType": "none",
+        "EncodingThreadCount": -1,
+        "H264Preset": "veryfast",
+        "EnableThrottling": true,
+        "EnableSegmentDeletion": true,
+        "EnableTonemapping": false,
+        "AllowHevcEncoding": false,
+        "AllowAv1Encoding": false,
+        "EnableFallbackFont": true
+    });
+
+    let merged = merge_fields(current, &defaults);
+    match overrides {
+        Some(overrides) => merge_fields(&merged, overrides),
+        None => merged,
+    }
+}
+
+/// Applique les options de transcodage (GET puis POST /System/Configuration/encoding)
+pub async fn configure_transcoding(target: &SshTarget<'_>, token: &str, overrides: Option<&Value>) -> Result<()> {
+    let current = request_json(target, "GET", "/System/Configuration/encoding", Some(token), None).await?;
+    let encoding = pi_encoding_options(&current, overrides);
+    request(target, "POST", "/System/Configuration/encoding", Some(token), Some(&encoding)).await?;
+
+    tracing::info!("Transcoding configured ({})", encoding["HardwareAccelerationType"]);
+    Ok(())
+}
+
+/// Premier démarrage complet: assistant, authentification, bibliothèques, transcodage, plugins
+pub async fn first_run_setup(
+    target: &SshTarget<'_>,
+    config: &InstallConfig,
+    media_paths: &MediaPaths,
+    encoding_overrides: Option<&Value>,
+) -> Result<JellyfinSession> {
+    // Modèle du compte capturé sur une installation précédente: réglages, plugins, bibliothèques
+    if !startup_wizard_completed(target).await? {
+        if let Err(e) = super::jellyfin_template::apply(target).await {
+            tracing::warn!("⚠️ Template: {}", e);
+        }
+    }
+    run_startup_wizard(target, config).await?;
+
+    // Laisser Jellyfin finaliser l'utilisateur avant l'authentification
+    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+    let session = authenticate(target, &config.jellyfin_username, config.jellyfin_password.expose()).await?;
+
+    ensure_libraries(target, &session.access_token, media_paths).await?;
+    configure_transcoding(target, &session.access_token, encoding_overrides).await?;
+    if !session.user_id.is_empty() {
+        configure_user_languages(target, &session.access_token, &session.user_id, config.media_language()).await?;
+    }
+
+    // Plugins: confort, jamais bloquant pour l'installation
+    if let Err(e) = super::jellyfin_plugins::install_plugins(target, &session.access_token, &super::jellyfin_plugins::default_plugins()).await {
+        tracing::warn!("⚠️ Plugins: {}", e);
+    }
+
+    tracing::info!("✅ First-run setup completed");
+    Ok(session)
+}
 
 /// Applique la configuration Jellyfin depuis master_config (avec clé privée)
 pub async fn apply_config(
@@ -7,23 +284,11 @@ pub async fn apply_config(
     username: &str,
     private_key: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Jellyfin] Applying master configuration...");
+    tracing::debug!("Applying master configuration...");
 
-    // Jellyfin a plusieurs fichiers de config
-    // - system.xml (configuration système)
-    // - network.xml (configuration réseau)
-    // - logging.json (configuration des logs)
-    // - encoding.xml (configuration du transcodage)
-
-    // Pour l'instant on log juste la config reçue
-    println!("[Jellyfin] Config received: {}", serde_json::to_string_pretty(config)?);
-
-    // TODO: Implémenter la configuration des fichiers Jellyfin
-    // Cela nécessite de mapper le JSON vers les différents fichiers XML/JSON
-
-    println!("[Jellyfin] ✅ Configuration applied");
-    Ok(())
+    configure(SshTarget::Key { host, username, private_key }, config, vars).await
 }
 
 /// Applique la configuration Jellyfin depuis master_config (avec mot de passe)
@@ -32,69 +297,49 @@ pub async fn apply_config_password(
     username: &str,
     password: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Jellyfin] Applying master configuration...");
-
-    // IMPORTANT: Jellyfin NE PEUT PAS démarrer avec une DB vide
-    // Si on supprime la DB, Jellyfin crash au démarrage avec "no such table: __EFMigrationsHistory"
-    // Stratégie: Créer system.xml seulement s'il n'existe pas (première installation)
-    // Lors des upgrades, on GARDE la DB Jellyfin existante
-
-    let system_xml = r#"<?xml version="1.0" encoding="utf-8"?>
-<ServerConfiguration xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:xsd="http://www.w3.org/2001/XMLSchema">
-  <IsStartupWizardCompleted>true</IsStartupWizardCompleted>
-  <EnableUPnP>false</EnableUPnP>
-  <PublicPort>8096</PublicPort>
-  <PublicHttpsPort>8920</PublicHttpsPort>
-  <HttpServerPortNumber>8096</HttpServerPortNumber>
-  <HttpsPortNumber>8920</HttpsPortNumber>
-  <EnableHttps>false</EnableHttps>
-  <EnableRemoteAccess>true</EnableRemoteAccess>
-  <CertificatePath />
-  <CertificatePassword />
-  <IsPortAuthorized>false</IsPortAuthorized>
-  <QuickConnectAvailable>false</QuickConnectAvailable>
-  <EnableRemoteControlOfSharedDevices>false</EnableRemoteControlOfSharedDevices>
-  <EnableActivityLogging>true</EnableActivityLogging>
-  <RemoteClientBitrateLimit>0</RemoteClientBitrateLimit>
-  <EnableFolderView>false</EnableFolderView>
-  <EnableGroupingIntoCollections>false</EnableGroupingIntoCollections>
-  <DisplaySpecialsWithinSeasons>true</DisplaySpecialsWithinSeasons>
-  <CodecsUsed />
-  <PluginRepositories />
-  <EnableExternalContentInSuggestions>true</EnableExternalContentInSuggestions>
-  <ImageExtractionTimeoutMs>0</ImageExtractionTimeoutMs>
-  <PathSubstitutions />
-  <EnableSlowResponseWarning>true</EnableSlowResponseWarning>
-  <SlowResponseThresholdMs>500</SlowResponseThresholdMs>
-  <CorsHosts>
-    <string>*</string>
-  </CorsHosts>
-  <ActivityLogRetentionDays>30</ActivityLogRetentionDays>
-  <LibraryScanFanoutConcurrency>0</LibraryScanFanoutConcurrency>
-  <LibraryMetadataRefreshConcurrency>0</LibraryMetadataRefreshConcurrency>
-  <RemoveOldPlugins>false</RemoveOldPlugins>
-  <AllowClientLogUpload>false</AllowClientLogUpload>
-</ServerConfiguration>
-"#;
-
-    let script = r#"
-# STRATÉGIE JELLYFIN:
-# Jellyfin NE PEUT PAS démarrer depuis une DB vide - crash systématique avec "no such table: __EFMigrationsHistory"
-#
-# Solution en 2 temps:
-# 1. PREMIÈRE INSTALLATION (jamais fait): L'utilisateur DOIT faire le wizard manuellement (NON NÉGOCIABLE techniquement)
-#    On sauvegarde ensuite la DB comme template dans Supabase
-# 2. FRESH INSTALLS SUIVANTES: On télécharge la DB template depuis Supabase et on skip le wizard
-#
-# Pour l'instant, on ne fait rien lors de l'upgrade - Jellyfin garde sa DB existante
-
-echo "✅ Jellyfin: conservation de la DB existante"
-cd ~/media-stack && docker compose restart jellyfin
-"#;
-
-    ssh::execute_command_password(host, username, password, &script).await?;
-    println!("[Jellyfin] ✅ Configuration applied - wizard completed automatically via API");
+    tracing::debug!("Applying master configuration...");
 
+    // La DB Jellyfin est conservée (contrairement aux *arr): elle contient le compte admin
+    // et les bibliothèques créés par l'assistant
+    configure(SshTarget::Password { host, username, password }, config, vars).await
+}
+
+/// Sections master_config: `encoding` (options de transcodage), `sync_play` (visionnage groupé)
+async fn configure(target: SshTarget<'_>, config: &Value, vars: &TemplateVars) -> Result<()> {
+    let session = authenticate(
+        &target,
+        vars.get("JELLYFIN_USERNAME").unwrap_or_default(),
+        vars.get("JELLYFIN_PASSWORD").unwrap_or_default(),
+    )
+    .await?;
+
+    configure_transcoding(&target, &session.access_token, config.get("encoding")).await?;
+    if let Some(enabled) = config.get("sync_play").and_then(|v| v.as_bool()) {
+        crate::sync_play::apply_to_users(&target, &session.access_token, enabled).await?;
+    }
+
+    // Les options serveur ne sont toutes prises en compte qu'au redémarrage
+    restart(&target).await?;
+
+    tracing::info!("✅ Configuration applied");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pi_encoding_options() {
+        let current = json!({ "HardwareAccelerationType": "vaapi", "TranscodingTempPath": "/cache" });
+        let overrides = json!({ "H264Preset": "superfast" });
+
+        let options = pi_encoding_options(&current, Some(&overrides));
+        assert_eq!(options["HardwareAccelerationType"], "none");
+        assert_eq!(options["TranscodingTempPath"], "/cache");
+        assert_eq!(options["H264Preset"], "superfast");
+        assert_eq!(options["EnableThrottling"], true);
+    }
+}
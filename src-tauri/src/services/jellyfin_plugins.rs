@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::ssh::SshTarget;
+
+use super::arr::merge_fields;
+use super::jellyfin::{request, request_json};
+
+// =============================================================================
+// Plugins Jellyfin: dépôt, installation, configuration et tâches planifiées (API REST)
+// =============================================================================
+//
+//   POST /Repositories (ajout du dépôt) -> GET /Packages -> POST /Packages/Installed/{name}
+//   -> redémarrage de Jellyfin (chargement des plugins) -> POST /Plugins/{id}/Configuration
+//   -> POST /ScheduledTasks/{id}/Triggers
+//
+// Chaque plugin est décrit par un `PluginSpec`; l'installation est idempotente.
+
+/// Ticks .NET (100 ns) par heure, unité des déclencheurs de tâches Jellyfin
+const TICKS_PER_HOUR: i64 = 36_000_000_000;
+
+/// Plugin installé par JellySetup
+#[derive(Debug, Clone)]
+pub struct PluginSpec {
+    /// Nom du paquet dans le manifeste du dépôt
+    pub name: &'static str,
+    pub repository_name: &'static str,
+    pub repository_url: &'static str,
+    /// Fusionnée dans la configuration par défaut du plugin
+    pub configuration: Value,
+    /// Tâche planifiée (nom exact) et heure d'exécution quotidienne
+    pub daily_task: Option<(&'static str, u8)>,
+    /// Vérification des prérequis dans le conteneur (ffmpeg, ...), doit afficher OK
+    pub requirement_check: Option<&'static str>,
+}
+
+/// Intro Skipper: détection des génériques (empreintes audio chromaprint via jellyfin-ffmpeg)
+pub fn intro_skipper() -> PluginSpec {
+    PluginSpec {
+        name: "Intro Skipper",
+        repository_name: "Intro Skipper",
+        repository_url: "https://intro-skipper.org/manifest.json",
+        configuration: json!({
+            "ScanIntroduction": true,
+            "ScanCredits": true,
+            "AnalyzeSeasonZero": false,
+            // Pi: une seule analyse à la fois, limitée au début/à la fin des épisodes
+            "MaxParallelism": 1,
+            "AnalysisPercent": 25,
+            "AnalysisLengthLimit": 10,
+            "AutoSkip": false,
+            "SkipButtonVisible": true
+        }),
+        // La nuit, pour ne pas ralentir la lecture
+        daily_task: Some(("Detect Intros and Credits", 3)),
+        requirement_check: Some(
+            "docker exec jellyfin /usr/lib/jellyfin-ffmpeg/ffmpeg -hide_banner -muxers 2>/dev/null | grep -q chromaprint && echo OK",
+        ),
+    }
+}
+
+/// Plugins installés par défaut
+pub fn default_plugins() -> Vec<PluginSpec> {
+    vec![intro_skipper()]
+}
+
+/// Liste des dépôts avec celui du plugin (inchangée s'il est déjà présent)
+pub fn with_repository(repositories: &Value, spec: &PluginSpec) -> Option<Value> {
+    let mut list = repositories.as_array().cloned().unwrap_or_default();
+    if list.iter().any(|r| r["Url"] == spec.repository_url) {
+        return None;
+    }
+    list.push(json!({ "Name": spec.repository_name, "Url": spec.repository_url, "Enabled": true }));
+    Some(Value::Array(list))
+}
+
+/// GUID du paquet dans /Packages
+pub fn package_guid<'a>(packages: &'a Value, name: &str) -> Option<&'a str> {
+    packages.as_array()?.iter().find(|p| p["name"] == name)?["guid"].as_str()
+}
+
+/// Id du plugin chargé dans /Plugins
+pub fn installed_plugin_id<'a>(plugins: &'a Value, name: &str) -> Option<&'a str> {
+    plugins.as_array()?.iter().find(|p| p["Name"] == name)?["Id"].as_str()
+}
+
+/// Déclencheur quotidien à `hour` heures
+pub fn daily_trigger(hour: u8) -> Value {
+    json!([{ "Type": "DailyTrigger", "TimeOfDayTicks": hour as i64 * TICKS_PER_HOUR }])
+}
+
+/// Redémarre Jellyfin et attend /health (chargement des plugins installés)
+async fn restart_jellyfin(target: &SshTarget<'_>) -> Result<()> {
+    target.exec("docker restart jellyfin").await?;
+    super::wait_for_api(target, "jellyfin", crate::config::ports::JELLYFIN, "/health", |body| body.contains("Healthy"), Duration::from_secs(120))
+        .await?;
+    Ok(())
+}
+
+async fn configure_plugin(target: &SshTarget<'_>, token: &str, spec: &PluginSpec) -> Result<()> {
+    let plugins = request_json(target, "GET", "/Plugins", Some(token), None).await?;
+    let id = installed_plugin_id(&plugins, spec.name)
+        .ok_or_else(|| anyhow!("{}: plugin non chargé après redémarrage", spec.name))?;
+
+    let endpoint = format!("/Plugins/{}/Configuration", id);
+    let current = request_json(target, "GET", &endpoint, Some(token), None).await.unwrap_or_else(|_| json!({}));
+    request(target, "POST", &endpoint, Some(token), Some(&merge_fields(&current, &spec.configuration))).await?;
+
+    if let Some((task_name, hour)) = spec.daily_task {
+        let tasks = request_json(target, "GET", "/ScheduledTasks", Some(token), None).await?;
+        match tasks.as_array().and_then(|t| t.iter().find(|t| t["Name"] == task_name)).and_then(|t| t["Id"].as_str()) {
+            Some(task_id) => {
+                let endpoint = format!("/ScheduledTasks/{}/Triggers", task_id);
+                request(target, "POST", &endpoint, Some(token), Some(&daily_trigger(hour))).await?;
+            }
+            None => tracing::warn!("⚠️ {}: scheduled task '{}' not found", spec.name, task_name),
+        }
+    }
+    Ok(())
+}
+
+/// Installe et configure les plugins (redémarre Jellyfin une fois si un plugin a été installé)
+pub async fn install_plugins(target: &SshTarget<'_>, token: &str, specs: &[PluginSpec]) -> Result<()> {
+    let mut installed_any = false;
+    let loaded = request_json(target, "GET", "/Plugins", Some(token), None).await?;
+
+    for spec in specs {
+        if let Some(check) = spec.requirement_check {
+            if target.exec(check).await.unwrap_or_default().trim() != "OK" {
+                tracing::warn!("⚠️ {}: requirements missing in container, skipped", spec.name);
+                continue;
+            }
+        }
+        if installed_plugin_id(&loaded, spec.name).is_some() {
+            tracing::info!("Plugin {} already installed", spec.name);
+            continue;
+        }
+
+        let repositories = request_json(target, "GET", "/Repositories", Some(token), None).await?;
+        if let Some(repositories) = with_repository(&repositories, spec) {
+            request(target, "POST", "/Repositories", Some(token), Some(&repositories)).await?;
+        }
+
+        let packages = request_json(target, "GET", "/Packages", Some(token), None).await?;
+        let guid = package_guid(&packages, spec.name)
+            .ok_or_else(|| anyhow!("{}: paquet introuvable dans {}", spec.name, spec.repository_url))?;
+        let url = reqwest::Url::parse_with_params(
+            &format!("http://localhost:8096/Packages/Installed/{}", spec.name),
+            &[("assemblyGuid", guid), ("repositoryUrl", spec.repository_url)],
+        )?;
+        let endpoint = format!("{}?{}", url.path(), url.query().unwrap_or_default());
+        request(target, "POST", &endpoint, Some(token), None).await?;
+        tracing::info!("Plugin {} installed", spec.name);
+        installed_any = true;
+    }
+
+    if installed_any {
+        // L'installation se termine en tâche de fond avant le redémarrage
+        crate::operations::sleep(Duration::from_secs(10)).await?;
+        restart_jellyfin(target).await?;
+    }
+
+    let loaded = request_json(target, "GET", "/Plugins", Some(token), None).await?;
+    for spec in specs.iter().filter(|s| installed_plugin_id(&loaded, s.name).is_some()) {
+        configure_plugin(target, token, spec).await?;
+        tracing::info!("✅ Plugin {} configured", spec.name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_api_helpers() {
+        let spec = intro_skipper();
+        let repositories = json!([{ "Name": "Jellyfin Stable", "Url": "https://repo.jellyfin.org/files/plugin/manifest.json", "Enabled": true }]);
+        let updated = with_repository(&repositories, &spec).unwrap();
+        assert_eq!(updated.as_array().unwrap().len(), 2);
+        assert!(with_repository(&updated, &spec).is_none());
+
+        let packages = json!([{ "name": "Intro Skipper", "guid": "c83d86bb-a1e0-4c35-a113-e2101cf4ee6b" }]);
+        assert_eq!(package_guid(&packages, "Intro Skipper"), Some("c83d86bb-a1e0-4c35-a113-e2101cf4ee6b"));
+        assert_eq!(installed_plugin_id(&json!([]), "Intro Skipper"), None);
+
+        assert_eq!(daily_trigger(3)[0]["TimeOfDayTicks"], 108_000_000_000i64);
+    }
+}
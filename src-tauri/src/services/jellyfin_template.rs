@@ -0,0 +1,225 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use ts_rs::TS;
+
+use crate::ssh::{self, SshTarget};
+
+use super::jellyfin::request_json;
+
+// =============================================================================
+// Modèle de base Jellyfin (capture puis réutilisation sur les installations suivantes)
+// =============================================================================
+//
+// Une fois un Jellyfin configuré, ses réglages (config/*.xml), plugins et
+// définitions de bibliothèques sont capturés conteneur arrêté et déposés dans
+// Supabase Storage, dans le dossier du compte JellySetup connecté (account.rs),
+// par version de Jellyfin. Les bases SQLite (utilisateurs, mots de passe,
+// historique de lecture, appareils, clés API) n'en font jamais partie, pas plus
+// que les caches, logs, métadonnées et l'identifiant du serveur.
+//
+// Sur une installation fraîche de la même version, le modèle du compte (jamais
+// celui d'un autre) remplace la configuration vide avant l'assistant, remis à
+// zéro pour créer l'admin avec les identifiants de l'installation. Au moindre
+// échec, la configuration est vidée et l'assistant part d'une base vierge.
+
+const REMOTE_TEMPLATE: &str = "/tmp/jellysetup-jellyfin-template.tgz";
+const ARCHIVE_FILE: &str = "jellyfin.tar.gz";
+const METADATA_FILE: &str = "template.json";
+/// Données personnelles, propres à un serveur ou régénérables, jamais incluses dans le modèle
+const EXCLUDES: [&str; 12] = [
+    "*.db",
+    "*.db-wal",
+    "*.db-shm",
+    "*.db-journal",
+    "jellyfin/cache",
+    "jellyfin/log",
+    "jellyfin/metadata",
+    "jellyfin/data/metadata",
+    "jellyfin/data/transcodes",
+    "jellyfin/data/device.txt",
+    "jellyfin/config/passwordreset*",
+    "*.pid",
+];
+const TEMPLATE_TIMEOUT_SECS: u64 = 900;
+
+/// template.json d'un modèle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct JellyfinTemplate {
+    pub jellyfin_version: String,
+    /// RFC 3339
+    pub captured_at: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Version de Jellyfin qui tourne sur le Pi
+async fn server_version(target: &SshTarget<'_>) -> Result<String> {
+    let info = request_json(target, "GET", "/System/Info/Public", None, None).await?;
+    info.get("Version")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Version Jellyfin absente de /System/Info/Public"))
+}
+
+/// Conteneur arrêté le temps de l'archive (fichiers cohérents), redémarré même en cas d'échec
+fn capture_command(sudo: &str) -> String {
+    let excludes: Vec<String> = EXCLUDES.iter().map(|e| format!("--exclude='{}'", e)).collect();
+    format!(
+        "cd ~/media-stack && docker compose stop jellyfin && {sudo} tar czf {archive} {excludes} jellyfin; \
+         status=$?; docker compose start jellyfin; [ $status -eq 0 ] && {sudo} chown $(id -u) {archive} && \
+         echo \"SIZE=$(stat -c%s {archive})\" && echo \"SHA256=$(sha256sum {archive} | cut -d' ' -f1)\"",
+        sudo = sudo,
+        archive = REMOTE_TEMPLATE,
+        excludes = excludes.join(" ")
+    )
+}
+
+/// Remplace la configuration par le modèle et rouvre l'assistant de premier démarrage
+fn inject_command(sudo: &str, uid: u32, gid: u32) -> String {
+    format!(
+        "cd ~/media-stack && docker compose stop jellyfin && {sudo} rm -rf jellyfin && {sudo} tar xzf {archive} && \
+         {sudo} find jellyfin -name system.xml -exec sed -i \
+           's|<IsStartupWizardCompleted>true</IsStartupWizardCompleted>|<IsStartupWizardCompleted>false</IsStartupWizardCompleted>|' {{}} + && \
+         {sudo} chown -R {uid}:{gid} jellyfin && rm -f {archive} && docker compose start jellyfin",
+        sudo = sudo,
+        archive = REMOTE_TEMPLATE,
+        uid = uid,
+        gid = gid
+    )
+}
+
+/// Copie locale des modèles d'un compte (data_dir/jellysetup/templates/<compte>)
+fn local_archive(owner_id: &str, jellyfin_version: &str) -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| anyhow!("Cannot determine data directory"))?
+        .join("jellysetup")
+        .join("templates")
+        .join(owner_id);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("jellyfin-{}.tar.gz", jellyfin_version)))
+}
+
+/// Capture la configuration Jellyfin du Pi comme modèle du compte connecté pour sa version
+pub async fn capture(target: &SshTarget<'_>) -> Result<JellyfinTemplate> {
+    let account = crate::account::current()
+        .await
+        .ok_or_else(|| anyhow!("Connectez-vous à votre compte JellySetup pour enregistrer un modèle"))?;
+    let jellyfin_version = server_version(target).await?;
+    tracing::debug!("Capturing Jellyfin {} template...", jellyfin_version);
+
+    let options = ssh::SshOptions { command_timeout_secs: TEMPLATE_TIMEOUT_SECS, ..ssh::default_options() };
+    let info = target.exec_with_options(&capture_command(&target.sudo()), &options).await?;
+    let (size, sha256) = crate::backup::parse_archive_info(&info).ok_or_else(|| anyhow!("Archive Jellyfin non créée: {}", info.trim()))?;
+
+    let local = local_archive(&account.user_id, &jellyfin_version)?;
+    crate::backup::pull_archive(target, REMOTE_TEMPLATE, size, &local, &options, &mut |_| {}).await?;
+
+    let template = JellyfinTemplate {
+        jellyfin_version: jellyfin_version.clone(),
+        captured_at: chrono::Utc::now().to_rfc3339(),
+        size,
+        sha256,
+    };
+    crate::supabase::upload_jellyfin_template_file(&jellyfin_version, ARCHIVE_FILE, "application/gzip", std::fs::read(&local)?).await?;
+    crate::supabase::upload_jellyfin_template_file(
+        &jellyfin_version,
+        METADATA_FILE,
+        "application/json",
+        serde_json::to_vec_pretty(&template)?,
+    )
+    .await?;
+
+    tracing::info!("✅ Template for Jellyfin {} uploaded ({} bytes)", jellyfin_version, size);
+    Ok(template)
+}
+
+/// Modèle du compte pour cette version (archive téléchargée dans la copie locale si besoin)
+async fn fetch(owner_id: &str, jellyfin_version: &str) -> Result<Option<(JellyfinTemplate, PathBuf)>> {
+    let Some(bytes) = crate::supabase::download_jellyfin_template_file(jellyfin_version, METADATA_FILE).await? else {
+        return Ok(None);
+    };
+    let template: JellyfinTemplate = serde_json::from_slice(&bytes)?;
+
+    let local = local_archive(owner_id, jellyfin_version)?;
+    let cached = std::fs::metadata(&local).map(|m| m.len() == template.size).unwrap_or(false);
+    if !cached {
+        let bytes = crate::supabase::download_jellyfin_template_file(jellyfin_version, ARCHIVE_FILE)
+            .await?
+            .ok_or_else(|| anyhow!("Archive du modèle Jellyfin {} absente", jellyfin_version))?;
+        if bytes.len() as u64 != template.size {
+            return Err(anyhow!("Modèle Jellyfin incomplet: {} octets sur {}", bytes.len(), template.size));
+        }
+        std::fs::write(&local, bytes)?;
+    }
+    Ok(Some((template, local)))
+}
+
+/// Remplace la configuration vide du Pi par le modèle (PUID/PGID du compose)
+async fn inject(target: &SshTarget<'_>, template: &JellyfinTemplate, local: &Path) -> Result<()> {
+    crate::backup::push_file(target, local, REMOTE_TEMPLATE, &template.sha256).await?;
+
+    let compose = target.exec("cat ~/media-stack/docker-compose.yml").await?;
+    let (uid, gid) = crate::backup::compose_owner(&compose).unwrap_or((1000, 1000));
+    let options = ssh::SshOptions { command_timeout_secs: TEMPLATE_TIMEOUT_SECS, ..ssh::default_options() };
+    target.exec_with_options(&inject_command(&target.sudo(), uid, gid), &options).await?;
+
+    for _ in 0..24 {
+        crate::operations::sleep(std::time::Duration::from_secs(5)).await?;
+        if request_json(target, "GET", "/System/Info/Public", None, None).await.is_ok() {
+            return Ok(());
+        }
+    }
+    Err(anyhow!("Jellyfin ne répond pas après injection du modèle"))
+}
+
+/// Applique le modèle du compte connecté pour la version installée, si disponible.
+/// L'assistant de premier démarrage reste à lancer dans tous les cas (admin de l'installation).
+/// Ok(false): pas de compte connecté ou aucun modèle
+pub async fn apply(target: &SshTarget<'_>) -> Result<bool> {
+    let Some(account) = crate::account::current().await else {
+        tracing::info!("Not signed in, no template applied");
+        return Ok(false);
+    };
+    let jellyfin_version = server_version(target).await?;
+    let Some((template, local)) = fetch(&account.user_id, &jellyfin_version).await? else {
+        tracing::info!("No template for Jellyfin {}", jellyfin_version);
+        return Ok(false);
+    };
+    tracing::debug!("Applying template captured {} ...", template.captured_at);
+
+    if let Err(e) = inject(target, &template, &local).await {
+        // Configuration vierge pour que l'assistant classique puisse reprendre
+        tracing::warn!("⚠️ Template failed ({}), resetting Jellyfin configuration", e);
+        let reset = format!(
+            "cd ~/media-stack && docker compose stop jellyfin && {sudo} rm -rf jellyfin && docker compose start jellyfin",
+            sudo = target.sudo()
+        );
+        target.exec_with_options(&reset, &ssh::SshOptions { command_timeout_secs: TEMPLATE_TIMEOUT_SECS, ..ssh::default_options() }).await?;
+        tokio::time::sleep(std::time::Duration::from_secs(crate::config::delays::JELLYFIN_BOOT_SECS)).await;
+        return Err(e);
+    }
+
+    tracing::info!("✅ Template applied, startup wizard reopened for the admin account");
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_commands() {
+        let command = capture_command("sudo");
+        assert!(command.contains("--exclude='jellyfin/data/device.txt'"));
+        assert!(command.contains("--exclude='*.db'"));
+        assert!(command.contains("--exclude='*.db-wal'"));
+        assert!(command.contains("docker compose start jellyfin"));
+
+        let command = inject_command("echo 'pw' | sudo -S", 1000, 1000);
+        assert!(command.contains("<IsStartupWizardCompleted>false</IsStartupWizardCompleted>"));
+        assert!(command.contains("chown -R 1000:1000 jellyfin"));
+    }
+}
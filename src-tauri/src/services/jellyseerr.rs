@@ -1,5 +1,172 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
 use crate::ssh;
+use crate::InstallConfig;
+
+/// URL de l'API Jellyseerr vue depuis le Pi
+const API_URL: &str = "http://localhost:5055/api/v1";
+
+/// Fichier cookie de session utilisé pendant le setup initial
+pub const COOKIE_FILE: &str = "/tmp/jellyseerr_cookies.txt";
+
+/// Lit settings.json (volume ./jellyseerr monté sur /app/config)
+pub const READ_SETTINGS_COMMAND: &str =
+    "cat ~/media-stack/jellyseerr/settings.json 2>/dev/null || cat ~/media-stack/jellyseerr/config/settings.json 2>/dev/null";
+
+/// /api/v1/status répond {"version": ..., "commitTag": ...} dès que le serveur écoute
+fn status_ready(body: &str) -> bool {
+    body.contains("version") || body.contains("initialized")
+}
+
+/// Session d'une requête curl vers Jellyseerr (setup initial, avant que l'API key existe)
+pub enum CurlAuth {
+    /// Sauvegarde les cookies de session (login)
+    SaveCookies,
+    /// Réutilise les cookies de session
+    Cookies,
+}
+
+#[derive(Debug, Deserialize)]
+struct JellyfinLibrary {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SettingsFile {
+    main: MainSettings,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MainSettings {
+    api_key: String,
+}
+
+/// Construit une commande curl vers l'API Jellyseerr
+/// Le corps JSON passe par un heredoc: aucun échappement shell nécessaire
+pub fn curl_command(method: &str, endpoint: &str, auth: CurlAuth, body: Option<&Value>) -> String {
+    let auth_args = match auth {
+        CurlAuth::SaveCookies => format!("-c {}", COOKIE_FILE),
+        CurlAuth::Cookies => format!("-b {}", COOKIE_FILE),
+    };
+
+    match body {
+        Some(body) => format!(
+            "curl -s -X {} '{}{}' {} -H 'Content-Type: application/json' --data-binary @- <<'JELLYSEERR_JSON'\n{}\nJELLYSEERR_JSON",
+            method, API_URL, endpoint, auth_args, body
+        ),
+        None => format!("curl -s -X {} '{}{}' {}", method, API_URL, endpoint, auth_args),
+    }
+}
+
+/// Vérifie qu'une réponse de /auth/jellyfin contient bien l'utilisateur créé
+pub fn is_auth_success(response: &str) -> bool {
+    serde_json::from_str::<Value>(response.trim())
+        .ok()
+        .and_then(|v| v.get("id").cloned())
+        .map(|id| !id.is_null())
+        .unwrap_or(false)
+}
+
+/// Extrait les IDs des bibliothèques renvoyées par /settings/jellyfin/library
+pub fn parse_library_ids(response: &str) -> Result<Vec<String>> {
+    let libraries: Vec<JellyfinLibrary> = serde_json::from_str(response.trim())
+        .map_err(|e| anyhow!("Réponse bibliothèques Jellyseerr invalide: {}", e))?;
+    Ok(libraries.into_iter().map(|l| l.id).collect())
+}
+
+/// Extrait l'API key de Jellyseerr depuis le contenu de settings.json
+pub fn parse_settings_api_key(settings: &str) -> Result<String> {
+    let settings: SettingsFile = serde_json::from_str(settings.trim())
+        .map_err(|e| anyhow!("settings.json Jellyseerr invalide: {}", e))?;
+
+    if settings.main.api_key.is_empty() {
+        return Err(anyhow!("API key Jellyseerr vide dans settings.json"));
+    }
+
+    Ok(settings.main.api_key)
+}
+
+/// Corps de la requête /settings/radarr ou /settings/sonarr
+pub fn arr_server_payload(service: &str, hostname: &str, api_key: &str, root_folder: &str) -> Value {
+    let mut payload = json!({
+        "hostname": hostname,
+        "apiKey": api_key,
+        "useSsl": false,
+        "activeProfileId": 4,
+        "activeProfileName": "HD-1080p",
+        "activeDirectory": root_folder,
+        "is4k": false,
+        "isDefault": true,
+        "syncEnabled": true
+    });
+
+    if service == "sonarr" {
+        payload["name"] = json!("Sonarr");
+        payload["port"] = json!(8989);
+        payload["enableSeasonFolders"] = json!(true);
+    } else {
+        payload["name"] = json!("Radarr");
+        payload["port"] = json!(7878);
+        payload["minimumAvailability"] = json!("released");
+    }
+
+    payload
+}
+
+/// Serveur Radarr/Sonarr déjà enregistré dans Jellyseerr
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArrServer {
+    pub id: u64,
+    pub name: String,
+    pub hostname: String,
+    pub port: u16,
+    #[serde(default)]
+    pub is4k: bool,
+}
+
+/// Cherche un serveur équivalent (même nom, ou même hôte:port et même type 4K)
+pub fn find_existing_server(servers: &[ArrServer], payload: &Value) -> Option<u64> {
+    let name = payload.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+    let hostname = payload.get("hostname").and_then(|v| v.as_str()).unwrap_or_default();
+    let port = payload.get("port").and_then(|v| v.as_u64()).unwrap_or_default();
+    let is4k = payload.get("is4k").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    servers
+        .iter()
+        .find(|s| s.is4k == is4k && (s.name == name || (s.hostname == hostname && s.port as u64 == port)))
+        .map(|s| s.id)
+}
+
+/// Réglages généraux: langue de l'interface
+pub fn main_settings_payload(install_config: &InstallConfig) -> Value {
+    json!({
+        "locale": install_config.preferred_language()
+    })
+}
+
+/// Réglages du compte admin (id 1, créé par /auth/jellyfin)
+pub fn admin_user_payload(install_config: &InstallConfig) -> Value {
+    json!({
+        "username": install_config.admin_display_name(),
+        "email": install_config.admin_email(),
+        "locale": install_config.preferred_language()
+    })
+}
+
+/// Expéditeur des notifications email (désactivées tant que le SMTP n'est pas configuré)
+pub fn email_notification_payload(install_config: &InstallConfig) -> Value {
+    json!({
+        "enabled": false,
+        "types": 0,
+        "options": {
+            "emailFrom": install_config.admin_email(),
+            "senderName": install_config.admin_display_name()
+        }
+    })
+}
 
 /// Applique la configuration Jellyseerr depuis master_config (avec clé privée)
 pub async fn apply_config(
@@ -8,7 +175,7 @@ pub async fn apply_config(
     private_key: &str,
     config: &serde_json::Value,
 ) -> Result<()> {
-    println!("[Jellyseerr] Applying master configuration...");
+    tracing::debug!("Applying master configuration...");
 
     // Convertir la config en JSON string
     let config_str = serde_json::to_string_pretty(config)?;
@@ -25,7 +192,7 @@ echo "✅ Jellyseerr config written"
     // Écrire la config via SSH
     ssh::execute_command(host, username, private_key, &script).await?;
 
-    println!("[Jellyseerr] ✅ Configuration applied successfully");
+    tracing::info!("✅ Configuration applied successfully");
 
     // Redémarrer le container pour appliquer la config
     ssh::execute_command(
@@ -35,7 +202,7 @@ echo "✅ Jellyseerr config written"
         "cd ~/media-stack && docker-compose restart jellyseerr"
     ).await?;
 
-    println!("[Jellyseerr] ✅ Container restarted");
+    tracing::info!("✅ Container restarted");
 
     Ok(())
 }
@@ -48,11 +215,9 @@ pub async fn apply_config_password(
     config: &serde_json::Value,
     radarr_api_key: &str,
     sonarr_api_key: &str,
-    jellyfin_username: &str,
-    jellyfin_password: &str,
-    admin_email: &str,
+    install_config: &InstallConfig,
 ) -> Result<()> {
-    println!("[Jellyseerr] Applying master configuration...");
+    tracing::debug!("Applying master configuration...");
 
     // NOUVELLE STRATÉGIE 100% AUTONOME via API officielle:
     // 1. Clean la DB et redémarrer Jellyseerr
@@ -81,56 +246,18 @@ echo "✅ Jellyseerr cleanup done, container starting in background"
 
     ssh::execute_command_password(host, username, password, &script).await?;
 
-    println!("[Jellyseerr] ✅ Configuration applied successfully (fresh config)");
-
-    // Attendre que Jellyseerr démarre et que l'API soit prête
-    println!("[Jellyseerr] Waiting for API to be ready...");
-    let mut jellyseerr_ready = false;
-    for i in 0..36 {  // Max 3 minutes (36 * 5s)
-        // Vérifier d'abord si le container tourne
-        let container_status = ssh::execute_command_password(host, username, password,
-            "docker ps -a --filter name=jellyseerr --format '{{.Status}}' 2>/dev/null"
-        ).await.unwrap_or_default();
-
-        // Si le container a crashé ou est arrêté
-        if container_status.contains("Exited") || container_status.contains("Dead") {
-            let logs = ssh::execute_command_password(host, username, password,
-                "docker logs jellyseerr --tail 20 2>&1"
-            ).await.unwrap_or_default();
-
-            return Err(anyhow::anyhow!(
-                "Jellyseerr container crashed or exited unexpectedly!\n\nContainer status: {}\n\nLast logs:\n{}",
-                container_status.trim(),
-                logs
-            ));
-        }
-
-        // Tester l'API
-        let check = ssh::execute_command_password(host, username, password,
-            "curl -s 'http://localhost:5055/api/v1/status' 2>/dev/null || echo 'API_ERROR'"
-        ).await.unwrap_or_default();
-
-        println!("[Jellyseerr] Check {}/36: {}", i + 1, if check.contains("version") || check.contains("initialized") { "API ready" } else { "waiting..." });
+    tracing::info!("✅ Configuration applied successfully (fresh config)");
 
-        if check.contains("version") || check.contains("initialized") || check.len() > 10 {
-            jellyseerr_ready = true;
-            println!("[Jellyseerr] ✅ API ready after {} seconds", (i + 1) * 5);
-            break;
-        }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-    }
-
-    if !jellyseerr_ready {
-        // Récupérer les logs pour diagnostic
-        let logs = ssh::execute_command_password(host, username, password,
-            "docker logs jellyseerr --tail 30 2>&1"
-        ).await.unwrap_or_default();
-
-        return Err(anyhow::anyhow!(
-            "Jellyseerr API not ready after 180 seconds (3 minutes).\n\nPossible causes:\n- Container taking too long to start\n- Insufficient resources (RAM/CPU)\n- Configuration error\n\nLast logs:\n{}",
-            logs
-        ));
-    }
+    // Attendre que Jellyseerr démarre et que l'API soit prête (max 3 minutes)
+    super::wait_for_api(
+        &ssh::SshTarget::Password { host, username, password },
+        "jellyseerr",
+        5055,
+        "/api/v1/status",
+        status_ready,
+        std::time::Duration::from_secs(180),
+    )
+    .await?;
 
     // WORKFLOW COMPLET comme Buildarr:
     // 1. POST /auth/jellyfin (sauvegarde cookies)
@@ -138,131 +265,147 @@ echo "✅ Jellyseerr cleanup done, container starting in background"
     // 3. GET /settings/jellyfin/library?enable=... (avec cookies)
     // 4. POST /settings/initialize (avec cookies)
 
-    println!("[Jellyseerr] Initializing via Buildarr-style workflow...");
-
-    let initialization_script = format!(r#"
-# Fichier cookie temporaire
-COOKIE_FILE="/tmp/jellyseerr_cookies.txt"
-rm -f "$COOKIE_FILE"
+    tracing::debug!("Initializing via Buildarr-style workflow...");
+
+    ssh::execute_command_password(host, username, password, &format!("rm -f {}", COOKIE_FILE)).await?;
+
+    // 1. Authentification Jellyfin (crée le premier admin)
+    tracing::debug!("Step 1: Authenticating with Jellyfin...");
+    let auth_body = json!({
+        "hostname": "http://localhost:8096",
+        "username": install_config.jellyfin_username,
+        "password": install_config.jellyfin_password,
+        "email": install_config.admin_email()
+    });
+    let auth_result = ssh::execute_command_password(
+        host, username, password,
+        &curl_command("POST", "/auth/jellyfin", CurlAuth::SaveCookies, Some(&auth_body))
+    ).await?;
 
-echo "📡 Step 1: Authenticating with Jellyfin..."
-AUTH_RESULT=$(curl -s -c "$COOKIE_FILE" -X POST 'http://localhost:5055/api/v1/auth/jellyfin' \
-  -H 'Content-Type: application/json' \
-  -d '{{
-    "hostname": "http://localhost:8096",
-    "username": "{}",
-    "password": "{}",
-    "email": "{}"
-  }}')
+    if !is_auth_success(&auth_result) {
+        ssh::execute_command_password(host, username, password, &format!("rm -f {}", COOKIE_FILE)).await.ok();
+        return Err(anyhow!("Failed to authenticate with Jellyfin: {}", auth_result.trim()));
+    }
+    tracing::info!("✅ Authenticated successfully");
 
-echo "Auth response: $AUTH_RESULT"
+    // 2. Sync des bibliothèques Jellyfin
+    tracing::debug!("Step 2: Syncing Jellyfin libraries...");
+    let libraries = ssh::execute_command_password(
+        host, username, password,
+        &curl_command("GET", "/settings/jellyfin/library?sync=true", CurlAuth::Cookies, None)
+    ).await?;
 
-# Vérifier si auth a réussi (pas d'erreur critique)
-if echo "$AUTH_RESULT" | grep -q '"error"'; then
-  echo "❌ Authentication failed: $AUTH_RESULT"
-  rm -f "$COOKIE_FILE"
-  exit 1
-fi
+    let library_ids = match parse_library_ids(&libraries) {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("⚠️ {}", e);
+            Vec::new()
+        }
+    };
+
+    // 3. Activation des bibliothèques trouvées
+    if !library_ids.is_empty() {
+        let ids = library_ids.join(",");
+        tracing::info!("Step 3: Enabling libraries: {}", ids);
+        ssh::execute_command_password(
+            host, username, password,
+            &curl_command("GET", &format!("/settings/jellyfin/library?enable={}", ids), CurlAuth::Cookies, None)
+        ).await?;
+    }
 
-echo "✅ Authenticated successfully"
+    // 4. Finalisation
+    tracing::debug!("Step 4: Finalizing initialization...");
+    let init_result = ssh::execute_command_password(
+        host, username, password,
+        &curl_command("POST", "/settings/initialize", CurlAuth::Cookies, Some(&json!({})))
+    ).await?;
+    tracing::info!("Initialize response: {}", init_result.trim());
+
+    // 5. Compte admin: nom affiché, langue et expéditeur des notifications
+    let account_commands = [
+        curl_command("POST", "/settings/main", CurlAuth::Cookies, Some(&main_settings_payload(install_config))),
+        curl_command("POST", "/user/1/settings/main", CurlAuth::Cookies, Some(&admin_user_payload(install_config))),
+        curl_command("POST", "/settings/notifications/email", CurlAuth::Cookies, Some(&email_notification_payload(install_config))),
+    ];
+    for command in &account_commands {
+        if let Err(e) = ssh::execute_command_password(host, username, password, command).await {
+            tracing::warn!("⚠️ Account settings warning: {}", e);
+        }
+    }
 
-echo "📚 Step 2: Syncing Jellyfin libraries..."
-LIBRARIES=$(curl -s -b "$COOKIE_FILE" 'http://localhost:5055/api/v1/settings/jellyfin/library?sync=true')
-echo "Libraries: $LIBRARIES"
+    ssh::execute_command_password(host, username, password, &format!("rm -f {}", COOKIE_FILE)).await.ok();
 
-# Extraire les IDs des bibliothèques (Movies + TV)
-LIBRARY_IDS=$(echo "$LIBRARIES" | grep -o '"id":"[^"]*"' | cut -d'"' -f4 | tr '\n' ',' | sed 's/,$//')
-echo "Library IDs: $LIBRARY_IDS"
+    tracing::info!("✅ Admin created and initialized via Buildarr workflow");
 
-if [ -n "$LIBRARY_IDS" ]; then
-  echo "📝 Step 3: Enabling libraries: $LIBRARY_IDS"
-  curl -s -b "$COOKIE_FILE" "http://localhost:5055/api/v1/settings/jellyfin/library?enable=$LIBRARY_IDS" > /dev/null
-  echo "✅ Libraries enabled"
-fi
+    // Configurer Radarr et Sonarr via l'API Jellyseerr
+    // Cela garantit que les serveurs sont bien enregistrés dans la base de données
+    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    let client = super::arr_client::JellyseerrClient::connect(&ssh::SshTarget::Password { host, username, password }).await?;
 
-echo "🏁 Step 4: Finalizing initialization..."
-INIT_RESULT=$(curl -s -b "$COOKIE_FILE" -X POST 'http://localhost:5055/api/v1/settings/initialize')
-echo "Initialize response: $INIT_RESULT"
+    let media_paths = install_config.media_paths();
+    let radarr = arr_server_payload("radarr", "radarr", radarr_api_key, &media_paths.movies_path());
+    let mut sonarr = arr_server_payload("sonarr", "sonarr", sonarr_api_key, &media_paths.tv_path());
+    super::profiles::apply_anime_directory(&mut sonarr, install_config);
 
-# Nettoyer
-rm -f "$COOKIE_FILE"
+    client.upsert_arr_server("radarr", &radarr).await?;
+    client.upsert_arr_server("sonarr", &sonarr).await?;
 
-echo "✅ Jellyseerr fully initialized!"
-"#, jellyfin_username, jellyfin_password, admin_email);
+    tracing::info!("✅ Radarr and Sonarr configured via API");
 
-    let init_result = ssh::execute_command_password(
-        host,
-        username,
-        password,
-        &initialization_script
-    ).await?;
+    Ok(())
+}
 
-    println!("[Jellyseerr] Initialization output:\n{}", init_result);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_library_ids() {
+        let body = r#"[
+          {"id": "f137a2dd21bbc1b99aa5c0f6bf02a805", "name": "Films", "enabled": false},
+          {"id":"a656b907eb3a73532e40e44b968d0225","name":"Séries","enabled":false}
+        ]"#;
+        let ids = parse_library_ids(body).unwrap();
+        assert_eq!(ids, vec!["f137a2dd21bbc1b99aa5c0f6bf02a805", "a656b907eb3a73532e40e44b968d0225"]);
+        assert!(parse_library_ids("<html>502 Bad Gateway</html>").is_err());
+    }
 
-    if init_result.contains("Authentication failed") {
-        return Err(anyhow::anyhow!("Failed to authenticate with Jellyfin"));
+    #[test]
+    fn test_parse_settings_api_key() {
+        let settings = r#"{
+          "clientId": "0b5d2a8c",
+          "jellyfin": {"apiKey": "jellyfin-key"},
+          "main": {
+            "apiKey": "MTY5ODc2NTQzMjEwOQ==",
+            "applicationTitle": "Jellyseerr"
+          }
+        }"#;
+        assert_eq!(parse_settings_api_key(settings).unwrap(), "MTY5ODc2NTQzMjEwOQ==");
+        assert!(parse_settings_api_key("{}").is_err());
     }
 
-    println!("[Jellyseerr] ✅ Admin created and initialized via Buildarr workflow");
+    #[test]
+    fn test_find_existing_server() {
+        let servers: Vec<ArrServer> = serde_json::from_str(r#"[
+          {"id": 0, "name": "Radarr", "hostname": "radarr", "port": 7878, "is4k": false},
+          {"id": 3, "name": "Radarr 4K", "hostname": "192.168.1.20", "port": 7879, "is4k": true}
+        ]"#).unwrap();
 
-    // Configurer Radarr et Sonarr via l'API Jellyseerr
-    // Cela garantit que les serveurs sont bien enregistrés dans la base de données
-    let api_config_script = format!(r#"
-# Récupérer l'API key de Jellyseerr depuis settings.json
-API_KEY=$(cat ~/media-stack/jellyseerr/config/settings.json | grep -o '"apiKey":"[^"]*"' | head -1 | cut -d'"' -f4)
-
-# Attendre que Jellyseerr soit prêt
-sleep 5
-
-# Configurer Radarr via l'API
-curl -s -X POST "http://localhost:5055/api/v1/settings/radarr" \
-  -H "X-Api-Key: $API_KEY" \
-  -H "Content-Type: application/json" \
-  -d '{{
-    "name": "Radarr",
-    "hostname": "radarr",
-    "port": 7878,
-    "apiKey": "{}",
-    "useSsl": false,
-    "activeProfileId": 4,
-    "activeProfileName": "HD-1080p",
-    "activeDirectory": "/mnt/decypharr/movies",
-    "is4k": false,
-    "minimumAvailability": "released",
-    "isDefault": true,
-    "syncEnabled": true
-  }}' > /dev/null 2>&1
-
-# Configurer Sonarr via l'API
-curl -s -X POST "http://localhost:5055/api/v1/settings/sonarr" \
-  -H "X-Api-Key: $API_KEY" \
-  -H "Content-Type: application/json" \
-  -d '{{
-    "name": "Sonarr",
-    "hostname": "sonarr",
-    "port": 8989,
-    "apiKey": "{}",
-    "useSsl": false,
-    "activeProfileId": 4,
-    "activeProfileName": "HD-1080p",
-    "activeDirectory": "/mnt/decypharr/tv",
-    "is4k": false,
-    "enableSeasonFolders": true,
-    "isDefault": true,
-    "syncEnabled": true
-  }}' > /dev/null 2>&1
-
-echo "✅ Radarr and Sonarr configured via API"
-"#, radarr_api_key, sonarr_api_key);
-
-    ssh::execute_command_password(
-        host,
-        username,
-        password,
-        &api_config_script
-    ).await?;
+        let same_name = arr_server_payload("radarr", "192.168.1.20", "key", "/mnt/decypharr/movies");
+        assert_eq!(find_existing_server(&servers, &same_name), Some(0));
 
-    println!("[Jellyseerr] ✅ Radarr and Sonarr configured via API");
+        let mut renamed = arr_server_payload("radarr", "radarr", "key", "/mnt/decypharr/movies");
+        renamed["name"] = json!("Films");
+        assert_eq!(find_existing_server(&servers, &renamed), Some(0));
 
-    Ok(())
+        let sonarr = arr_server_payload("sonarr", "sonarr", "key", "/mnt/decypharr/tv");
+        assert_eq!(find_existing_server(&servers, &sonarr), None);
+    }
+
+    #[test]
+    fn test_is_auth_success() {
+        assert!(is_auth_success(r#"{"id":1,"email":"admin@easyjelly.local"}"#));
+        assert!(!is_auth_success(r#"{"message":"Unauthorized","error":"INVALID_CREDENTIALS"}"#));
+        assert!(!is_auth_success(""));
+    }
 }
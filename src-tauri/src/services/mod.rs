@@ -2,56 +2,215 @@ pub mod jellyseerr;
 pub mod radarr;
 pub mod sonarr;
 pub mod prowlarr;
+pub mod bazarr;
+pub mod decypharr;
+pub mod flaresolverr;
 pub mod jellyfin;
+pub mod jellyfin_plugins;
+pub mod jellyfin_template;
+pub mod profiles;
+pub mod policies;
+pub mod arr;
+pub mod arr_client;
+pub mod review;
+mod api_keys;
+mod readiness;
 
-use anyhow::Result;
-use crate::ssh;
+pub use api_keys::{harvest_api_keys, parse_config_xml_api_key, ArrApiKeys};
+pub use readiness::{arr_ping_ok, wait_for_api, ServiceNotReady};
+
+use anyhow::{anyhow, Result};
+use crate::ssh::SshTarget;
 use crate::template_engine::TemplateVars;
+use serde_json::Value;
+use crate::master_config::MasterConfig;
+use crate::InstallConfig;
 
-/// Applique la configuration d'un service sur le Pi via SSH (clé privée)
-pub async fn apply_service_config(
-    host: &str,
-    username: &str,
-    private_key: &str,
-    service_name: &str,
-    config_json: &serde_json::Value,
-    vars: &TemplateVars,
-) -> Result<()> {
-    println!("[Services] Applying {} configuration...", service_name);
+/// Ordre d'application des configurations master_config:
+/// Decypharr d'abord (client de téléchargement des *arr), puis Radarr/Sonarr (Prowlarr et Bazarr
+/// s'y connectent), Jellyfin avant Jellyseerr (qui s'y connecte)
+pub const SERVICE_ORDER: [&str; 7] = ["decypharr", "radarr", "sonarr", "prowlarr", "bazarr", "jellyfin", "jellyseerr"];
+
+/// Services toujours installés (le stack ne fonctionne pas sans eux)
+pub const REQUIRED_SERVICES: [&str; 5] = ["decypharr", "jellyfin", "radarr", "sonarr", "prowlarr"];
+
+/// Services que l'utilisateur peut exclure (`InstallConfig::enabled_services`)
+pub const OPTIONAL_SERVICES: [&str; 4] = ["jellyseerr", "bazarr", "flaresolverr", "supabazarr"];
+
+/// Nom affiché d'un service (messages de progression)
+pub fn display_name(service_name: &str) -> String {
+    let mut chars = service_name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Requête HTTP exécutée sur le Pi (corps JSON via heredoc, aucun échappement shell)
+pub fn local_curl(method: &str, url: &str, headers: &[String], body: Option<&Value>) -> String {
+    let headers: String = headers.iter().map(|h| format!(" -H '{}'", h)).collect();
+
+    match body {
+        Some(body) => format!(
+            "curl -s -X {} '{}'{} -H 'Content-Type: application/json' --data-binary @- <<'LOCAL_JSON'\n{}\nLOCAL_JSON",
+            method, url, headers, body
+        ),
+        None => format!("curl -s -X {} '{}'{}", method, url, headers),
+    }
+}
+
+/// Lit l'API key d'un *arr depuis son config.xml
+pub async fn read_arr_api_key(target: &SshTarget<'_>, service: &str) -> Result<String> {
+    let key = target
+        .exec(&format!(
+            "grep -oP '(?<=<ApiKey>)[^<]+' ~/media-stack/{}/config.xml 2>/dev/null || echo ''",
+            service
+        ))
+        .await?
+        .trim()
+        .to_string();
+
+    if key.is_empty() {
+        return Err(anyhow!("API key {} introuvable", service));
+    }
+    Ok(key)
+}
+
+/// Phase 1: remplace les variables dans les configs master_config, dans l'ordre des dépendances
+/// Le résultat peut être relu/modifié par l'utilisateur (voir `review`) avant application
+pub fn render_service_configs(master: &MasterConfig, vars: &TemplateVars) -> Vec<(String, Value)> {
+    SERVICE_ORDER
+        .iter()
+        .filter_map(|name| {
+            master
+                .service_config(name)
+                .map(|config| (name.to_string(), vars.replace_in_json(config)))
+        })
+        .collect()
+}
+
+/// Mode d'application d'une configuration master_config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigMode {
+    /// Première installation: bases des *arr et de Jellyseerr réinitialisées avant configuration
+    Fresh,
+    /// Reconfiguration: réglages patchés via les API, bibliothèques et historiques conservés
+    Merge,
+}
+
+/// Écrit après une première configuration réussie (supprimé avec ~/media-stack)
+const CONFIGURED_MARKER: &str = "~/media-stack/.jellysetup-configured";
+
+impl ConfigMode {
+    /// Mode selon la sortie de `test -f CONFIGURED_MARKER && echo configured`
+    pub fn from_marker(output: &str) -> Self {
+        if output.trim() == "configured" {
+            ConfigMode::Merge
+        } else {
+            ConfigMode::Fresh
+        }
+    }
 
-    // Remplacer les variables dans la config
-    let resolved_config = vars.replace_in_json(config_json);
+    /// Merge si le stack a déjà été configuré une fois sur ce Pi
+    pub async fn detect(target: &SshTarget<'_>) -> Self {
+        let output = target
+            .exec(&format!("test -f {} && echo configured || true", CONFIGURED_MARKER))
+            .await
+            .unwrap_or_default();
+        let mode = Self::from_marker(&output);
+        tracing::info!("Configuration mode: {:?}", mode);
+        mode
+    }
+
+    /// Les prochaines configurations de ce Pi se feront en Merge
+    pub async fn mark_configured(target: &SshTarget<'_>) -> Result<()> {
+        target.exec(&format!("date -Iseconds > {}", CONFIGURED_MARKER)).await?;
+        Ok(())
+    }
+}
+
+/// Configuration rendue d'un service, à appliquer sur le Pi
+pub struct ServiceConfig<'a> {
+    pub service_name: &'a str,
+    pub resolved_config: &'a Value,
+    pub vars: &'a TemplateVars,
+    pub mode: ConfigMode,
+}
+
+/// Services re-configurables sans réinitialisation (API uniquement, aucune base supprimée)
+pub const RECONFIGURABLE_SERVICES: [&str; 5] = ["decypharr", "prowlarr", "radarr", "sonarr", "bazarr"];
+
+/// Ré-applique une config rendue sur un service déjà installé, sans effacer ses données
+/// (`ConfigMode::Merge` de `apply_service_config`)
+pub async fn reconfigure(target: SshTarget<'_>, service_name: &str, resolved_config: &Value, vars: &TemplateVars) -> Result<()> {
+    match service_name {
+        "decypharr" => decypharr::configure(target, resolved_config, vars).await,
+        "prowlarr" => prowlarr::configure(target, resolved_config, vars).await,
+        "radarr" => radarr::configure(target, resolved_config, vars).await,
+        "sonarr" => sonarr::configure(target, resolved_config, vars).await,
+        "bazarr" => bazarr::configure(target, resolved_config, vars).await,
+        _ => Err(anyhow!("Service {} non re-configurable sans réinstallation", service_name)),
+    }
+}
+
+/// Mode Merge: API des services re-configurables; Jellyseerr est conservé tel quel
+/// (sa configuration initiale efface comptes et demandes). `None`: même chemin qu'en Fresh.
+async fn merge_config(target: SshTarget<'_>, service_name: &str, resolved_config: &Value, vars: &TemplateVars) -> Option<Result<()>> {
+    if RECONFIGURABLE_SERVICES.contains(&service_name) {
+        return Some(reconfigure(target, service_name, resolved_config, vars).await);
+    }
+    if service_name == "jellyseerr" {
+        tracing::info!("Jellyseerr already configured: users and requests kept");
+        return Some(Ok(()));
+    }
+    None
+}
+
+/// Phase 2: applique la configuration rendue d'un service sur le Pi via SSH (clé privée)
+#[tracing::instrument(skip_all, fields(service = %service.service_name, host = %host))]
+pub async fn apply_service_config(host: &str, username: &str, private_key: &str, service: ServiceConfig<'_>) -> Result<()> {
+    let ServiceConfig { service_name, resolved_config, vars, mode } = service;
+    tracing::debug!("Applying {} configuration ({:?})...", service_name, mode);
+
+    if mode == ConfigMode::Merge {
+        if let Some(outcome) = merge_config(SshTarget::Key { host, username, private_key }, service_name, resolved_config, vars).await {
+            return outcome;
+        }
+    }
 
     // Appliquer la config selon le service
     match service_name {
-        "jellyseerr" => jellyseerr::apply_config(host, username, private_key, &resolved_config).await,
-        "radarr" => radarr::apply_config(host, username, private_key, &resolved_config).await,
-        "sonarr" => sonarr::apply_config(host, username, private_key, &resolved_config).await,
-        "prowlarr" => prowlarr::apply_config(host, username, private_key, &resolved_config).await,
-        "jellyfin" => jellyfin::apply_config(host, username, private_key, &resolved_config).await,
+        "jellyseerr" => jellyseerr::apply_config(host, username, private_key, resolved_config).await,
+        "radarr" => radarr::apply_config(host, username, private_key, resolved_config, vars).await,
+        "sonarr" => sonarr::apply_config(host, username, private_key, resolved_config, vars).await,
+        "prowlarr" => prowlarr::apply_config(host, username, private_key, resolved_config, vars).await,
+        "bazarr" => bazarr::apply_config(host, username, private_key, resolved_config, vars).await,
+        "decypharr" => decypharr::apply_config(host, username, private_key, resolved_config, vars).await,
+        "jellyfin" => jellyfin::apply_config(host, username, private_key, resolved_config, vars).await,
         _ => {
-            println!("[Services] Unknown service: {}", service_name);
+            tracing::info!("Unknown service: {}", service_name);
             Ok(())
         }
     }
 }
 
-/// Applique la configuration d'un service sur le Pi via SSH (mot de passe)
+/// Phase 2: applique la configuration rendue d'un service sur le Pi via SSH (mot de passe)
+#[tracing::instrument(skip_all, fields(service = %service.service_name, host = %host))]
 pub async fn apply_service_config_password(
     host: &str,
     username: &str,
     password: &str,
-    service_name: &str,
-    config_json: &serde_json::Value,
-    vars: &TemplateVars,
-    jellyfin_username: &str,
-    jellyfin_password: &str,
-    admin_email: &str,
+    service: ServiceConfig<'_>,
+    install_config: &InstallConfig,
 ) -> Result<()> {
-    println!("[Services] Applying {} configuration...", service_name);
+    let ServiceConfig { service_name, resolved_config, vars, mode } = service;
+    tracing::debug!("Applying {} configuration ({:?})...", service_name, mode);
 
-    // Remplacer les variables dans la config
-    let resolved_config = vars.replace_in_json(config_json);
+    if mode == ConfigMode::Merge {
+        if let Some(outcome) = merge_config(SshTarget::Password { host, username, password }, service_name, resolved_config, vars).await {
+            return outcome;
+        }
+    }
 
     // Appliquer la config selon le service
     match service_name {
@@ -73,18 +232,31 @@ pub async fn apply_service_config_password(
                 .unwrap_or("");
 
             jellyseerr::apply_config_password(
-                host, username, password, &resolved_config,
+                host, username, password, resolved_config,
                 radarr_api, sonarr_api,
-                jellyfin_username, jellyfin_password, admin_email
+                install_config
             ).await
         },
-        "radarr" => radarr::apply_config_password(host, username, password, &resolved_config).await,
-        "sonarr" => sonarr::apply_config_password(host, username, password, &resolved_config).await,
-        "prowlarr" => prowlarr::apply_config_password(host, username, password, &resolved_config).await,
-        "jellyfin" => jellyfin::apply_config_password(host, username, password, &resolved_config).await,
+        "radarr" => radarr::apply_config_password(host, username, password, resolved_config, vars).await,
+        "sonarr" => sonarr::apply_config_password(host, username, password, resolved_config, vars).await,
+        "prowlarr" => prowlarr::apply_config_password(host, username, password, resolved_config, vars).await,
+        "bazarr" => bazarr::apply_config_password(host, username, password, resolved_config, vars).await,
+        "decypharr" => decypharr::apply_config_password(host, username, password, resolved_config, vars).await,
+        "jellyfin" => jellyfin::apply_config_password(host, username, password, resolved_config, vars).await,
         _ => {
-            println!("[Services] Unknown service: {}", service_name);
+            tracing::info!("Unknown service: {}", service_name);
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_mode_from_marker() {
+        assert_eq!(ConfigMode::from_marker("configured\n"), ConfigMode::Merge);
+        assert_eq!(ConfigMode::from_marker(""), ConfigMode::Fresh);
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::master_config::Policies;
+use crate::ssh::SshTarget;
+
+use super::arr_client::{ArrClient, JellyseerrClient};
+
+// =============================================================================
+// Politiques par défaut (section "policies" de master_config)
+// =============================================================================
+
+/// Applique les politiques via les APIs des services
+/// Chaque politique est indépendante: un échec est loggé sans bloquer les autres
+pub async fn apply_policies(target: &SshTarget<'_>, policies: &Policies) -> Result<()> {
+    tracing::info!("Applying policies: {:?}", policies);
+
+    if let Some(max) = policies.max_concurrent_downloads {
+        if let Err(e) = apply_decypharr_max_downloads(target, max).await {
+            tracing::warn!("⚠️ Decypharr: {}", e);
+        }
+    }
+
+    if let Some(payload) = jellyseerr_quota_payload(policies) {
+        let outcome = match JellyseerrClient::connect(target).await {
+            Ok(client) => client.update_main_settings(&payload).await,
+            Err(e) => Err(e),
+        };
+        match outcome {
+            Ok(()) => tracing::info!("Jellyseerr: default quotas applied"),
+            Err(e) => tracing::warn!("⚠️ Jellyseerr: {}", e),
+        }
+    }
+
+    if let Some(unmonitor) = policies.radarr_unmonitor_downloaded {
+        if let Err(e) = apply_arr_unmonitor(target, "radarr", "autoUnmonitorPreviouslyDownloadedMovies", unmonitor).await {
+            tracing::warn!("⚠️ Radarr: {}", e);
+        }
+    }
+
+    if let Some(unmonitor) = policies.sonarr_unmonitor_downloaded {
+        if let Err(e) = apply_arr_unmonitor(target, "sonarr", "autoUnmonitorPreviouslyDownloadedEpisodes", unmonitor).await {
+            tracing::warn!("⚠️ Sonarr: {}", e);
+        }
+    }
+
+    tracing::info!("✅ Policies applied");
+    Ok(())
+}
+
+/// Quotas par défaut des utilisateurs Jellyseerr (None si aucun quota défini)
+pub fn jellyseerr_quota_payload(policies: &Policies) -> Option<Value> {
+    if policies.movie_quota.is_none() && policies.tv_quota.is_none() {
+        return None;
+    }
+
+    let quota = |q: &Option<crate::master_config::Quota>| match q {
+        Some(q) => json!({ "quotaLimit": q.limit, "quotaDays": q.days }),
+        // 0 = illimité côté Jellyseerr
+        None => json!({ "quotaLimit": 0, "quotaDays": 7 }),
+    };
+
+    Some(json!({
+        "defaultQuotas": {
+            "movie": quota(&policies.movie_quota),
+            "tv": quota(&policies.tv_quota)
+        }
+    }))
+}
+
+/// Limite les téléchargements simultanés dans config.json de Decypharr puis redémarre le conteneur
+async fn apply_decypharr_max_downloads(target: &SshTarget<'_>, max: u32) -> Result<()> {
+    let raw = target.exec("cat ~/media-stack/decypharr/config.json").await?;
+    let mut config: Value = serde_json::from_str(raw.trim())
+        .map_err(|e| anyhow!("config.json Decypharr invalide: {}", e))?;
+
+    config
+        .get_mut("qbittorrent")
+        .and_then(|q| q.as_object_mut())
+        .ok_or_else(|| anyhow!("Section qbittorrent absente de config.json"))?
+        .insert("max_downloads".to_string(), json!(max));
+
+    crate::delta_sync::push_file(target, "~/media-stack/decypharr/config.json", &serde_json::to_string_pretty(&config)?).await?;
+    target.exec("cd ~/media-stack && nohup docker compose restart decypharr > /dev/null 2>&1 &").await?;
+
+    tracing::info!("Decypharr: max {} concurrent downloads", max);
+    Ok(())
+}
+
+/// Active/désactive le "unmonitor" automatique des médias déjà téléchargés (Radarr/Sonarr)
+async fn apply_arr_unmonitor(target: &SshTarget<'_>, service: &'static str, field: &str, value: bool) -> Result<()> {
+    ArrClient::connect(target, service)
+        .await?
+        .update_config("mediamanagement", &json!({ field: value }))
+        .await?;
+
+    tracing::info!("{}: {} = {}", service, field, value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::master_config::Quota;
+
+    #[test]
+    fn test_jellyseerr_quota_payload() {
+        assert!(jellyseerr_quota_payload(&Policies::default()).is_none());
+
+        let policies = Policies {
+            movie_quota: Some(Quota { limit: 5, days: 7 }),
+            ..Default::default()
+        };
+        let payload = jellyseerr_quota_payload(&policies).unwrap();
+        assert_eq!(payload["defaultQuotas"]["movie"]["quotaLimit"], 5);
+        assert_eq!(payload["defaultQuotas"]["tv"]["quotaLimit"], 0);
+    }
+}
@@ -0,0 +1,397 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::master_config::{MasterConfig, MediaPaths};
+use crate::ssh::SshTarget;
+use crate::template_engine::TemplateVars;
+use crate::InstallConfig;
+
+use super::arr_client::ArrClient;
+use super::local_curl;
+
+// =============================================================================
+// Profils de bibliothèques optionnels (anime, musique, livres, livres audio)
+// =============================================================================
+//
+// Un profil est coché comme les autres services (`InstallConfig::enabled_services`),
+// mais n'est jamais installé par défaut:
+// - anime          : root folder + tag Sonarr, bibliothèque Jellyfin "Anime"
+// - lidarr         : conteneur Lidarr + root folder, bibliothèque Jellyfin "Musique"
+// - readarr        : conteneur Readarr + root folder, bibliothèque Jellyfin "Livres"
+// - audiobookshelf : conteneur Audiobookshelf + bibliothèque, bibliothèque Jellyfin "Livres audio"
+//
+// Chaque profil peut être ajusté par sa section master_config (anime_config,
+// lidarr_config, readarr_config, audiobookshelf_config), après remplacement des
+// variables {{...}}. Toutes les étapes sont idempotentes (réinstallation, reprise).
+
+/// Services des profils, à cocher explicitement
+pub const PROFILE_SERVICES: [&str; 4] = ["anime", "lidarr", "readarr", "audiobookshelf"];
+
+/// Compte root Audiobookshelf généré pour le Pi (jamais les identifiants Jellyfin)
+const AUDIOBOOKSHELF_CREDENTIALS: &str = "~/media-stack/audiobookshelf/credentials.json";
+const AUDIOBOOKSHELF_USERNAME: &str = "root";
+
+/// Profil de bibliothèque optionnel, en plus de Films/Séries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaProfile {
+    Anime,
+    Music,
+    Books,
+    Audiobooks,
+}
+
+impl MediaProfile {
+    pub const ALL: [MediaProfile; 4] = [
+        MediaProfile::Anime,
+        MediaProfile::Music,
+        MediaProfile::Books,
+        MediaProfile::Audiobooks,
+    ];
+
+    /// Case à cocher du profil dans la sélection des services
+    pub fn service(self) -> &'static str {
+        match self {
+            MediaProfile::Anime => "anime",
+            MediaProfile::Music => "lidarr",
+            MediaProfile::Books => "readarr",
+            MediaProfile::Audiobooks => "audiobookshelf",
+        }
+    }
+
+    /// Section master_config du profil
+    fn master_section(self, master: &MasterConfig) -> Option<&Value> {
+        match self {
+            MediaProfile::Anime => master.anime_config.as_ref(),
+            MediaProfile::Music => master.lidarr_config.as_ref(),
+            MediaProfile::Books => master.readarr_config.as_ref(),
+            MediaProfile::Audiobooks => master.audiobookshelf_config.as_ref(),
+        }
+    }
+
+    fn default_library_name(self) -> &'static str {
+        match self {
+            MediaProfile::Anime => "Anime",
+            MediaProfile::Music => "Musique",
+            MediaProfile::Books => "Livres",
+            MediaProfile::Audiobooks => "Livres audio",
+        }
+    }
+
+    /// Type de collection Jellyfin
+    fn collection_type(self) -> &'static str {
+        match self {
+            MediaProfile::Anime => "tvshows",
+            MediaProfile::Music => "music",
+            MediaProfile::Books | MediaProfile::Audiobooks => "books",
+        }
+    }
+
+    fn path(self, paths: &MediaPaths) -> String {
+        match self {
+            MediaProfile::Anime => paths.anime_path(),
+            MediaProfile::Music => paths.music_path(),
+            MediaProfile::Books => paths.books_path(),
+            MediaProfile::Audiobooks => paths.audiobooks_path(),
+        }
+    }
+}
+
+/// Ajoute le dossier anime au serveur Sonarr déclaré dans Jellyseerr (si le profil est actif)
+pub fn apply_anime_directory(sonarr_payload: &mut Value, config: &InstallConfig) {
+    if config.service_enabled(MediaProfile::Anime.service()) {
+        sonarr_payload["activeAnimeDirectory"] = json!(config.media_paths().anime_path());
+    }
+}
+
+/// Configure les profils sélectionnés (services dédiés + bibliothèques Jellyfin)
+/// Un profil en échec n'empêche pas les suivants
+pub async fn apply_profiles(
+    target: &SshTarget<'_>,
+    config: &InstallConfig,
+    paths: &MediaPaths,
+    master: Option<&MasterConfig>,
+) -> Result<()> {
+    let profiles = config.profiles();
+    if profiles.is_empty() {
+        return Ok(());
+    }
+
+    let mut vars = TemplateVars::new();
+    paths.apply_to(&mut vars);
+
+    let jellyfin_session =
+        super::jellyfin::authenticate(target, &config.jellyfin_username, config.jellyfin_password.expose()).await;
+    if let Err(e) = &jellyfin_session {
+        tracing::warn!(
+            "⚠️ Jellyfin auth failed, libraries will not be created: {}",
+            e
+        );
+    }
+
+    for profile in profiles {
+        tracing::debug!("Configuring {:?} profile...", profile);
+
+        let section = master
+            .and_then(|m| profile.master_section(m))
+            .map(|s| vars.replace_in_json(s))
+            .unwrap_or(Value::Null);
+
+        let path = section
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| profile.path(paths));
+
+        let library_name = section
+            .get("libraryName")
+            .and_then(|v| v.as_str())
+            .unwrap_or(profile.default_library_name());
+
+        let outcome = match profile {
+            MediaProfile::Anime => configure_sonarr_anime(target, &path, &section).await,
+            MediaProfile::Music => {
+                configure_root_folder(
+                    target,
+                    "lidarr",
+                    crate::config::ports::LIDARR,
+                    &lidarr_root_folder(&path, library_name, &section),
+                )
+                .await
+            }
+            MediaProfile::Books => {
+                configure_root_folder(
+                    target,
+                    "readarr",
+                    crate::config::ports::READARR,
+                    &readarr_root_folder(&path, library_name, &section),
+                )
+                .await
+            }
+            MediaProfile::Audiobooks => configure_audiobookshelf(target, &path, library_name).await,
+        };
+
+        if let Err(e) = outcome {
+            tracing::warn!("⚠️ {:?}: {}", profile, e);
+        }
+
+        if let Ok(session) = &jellyfin_session {
+            let library = super::jellyfin::ensure_library(
+                target,
+                &session.access_token,
+                library_name,
+                profile.collection_type(),
+                &path,
+            );
+            if let Err(e) = library.await {
+                tracing::warn!("⚠️ Jellyfin library {}: {}", library_name, e);
+            }
+        }
+    }
+
+    tracing::info!("✅ Profiles configured");
+    Ok(())
+}
+
+/// Anime: root folder dédié + tag "anime" dans Sonarr
+async fn configure_sonarr_anime(target: &SshTarget<'_>, path: &str, section: &Value) -> Result<()> {
+    let sonarr = ArrClient::connect(target, "sonarr").await?;
+    sonarr.ensure_root_folder(path).await?;
+
+    let tag = section.get("tag").and_then(|v| v.as_str()).unwrap_or("anime");
+    sonarr.ensure_tag(tag).await?;
+
+    tracing::info!("Sonarr: anime root folder {} ready", path);
+    Ok(())
+}
+
+/// Root folder Lidarr avec ses profils par défaut
+fn lidarr_root_folder(path: &str, name: &str, section: &Value) -> Value {
+    json!({
+        "name": name,
+        "path": path,
+        "defaultMetadataProfileId": section.get("metadataProfileId").and_then(|v| v.as_u64()).unwrap_or(1),
+        "defaultQualityProfileId": section.get("qualityProfileId").and_then(|v| v.as_u64()).unwrap_or(1),
+        "defaultMonitorOption": "all",
+        "defaultNewItemMonitorOption": "all",
+        "defaultTags": []
+    })
+}
+
+/// Root folder Readarr (même format que Lidarr, hors bibliothèque Calibre)
+fn readarr_root_folder(path: &str, name: &str, section: &Value) -> Value {
+    let mut payload = lidarr_root_folder(path, name, section);
+    payload["isCalibreLibrary"] = json!(false);
+    payload
+}
+
+/// Musique, livres: attend le *arr puis ajoute le root folder
+async fn configure_root_folder(
+    target: &SshTarget<'_>,
+    service: &'static str,
+    port: u16,
+    payload: &Value,
+) -> Result<()> {
+    super::wait_for_api(
+        target,
+        service,
+        port,
+        "/ping",
+        super::arr_ping_ok,
+        std::time::Duration::from_secs(120),
+    )
+    .await?;
+    ArrClient::connect(target, service)
+        .await?
+        .ensure_root_folder_with(payload)
+        .await?;
+    Ok(())
+}
+
+fn audiobookshelf_url(endpoint: &str) -> String {
+    format!("http://localhost:{}{}", crate::config::ports::AUDIOBOOKSHELF, endpoint)
+}
+
+async fn audiobookshelf_json(
+    target: &SshTarget<'_>,
+    method: &str,
+    endpoint: &str,
+    token: Option<&str>,
+    body: Option<&Value>,
+) -> Result<Value> {
+    let headers: Vec<String> = token
+        .map(|t| format!("Authorization: Bearer {}", t))
+        .into_iter()
+        .collect();
+    let response = target
+        .exec(&local_curl(method, &audiobookshelf_url(endpoint), &headers, body))
+        .await?;
+    serde_json::from_str(response.trim()).map_err(|e| anyhow!("Réponse Audiobookshelf {} invalide: {}", endpoint, e))
+}
+
+/// Identifiants root: créés au premier lancement puis relus depuis le Pi
+async fn audiobookshelf_credentials(target: &SshTarget<'_>) -> Result<Value> {
+    let status = audiobookshelf_json(target, "GET", "/status", None, None).await?;
+    if status.get("isInit").and_then(|v| v.as_bool()).unwrap_or(false) {
+        let saved = target
+            .exec(&format!("cat {} 2>/dev/null", AUDIOBOOKSHELF_CREDENTIALS))
+            .await
+            .unwrap_or_default();
+        let credentials: Value = serde_json::from_str(saved.trim()).map_err(|_| {
+            anyhow!(
+                "Audiobookshelf déjà initialisé sans identifiants dans {}",
+                AUDIOBOOKSHELF_CREDENTIALS
+            )
+        })?;
+        if let Some(password) = credentials.get("password").and_then(|v| v.as_str()) {
+            crate::redact::register(password);
+        }
+        return Ok(credentials);
+    }
+
+    let password = crate::crypto::generate_strong_password(crate::crypto::DEFAULT_PASSWORD_LENGTH)?.password;
+    crate::redact::register(&password);
+    let credentials = json!({ "username": AUDIOBOOKSHELF_USERNAME, "password": password });
+    target
+        .exec(&format!(
+            "umask 077 && mkdir -p \"$(dirname {path})\" && cat > {path} << 'EOFABS'\n{content}\nEOFABS",
+            path = AUDIOBOOKSHELF_CREDENTIALS,
+            content = credentials
+        ))
+        .await?;
+    target
+        .exec(&local_curl(
+            "POST",
+            &audiobookshelf_url("/init"),
+            &[],
+            Some(&json!({ "newRoot": credentials })),
+        ))
+        .await?;
+
+    tracing::info!(
+        "Audiobookshelf: root account created, credentials in {}",
+        AUDIOBOOKSHELF_CREDENTIALS
+    );
+    Ok(credentials)
+}
+
+/// Noms des bibliothèques de GET /api/libraries (`{"libraries": [...]}`, tableau nu sur les anciennes versions)
+fn audiobookshelf_library_names(response: &Value) -> Vec<String> {
+    response
+        .get("libraries")
+        .unwrap_or(response)
+        .as_array()
+        .map(|libraries| {
+            libraries
+                .iter()
+                .filter_map(|l| l.get("name")?.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Livres audio: compte root propre à Audiobookshelf + bibliothèque
+async fn configure_audiobookshelf(target: &SshTarget<'_>, path: &str, name: &str) -> Result<()> {
+    super::wait_for_api(
+        target,
+        "audiobookshelf",
+        crate::config::ports::AUDIOBOOKSHELF,
+        "/healthcheck",
+        |body| !body.trim().is_empty(),
+        std::time::Duration::from_secs(120),
+    )
+    .await?;
+
+    let credentials = audiobookshelf_credentials(target).await?;
+    let login = audiobookshelf_json(target, "POST", "/login", None, Some(&credentials)).await?;
+    let token = login
+        .pointer("/user/token")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Token Audiobookshelf absent"))?;
+
+    let libraries = audiobookshelf_json(target, "GET", "/api/libraries", Some(token), None).await?;
+    if audiobookshelf_library_names(&libraries).iter().any(|n| n == name) {
+        tracing::info!("Audiobookshelf: library {} already exists", name);
+        return Ok(());
+    }
+
+    let library = json!({
+        "name": name,
+        "folders": [{ "fullPath": path }],
+        "mediaType": "book"
+    });
+    audiobookshelf_json(target, "POST", "/api/libraries", Some(token), Some(&library)).await?;
+
+    tracing::info!("Audiobookshelf: library {} created ({})", name, path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_and_payloads() {
+        let services: Vec<&str> = MediaProfile::ALL.iter().map(|p| p.service()).collect();
+        assert_eq!(services, PROFILE_SERVICES);
+        assert_eq!(serde_json::to_value(MediaProfile::Books).unwrap(), "books");
+
+        let section = json!({ "qualityProfileId": 3 });
+        let lidarr = lidarr_root_folder("/mnt/decypharr/music", "Musique", &section);
+        assert_eq!(lidarr["defaultQualityProfileId"], 3);
+        assert_eq!(lidarr["defaultMetadataProfileId"], 1);
+        assert!(lidarr.get("isCalibreLibrary").is_none());
+        let readarr = readarr_root_folder("/mnt/decypharr/books", "Livres", &Value::Null);
+        assert_eq!(readarr["path"], "/mnt/decypharr/books");
+        assert_eq!(readarr["isCalibreLibrary"], false);
+
+        let libraries = json!({ "libraries": [{ "id": "lib_1", "name": "Livres audio" }] });
+        assert_eq!(audiobookshelf_library_names(&libraries), vec!["Livres audio"]);
+        assert_eq!(
+            audiobookshelf_library_names(&json!([{ "name": "Podcasts" }])),
+            vec!["Podcasts"]
+        );
+        assert!(audiobookshelf_library_names(&json!({ "error": "Unauthorized" })).is_empty());
+    }
+}
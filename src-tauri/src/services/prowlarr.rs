@@ -1,5 +1,13 @@
 use anyhow::Result;
-use crate::ssh;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::ssh::{self, SshTarget};
+use crate::template_engine::TemplateVars;
+
+use super::arr::merge_fields;
+use super::arr_client::ArrClient;
+use super::flaresolverr;
 
 /// Applique la configuration Prowlarr depuis master_config (avec clé privée)
 pub async fn apply_config(
@@ -7,29 +15,22 @@ pub async fn apply_config(
     username: &str,
     private_key: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Prowlarr] Applying master configuration...");
-
-    // Prowlarr gère les indexers
-    if let Some(indexers) = config.get("indexers").and_then(|v| v.as_array()) {
-        println!("[Prowlarr] Configuring {} indexers...", indexers.len());
+    tracing::debug!("Applying master configuration...");
 
-        // TODO: Implémenter la configuration via API Prowlarr
-        println!("[Prowlarr] Indexers config received: {}", serde_json::to_string_pretty(indexers)?);
-    }
-
-    println!("[Prowlarr] ✅ Configuration applied");
-    Ok(())
+    configure(SshTarget::Key { host, username, private_key }, config, vars).await
 }
 
-/// Applique la configuration Prowlarr depuis master_config (avec mot de passe)
+/// Applique la configuration Prowlarr depuis master_config (avec mot de passe, mode Fresh: base réinitialisée)
 pub async fn apply_config_password(
     host: &str,
     username: &str,
     password: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Prowlarr] Applying master configuration...");
+    tracing::debug!("Applying master configuration...");
 
     // IMPORTANT: Supprimer la DB Prowlarr pour repartir sur une base propre
     // Utiliser docker run avec Alpine pour éviter sudo
@@ -44,39 +45,159 @@ cd ~/media-stack && docker compose up -d prowlarr
 "#;
 
     ssh::execute_command_password(host, username, password, cleanup_script).await?;
-    println!("[Prowlarr] ✅ Database cleaned and service restarted");
-
-    // Attendre que Prowlarr démarre et crée la base de données
-    println!("[Prowlarr] Waiting for database initialization...");
-    let mut prowlarr_ready = false;
-    for i in 0..24 {  // Max 2 minutes (24 * 5s)
-        // Vérifier si Prowlarr répond sur son API
-        let check = ssh::execute_command_password(host, username, password,
-            "curl -s 'http://localhost:9696/api/v1/system/status' 2>/dev/null || echo 'API_ERROR'"
-        ).await.unwrap_or_default();
-
-        println!("[Prowlarr] Check {}/24: {}", i + 1, if check.contains("instanceName") { "API ready" } else { "waiting..." });
-
-        if check.contains("instanceName") || check.contains("\"version\"") {
-            prowlarr_ready = true;
-            println!("[Prowlarr] ✅ Database ready after {} seconds", (i + 1) * 5);
-            break;
+    tracing::info!("✅ Database cleaned and service restarted");
+
+    // Attendre que Prowlarr démarre et recrée sa base de données
+    let target = SshTarget::Password { host, username, password };
+    super::wait_for_api(&target, "prowlarr", 9696, "/ping", super::arr_ping_ok, Duration::from_secs(120)).await?;
+
+    configure(target, config, vars).await
+}
+
+/// Configuration via l'API v1: proxy FlareSolverr, indexers, applications Radarr/Sonarr
+///
+/// Clés master_config reconnues:
+/// - `indexers`: `{ name, definitionName, fields: [{name, value}], flaresolverr }`, valeurs
+///   déjà rendues (ex: `{{YGG_PASSKEY}}`); un indexer dont un champ est vide est ignoré
+/// - `flareSolverrUrl` (défaut: conteneur flaresolverr du compose, s'il est installé)
+pub(super) async fn configure(target: SshTarget<'_>, config: &Value, vars: &TemplateVars) -> Result<()> {
+    let client = ArrClient::connect(&target, "prowlarr").await?;
+
+    // Proxy FlareSolverr, appliqué aux indexers portant le tag "flaresolverr"; ignoré si
+    // le conteneur a été exclu du stack (sauf URL explicite dans master_config)
+    let explicit_url = config.get("flareSolverrUrl").and_then(|v| v.as_str());
+    let tag_id = if explicit_url.is_some() || vars.get(flaresolverr::ENABLED_VAR) == Some("true") {
+        Some(flaresolverr::register_proxy(&client, explicit_url.unwrap_or(flaresolverr::DEFAULT_URL)).await?)
+    } else {
+        tracing::info!("FlareSolverr not in the stack, proxy not registered");
+        None
+    };
+
+    // Indexers déclarés dans master_config
+    if let Some(indexers) = config.get("indexers").and_then(|v| v.as_array()) {
+        tracing::debug!("Configuring {} indexers...", indexers.len());
+        let schemas: Value = client.get("/indexer/schema").await?;
+
+        for indexer in indexers {
+            let name = indexer.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+            if has_empty_field(indexer) {
+                tracing::warn!("⚠️ {} skipped (missing credentials)", name);
+                continue;
+            }
+
+            let payload = indexer_payload(&schemas, indexer, tag_id);
+            if let Err(e) = client.upsert_by_name("/indexer", &payload).await {
+                tracing::warn!("⚠️ {}: {}", name, e);
+            }
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
     }
 
-    if !prowlarr_ready {
-        return Err(anyhow::anyhow!("Prowlarr not initialized after 120 seconds"));
+    // Applications: les indexers se synchronisent automatiquement vers Radarr/Sonarr
+    for (app, port) in [("Radarr", 7878), ("Sonarr", 8989)] {
+        let api_key = vars.get(&format!("{}_API_KEY", app.to_uppercase())).unwrap_or_default();
+        if api_key.is_empty() {
+            tracing::warn!("⚠️ {} API key unknown, application skipped", app);
+            continue;
+        }
+
+        client
+            .upsert_by_name(
+                "/applications",
+                &json!({
+                    "name": app,
+                    "implementation": app,
+                    "configContract": format!("{}Settings", app),
+                    "syncLevel": "fullSync",
+                    "enable": true,
+                    "fields": [
+                        { "name": "prowlarrUrl", "value": "http://prowlarr:9696" },
+                        { "name": "baseUrl", "value": format!("http://{}:{}", app.to_lowercase(), port) },
+                        { "name": "apiKey", "value": api_key }
+                    ]
+                }),
+            )
+            .await?;
     }
 
-    // Prowlarr gère les indexers
-    if let Some(indexers) = config.get("indexers").and_then(|v| v.as_array()) {
-        println!("[Prowlarr] Configuring {} indexers...", indexers.len());
+    tracing::info!("✅ Configuration applied");
+    Ok(())
+}
+
+/// Un champ déclaré sans valeur (variable de template non fournie, ex: pas de passkey YGG)
+fn has_empty_field(indexer: &Value) -> bool {
+    indexer
+        .get("fields")
+        .and_then(|f| f.as_array())
+        .map(|fields| fields.iter().any(|f| f.get("value").and_then(|v| v.as_str()) == Some("")))
+        .unwrap_or(false)
+}
 
-        // TODO: Implémenter la configuration via API Prowlarr
-        println!("[Prowlarr] Indexers config received: {}", serde_json::to_string_pretty(indexers)?);
+/// Complète l'indexer avec le schéma de sa définition (tous les champs attendus par Prowlarr)
+fn indexer_payload(schemas: &Value, indexer: &Value, flaresolverr_tag: Option<u64>) -> Value {
+    let definition = indexer.get("definitionName").and_then(|v| v.as_str());
+    let schema = schemas
+        .as_array()
+        .and_then(|all| all.iter().find(|s| s.get("definitionName").and_then(|v| v.as_str()) == definition))
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+
+    let mut payload = merge_fields(&schema, &json!({ "enable": true, "appProfileId": 1 }));
+
+    // Valeurs des champs fusionnées par nom, le reste du schéma est conservé
+    let mut fields = schema.get("fields").and_then(|f| f.as_array()).cloned().unwrap_or_default();
+    for field in indexer.get("fields").and_then(|f| f.as_array()).into_iter().flatten() {
+        let name = field.get("name");
+        match fields.iter_mut().find(|f| f.get("name") == name) {
+            Some(existing) => existing["value"] = field.get("value").cloned().unwrap_or(Value::Null),
+            None => fields.push(field.clone()),
+        }
     }
 
-    println!("[Prowlarr] ✅ Configuration applied");
-    Ok(())
+    for (key, value) in indexer.as_object().into_iter().flatten() {
+        if key != "fields" && key != "flaresolverr" {
+            payload[key] = value.clone();
+        }
+    }
+    payload["fields"] = json!(fields);
+
+    if let Some(tag) = flaresolverr_tag.filter(|_| indexer.get("flaresolverr").and_then(|v| v.as_bool()).unwrap_or(false)) {
+        payload["tags"] = json!([tag]);
+    }
+
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexer_payload_merges_schema() {
+        let schemas = json!([{
+            "definitionName": "yggtorrent",
+            "implementation": "Cardigann",
+            "configContract": "CardigannSettings",
+            "fields": [
+                { "name": "definitionFile", "value": "yggtorrent" },
+                { "name": "passkey", "value": null }
+            ]
+        }]);
+        let indexer = json!({
+            "name": "YGGTorrent",
+            "definitionName": "yggtorrent",
+            "flaresolverr": true,
+            "fields": [{ "name": "passkey", "value": "abc123" }]
+        });
+
+        let payload = indexer_payload(&schemas, &indexer, Some(3));
+        assert_eq!(payload["implementation"], "Cardigann");
+        assert_eq!(payload["name"], "YGGTorrent");
+        assert_eq!(payload["fields"][0]["value"], "yggtorrent");
+        assert_eq!(payload["fields"][1]["value"], "abc123");
+        assert_eq!(payload["tags"], json!([3]));
+        assert!(payload.get("flaresolverr").is_none());
+        assert!(indexer_payload(&schemas, &indexer, None).get("tags").is_none());
+
+        assert!(has_empty_field(&json!({ "fields": [{ "name": "passkey", "value": "" }] })));
+    }
 }
@@ -1,5 +1,12 @@
-use anyhow::Result;
-use crate::ssh;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::ssh::{self, SshTarget};
+use crate::template_engine::TemplateVars;
+
+use super::arr::decypharr_client_payload;
+use super::arr_client::{ArrClient, DownloadClient};
 
 /// Applique la configuration Radarr depuis master_config (avec clé privée)
 pub async fn apply_config(
@@ -7,32 +14,22 @@ pub async fn apply_config(
     username: &str,
     private_key: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Radarr] Applying master configuration...");
-
-    // Radarr utilise un fichier config.xml
-    // On va extraire les indexers et les configurer via l'API Radarr
-
-    if let Some(indexers) = config.get("indexers").and_then(|v| v.as_array()) {
-        println!("[Radarr] Configuring {} indexers...", indexers.len());
-
-        // TODO: Implémenter la configuration via API Radarr
-        // Pour l'instant on log juste qu'on a reçu la config
-        println!("[Radarr] Indexers config received: {}", serde_json::to_string_pretty(indexers)?);
-    }
+    tracing::debug!("Applying master configuration...");
 
-    println!("[Radarr] ✅ Configuration applied");
-    Ok(())
+    configure(SshTarget::Key { host, username, private_key }, config, vars).await
 }
 
-/// Applique la configuration Radarr depuis master_config (avec mot de passe)
+/// Applique la configuration Radarr depuis master_config (avec mot de passe, mode Fresh: base réinitialisée)
 pub async fn apply_config_password(
     host: &str,
     username: &str,
     password: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Radarr] Applying master configuration...");
+    tracing::debug!("Applying master configuration...");
 
     // IMPORTANT: Supprimer la DB Radarr pour repartir sur une base propre
     // Utiliser docker run avec Alpine pour éviter sudo
@@ -47,42 +44,102 @@ cd ~/media-stack && docker compose up -d radarr
 "#;
 
     ssh::execute_command_password(host, username, password, cleanup_script).await?;
-    println!("[Radarr] ✅ Database cleaned and service restarted");
-
-    // Attendre que Radarr démarre et crée la base de données
-    println!("[Radarr] Waiting for database initialization...");
-    let mut radarr_ready = false;
-    for i in 0..24 {  // Max 2 minutes (24 * 5s)
-        // Vérifier si Radarr répond sur son API
-        let check = ssh::execute_command_password(host, username, password,
-            "curl -s 'http://localhost:7878/api/v3/system/status' 2>/dev/null || echo 'API_ERROR'"
-        ).await.unwrap_or_default();
-
-        println!("[Radarr] Check {}/24: {}", i + 1, if check.contains("instanceName") { "API ready" } else { "waiting..." });
-
-        if check.contains("instanceName") || check.contains("\"version\"") {
-            radarr_ready = true;
-            println!("[Radarr] ✅ Database ready after {} seconds", (i + 1) * 5);
-            break;
+    tracing::info!("✅ Database cleaned and service restarted");
+
+    // Attendre que Radarr démarre et recrée sa base de données
+    let target = SshTarget::Password { host, username, password };
+    super::wait_for_api(&target, "radarr", 7878, "/ping", super::arr_ping_ok, Duration::from_secs(120)).await?;
+
+    configure(target, config, vars).await
+}
+
+/// Dossier racine des films: master_config, puis MOVIES_PATH, puis le montage Decypharr
+fn root_folder<'a>(config: &'a Value, vars: &'a TemplateVars) -> &'a str {
+    config
+        .get("rootFolderPath")
+        .and_then(|v| v.as_str())
+        .or_else(|| vars.get("MOVIES_PATH"))
+        .unwrap_or("/mnt/decypharr/movies")
+}
+
+/// Client de téléchargement Decypharr (catégorie `radarr` par défaut)
+fn download_client(config: &Value, vars: &TemplateVars) -> Result<DownloadClient> {
+    let payload = decypharr_client_payload(
+        config.get("downloadClient"),
+        vars.get("PI_IP").unwrap_or("localhost"),
+        "movieCategory",
+        "radarr",
+    );
+    Ok(serde_json::from_value(payload)?)
+}
+
+/// Configuration via l'API v3: root folder, profils de qualité, client Decypharr
+///
+/// Clés master_config reconnues: `rootFolderPath`, `qualityProfiles` (fusionnés par nom),
+/// `downloadClient` (`name`, `host`, `port`, `category`).
+pub(super) async fn configure(target: SshTarget<'_>, config: &Value, vars: &TemplateVars) -> Result<()> {
+    let client = ArrClient::connect(&target, "radarr").await?;
+    // Chaque étape est tentée même si la précédente échoue; les échecs sont remontés ensemble
+    let mut failures = Vec::new();
+
+    let root_folder = root_folder(config, vars);
+    if let Err(e) = client.ensure_root_folder(root_folder).await {
+        tracing::warn!("Root folder {} not created: {}", root_folder, e);
+        failures.push(format!("dossier racine ({})", e));
+    }
+
+    if let Some(profiles) = config.get("qualityProfiles").and_then(|v| v.as_array()) {
+        if let Err(e) = client.apply_quality_profiles(profiles).await {
+            tracing::warn!("Quality profiles not applied: {}", e);
+            failures.push(format!("profils de qualité ({})", e));
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
     }
 
-    if !radarr_ready {
-        return Err(anyhow::anyhow!("Radarr not initialized after 120 seconds"));
+    let download_client = download_client(config, vars)?;
+    if let Err(e) = client.upsert_download_client(&download_client).await {
+        tracing::warn!("Download client not registered: {}", e);
+        failures.push(format!("client de téléchargement ({})", e));
     }
 
-    // Radarr utilise un fichier config.xml
-    // On va extraire les indexers et les configurer via l'API Radarr
+    if !failures.is_empty() {
+        return Err(anyhow!("Configuration Radarr incomplète: {}", failures.join("; ")));
+    }
 
-    if let Some(indexers) = config.get("indexers").and_then(|v| v.as_array()) {
-        println!("[Radarr] Configuring {} indexers...", indexers.len());
+    // Les indexers sont synchronisés par Prowlarr (fullSync)
+    tracing::info!("✅ Configuration applied");
+    Ok(())
+}
 
-        // TODO: Implémenter la configuration via API Radarr
-        // Pour l'instant on log juste qu'on a reçu la config
-        println!("[Radarr] Indexers config received: {}", serde_json::to_string_pretty(indexers)?);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_root_folder() {
+        let mut vars = TemplateVars::new();
+        assert_eq!(root_folder(&json!({}), &vars), "/mnt/decypharr/movies");
+        vars.set("MOVIES_PATH", "/data/films");
+        assert_eq!(root_folder(&json!({}), &vars), "/data/films");
+        assert_eq!(root_folder(&json!({ "rootFolderPath": "/movies" }), &vars), "/movies");
     }
 
-    println!("[Radarr] ✅ Configuration applied");
-    Ok(())
+    #[test]
+    fn test_download_client() {
+        let mut vars = TemplateVars::new();
+        vars.set("PI_IP", "192.168.1.20");
+        let field = |client: &DownloadClient, name: &str| {
+            client.fields.iter().find(|f| f.name == name).map(|f| f.value.clone()).unwrap()
+        };
+
+        let client = download_client(&json!({}), &vars).unwrap();
+        assert_eq!(client.name, "Decypharr");
+        assert_eq!(client.implementation, "QBittorrent");
+        assert_eq!(field(&client, "host"), "192.168.1.20");
+        assert_eq!(field(&client, "movieCategory"), "radarr");
+
+        let client = download_client(&json!({ "downloadClient": { "category": "films", "port": 8383 } }), &vars).unwrap();
+        assert_eq!(field(&client, "movieCategory"), "films");
+        assert_eq!(field(&client, "port"), 8383);
+    }
 }
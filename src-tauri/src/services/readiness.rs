@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Attente générique de disponibilité d'une API de service
+// =============================================================================
+//
+// Remplace les boucles "curl toutes les 5s, N fois" de chaque service: on
+// interroge l'API via le Pi (localhost) et on vérifie à chaque tour que le
+// conteneur n'a pas crashé, pour échouer tout de suite avec ses logs au lieu
+// d'attendre la fin du délai.
+
+const POLL_INTERVAL_SECS: u64 = 5;
+
+/// Redémarrages au-delà desquels un conteneur "restarting" est considéré en crash loop
+const MAX_RESTARTS: u32 = 3;
+
+/// Service qui n'est pas devenu disponible
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceNotReady {
+    #[error("{service}: conteneur arrêté ({status})\n\nDerniers logs:\n{logs}")]
+    ContainerExited { service: String, status: String, logs: String },
+    #[error("{service}: conteneur en boucle de redémarrage ({restarts} redémarrages)\n\nDerniers logs:\n{logs}")]
+    CrashLoop { service: String, restarts: u32, logs: String },
+    #[error("{service}: API non disponible après {secs} secondes\n\nDerniers logs:\n{logs}")]
+    Timeout { service: String, secs: u64, logs: String },
+    #[error("{service}: attente interrompue (opération annulée)")]
+    Cancelled { service: String },
+}
+
+/// État du conteneur lu par `docker inspect` ("running 0", "exited 2", ...)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerState {
+    Running,
+    /// Pas encore créé (compose en cours) ou état transitoire
+    Starting,
+    Exited(String),
+    Restarting(u32),
+}
+
+pub fn parse_container_state(output: &str) -> ContainerState {
+    let mut parts = output.split_whitespace();
+    let status = parts.next().unwrap_or_default();
+    let restarts = parts.next().and_then(|r| r.parse().ok()).unwrap_or(0);
+
+    match status {
+        "running" => ContainerState::Running,
+        "restarting" => ContainerState::Restarting(restarts),
+        "exited" | "dead" => ContainerState::Exited(status.to_string()),
+        _ => ContainerState::Starting,
+    }
+}
+
+async fn container_logs(target: &SshTarget<'_>, container: &str) -> String {
+    target
+        .exec(&format!("docker logs {} --tail 20 2>&1", container))
+        .await
+        .unwrap_or_default()
+}
+
+/// Attend que `http://localhost:{port}{path}` réponde un corps accepté par `matcher`
+///
+/// `container` sert à la détection de crash et aux logs joints à l'erreur.
+/// Retourne le corps de la réponse acceptée.
+pub async fn wait_for_api(
+    target: &SshTarget<'_>,
+    container: &str,
+    port: u16,
+    path: &str,
+    matcher: fn(&str) -> bool,
+    timeout: Duration,
+) -> std::result::Result<String, ServiceNotReady> {
+    let attempts = (timeout.as_secs() / POLL_INTERVAL_SECS).max(1);
+    let display_name = super::display_name(container);
+    let probe = format!("curl -s -m 5 'http://localhost:{}{}' 2>/dev/null", port, path);
+    let inspect = format!(
+        "docker inspect -f '{{{{.State.Status}}}} {{{{.RestartCount}}}}' {} 2>/dev/null",
+        container
+    );
+
+    for attempt in 1..=attempts {
+        let state = parse_container_state(&target.exec(&inspect).await.unwrap_or_default());
+        match state {
+            ContainerState::Exited(status) => {
+                return Err(ServiceNotReady::ContainerExited {
+                    service: display_name,
+                    status,
+                    logs: container_logs(target, container).await,
+                });
+            }
+            ContainerState::Restarting(restarts) if restarts >= MAX_RESTARTS => {
+                return Err(ServiceNotReady::CrashLoop {
+                    service: display_name,
+                    restarts,
+                    logs: container_logs(target, container).await,
+                });
+            }
+            _ => {}
+        }
+
+        let body = target.exec(&probe).await.unwrap_or_default();
+        if matcher(&body) {
+            tracing::info!(
+                "[{}] ✅ API ready after {} seconds",
+                display_name,
+                (attempt - 1) * POLL_INTERVAL_SECS
+            );
+            return Ok(body);
+        }
+
+        tracing::debug!("[{}] Waiting for API ({}/{})...", display_name, attempt, attempts);
+        if crate::operations::sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await.is_err() {
+            return Err(ServiceNotReady::Cancelled { service: display_name });
+        }
+    }
+
+    Err(ServiceNotReady::Timeout {
+        service: display_name,
+        secs: attempts * POLL_INTERVAL_SECS,
+        logs: container_logs(target, container).await,
+    })
+}
+
+/// Matcher des *arr: `/ping` répond {"status": "OK"} sans API key
+pub fn arr_ping_ok(body: &str) -> bool {
+    body.contains("OK")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_container_state() {
+        assert_eq!(parse_container_state("running 0\n"), ContainerState::Running);
+        assert_eq!(parse_container_state("restarting 4"), ContainerState::Restarting(4));
+        assert_eq!(parse_container_state("exited 1"), ContainerState::Exited("exited".to_string()));
+        assert_eq!(parse_container_state(""), ContainerState::Starting);
+        assert!(arr_ping_ok(r#"{"status": "OK"}"#));
+        assert!(!arr_ping_ok(""));
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use tauri::Window;
+use tokio::sync::oneshot;
+
+// =============================================================================
+// Relecture des configurations master_config avant application
+// =============================================================================
+//
+// Événement:
+//   backend -> frontend : "config-review" { review_id, services: [{ service, display_name, config }] }
+// Commandes:
+//   reveal_config_review(review_id, service)          -> config sans masquage
+//   submit_config_review(review_id, approved, edits)  -> reprend l'installation
+//
+// Les secrets sont masqués dans l'événement; une valeur éditée laissée à SECRET_MASK
+// conserve la valeur d'origine lors de la fusion.
+// Un refus ou une absence de réponse annule l'étape: aucune configuration non
+// validée n'est appliquée.
+
+pub const SECRET_MASK: &str = "••••••••";
+
+/// Sans réponse après ce délai, la relecture est annulée (rien n'est appliqué)
+const REVIEW_TIMEOUT_SECS: u64 = 30 * 60;
+
+struct PendingReview {
+    rendered: Vec<(String, Value)>,
+    sender: oneshot::Sender<ReviewDecision>,
+}
+
+enum ReviewDecision {
+    Approve(HashMap<String, Value>),
+    Cancel,
+}
+
+// Relectures en attente, indexées par ID
+static PENDING: Lazy<Mutex<HashMap<String, PendingReview>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn pending() -> Result<MutexGuard<'static, HashMap<String, PendingReview>>> {
+    PENDING.lock().map_err(|_| anyhow!("Review registry poisoned"))
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReviewedService {
+    service: String,
+    display_name: String,
+    config: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReviewPayload {
+    review_id: String,
+    services: Vec<ReviewedService>,
+}
+
+/// Clé contenant un secret (apiKey, password, token, passkey...)
+pub fn is_secret_key(key: &str) -> bool {
+    let key: String = key.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+    ["apikey", "password", "token", "passkey", "secret"].iter().any(|s| key.contains(s))
+}
+
+/// Copie de la config avec les valeurs secrètes remplacées par SECRET_MASK
+pub fn mask_secrets(value: &Value) -> Value {
+    match value {
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(k, v)| {
+                    let masked = match v {
+                        Value::String(s) if is_secret_key(k) && !s.is_empty() => Value::String(SECRET_MASK.to_string()),
+                        other => mask_secrets(other),
+                    };
+                    (k.clone(), masked)
+                })
+                .collect(),
+        ),
+        Value::Array(arr) => Value::Array(arr.iter().map(mask_secrets).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Fusionne la config éditée: les valeurs restées masquées reprennent l'original
+pub fn merge_edits(original: &Value, edited: &Value) -> Value {
+    match (original, edited) {
+        (_, Value::String(s)) if s == SECRET_MASK => original.clone(),
+        (Value::Object(orig), Value::Object(edit)) => Value::Object(
+            edit.iter()
+                .map(|(k, v)| (k.clone(), merge_edits(orig.get(k).unwrap_or(&Value::Null), v)))
+                .collect(),
+        ),
+        (Value::Array(orig), Value::Array(edit)) => Value::Array(
+            edit.iter()
+                .enumerate()
+                .map(|(i, v)| merge_edits(orig.get(i).unwrap_or(&Value::Null), v))
+                .collect(),
+        ),
+        (_, edited) => edited.clone(),
+    }
+}
+
+/// Envoie les configurations rendues au frontend et attend sa validation
+/// Retourne les configurations à appliquer (éditions fusionnées)
+pub async fn request_review(window: &Window, rendered: Vec<(String, Value)>) -> Result<Vec<(String, Value)>> {
+    let review_id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = oneshot::channel();
+
+    let payload = ReviewPayload {
+        review_id: review_id.clone(),
+        services: rendered
+            .iter()
+            .map(|(service, config)| ReviewedService {
+                service: service.clone(),
+                display_name: super::display_name(service),
+                config: mask_secrets(config),
+            })
+            .collect(),
+    };
+
+    pending()?.insert(
+        review_id.clone(),
+        PendingReview { rendered: rendered.clone(), sender },
+    );
+    let _ = window.emit("config-review", payload);
+    tracing::debug!("Waiting for user review ({})...", review_id);
+
+    let decision = tokio::time::timeout(std::time::Duration::from_secs(REVIEW_TIMEOUT_SECS), receiver).await;
+    if let Ok(mut pending) = pending() {
+        pending.remove(&review_id);
+    }
+
+    match decision {
+        Ok(Ok(ReviewDecision::Approve(edits))) => {
+            tracing::info!("✅ Approved ({} services edited)", edits.len());
+            Ok(rendered
+                .into_iter()
+                .map(|(service, config)| {
+                    let config = match edits.get(&service) {
+                        Some(edited) => merge_edits(&config, edited),
+                        None => config,
+                    };
+                    (service, config)
+                })
+                .collect())
+        }
+        Ok(Ok(ReviewDecision::Cancel)) | Ok(Err(_)) => Err(anyhow!("Configuration refusée par l'utilisateur")),
+        Err(_) => {
+            tracing::warn!("⚠️ No answer after {}s, review cancelled", REVIEW_TIMEOUT_SECS);
+            Err(anyhow!("Relecture sans réponse après {} minutes, configuration annulée", REVIEW_TIMEOUT_SECS / 60))
+        }
+    }
+}
+
+/// Config d'un service sans masquage (bouton "afficher les secrets")
+pub fn reveal(review_id: &str, service: &str) -> Result<Value> {
+    let pending = pending()?;
+    let review = pending
+        .get(review_id)
+        .ok_or_else(|| anyhow!("Relecture {} introuvable", review_id))?;
+
+    review
+        .rendered
+        .iter()
+        .find(|(name, _)| name == service)
+        .map(|(_, config)| config.clone())
+        .ok_or_else(|| anyhow!("Service {} absent de la relecture", service))
+}
+
+/// Réponse du frontend: validation ou refus
+/// `edits`: config complète éditée, par service (les services absents sont appliqués tels quels)
+pub fn submit(review_id: &str, approved: bool, edits: HashMap<String, Value>) -> Result<()> {
+    let review = pending()?
+        .remove(review_id)
+        .ok_or_else(|| anyhow!("Relecture {} introuvable", review_id))?;
+
+    let decision = if approved { ReviewDecision::Approve(edits) } else { ReviewDecision::Cancel };
+    review
+        .sender
+        .send(decision)
+        .map_err(|_| anyhow!("L'installation n'attend plus cette relecture"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mask_and_merge_edits() {
+        let original = json!({
+            "apiKey": "secret-key",
+            "url": "http://192.168.1.20:7878",
+            "servers": [{ "password": "hunter2", "name": "Radarr" }]
+        });
+
+        let masked = mask_secrets(&original);
+        assert_eq!(masked["apiKey"], SECRET_MASK);
+        assert_eq!(masked["servers"][0]["password"], SECRET_MASK);
+        assert_eq!(masked["url"], "http://192.168.1.20:7878");
+
+        let mut edited = masked.clone();
+        edited["url"] = json!("http://radarr:7878");
+        let merged = merge_edits(&original, &edited);
+        assert_eq!(merged["apiKey"], "secret-key");
+        assert_eq!(merged["servers"][0]["password"], "hunter2");
+        assert_eq!(merged["url"], "http://radarr:7878");
+    }
+}
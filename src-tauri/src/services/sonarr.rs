@@ -1,5 +1,12 @@
-use anyhow::Result;
-use crate::ssh;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::ssh::{self, SshTarget};
+use crate::template_engine::TemplateVars;
+
+use super::arr::decypharr_client_payload;
+use super::arr_client::{ArrClient, DownloadClient};
 
 /// Applique la configuration Sonarr depuis master_config (avec clé privée)
 pub async fn apply_config(
@@ -7,32 +14,22 @@ pub async fn apply_config(
     username: &str,
     private_key: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Sonarr] Applying master configuration...");
-
-    // Sonarr utilise un fichier config.xml
-    // On va extraire les indexers et les configurer via l'API Sonarr
-
-    if let Some(indexers) = config.get("indexers").and_then(|v| v.as_array()) {
-        println!("[Sonarr] Configuring {} indexers...", indexers.len());
-
-        // TODO: Implémenter la configuration via API Sonarr
-        // Pour l'instant on log juste qu'on a reçu la config
-        println!("[Sonarr] Indexers config received: {}", serde_json::to_string_pretty(indexers)?);
-    }
+    tracing::debug!("Applying master configuration...");
 
-    println!("[Sonarr] ✅ Configuration applied");
-    Ok(())
+    configure(SshTarget::Key { host, username, private_key }, config, vars).await
 }
 
-/// Applique la configuration Sonarr depuis master_config (avec mot de passe)
+/// Applique la configuration Sonarr depuis master_config (avec mot de passe, mode Fresh: base réinitialisée)
 pub async fn apply_config_password(
     host: &str,
     username: &str,
     password: &str,
     config: &serde_json::Value,
+    vars: &TemplateVars,
 ) -> Result<()> {
-    println!("[Sonarr] Applying master configuration...");
+    tracing::debug!("Applying master configuration...");
 
     // IMPORTANT: Supprimer la DB Sonarr pour repartir sur une base propre
     // Utiliser docker run avec Alpine pour éviter sudo
@@ -47,42 +44,88 @@ cd ~/media-stack && docker compose up -d sonarr
 "#;
 
     ssh::execute_command_password(host, username, password, cleanup_script).await?;
-    println!("[Sonarr] ✅ Database cleaned and service restarted");
-
-    // Attendre que Sonarr démarre et crée la base de données
-    println!("[Sonarr] Waiting for database initialization...");
-    let mut sonarr_ready = false;
-    for i in 0..24 {  // Max 2 minutes (24 * 5s)
-        // Vérifier si Sonarr répond sur son API
-        let check = ssh::execute_command_password(host, username, password,
-            "curl -s 'http://localhost:8989/api/v3/system/status' 2>/dev/null || echo 'API_ERROR'"
-        ).await.unwrap_or_default();
-
-        println!("[Sonarr] Check {}/24: {}", i + 1, if check.contains("instanceName") { "API ready" } else { "waiting..." });
-
-        if check.contains("instanceName") || check.contains("\"version\"") {
-            sonarr_ready = true;
-            println!("[Sonarr] ✅ Database ready after {} seconds", (i + 1) * 5);
-            break;
+    tracing::info!("✅ Database cleaned and service restarted");
+
+    // Attendre que Sonarr démarre et recrée sa base de données
+    let target = SshTarget::Password { host, username, password };
+    super::wait_for_api(&target, "sonarr", 8989, "/ping", super::arr_ping_ok, Duration::from_secs(120)).await?;
+
+    configure(target, config, vars).await
+}
+
+/// Configuration via l'API v3: root folder, profils, dossiers de saison, client Decypharr
+///
+/// Clés master_config reconnues: `rootFolderPath`, `qualityProfiles`, `languageProfiles`
+/// (Sonarr v3 uniquement), `seasonFolderFormat`, `naming` (fusionné dans config/naming),
+/// `downloadClient` (`name`, `host`, `port`, `category`).
+pub(super) async fn configure(target: SshTarget<'_>, config: &Value, vars: &TemplateVars) -> Result<()> {
+    let client = ArrClient::connect(&target, "sonarr").await?;
+
+    let root_folder = config
+        .get("rootFolderPath")
+        .and_then(|v| v.as_str())
+        .or_else(|| vars.get("TV_PATH"))
+        .unwrap_or("/mnt/decypharr/tv");
+    client.ensure_root_folder(root_folder).await?;
+
+    if let Some(profiles) = config.get("qualityProfiles").and_then(|v| v.as_array()) {
+        client.apply_quality_profiles(profiles).await?;
+    }
+
+    // Les profils de langue ont disparu avec Sonarr v4 (remplacés par les custom formats)
+    if let Some(profiles) = config.get("languageProfiles").and_then(|v| v.as_array()) {
+        if let Err(e) = client.apply_profiles("/languageprofile", profiles).await {
+            tracing::warn!("⚠️ Language profiles skipped: {}", e);
         }
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
     }
 
-    if !sonarr_ready {
-        return Err(anyhow::anyhow!("Sonarr not initialized after 120 seconds"));
+    if let Some(naming) = naming_overrides(config) {
+        client.update_config("naming", &naming).await?;
     }
 
-    // Sonarr utilise un fichier config.xml
-    // On va extraire les indexers et les configurer via l'API Sonarr
+    let download_client = decypharr_client_payload(
+        config.get("downloadClient"),
+        vars.get("PI_IP").unwrap_or("localhost"),
+        "tvCategory",
+        "sonarr",
+    );
+    let download_client: DownloadClient = serde_json::from_value(download_client)?;
+    client.upsert_download_client(&download_client).await?;
+
+    // Vérification: le root folder doit être visible par Sonarr
+    if !client.root_folders().await?.iter().any(|f| f.path.trim_end_matches('/') == root_folder.trim_end_matches('/')) {
+        return Err(anyhow!("Root folder {} absent de Sonarr après configuration", root_folder));
+    }
 
-    if let Some(indexers) = config.get("indexers").and_then(|v| v.as_array()) {
-        println!("[Sonarr] Configuring {} indexers...", indexers.len());
+    tracing::info!("✅ Configuration applied");
+    Ok(())
+}
 
-        // TODO: Implémenter la configuration via API Sonarr
-        // Pour l'instant on log juste qu'on a reçu la config
-        println!("[Sonarr] Indexers config received: {}", serde_json::to_string_pretty(indexers)?);
+/// Réglages config/naming (section `naming` + raccourci `seasonFolderFormat`)
+fn naming_overrides(config: &Value) -> Option<Value> {
+    let mut naming = config.get("naming").cloned().unwrap_or_else(|| json!({}));
+    if let Some(format) = config.get("seasonFolderFormat") {
+        naming["seasonFolderFormat"] = format.clone();
     }
 
-    println!("[Sonarr] ✅ Configuration applied");
-    Ok(())
+    naming.as_object().filter(|n| !n.is_empty())?;
+    Some(naming)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_naming_overrides() {
+        assert!(naming_overrides(&json!({ "rootFolderPath": "/tv" })).is_none());
+
+        let naming = naming_overrides(&json!({
+            "seasonFolderFormat": "Saison {season:00}",
+            "naming": { "renameEpisodes": true }
+        }))
+        .unwrap();
+        assert_eq!(naming["seasonFolderFormat"], "Saison {season:00}");
+        assert_eq!(naming["renameEpisodes"], true);
+    }
 }
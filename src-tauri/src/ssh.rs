@@ -1,19 +1,174 @@
 use anyhow::{anyhow, Result};
 use russh::*;
 use russh_keys::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
 use once_cell::sync::Lazy;
 use tokio::sync::Mutex as TokioMutex;
 
+use crate::operations::{self, Cancelled};
+use crate::secret::SecretString;
+
+/// Préfixe des erreurs de fingerprint (détecté côté UI)
+pub const HOST_KEY_MISMATCH: &str = "HOST_KEY_MISMATCH";
+
 // Stockage temporaire du dernier fingerprint capturé
 static LAST_HOST_FINGERPRINT: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
 
+// Fingerprints attendus par host (Supabase ou première connexion - TOFU)
+static PINNED_FINGERPRINTS: Lazy<Mutex<HashMap<String, String>>> =
+    Lazy::new(|| Mutex::new(load_pinned_fingerprints()));
+
+// Hostnames des cartes flashées par l'application (clés d'hôte régénérées au premier boot)
+static FRESHLY_FLASHED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
 // Session SSH persistante globale
 static PERSISTENT_SESSION: Lazy<TokioMutex<Option<PersistentSession>>> =
     Lazy::new(|| TokioMutex::new(None));
 
-struct Client {}
+// Bastions configurés par host cible (ProxyJump)
+static JUMP_HOSTS: Lazy<Mutex<HashMap<String, JumpHost>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Sessions bastion ouvertes, réutilisées pour chaque tunnel direct-tcpip
+static BASTION_SESSIONS: Lazy<TokioMutex<HashMap<String, client::Handle<Client>>>> =
+    Lazy::new(|| TokioMutex::new(HashMap::new()));
+
+/// Machine intermédiaire (bastion) pour joindre un Pi distant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JumpHost {
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    pub username: String,
+    pub password: Option<SecretString>,
+    pub private_key: Option<SecretString>,
+}
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+// Options SSH par défaut (modifiables depuis l'UI)
+static DEFAULT_SSH_OPTIONS: Lazy<Mutex<SshOptions>> =
+    Lazy::new(|| Mutex::new(SshOptions::default()));
+
+/// Options de connexion SSH (timeouts, retries, keepalive)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SshOptions {
+    /// Timeout de connexion TCP + handshake (secondes)
+    pub connect_timeout_secs: u64,
+    /// Timeout d'exécution d'une commande (secondes, 0 = aucun)
+    pub command_timeout_secs: u64,
+    /// Nombre de tentatives de connexion
+    pub retries: u32,
+    /// Délai avant la 2e tentative, doublé à chaque échec (millisecondes)
+    pub backoff_initial_ms: u64,
+    /// Délai maximum entre deux tentatives (millisecondes)
+    pub backoff_max_ms: u64,
+    /// Intervalle des keepalives SSH (0 = désactivé)
+    pub keepalive_interval_secs: u64,
+}
+
+impl Default for SshOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 15,
+            // Pas de limite par défaut: get-docker.sh, apt upgrade, docker pull/load
+            // durent plusieurs minutes. Les appelants qui veulent échouer vite
+            // passent un timeout explicite.
+            command_timeout_secs: 0,
+            retries: 3,
+            backoff_initial_ms: 2000,
+            backoff_max_ms: 30000,
+            keepalive_interval_secs: 15,
+        }
+    }
+}
+
+impl SshOptions {
+    fn connect_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    fn command_timeout(&self) -> Option<std::time::Duration> {
+        (self.command_timeout_secs > 0).then(|| std::time::Duration::from_secs(self.command_timeout_secs))
+    }
+
+    /// Options identiques avec un timeout de commande court (appels fail-fast)
+    pub fn fail_fast(secs: u64) -> Self {
+        Self { command_timeout_secs: secs, ..default_options() }
+    }
+
+    /// Délai avant la tentative suivante (backoff exponentiel plafonné)
+    fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.backoff_initial_ms.saturating_mul(factor).min(self.backoff_max_ms);
+        std::time::Duration::from_millis(delay)
+    }
+}
+
+/// Attend `future`, borné par `timeout` s'il est défini (Err = timeout)
+async fn with_timeout<F: std::future::Future>(
+    timeout: Option<std::time::Duration>,
+    future: F,
+) -> std::result::Result<F::Output, tokio::time::error::Elapsed> {
+    match timeout {
+        Some(limit) => tokio::time::timeout(limit, future).await,
+        None => Ok(future.await),
+    }
+}
+
+/// Options SSH courantes
+pub fn default_options() -> SshOptions {
+    DEFAULT_SSH_OPTIONS.lock().map(|o| o.clone()).unwrap_or_default()
+}
+
+/// Remplace les options SSH par défaut
+pub fn set_default_options(options: SshOptions) {
+    if let Ok(mut current) = DEFAULT_SSH_OPTIONS.lock() {
+        tracing::info!("Options updated: {:?}", options);
+        *current = options;
+    }
+}
+
+/// Config russh dérivée des options (keepalive)
+fn client_config(options: &SshOptions) -> Arc<client::Config> {
+    let keepalive_interval = if options.keepalive_interval_secs > 0 {
+        Some(std::time::Duration::from_secs(options.keepalive_interval_secs))
+    } else {
+        None
+    };
+
+    Arc::new(client::Config {
+        keepalive_interval,
+        ..Default::default()
+    })
+}
+
+pub(crate) struct Client {
+    host: String,
+    expected_fingerprint: Option<String>,
+}
+
+impl Client {
+    /// Crée le handler avec le fingerprint attendu pour ce host (s'il est connu)
+    fn new(host: &str) -> Self {
+        let expected_fingerprint = PINNED_FINGERPRINTS
+            .lock()
+            .ok()
+            .and_then(|pins| pins.get(host).cloned());
+
+        Self {
+            host: host.to_string(),
+            expected_fingerprint,
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl client::Handler for Client {
@@ -26,18 +181,115 @@ impl client::Handler for Client {
         let fingerprint = server_public_key.fingerprint();
 
         if let Ok(mut fp) = LAST_HOST_FINGERPRINT.lock() {
-            *fp = Some(fingerprint);
+            *fp = Some(fingerprint.clone());
+        }
+
+        match &self.expected_fingerprint {
+            Some(expected) if *expected != fingerprint => {
+                tracing::error!(
+                    "❌ Host key mismatch for {}: expected {}, got {}",
+                    self.host, expected, fingerprint
+                );
+                return Err(anyhow!(
+                    "{}: l'empreinte SSH de {} a changé (attendue {}, reçue {}). \
+                     Possible attaque MITM ou Pi reflashé. Si vous avez reflashé la carte SD, \
+                     oubliez l'empreinte enregistrée puis réessayez.",
+                    HOST_KEY_MISMATCH, self.host, expected, fingerprint
+                ));
+            }
+            Some(_) => {}
+            None => {
+                // TOFU: première connexion, on mémorise l'empreinte
+                tracing::info!("First connection to {}, pinning fingerprint {}", self.host, fingerprint);
+                if let Err(e) = pin_host_fingerprint(&self.host, &fingerprint) {
+                    tracing::warn!("⚠️ Could not persist fingerprint: {}", e);
+                }
+            }
         }
 
         Ok((self, true))
     }
 }
 
+/// Indique si une erreur de connexion provient d'une empreinte SSH différente
+pub fn is_host_key_mismatch(error: &anyhow::Error) -> bool {
+    error.to_string().contains(HOST_KEY_MISMATCH)
+}
+
+/// Fichier local des empreintes épinglées
+fn pinned_fingerprints_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("jellysetup").join("known_hosts.json"))
+}
+
+fn load_pinned_fingerprints() -> HashMap<String, String> {
+    pinned_fingerprints_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_pinned_fingerprints(pins: &HashMap<String, String>) -> Result<()> {
+    let path = pinned_fingerprints_path()
+        .ok_or_else(|| anyhow!("Cannot determine config directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(pins)?)?;
+    Ok(())
+}
+
+/// Épingle le fingerprint attendu pour un host (ex: depuis Supabase)
+pub fn pin_host_fingerprint(host: &str, fingerprint: &str) -> Result<()> {
+    let mut pins = PINNED_FINGERPRINTS
+        .lock()
+        .map_err(|_| anyhow!("Fingerprint store poisoned"))?;
+    pins.insert(host.to_string(), fingerprint.to_string());
+    save_pinned_fingerprints(&pins)
+}
+
+/// Oublie le fingerprint épinglé d'un host (après un reflash volontaire)
+pub fn forget_host_fingerprint(host: &str) -> Result<()> {
+    let mut pins = PINNED_FINGERPRINTS
+        .lock()
+        .map_err(|_| anyhow!("Fingerprint store poisoned"))?;
+    if pins.remove(host).is_some() {
+        tracing::info!("Forgot pinned fingerprint for {}", host);
+    }
+    save_pinned_fingerprints(&pins)
+}
+
+fn flashed_key(hostname: &str) -> String {
+    hostname.trim().trim_end_matches(".local").to_ascii_lowercase()
+}
+
+/// Note que la carte de `hostname` vient d'être flashée: son Pi aura de nouvelles clés d'hôte
+pub fn mark_freshly_flashed(hostname: &str) {
+    if let Ok(mut flashed) = FRESHLY_FLASHED.lock() {
+        flashed.insert(flashed_key(hostname));
+    }
+}
+
+/// Carte de `pi_name` flashée par l'application: oublie l'empreinte épinglée de `host`
+/// (sinon la première connexion au Pi reflashé échoue en HOST_KEY_MISMATCH)
+/// Renvoie true si l'empreinte a été oubliée
+pub fn forget_if_freshly_flashed(pi_name: &str, host: &str) -> Result<bool> {
+    let freshly_flashed = FRESHLY_FLASHED
+        .lock()
+        .map_err(|_| anyhow!("Flashed cards store poisoned"))?
+        .remove(&flashed_key(pi_name));
+    if !freshly_flashed {
+        return Ok(false);
+    }
+    forget_host_fingerprint(host)?;
+    forget_host_fingerprint(&format!("{}.local", flashed_key(pi_name)))?;
+    Ok(true)
+}
+
 /// Structure pour gérer une session SSH persistante
 struct PersistentSession {
     host: String,
     username: String,
-    password: String,
+    password: SecretString,
     session: client::Handle<Client>,
     command_count: u32,
 }
@@ -45,30 +297,26 @@ struct PersistentSession {
 impl PersistentSession {
     /// Crée une nouvelle session persistante
     async fn new(host: &str, username: &str, password: &str) -> Result<Self> {
-        println!("[SSH-PERSISTENT] Creating new persistent session to {}@{}", username, host);
+        crate::redact::register(password);
+        tracing::info!("Creating new persistent session to {}@{}", username, host);
 
-        let config = Arc::new(client::Config::default());
+        let options = default_options();
 
-        let mut session = match tokio::time::timeout(
-            std::time::Duration::from_secs(15),
-            client::connect(config, (host, 22), Client {})
-        ).await {
-            Ok(Ok(s)) => s,
-            Ok(Err(e)) => return Err(anyhow!("Connection failed: {}", e)),
-            Err(_) => return Err(anyhow!("Connection timeout")),
-        };
+        let mut session = open_transport(host, &options)
+            .await
+            .map_err(|e| anyhow!("Connection failed: {}", e))?;
 
         let auth_result = session.authenticate_password(username, password).await?;
         if !auth_result {
             return Err(anyhow!("Authentication failed"));
         }
 
-        println!("[SSH-PERSISTENT] ✅ Session established and authenticated");
+        tracing::info!("✅ Session established and authenticated");
 
         Ok(Self {
             host: host.to_string(),
             username: username.to_string(),
-            password: password.to_string(),
+            password: password.into(),
             session,
             command_count: 0,
         })
@@ -78,13 +326,8 @@ impl PersistentSession {
     async fn exec(&mut self, command: &str) -> Result<String> {
         self.command_count += 1;
 
-        // Log court pour les commandes
-        let cmd_preview = if command.len() > 60 {
-            format!("{}...", &command[..60])
-        } else {
-            command.to_string()
-        };
-        println!("[SSH-P #{}] {}", self.command_count, cmd_preview);
+        // Log court (et masqué) pour les commandes
+        tracing::info!("[SSH-P #{}] {}", self.command_count, crate::redact::preview(command, 60));
 
         // Ouvrir un channel pour cette commande - timeout court pour fail fast
         let mut channel = match tokio::time::timeout(
@@ -93,11 +336,11 @@ impl PersistentSession {
         ).await {
             Ok(Ok(ch)) => ch,
             Ok(Err(e)) => {
-                println!("[SSH-P] Channel failed: {}", e);
+                tracing::warn!("Channel failed: {}", e);
                 return Err(anyhow!("Channel open failed: {}", e));
             }
             Err(_) => {
-                println!("[SSH-P] Channel timeout");
+                tracing::warn!("Channel timeout");
                 return Err(anyhow!("Channel open timeout"));
             }
         };
@@ -107,31 +350,44 @@ impl PersistentSession {
         }
 
         let mut output = String::new();
+        let token = operations::current_token();
 
-        loop {
-            match channel.wait().await {
-                Some(ChannelMsg::Data { data }) => {
-                    output.push_str(&String::from_utf8_lossy(&data));
-                }
-                Some(ChannelMsg::ExtendedData { data, .. }) => {
-                    output.push_str(&String::from_utf8_lossy(&data));
-                }
-                Some(ChannelMsg::ExitStatus { exit_status }) => {
-                    if exit_status != 0 {
-                        tracing::warn!("Command exited with status {}: {}", exit_status, output);
+        let read_output = async {
+            loop {
+                match channel.wait().await {
+                    Some(ChannelMsg::Data { data }) => {
+                        output.push_str(&String::from_utf8_lossy(&data));
                     }
-                    break;
+                    Some(ChannelMsg::ExtendedData { data, .. }) => {
+                        output.push_str(&String::from_utf8_lossy(&data));
+                    }
+                    Some(ChannelMsg::ExitStatus { exit_status }) => {
+                        if exit_status != 0 {
+                            tracing::warn!("Command exited with status {}: {}", exit_status, crate::redact::redact(&output));
+                        }
+                        break;
+                    }
+                    Some(ChannelMsg::Eof) => break,
+                    None => break,
+                    _ => {}
                 }
-                Some(ChannelMsg::Eof) => break,
-                None => break,
-                _ => {}
             }
-        }
+        };
 
-        // Fermer proprement le channel
+        let cancelled = tokio::select! {
+            _ = read_output => false,
+            _ = token.cancelled() => true,
+        };
+
+        // Fermer proprement le channel (la session reste utilisable)
         let _ = channel.eof().await;
         let _ = channel.close().await;
 
+        if cancelled {
+            tracing::info!("[SSH-P #{}] Cancelled, channel closed", self.command_count);
+            return Err(Cancelled.into());
+        }
+
         // Attendre un peu pour que le channel se ferme complètement
         tokio::time::sleep(std::time::Duration::from_millis(50)).await;
 
@@ -156,17 +412,17 @@ pub fn get_last_host_fingerprint() -> Option<String> {
 pub fn clear_known_hosts_for_ip(ip: &str) -> Result<()> {
     use std::process::Command;
 
-    println!("[SSH] Clearing known_hosts entry for {}...", ip);
+    tracing::debug!("Clearing known_hosts entry for {}...", ip);
 
     let output = Command::new("ssh-keygen")
         .args(["-R", ip])
         .output()?;
 
     if output.status.success() {
-        println!("[SSH] Cleared known_hosts entry for {}", ip);
+        tracing::info!("Cleared known_hosts entry for {}", ip);
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        println!("[SSH] Warning clearing known_hosts: {}", stderr);
+        tracing::warn!("Failed clearing known_hosts: {}", stderr);
     }
 
     Ok(())
@@ -181,13 +437,13 @@ pub async fn init_persistent_session(host: &str, username: &str, password: &str)
         if existing.host == host && existing.username == username {
             // Vérifier que la session est encore vivante
             if existing.is_alive().await {
-                println!("[SSH-PERSISTENT] Reusing existing session ({} commands executed)", existing.command_count);
+                tracing::info!("Reusing existing session ({} commands executed)", existing.command_count);
                 return Ok(());
             } else {
-                println!("[SSH-PERSISTENT] Existing session is dead, recreating...");
+                tracing::debug!("Existing session is dead, recreating...");
             }
         } else {
-            println!("[SSH-PERSISTENT] Different host/user, creating new session");
+            tracing::info!("Different host/user, creating new session");
         }
     }
 
@@ -205,8 +461,9 @@ pub async fn exec_persistent(command: &str) -> Result<String> {
     if let Some(ref mut session) = *session_guard {
         match session.exec(command).await {
             Ok(output) => return Ok(output),
+            Err(e) if e.is::<Cancelled>() => return Err(e),
             Err(e) => {
-                println!("[SSH-PERSISTENT] Command failed, session might be dead: {}", e);
+                tracing::warn!("Command failed, session might be dead: {}", e);
                 // La session est morte, on la supprime
                 *session_guard = None;
                 return Err(anyhow!("Session dead: {}", e));
@@ -221,91 +478,367 @@ pub async fn exec_persistent(command: &str) -> Result<String> {
 pub async fn close_persistent_session() {
     let mut session_guard = PERSISTENT_SESSION.lock().await;
     if session_guard.is_some() {
-        println!("[SSH-PERSISTENT] Closing persistent session");
+        tracing::info!("Closing persistent session");
         *session_guard = None;
     }
 }
 
-/// Teste la connexion SSH avec clé privée
-pub async fn test_connection(host: &str, username: &str, private_key: &str) -> Result<bool> {
-    let config = Arc::new(client::Config::default());
+/// Ouvre une connexion SSH (sans authentification) avec retries et backoff exponentiel
+#[tracing::instrument(name = "ssh_connect", skip_all, fields(host = %host, label = %label))]
+async fn connect(host: &str, options: &SshOptions, label: &str) -> Result<client::Handle<Client>> {
+    let attempts = options.retries.max(1);
+    let mut last_error = None;
+
+    for attempt in 1..=attempts {
+        // Pas de nouvelle connexion pour une opération annulée
+        if operations::is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        match open_transport(host, options).await {
+            Ok(s) => {
+                tracing::info!("{}: connected (attempt {})", label, attempt);
+                return Ok(s);
+            }
+            Err(e) => {
+                if is_host_key_mismatch(&e) {
+                    return Err(e);
+                }
+                tracing::warn!("{}: connection failed (attempt {}): {}", label, attempt, e);
+                last_error = Some(e);
+            }
+        }
 
-    let key = russh_keys::decode_secret_key(private_key, None)?;
+        if attempt < attempts {
+            tokio::time::sleep(options.backoff(attempt)).await;
+        }
+    }
 
-    let mut session = match tokio::time::timeout(
-        std::time::Duration::from_secs(15),
-        client::connect(config, (host, 22), Client {})
-    ).await {
-        Ok(Ok(s)) => s,
-        Ok(Err(e)) => return Err(anyhow!("Connection failed: {}", e)),
-        Err(_) => return Err(anyhow!("Connection timeout after 15s")),
+    Err(anyhow!(
+        "Connection failed after {} attempts: {}",
+        attempts,
+        last_error.unwrap_or_else(|| anyhow!("unknown error"))
+    ))
+}
+
+/// Une tentative de connexion: directe, ou via le bastion configuré pour ce host
+async fn open_transport(host: &str, options: &SshOptions) -> Result<client::Handle<Client>> {
+    let jump_host = JUMP_HOSTS.lock().ok().and_then(|j| j.get(host).cloned());
+
+    let connecting = async {
+        match jump_host {
+            None => client::connect(client_config(options), (host, crate::config::ports::SSH), Client::new(host)).await,
+            Some(jump) => {
+                let channel = open_jump_channel(&jump, host, options).await?;
+                client::connect_stream(client_config(options), channel.into_stream(), Client::new(host)).await
+            }
+        }
     };
 
-    let auth_result = session
-        .authenticate_publickey(username, Arc::new(key))
+    match tokio::time::timeout(options.connect_timeout(), connecting).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("Connection timeout after {}s", options.connect_timeout_secs)),
+    }
+}
+
+/// Ouvre un channel direct-tcpip vers host:22 à travers le bastion (session réutilisée)
+async fn open_jump_channel(
+    jump: &JumpHost,
+    target: &str,
+    options: &SshOptions,
+) -> Result<Channel<client::Msg>> {
+    let mut bastions = BASTION_SESSIONS.lock().await;
+    let key = format!("{}@{}:{}", jump.username, jump.host, jump.port);
+
+    // Réutiliser la session bastion si elle est encore ouverte
+    if let Some(session) = bastions.get(&key) {
+        match session.channel_open_direct_tcpip(target, crate::config::ports::SSH.into(), "127.0.0.1", 0).await {
+            Ok(channel) => return Ok(channel),
+            Err(e) => {
+                tracing::debug!("Bastion session dead ({}), reconnecting...", e);
+                bastions.remove(&key);
+            }
+        }
+    }
+
+    tracing::debug!("Connecting to bastion {}...", key);
+    let mut session = client::connect(
+        client_config(options),
+        (jump.host.as_str(), jump.port),
+        Client::new(&jump.host),
+    )
+    .await?;
+
+    let authenticated = if let Some(ref private_key) = jump.private_key {
+        let key_pair = crate::ssh_keys::decode(private_key.expose())?;
+        session.authenticate_publickey(&jump.username, Arc::new(key_pair)).await?
+    } else if let Some(ref password) = jump.password {
+        session.authenticate_password(&jump.username, password.expose()).await?
+    } else {
+        return Err(anyhow!("Bastion {}: aucune clé ni mot de passe fourni", key));
+    };
+
+    if !authenticated {
+        return Err(anyhow!("Bastion {}: authentication failed", key));
+    }
+
+    tracing::info!("✅ Bastion authenticated, opening tunnel to {}:22", target);
+    let channel = session
+        .channel_open_direct_tcpip(target, crate::config::ports::SSH.into(), "127.0.0.1", 0)
         .await?;
 
-    session.disconnect(Disconnect::ByApplication, "", "").await?;
+    bastions.insert(key, session);
+    Ok(channel)
+}
 
-    Ok(auth_result)
+/// Vérifie si le port SSH du host accepte les connexions (directement ou via le bastion)
+pub async fn is_ssh_port_open(host: &str, timeout: std::time::Duration) -> bool {
+    let jump_host = JUMP_HOSTS.lock().ok().and_then(|j| j.get(host).cloned());
+
+    let probing = async {
+        match jump_host {
+            None => tokio::net::TcpStream::connect((host, crate::config::ports::SSH)).await.is_ok(),
+            Some(jump) => match open_jump_channel(&jump, host, &default_options()).await {
+                Ok(channel) => {
+                    let _ = channel.close().await;
+                    true
+                }
+                Err(_) => false,
+            },
+        }
+    };
+
+    tokio::time::timeout(timeout, probing).await.unwrap_or(false)
 }
 
-/// Teste la connexion SSH avec mot de passe
-pub async fn test_connection_password(host: &str, username: &str, password: &str) -> Result<bool> {
-    println!("[SSH] Connecting to {}@{}...", username, host);
+/// Configure (ou retire) le bastion à utiliser pour joindre un host
+pub fn set_jump_host(target_host: &str, jump_host: Option<JumpHost>) {
+    if let Ok(mut jumps) = JUMP_HOSTS.lock() {
+        match jump_host {
+            Some(jump) => {
+                tracing::info!("{} will be reached through {}@{}:{}", target_host, jump.username, jump.host, jump.port);
+                jumps.insert(target_host.to_string(), jump);
+            }
+            None => {
+                jumps.remove(target_host);
+            }
+        }
+    }
+}
 
-    let mut session = None;
-    let mut last_error = None;
+/// Ouvre une session authentifiée (clé privée prioritaire, sinon mot de passe)
+/// Utilisée par les fonctionnalités qui gardent la session ouverte (terminal, tunnels)
+pub(crate) async fn open_authenticated_session(
+    host: &str,
+    username: &str,
+    password: Option<&str>,
+    private_key: Option<&str>,
+) -> Result<client::Handle<Client>> {
+    let options = default_options();
+    let mut session = connect(host, &options, "open_session").await?;
+
+    let authenticated = if let Some(private_key) = private_key {
+        let key = crate::ssh_keys::decode(private_key)?;
+        session.authenticate_publickey(username, Arc::new(key)).await?
+    } else if let Some(password) = password {
+        session.authenticate_password(username, password).await?
+    } else {
+        return Err(anyhow!("Aucune clé ni mot de passe fourni"));
+    };
 
-    for attempt in 1..=3 {
-        let config = Arc::new(client::Config::default());
+    if !authenticated {
+        return Err(anyhow!("Authentication failed"));
+    }
 
-        match tokio::time::timeout(
-            std::time::Duration::from_secs(15),
-            client::connect(config, (host, 22), Client {})
-        ).await {
-            Ok(Ok(s)) => {
-                println!("[SSH] test_connection: connected (attempt {})", attempt);
-                session = Some(s);
-                break;
+    Ok(session)
+}
+
+/// Valeur entre apostrophes pour le shell distant (apostrophes internes échappées)
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Préfixe d'élévation: "sudo" sans mot de passe, sinon "echo '<mot de passe>' | sudo -S"
+pub fn sudo_prefix(password: Option<&str>) -> String {
+    match password {
+        Some(password) => format!("echo {} | sudo -S", shell_quote(password)),
+        None => "sudo".to_string(),
+    }
+}
+
+/// Cible SSH (clé privée ou mot de passe) pour le code partagé entre les deux modes
+#[derive(Clone, Copy)]
+pub enum SshTarget<'a> {
+    Key { host: &'a str, username: &'a str, private_key: &'a str },
+    Password { host: &'a str, username: &'a str, password: &'a str },
+}
+
+/// Identifiants jamais affichés (même masquage que SecretString)
+impl std::fmt::Debug for SshTarget<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (variant, host, username, secret) = match *self {
+            SshTarget::Key { host, username, .. } => ("Key", host, username, "private_key"),
+            SshTarget::Password { host, username, .. } => ("Password", host, username, "password"),
+        };
+        f.debug_struct(variant)
+            .field("host", &host)
+            .field("username", &username)
+            .field(secret, &"••••••")
+            .finish()
+    }
+}
+
+impl<'a> SshTarget<'a> {
+    /// Préfixe d'élévation pour cette cible (clé: sudo sans mot de passe)
+    pub fn sudo(&self) -> String {
+        match *self {
+            SshTarget::Key { .. } => sudo_prefix(None),
+            SshTarget::Password { password, .. } => sudo_prefix(Some(password)),
+        }
+    }
+
+    /// Exécute une commande sur la cible
+    pub async fn exec(&self, command: &str) -> Result<String> {
+        match *self {
+            SshTarget::Key { host, username, private_key } => {
+                execute_command(host, username, private_key, command).await
             }
-            Ok(Err(e)) => {
-                println!("[SSH] test_connection: connection failed (attempt {}): {}", attempt, e);
-                last_error = Some(anyhow!("{}", e));
-                if attempt < 3 {
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                }
+            SshTarget::Password { host, username, password } => {
+                execute_command_password(host, username, password, command).await
             }
-            Err(_) => {
-                println!("[SSH] test_connection: timeout (attempt {})", attempt);
-                last_error = Some(anyhow!("Connection timeout after 15s"));
-                if attempt < 3 {
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                }
+        }
+    }
+
+    /// Exécute une commande avec des options explicites (timeout propre à la commande)
+    pub async fn exec_with_options(&self, command: &str, options: &SshOptions) -> Result<String> {
+        match *self {
+            SshTarget::Key { host, username, private_key } => {
+                execute_command_with_options(host, username, private_key, command, options).await
+            }
+            SshTarget::Password { host, username, password } => {
+                execute_command_password_with_options(host, username, password, command, options).await
             }
         }
     }
 
-    let mut session = match session {
-        Some(s) => s,
-        None => {
-            return Err(anyhow!("Connection failed after 3 attempts: {}", last_error.unwrap()));
+    /// Exécute une commande longue en transmettant sa sortie au fil de l'eau
+    /// (toujours sur une connexion dédiée, jamais sur la session persistante)
+    pub async fn exec_streaming(
+        &self,
+        command: &str,
+        options: &SshOptions,
+        on_output: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<String> {
+        let (host, username, password, private_key) = match *self {
+            SshTarget::Key { host, username, private_key } => (host, username, None, Some(private_key)),
+            SshTarget::Password { host, username, password } => (host, username, Some(password), None),
+        };
+        let mut session = open_authenticated_session(host, username, password, private_key).await?;
+        execute_on_session_streaming(&mut session, command, options, on_output).await
+    }
+
+    /// Écrit `content` dans `remote_path` sur le Pi en le passant sur l'entrée standard
+    /// de `cat` (pas de limite de taille d'argument, un seul channel)
+    pub async fn upload(&self, remote_path: &str, content: &[u8], options: &SshOptions) -> Result<()> {
+        let (host, username, password, private_key) = match *self {
+            SshTarget::Key { host, username, private_key } => (host, username, None, Some(private_key)),
+            SshTarget::Password { host, username, password } => (host, username, Some(password), None),
+        };
+        let mut session = open_authenticated_session(host, username, password, private_key).await?;
+        let result = upload_on_session(&mut session, remote_path, content, options).await;
+        let _ = session.disconnect(Disconnect::ByApplication, "", "").await;
+        result
+    }
+}
+
+/// `cat > remote_path` alimenté par `content`, statut de sortie vérifié
+async fn upload_on_session(
+    session: &mut client::Handle<Client>,
+    remote_path: &str,
+    content: &[u8],
+    options: &SshOptions,
+) -> Result<()> {
+    let mut channel = session.channel_open_session().await?;
+    channel.exec(true, format!("cat > {}", remote_path)).await?;
+
+    let transfer = async {
+        channel.data(content).await?;
+        channel.eof().await?;
+        let mut exit_status = None;
+        while let Some(msg) = channel.wait().await {
+            if let ChannelMsg::ExitStatus { exit_status: status } = msg {
+                exit_status = Some(status);
+            }
         }
+        Ok::<_, anyhow::Error>(exit_status)
+    };
+
+    let token = operations::current_token();
+    let outcome = tokio::select! {
+        outcome = with_timeout(options.command_timeout(), transfer) => outcome,
+        _ = token.cancelled() => return Err(Cancelled.into()),
     };
+    match outcome {
+        Err(_) => Err(anyhow!("Upload timeout after {}s", options.command_timeout_secs)),
+        Ok(Err(e)) => Err(anyhow!("Upload to {} failed: {}", remote_path, e)),
+        Ok(Ok(Some(0))) => Ok(()),
+        Ok(Ok(status)) => Err(anyhow!("Upload to {} failed (exit status {:?})", remote_path, status)),
+    }
+}
 
-    println!("[SSH] Authenticating with password...");
+/// Teste la connexion SSH avec clé privée
+pub async fn test_connection(host: &str, username: &str, private_key: &str) -> Result<bool> {
+    test_connection_with_options(host, username, private_key, &default_options()).await
+}
+
+/// Teste la connexion SSH avec clé privée (options explicites)
+pub async fn test_connection_with_options(
+    host: &str,
+    username: &str,
+    private_key: &str,
+    options: &SshOptions,
+) -> Result<bool> {
+    let key = crate::ssh_keys::decode(private_key)?;
+
+    let mut session = connect(host, options, "test_connection").await?;
+
+    let auth_result = session
+        .authenticate_publickey(username, Arc::new(key))
+        .await?;
+
+    session.disconnect(Disconnect::ByApplication, "", "").await?;
+
+    Ok(auth_result)
+}
+
+/// Teste la connexion SSH avec mot de passe
+pub async fn test_connection_password(host: &str, username: &str, password: &str) -> Result<bool> {
+    test_connection_password_with_options(host, username, password, &default_options()).await
+}
+
+/// Teste la connexion SSH avec mot de passe (options explicites)
+pub async fn test_connection_password_with_options(
+    host: &str,
+    username: &str,
+    password: &str,
+    options: &SshOptions,
+) -> Result<bool> {
+    tracing::debug!("Connecting to {}@{}...", username, host);
+
+    let mut session = connect(host, options, "test_connection").await?;
+
+    tracing::debug!("Authenticating with password...");
     let auth_result = match session.authenticate_password(username, password).await {
         Ok(r) => r,
         Err(e) => {
-            println!("[SSH] Password auth failed: {}", e);
+            tracing::warn!("Password auth failed: {}", e);
             return Err(anyhow!("Password auth failed: {}", e));
         }
     };
 
-    println!("[SSH] Auth result: {}", auth_result);
+    tracing::info!("Auth result: {}", auth_result);
 
     if let Err(e) = session.disconnect(Disconnect::ByApplication, "", "").await {
-        println!("[SSH] Disconnect warning: {}", e);
+        tracing::warn!("Disconnect warning: {}", e);
     }
 
     Ok(auth_result)
@@ -318,46 +851,21 @@ pub async fn execute_command(
     private_key: &str,
     command: &str,
 ) -> Result<String> {
-    let key = russh_keys::decode_secret_key(private_key, None)?;
-
-    let mut session = None;
-    let mut last_error = None;
-
-    for attempt in 1..=3 {
-        let config = Arc::new(client::Config::default());
+    execute_command_with_options(host, username, private_key, command, &default_options()).await
+}
 
-        match tokio::time::timeout(
-            std::time::Duration::from_secs(15),
-            client::connect(config, (host, 22), Client {})
-        ).await {
-            Ok(Ok(s)) => {
-                println!("[SSH] execute_command: connected (attempt {})", attempt);
-                session = Some(s);
-                break;
-            }
-            Ok(Err(e)) => {
-                println!("[SSH] execute_command: connection failed (attempt {}): {}", attempt, e);
-                last_error = Some(anyhow!("{}", e));
-                if attempt < 3 {
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                }
-            }
-            Err(_) => {
-                println!("[SSH] execute_command: timeout (attempt {})", attempt);
-                last_error = Some(anyhow!("Connection timeout after 15s"));
-                if attempt < 3 {
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                }
-            }
-        }
-    }
+/// Exécute une commande SSH avec clé privée (options explicites)
+#[tracing::instrument(name = "ssh_exec", skip_all, fields(host = %host, user = %username))]
+pub async fn execute_command_with_options(
+    host: &str,
+    username: &str,
+    private_key: &str,
+    command: &str,
+    options: &SshOptions,
+) -> Result<String> {
+    let key = crate::ssh_keys::decode(private_key)?;
 
-    let mut session = match session {
-        Some(s) => s,
-        None => {
-            return Err(anyhow!("Connection failed after 3 attempts: {:?}", last_error));
-        }
-    };
+    let mut session = connect(host, options, "execute_command").await?;
 
     let auth_result = session
         .authenticate_publickey(username, Arc::new(key))
@@ -367,7 +875,7 @@ pub async fn execute_command(
         return Err(anyhow!("Authentication failed"));
     }
 
-    execute_on_session(&mut session, command).await
+    execute_on_session(&mut session, command, options).await
 }
 
 /// Exécute une commande SSH et retourne la sortie (mot de passe)
@@ -378,24 +886,38 @@ pub async fn execute_command_password(
     password: &str,
     command: &str,
 ) -> Result<String> {
+    execute_command_password_with_options(host, username, password, command, &default_options()).await
+}
+
+/// Exécute une commande SSH avec mot de passe (options explicites)
+#[tracing::instrument(name = "ssh_exec", skip_all, fields(host = %host, user = %username))]
+pub async fn execute_command_password_with_options(
+    host: &str,
+    username: &str,
+    password: &str,
+    command: &str,
+    options: &SshOptions,
+) -> Result<String> {
+    crate::redact::register(password);
+
     // Essayer d'utiliser la session persistante si disponible
     {
         let mut session_guard = PERSISTENT_SESSION.lock().await;
         if let Some(ref mut session) = *session_guard {
             if session.host == host && session.username == username {
-                // Timeout de 60s pour les commandes via session persistante
-                match tokio::time::timeout(
-                    std::time::Duration::from_secs(60),
+                match with_timeout(
+                    options.command_timeout(),
                     session.exec(command)
                 ).await {
                     Ok(Ok(output)) => return Ok(output),
+                    Ok(Err(e)) if e.is::<Cancelled>() => return Err(e),
                     Ok(Err(e)) => {
-                        println!("[SSH] Persistent session command failed: {}", e);
+                        tracing::warn!("Persistent session command failed: {}", e);
                         // Réinitialiser la session
                         *session_guard = None;
                     }
                     Err(_) => {
-                        println!("[SSH] Persistent session timeout, reconnecting...");
+                        tracing::debug!("Persistent session timeout, reconnecting...");
                         *session_guard = None;
                     }
                 }
@@ -409,7 +931,7 @@ pub async fn execute_command_password(
                         match session.exec(command).await {
                             Ok(output) => return Ok(output),
                             Err(e) => {
-                                println!("[SSH] Reconnected session also failed: {}", e);
+                                tracing::error!("Reconnected session also failed: {}", e);
                                 *session_guard = None;
                             }
                         }
@@ -420,72 +942,46 @@ pub async fn execute_command_password(
     }
 
     // Fallback: créer une nouvelle connexion
-    println!("[SSH] exec_password: connecting to {}@{}", username, host);
-    println!("[SSH] Command: {}", &command[..command.len().min(100)]);
-
-    let mut session = None;
-    let mut last_error = None;
+    tracing::info!("exec_password: connecting to {}@{}", username, host);
+    tracing::info!("Command: {}", crate::redact::preview(command, 100));
 
-    for attempt in 1..=3 {
-        let config = Arc::new(client::Config::default());
+    let mut session = connect(host, options, "exec_password").await?;
 
-        match tokio::time::timeout(
-            std::time::Duration::from_secs(15),
-            client::connect(config, (host, 22), Client {})
-        ).await {
-            Ok(Ok(s)) => {
-                println!("[SSH] exec_password: connected (attempt {})", attempt);
-                session = Some(s);
-                break;
-            }
-            Ok(Err(e)) => {
-                println!("[SSH] exec_password: connection failed (attempt {}): {}", attempt, e);
-                last_error = Some(anyhow!("{}", e));
-                if attempt < 3 {
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                }
-            }
-            Err(_) => {
-                println!("[SSH] exec_password: timeout (attempt {})", attempt);
-                last_error = Some(anyhow!("Connection timeout after 15s"));
-                if attempt < 3 {
-                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                }
-            }
-        }
-    }
-
-    let mut session = match session {
-        Some(s) => s,
-        None => {
-            return Err(anyhow!("Connection failed after 3 attempts: {:?}", last_error));
-        }
-    };
-
-    println!("[SSH] exec_password: authenticating...");
+    tracing::debug!("exec_password: authenticating...");
     let auth_result = match session.authenticate_password(username, password).await {
         Ok(r) => r,
         Err(e) => {
-            println!("[SSH] exec_password: auth failed: {}", e);
+            tracing::warn!("exec_password: auth failed: {}", e);
             return Err(anyhow!("Password auth failed: {}", e));
         }
     };
 
     if !auth_result {
-        println!("[SSH] exec_password: auth returned false");
+        tracing::info!("exec_password: auth returned false");
         return Err(anyhow!("Password authentication failed"));
     }
 
-    println!("[SSH] exec_password: executing command...");
-    execute_on_session(&mut session, command).await
+    tracing::debug!("exec_password: executing command...");
+    execute_on_session(&mut session, command, options).await
 }
 
 /// Fonction interne pour exécuter une commande sur une session
 async fn execute_on_session(
     session: &mut client::Handle<Client>,
     command: &str,
+    options: &SshOptions,
+) -> Result<String> {
+    execute_on_session_streaming(session, command, options, &mut |_| {}).await
+}
+
+/// Exécute une commande en transmettant la sortie au fil de l'eau à `on_output`
+async fn execute_on_session_streaming(
+    session: &mut client::Handle<Client>,
+    command: &str,
+    options: &SshOptions,
+    on_output: &mut (dyn FnMut(&str) + Send),
 ) -> Result<String> {
-    println!("[SSH] Opening channel...");
+    tracing::debug!("Opening channel...");
     let mut channel = match tokio::time::timeout(
         std::time::Duration::from_secs(30),
         session.channel_open_session()
@@ -495,36 +991,59 @@ async fn execute_on_session(
         Err(_) => return Err(anyhow!("Channel open timeout after 30s")),
     };
 
-    println!("[SSH] Executing command...");
+    tracing::debug!("Executing command...");
     if let Err(e) = channel.exec(true, command).await {
         return Err(anyhow!("Command exec failed: {}", e));
     }
 
     let mut output = String::new();
 
-    loop {
-        match channel.wait().await {
-            Some(ChannelMsg::Data { data }) => {
-                output.push_str(&String::from_utf8_lossy(&data));
-            }
-            Some(ChannelMsg::ExtendedData { data, .. }) => {
-                output.push_str(&String::from_utf8_lossy(&data));
-            }
-            Some(ChannelMsg::ExitStatus { exit_status }) => {
-                if exit_status != 0 {
-                    tracing::warn!("Command exited with status {}: {}", exit_status, output);
+    let read_output = async {
+        loop {
+            match channel.wait().await {
+                Some(ChannelMsg::Data { data }) => {
+                    let chunk = String::from_utf8_lossy(&data);
+                    on_output(&chunk);
+                    output.push_str(&chunk);
+                }
+                Some(ChannelMsg::ExtendedData { data, .. }) => {
+                    let chunk = String::from_utf8_lossy(&data);
+                    on_output(&chunk);
+                    output.push_str(&chunk);
+                }
+                Some(ChannelMsg::ExitStatus { exit_status }) => {
+                    if exit_status != 0 {
+                        tracing::warn!("Command exited with status {}: {}", exit_status, crate::redact::redact(&output));
+                    }
+                    break;
                 }
-                break;
+                Some(ChannelMsg::Eof) => break,
+                None => break,
+                _ => {}
             }
-            Some(ChannelMsg::Eof) => break,
-            None => break,
-            _ => {}
         }
-    }
+    };
+
+    let token = operations::current_token();
+    let (timed_out, cancelled) = tokio::select! {
+        outcome = with_timeout(options.command_timeout(), read_output) => (outcome.is_err(), false),
+        _ = token.cancelled() => (false, true),
+    };
 
     let _ = channel.eof().await;
     let _ = session.disconnect(Disconnect::ByApplication, "", "").await;
 
+    if cancelled {
+        tracing::info!("Command cancelled, connection closed");
+        return Err(Cancelled.into());
+    }
+    if timed_out {
+        return Err(anyhow!(
+            "Command timeout after {}s",
+            options.command_timeout_secs
+        ));
+    }
+
     Ok(output)
 }
 
@@ -560,3 +1079,16 @@ pub async fn upload_file(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote_and_sudo_prefix() {
+        assert_eq!(shell_quote("raspberry"), "'raspberry'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(sudo_prefix(None), "sudo");
+        assert_eq!(sudo_prefix(Some("pa'ss")), "echo 'pa'\\''ss' | sudo -S");
+    }
+}
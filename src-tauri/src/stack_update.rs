@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+use crate::ssh::{self, SshTarget};
+use crate::notifications::DiscordNotifier;
+use crate::template_engine::TemplateVars;
+use crate::verification::HealthReport;
+
+// =============================================================================
+// Mise à jour d'un stack installé (images + configs master_config)
+// =============================================================================
+//
+// 1. relève l'image de chaque conteneur, `docker compose pull` puis `up -d`
+// 2. compare les images avant/après: services réellement mis à jour
+// 3. ré-applique la master_config via les API des services (aucune base supprimée)
+// 4. enregistre les images avant/après dans Supabase
+//
+// Si les vérifications échouent après la mise à jour, le docker-compose.yml sauvegardé
+// est restauré et les tags sont re-pointés sur les anciennes images (toujours présentes
+// localement: `pull` ne supprime rien), puis le stack est relancé sans pull.
+
+/// Image (id sha256) de chaque service du projet, une ligne service=image
+const IMAGE_SNAPSHOT_COMMAND: &str = "cd ~/media-stack && for c in $(docker compose ps -q); do \
+     docker inspect --format '{{index .Config.Labels \"com.docker.compose.service\"}}={{.Image}}' \"$c\"; done";
+
+/// Pull des images (Pi 4 + carte SD lente: large)
+const PULL_TIMEOUT_SECS: u64 = 1800;
+
+const COMPOSE_BACKUP: &str = "docker-compose.yml.pre-update";
+
+/// Les services mettent du temps à redémarrer: vérifications répétées avant rollback
+const HEALTH_ATTEMPTS: usize = 4;
+const HEALTH_RETRY_SECS: u64 = 20;
+
+/// Service dont l'image a changé
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ServiceImageChange {
+    pub service: String,
+    pub old_image: Option<String>,
+    pub new_image: Option<String>,
+}
+
+/// Compte rendu de `update_stack`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct StackUpdateReport {
+    pub pi_name: String,
+    pub updated_at: String,
+    pub changed: Vec<ServiceImageChange>,
+    /// Une ligne par service de la master_config ("radarr: ré-appliqué", ...)
+    pub reconfigured: Vec<String>,
+    /// Vérifications après mise à jour (absentes si aucune image n'a changé)
+    pub health: Option<HealthReport>,
+    pub rolled_back: bool,
+}
+
+/// Parse la sortie de IMAGE_SNAPSHOT_COMMAND
+pub fn parse_image_snapshot(output: &str) -> BTreeMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .filter(|(service, image)| !service.is_empty() && !image.is_empty())
+        .map(|(service, image)| (service.to_string(), image.to_string()))
+        .collect()
+}
+
+/// Services ajoutés, supprimés ou dont l'image a changé
+pub fn diff_images(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Vec<ServiceImageChange> {
+    let mut services: Vec<&String> = before.keys().chain(after.keys()).collect();
+    services.sort();
+    services.dedup();
+
+    services
+        .into_iter()
+        .filter(|service| before.get(*service) != after.get(*service))
+        .map(|service| ServiceImageChange {
+            service: service.clone(),
+            old_image: before.get(service).cloned(),
+            new_image: after.get(service).cloned(),
+        })
+        .collect()
+}
+
+/// Re-pointe les tags sur les anciennes images puis restaure le compose sauvegardé
+pub fn rollback_script(changed: &[ServiceImageChange]) -> String {
+    let mut lines = vec!["cd ~/media-stack".to_string()];
+    for change in changed {
+        if let Some(old_image) = &change.old_image {
+            lines.push(format!(
+                "docker tag {} \"$(docker inspect --format '{{{{.Config.Image}}}}' $(docker compose ps -q {}))\" || true",
+                old_image, change.service
+            ));
+        }
+    }
+    lines.push(format!("cp -p {} docker-compose.yml", COMPOSE_BACKUP));
+    lines.push("docker compose up -d --remove-orphans".to_string());
+    lines.join("\n")
+}
+
+async fn image_snapshot(target: &SshTarget<'_>) -> Result<BTreeMap<String, String>> {
+    Ok(parse_image_snapshot(&target.exec(IMAGE_SNAPSHOT_COMMAND).await?))
+}
+
+/// Variables de template d'un Pi déjà installé (clés lues sur le Pi)
+async fn installed_vars(target: &SshTarget<'_>, pi_name: &str) -> Result<TemplateVars> {
+    let host = match *target {
+        SshTarget::Key { host, .. } | SshTarget::Password { host, .. } => host,
+    };
+    let mut vars = TemplateVars::new();
+    vars.set("PI_IP", host);
+    vars.set("PI_HOSTNAME", pi_name);
+    crate::master_config::installed_media_paths(target).await.apply_to(&mut vars);
+    crate::services::harvest_api_keys(target).await?.apply_to(&mut vars);
+
+    // FlareSolverr: seulement s'il fait partie du stack du Pi
+    let containers = target.exec("docker ps -a --format '{{.Names}}'").await.unwrap_or_default();
+    let flaresolverr = containers.lines().any(|name| name.trim() == "flaresolverr");
+    vars.set(crate::services::flaresolverr::ENABLED_VAR, &flaresolverr.to_string());
+
+    // Clé AllDebrid: celle de la config Decypharr en place
+    let decypharr = target.exec("cat ~/media-stack/decypharr/config.json 2>/dev/null").await.unwrap_or_default();
+    if let Some(key) = serde_json::from_str::<Value>(decypharr.trim())
+        .ok()
+        .and_then(|c| c["debrids"][0]["api_key"].as_str().map(String::from))
+    {
+        vars.set("ALLDEBRID_API_KEY", &key);
+    }
+    Ok(vars)
+}
+
+/// Vérifie le stack jusqu'à HEALTH_ATTEMPTS fois (le temps que les services démarrent)
+async fn wait_healthy(target: &SshTarget<'_>, pi_name: &str) -> Result<HealthReport> {
+    let mut attempt = 1;
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(HEALTH_RETRY_SECS)).await;
+        let report = crate::verification::verify_stack(target, pi_name).await?;
+        if report.all_passed || attempt == HEALTH_ATTEMPTS {
+            return Ok(report);
+        }
+        tracing::info!("{} checks failing, retrying ({}/{})...", report.failures(), attempt, HEALTH_ATTEMPTS);
+        attempt += 1;
+    }
+}
+
+/// Restaure le compose et les images d'avant la mise à jour, journalise et prévient
+async fn rollback(
+    target: &SshTarget<'_>,
+    pi_name: &str,
+    changed: &[ServiceImageChange],
+    health: Option<&HealthReport>,
+    notifier: Option<&DiscordNotifier>,
+) -> Result<()> {
+    tracing::warn!("⚠️ Rolling back update on {}...", pi_name);
+    target.exec(&rollback_script(changed)).await?;
+
+    let services: Vec<String> = changed.iter().map(|c| c.service.clone()).collect();
+    let failed_checks: Vec<String> = health
+        .map(|h| h.checks.iter().filter(|c| !c.passed).map(|c| format!("{}: {}", c.name, c.detail)).collect())
+        .unwrap_or_default();
+    let message = format!("Mise à jour annulée ({}); services restaurés: {}", failed_checks.join("; "), services.join(", "));
+    if let Err(e) = crate::cloud::backend().add_log(pi_name, "stack_update", "error", &message, None).await {
+        tracing::warn!("could not log rollback: {}", e);
+    }
+    if let Some(notifier) = notifier {
+        notifier.update_rolled_back(pi_name, &failed_checks, &services).await;
+    }
+
+    tracing::info!("✅ Previous stack restored on {}", pi_name);
+    Ok(())
+}
+
+/// Met à jour les images puis ré-applique la master_config sans réinitialiser les services.
+/// En cas d'échec des vérifications, revient automatiquement à la version précédente.
+pub async fn update_stack(
+    target: SshTarget<'_>,
+    pi_name: &str,
+    notifier: Option<&DiscordNotifier>,
+) -> Result<StackUpdateReport> {
+    tracing::info!("Updating stack on {}...", pi_name);
+    let before = image_snapshot(&target).await?;
+    if before.is_empty() {
+        return Err(anyhow!("Aucun conteneur du stack trouvé sur {}", pi_name));
+    }
+    target
+        .exec(&format!("cd ~/media-stack && cp -p docker-compose.yml {}", COMPOSE_BACKUP))
+        .await?;
+
+    let options = ssh::SshOptions { command_timeout_secs: PULL_TIMEOUT_SECS, ..ssh::default_options() };
+    let pulled = target
+        .exec_with_options("cd ~/media-stack && docker compose pull -q && docker compose up -d --remove-orphans", &options)
+        .await;
+
+    let after = image_snapshot(&target).await?;
+    let changed = diff_images(&before, &after);
+    tracing::info!("{} services updated", changed.len());
+
+    let mut report = StackUpdateReport {
+        pi_name: pi_name.to_string(),
+        updated_at: chrono::Utc::now().to_rfc3339(),
+        changed,
+        ..Default::default()
+    };
+
+    if let Err(e) = pulled {
+        rollback(&target, pi_name, &report.changed, None, notifier).await?;
+        return Err(anyhow!("Mise à jour annulée: {}", e));
+    }
+
+    if !report.changed.is_empty() {
+        let health = wait_healthy(&target, pi_name).await?;
+        if !health.all_passed {
+            rollback(&target, pi_name, &report.changed, Some(&health), notifier).await?;
+            report.rolled_back = true;
+        }
+        report.health = Some(health);
+    }
+
+    if !report.rolled_back {
+        report.reconfigured = reapply_master_config(&target, pi_name).await?;
+    }
+
+    if let Err(e) = crate::supabase::save_stack_update(pi_name, &serde_json::to_value(&report)?).await {
+        tracing::warn!("could not save stack update: {}", e);
+    }
+
+    if report.rolled_back {
+        tracing::warn!("⚠️ Update of {} rolled back", pi_name);
+    } else {
+        tracing::info!("✅ Stack updated on {}", pi_name);
+    }
+    Ok(report)
+}
+
+/// Ré-applique la master_config via les API des services (une ligne par service)
+async fn reapply_master_config(target: &SshTarget<'_>, pi_name: &str) -> Result<Vec<String>> {
+    let mut reconfigured = Vec::new();
+    match crate::cloud::backend().fetch_master_config(Some("streaming")).await {
+        Ok(Some(master)) => {
+            let vars = installed_vars(target, pi_name).await?;
+            for (service, config) in crate::services::render_service_configs(&master, &vars) {
+                if !crate::services::RECONFIGURABLE_SERVICES.contains(&service.as_str()) {
+                    continue;
+                }
+                reconfigured.push(match crate::services::reconfigure(*target, &service, &config, &vars).await {
+                    Ok(()) => format!("{}: ré-appliqué", service),
+                    Err(e) => format!("{}: échec ({})", service, e),
+                });
+            }
+        }
+        Ok(None) => tracing::info!("No master_config, configs left untouched"),
+        Err(e) => tracing::warn!("⚠️ Could not fetch master_config: {}", e),
+    }
+    Ok(reconfigured)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_images() {
+        let before = parse_image_snapshot("radarr=sha256:aaa\nsonarr=sha256:bbb\nbazarr=sha256:ccc\n");
+        let after = parse_image_snapshot("radarr=sha256:aaa\nsonarr=sha256:ddd\njellyseerr=sha256:eee\n\n");
+        let changed = diff_images(&before, &after);
+
+        assert_eq!(changed.len(), 3);
+        assert_eq!(changed[0].service, "bazarr");
+        assert_eq!(changed[0].new_image, None);
+        assert_eq!(changed[1].service, "jellyseerr");
+        assert_eq!(changed[2].old_image.as_deref(), Some("sha256:bbb"));
+        assert_eq!(changed[2].new_image.as_deref(), Some("sha256:ddd"));
+
+        let script = rollback_script(&changed);
+        assert!(script.contains("docker tag sha256:bbb \"$(docker inspect --format '{{.Config.Image}}' $(docker compose ps -q sonarr))\""));
+        assert!(!script.contains("jellyseerr"));
+        assert!(script.ends_with("cp -p docker-compose.yml.pre-update docker-compose.yml\ndocker compose up -d --remove-orphans"));
+    }
+}
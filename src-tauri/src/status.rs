@@ -0,0 +1,175 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// =============================================================================
+// Page de statut agrégée sur le Pi (http://<pi>:80/status)
+// =============================================================================
+//
+// Un conteneur busybox (réseau host) interroge toutes les 30s l'endpoint de santé
+// de chaque service exposé dans docker-compose.yml et publie le résultat en JSON.
+// L'application lit ce JSON au lieu de sonder chaque port depuis le poste.
+
+pub const STATUS_PORT: u16 = 80;
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Service à surveiller, déduit du docker-compose.yml généré
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusTarget {
+    pub name: String,
+    pub port: u16,
+    pub path: &'static str,
+}
+
+/// Santé d'un service telle que publiée par le Pi
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct ServiceHealth {
+    pub name: String,
+    pub port: u16,
+    pub healthy: bool,
+}
+
+/// Contenu de http://<pi>/status
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct StackStatus {
+    /// Timestamp Unix de la dernière vérification sur le Pi
+    #[ts(type = "number")]
+    pub updated_at: i64,
+    pub services: Vec<ServiceHealth>,
+}
+
+impl StackStatus {
+    pub fn all_healthy(&self) -> bool {
+        self.services.iter().all(|s| s.healthy)
+    }
+}
+
+/// Endpoint de santé de chaque service (accessible sans authentification)
+fn health_path(service: &str) -> &'static str {
+    match service {
+        "jellyfin" => "/health",
+        "radarr" | "sonarr" | "prowlarr" | "lidarr" | "readarr" => "/ping",
+        "jellyseerr" => "/api/v1/status",
+        "flaresolverr" | "supabazarr" => "/health",
+        "audiobookshelf" => "/healthcheck",
+        _ => "/",
+    }
+}
+
+/// Liste les services du compose avec leur premier port publié (côté hôte)
+/// Les services sans port (cloudflared, status) sont ignorés
+pub fn status_targets(compose: &str) -> Vec<StatusTarget> {
+    crate::compose::parse_services(compose)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(name, service)| {
+            let port = *crate::compose::service_host_ports(&service).first()?;
+            Some(StatusTarget { path: health_path(&name), name, port })
+        })
+        .collect()
+}
+
+/// Script exécuté par le conteneur status
+pub fn status_script(targets: &[StatusTarget]) -> String {
+    let checks: String = targets
+        .iter()
+        .map(|t| format!("  check {} {} {}\n", t.name, t.port, t.path))
+        .collect();
+
+    format!(
+        r#"#!/bin/sh
+# Généré par JellySetup - agrège les healthchecks sur http://<pi>:{port}/status
+mkdir -p /www
+echo '{{"updated_at":0,"services":[]}}' > /www/status
+httpd -p {port} -h /www
+
+check() {{
+  if wget -q -T 5 -O /dev/null "http://127.0.0.1:$2$3"; then healthy=true; else healthy=false; fi
+  out="$out$sep{{\"name\":\"$1\",\"port\":$2,\"healthy\":$healthy}}"
+  sep=','
+}}
+
+while true; do
+  out=''
+  sep=''
+{checks}  echo "{{\"updated_at\":$(date +%s),\"services\":[$out]}}" > /www/status.tmp
+  mv /www/status.tmp /www/status
+  sleep {interval}
+done
+"#,
+        port = STATUS_PORT,
+        checks = checks,
+        interval = CHECK_INTERVAL_SECS
+    )
+}
+
+/// Commande d'écriture de ~/media-stack/status/status.sh (à lancer avant `docker compose up`)
+pub fn write_script_command(compose: &str) -> String {
+    format!(
+        "mkdir -p ~/media-stack/status && cat > ~/media-stack/status/status.sh << 'EOFSTATUS'\n{}\nEOFSTATUS",
+        status_script(&status_targets(compose))
+    )
+}
+
+/// Service docker-compose du conteneur status
+pub fn compose_service() -> &'static str {
+    r#"
+  # Status - Healthchecks agrégés sur http://<pi-ip>/status
+  status:
+    image: busybox:latest
+    container_name: status
+    restart: unless-stopped
+    network_mode: host
+    volumes:
+      - ./status:/status:ro
+    command: sh /status/status.sh
+    deploy:
+      resources:
+        limits:
+          memory: 32M
+"#
+}
+
+/// Lit la page de statut publiée par le Pi
+pub async fn fetch_stack_status(host: &str) -> Result<StackStatus> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()?;
+
+    let response = client
+        .get(format!("http://{}:{}/status", host, STATUS_PORT))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Page de statut indisponible (HTTP {})", response.status()));
+    }
+
+    let status: StackStatus = serde_json::from_str(&response.text().await?)
+        .map_err(|e| anyhow!("Page de statut invalide: {}", e))?;
+
+    tracing::info!(
+        "{} {} services, {} unhealthy",
+        if status.all_healthy() { "✅" } else { "⚠️" },
+        status.services.len(),
+        status.services.iter().filter(|s| !s.healthy).count()
+    );
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_targets_from_compose() {
+        let compose = "---\n# header\nservices:\n  # Jellyfin\n  jellyfin:\n    image: jellyfin\n    ports:\n      - 8096:8096\n    healthcheck:\n      test: [\"CMD\"]\n\n  jellyseerr:\n    ports:\n      - 5056:5055\n\n  cloudflared:\n    image: cloudflare/cloudflared:latest\n\nvolumes:\n  data:\n";
+        let targets = status_targets(compose);
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0], StatusTarget { name: "jellyfin".into(), port: 8096, path: "/health" });
+        assert_eq!(targets[1].name, "jellyseerr");
+        assert_eq!(targets[1].port, 5056);
+    }
+}
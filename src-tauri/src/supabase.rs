@@ -1,31 +1,69 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Mutex;
 use once_cell::sync::Lazy;
 
+use crate::secret::SecretString;
+use crate::secrets::{self, SecretKind};
+
+// Les écritures passent par les Edge Functions jellysetup-* avec la clé anon et
+// le token du Pi (en-tête X-Pi-Token), délivré par jellysetup-init à la création
+// du schéma et limité à ce schéma. Le binaire n'embarque plus la clé service:
+// seule une instance auto-hébergée en fournit une (réglages). Les appels REST et
+// Storage directs (catalogue, sauvegardes, historique de configuration) portent
+// aussi le token du Pi, vérifié par les politiques RLS de supabase/schema.sql.
+
+/// En-tête portant le token du Pi
+pub const PI_TOKEN_HEADER: &str = "X-Pi-Token";
+
 // Set des schémas déjà initialisés (un par Pi)
 static INITIALIZED_SCHEMAS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 
-// Ces valeurs sont injectées au build via .env
+// Tokens des Pis par schéma: rangés dans le coffre de l'OS, gardés ici une fois lus
+static PI_TOKENS: Lazy<Mutex<HashMap<String, SecretString>>> = Lazy::new(|| Mutex::new(migrate_legacy_pi_tokens()));
+
+// Projet JellySetup (valeurs injectées au build) ou Supabase auto-hébergé, voir cloud.rs.
+// En mode local, valeurs vides: http_client refuse tout appel.
 fn get_supabase_url() -> String {
-    option_env!("SUPABASE_URL")
-        .unwrap_or("https://ncxowprkehliisvnpmlt.supabase.co")
-        .to_string()
+    crate::cloud::endpoint().map(|e| e.url).unwrap_or_default()
 }
 
 fn get_supabase_key() -> String {
-    option_env!("SUPABASE_ANON_KEY")
-        .unwrap_or("your-anon-key")
-        .to_string()
+    crate::cloud::endpoint().map(|e| e.anon_key.expose().to_string()).unwrap_or_default()
+}
+
+/// Clé des lectures REST et Storage: clé service d'une instance auto-hébergée, sinon clé anon
+pub fn get_supabase_rest_key() -> String {
+    crate::cloud::endpoint()
+        .map(|e| e.service_key.unwrap_or(e.anon_key).expose().to_string())
+        .unwrap_or_default()
 }
 
-/// Get service key for Supabazarr (allows write access)
-pub fn get_supabase_service_key() -> String {
-    option_env!("SUPABASE_SERVICE_KEY")
-        .unwrap_or("your-service-key")
-        .to_string()
+/// Supabase injoignable (mode hors ligne, erreur 5xx): écriture à rejouer (sync_outbox)
+#[derive(Debug)]
+pub struct Unavailable(pub String);
+
+impl std::fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Supabase indisponible: {}", self.0)
+    }
+}
+
+impl std::error::Error for Unavailable {}
+
+/// Client HTTP des appels Supabase (refusé en mode hors ligne: l'appelant journalise et continue)
+pub(crate) fn http_client() -> Result<reqwest::Client> {
+    let settings = crate::config::settings();
+    if crate::cloud::endpoint().is_none() {
+        return Err(anyhow!("Backend cloud désactivé ({:?}): rien n'est envoyé", settings.cloud_backend));
+    }
+    if settings.offline_mode {
+        return Err(Unavailable("mode hors ligne".to_string()).into());
+    }
+    Ok(reqwest::Client::new())
 }
 
 /// Get Supabase URL for external use
@@ -47,6 +85,109 @@ fn pi_name_to_schema(pi_name: &str) -> String {
         .collect()
 }
 
+/// Ancien stockage en clair des tokens (avant le passage au coffre)
+fn legacy_pi_tokens_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("jellysetup").join("pi_tokens.json"))
+}
+
+/// Déplace les tokens de l'ancien pi_tokens.json dans le coffre; le fichier n'est
+/// supprimé que si tous y sont rangés (sinon ils restent servis depuis la mémoire)
+fn migrate_legacy_pi_tokens() -> HashMap<String, SecretString> {
+    let Some(path) = legacy_pi_tokens_path() else { return HashMap::new() };
+    let Some(tokens) = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<HashMap<String, SecretString>>(&content).ok())
+    else {
+        return HashMap::new();
+    };
+
+    let mut migrated = true;
+    for (schema_name, token) in &tokens {
+        if let Err(e) = secrets::store(SecretKind::PiToken, schema_name, token) {
+            tracing::warn!("⚠️ Could not move Pi token for '{}' to the keychain: {}", schema_name, e);
+            migrated = false;
+        }
+    }
+    if migrated {
+        match std::fs::remove_file(&path) {
+            Ok(()) => tracing::info!("Moved {} Pi token(s) from pi_tokens.json to the keychain", tokens.len()),
+            Err(e) => tracing::warn!("⚠️ Could not remove {}: {}", path.display(), e),
+        }
+    }
+    tokens
+}
+
+fn store_pi_token(schema_name: &str, token: SecretString) -> Result<()> {
+    secrets::store(SecretKind::PiToken, schema_name, &token)?;
+    PI_TOKENS
+        .lock()
+        .map_err(|_| anyhow!("Pi token cache poisoned"))?
+        .insert(schema_name.to_string(), token);
+    Ok(())
+}
+
+/// Token du Pi (None tant que jellysetup-init ne l'a pas délivré)
+pub fn pi_token(pi_name: &str) -> Option<SecretString> {
+    let schema_name = pi_name_to_schema(pi_name);
+    let mut tokens = match PI_TOKENS.lock() {
+        Ok(tokens) => tokens,
+        Err(_) => {
+            tracing::warn!("⚠️ Pi token cache poisoned, reading the keychain directly");
+            return secrets::get(SecretKind::PiToken, &schema_name).ok().flatten();
+        }
+    };
+    if let Some(token) = tokens.get(&schema_name) {
+        return Some(token.clone());
+    }
+    match secrets::get(SecretKind::PiToken, &schema_name) {
+        Ok(Some(token)) => {
+            tokens.insert(schema_name, token.clone());
+            Some(token)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("⚠️ Could not read Pi token for '{}': {}", schema_name, e);
+            None
+        }
+    }
+}
+
+/// Ajoute la clé anon et le token du Pi à une requête vers une Edge Function
+pub(crate) fn with_pi_auth(request: reqwest::RequestBuilder, pi_name: &str) -> reqwest::RequestBuilder {
+    let anon_key = get_supabase_key();
+    let request = request
+        .header("apikey", &anon_key)
+        .header("Authorization", format!("Bearer {}", anon_key));
+    match pi_token(pi_name) {
+        Some(token) => request.header(PI_TOKEN_HEADER, token.expose()),
+        None => request,
+    }
+}
+
+/// Ajoute la clé REST et le token du Pi à un appel PostgREST ou Storage direct:
+/// les politiques RLS des schémas par Pi et des buckets `backups`/`config-history`
+/// n'acceptent que le token du Pi concerné (supabase/schema.sql)
+pub(crate) fn with_rest_auth(request: reqwest::RequestBuilder, pi_name: &str) -> reqwest::RequestBuilder {
+    let rest_key = get_supabase_rest_key();
+    let request = request
+        .header("apikey", &rest_key)
+        .header("Authorization", format!("Bearer {}", rest_key));
+    match pi_token(pi_name) {
+        Some(token) => request.header(PI_TOKEN_HEADER, token.expose()),
+        None => request,
+    }
+}
+
+/// Appelle une action de l'Edge Function jellysetup-api pour un Pi
+async fn call_api(pi_name: &str, action: &str, data: &serde_json::Value) -> Result<reqwest::Response> {
+    let client = http_client()?;
+    let request = client
+        .post(format!("{}/functions/v1/jellysetup-api", get_supabase_url()))
+        .header("Content-Type", "application/json")
+        .json(&json!({ "action": action, "pi_name": pi_name, "data": data }));
+    Ok(with_pi_auth(request, pi_name).send().await?)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ConfigRow {
     id: Option<String>,
@@ -57,6 +198,9 @@ struct ConfigRow {
     ssh_host_fingerprint: Option<String>,  // Fingerprint du serveur SSH du Pi
     status: Option<String>,
     installer_version: Option<String>,
+    /// État de référence du Pi (détection de dérive)
+    #[serde(default)]
+    desired_state: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -67,6 +211,9 @@ struct InitResponse {
     schema: Option<String>,
     tables: Option<Vec<String>>,
     error: Option<String>,
+    /// Token délivré à la première initialisation du schéma
+    #[serde(default)]
+    pi_token: Option<SecretString>,
 }
 
 /// Initialise le schéma Supabase pour un Pi spécifique
@@ -75,54 +222,59 @@ pub async fn ensure_schema_initialized(pi_name: &str) -> Result<String> {
 
     // Skip si déjà initialisé
     {
-        let schemas = INITIALIZED_SCHEMAS.lock().unwrap();
+        let schemas = INITIALIZED_SCHEMAS.lock().map_err(|_| anyhow!("Initialized schemas poisoned"))?;
         if schemas.contains(&schema_name) {
             return Ok(schema_name);
         }
     }
 
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
-    println!("[Supabase] Initializing schema '{}' for Pi '{}'...", schema_name, pi_name);
+    tracing::debug!("Initializing schema '{}' for Pi '{}'...", schema_name, pi_name);
 
-    let response = client
+    // Token existant joint: jellysetup-init ne délivre un token qu'au premier appel
+    let request = client
         .post(format!("{}/functions/v1/jellysetup-init", supabase_url))
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
-        .json(&json!({ "pi_name": pi_name }))
-        .send()
-        .await;
+        .json(&json!({ "pi_name": pi_name }));
+    let response = with_pi_auth(request, pi_name).send().await;
 
     // Gérer les erreurs Supabase sans bloquer l'installation
-    let result = match response {
+    let mut result = match response {
         Ok(resp) => {
             match resp.json::<InitResponse>().await {
                 Ok(r) => Some(r),
                 Err(e) => {
-                    println!("[Supabase] Warning: could not parse response: {}", e);
+                    tracing::warn!("could not parse response: {}", e);
                     None
                 }
             }
         }
         Err(e) => {
-            println!("[Supabase] Warning: request failed: {}", e);
+            tracing::warn!("request failed: {}", e);
             None
         }
     };
 
+    if let Some(token) = result.as_mut().and_then(|r| r.pi_token.take()) {
+        match store_pi_token(&schema_name, token) {
+            Ok(()) => tracing::info!("✅ Pi token stored for '{}'", schema_name),
+            Err(e) => tracing::warn!("⚠️ Could not store Pi token: {}", e),
+        }
+    }
+
     if result.as_ref().map(|r| r.success).unwrap_or(false) {
-        println!("[Supabase] Schema '{}' initialized: {:?}",
+        tracing::info!("Schema '{}' initialized: {:?}",
                  result.as_ref().and_then(|r| r.schema.clone()).unwrap_or_default(),
                  result.as_ref().and_then(|r| r.tables.clone()));
-        let mut schemas = INITIALIZED_SCHEMAS.lock().unwrap();
+        let mut schemas = INITIALIZED_SCHEMAS.lock().map_err(|_| anyhow!("Initialized schemas poisoned"))?;
         schemas.insert(schema_name.clone());
         Ok(schema_name)
     } else {
-        println!("[Supabase] Schema init warning: {:?}", result.as_ref().and_then(|r| r.error.clone()));
+        tracing::warn!("Schema init warning: {:?}", result.as_ref().and_then(|r| r.error.clone()));
         // On continue quand même, le schéma existe peut-être déjà
-        let mut schemas = INITIALIZED_SCHEMAS.lock().unwrap();
+        let mut schemas = INITIALIZED_SCHEMAS.lock().map_err(|_| anyhow!("Initialized schemas poisoned"))?;
         schemas.insert(schema_name.clone());
         Ok(schema_name)
     }
@@ -130,47 +282,42 @@ pub async fn ensure_schema_initialized(pi_name: &str) -> Result<String> {
 
 /// Sauvegarde une installation dans le schéma dédié au Pi via Edge Function
 /// Note: ssh_public_key et ssh_private_key_encrypted sont optionnels pour les installations par mot de passe
-pub async fn save_installation(
-    pi_name: &str,
-    pi_ip: &str,
-    ssh_public_key: Option<&str>,
-    ssh_private_key_encrypted: Option<&str>,
-    ssh_host_fingerprint: Option<&str>,
-    installer_version: &str,
-) -> Result<String> {
+/// Hors connexion, l'écriture est mise en attente (sync_outbox) et l'erreur retournée
+pub async fn save_installation(registration: &crate::registration::InstallationRegistration) -> Result<String> {
+    let result = send_installation(registration).await;
+    if let Err(e) = &result {
+        let write = crate::sync_outbox::PendingWrite::SaveInstallation { registration: registration.clone() };
+        crate::sync_outbox::queue_if_retryable(write, e);
+    }
+    result
+}
+
+pub(crate) async fn send_installation(registration: &crate::registration::InstallationRegistration) -> Result<String> {
+    let pi_name = registration.pi_name.as_str();
+
     // S'assurer que le schéma existe
     ensure_schema_initialized(pi_name).await?;
 
-    let client = reqwest::Client::new();
-    let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
-
     // Utiliser l'Edge Function pour éviter les problèmes de schémas non exposés
-    let body = json!({
-        "action": "save_installation",
-        "pi_name": pi_name,
-        "data": {
-            "local_ip": pi_ip,
-            "ssh_public_key": ssh_public_key,
-            "ssh_private_key_encrypted": ssh_private_key_encrypted,
-            "ssh_host_fingerprint": ssh_host_fingerprint,
-            "installer_version": installer_version
-        }
+    let data = json!({
+        "payload_version": registration.version,
+        "auth_method": registration.auth_method(),
+        "local_ip": registration.pi_ip,
+        "ssh_public_key": registration.ssh_public_key(),
+        "ssh_private_key_encrypted": registration.ssh_private_key_encrypted(),
+        "ssh_host_fingerprint": registration.ssh_host_fingerprint,
+        "installer_version": registration.installer_version
     });
-
-    let response = client
-        .post(format!("{}/functions/v1/jellysetup-api", supabase_url))
-        .header("Authorization", format!("Bearer {}", service_key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
+    let response = call_api(pi_name, "save_installation", &data).await?;
 
     let status = response.status();
     let text = response.text().await?;
 
+    if status.is_server_error() {
+        return Err(Unavailable(format!("{} - {}", status, text)).into());
+    }
     if !status.is_success() {
-        println!("[Supabase] Error saving installation: {} - {}", status, text);
+        tracing::error!("Error saving installation: {} - {}", status, text);
         return Ok("local".to_string());
     }
 
@@ -193,45 +340,150 @@ pub async fn save_installation(
 
     if result.success {
         let config_id = result.data.and_then(|d| d.config_id).unwrap_or_else(|| "local".to_string());
-        println!("[Supabase] Installation saved via Edge Function: {}", config_id);
+        tracing::info!("Installation saved via Edge Function: {}", config_id);
         return Ok(config_id);
     }
 
-    println!("[Supabase] Warning: {}", result.error.unwrap_or_default());
+    tracing::warn!("{}", result.error.unwrap_or_default());
     Ok("local".to_string())
 }
 
 /// Met à jour le statut d'une installation via Edge Function
 pub async fn update_status(pi_name: &str, config_id: &str, status: &str, error: Option<&str>) -> Result<()> {
-    let client = reqwest::Client::new();
-    let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
-
-    let body = json!({
-        "action": "update_status",
-        "pi_name": pi_name,
-        "data": {
-            "config_id": config_id,
-            "status": status,
-            "error_message": error
-        }
+    let data = json!({
+        "config_id": config_id,
+        "status": status,
+        "error_message": error
     });
+    let response = call_api(pi_name, "update_status", &data).await?;
+
+    if !response.status().is_success() {
+        tracing::warn!("Failed updating status: {}", response.text().await.unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// Marque la config d'un Pi comme décommissionnée (stack désinstallé)
+pub async fn decommission_installation(pi_name: &str) -> Result<()> {
+    let schema_name = pi_name_to_schema(pi_name);
+    let config_id = check_existing_config(&schema_name)
+        .await?
+        .ok_or_else(|| anyhow!("Aucune config enregistrée pour {}", pi_name))?;
+    update_status(pi_name, &config_id, "decommissioned", None).await?;
+    tracing::info!("✅ Config {} of {} decommissioned", config_id, pi_name);
+    Ok(())
+}
+
+/// Enregistre la progression (étapes terminées) d'une installation via Edge Function
+pub async fn save_install_state(pi_name: &str, state: &serde_json::Value) -> Result<()> {
+    let response = call_api(pi_name, "save_install_state", state).await?;
+
+    if !response.status().is_success() {
+        tracing::warn!("Failed saving install state: {}", response.text().await.unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// Sauvegarde le rapport de vérification post-installation via Edge Function
+pub async fn save_health_report(pi_name: &str, report: &serde_json::Value) -> Result<()> {
+    let response = call_api(pi_name, "save_health_report", report).await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to save health report: {}", response.text().await.unwrap_or_default()));
+    }
+
+    Ok(())
+}
+
+/// Enregistre une mise à jour du stack (images avant/après, configs ré-appliquées) via Edge Function
+pub async fn save_stack_update(pi_name: &str, update: &serde_json::Value) -> Result<()> {
+    let response = call_api(pi_name, "save_stack_update", update).await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to save stack update: {}", response.text().await.unwrap_or_default()));
+    }
 
-    let response = client
-        .post(format!("{}/functions/v1/jellysetup-api", supabase_url))
-        .header("Authorization", format!("Bearer {}", service_key))
+    Ok(())
+}
+
+/// Enregistre un battement de cœur de la surveillance (conteneurs, disque, température) via Edge Function
+pub async fn save_heartbeat(pi_name: &str, heartbeat: &serde_json::Value) -> Result<()> {
+    let response = call_api(pi_name, "save_heartbeat", heartbeat).await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to save heartbeat: {}", response.text().await.unwrap_or_default()));
+    }
+
+    Ok(())
+}
+
+/// Ajoute ou met à jour un Pi du registre du compte connecté (table public.pi_registry,
+/// lignes limitées à leur propriétaire par RLS)
+pub async fn save_registered_pi(pi: &serde_json::Value) -> Result<()> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+
+    let mut row = pi.clone();
+    if let Some(fields) = row.as_object_mut() {
+        fields.insert("owner_id".to_string(), json!(owner_id));
+    }
+    let request = client
+        .post(format!("{}/rest/v1/pi_registry?on_conflict=owner_id,pi_name", get_supabase_url()))
         .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
+        .header("Prefer", "resolution=merge-duplicates")
+        .json(&row);
+    let response = with_owner_auth(request, &access_token).send().await?;
 
     if !response.status().is_success() {
-        println!("[Supabase] Warning updating status: {}", response.text().await.unwrap_or_default());
+        return Err(anyhow!("Failed to save registered Pi: {}", response.text().await.unwrap_or_default()));
     }
 
     Ok(())
 }
 
+/// Retire un Pi du registre du compte connecté
+pub async fn remove_registered_pi(pi_name: &str) -> Result<()> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+
+    let request = client
+        .delete(format!("{}/rest/v1/pi_registry", get_supabase_url()))
+        .query(&[("owner_id", format!("eq.{}", owner_id)), ("pi_name", format!("eq.{}", pi_name))]);
+    let response = with_owner_auth(request, &access_token).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to remove registered Pi: {}", response.text().await.unwrap_or_default()));
+    }
+
+    Ok(())
+}
+
+/// Pis du registre du compte connecté
+pub async fn list_registered_pis() -> Result<Vec<serde_json::Value>> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+
+    let request = client
+        .get(format!("{}/rest/v1/pi_registry", get_supabase_url()))
+        .query(&[
+            ("select", "pi_name,host,username,auth_method,ssh_host_fingerprint,installed_version,added_at,last_seen".to_string()),
+            ("owner_id", format!("eq.{}", owner_id)),
+            ("order", "pi_name.asc".to_string()),
+        ]);
+    let response = with_owner_auth(request, &access_token).send().await?;
+
+    let status = response.status();
+    let text = response.text().await?;
+
+    if !status.is_success() {
+        return Err(anyhow!("list_registered_pis error ({}): {}", status, text));
+    }
+
+    Ok(serde_json::from_str(&text).unwrap_or_default())
+}
+
 /// Ajoute un log d'installation dans le schéma du Pi via Edge Function
 pub async fn add_log(
     pi_name: &str,
@@ -240,31 +492,41 @@ pub async fn add_log(
     message: &str,
     duration_ms: Option<i64>,
 ) -> Result<()> {
-    let client = reqwest::Client::new();
-    let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
-
-    let body = json!({
-        "action": "add_log",
-        "pi_name": pi_name,
-        "data": {
-            "step": step,
-            "level": level,
-            "message": message,
-            "duration_ms": duration_ms
-        }
-    });
+    let result = send_log(pi_name, step, level, message, duration_ms).await;
+    if let Err(e) = &result {
+        let write = crate::sync_outbox::PendingWrite::AddLog {
+            pi_name: pi_name.to_string(),
+            step: step.to_string(),
+            level: level.to_string(),
+            message: message.to_string(),
+            duration_ms,
+        };
+        crate::sync_outbox::queue_if_retryable(write, e);
+    }
+    result
+}
 
-    let response = client
-        .post(format!("{}/functions/v1/jellysetup-api", supabase_url))
-        .header("Authorization", format!("Bearer {}", service_key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
+pub(crate) async fn send_log(
+    pi_name: &str,
+    step: &str,
+    level: &str,
+    message: &str,
+    duration_ms: Option<i64>,
+) -> Result<()> {
+    let data = json!({
+        "step": step,
+        "level": level,
+        "message": message,
+        "duration_ms": duration_ms
+    });
+    let response = call_api(pi_name, "add_log", &data).await?;
 
-    if !response.status().is_success() {
-        println!("[Supabase] Warning adding log: {}", response.text().await.unwrap_or_default());
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(Unavailable(format!("{} - {}", status, response.text().await.unwrap_or_default())).into());
+    }
+    if !status.is_success() {
+        tracing::warn!("Failed adding log: {}", response.text().await.unwrap_or_default());
     }
 
     Ok(())
@@ -272,26 +534,22 @@ pub async fn add_log(
 
 /// Vérifie si une config existe déjà dans le schéma
 async fn check_existing_config(schema_name: &str) -> Result<Option<String>> {
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
-    let response = client
+    let request = client
         .get(format!(
             "{}/rest/v1/config?select=id,status&limit=1",
             supabase_url
         ))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
-        .header("Accept-Profile", schema_name)
-        .send()
-        .await?;
+        .header("Accept-Profile", schema_name);
+    let response = with_rest_auth(request, schema_name).send().await?;
 
     let status = response.status();
     let text = response.text().await?;
 
     if !status.is_success() {
-        println!("[Supabase] check_existing_config error ({}): {}", status, text);
+        tracing::error!("check_existing_config error ({}): {}", status, text);
         return Ok(None);
     }
 
@@ -299,53 +557,350 @@ async fn check_existing_config(schema_name: &str) -> Result<Option<String>> {
     match serde_json::from_str::<Vec<ConfigRow>>(&text) {
         Ok(results) => Ok(results.first().and_then(|i| i.id.clone())),
         Err(e) => {
-            println!("[Supabase] check_existing_config parse error: {} - response: {}", e, text);
+            tracing::error!("check_existing_config parse error: {} - response: {}", e, text);
             Ok(None)
         }
     }
 }
 
-/// Sauvegarde la configuration du Pi (credentials, services, etc.) via Edge Function
-pub async fn save_pi_config(
-    pi_name: &str,
-    config_id: &str,
-    alldebrid_key: Option<&str>,
-    ygg_passkey: Option<&str>,
-    cloudflare_token: Option<&str>,
-    jellyfin_api_key: Option<&str>,
-    radarr_api_key: Option<&str>,
-    sonarr_api_key: Option<&str>,
-    prowlarr_api_key: Option<&str>,
-) -> Result<()> {
-    let client = reqwest::Client::new();
+/// Récupère le fingerprint SSH enregistré pour un Pi (None si inconnu)
+pub async fn get_host_fingerprint(pi_name: &str) -> Result<Option<String>> {
+    let schema_name = pi_name_to_schema(pi_name);
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
-
-    let body = json!({
-        "action": "save_credentials",
-        "pi_name": pi_name,
-        "data": {
-            "config_id": config_id,
-            "alldebrid_api_key": alldebrid_key,
-            "ygg_passkey": ygg_passkey,
-            "cloudflare_token": cloudflare_token,
-            "jellyfin_api_key": jellyfin_api_key,
-            "radarr_api_key": radarr_api_key,
-            "sonarr_api_key": sonarr_api_key,
-            "prowlarr_api_key": prowlarr_api_key
-        }
+
+    let request = client
+        .get(format!(
+            "{}/rest/v1/config?select=ssh_host_fingerprint&order=created_at.desc&limit=1",
+            supabase_url
+        ))
+        .header("Accept-Profile", &schema_name);
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    let status = response.status();
+    let text = response.text().await?;
+
+    if !status.is_success() {
+        tracing::error!("get_host_fingerprint error ({}): {}", status, text);
+        return Ok(None);
+    }
+
+    let rows: Vec<ConfigRow> = serde_json::from_str(&text).unwrap_or_default();
+    Ok(rows.into_iter().next().and_then(|r| r.ssh_host_fingerprint))
+}
+
+/// Enregistre l'état de référence du Pi (hashes, conteneurs, réglages) via Edge Function
+pub async fn save_desired_state(pi_name: &str, config_id: &str, snapshot: &crate::drift::Snapshot) -> Result<()> {
+    let data = json!({
+        "config_id": config_id,
+        "desired_state": snapshot
     });
+    let response = call_api(pi_name, "save_desired_state", &data).await?;
+
+    if !response.status().is_success() {
+        tracing::warn!("Failed saving desired state: {}", response.text().await.unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// Récupère l'état de référence du Pi (None si jamais enregistré)
+pub async fn get_desired_state(pi_name: &str) -> Result<Option<crate::drift::Snapshot>> {
+    let schema_name = pi_name_to_schema(pi_name);
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .get(format!(
+            "{}/rest/v1/config?select=desired_state&order=created_at.desc&limit=1",
+            supabase_url
+        ))
+        .header("Accept-Profile", &schema_name);
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    let status = response.status();
+    let text = response.text().await?;
 
-    let response = client
-        .post(format!("{}/functions/v1/jellysetup-api", supabase_url))
-        .header("Authorization", format!("Bearer {}", service_key))
+    if !status.is_success() {
+        return Err(anyhow!("get_desired_state error ({}): {}", status, text));
+    }
+
+    let rows: Vec<ConfigRow> = serde_json::from_str(&text).unwrap_or_default();
+    Ok(rows
+        .into_iter()
+        .next()
+        .and_then(|r| r.desired_state)
+        .and_then(|state| serde_json::from_value(state).ok()))
+}
+
+/// Bucket Storage des versions de configuration (compose + configs rendues)
+const CONFIG_HISTORY_BUCKET: &str = "config-history";
+
+/// Chemin d'un blob de configuration dans le bucket (un dossier par Pi)
+pub fn config_blob_path(pi_name: &str, hash: &str) -> String {
+    format!("{}/{}.json", pi_name_to_schema(pi_name), hash)
+}
+
+/// Dépose un blob de configuration dans Supabase Storage (écrase s'il existe déjà)
+pub async fn upload_config_blob(pi_name: &str, blob_path: &str, blob: &serde_json::Value) -> Result<()> {
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .post(format!("{}/storage/v1/object/{}/{}", supabase_url, CONFIG_HISTORY_BUCKET, blob_path))
         .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
+        .header("x-upsert", "true")
+        .body(serde_json::to_vec(blob)?);
+    let response = with_rest_auth(request, pi_name).send().await?;
 
     if !response.status().is_success() {
-        println!("[Supabase] Warning saving credentials: {}", response.text().await.unwrap_or_default());
+        return Err(anyhow!("Upload {} échoué: {}", blob_path, response.text().await.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+/// Bucket Storage des sauvegardes de configuration des services
+const BACKUPS_BUCKET: &str = "backups";
+
+/// Chemin d'une archive de sauvegarde dans le bucket (un dossier par Pi et par sauvegarde)
+pub fn backup_archive_path(pi_name: &str, backup_id: &str, service: &str) -> String {
+    format!("{}/{}/{}.tar.gz", pi_name_to_schema(pi_name), backup_id, service)
+}
+
+/// Dépose une archive de sauvegarde dans Supabase Storage
+pub async fn upload_backup_archive(pi_name: &str, archive_path: &str, archive: Vec<u8>) -> Result<()> {
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .post(format!("{}/storage/v1/object/{}/{}", supabase_url, BACKUPS_BUCKET, archive_path))
+        .header("Content-Type", "application/gzip")
+        .header("x-upsert", "true")
+        .body(archive);
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Upload {} échoué: {}", archive_path, response.text().await.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+/// Télécharge une archive de sauvegarde depuis Supabase Storage
+pub async fn download_backup_archive(pi_name: &str, archive_path: &str) -> Result<Vec<u8>> {
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .get(format!("{}/storage/v1/object/{}/{}", supabase_url, BACKUPS_BUCKET, archive_path));
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Archive {} introuvable ({})", archive_path, response.status()));
+    }
+    Ok(response.bytes().await?.to_vec())
+}
+
+const JELLYFIN_TEMPLATES_BUCKET: &str = "jellyfin-templates";
+
+/// Chemin d'un fichier de modèle Jellyfin dans le bucket (dossier du compte, puis version de Jellyfin)
+pub fn jellyfin_template_path(owner_id: &str, jellyfin_version: &str, file: &str) -> Result<String> {
+    if jellyfin_version.is_empty() || !jellyfin_version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return Err(anyhow!("Version Jellyfin invalide: {}", jellyfin_version));
+    }
+    Ok(format!("{}/{}/{}", owner_id, jellyfin_version, file))
+}
+
+/// Dépose un fichier de modèle Jellyfin (archive ou métadonnées) dans le dossier du compte connecté
+pub async fn upload_jellyfin_template_file(jellyfin_version: &str, file: &str, content_type: &str, content: Vec<u8>) -> Result<()> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+    let template_path = jellyfin_template_path(&owner_id, jellyfin_version, file)?;
+
+    let request = client
+        .post(format!("{}/storage/v1/object/{}/{}", get_supabase_url(), JELLYFIN_TEMPLATES_BUCKET, template_path))
+        .header("Content-Type", content_type)
+        .header("x-upsert", "true")
+        .body(content);
+    let response = with_owner_auth(request, &access_token).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Upload {} échoué: {}", template_path, response.text().await.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+/// Télécharge un fichier de modèle Jellyfin du compte connecté (None si aucun modèle pour cette version)
+pub async fn download_jellyfin_template_file(jellyfin_version: &str, file: &str) -> Result<Option<Vec<u8>>> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+    let template_path = jellyfin_template_path(&owner_id, jellyfin_version, file)?;
+
+    let request = client.get(format!("{}/storage/v1/object/{}/{}", get_supabase_url(), JELLYFIN_TEMPLATES_BUCKET, template_path));
+    let response = with_owner_auth(request, &access_token).send().await?;
+
+    let status = response.status();
+    // Storage répond 400 "Object not found" (ou 404) pour un objet absent
+    if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::BAD_REQUEST {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(anyhow!("Modèle {} indisponible ({})", template_path, status));
+    }
+    Ok(Some(response.bytes().await?.to_vec()))
+}
+
+/// Ajoute le jeton du compte connecté (account::credentials) à un appel Storage ou
+/// REST limité à son propriétaire (politiques RLS sur auth.uid())
+pub(crate) fn with_owner_auth(request: reqwest::RequestBuilder, access_token: &SecretString) -> reqwest::RequestBuilder {
+    request
+        .header("apikey", get_supabase_key())
+        .header("Authorization", format!("Bearer {}", access_token.expose()))
+}
+
+/// Bucket Storage des points de reprise d'installation (contenu chiffré)
+const INSTALL_RESUME_BUCKET: &str = "install-resume";
+
+/// Chemin du point de reprise d'un Pi dans le bucket (dossier du compte propriétaire)
+pub fn resume_point_path(owner_id: &str, pi_name: &str) -> String {
+    format!("{}/{}/resume.json", owner_id, pi_name_to_schema(pi_name))
+}
+
+fn resume_point_url(owner_id: &str, pi_name: &str) -> String {
+    format!("{}/storage/v1/object/{}/{}", get_supabase_url(), INSTALL_RESUME_BUCKET, resume_point_path(owner_id, pi_name))
+}
+
+/// Dépose le point de reprise d'une installation (écrase le précédent)
+pub async fn upload_resume_point(pi_name: &str, point: &serde_json::Value) -> Result<()> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+
+    let request = client
+        .post(resume_point_url(&owner_id, pi_name))
+        .header("Content-Type", "application/json")
+        .header("x-upsert", "true")
+        .body(serde_json::to_vec(point)?);
+    let response = with_owner_auth(request, &access_token).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Upload du point de reprise de {} échoué: {}", pi_name, response.text().await.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+/// Télécharge le point de reprise d'un Pi (None si aucune installation interrompue)
+pub async fn download_resume_point(pi_name: &str) -> Result<Option<serde_json::Value>> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+
+    let request = client.get(resume_point_url(&owner_id, pi_name));
+    let response = with_owner_auth(request, &access_token).send().await?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::BAD_REQUEST {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(anyhow!("Point de reprise de {} indisponible ({})", pi_name, status));
+    }
+    Ok(Some(response.json().await?))
+}
+
+/// Supprime le point de reprise (installation terminée)
+pub async fn delete_resume_point(pi_name: &str) -> Result<()> {
+    let client = http_client()?;
+    let (access_token, owner_id) = crate::account::credentials().await?;
+
+    let request = client.delete(resume_point_url(&owner_id, pi_name));
+    let response = with_owner_auth(request, &access_token).send().await?;
+
+    let status = response.status();
+    if !status.is_success() && status != reqwest::StatusCode::NOT_FOUND && status != reqwest::StatusCode::BAD_REQUEST {
+        return Err(anyhow!("Suppression du point de reprise de {} échouée ({})", pi_name, status));
+    }
+    Ok(())
+}
+
+/// Télécharge un blob de configuration depuis Supabase Storage
+pub async fn download_config_blob(pi_name: &str, blob_path: &str) -> Result<serde_json::Value> {
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .get(format!("{}/storage/v1/object/{}/{}", supabase_url, CONFIG_HISTORY_BUCKET, blob_path));
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Blob {} introuvable ({})", blob_path, response.status()));
+    }
+    Ok(response.json().await?)
+}
+
+/// Enregistre une version de configuration appliquée via Edge Function
+pub async fn save_config_version(pi_name: &str, version: &serde_json::Value) -> Result<()> {
+    let response = call_api(pi_name, "save_config_version", version).await?;
+
+    if !response.status().is_success() {
+        tracing::warn!("Failed saving config version: {}", response.text().await.unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// Versions de configuration d'un Pi, de la plus récente à la plus ancienne
+pub async fn list_config_versions(pi_name: &str) -> Result<Vec<serde_json::Value>> {
+    let schema_name = pi_name_to_schema(pi_name);
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .get(format!(
+            "{}/rest/v1/config_versions?select=*&order=created_at.desc",
+            supabase_url
+        ))
+        .header("Accept-Profile", &schema_name);
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    let status = response.status();
+    let text = response.text().await?;
+
+    if !status.is_success() {
+        return Err(anyhow!("list_config_versions error ({}): {}", status, text));
+    }
+
+    Ok(serde_json::from_str(&text).unwrap_or_default())
+}
+
+/// Credentials sauvegardés avec la configuration du Pi (None: non renseigné)
+#[derive(Default)]
+pub struct PiCredentials<'a> {
+    pub alldebrid_api_key: Option<&'a str>,
+    pub ygg_passkey: Option<&'a str>,
+    pub cloudflare_token: Option<&'a str>,
+    pub jellyfin_api_key: Option<&'a str>,
+    pub radarr_api_key: Option<&'a str>,
+    pub sonarr_api_key: Option<&'a str>,
+    pub prowlarr_api_key: Option<&'a str>,
+    /// Compte admin chiffré avec le mot de passe Jellyfin
+    pub admin_account_encrypted: Option<&'a str>,
+}
+
+/// Sauvegarde la configuration du Pi (credentials, services, etc.) via Edge Function
+pub async fn save_pi_config(pi_name: &str, config_id: &str, credentials: &PiCredentials<'_>) -> Result<()> {
+    let data = json!({
+        "config_id": config_id,
+        "alldebrid_api_key": credentials.alldebrid_api_key,
+        "ygg_passkey": credentials.ygg_passkey,
+        "cloudflare_token": credentials.cloudflare_token,
+        "jellyfin_api_key": credentials.jellyfin_api_key,
+        "radarr_api_key": credentials.radarr_api_key,
+        "sonarr_api_key": credentials.sonarr_api_key,
+        "prowlarr_api_key": credentials.prowlarr_api_key,
+        "admin_account_encrypted": credentials.admin_account_encrypted
+    });
+    let response = call_api(pi_name, "save_credentials", &data).await?;
+
+    if !response.status().is_success() {
+        tracing::warn!("Failed saving credentials: {}", response.text().await.unwrap_or_default());
     }
 
     Ok(())
@@ -361,53 +916,93 @@ pub async fn save_service(
     image: Option<&str>,
     config: Option<serde_json::Value>,
 ) -> Result<()> {
-    let client = reqwest::Client::new();
-    let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
-
-    let body = json!({
-        "action": "save_service",
-        "pi_name": pi_name,
-        "data": {
-            "service_name": service_name,
-            "container_id": container_id,
-            "status": status,
-            "port": port,
-            "image": image,
-            "config": config
-        }
-    });
+    let result = send_service(pi_name, service_name, container_id, status, port, image, config.clone()).await;
+    if let Err(e) = &result {
+        let write = crate::sync_outbox::PendingWrite::SaveService {
+            pi_name: pi_name.to_string(),
+            service_name: service_name.to_string(),
+            container_id: container_id.map(String::from),
+            status: status.to_string(),
+            port,
+            image: image.map(String::from),
+            config,
+        };
+        crate::sync_outbox::queue_if_retryable(write, e);
+    }
+    result
+}
 
-    let response = client
-        .post(format!("{}/functions/v1/jellysetup-api", supabase_url))
-        .header("Authorization", format!("Bearer {}", service_key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await?;
+pub(crate) async fn send_service(
+    pi_name: &str,
+    service_name: &str,
+    container_id: Option<&str>,
+    status: &str,
+    port: Option<i32>,
+    image: Option<&str>,
+    config: Option<serde_json::Value>,
+) -> Result<()> {
+    let data = json!({
+        "service_name": service_name,
+        "container_id": container_id,
+        "status": status,
+        "port": port,
+        "image": image,
+        "config": config
+    });
+    let response = call_api(pi_name, "save_service", &data).await?;
 
-    if !response.status().is_success() {
-        println!("[Supabase] Warning saving service: {}", response.text().await.unwrap_or_default());
+    let http_status = response.status();
+    if http_status.is_server_error() {
+        return Err(Unavailable(format!("{} - {}", http_status, response.text().await.unwrap_or_default())).into());
+    }
+    if !http_status.is_success() {
+        tracing::warn!("Failed saving service: {}", response.text().await.unwrap_or_default());
     }
 
     Ok(())
 }
 
+/// Archives d'une sauvegarde (lignes `backups` portant son backup_id dans metadata)
+pub async fn list_backup_archives(pi_name: &str, backup_id: &str) -> Result<Vec<serde_json::Value>> {
+    let schema_name = pi_name_to_schema(pi_name);
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .get(format!(
+            "{}/rest/v1/backups?select=*&metadata->>backup_id=eq.{}&order=service_name.asc",
+            supabase_url, backup_id
+        ))
+        .header("Accept-Profile", &schema_name);
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    let status = response.status();
+    let text = response.text().await?;
+
+    if !status.is_success() {
+        return Err(anyhow!("list_backup_archives error ({}): {}", status, text));
+    }
+
+    Ok(serde_json::from_str(&text).unwrap_or_default())
+}
+
+/// Ligne de la table `backups`
+pub struct BackupRecord<'a> {
+    pub backup_type: &'a str,
+    pub service_name: Option<&'a str>,
+    pub file_path: &'a str,
+    pub file_size: i64,
+    pub checksum: &'a str,
+    pub storage_path: &'a str,
+    pub metadata: Option<serde_json::Value>,
+}
+
 /// Enregistre un backup dans le schéma du Pi
-pub async fn save_backup(
-    pi_name: &str,
-    backup_type: &str,
-    service_name: Option<&str>,
-    file_path: &str,
-    file_size: i64,
-    checksum: &str,
-    storage_path: &str,
-    metadata: Option<serde_json::Value>,
-) -> Result<String> {
+pub async fn save_backup(pi_name: &str, record: BackupRecord<'_>) -> Result<String> {
+    let BackupRecord { backup_type, service_name, file_path, file_size, checksum, storage_path, metadata } = record;
     let schema_name = pi_name_to_schema(pi_name);
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
     let mut body = json!({
         "backup_type": backup_type,
@@ -424,16 +1019,13 @@ pub async fn save_backup(
         body["metadata"] = meta;
     }
 
-    let response = client
+    let request = client
         .post(format!("{}/rest/v1/backups", supabase_url))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
         .header("Content-Profile", &schema_name)
         .header("Prefer", "return=representation")
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = with_rest_auth(request, pi_name).send().await?;
 
     #[derive(Deserialize)]
     struct BackupRow {
@@ -443,7 +1035,7 @@ pub async fn save_backup(
     let result: Vec<BackupRow> = response.json().await?;
     let id = result.first().map(|b| b.id.clone()).unwrap_or_default();
 
-    println!("[Supabase] Saved backup in schema '{}': {}", schema_name, id);
+    tracing::info!("Saved backup in schema '{}': {}", schema_name, id);
     Ok(id)
 }
 
@@ -460,26 +1052,30 @@ pub enum MediaType {
     Episode,
 }
 
+/// Film ou série du catalogue (champs optionnels omis s'ils sont absents)
+pub struct MediaRecord<'a> {
+    pub media_type: MediaType,
+    pub title: &'a str,
+    pub year: Option<i32>,
+    pub imdb_id: Option<&'a str>,
+    pub tmdb_id: Option<i32>,
+    pub file_path: Option<&'a str>,
+    pub file_size: Option<i64>,
+    pub quality: Option<&'a str>,
+    pub debrid_link: Option<&'a str>,
+    pub poster_url: Option<&'a str>,
+    pub overview: Option<&'a str>,
+    pub metadata: Option<serde_json::Value>,
+}
+
 /// Ajoute ou met à jour un film/série dans le catalogue
-pub async fn upsert_media(
-    pi_name: &str,
-    media_type: MediaType,
-    title: &str,
-    year: Option<i32>,
-    imdb_id: Option<&str>,
-    tmdb_id: Option<i32>,
-    file_path: Option<&str>,
-    file_size: Option<i64>,
-    quality: Option<&str>,
-    debrid_link: Option<&str>,
-    poster_url: Option<&str>,
-    overview: Option<&str>,
-    metadata: Option<serde_json::Value>,
-) -> Result<String> {
+pub async fn upsert_media(pi_name: &str, media: MediaRecord<'_>) -> Result<String> {
+    let MediaRecord {
+        media_type, title, year, imdb_id, tmdb_id, file_path, file_size, quality, debrid_link, poster_url, overview, metadata,
+    } = media;
     let schema_name = pi_name_to_schema(pi_name);
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
     let media_type_str = match media_type {
         MediaType::Movie => "movie",
@@ -504,16 +1100,13 @@ pub async fn upsert_media(
     if let Some(meta) = metadata { body["metadata"] = meta; }
 
     // Upsert basé sur imdb_id ou tmdb_id si présent
-    let response = client
+    let request = client
         .post(format!("{}/rest/v1/media", supabase_url))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
         .header("Content-Profile", &schema_name)
         .header("Prefer", "return=representation")
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = with_rest_auth(request, pi_name).send().await?;
 
     #[derive(Deserialize)]
     struct MediaRow { id: String }
@@ -521,25 +1114,26 @@ pub async fn upsert_media(
     let result: Vec<MediaRow> = response.json().await?;
     let id = result.first().map(|m| m.id.clone()).unwrap_or_default();
 
-    println!("[Supabase] Saved media '{}' in schema '{}': {}", title, schema_name, id);
+    tracing::info!("Saved media '{}' in schema '{}': {}", title, schema_name, id);
     Ok(id)
 }
 
+/// Épisode rattaché à une série du catalogue
+pub struct EpisodeRecord<'a> {
+    pub season_number: i32,
+    pub episode_number: i32,
+    pub episode_title: &'a str,
+    pub file_path: Option<&'a str>,
+    pub file_size: Option<i64>,
+    pub debrid_link: Option<&'a str>,
+}
+
 /// Ajoute un épisode à une série existante
-pub async fn add_episode(
-    pi_name: &str,
-    series_id: &str,
-    season_number: i32,
-    episode_number: i32,
-    episode_title: &str,
-    file_path: Option<&str>,
-    file_size: Option<i64>,
-    debrid_link: Option<&str>,
-) -> Result<String> {
+pub async fn add_episode(pi_name: &str, series_id: &str, episode: EpisodeRecord<'_>) -> Result<String> {
+    let EpisodeRecord { season_number, episode_number, episode_title, file_path, file_size, debrid_link } = episode;
     let schema_name = pi_name_to_schema(pi_name);
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
     let mut body = json!({
         "media_type": "episode",
@@ -554,16 +1148,13 @@ pub async fn add_episode(
     if let Some(size) = file_size { body["file_size"] = json!(size); }
     if let Some(link) = debrid_link { body["debrid_link"] = json!(link); }
 
-    let response = client
+    let request = client
         .post(format!("{}/rest/v1/media", supabase_url))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
         .header("Content-Profile", &schema_name)
         .header("Prefer", "return=representation")
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = with_rest_auth(request, pi_name).send().await?;
 
     #[derive(Deserialize)]
     struct MediaRow { id: String }
@@ -580,9 +1171,8 @@ pub async fn update_media_debrid_link(
     expires_at: Option<&str>,
 ) -> Result<()> {
     let schema_name = pi_name_to_schema(pi_name);
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
     let mut body = json!({
         "debrid_link": debrid_link
@@ -592,22 +1182,56 @@ pub async fn update_media_debrid_link(
         body["debrid_link_expires"] = json!(exp);
     }
 
-    client
+    let request = client
         .patch(format!(
             "{}/rest/v1/media?id=eq.{}",
             supabase_url, media_id
         ))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
         .header("Content-Profile", &schema_name)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    with_rest_auth(request, pi_name).send().await?;
 
     Ok(())
 }
 
+/// Lien debrid d'un média du catalogue
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaDebridLink {
+    pub id: String,
+    pub title: String,
+    pub debrid_link: String,
+    pub debrid_link_expires: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Médias dont le lien debrid expire avant `before` (RFC 3339) ou n'a pas d'expiration connue
+pub async fn list_expiring_debrid_links(pi_name: &str, before: &str) -> Result<Vec<MediaDebridLink>> {
+    let schema_name = pi_name_to_schema(pi_name);
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let request = client
+        .get(format!("{}/rest/v1/media", supabase_url))
+        .query(&[
+            ("select", "id,title,debrid_link,debrid_link_expires,metadata"),
+            ("debrid_link", "not.is.null"),
+            ("or", &format!("(debrid_link_expires.is.null,debrid_link_expires.lt.{})", before)),
+        ])
+        .header("Accept-Profile", &schema_name);
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    let status = response.status();
+    let text = response.text().await?;
+
+    if !status.is_success() {
+        return Err(anyhow!("list_expiring_debrid_links error ({}): {}", status, text));
+    }
+
+    Ok(serde_json::from_str(&text)?)
+}
+
 /// Marque un média comme regardé
 pub async fn mark_media_watched(
     pi_name: &str,
@@ -615,9 +1239,8 @@ pub async fn mark_media_watched(
     progress_seconds: Option<i32>,
 ) -> Result<()> {
     let schema_name = pi_name_to_schema(pi_name);
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
     let mut body = json!({
         "watched": true,
@@ -629,22 +1252,56 @@ pub async fn mark_media_watched(
         body["watch_progress"] = json!(progress);
     }
 
-    client
+    let request = client
         .patch(format!(
             "{}/rest/v1/media?id=eq.{}",
             supabase_url, media_id
         ))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
         .header("Content-Profile", &schema_name)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    with_rest_auth(request, pi_name).send().await?;
 
     Ok(())
 }
 
+// =============================================================================
+// DEMANDES JELLYSEERR
+// =============================================================================
+
+/// Upsert des demandes du foyer (clé: jellyseerr_id)
+pub async fn upsert_requests(pi_name: &str, rows: &[serde_json::Value]) -> Result<()> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let schema_name = pi_name_to_schema(pi_name);
+    let client = http_client()?;
+    let supabase_url = get_supabase_url();
+
+    let synced_at = chrono::Utc::now().to_rfc3339();
+    let rows: Vec<serde_json::Value> = rows
+        .iter()
+        .cloned()
+        .map(|mut row| {
+            row["synced_at"] = json!(synced_at);
+            row
+        })
+        .collect();
+
+    let request = client
+        .post(format!("{}/rest/v1/requests?on_conflict=jellyseerr_id", supabase_url))
+        .header("Content-Type", "application/json")
+        .header("Content-Profile", &schema_name)
+        .header("Prefer", "resolution=merge-duplicates")
+        .json(&rows);
+    let response = with_rest_auth(request, pi_name).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("upsert_requests error: {}", response.text().await.unwrap_or_default()));
+    }
+    Ok(())
+}
+
 // =============================================================================
 // TÉLÉCHARGEMENTS
 // =============================================================================
@@ -659,9 +1316,8 @@ pub async fn create_download(
     total_size: Option<i64>,
 ) -> Result<String> {
     let schema_name = pi_name_to_schema(pi_name);
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
     let mut body = json!({
         "media_id": media_id,
@@ -673,16 +1329,13 @@ pub async fn create_download(
     if let Some(hash) = torrent_hash { body["torrent_hash"] = json!(hash); }
     if let Some(size) = total_size { body["total_size"] = json!(size); }
 
-    let response = client
+    let request = client
         .post(format!("{}/rest/v1/downloads", supabase_url))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
         .header("Content-Profile", &schema_name)
         .header("Prefer", "return=representation")
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = with_rest_auth(request, pi_name).send().await?;
 
     #[derive(Deserialize)]
     struct DownloadRow { id: String }
@@ -691,21 +1344,22 @@ pub async fn create_download(
     Ok(result.first().map(|d| d.id.clone()).unwrap_or_default())
 }
 
+/// Progression d'un téléchargement (started_at/completed_at déduits du statut)
+pub struct DownloadProgress<'a> {
+    pub status: &'a str,
+    pub progress: f64,
+    pub download_speed: Option<i64>,
+    pub downloaded_size: Option<i64>,
+    pub seeds: Option<i32>,
+    pub peers: Option<i32>,
+}
+
 /// Met à jour la progression d'un téléchargement
-pub async fn update_download_progress(
-    pi_name: &str,
-    download_id: &str,
-    status: &str,
-    progress: f64,
-    download_speed: Option<i64>,
-    downloaded_size: Option<i64>,
-    seeds: Option<i32>,
-    peers: Option<i32>,
-) -> Result<()> {
+pub async fn update_download_progress(pi_name: &str, download_id: &str, update: DownloadProgress<'_>) -> Result<()> {
+    let DownloadProgress { status, progress, download_speed, downloaded_size, seeds, peers } = update;
     let schema_name = pi_name_to_schema(pi_name);
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let supabase_url = get_supabase_url();
-    let service_key = get_supabase_service_key();
 
     let mut body = json!({
         "status": status,
@@ -727,18 +1381,15 @@ pub async fn update_download_progress(
         body["completed_at"] = json!(chrono::Utc::now().to_rfc3339());
     }
 
-    client
+    let request = client
         .patch(format!(
             "{}/rest/v1/downloads?id=eq.{}",
             supabase_url, download_id
         ))
-        .header("apikey", &service_key)
-        .header("Authorization", format!("Bearer {}", service_key))
         .header("Content-Type", "application/json")
         .header("Content-Profile", &schema_name)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    with_rest_auth(request, pi_name).send().await?;
 
     Ok(())
 }
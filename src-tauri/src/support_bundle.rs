@@ -0,0 +1,165 @@
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// =============================================================================
+// Bundle de support (zip à joindre à un rapport de bug)
+// =============================================================================
+//
+// Rassemble en une archive: les journaux de l'application (app_logs), flash.log,
+// le journal de découverte réseau, l'install.log de la dernière session
+// (InstallationLogger), les réglages et la configuration du formulaire, plus
+// les versions de l'application et du système. Tout ce qui est texte repasse
+// par redact; dans les réglages et la configuration, les champs dont le nom
+// ressemble à un secret sont masqués quelle que soit leur valeur. Les fichiers
+// absents sont simplement ignorés.
+
+const MASK: &str = "***";
+const DISCOVERY_LOG: &str = "/tmp/jellysetup_discovery.log";
+
+/// Nom de champ qui désigne un secret (password, wifiPassword, alldebridKey...)
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    ["password", "passphrase", "passkey", "token", "secret", "webhook", "privatekey"]
+        .iter()
+        .any(|word| key.contains(word))
+        || (key.ends_with("key") && key != "keymap")
+}
+
+/// Configuration sans secrets: champs sensibles masqués, le reste passé par redact
+fn sanitize(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if is_secret_key(key) && !field.is_null() {
+                    *field = serde_json::Value::String(MASK.to_string());
+                } else {
+                    sanitize(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(sanitize),
+        serde_json::Value::String(_) => crate::redact::redact_json(value),
+        _ => {}
+    }
+}
+
+fn system_info() -> serde_json::Value {
+    serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "family": std::env::consts::FAMILY,
+        "arch": std::env::consts::ARCH,
+        "generated_at": chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+struct Bundle {
+    zip: ZipWriter<std::fs::File>,
+    options: FileOptions,
+}
+
+impl Bundle {
+    fn add(&mut self, name: &str, content: &str) -> Result<()> {
+        self.zip.start_file(name, self.options)?;
+        self.zip.write_all(content.as_bytes())?;
+        Ok(())
+    }
+
+    fn add_json(&mut self, name: &str, value: &serde_json::Value) -> Result<()> {
+        self.add(name, &serde_json::to_string_pretty(value)?)
+    }
+
+    /// Copie masquée d'un fichier texte, ignoré s'il n'existe pas
+    fn add_file(&mut self, name: &str, path: &Path) -> Result<()> {
+        match std::fs::read(path) {
+            Ok(bytes) => self.add(name, &crate::redact::redact(&String::from_utf8_lossy(&bytes))),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+fn output_dir() -> Result<PathBuf> {
+    dirs::download_dir()
+        .or_else(|| dirs::data_dir().map(|dir| dir.join("jellysetup")))
+        .ok_or_else(|| anyhow!("Cannot determine output directory"))
+}
+
+/// Écrit le bundle (dans Téléchargements) et renvoie son chemin
+pub fn export(config: Option<serde_json::Value>) -> Result<PathBuf> {
+    let dir = output_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("jellysetup-support-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    tracing::info!("Exporting support bundle to {}", path.display());
+
+    let mut bundle = Bundle {
+        zip: ZipWriter::new(std::fs::File::create(&path)?),
+        options: FileOptions::default().compression_method(CompressionMethod::Deflated),
+    };
+
+    bundle.add_json("system.json", &system_info())?;
+
+    let mut settings = serde_json::to_value(crate::config::settings())?;
+    sanitize(&mut settings);
+    bundle.add_json("settings.json", &settings)?;
+
+    if let Some(mut config) = config {
+        sanitize(&mut config);
+        bundle.add_json("config.json", &config)?;
+    }
+
+    if let Ok(logs) = crate::app_logs::logs_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&logs)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| path.is_file()).collect())
+            .unwrap_or_default();
+        files.sort();
+        for file in files {
+            if let Some(name) = file.file_name().and_then(|n| n.to_str()) {
+                bundle.add_file(&format!("logs/{}", name), &file)?;
+            }
+        }
+    }
+
+    if let Some(cache) = dirs::cache_dir() {
+        bundle.add_file("flash.log", &cache.join("jellysetup").join("flash.log"))?;
+    }
+    bundle.add_file("discovery.log", Path::new(DISCOVERY_LOG))?;
+    if let Some(session) = crate::workspace::latest() {
+        bundle.add_file("install.log", &session.join("install.log"))?;
+    }
+
+    bundle.zip.finish()?;
+    tracing::info!("✅ Support bundle ready: {}", path.display());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_config() {
+        let mut config = serde_json::json!({
+            "piName": "jellypi",
+            "systemPassword": "hunter22",
+            "wifiPassword": "maisonwifi",
+            "alldebridKey": "abcdef123456",
+            "keymap": "fr",
+            "discordWebhook": null,
+            "services": [{ "name": "radarr", "apiKey": "0123456789" }],
+            "notes": "echo 'raspberry' | sudo -S reboot",
+        });
+        sanitize(&mut config);
+        assert_eq!(config["piName"], "jellypi");
+        assert_eq!(config["systemPassword"], MASK);
+        assert_eq!(config["wifiPassword"], MASK);
+        assert_eq!(config["alldebridKey"], MASK);
+        assert_eq!(config["keymap"], "fr");
+        assert!(config["discordWebhook"].is_null());
+        assert_eq!(config["services"][0]["name"], "radarr");
+        assert_eq!(config["services"][0]["apiKey"], MASK);
+        assert_eq!(config["notes"], "echo '***' | sudo -S reboot");
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use ts_rs::TS;
+
+use crate::registration::InstallationRegistration;
+
+// =============================================================================
+// File d'attente des écritures Supabase (outbox)
+// =============================================================================
+//
+// Une écriture Supabase qui échoue faute de connexion (erreur réseau, 5xx, mode
+// hors ligne) est enregistrée dans config_dir/jellysetup/supabase_outbox.json au
+// lieu d'être perdue. Les écritures en attente sont rejouées toutes les minutes
+// (délai exponentiel par écriture, 30 s à 1 h) ou sur demande
+// (`flush_pending_sync`). Une écriture refusée par Supabase (4xx) est abandonnée.
+
+const RETRY_INTERVAL_SECS: u64 = 60;
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 3600;
+
+/// Écriture à rejouer (arguments de la fonction supabase:: d'origine)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PendingWrite {
+    SaveInstallation {
+        registration: InstallationRegistration,
+    },
+    AddLog {
+        pi_name: String,
+        step: String,
+        level: String,
+        message: String,
+        duration_ms: Option<i64>,
+    },
+    SaveService {
+        pi_name: String,
+        service_name: String,
+        container_id: Option<String>,
+        status: String,
+        port: Option<i32>,
+        image: Option<String>,
+        config: Option<Value>,
+    },
+    /// Lot de logs d'installation (InstallationLogger)
+    InstallLogs {
+        pi_name: String,
+        body: Value,
+    },
+}
+
+impl PendingWrite {
+    fn pi_name(&self) -> &str {
+        match self {
+            Self::SaveInstallation { registration } => &registration.pi_name,
+            Self::AddLog { pi_name, .. } | Self::SaveService { pi_name, .. } | Self::InstallLogs { pi_name, .. } => pi_name,
+        }
+    }
+
+    async fn replay(&self) -> Result<()> {
+        match self {
+            Self::SaveInstallation { registration } => crate::supabase::send_installation(registration).await.map(|_| ()),
+            Self::AddLog { pi_name, step, level, message, duration_ms } => {
+                crate::supabase::send_log(pi_name, step, level, message, *duration_ms).await
+            }
+            Self::SaveService { pi_name, service_name, container_id, status, port, image, config } => {
+                crate::supabase::send_service(
+                    pi_name,
+                    service_name,
+                    container_id.as_deref(),
+                    status,
+                    *port,
+                    image.as_deref(),
+                    config.clone(),
+                )
+                .await
+            }
+            Self::InstallLogs { pi_name, body } => crate::logging::send_logs(pi_name, body).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingEntry {
+    id: String,
+    write: PendingWrite,
+    queued_at: String,
+    attempts: u32,
+    next_attempt_at: String,
+    last_error: String,
+}
+
+impl PendingEntry {
+    fn due(&self, now: DateTime<Utc>) -> bool {
+        DateTime::parse_from_rfc3339(&self.next_attempt_at).map(|at| at <= now).unwrap_or(true)
+    }
+
+    fn failed(&mut self, error: &anyhow::Error, now: DateTime<Utc>) {
+        self.attempts += 1;
+        self.next_attempt_at = (now + Duration::seconds(backoff_secs(self.attempts))).to_rfc3339();
+        self.last_error = error.to_string();
+    }
+}
+
+/// Résultat d'un rejeu des écritures en attente
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct PendingSyncReport {
+    pub sent: u32,
+    pub remaining: u32,
+    /// Écritures refusées par Supabase, abandonnées
+    pub dropped: Vec<String>,
+}
+
+/// Délai avant la tentative suivante (doublé à chaque échec)
+fn backoff_secs(attempts: u32) -> i64 {
+    (BASE_BACKOFF_SECS << attempts.saturating_sub(1).min(10)).min(MAX_BACKOFF_SECS)
+}
+
+/// Échec temporaire: la même écriture a des chances d'aboutir plus tard
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    error.is::<crate::supabase::Unavailable>() || error.downcast_ref::<reqwest::Error>().is_some()
+}
+
+// Lecture-modification-écriture du fichier (jamais tenu pendant un appel réseau)
+static FILE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+// Un seul rejeu à la fois (boucle de fond et commande)
+static FLUSH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+fn outbox_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("jellysetup").join("supabase_outbox.json"))
+}
+
+fn load() -> Vec<PendingEntry> {
+    outbox_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(entries: &[PendingEntry]) -> Result<()> {
+    let path = outbox_path().ok_or_else(|| anyhow::anyhow!("Cannot determine config directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(entries)?)?;
+    Ok(())
+}
+
+/// Met l'écriture en attente si l'échec est temporaire
+pub fn queue_if_retryable(write: PendingWrite, error: &anyhow::Error) {
+    if !is_retryable(error) {
+        return;
+    }
+    let now = Utc::now();
+    let mut entry = PendingEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        write,
+        queued_at: now.to_rfc3339(),
+        attempts: 0,
+        next_attempt_at: String::new(),
+        last_error: String::new(),
+    };
+    entry.failed(error, now);
+
+    let _lock = FILE_LOCK.lock().unwrap();
+    let mut entries = load();
+    entries.push(entry);
+    match save(&entries) {
+        Ok(()) => tracing::warn!("⚠️ Supabase write queued ({} pending): {}", entries.len(), error),
+        Err(e) => tracing::error!("❌ Could not queue Supabase write: {}", e),
+    }
+}
+
+/// Nombre d'écritures en attente
+pub fn pending_count() -> usize {
+    let _lock = FILE_LOCK.lock().unwrap();
+    load().len()
+}
+
+/// Rejoue les écritures en attente (`force`: sans attendre leur délai)
+pub async fn flush(force: bool) -> Result<PendingSyncReport> {
+    let _flushing = FLUSH_LOCK.lock().await;
+    let entries = {
+        let _lock = FILE_LOCK.lock().unwrap();
+        load()
+    };
+    let mut report = PendingSyncReport::default();
+    let mut done: Vec<String> = Vec::new();
+    let mut retried: Vec<PendingEntry> = Vec::new();
+
+    let now = Utc::now();
+    for mut entry in entries.into_iter().filter(|e| force || e.due(now)) {
+        match entry.write.replay().await {
+            Ok(()) => {
+                report.sent += 1;
+                done.push(entry.id);
+            }
+            Err(e) if is_retryable(&e) => {
+                tracing::info!("Supabase still unreachable ({}), retrying later", e);
+                entry.failed(&e, Utc::now());
+                retried.push(entry);
+                // Connexion toujours absente: inutile d'essayer les suivantes
+                break;
+            }
+            Err(e) => {
+                tracing::error!("❌ Dropping write for {} rejected by Supabase: {}", entry.write.pi_name(), e);
+                report.dropped.push(format!("{}: {}", entry.write.pi_name(), e));
+                done.push(entry.id);
+            }
+        }
+    }
+
+    // Fusion avec le fichier courant: des écritures ont pu être ajoutées entre-temps
+    let _lock = FILE_LOCK.lock().unwrap();
+    let mut current = load();
+    current.retain(|e| !done.contains(&e.id));
+    for updated in retried {
+        if let Some(slot) = current.iter_mut().find(|e| e.id == updated.id) {
+            *slot = updated;
+        }
+    }
+    save(&current)?;
+    report.remaining = current.len() as u32;
+    if report.sent > 0 {
+        tracing::info!("✅ {} queued Supabase writes sent, {} remaining", report.sent, report.remaining);
+    }
+    Ok(report)
+}
+
+/// Boucle de rejeu en arrière-plan (lancée au démarrage de l'application)
+pub fn start() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+            if crate::config::settings().offline_mode || pending_count() == 0 {
+                continue;
+            }
+            if let Err(e) = flush(false).await {
+                tracing::warn!("⚠️ Retry failed: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outbox_backoff() {
+        assert_eq!(backoff_secs(1), 30);
+        assert_eq!(backoff_secs(3), 120);
+        assert_eq!(backoff_secs(20), MAX_BACKOFF_SECS);
+
+        let now = Utc::now();
+        let mut entry = PendingEntry {
+            id: "1".to_string(),
+            write: PendingWrite::AddLog {
+                pi_name: "jellypi".to_string(),
+                step: "docker".to_string(),
+                level: "error".to_string(),
+                message: "timeout".to_string(),
+                duration_ms: None,
+            },
+            queued_at: now.to_rfc3339(),
+            attempts: 0,
+            next_attempt_at: String::new(),
+            last_error: String::new(),
+        };
+        assert!(entry.due(now));
+        entry.failed(&anyhow::Error::new(crate::supabase::Unavailable("offline".to_string())), now);
+        assert_eq!(entry.attempts, 1);
+        assert!(!entry.due(now));
+        assert!(entry.due(now + Duration::seconds(31)));
+
+        let stored: PendingEntry = serde_json::from_value(serde_json::to_value(&entry).unwrap()).unwrap();
+        assert_eq!(stored, entry);
+        assert_eq!(serde_json::to_value(&entry.write).unwrap()["kind"], "add_log");
+
+        assert!(is_retryable(&anyhow::Error::new(crate::supabase::Unavailable("503".to_string()))));
+        assert!(!is_retryable(&anyhow::anyhow!("400 Bad Request")));
+    }
+}
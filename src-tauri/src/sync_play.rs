@@ -0,0 +1,112 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::services::jellyfin;
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// SyncPlay Jellyfin (visionnage groupé, y compris à distance)
+// =============================================================================
+//
+// SyncPlay est intégré à Jellyfin: il suffit d'autoriser les utilisateurs
+// (policy `SyncPlayAccess`). Les groupes passent par le WebSocket de Jellyfin
+// (port 8096): aucun port supplémentaire, mais le co-visionnage hors du foyer
+// nécessite un accès distant qui laisse passer les WebSockets.
+
+/// Accès accordé quand SyncPlay est activé: créer et rejoindre des groupes
+const ACCESS_ENABLED: &str = "CreateAndJoinGroups";
+const ACCESS_DISABLED: &str = "None";
+
+/// Résultat du réglage, avec les implications réseau pour le foyer
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct SyncPlaySettings {
+    pub enabled: bool,
+    pub users_updated: Vec<String>,
+    pub network_notes: Vec<String>,
+}
+
+/// Policy utilisateur avec SyncPlay autorisé ou non (autres champs conservés)
+pub fn sync_play_policy(policy: &Value, enabled: bool) -> Value {
+    let mut policy = policy.clone();
+    if let Some(fields) = policy.as_object_mut() {
+        let access = if enabled { ACCESS_ENABLED } else { ACCESS_DISABLED };
+        fields.insert("SyncPlayAccess".to_string(), Value::from(access));
+    }
+    policy
+}
+
+/// Implications réseau selon le docker-compose.yml du Pi
+pub fn network_notes(compose: &str, enabled: bool) -> Vec<String> {
+    if !enabled {
+        return vec!["SyncPlay désactivé: aucun impact réseau".to_string()];
+    }
+    let mut notes = vec![
+        "SyncPlay utilise le WebSocket de Jellyfin (port 8096/TCP): aucun port supplémentaire à ouvrir".to_string(),
+        "Sur le réseau local, tous les clients Jellyfin récents peuvent créer ou rejoindre un groupe".to_string(),
+    ];
+    if compose.contains("\n  cloudflared:") {
+        notes.push("Accès distant via le tunnel Cloudflare: les WebSockets passent, rien à configurer sur la box".to_string());
+    } else {
+        notes.push(
+            "Aucun accès distant configuré: pour co-regarder hors du foyer, activer le tunnel Cloudflare \
+             (recommandé) ou rediriger le port 8096/TCP de la box vers le Pi"
+                .to_string(),
+        );
+    }
+    notes
+}
+
+/// Applique le réglage à tous les utilisateurs Jellyfin; renvoie les noms mis à jour
+pub async fn apply_to_users(target: &SshTarget<'_>, token: &str, enabled: bool) -> Result<Vec<String>> {
+    let mut updated = Vec::new();
+    for user in jellyfin::list_users(target, token).await? {
+        let (Some(id), Some(name)) = (user["Id"].as_str(), user["Name"].as_str()) else {
+            continue;
+        };
+        if user["Policy"]["SyncPlayAccess"].as_str() == Some(if enabled { ACCESS_ENABLED } else { ACCESS_DISABLED }) {
+            continue;
+        }
+        jellyfin::update_user_policy(target, token, id, &sync_play_policy(&user["Policy"], enabled)).await?;
+        updated.push(name.to_string());
+    }
+    tracing::info!("✅ SyncPlay {} ({} users updated)", if enabled { "enabled" } else { "disabled" }, updated.len());
+    Ok(updated)
+}
+
+/// Active ou désactive SyncPlay pour le foyer (réglage post-installation)
+pub async fn set_sync_play(
+    target: SshTarget<'_>,
+    jellyfin_username: &str,
+    jellyfin_password: &str,
+    enabled: bool,
+) -> Result<SyncPlaySettings> {
+    let session = jellyfin::authenticate(&target, jellyfin_username, jellyfin_password).await?;
+    let users_updated = apply_to_users(&target, &session.access_token, enabled).await?;
+    let compose = target.exec("cat ~/media-stack/docker-compose.yml").await.unwrap_or_default();
+
+    Ok(SyncPlaySettings { enabled, users_updated, network_notes: network_notes(&compose, enabled) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sync_play_policy_and_notes() {
+        let policy = json!({ "IsAdministrator": false, "SyncPlayAccess": "None", "EnableRemoteAccess": true });
+        let enabled = sync_play_policy(&policy, true);
+        assert_eq!(enabled["SyncPlayAccess"], "CreateAndJoinGroups");
+        assert_eq!(enabled["EnableRemoteAccess"], true);
+        assert_eq!(sync_play_policy(&enabled, false)["SyncPlayAccess"], "None");
+
+        let with_tunnel = network_notes("services:\n  jellyfin:\n    image: x\n  cloudflared:\n    image: y\n", true);
+        assert!(with_tunnel.iter().any(|n| n.contains("tunnel Cloudflare: les WebSockets passent")));
+        let lan_only = network_notes("services:\n  jellyfin:\n    image: x\n", true);
+        assert!(lan_only.iter().any(|n| n.contains("8096/TCP de la box")));
+        assert_eq!(network_notes("", false).len(), 1);
+    }
+}
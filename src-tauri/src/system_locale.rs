@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Langue du système du Pi (LANG, LC_ALL, police console)
+// =============================================================================
+//
+// Le flash ne règle que le clavier et le fuseau horaire (custom.toml). La locale
+// est appliquée pendant l'installation via `raspi-config nonint`, pour que les
+// logs et les sessions shell du Pi soient dans la langue de l'utilisateur.
+
+/// Pays par défaut d'une langue ISO 639-1 (code ISO 3166-1, "US" si inconnue)
+pub fn country_for_language(language: &str) -> &'static str {
+    match language {
+        "fr" => "FR",
+        "es" => "ES",
+        "de" => "DE",
+        "it" => "IT",
+        "pt" => "BR",
+        "nl" => "NL",
+        "sv" => "SE",
+        "da" => "DK",
+        "nb" | "no" => "NO",
+        "fi" => "FI",
+        "pl" => "PL",
+        "cs" => "CZ",
+        "el" => "GR",
+        "ru" => "RU",
+        "uk" => "UA",
+        "ja" => "JP",
+        "ko" => "KR",
+        "zh" => "CN",
+        _ => "US",
+    }
+}
+
+/// Locale système choisie dans le wizard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemLocale {
+    /// Ex: "fr_FR.UTF-8"
+    pub lang: String,
+    /// Forcer toutes les catégories (LC_ALL), sinon seulement LANG
+    #[serde(default)]
+    pub lc_all: Option<String>,
+    /// FONTFACE de /etc/default/console-setup (ex: "Terminus"), inchangée si absente
+    #[serde(default)]
+    pub console_font: Option<String>,
+}
+
+impl SystemLocale {
+    /// Locale par défaut d'une langue ISO 639-1
+    pub fn for_language(language: &str) -> Self {
+        let lang = match language {
+            "en" => "en_US.UTF-8",
+            "es" => "es_ES.UTF-8",
+            "de" => "de_DE.UTF-8",
+            "it" => "it_IT.UTF-8",
+            "pt" => "pt_BR.UTF-8",
+            _ => "fr_FR.UTF-8",
+        };
+        Self { lang: lang.to_string(), lc_all: None, console_font: None }
+    }
+
+    /// Les valeurs finissent dans des commandes shell: format strict
+    pub fn validate(&self) -> Result<()> {
+        let locale_ok = |l: &str| {
+            let (name, charset) = l.split_once('.').unwrap_or((l, ""));
+            let (language, country) = name.split_once('_').unwrap_or((name, ""));
+            (2..=3).contains(&language.len())
+                && language.chars().all(|c| c.is_ascii_lowercase())
+                && country.len() == 2
+                && country.chars().all(|c| c.is_ascii_uppercase())
+                && charset == "UTF-8"
+        };
+        if !locale_ok(&self.lang) {
+            return Err(anyhow!("Locale invalide: {}", self.lang));
+        }
+        if let Some(lc_all) = self.lc_all.as_deref().filter(|l| !locale_ok(l)) {
+            return Err(anyhow!("Locale LC_ALL invalide: {}", lc_all));
+        }
+        if let Some(font) = self.console_font.as_deref().filter(|f| !f.chars().all(|c| c.is_ascii_alphanumeric())) {
+            return Err(anyhow!("Police console invalide: {}", font));
+        }
+        Ok(())
+    }
+
+    /// Commandes à exécuter, `sudo` étant le préfixe d'élévation ("sudo" ou "echo 'pw' | sudo -S")
+    pub fn commands(&self, sudo: &str) -> Vec<String> {
+        let mut commands = vec![format!("{} raspi-config nonint do_change_locale {}", sudo, self.lang)];
+        if let Some(lc_all) = &self.lc_all {
+            commands.push(format!("{} update-locale LANG={} LC_ALL={}", sudo, self.lang, lc_all));
+        }
+        if let Some(font) = &self.console_font {
+            commands.push(format!(
+                "{} sed -i 's/^FONTFACE=.*/FONTFACE=\"{}\"/' /etc/default/console-setup",
+                sudo, font
+            ));
+            commands.push(format!("{} setupcon --force || true", sudo));
+        }
+        commands
+    }
+}
+
+/// Applique la locale (effective aux prochaines sessions / au prochain redémarrage)
+pub async fn apply(target: &SshTarget<'_>, locale: &SystemLocale, sudo_password: Option<&str>) -> Result<()> {
+    locale.validate()?;
+    let sudo = crate::ssh::sudo_prefix(sudo_password);
+    target.exec(&locale.commands(&sudo).join(" && ")).await?;
+    tracing::info!("✅ System locale set to {}", locale.lang);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_commands() {
+        assert_eq!(country_for_language("fr"), "FR");
+        assert_eq!(country_for_language("ja"), "JP");
+        assert_eq!(country_for_language("en"), "US");
+        assert_eq!(country_for_language("eo"), "US");
+
+        let locale = SystemLocale::for_language("en");
+        assert!(locale.validate().is_ok());
+        assert_eq!(locale.commands("sudo"), vec!["sudo raspi-config nonint do_change_locale en_US.UTF-8"]);
+
+        let full = SystemLocale {
+            lang: "de_DE.UTF-8".to_string(),
+            lc_all: Some("de_DE.UTF-8".to_string()),
+            console_font: Some("Terminus".to_string()),
+        };
+        let commands = full.commands("sudo");
+        assert_eq!(commands.len(), 4);
+        assert_eq!(commands[1], "sudo update-locale LANG=de_DE.UTF-8 LC_ALL=de_DE.UTF-8");
+
+        let injected = SystemLocale { lang: "fr_FR.UTF-8; rm -rf /".to_string(), ..SystemLocale::for_language("fr") };
+        assert!(injected.validate().is_err());
+    }
+}
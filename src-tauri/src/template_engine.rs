@@ -18,6 +18,16 @@ impl TemplateVars {
         self.vars.insert(key.to_string(), value.to_string());
     }
 
+    /// Valeur d'une variable
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.vars.get(key).map(String::as_str)
+    }
+
+    /// Noms des variables définies
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.vars.keys().map(String::as_str)
+    }
+
     /// Remplace toutes les variables {{VAR}} dans une chaîne
     pub fn replace(&self, template: &str) -> String {
         let re = Regex::new(r"\{\{([A-Z_0-9]+)\}\}").unwrap();
@@ -27,7 +37,7 @@ impl TemplateVars {
             match self.vars.get(var_name) {
                 Some(value) => value.clone(),
                 None => {
-                    println!("[Template] Warning: Variable {{{{{}}}}} not found, replacing with empty string", var_name);
+                    tracing::warn!("Variable {{{{{}}}}} not found, replacing with empty string", var_name);
                     String::new()
                 }
             }
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use russh::ChannelMsg;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Window;
+use tokio::sync::mpsc;
+
+use crate::ssh;
+
+// =============================================================================
+// Terminal interactif (PTY) vers le Pi
+// =============================================================================
+//
+// Événements:
+//   frontend -> backend : "terminal-input"  { id, data }
+//                         "terminal-resize" { id, cols, rows }
+//   backend -> frontend : "terminal-output" { id, data }
+//                         "terminal-exit"   { id, exit_status }
+
+// Shells ouverts, indexés par ID
+static SHELLS: Lazy<Mutex<HashMap<String, mpsc::UnboundedSender<ShellInput>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+enum ShellInput {
+    Data(Vec<u8>),
+    Resize { cols: u32, rows: u32 },
+    Close,
+}
+
+#[derive(Debug, Deserialize)]
+struct InputPayload {
+    id: String,
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResizePayload {
+    id: String,
+    cols: u32,
+    rows: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OutputPayload {
+    id: String,
+    data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ExitPayload {
+    id: String,
+    exit_status: Option<u32>,
+}
+
+/// Ouvre un shell PTY sur le Pi et le relie au frontend via des événements
+pub async fn open_shell(
+    window: Window,
+    host: &str,
+    username: &str,
+    password: Option<&str>,
+    private_key: Option<&str>,
+    cols: u32,
+    rows: u32,
+) -> Result<String> {
+    let session = ssh::open_authenticated_session(host, username, password, private_key).await?;
+
+    let mut channel = session.channel_open_session().await?;
+    channel
+        .request_pty(false, "xterm-256color", cols, rows, 0, 0, &[])
+        .await?;
+    channel.request_shell(true).await?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let (tx, mut rx) = mpsc::unbounded_channel::<ShellInput>();
+
+    SHELLS
+        .lock()
+        .map_err(|_| anyhow!("Shell registry poisoned"))?
+        .insert(id.clone(), tx.clone());
+
+    // Entrées clavier et redimensionnement envoyés par le frontend
+    let input_tx = tx.clone();
+    let input_id = id.clone();
+    let input_listener = window.listen("terminal-input", move |event| {
+        if let Some(payload) = event.payload().and_then(|p| serde_json::from_str::<InputPayload>(p).ok()) {
+            if payload.id == input_id {
+                let _ = input_tx.send(ShellInput::Data(payload.data.into_bytes()));
+            }
+        }
+    });
+
+    let resize_id = id.clone();
+    let resize_listener = window.listen("terminal-resize", move |event| {
+        if let Some(payload) = event.payload().and_then(|p| serde_json::from_str::<ResizePayload>(p).ok()) {
+            if payload.id == resize_id {
+                let _ = tx.send(ShellInput::Resize { cols: payload.cols, rows: payload.rows });
+            }
+        }
+    });
+
+    tracing::info!("✅ Shell {} opened on {}@{}", id, username, host);
+
+    let shell_id = id.clone();
+    tokio::spawn(async move {
+        // La session doit rester vivante tant que le shell est ouvert
+        let _session = session;
+        let mut exit_status = None;
+
+        loop {
+            tokio::select! {
+                msg = channel.wait() => match msg {
+                    Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. }) => {
+                        let _ = window.emit("terminal-output", OutputPayload {
+                            id: shell_id.clone(),
+                            data: String::from_utf8_lossy(&data).to_string(),
+                        });
+                    }
+                    Some(ChannelMsg::ExitStatus { exit_status: status }) => {
+                        exit_status = Some(status);
+                    }
+                    Some(ChannelMsg::Eof) | Some(ChannelMsg::Close) | None => break,
+                    _ => {}
+                },
+                input = rx.recv() => match input {
+                    Some(ShellInput::Data(bytes)) => {
+                        if let Err(e) = channel.data(&bytes[..]).await {
+                            tracing::warn!("⚠️ Write failed on {}: {}", shell_id, e);
+                            break;
+                        }
+                    }
+                    Some(ShellInput::Resize { cols, rows }) => {
+                        let _ = channel.window_change(cols, rows, 0, 0).await;
+                    }
+                    Some(ShellInput::Close) | None => {
+                        let _ = channel.eof().await;
+                        let _ = channel.close().await;
+                        break;
+                    }
+                },
+            }
+        }
+
+        window.unlisten(input_listener);
+        window.unlisten(resize_listener);
+        if let Ok(mut shells) = SHELLS.lock() {
+            shells.remove(&shell_id);
+        }
+
+        tracing::info!("Shell {} closed (exit status: {:?})", shell_id, exit_status);
+        let _ = window.emit("terminal-exit", ExitPayload { id: shell_id, exit_status });
+    });
+
+    Ok(id)
+}
+
+/// Ferme un shell ouvert
+pub fn close_shell(id: &str) -> Result<()> {
+    let shells = SHELLS.lock().map_err(|_| anyhow!("Shell registry poisoned"))?;
+    let tx = shells.get(id).ok_or_else(|| anyhow!("Shell inconnu: {}", id))?;
+    let _ = tx.send(ShellInput::Close);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_shell() {
+        let id = uuid::Uuid::new_v4().to_string();
+        let (tx, mut rx) = mpsc::unbounded_channel::<ShellInput>();
+        SHELLS.lock().unwrap().insert(id.clone(), tx);
+
+        close_shell(&id).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(ShellInput::Close)));
+        SHELLS.lock().unwrap().remove(&id);
+
+        let err = close_shell(&id).unwrap_err();
+        assert!(err.to_string().contains("Shell inconnu"));
+    }
+
+    #[test]
+    fn test_event_payloads() {
+        let input: InputPayload = serde_json::from_str(r#"{"id":"a","data":"ls\r"}"#).unwrap();
+        assert_eq!((input.id.as_str(), input.data.as_str()), ("a", "ls\r"));
+
+        let resize: ResizePayload = serde_json::from_str(r#"{"id":"a","cols":120,"rows":40}"#).unwrap();
+        assert_eq!((resize.cols, resize.rows), (120, 40));
+
+        let exit = serde_json::to_value(ExitPayload { id: "a".to_string(), exit_status: Some(0) }).unwrap();
+        assert_eq!(exit, serde_json::json!({ "id": "a", "exit_status": 0 }));
+    }
+}
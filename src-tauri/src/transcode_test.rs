@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::ssh::{self, SshTarget};
+
+// =============================================================================
+// Auto-test de transcodage 4K -> 1080p (jellyfin-ffmpeg dans le conteneur)
+// =============================================================================
+//
+// Le clip d'essai (mire 2160p Motion-JPEG, 10 images, ~1,4 Mo) est livré avec
+// l'application et envoyé sur le Pi, puis lu en boucle pour 10 s de transcodage:
+// rien n'est rendu en 4K sur le Pi. Le transcodage utilise l'encodeur correspondant
+// à l'accélération configurée dans Jellyfin; les i/s obtenus disent si la lecture
+// 4K tiendra.
+
+const FFMPEG: &str = "/usr/lib/jellyfin-ffmpeg/ffmpeg";
+const SAMPLE: &[u8] = include_bytes!("../resources/transcode-sample-2160p.mjpeg");
+/// Dossier du clip sur le Pi (monté sur /config dans le conteneur Jellyfin)
+const SAMPLE_HOST_DIR: &str = "~/media-stack/jellyfin/transcode-test";
+const SAMPLE_CONTAINER_PATH: &str = "/config/transcode-test/sample-2160p.mjpeg";
+/// Cadence du clip: en dessous, le transcodage n'est pas temps réel
+const SAMPLE_FPS: f64 = 30.0;
+/// Lectures supplémentaires du clip (10 images x 30 = 10 s à 30 i/s)
+const SAMPLE_LOOPS: u32 = 29;
+const TEST_TIMEOUT_SECS: u64 = 600;
+
+/// Résultat de l'auto-test
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct TranscodeTestResult {
+    /// Accélération configurée dans Jellyfin ("none", "v4l2", ...)
+    pub acceleration: String,
+    pub encoder: String,
+    pub fps: f64,
+    /// Vitesse relative à la lecture (1.0 = temps réel)
+    pub speed: f64,
+    pub realtime: bool,
+    pub verdict: String,
+}
+
+/// Encodeur H.264 pour l'accélération Jellyfin
+pub fn encoder_for(acceleration: &str) -> &'static str {
+    match acceleration {
+        "v4l2" => "h264_v4l2m2m",
+        "vaapi" => "h264_vaapi",
+        "qsv" => "h264_qsv",
+        "nvenc" => "h264_nvenc",
+        _ => "libx264",
+    }
+}
+
+/// Accélération lue dans encoding.xml ("none" si absente)
+pub fn parse_acceleration(encoding_xml: &str) -> String {
+    encoding_xml
+        .split("<HardwareAccelerationType>")
+        .nth(1)
+        .and_then(|rest| rest.split('<').next())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .unwrap_or("none")
+        .to_string()
+}
+
+fn last_value(output: &str, key: &str) -> Option<f64> {
+    output
+        .rsplit(key)
+        .next()
+        .filter(|_| output.contains(key))?
+        .trim_start()
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Dernières valeurs `fps=` et `speed=` de la progression ffmpeg
+pub fn parse_ffmpeg_progress(output: &str) -> Option<(f64, f64)> {
+    Some((last_value(output, "fps=")?, last_value(output, "speed=")?))
+}
+
+pub fn verdict(fps: f64) -> String {
+    if fps >= SAMPLE_FPS {
+        "Transcodage 4K → 1080p en temps réel: lecture fluide".to_string()
+    } else if fps >= SAMPLE_FPS * 0.8 {
+        "Transcodage 4K → 1080p limite: saccades possibles, préférer la lecture directe".to_string()
+    } else {
+        "Transcodage 4K → 1080p trop lent: utiliser des versions 1080p ou la lecture directe".to_string()
+    }
+}
+
+/// Envoie le clip sur le Pi (sauf s'il y est déjà, même taille)
+async fn push_sample(target: &SshTarget<'_>) -> Result<()> {
+    let host_path = format!("{}/sample-2160p.mjpeg", SAMPLE_HOST_DIR);
+    let size = target
+        .exec(&format!("mkdir -p {} && stat -c %s {} 2>/dev/null || true", SAMPLE_HOST_DIR, host_path))
+        .await?;
+    if size.trim() == SAMPLE.len().to_string() {
+        return Ok(());
+    }
+    target.upload(&host_path, SAMPLE, &ssh::default_options()).await
+}
+
+/// Envoie le clip si besoin puis mesure un transcodage 2160p -> 1080p
+pub async fn run(target: &SshTarget<'_>) -> Result<TranscodeTestResult> {
+    let encoding = target
+        .exec("docker exec jellyfin sh -c 'cat /config/encoding.xml /config/config/encoding.xml 2>/dev/null' || true")
+        .await?;
+    let acceleration = parse_acceleration(&encoding);
+    let encoder = encoder_for(&acceleration);
+    tracing::info!("Testing 2160p -> 1080p with {} ({})...", encoder, acceleration);
+
+    push_sample(target).await?;
+
+    let script = format!(
+        "docker exec jellyfin {ffmpeg} -hide_banner -stream_loop {loops} -f mjpeg -framerate {fps} -i {sample} \
+         -vf scale=-2:1080 -c:v {encoder} -b:v 8M -f null - 2>&1",
+        ffmpeg = FFMPEG,
+        loops = SAMPLE_LOOPS,
+        fps = SAMPLE_FPS,
+        sample = SAMPLE_CONTAINER_PATH,
+        encoder = encoder
+    );
+    let options = ssh::SshOptions { command_timeout_secs: TEST_TIMEOUT_SECS, ..ssh::default_options() };
+    let output = target.exec_with_options(&script, &options).await?;
+
+    let (fps, speed) = parse_ffmpeg_progress(&output)
+        .ok_or_else(|| anyhow!("Transcodage de test échoué: {}", output.lines().last().unwrap_or_default()))?;
+    let result = TranscodeTestResult {
+        acceleration,
+        encoder: encoder.to_string(),
+        fps,
+        speed,
+        realtime: fps >= SAMPLE_FPS,
+        verdict: verdict(fps),
+    };
+
+    tracing::info!("✅ {:.1} fps (x{:.2}): {}", result.fps, result.speed, result.verdict);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transcode_output() {
+        let output = "Stream mapping:\n  Stream #0:0 -> #0:0 (h264 (native) -> h264 (libx264))\n\
+            frame=   42 fps= 21 q=28.0 size=N/A time=00:00:01.40 bitrate=N/A speed=0.70x\r\
+            frame=  300 fps= 24.5 q=-1.0 Lsize=N/A time=00:00:10.00 bitrate=N/A speed=0.817x\n";
+        assert_eq!(parse_ffmpeg_progress(output), Some((24.5, 0.817)));
+        assert_eq!(parse_ffmpeg_progress("Conversion failed!"), None);
+
+        assert_eq!(parse_acceleration("<EncodingOptions>\n  <HardwareAccelerationType>v4l2</HardwareAccelerationType>"), "v4l2");
+        assert_eq!(parse_acceleration(""), "none");
+        assert_eq!(encoder_for("none"), "libx264");
+        assert!(verdict(24.5).contains("limite"));
+        // Clip livré: flux Motion-JPEG (images JPEG concaténées)
+        assert!(SAMPLE.starts_with(&[0xFF, 0xD8, 0xFF]) && SAMPLE.ends_with(&[0xFF, 0xD9]));
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::ssh;
+
+// =============================================================================
+// Tunnels SSH (port forwarding local -> Pi)
+// =============================================================================
+
+/// Attente après un accept en échec (EMFILE...), doublée à chaque échec consécutif
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(100);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+// Tunnels actifs, indexés par ID
+static TUNNELS: Lazy<Mutex<HashMap<String, ActiveTunnel>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Tunnel ouvert, renvoyé au frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunnelInfo {
+    pub id: String,
+    pub host: String,
+    pub local_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+}
+
+struct ActiveTunnel {
+    info: TunnelInfo,
+    accept_task: JoinHandle<()>,
+}
+
+/// Ouvre un tunnel localhost:local_port -> remote_host:remote_port (vu depuis le Pi)
+/// local_port = 0 choisit un port libre
+pub async fn open_tunnel(
+    host: &str,
+    username: &str,
+    password: Option<&str>,
+    private_key: Option<&str>,
+    local_port: u16,
+    remote_host: &str,
+    remote_port: u16,
+) -> Result<TunnelInfo> {
+    let session = Arc::new(
+        ssh::open_authenticated_session(host, username, password, private_key).await?,
+    );
+
+    let listener = TcpListener::bind(("127.0.0.1", local_port))
+        .await
+        .map_err(|e| anyhow!("Port local {} indisponible: {}", local_port, e))?;
+    let local_port = listener.local_addr()?.port();
+
+    let info = TunnelInfo {
+        id: uuid::Uuid::new_v4().to_string(),
+        host: host.to_string(),
+        local_port,
+        remote_host: remote_host.to_string(),
+        remote_port,
+    };
+
+    let target_host = remote_host.to_string();
+    let tunnel_id = info.id.clone();
+    let accept_task = tokio::spawn(async move {
+        let mut backoff = ACCEPT_BACKOFF_MIN;
+        loop {
+            let (mut socket, peer) = match listener.accept().await {
+                Ok(conn) => {
+                    backoff = ACCEPT_BACKOFF_MIN;
+                    conn
+                }
+                Err(e) => {
+                    // Sans pause, une erreur persistante ferait tourner la boucle à vide
+                    tracing::warn!("⚠️ Accept failed on {}: {} (retry in {:?})", tunnel_id, e, backoff);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                    continue;
+                }
+            };
+
+            let session = session.clone();
+            let target_host = target_host.clone();
+            tokio::spawn(async move {
+                let channel = match session
+                    .channel_open_direct_tcpip(
+                        target_host.as_str(),
+                        remote_port as u32,
+                        "127.0.0.1",
+                        peer.port() as u32,
+                    )
+                    .await
+                {
+                    Ok(ch) => ch,
+                    Err(e) => {
+                        tracing::error!("❌ direct-tcpip to {}:{} failed: {}", target_host, remote_port, e);
+                        return;
+                    }
+                };
+
+                let mut stream = channel.into_stream();
+                if let Err(e) = tokio::io::copy_bidirectional(&mut socket, &mut stream).await {
+                    tracing::info!("Connection closed: {}", e);
+                }
+            });
+        }
+    });
+
+    tracing::info!(
+        "✅ localhost:{} -> {}:{} via {}",
+        info.local_port, info.remote_host, info.remote_port, host
+    );
+
+    TUNNELS
+        .lock()
+        .map_err(|_| anyhow!("Tunnel registry poisoned"))?
+        .insert(info.id.clone(), ActiveTunnel { info: info.clone(), accept_task });
+
+    Ok(info)
+}
+
+/// Liste les tunnels actifs
+pub fn list_tunnels() -> Vec<TunnelInfo> {
+    TUNNELS
+        .lock()
+        .map(|tunnels| tunnels.values().map(|t| t.info.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Ferme un tunnel (les connexions en cours se terminent d'elles-mêmes)
+pub fn stop_tunnel(id: &str) -> Result<()> {
+    let tunnel = TUNNELS
+        .lock()
+        .map_err(|_| anyhow!("Tunnel registry poisoned"))?
+        .remove(id)
+        .ok_or_else(|| anyhow!("Tunnel inconnu: {}", id))?;
+
+    tunnel.accept_task.abort();
+    tracing::info!("Closed localhost:{}", tunnel.info.local_port);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tunnel_registry() {
+        let info = TunnelInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            host: "jellypi.local".to_string(),
+            local_port: 8096,
+            remote_host: "127.0.0.1".to_string(),
+            remote_port: 8096,
+        };
+        let accept_task = tokio::spawn(std::future::pending::<()>());
+        let abort_handle = accept_task.abort_handle();
+        TUNNELS
+            .lock()
+            .unwrap()
+            .insert(info.id.clone(), ActiveTunnel { info: info.clone(), accept_task });
+
+        assert!(list_tunnels().iter().any(|t| t.id == info.id && t.local_port == 8096));
+
+        stop_tunnel(&info.id).unwrap();
+        for _ in 0..100 {
+            if abort_handle.is_finished() {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert!(abort_handle.is_finished());
+        assert!(!list_tunnels().iter().any(|t| t.id == info.id));
+
+        let err = stop_tunnel(&info.id).unwrap_err();
+        assert!(err.to_string().contains("Tunnel inconnu"));
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Désinstallation du stack (retour à un Pi "propre" sans reflasher)
+// =============================================================================
+//
+// docker compose down, crons et agents JellySetup, ~/media-stack, puis en option
+// les volumes Docker et les données /mnt/decypharr. La config Supabase est
+// marquée "decommissioned" et l'état local d'installation est oublié.
+
+const MEDIA_DATA_DIR: &str = "/mnt/decypharr";
+
+/// Choix de l'utilisateur (tout est conservé par défaut hors stack)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct UninstallOptions {
+    /// Supprimer aussi les volumes Docker nommés (supabazarr_data, ...)
+    #[serde(default)]
+    pub remove_volumes: bool,
+    /// Démonter et supprimer /mnt/decypharr (cache et liens debrid)
+    #[serde(default)]
+    pub remove_media_data: bool,
+}
+
+/// Étapes effectuées sur le Pi et côté Supabase
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct UninstallReport {
+    pub steps: Vec<String>,
+    pub decommissioned: bool,
+}
+
+/// Script de désinstallation (`sudo`: "sudo" ou "echo 'pw' | sudo -S"); une ligne STEP= par étape
+pub fn uninstall_script(options: &UninstallOptions, sudo: &str) -> String {
+    let down_flags = if options.remove_volumes { "--remove-orphans -v" } else { "--remove-orphans" };
+    let mut lines = vec![
+        format!(
+            "if [ -f ~/media-stack/docker-compose.yml ]; then (cd ~/media-stack && docker compose down {}) && echo STEP=compose_down; fi",
+            down_flags
+        ),
+        "(crontab -l 2>/dev/null | grep -v '# jellysetup-') | crontab - && echo STEP=cron_removed".to_string(),
+        "rm -rf ~/jellysetup-agent && echo STEP=agents_removed".to_string(),
+        format!("{} rm -rf ~/media-stack && echo STEP=media_stack_removed", sudo),
+    ];
+    if options.remove_media_data {
+        lines.push(format!("{} umount -l {} 2>/dev/null", sudo, MEDIA_DATA_DIR));
+        lines.push(format!("{} rm -rf {} && echo STEP=media_data_removed", sudo, MEDIA_DATA_DIR));
+    }
+    lines.join("\n")
+}
+
+fn parse_steps(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("STEP="))
+        .map(String::from)
+        .collect()
+}
+
+/// Désinstalle le stack du Pi et décommissionne sa config Supabase
+pub async fn uninstall_stack(
+    target: &SshTarget<'_>,
+    host: &str,
+    pi_name: &str,
+    options: &UninstallOptions,
+    sudo: &str,
+) -> Result<UninstallReport> {
+    tracing::info!("Removing stack from {} ({:?})...", pi_name, options);
+    let output = target.exec(&uninstall_script(options, sudo)).await?;
+    let mut report = UninstallReport { steps: parse_steps(&output), decommissioned: false };
+
+    match crate::supabase::decommission_installation(pi_name).await {
+        Ok(()) => report.decommissioned = true,
+        Err(e) => tracing::warn!("⚠️ Could not mark Supabase config as decommissioned: {}", e),
+    }
+    crate::install_state::InstallState::forget(host);
+
+    tracing::info!("✅ {} cleaned ({})", pi_name, report.steps.join(", "));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uninstall_script() {
+        let minimal = uninstall_script(&UninstallOptions::default(), "sudo");
+        assert!(minimal.contains("docker compose down --remove-orphans)"));
+        assert!(minimal.contains("sudo rm -rf ~/media-stack"));
+        assert!(!minimal.contains(MEDIA_DATA_DIR));
+
+        let full = uninstall_script(&UninstallOptions { remove_volumes: true, remove_media_data: true }, "sudo");
+        assert!(full.contains("docker compose down --remove-orphans -v"));
+        assert!(full.contains("sudo umount -l /mnt/decypharr"));
+
+        assert_eq!(parse_steps("STEP=compose_down\nnoise\nSTEP=cron_removed\n"), vec!["compose_down", "cron_removed"]);
+    }
+}
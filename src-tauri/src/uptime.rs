@@ -0,0 +1,121 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::ssh::{shell_quote, SshTarget};
+
+// =============================================================================
+// Surveillance de disponibilité externe (healthchecks.io, push Uptime Kuma, ...)
+// =============================================================================
+//
+// Principe "dead man's switch": un cron sur le Pi vérifie l'URL publique (tunnel
+// Cloudflare/Tailscale) ou Jellyfin en local, et ne pingue le moniteur externe que
+// si la vérification réussit. Sans ping, le moniteur alerte l'utilisateur.
+
+const CRON_MARKER: &str = "# jellysetup-uptime";
+const LOCAL_CHECK_URL: &str = "http://localhost:8096/health";
+
+/// Moniteur externe choisi pendant l'installation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UptimeMonitor {
+    /// URL de ping (ex: https://hc-ping.com/<uuid> ou push URL Uptime Kuma)
+    pub ping_url: String,
+    /// URL publique à vérifier (tunnel), sinon Jellyfin en local
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// Fréquence de vérification en minutes (5 par défaut)
+    #[serde(default)]
+    pub interval_minutes: Option<u32>,
+}
+
+impl UptimeMonitor {
+    fn check_url(&self) -> &str {
+        self.public_url
+            .as_deref()
+            .filter(|u| !u.trim().is_empty())
+            .unwrap_or(LOCAL_CHECK_URL)
+    }
+
+    /// URLs http(s) absolues avec un hôte, sans identifiants ni retour à la ligne
+    fn validate(&self) -> Result<()> {
+        for url in [self.ping_url.as_str(), self.check_url()] {
+            let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("URL de surveillance invalide: {} ({})", url, e))?;
+            let valid = matches!(parsed.scheme(), "http" | "https")
+                && parsed.host_str().is_some()
+                && parsed.username().is_empty()
+                && parsed.password().is_none()
+                && !url.contains(['\n', '\r']);
+            if !valid {
+                return Err(anyhow!("URL de surveillance invalide: {}", url));
+            }
+        }
+        Ok(())
+    }
+
+    /// Ligne crontab: ping uniquement si le serveur répond
+    /// (URLs entre apostrophes, `%` échappés: cron les change en retours à la ligne)
+    pub fn cron_line(&self) -> String {
+        let interval = self.interval_minutes.unwrap_or(5).clamp(1, 59);
+        let quote = |url: &str| shell_quote(url).replace('%', "\\%");
+        format!(
+            "*/{} * * * * curl -fsS -m 10 -o /dev/null {} && curl -fsS -m 10 --retry 3 -o /dev/null {} {}",
+            interval,
+            quote(self.check_url()),
+            quote(&self.ping_url),
+            CRON_MARKER
+        )
+    }
+}
+
+/// Installe (ou remplace) le cron de surveillance et envoie un premier ping
+pub async fn install_monitor(target: &SshTarget<'_>, monitor: &UptimeMonitor) -> Result<()> {
+    monitor.validate()?;
+
+    let cmd = format!(
+        "(crontab -l 2>/dev/null | grep -v '{marker}'; printf '%s\\n' {line}) | crontab - && curl -fsS -m 10 -o /dev/null {ping} && echo OK",
+        marker = CRON_MARKER,
+        line = shell_quote(&monitor.cron_line()),
+        ping = shell_quote(&monitor.ping_url)
+    );
+
+    let output = target.exec(&cmd).await?;
+    if !output.contains("OK") {
+        return Err(anyhow!("Ping initial du moniteur échoué: {}", output.trim()));
+    }
+
+    tracing::info!("✅ Monitoring {} every {} min", monitor.check_url(), monitor.interval_minutes.unwrap_or(5));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cron_line() {
+        let monitor = UptimeMonitor {
+            ping_url: "https://hc-ping.com/abc".to_string(),
+            public_url: Some("https://jellyfin.example.com/health".to_string()),
+            interval_minutes: None,
+        };
+        assert_eq!(
+            monitor.cron_line(),
+            "*/5 * * * * curl -fsS -m 10 -o /dev/null 'https://jellyfin.example.com/health' && curl -fsS -m 10 --retry 3 -o /dev/null 'https://hc-ping.com/abc' # jellysetup-uptime"
+        );
+
+        let quoted = UptimeMonitor {
+            ping_url: "https://kuma.example.com/api/push/x?msg=OK%20'$(id)'".to_string(),
+            public_url: None,
+            interval_minutes: Some(10),
+        };
+        assert!(quoted.validate().is_ok());
+        assert_eq!(
+            quoted.cron_line(),
+            "*/10 * * * * curl -fsS -m 10 -o /dev/null 'http://localhost:8096/health' && curl -fsS -m 10 --retry 3 -o /dev/null 'https://kuma.example.com/api/push/x?msg=OK\\%20'\\''$(id)'\\''' # jellysetup-uptime"
+        );
+
+        for bad in ["ftp://x/ping", "https://user:pw@x/ping", "hc-ping.com/abc", "https://x/a\nb"] {
+            let monitor = UptimeMonitor { ping_url: bad.to_string(), public_url: None, interval_minutes: None };
+            assert!(monitor.validate().is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use ts_rs::TS;
+
+use crate::secret::SecretString;
+
+// =============================================================================
+// Coffre local multi-Pi protégé par un mot de passe maître
+// =============================================================================
+//
+// Identifiants de chaque Pi géré (utilisateur, mot de passe système, clé SSH,
+// compte Jellyfin) dans data_dir/jellysetup/vault.json, chiffrés en bloc avec
+// crypto::encrypt_secret (Argon2id + AES-256-GCM). Le premier déverrouillage
+// crée le coffre avec le mot de passe saisi. Déverrouillé, le contenu et le mot
+// de passe maître restent en mémoire (SecretString, effacés au verrouillage)
+// pour réécrire le fichier à chaque modification. Sans activité pendant
+// AUTO_LOCK_SECS, le coffre se reverrouille tout seul.
+
+const AUTO_LOCK_SECS: u64 = 5 * 60;
+const LOCK_CHECK_INTERVAL_SECS: u64 = 30;
+const MIN_MASTER_PASSWORD_LENGTH: usize = 8;
+const VAULT_VERSION: u32 = 1;
+
+/// Identifiants d'un Pi
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct PiCredentials {
+    pub hostname: String,
+    pub username: String,
+    #[ts(type = "string | null")]
+    pub system_password: Option<SecretString>,
+    #[ts(type = "string | null")]
+    pub ssh_private_key: Option<SecretString>,
+    pub jellyfin_username: Option<String>,
+    #[ts(type = "string | null")]
+    pub jellyfin_password: Option<SecretString>,
+    /// RFC 3339 (renseigné à l'enregistrement)
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// État du coffre affiché par le frontend
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct VaultStatus {
+    /// Le fichier existe (sinon le prochain déverrouillage le crée)
+    pub exists: bool,
+    pub unlocked: bool,
+    /// Hostnames enregistrés (vide si verrouillé)
+    pub hostnames: Vec<String>,
+    pub auto_lock_secs: u32,
+}
+
+/// Fichier du coffre (le contenu n'est jamais écrit en clair)
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    encrypted: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct VaultContents {
+    pis: BTreeMap<String, PiCredentials>,
+}
+
+struct UnlockedVault {
+    master_password: SecretString,
+    contents: VaultContents,
+    last_activity: Instant,
+}
+
+static VAULT: Lazy<Mutex<Option<UnlockedVault>>> = Lazy::new(|| Mutex::new(None));
+
+fn vault_path() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| anyhow!("Cannot determine data directory"))?
+        .join("jellysetup")
+        .join("vault.json"))
+}
+
+fn seal(contents: &VaultContents, master_password: &str) -> Result<String> {
+    let json = SecretString::from(serde_json::to_string(contents)?);
+    let file = VaultFile {
+        version: VAULT_VERSION,
+        encrypted: crate::crypto::encrypt_secret(json.expose(), master_password)?,
+    };
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+fn open_sealed(sealed: &str, master_password: &str) -> Result<VaultContents> {
+    let file: VaultFile = serde_json::from_str(sealed)?;
+    if file.version != VAULT_VERSION {
+        return Err(anyhow!("Version de coffre non supportée: {}", file.version));
+    }
+    let json = crate::crypto::decrypt_secret(&file.encrypted, master_password)
+        .map_err(|_| anyhow!("Mot de passe maître incorrect"))?;
+    Ok(serde_json::from_str(json.expose())?)
+}
+
+fn save(vault: &UnlockedVault) -> Result<()> {
+    let path = vault_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Écriture atomique: un arrêt brutal ne laisse pas un coffre tronqué
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, seal(&vault.contents, vault.master_password.expose())?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Verrouille le coffre s'il est resté inactif trop longtemps
+fn lock_if_idle(vault: &mut Option<UnlockedVault>) {
+    if vault
+        .as_ref()
+        .is_some_and(|v| v.last_activity.elapsed() >= Duration::from_secs(AUTO_LOCK_SECS))
+    {
+        *vault = None;
+        tracing::info!("Auto-locked after inactivity");
+    }
+}
+
+/// Exécute `f` sur le coffre déverrouillé (et repousse le verrouillage automatique)
+fn with_unlocked<T>(f: impl FnOnce(&mut UnlockedVault) -> Result<T>) -> Result<T> {
+    let mut guard = VAULT.lock().unwrap();
+    lock_if_idle(&mut guard);
+    let vault = guard.as_mut().ok_or_else(|| anyhow!("Coffre verrouillé"))?;
+    vault.last_activity = Instant::now();
+    f(vault)
+}
+
+/// État du coffre (sans repousser le verrouillage automatique)
+pub fn status() -> VaultStatus {
+    let mut guard = VAULT.lock().unwrap();
+    lock_if_idle(&mut guard);
+    let hostnames: Option<Vec<String>> = guard.as_ref().map(|vault| vault.contents.pis.keys().cloned().collect());
+    VaultStatus {
+        exists: vault_path().map(|path| path.exists()).unwrap_or(false),
+        unlocked: hostnames.is_some(),
+        hostnames: hostnames.unwrap_or_default(),
+        auto_lock_secs: AUTO_LOCK_SECS as u32,
+    }
+}
+
+fn check_master_password(password: &SecretString) -> Result<()> {
+    if password.expose().chars().count() < MIN_MASTER_PASSWORD_LENGTH {
+        return Err(anyhow!(
+            "Mot de passe maître trop court ({} caractères minimum)",
+            MIN_MASTER_PASSWORD_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+/// Déverrouille le coffre (le crée s'il n'existe pas encore)
+pub fn unlock(master_password: SecretString) -> Result<VaultStatus> {
+    let path = vault_path()?;
+    let vault = if path.exists() {
+        let contents = open_sealed(&std::fs::read_to_string(&path)?, master_password.expose())?;
+        UnlockedVault { master_password, contents, last_activity: Instant::now() }
+    } else {
+        check_master_password(&master_password)?;
+        let vault = UnlockedVault { master_password, contents: VaultContents::default(), last_activity: Instant::now() };
+        save(&vault)?;
+        tracing::info!("✅ New vault created");
+        vault
+    };
+    tracing::info!("✅ Unlocked ({} Pi)", vault.contents.pis.len());
+    *VAULT.lock().unwrap() = Some(vault);
+    Ok(status())
+}
+
+/// Verrouille le coffre (contenu et mot de passe maître effacés de la mémoire)
+pub fn lock() {
+    if VAULT.lock().unwrap().take().is_some() {
+        tracing::info!("Locked");
+    }
+}
+
+/// Change le mot de passe maître (l'ancien est vérifié sur le fichier)
+pub fn change_password(current: SecretString, new: SecretString) -> Result<()> {
+    check_master_password(&new)?;
+    let sealed = std::fs::read_to_string(vault_path()?).map_err(|_| anyhow!("Aucun coffre à modifier"))?;
+    let contents = open_sealed(&sealed, current.expose())?;
+    let vault = UnlockedVault { master_password: new, contents, last_activity: Instant::now() };
+    save(&vault)?;
+    *VAULT.lock().unwrap() = Some(vault);
+    tracing::info!("✅ Master password changed");
+    Ok(())
+}
+
+pub fn get(hostname: &str) -> Result<Option<PiCredentials>> {
+    with_unlocked(|vault| Ok(vault.contents.pis.get(hostname).cloned()))
+}
+
+/// Enregistre (ou remplace) les identifiants d'un Pi
+pub fn put(mut credentials: PiCredentials) -> Result<()> {
+    if credentials.hostname.trim().is_empty() {
+        return Err(anyhow!("Hostname requis"));
+    }
+    credentials.updated_at = chrono::Utc::now().to_rfc3339();
+    with_unlocked(|vault| {
+        vault.contents.pis.insert(credentials.hostname.clone(), credentials);
+        save(vault)
+    })
+}
+
+pub fn remove(hostname: &str) -> Result<()> {
+    with_unlocked(|vault| {
+        vault.contents.pis.remove(hostname);
+        save(vault)
+    })
+}
+
+/// Reverrouille le coffre après AUTO_LOCK_SECS d'inactivité (au démarrage de l'application)
+pub fn start_auto_lock() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(Duration::from_secs(LOCK_CHECK_INTERVAL_SECS)).await;
+            lock_if_idle(&mut VAULT.lock().unwrap());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_seal_roundtrip() {
+        let mut contents = VaultContents::default();
+        contents.pis.insert(
+            "jellypi".to_string(),
+            PiCredentials {
+                hostname: "jellypi".to_string(),
+                username: "maison".to_string(),
+                system_password: Some("raspberry".into()),
+                ..Default::default()
+            },
+        );
+
+        let sealed = seal(&contents, "mot de passe maître").unwrap();
+        assert!(!sealed.contains("raspberry"));
+        assert!(!sealed.contains("jellypi"));
+
+        let opened = open_sealed(&sealed, "mot de passe maître").unwrap();
+        let pi = &opened.pis["jellypi"];
+        assert_eq!(pi.username, "maison");
+        assert_eq!(pi.system_password.as_ref().unwrap().expose(), "raspberry");
+        assert!(open_sealed(&sealed, "autre mot de passe").is_err());
+    }
+}
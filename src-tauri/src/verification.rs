@@ -0,0 +1,300 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::services::arr_client::{ArrClient, ArrClientError, JellyseerrClient};
+use crate::services::{self, decypharr, jellyfin};
+use crate::ssh::SshTarget;
+
+// =============================================================================
+// Vérification post-installation (checklist affichée par le wizard)
+// =============================================================================
+//
+// - chaque conteneur du compose tourne
+// - chaque API répond sur son port publié
+// - les indexers Prowlarr passent le test
+// - les bibliothèques Jellyfin sont visibles
+// - le montage Decypharr est présent
+// - Jellyseerr sait rechercher (accès TMDB)
+
+/// Recherche de référence pour tester Jellyseerr
+const SEARCH_PROBE: &str = "Matrix";
+
+/// Résultat d'une vérification
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct HealthCheckItem {
+    pub name: String,
+    /// "container", "api", "prowlarr", "jellyfin", "decypharr", "jellyseerr", "remote"
+    pub category: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Rapport complet retourné au frontend et sauvegardé dans Supabase
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/bindings/")]
+pub struct HealthReport {
+    pub pi_name: String,
+    pub checked_at: String,
+    pub checks: Vec<HealthCheckItem>,
+    pub all_passed: bool,
+}
+
+impl HealthReport {
+    pub fn new(pi_name: &str, checks: Vec<HealthCheckItem>) -> Self {
+        Self {
+            pi_name: pi_name.to_string(),
+            checked_at: chrono::Utc::now().to_rfc3339(),
+            all_passed: checks.iter().all(|c| c.passed),
+            checks,
+        }
+    }
+
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|c| !c.passed).count()
+    }
+}
+
+fn item(name: &str, category: &str, outcome: Result<String>) -> HealthCheckItem {
+    let (passed, detail) = match outcome {
+        Ok(detail) => (true, detail),
+        Err(e) => (false, e.to_string()),
+    };
+    HealthCheckItem { name: name.to_string(), category: category.to_string(), passed, detail }
+}
+
+/// Parse `docker ps -a --format '{{.Names}}={{.State}}'`
+pub fn parse_container_states(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .map(|(name, state)| (name.to_string(), state.to_string()))
+        .collect()
+}
+
+/// Une API "répond" si le code HTTP n'est ni une erreur serveur ni un échec de connexion
+pub fn http_code_ok(code: &str) -> bool {
+    matches!(code.trim().parse::<u16>(), Ok(200..=499))
+}
+
+/// Indexers dont le test a échoué (réponse de POST /indexer/testall)
+pub fn failed_indexers(response: &Value) -> Vec<String> {
+    response
+        .as_array()
+        .map(|results| {
+            results
+                .iter()
+                .filter(|r| !r["isValid"].as_bool().unwrap_or(false))
+                .map(|r| {
+                    let reasons = services::arr::validation_messages(&r["validationFailures"]);
+                    format!("#{} ({})", r["id"], reasons.join("; "))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn check_containers(target: &SshTarget<'_>, compose: &str) -> Vec<HealthCheckItem> {
+    let output = target
+        .exec("docker ps -a --format '{{.Names}}={{.State}}'")
+        .await
+        .unwrap_or_default();
+    let states = parse_container_states(&output);
+
+    crate::status::status_targets(compose)
+        .iter()
+        .map(|service| {
+            let state = states.iter().find(|(name, _)| name == &service.name).map(|(_, s)| s.as_str());
+            let outcome = match state {
+                Some("running") => Ok("running".to_string()),
+                Some(other) => Err(anyhow::anyhow!("conteneur {}", other)),
+                None => Err(anyhow::anyhow!("conteneur absent")),
+            };
+            item(&services::display_name(&service.name), "container", outcome)
+        })
+        .collect()
+}
+
+async fn check_apis(target: &SshTarget<'_>, compose: &str) -> Vec<HealthCheckItem> {
+    let mut checks = Vec::new();
+    for service in crate::status::status_targets(compose) {
+        let code = target
+            .exec(&format!(
+                "curl -s -o /dev/null -m 10 -w '%{{http_code}}' 'http://localhost:{}{}'",
+                service.port, service.path
+            ))
+            .await
+            .unwrap_or_default();
+        let outcome = if http_code_ok(&code) {
+            Ok(format!("HTTP {} sur le port {}", code.trim(), service.port))
+        } else {
+            Err(anyhow::anyhow!("pas de réponse sur le port {} (HTTP {})", service.port, code.trim()))
+        };
+        checks.push(item(&services::display_name(&service.name), "api", outcome));
+    }
+    checks
+}
+
+async fn check_prowlarr(target: SshTarget<'_>) -> Result<String> {
+    let client = ArrClient::connect(&target, "prowlarr").await?;
+    let count = client.indexers().await?.len();
+    if count == 0 {
+        return Err(anyhow::anyhow!("aucun indexer configuré"));
+    }
+
+    let results: Value = match client.post("/indexer/testall", &serde_json::json!({})).await {
+        Ok(results) => results,
+        // Prowlarr répond 400 quand au moins un indexer échoue, avec le détail dans le corps
+        Err(ArrClientError::Http { body, .. }) => serde_json::from_str(&body).unwrap_or_default(),
+        Err(e) => return Err(e.into()),
+    };
+    let failed = failed_indexers(&results);
+    if failed.is_empty() {
+        Ok(format!("{} indexer(s) OK", count))
+    } else {
+        Err(anyhow::anyhow!("{} indexer(s) en échec: {}", failed.len(), failed.join(", ")))
+    }
+}
+
+async fn check_jellyfin(target: &SshTarget<'_>, username: &str, password: &str) -> Result<String> {
+    let session = jellyfin::authenticate(target, username, password).await?;
+    let libraries = jellyfin::library_names(target, &session.access_token).await?;
+    if libraries.is_empty() {
+        Err(anyhow::anyhow!("aucune bibliothèque visible"))
+    } else {
+        Ok(libraries.join(", "))
+    }
+}
+
+async fn check_decypharr(target: &SshTarget<'_>) -> Result<String> {
+    let mount_path = crate::master_config::installed_media_paths(target).await.root;
+    if decypharr::mount_present(target, &mount_path).await {
+        Ok("montage rclone présent".to_string())
+    } else {
+        Err(anyhow::anyhow!("montage rclone absent"))
+    }
+}
+
+async fn check_jellyseerr(target: SshTarget<'_>) -> Result<String> {
+    let results = JellyseerrClient::connect(&target).await?.search_results(SEARCH_PROBE).await?;
+    if results == 0 {
+        Err(anyhow::anyhow!("aucun résultat pour \"{}\"", SEARCH_PROBE))
+    } else {
+        Ok(format!("{} résultat(s) pour \"{}\"", results, SEARCH_PROBE))
+    }
+}
+
+/// Accès externe par chaque route du tunnel Cloudflare provisionné
+async fn check_remote_access(target: &SshTarget<'_>) -> Vec<HealthCheckItem> {
+    let config = target
+        .exec(&format!("cat {}/config.yml 2>/dev/null", crate::cloudflare::REMOTE_CONFIG_DIR))
+        .await
+        .unwrap_or_default();
+
+    let mut checks = Vec::new();
+    for hostname in crate::cloudflare::ingress_hostnames(&config) {
+        let outcome = crate::cloudflare::check_external_access(&hostname).await;
+        checks.push(item(&format!("Accès externe {}", hostname), "remote", outcome));
+    }
+    checks
+}
+
+/// Lance toutes les vérifications (les services absents du compose sont ignorés)
+pub async fn verify_installation(
+    target: SshTarget<'_>,
+    pi_name: &str,
+    jellyfin_username: &str,
+    jellyfin_password: &str,
+) -> Result<HealthReport> {
+    tracing::info!("Running post-install checks on {}...", pi_name);
+    let compose = target.exec("cat ~/media-stack/docker-compose.yml").await?;
+    let has = |service: &str| compose.contains(&format!("\n  {}:", service));
+
+    let mut checks = check_containers(&target, &compose).await;
+    checks.extend(check_apis(&target, &compose).await);
+
+    if has("prowlarr") {
+        checks.push(item("Indexers Prowlarr", "prowlarr", check_prowlarr(target).await));
+    }
+    if has("jellyfin") {
+        checks.push(item(
+            "Bibliothèques Jellyfin",
+            "jellyfin",
+            check_jellyfin(&target, jellyfin_username, jellyfin_password).await,
+        ));
+    }
+    if has("decypharr") {
+        checks.push(item("Montage Decypharr", "decypharr", check_decypharr(&target).await));
+    }
+    if has("jellyseerr") {
+        checks.push(item("Recherche Jellyseerr", "jellyseerr", check_jellyseerr(target).await));
+    }
+    if has("cloudflared") {
+        checks.extend(check_remote_access(&target).await);
+    }
+
+    let report = HealthReport::new(pi_name, checks);
+    if report.all_passed {
+        tracing::info!("✅ {} checks passed", report.checks.len());
+    } else {
+        tracing::warn!("⚠️ {}/{} checks failed", report.failures(), report.checks.len());
+    }
+    Ok(report)
+}
+
+/// Vérification rapide sans identifiants (conteneurs + API), utilisée après une mise à jour
+pub async fn verify_stack(target: &SshTarget<'_>, pi_name: &str) -> Result<HealthReport> {
+    let compose = target.exec("cat ~/media-stack/docker-compose.yml").await?;
+    let mut checks = check_containers(target, &compose).await;
+    checks.extend(check_apis(target, &compose).await);
+    Ok(HealthReport::new(pi_name, checks))
+}
+
+/// Vérifie puis sauvegarde le rapport dans Supabase (échec de sauvegarde non bloquant)
+pub async fn verify_and_persist(
+    target: SshTarget<'_>,
+    pi_name: &str,
+    jellyfin_username: &str,
+    jellyfin_password: &str,
+) -> Result<HealthReport> {
+    let report = verify_installation(target, pi_name, jellyfin_username, jellyfin_password).await?;
+    if let Err(e) = crate::supabase::save_health_report(pi_name, &serde_json::to_value(&report)?).await {
+        tracing::warn!("could not save health report: {}", e);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_health_report_parsing() {
+        let states = parse_container_states("jellyfin=running\nradarr=exited\n\n");
+        assert_eq!(states, vec![
+            ("jellyfin".to_string(), "running".to_string()),
+            ("radarr".to_string(), "exited".to_string()),
+        ]);
+
+        assert!(http_code_ok("200"));
+        assert!(http_code_ok("401"));
+        assert!(!http_code_ok("000"));
+        assert!(!http_code_ok("502"));
+
+        let failed = failed_indexers(&json!([
+            { "id": 1, "isValid": true, "validationFailures": [] },
+            { "id": 2, "isValid": false, "validationFailures": [{ "errorMessage": "Timeout" }] }
+        ]));
+        assert_eq!(failed, vec!["#2 (Timeout)".to_string()]);
+
+        let report = HealthReport::new("pi", vec![
+            item("Jellyfin", "container", Ok("running".to_string())),
+            item("Radarr", "container", Err(anyhow::anyhow!("conteneur exited"))),
+        ]);
+        assert!(!report.all_passed);
+        assert_eq!(report.failures(), 1);
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// =============================================================================
+// Dossier de travail par installation (session)
+// =============================================================================
+//
+// Chaque flash ou installation lancé par operations::start reçoit un dossier
+// data_dir/jellysetup/sessions/<id de l'opération>/ où sont conservés:
+//   docker-compose.yml       compose rendu envoyé au Pi
+//   configs/<service>.json   configurations de services résolues
+//   install.log              journal de l'installation (InstallationLogger)
+//   snapshots/<étape>.json   état d'installation à la fin de chaque étape
+// Le chemin est exposé dans `Operation.workspace` et ouvert par la commande
+// `open_session_folder`. Les écritures se font depuis la tâche de l'opération
+// (no-op ailleurs) et n'interrompent jamais l'installation en cas d'échec.
+// Tout artefact passe par redact avant d'être écrit: le dossier est ouvert par
+// l'utilisateur et joint aux demandes d'aide (tokens, API keys, passkeys).
+// Rétention: au démarrage, les sessions de plus de 30 jours sont supprimées,
+// et seules les 20 plus récentes sont gardées.
+
+const RETENTION_DAYS: u64 = 30;
+const MAX_SESSIONS: usize = 20;
+
+fn sessions_dir() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or_else(|| anyhow!("Cannot determine data directory"))?
+        .join("jellysetup")
+        .join("sessions"))
+}
+
+/// Dossier de la session `id` (ID d'opération)
+pub fn dir(id: &str) -> Result<PathBuf> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+        return Err(anyhow!("Identifiant de session invalide: {}", id));
+    }
+    Ok(sessions_dir()?.join(id))
+}
+
+/// Crée le dossier de la session
+pub fn create(id: &str) -> Result<PathBuf> {
+    let dir = dir(id)?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Dossier de la session de l'opération courante, s'il existe
+pub fn current() -> Option<PathBuf> {
+    crate::operations::current_id()
+        .and_then(|id| dir(&id).ok())
+        .filter(|dir| dir.is_dir())
+}
+
+fn artifact_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    let path = dir.join(name);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+/// Enregistre un artefact (`name` relatif au dossier) dans la session courante, secrets masqués
+pub fn write(name: &str, content: &str) {
+    let Some(dir) = current() else { return };
+    let written = artifact_path(&dir, name).and_then(|path| Ok(std::fs::write(path, crate::redact::redact(content))?));
+    if let Err(e) = written {
+        tracing::warn!("⚠️ Could not write {}: {}", name, e);
+    }
+}
+
+/// Ajoute une ligne à un fichier de la session courante, secrets masqués
+pub fn append(name: &str, line: &str) {
+    let Some(dir) = current() else { return };
+    let appended = artifact_path(&dir, name).and_then(|path| {
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(file.write_all(crate::redact::redact(line).as_bytes())?)
+    });
+    if let Err(e) = appended {
+        tracing::warn!("⚠️ Could not append to {}: {}", name, e);
+    }
+}
+
+/// Sessions à supprimer: au-delà des MAX_SESSIONS plus récentes ou plus vieilles que RETENTION_DAYS
+/// (`sessions`: nom et âge en secondes)
+fn expired(mut sessions: Vec<(String, u64)>) -> Vec<String> {
+    sessions.sort_by_key(|(_, age)| *age);
+    sessions
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (_, age))| *index >= MAX_SESSIONS || *age > RETENTION_DAYS * 24 * 3600)
+        .map(|(_, (name, _))| name)
+        .collect()
+}
+
+/// Applique la politique de rétention (au démarrage de l'application)
+pub fn cleanup() {
+    let Ok(root) = sessions_dir() else { return };
+    let Ok(entries) = std::fs::read_dir(&root) else { return };
+
+    let sessions: Vec<(String, u64)> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let age = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0);
+            (entry.file_name().to_string_lossy().to_string(), age)
+        })
+        .collect();
+
+    let removed = expired(sessions);
+    for name in &removed {
+        if let Err(e) = std::fs::remove_dir_all(root.join(name)) {
+            tracing::warn!("⚠️ Could not remove session {}: {}", name, e);
+        }
+    }
+    if !removed.is_empty() {
+        tracing::info!("{} old session folders removed", removed.len());
+    }
+}
+
+/// Dossier de la session la plus récente (dernière modification)
+pub fn latest() -> Option<PathBuf> {
+    std::fs::read_dir(sessions_dir().ok()?)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Ouvre le dossier de la session dans le gestionnaire de fichiers
+pub fn open(id: &str) -> Result<()> {
+    let dir = dir(id)?;
+    if !dir.is_dir() {
+        return Err(anyhow!("Dossier de session introuvable (supprimé par la rétention ?): {}", id));
+    }
+
+    #[cfg(target_os = "macos")]
+    let opener = "open";
+    #[cfg(target_os = "windows")]
+    let opener = "explorer";
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let opener = "xdg-open";
+
+    std::process::Command::new(opener).arg(&dir).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_retention() {
+        let day = 24 * 3600;
+        let mut sessions: Vec<(String, u64)> = (0..25).map(|i| (format!("recent-{}", i), i * 60)).collect();
+        sessions.push(("old".to_string(), 31 * day));
+        let mut removed = expired(sessions);
+        removed.sort();
+        assert_eq!(removed.len(), 6);
+        assert!(removed.contains(&"old".to_string()));
+        assert!(removed.contains(&"recent-24".to_string()));
+        assert!(!removed.contains(&"recent-0".to_string()));
+
+        assert!(expired(vec![("a".to_string(), 2 * day)]).is_empty());
+        assert!(dir("../../etc").is_err());
+        assert!(dir("3f2b9c1e-7a4d-4e8b-9f00-1c2d3e4f5a6b").is_ok());
+    }
+}
@@ -15,6 +15,7 @@ import Complete from './components/Wizard/Complete';
 import ServicesView from './components/Wizard/ServicesView';
 
 import { useStore } from './lib/store';
+import type { UnconfiguredCard } from './bindings/UnconfiguredCard';
 
 type WizardStep =
   | 'permission'
@@ -53,12 +54,22 @@ const CONNECT_STEPS = [
 function App() {
   const [step, setStep] = useState<WizardStep>('permission');
   const [flowMode, setFlowMode] = useState<FlowMode>('full');
-  const { config, setConfig, piInfo, setPiInfo } = useStore();
+  const { config, setConfig, piInfo, setPiInfo, selectedSD } = useStore();
+  // Carte flashée mais non configurée: on ne refait que la configuration du boot
+  const [unconfiguredCard, setUnconfiguredCard] = useState<UnconfiguredCard | null>(null);
+  const [configureOnly, setConfigureOnly] = useState(false);
 
   useEffect(() => {
     checkForUpdates();
   }, []);
 
+  useEffect(() => {
+    if (step !== 'menu') return;
+    invoke<UnconfiguredCard | null>('detect_unconfigured_card')
+      .then(setUnconfiguredCard)
+      .catch((error) => console.error('Erreur détection carte:', error));
+  }, [step]);
+
   const checkForUpdates = async () => {
     try {
       const latestVersion = await invoke<string | null>('check_for_updates');
@@ -89,8 +100,17 @@ function App() {
         return (
           <MainMenu
             hasExistingConfig={hasExistingConfig}
+            unconfiguredCard={unconfiguredCard}
+            onFinishConfiguration={() => {
+              setFlowMode('full');
+              setConfigureOnly(true);
+              if (unconfiguredCard?.hostname) setConfig({ hostname: unconfiguredCard.hostname });
+              // Carte du flash interrompu inconnue: la choisir avant de configurer
+              setStep(unconfiguredCard?.sd_path || selectedSD ? 'config' : 'sd-selection');
+            }}
             onNewSetup={() => {
               setFlowMode('full');
+              setConfigureOnly(false);
               setStep('welcome');
             }}
             onConnectExisting={() => {
@@ -142,6 +162,8 @@ function App() {
       case 'flash':
         return (
           <FlashProgress
+            configureOnly={configureOnly}
+            sdPath={configureOnly ? unconfiguredCard?.sd_path ?? undefined : undefined}
             onComplete={() => setStep('waiting')}
             onError={() => setStep('sd-selection')}
           />
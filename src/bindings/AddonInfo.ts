@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface AddonInfo { name: string, display_name: string, description: string, min_memory_mb: number, min_disk_gb: number, port: number, installed: boolean, }
\ No newline at end of file
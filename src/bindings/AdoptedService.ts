@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface AdoptedService { service: string, container: string, image: string, state: string, config_path: string | null, api_key_found: boolean, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AdoptedService } from "./AdoptedService";
+
+export interface AdoptionReport { pi_name: string, host: string, compose_dir: string | null, services: Array<AdoptedService>, unmanaged: Array<string>, registered: boolean, warnings: Array<string>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PreflightCheck } from "./PreflightCheck";
+
+export interface AppDiagnostics { app_version: string, os: string, generated_at: string, checks: Array<PreflightCheck>, has_failures: boolean, has_warnings: boolean, summary: string, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface BackupArchive { service: string, file: string, size: number, sha256: string, storage_path: string | null, }
\ No newline at end of file
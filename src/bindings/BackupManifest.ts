@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackupArchive } from "./BackupArchive";
+
+export interface BackupManifest { id: string, pi_name: string, created_at: string, directory: string, archives: Array<BackupArchive>, }
\ No newline at end of file
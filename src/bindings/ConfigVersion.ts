@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ConfigVersion { id: string, created_at: string, config_hash: string, master_config_id: string | null, blob_path: string, }
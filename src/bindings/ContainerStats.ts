@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ContainerStats { name: string, cpu_percent: number, mem_percent: number, mem_usage: string, net_io: string, block_io: string, }
\ No newline at end of file
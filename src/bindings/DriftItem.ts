@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DriftKind } from "./DriftKind";
+
+export interface DriftItem { key: string, kind: DriftKind, expected: string | null, actual: string | null, }
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DriftItem } from "./DriftItem";
+
+export interface DriftReport { pi_name: string, checked_at: string, has_baseline: boolean, items: Array<DriftItem>, }
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface FlashProfile { name: string, hostname: string, system_username: string, wifi_ssid: string, wifi_country: string, timezone: string, keymap: string, last_used: string, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JellyfinAuth } from "./JellyfinAuth";
+
+export interface FlashProgress { step: string, percent: number, message: string, speed: string | null, jellyfin_auth?: JellyfinAuth, }
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface GeneratedPassword { password: string, length: number, alphabet_size: number, entropy_bits: number, strength: string, }
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HealthCheckItem } from "./HealthCheckItem";
+
+export interface HealthReport { pi_name: string, checked_at: string, checks: Array<HealthCheckItem>, all_passed: boolean, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface HouseholdRequest { jellyseerr_id: number, media_type: string, tmdb_id: number, title: string, status: string, media_status: string, requested_by: string, requested_at: string, is_4k: boolean, }
\ No newline at end of file
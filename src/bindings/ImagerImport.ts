@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ImagerImport { hostname: string | null, system_username: string | null, system_password: string | null, wifi_ssid: string | null, wifi_password: string | null, wifi_hidden: boolean, wifi_country: string | null, timezone: string | null, keymap: string | null, ssh_authorized_keys: Array<string>, warnings: Array<string>, }
\ No newline at end of file
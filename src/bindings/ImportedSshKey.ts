@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SshKeyType } from "./SshKeyType";
+
+export interface ImportedSshKey { key_type: SshKeyType, public_key: string, private_key: string, encrypted: boolean, }
\ No newline at end of file
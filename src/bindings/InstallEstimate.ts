@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EstimatePhase } from "./EstimatePhase";
+import type { PiModel } from "./PiModel";
+
+export interface InstallEstimate { pi_model: PiModel, total_seconds: number, download_bytes: number, download_mbps: number | null, phases: Array<EstimatePhase>, summary: string, }
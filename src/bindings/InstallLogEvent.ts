@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LogLevel } from "./LogLevel";
+
+export interface InstallLogEvent { timestamp: string, level: LogLevel, step: string, substep: string | null, message: string, progress_percent: number | null, ssh_exit_code: number | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PlannedAction } from "./PlannedAction";
+
+export interface InstallationPlan { procedure_name: string, procedure_version: string, actions: Array<PlannedAction>, missing_variables: Array<string>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RegistrationAuth } from "./RegistrationAuth";
+
+export interface InstallationRegistration { version: number, pi_name: string, pi_ip: string, auth: RegistrationAuth, ssh_host_fingerprint: string | null, installer_version: string, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface JellyfinTemplate { jellyfin_version: string, captured_at: string, size: number, sha256: string, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type LogLevel = "DEBUG" | "INFO" | "WARN" | "ERROR" | "SUCCESS" | "CRITICAL";
\ No newline at end of file
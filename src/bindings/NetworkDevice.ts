@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface NetworkDevice { ip: string, hostname: string | null, mac_address: string | null, vendor: string | null, open_ports: Array<number>, likely_pi: boolean, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface NetworkHost { hostname: string, addresses: Array<string>, services: Array<string>, ssh_port: number | null, mac_address: string | null, first_seen: string, last_seen: string, }
\ No newline at end of file
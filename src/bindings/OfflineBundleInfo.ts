@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface OfflineBundleInfo { installer_version: string, created_at: string, os_image: string, procedure_version: string, templates: Array<string>, stack_images: string | null, size_bytes: number, }
\ No newline at end of file
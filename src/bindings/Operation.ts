@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OperationKind } from "./OperationKind";
+import type { OperationStatus } from "./OperationStatus";
+
+export interface Operation { id: string, kind: OperationKind, status: OperationStatus, step: string, percent: number, message: string, result: unknown, error: string | null, started_at: string, finished_at: string | null, workspace: string | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface PiCredentials { hostname: string, username: string, system_password: string | null, ssh_private_key: string | null, jellyfin_username: string | null, jellyfin_password: string | null, updated_at: string, }
\ No newline at end of file
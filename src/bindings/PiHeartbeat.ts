@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface PiHeartbeat { pi_name: string, host: string, checked_at: string, reachable: boolean, running: Array<string>, down: Array<string>, disk_percent: number | null, cpu_temp_c: number | null, mem_percent: number | null, }
\ No newline at end of file
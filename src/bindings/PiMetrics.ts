@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThrottleStatus } from "./ThrottleStatus";
+
+export interface PiMetrics { cpu_temp_c: number | null, throttle: ThrottleStatus | null, disk_total_bytes: number, disk_used_bytes: number, disk_available_bytes: number, mem_total_bytes: number, mem_used_bytes: number, mem_available_bytes: number, uptime_secs: number, warnings: Array<string>, }
\ No newline at end of file
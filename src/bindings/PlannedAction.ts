@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface PlannedAction { step_id: string, name: string, kind: string, critical: boolean, skipped: boolean, details: Array<string>, }
\ No newline at end of file
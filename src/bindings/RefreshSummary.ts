@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface RefreshSummary { checked: number, refreshed: number, failed: Array<string>, decypharr_refreshed: boolean, }
\ No newline at end of file
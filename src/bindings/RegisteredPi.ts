@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface RegisteredPi { pi_name: string, host: string, username: string, auth_method: string, ssh_host_fingerprint: string | null, installed_version: string, added_at: string, last_seen: string | null, }
\ No newline at end of file
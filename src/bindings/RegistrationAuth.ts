@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RegistrationAuth = { "method": "key", ssh_public_key: string | null, ssh_private_key_encrypted: string | null, } | { "method": "password" };
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RouteCheck } from "./RouteCheck";
+import type { TunnelState } from "./TunnelState";
+
+export interface RemoteAccessReport { routes: Array<RouteCheck>, tunnel: TunnelState, repaired: boolean, actions: Array<string>, needs_new_token: boolean, message: string, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HealthReport } from "./HealthReport";
+
+export interface RestoreReport { backup_id: string, pi_name: string, restored: Array<string>, health: HealthReport, }
\ No newline at end of file
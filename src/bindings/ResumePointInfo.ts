@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ResumePointInfo { pi_name: string, host: string, installer_version: string, saved_at: string, completed: Array<string>, failed_step: string | null, last_error: string | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SecretKind = "ssh_private_key" | "alldebrid_key" | "ygg_passkey" | "cloudflare_token" | "cloudflare_api_token" | "system_password" | "wifi_password" | "jellyfin_password" | "account_refresh_token" | "pi_token";
\ No newline at end of file
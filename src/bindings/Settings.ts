@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CloudBackendKind } from "./CloudBackendKind";
+
+export interface Settings { max_sd_size_bytes: number, min_sd_size_bytes: number, rpi_os_index_url: string, procedures_base_url: string, image_pull_attempts: number, offline_mode: boolean, cloud_backend: CloudBackendKind, self_hosted_url: string | null, self_hosted_key: string | null, }
\ No newline at end of file
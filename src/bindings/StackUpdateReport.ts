@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HealthReport } from "./HealthReport";
+import type { ServiceImageChange } from "./ServiceImageChange";
+
+export interface StackUpdateReport { pi_name: string, updated_at: string, changed: Array<ServiceImageChange>, reconfigured: Array<string>, health: HealthReport | null, rolled_back: boolean, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ThrottleStatus { raw: string, under_voltage: boolean, freq_capped: boolean, throttled: boolean, soft_temp_limit: boolean, under_voltage_occurred: boolean, freq_capped_occurred: boolean, throttled_occurred: boolean, soft_temp_limit_occurred: boolean, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface TranscodeTestResult { acceleration: string, encoder: string, fps: number, speed: number, realtime: boolean, verdict: string, }
\ No newline at end of file
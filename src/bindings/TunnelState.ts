@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TunnelState = "connected" | "token_rejected" | "disconnected" | "not_running" | "unknown";
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface UnconfiguredCard { boot_path: string, sd_path: string | null, hostname: string | null, written_at: string | null, }
\ No newline at end of file
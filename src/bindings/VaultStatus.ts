@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface VaultStatus { exists: boolean, unlocked: boolean, hostnames: Array<string>, auto_lock_secs: number, }
\ No newline at end of file
@@ -0,0 +1,158 @@
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/tauri';
+import { open } from '@tauri-apps/api/shell';
+import { Plus, Loader2, Check, ExternalLink, AlertTriangle, Trash2 } from 'lucide-react';
+import { useStore, PiInfo } from '../../lib/store';
+import type { AddonInfo } from '../../bindings/AddonInfo';
+import type { AddonInstallResult } from '../../bindings/AddonInstallResult';
+import type { AddonUninstallReport } from '../../bindings/AddonUninstallReport';
+
+interface AddonCatalogProps {
+  piInfo: PiInfo;
+}
+
+export default function AddonCatalog({ piInfo }: AddonCatalogProps) {
+  const { config, sshCredentials } = useStore();
+  const [addons, setAddons] = useState<AddonInfo[]>([]);
+  const [installing, setInstalling] = useState<string | null>(null);
+  const [result, setResult] = useState<AddonInstallResult | null>(null);
+  const [removal, setRemoval] = useState<AddonUninstallReport | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  // Clé SSH du flash si disponible, sinon mot de passe système
+  const connection = {
+    host: piInfo.ip,
+    username: config.systemUsername || 'maison',
+    password: sshCredentials ? null : config.systemPassword || null,
+    privateKey: sshCredentials?.privateKey ?? null,
+  };
+
+  useEffect(() => {
+    invoke<AddonInfo[]>('list_addons', connection)
+      .then(setAddons)
+      .catch((err) => console.error('[AddonCatalog] list_addons failed:', err));
+  }, [piInfo.ip]);
+
+  const install = async (addon: AddonInfo) => {
+    setInstalling(addon.name);
+    setError(null);
+    setResult(null);
+    try {
+      const installed = await invoke<AddonInstallResult>('install_addon', { ...connection, name: addon.name });
+      setResult(installed);
+      setRemoval(null);
+      setAddons((prev) => prev.map((a) => (a.name === addon.name ? { ...a, installed: true } : a)));
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setInstalling(null);
+    }
+  };
+
+  const uninstall = async (addon: AddonInfo) => {
+    if (!window.confirm(`Désinstaller ${addon.display_name} ?`)) return;
+    // OK: archive des données sur le Pi, Annuler: suppression définitive
+    const keepData = window.confirm('Conserver une archive des données ? (Annuler pour tout supprimer)');
+    setInstalling(addon.name);
+    setError(null);
+    setResult(null);
+    try {
+      const report = await invoke<AddonUninstallReport>('uninstall_addon', { ...connection, name: addon.name, keepData });
+      setRemoval(report);
+      setAddons((prev) => prev.map((a) => (a.name === addon.name ? { ...a, installed: false } : a)));
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setInstalling(null);
+    }
+  };
+
+  if (addons.length === 0) return null;
+
+  return (
+    <div className="card !p-4 space-y-3">
+      <span className="text-sm text-zinc-400">Ajouter des applications</span>
+      <div className="grid grid-cols-2 gap-2">
+        {addons.map((addon) => (
+          <div key={addon.name} className="bg-zinc-800/50 rounded-lg p-3 flex flex-col gap-2">
+            <div>
+              <span className="font-medium text-white text-sm block">{addon.display_name}</span>
+              <span className="text-[11px] text-zinc-500">{addon.description}</span>
+            </div>
+            {addon.installed ? (
+              <div className="flex items-center justify-between">
+                <button
+                  onClick={() => open(`http://${piInfo.ip}:${addon.port}`)}
+                  className="text-xs text-green-400 flex items-center gap-1"
+                >
+                  <Check className="w-3 h-3" /> Installé <ExternalLink className="w-3 h-3" />
+                </button>
+                <button
+                  onClick={() => uninstall(addon)}
+                  disabled={installing !== null}
+                  title="Désinstaller"
+                  className="text-zinc-500 hover:text-red-400 disabled:opacity-50"
+                >
+                  {installing === addon.name ? (
+                    <Loader2 className="w-3 h-3 animate-spin" />
+                  ) : (
+                    <Trash2 className="w-3 h-3" />
+                  )}
+                </button>
+              </div>
+            ) : (
+              <button
+                onClick={() => install(addon)}
+                disabled={installing !== null}
+                className="text-xs text-purple-300 flex items-center gap-1 disabled:opacity-50"
+              >
+                {installing === addon.name ? (
+                  <><Loader2 className="w-3 h-3 animate-spin" /> Installation...</>
+                ) : (
+                  <><Plus className="w-3 h-3" /> Installer ({addon.min_memory_mb} Mo RAM)</>
+                )}
+              </button>
+            )}
+          </div>
+        ))}
+      </div>
+
+      {result && (
+        <div className="text-xs text-zinc-300 bg-green-500/10 rounded-lg p-3 space-y-1">
+          <p>
+            {result.name} est prêt: <span className="font-mono">{result.url}</span>
+          </p>
+          {result.password && (
+            <p>
+              Mot de passe généré (à conserver): <code className="text-white">{result.password}</code>
+            </p>
+          )}
+          {result.notes && <pre className="whitespace-pre-wrap text-zinc-400">{result.notes}</pre>}
+        </div>
+      )}
+
+      {removal && (
+        <div className="text-xs text-zinc-300 bg-zinc-800/50 rounded-lg p-3 space-y-1">
+          <p>{removal.name} a été désinstallé.</p>
+          {removal.backup && (
+            <p>
+              Données archivées sur le Pi: <span className="font-mono">{removal.backup}</span>
+            </p>
+          )}
+          <p className={removal.health.all_passed ? 'text-green-400' : 'text-amber-400'}>
+            {removal.health.all_passed
+              ? 'Les autres services fonctionnent normalement.'
+              : `${removal.health.checks.filter((c) => !c.passed).length} vérification(s) en échec sur le reste du stack.`}
+          </p>
+        </div>
+      )}
+
+      {error && (
+        <div className="text-xs text-red-400 bg-red-500/10 rounded-lg p-3 flex gap-2">
+          <AlertTriangle className="w-4 h-4 flex-shrink-0" />
+          <pre className="whitespace-pre-wrap">{error}</pre>
+        </div>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,63 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/tauri';
+import { Loader2, Check, AlertTriangle } from 'lucide-react';
+import type { PreflightCheck } from '../../bindings/PreflightCheck';
+
+interface AlldebridAccountStatusProps {
+  apiKey: string;
+}
+
+// Délai avant de vérifier la clé (évite un appel par caractère saisi)
+const CHECK_DELAY_MS = 800;
+
+export default function AlldebridAccountStatus({ apiKey }: AlldebridAccountStatusProps) {
+  const [check, setCheck] = useState<PreflightCheck | null>(null);
+  const [loading, setLoading] = useState(false);
+
+  // Abonnement expiré ou proche de l'expiration: prévenir avant l'installation
+  useEffect(() => {
+    setCheck(null);
+    if (!apiKey.trim()) return;
+
+    let cancelled = false;
+    const timer = setTimeout(async () => {
+      setLoading(true);
+      try {
+        const result = await invoke<PreflightCheck>('check_alldebrid_account', { apiKey: apiKey.trim() });
+        if (!cancelled) setCheck(result);
+      } finally {
+        if (!cancelled) setLoading(false);
+      }
+    }, CHECK_DELAY_MS);
+
+    return () => {
+      cancelled = true;
+      clearTimeout(timer);
+      setLoading(false);
+    };
+  }, [apiKey]);
+
+  if (loading) {
+    return (
+      <p className="text-xs text-zinc-500 mt-2 flex items-center gap-1">
+        <Loader2 className="w-3 h-3 animate-spin" /> Vérification du compte...
+      </p>
+    );
+  }
+  if (!check) return null;
+
+  if (check.status === 'pass') {
+    return (
+      <p className="text-xs text-green-400 mt-2 flex items-center gap-1">
+        <Check className="w-3 h-3" /> {check.message}
+      </p>
+    );
+  }
+  return (
+    <p
+      className={`text-xs mt-2 flex items-center gap-1 ${check.status === 'fail' ? 'text-red-400' : 'text-amber-400'}`}
+    >
+      <AlertTriangle className="w-3 h-3 flex-shrink-0" /> {check.message}
+    </p>
+  );
+}
@@ -2,6 +2,7 @@ import { ExternalLink, Copy, Check, RotateCcw, PartyPopper } from 'lucide-react'
 import { useState } from 'react';
 import { open } from '@tauri-apps/api/shell';
 import { useStore, PiInfo } from '../../lib/store';
+import AddonCatalog from './AddonCatalog';
 
 interface CompleteProps {
   piInfo: PiInfo;
@@ -104,6 +105,9 @@ export default function Complete({ piInfo, onRestart }: CompleteProps) {
         </div>
       )}
 
+      {/* Applications optionnelles */}
+      <AddonCatalog piInfo={piInfo} />
+
       {/* Status */}
       <div className="flex items-center gap-2 p-3 bg-green-500/10 rounded-xl">
         <div className="w-2 h-2 bg-green-500 rounded-full animate-pulse" />
@@ -2,6 +2,8 @@ import { useState, useRef, useEffect } from 'react';
 import { ArrowLeft, ArrowRight, Eye, EyeOff, ExternalLink, Wifi, Key, User, Info, Monitor, Mail, Server } from 'lucide-react';
 import { open } from '@tauri-apps/api/shell';
 import { Config } from '../../lib/store';
+import AlldebridAccountStatus from './AlldebridAccountStatus';
+import SshKeyOptions from './SshKeyOptions';
 
 interface ConfigFormProps {
   config: Config;
@@ -56,7 +58,11 @@ export default function ConfigForm({ config, onConfigChange, onNext, onBack }: C
     else if (config.systemPassword.length < 4) newErrors.systemPassword = 'Min 4 caractères';
     // WiFi
     if (!config.wifiSSID.trim()) newErrors.wifiSSID = 'Requis';
+    // WPA2: 8 à 63 caractères, ou PSK déjà calculée (64 hexadécimaux)
+    const wifiPsk = /^[0-9a-fA-F]{64}$/.test(config.wifiPassword);
     if (!config.wifiPassword.trim()) newErrors.wifiPassword = 'Requis';
+    else if (!wifiPsk && (config.wifiPassword.length < 8 || config.wifiPassword.length > 63))
+      newErrors.wifiPassword = '8 à 63 caractères';
     // Services
     if (!config.alldebridKey.trim()) newErrors.alldebridKey = 'Requis';
     if (!config.jellyfinUsername.trim()) newErrors.jellyfinUsername = 'Requis';
@@ -142,6 +148,7 @@ export default function ConfigForm({ config, onConfigChange, onNext, onBack }: C
             ))}
           </select>
         </div>
+        <SshKeyOptions config={config} onConfigChange={onConfigChange} />
       </div>
 
       {/* WiFi */}
@@ -212,6 +219,7 @@ export default function ConfigForm({ config, onConfigChange, onNext, onBack }: C
           className={`input-field text-sm py-2.5 font-mono ${errors.alldebridKey ? 'input-field-error' : ''}`}
           placeholder="Votre clé API"
         />
+        <AlldebridAccountStatus apiKey={config.alldebridKey} />
       </div>
 
       {/* YGG Passkey */}
@@ -1,8 +1,14 @@
 import { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
 import { listen } from '@tauri-apps/api/event';
-import { Check, Loader2, Cpu, RefreshCw, AlertTriangle } from 'lucide-react';
-import { useStore, PiInfo, JellyfinAuth } from '../../lib/store';
+import { Check, Loader2, Cpu, RefreshCw, AlertTriangle, KeyRound } from 'lucide-react';
+import { useStore, PiInfo } from '../../lib/store';
+import { saveConfigSecrets } from '../../lib/secrets';
+import SupportBundleButton from './SupportBundleButton';
+import InstallLogPane from './InstallLogPane';
+import type { FlashProgress } from '../../bindings/FlashProgress';
+import type { InstallationRegistration } from '../../bindings/InstallationRegistration';
+import { runOperation } from '../../lib/operations';
 
 interface ConfigProgressProps {
   piInfo: PiInfo;
@@ -38,7 +44,7 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
 
   useEffect(() => {
     // Écouter les événements de progression du backend
-    const unlisten = listen<{ step: string; percent: number; message: string; jellyfin_auth?: JellyfinAuth }>('flash-progress', (event) => {
+    const unlisten = listen<FlashProgress>('flash-progress', (event) => {
       console.log('[ConfigProgress] Progress event:', event.payload);
       const { step, percent, message, jellyfin_auth } = event.payload;
 
@@ -76,12 +82,19 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
   const runConfiguration = async () => {
     try {
       setCurrentStep(0);
+      // Secrets saisis (clés API, mots de passe): rangés dans le coffre de l'OS
+      saveConfigSecrets(config).catch((err) => console.warn('[ConfigProgress] Keychain unavailable:', err));
       addLog(`Connexion à ${piInfo.ip}...`);
 
       // Utiliser mot de passe si pas de clés SSH (flow QuickConnect)
       const usePassword = !sshCredentials;
       const username = config.systemUsername || 'maison';
 
+      // Empreinte SSH enregistrée pour ce Pi (oubliée si l'application vient de flasher sa carte)
+      await invoke('load_expected_fingerprint', { piName: piInfo.hostname, host: piInfo.ip }).catch((err) =>
+        console.warn('[ConfigProgress] Expected fingerprint unavailable:', err)
+      );
+
       if (usePassword) {
         addLog(`Authentification par mot de passe pour ${username}@${piInfo.ip}`);
         const sshOk = await invoke<boolean>('test_ssh_connection_password', {
@@ -104,7 +117,7 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
 
       // Installation avec mot de passe ou clé
       if (usePassword) {
-        await invoke('run_installation_password', {
+        await runOperation('start_installation_password', {
           host: piInfo.ip,
           username: username,
           password: config.systemPassword,
@@ -114,13 +127,19 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
             jellyfin_password: config.jellyfinPassword,
             jellyfin_server_name: config.jellyfinServerName || config.hostname,
             admin_email: config.adminEmail || null,
+            admin_display_name: config.adminDisplayName || null,
+            preferred_language: config.preferredLanguage || null,
             ygg_passkey: config.yggPasskey || null,
             discord_webhook: config.discordWebhook || null,
             cloudflare_token: config.cloudflareToken || null,
+            cloudflare: config.cloudflareApiToken && config.cloudflareDomain
+              ? { api_token: config.cloudflareApiToken, domain: config.cloudflareDomain }
+              : null,
+            timezone: config.timezone || null,
           },
         });
       } else {
-        await invoke('run_installation', {
+        await runOperation('start_installation', {
           host: piInfo.ip,
           username: username,
           privateKey: sshCredentials.privateKey,
@@ -130,9 +149,15 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
             jellyfin_password: config.jellyfinPassword,
             jellyfin_server_name: config.jellyfinServerName || config.hostname,
             admin_email: config.adminEmail || null,
+            admin_display_name: config.adminDisplayName || null,
+            preferred_language: config.preferredLanguage || null,
             ygg_passkey: config.yggPasskey || null,
             discord_webhook: config.discordWebhook || null,
             cloudflare_token: config.cloudflareToken || null,
+            cloudflare: config.cloudflareApiToken && config.cloudflareDomain
+              ? { api_token: config.cloudflareApiToken, domain: config.cloudflareDomain }
+              : null,
+            timezone: config.timezone || null,
           },
         });
       }
@@ -142,13 +167,21 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
       setProgress(100);
       setStatusMessage('Installation terminée !');
 
-      const installId = await invoke<string>('save_to_supabase', {
-        piName: piInfo.hostname,
-        piIp: piInfo.ip,
-        sshPublicKey: sshCredentials?.publicKey || '',
-        sshPrivateKeyEncrypted: sshCredentials?.privateKey || '',
-        installerVersion: '1.0.0',
-      });
+      const payload: InstallationRegistration = {
+        version: 2,
+        pi_name: piInfo.hostname,
+        pi_ip: piInfo.ip,
+        auth: sshCredentials
+          ? {
+              method: 'key',
+              ssh_public_key: sshCredentials.publicKey || null,
+              ssh_private_key_encrypted: sshCredentials.privateKey || null,
+            }
+          : { method: 'password' },
+        ssh_host_fingerprint: null,
+        installer_version: '1.0.0',
+      };
+      const installId = await invoke<string>('register_installation', { payload });
       setInstallationId(installId);
       setProgress(100);
       setTimeout(onComplete, 1500);
@@ -158,7 +191,20 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
     }
   };
 
+  // Pi reflashé hors de l'application: l'utilisateur confirme avant d'oublier l'empreinte
+  const forgetHostAndRetry = async () => {
+    try {
+      await invoke('forget_ssh_host_fingerprint', { host: piInfo.ip });
+      addLog(`Empreinte SSH de ${piInfo.ip} oubliée`);
+      setError(null);
+      runConfiguration();
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
   if (error) {
+    const hostKeyMismatch = error.includes('HOST_KEY_MISMATCH');
     return (
       <div className="text-center space-y-4">
         <div className="w-16 h-16 mx-auto bg-red-500/20 rounded-2xl flex items-center justify-center">
@@ -168,10 +214,17 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
           <h3 className="text-lg font-semibold text-white mb-1">Erreur</h3>
           <p className="text-sm text-red-300/80">{error}</p>
         </div>
+        {hostKeyMismatch && (
+          <button onClick={forgetHostAndRetry} className="btn-secondary">
+            <KeyRound className="w-4 h-4" />
+            Oublier l'empreinte de ce Pi et réessayer
+          </button>
+        )}
         <button onClick={onError} className="btn-primary">
           <RefreshCw className="w-4 h-4" />
           Réessayer
         </button>
+        <SupportBundleButton />
       </div>
     );
   }
@@ -220,6 +273,9 @@ export default function ConfigProgress({ piInfo, onComplete, onError }: ConfigPr
           </div>
         ))}
       </div>
+
+      {/* Logs en direct */}
+      <InstallLogPane />
     </div>
   );
 }
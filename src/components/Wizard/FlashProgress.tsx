@@ -3,8 +3,15 @@ import { invoke } from '@tauri-apps/api/tauri';
 import { listen } from '@tauri-apps/api/event';
 import { Check, Loader2, HardDrive, AlertTriangle } from 'lucide-react';
 import { useStore } from '../../lib/store';
+import { saveConfigSecrets, storeSshPrivateKey } from '../../lib/secrets';
+import SupportBundleButton from './SupportBundleButton';
+import { runOperation } from '../../lib/operations';
 
 interface FlashProgressProps {
+  /** Carte déjà flashée: configuration du boot et éjection seulement */
+  configureOnly?: boolean;
+  /** Carte à configurer (flash interrompu), sinon la carte sélectionnée */
+  sdPath?: string;
   onComplete: () => void;
   onError: () => void;
 }
@@ -22,11 +29,11 @@ interface ProgressEvent {
   speed?: string;
 }
 
-export default function FlashProgress({ onComplete, onError }: FlashProgressProps) {
-  const { config, selectedSD, setSSHCredentials, addLog } = useStore();
+export default function FlashProgress({ configureOnly = false, sdPath, onComplete, onError }: FlashProgressProps) {
+  const { config, selectedSD, sshCredentials, setSSHCredentials, addLog } = useStore();
   const [steps, setSteps] = useState<FlashStep[]>([
-    { id: 'download', label: 'Téléchargement', status: 'pending' },
-    { id: 'write', label: 'Écriture', status: 'pending' },
+    { id: 'download', label: 'Téléchargement', status: configureOnly ? 'complete' : 'pending' },
+    { id: 'write', label: 'Écriture', status: configureOnly ? 'complete' : 'pending' },
     { id: 'configure', label: 'Configuration', status: 'pending' },
     { id: 'eject', label: 'Éjection', status: 'pending' },
   ]);
@@ -85,13 +92,29 @@ export default function FlashProgress({ onComplete, onError }: FlashProgressProp
 
   const startFlashing = async () => {
     try {
-      addLog('Génération des clés SSH...');
-      const sshKeys = await invoke<{ public_key: string; private_key: string }>('generate_ssh_keys');
+      // Clé importée par l'utilisateur, sinon nouvelle paire du type choisi
+      const sshKeys =
+        config.sshKeyImported && sshCredentials
+          ? { public_key: sshCredentials.publicKey, private_key: sshCredentials.privateKey }
+          : await (async () => {
+              addLog('Génération des clés SSH...');
+              return invoke<{ public_key: string; private_key: string }>('generate_ssh_keys', {
+                keyType: config.sshKeyType ?? 'ed25519',
+              });
+            })();
       setSSHCredentials({ publicKey: sshKeys.public_key, privateKey: sshKeys.private_key });
 
-      await invoke('flash_sd_card', {
+      // Clé privée et secrets saisis: rangés dans le coffre de l'OS, pas dans localStorage
+      try {
+        await storeSshPrivateKey(config.hostname || 'jellypi', sshKeys.private_key);
+        await saveConfigSecrets(config);
+      } catch (err) {
+        console.warn('[FlashProgress] Keychain unavailable:', err);
+      }
+
+      const flashArgs = {
         config: {
-          sdPath: selectedSD!.path,
+          sdPath: sdPath ?? selectedSD!.path,
           // Système
           hostname: config.hostname || 'jellypi',
           systemUsername: config.systemUsername || 'maison',
@@ -105,7 +128,12 @@ export default function FlashProgress({ onComplete, onError }: FlashProgressProp
           keymap: config.keymap || 'fr',
         },
         sshPublicKey: sshKeys.public_key,
-      });
+      };
+      if (configureOnly) {
+        await invoke('finish_boot_configuration', flashArgs);
+      } else {
+        await runOperation('start_flash', flashArgs);
+      }
 
       setSteps((prev) => prev.map((s) => ({ ...s, status: 'complete' })));
       setProgress(100);
@@ -135,6 +163,7 @@ export default function FlashProgress({ onComplete, onError }: FlashProgressProp
           <p className="text-sm text-red-300/80 font-mono">{error}</p>
         </div>
         <button onClick={onError} className="btn-primary">Réessayer</button>
+        <SupportBundleButton />
       </div>
     );
   }
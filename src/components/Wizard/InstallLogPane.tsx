@@ -0,0 +1,102 @@
+import { useState, useEffect, useRef } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { ScrollText, ChevronDown, ChevronUp } from 'lucide-react';
+import type { InstallLogEvent } from '../../bindings/InstallLogEvent';
+import type { LogLevel } from '../../bindings/LogLevel';
+
+// Au-delà, les plus anciennes entrées sont oubliées (le journal complet reste dans install.log)
+const MAX_ENTRIES = 1000;
+
+const LEVEL_RANK: Record<LogLevel, number> = {
+  DEBUG: 0, INFO: 1, SUCCESS: 1, WARN: 2, ERROR: 3, CRITICAL: 3,
+};
+
+const LEVEL_COLOR: Record<LogLevel, string> = {
+  DEBUG: 'text-zinc-500',
+  INFO: 'text-zinc-300',
+  SUCCESS: 'text-green-400',
+  WARN: 'text-yellow-400',
+  ERROR: 'text-red-400',
+  CRITICAL: 'text-red-500',
+};
+
+const FILTERS: { value: number; name: string }[] = [
+  { value: 0, name: 'Tout' },
+  { value: 1, name: 'Infos' },
+  { value: 2, name: 'Avertissements' },
+  { value: 3, name: 'Erreurs' },
+];
+
+/** Logs de l'installation en direct (événement `install-log`), filtrables par niveau et par texte */
+export default function InstallLogPane() {
+  const [entries, setEntries] = useState<InstallLogEvent[]>([]);
+  const [open, setOpen] = useState(false);
+  const [minRank, setMinRank] = useState(1);
+  const [search, setSearch] = useState('');
+  const bottomRef = useRef<HTMLDivElement>(null);
+
+  useEffect(() => {
+    const unlisten = listen<InstallLogEvent>('install-log', (event) => {
+      setEntries((prev) => [...prev.slice(-(MAX_ENTRIES - 1)), event.payload]);
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  const query = search.trim().toLowerCase();
+  const visible = entries.filter((e) =>
+    LEVEL_RANK[e.level] >= minRank &&
+    (!query || e.message.toLowerCase().includes(query) || e.step.toLowerCase().includes(query))
+  );
+
+  useEffect(() => {
+    if (open) bottomRef.current?.scrollIntoView({ block: 'nearest' });
+  }, [visible.length, open]);
+
+  return (
+    <div className="rounded-lg bg-zinc-900/50 border border-zinc-800">
+      <button
+        type="button"
+        onClick={() => setOpen(!open)}
+        className="w-full flex items-center gap-2 px-3 py-2 text-xs text-zinc-400 hover:text-white"
+      >
+        <ScrollText className="w-3 h-3" />
+        <span className="flex-1 text-left">Logs de l'installation ({entries.length})</span>
+        {open ? <ChevronUp className="w-3 h-3" /> : <ChevronDown className="w-3 h-3" />}
+      </button>
+
+      {open && (
+        <div className="border-t border-zinc-800 p-2 space-y-2">
+          <div className="flex gap-2">
+            <select
+              value={minRank}
+              onChange={(e) => setMinRank(Number(e.target.value))}
+              className="input-field text-xs py-1 w-40"
+            >
+              {FILTERS.map((f) => (
+                <option key={f.value} value={f.value}>{f.name}</option>
+              ))}
+            </select>
+            <input
+              value={search}
+              onChange={(e) => setSearch(e.target.value)}
+              className="input-field text-xs py-1 flex-1"
+              placeholder="Filtrer (étape, message)"
+            />
+          </div>
+          <div className="h-48 overflow-y-auto font-mono text-[11px] leading-relaxed">
+            {visible.map((e, i) => (
+              <div key={i} className={LEVEL_COLOR[e.level]}>
+                <span className="text-zinc-600">{new Date(e.timestamp).toLocaleTimeString()}</span>{' '}
+                <span className="text-zinc-500">[{e.step}]</span> {e.message}
+              </div>
+            ))}
+            <div ref={bottomRef} />
+          </div>
+        </div>
+      )}
+    </div>
+  );
+}
@@ -1,4 +1,5 @@
-import { HardDrive, Search, Settings, ArrowRight, Sparkles, Monitor } from 'lucide-react';
+import { HardDrive, Search, Settings, ArrowRight, Sparkles, Monitor, AlertTriangle } from 'lucide-react';
+import type { UnconfiguredCard } from '../../bindings/UnconfiguredCard';
 
 interface MainMenuProps {
   onNewSetup: () => void;
@@ -6,6 +7,8 @@ interface MainMenuProps {
   onReconfigure: () => void;
   onViewServices?: () => void;
   hasExistingConfig: boolean;
+  unconfiguredCard?: UnconfiguredCard | null;
+  onFinishConfiguration?: () => void;
 }
 
 export default function MainMenu({
@@ -13,7 +16,9 @@ export default function MainMenu({
   onConnectExisting,
   onReconfigure,
   onViewServices,
-  hasExistingConfig
+  hasExistingConfig,
+  unconfiguredCard,
+  onFinishConfiguration
 }: MainMenuProps) {
   return (
     <div className="space-y-6">
@@ -30,6 +35,30 @@ export default function MainMenu({
         </p>
       </div>
 
+      {/* Carte flashée mais jamais configurée (flash interrompu) */}
+      {unconfiguredCard && onFinishConfiguration && (
+        <div className="card !p-4 border-amber-500/40 bg-amber-500/5">
+          <div className="flex items-start gap-3">
+            <AlertTriangle className="w-5 h-5 text-amber-400 flex-shrink-0 mt-0.5" />
+            <div className="flex-1">
+              <p className="text-sm font-medium text-white">
+                Carte SD flashée mais non configurée
+              </p>
+              <p className="text-xs text-zinc-400 mt-1">
+                Le flash{unconfiguredCard.hostname ? ` de ${unconfiguredCard.hostname}` : ''} a été interrompu avant la configuration.
+                Sans elle, le Pi démarrera sans SSH ni WiFi.
+              </p>
+              <button
+                onClick={onFinishConfiguration}
+                className="mt-3 text-sm px-3 py-1.5 rounded-lg bg-amber-500/20 text-amber-300 hover:bg-amber-500/30 transition-colors"
+              >
+                Terminer la configuration (sans re-flasher)
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+
       {/* Options */}
       <div className="space-y-3">
         {/* Option 1: Nouveau setup complet */}
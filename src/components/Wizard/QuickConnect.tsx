@@ -2,6 +2,7 @@ import { useState, useRef, useEffect } from 'react';
 import { ArrowLeft, ArrowRight, Eye, EyeOff, Key, User, Monitor, Info, ExternalLink, Mail, Server } from 'lucide-react';
 import { open } from '@tauri-apps/api/shell';
 import { Config } from '../../lib/store';
+import AlldebridAccountStatus from './AlldebridAccountStatus';
 
 interface QuickConnectProps {
   config: Config;
@@ -124,6 +125,7 @@ export default function QuickConnect({ config, onConfigChange, onNext, onBack }:
           className="input-field text-sm py-2.5 font-mono"
           placeholder="Votre clé API AllDebrid"
         />
+        <AlldebridAccountStatus apiKey={config.alldebridKey} />
       </div>
 
       {/* YGG Passkey (optional) */}
@@ -1,8 +1,8 @@
 import { useState, useEffect } from 'react';
-import { invoke } from '@tauri-apps/api/tauri';
 import { Monitor, ArrowLeft, Loader2, Wifi, CheckCircle2 } from 'lucide-react';
 import Complete from './Complete';
 import { PiInfo } from '../../lib/store';
+import { runOperation } from '../../lib/operations';
 
 interface ServicesViewProps {
   onBack: () => void;
@@ -32,7 +32,7 @@ export default function ServicesView({ onBack }: ServicesViewProps) {
         for (const hostname of hostnames) {
           if (cancelled) return;
 
-          const result = await invoke<PiInfo | null>('discover_pi', {
+          const result = await runOperation<PiInfo | null>('start_discovery', {
             hostname: hostname,
             timeoutSecs: 5,
           });
@@ -0,0 +1,38 @@
+import { useState } from 'react';
+import { invoke } from '@tauri-apps/api/tauri';
+import { LifeBuoy, Loader2 } from 'lucide-react';
+import { useStore } from '../../lib/store';
+
+/** Exporte un zip (journaux, configuration masquée, versions) à joindre à un rapport de bug */
+export default function SupportBundleButton() {
+  const { config } = useStore();
+  const [loading, setLoading] = useState(false);
+  const [result, setResult] = useState<string | null>(null);
+
+  const exportBundle = async () => {
+    setLoading(true);
+    try {
+      const path = await invoke<string>('export_support_bundle', { config });
+      setResult(`Bundle enregistré: ${path}`);
+    } catch (err) {
+      setResult(`Export impossible: ${err}`);
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  return (
+    <div className="space-y-1">
+      <button
+        type="button"
+        onClick={exportBundle}
+        disabled={loading}
+        className="text-xs text-zinc-400 hover:text-white inline-flex items-center gap-1 disabled:opacity-50"
+      >
+        {loading ? <Loader2 className="w-3 h-3 animate-spin" /> : <LifeBuoy className="w-3 h-3" />}
+        Exporter un bundle de support
+      </button>
+      {result && <p className="text-xs text-zinc-500 break-all">{result}</p>}
+    </div>
+  );
+}
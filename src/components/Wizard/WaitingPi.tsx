@@ -1,7 +1,7 @@
 import { useState, useEffect, useRef } from 'react';
-import { invoke } from '@tauri-apps/api/tauri';
 import { ArrowLeft, Loader2, Wifi, RefreshCw, CheckCircle2, Edit3, Search } from 'lucide-react';
 import { useStore, PiInfo } from '../../lib/store';
+import { runOperation } from '../../lib/operations';
 
 interface WaitingPiProps {
   isQuickConnect?: boolean;  // true = Pi déjà configuré, pas besoin d'attendre le boot
@@ -57,12 +57,12 @@ export default function WaitingPi({ isQuickConnect = false, onPiFound, onBack }:
       setAttempts(currentAttempt);
 
       try {
-        console.log('[WaitingPi] Calling discover_pi...');
-        const piInfo = await invoke<PiInfo | null>('discover_pi', {
+        console.log('[WaitingPi] Calling start_discovery...');
+        const piInfo = await runOperation<PiInfo | null>('start_discovery', {
           hostname: hostname,
           timeoutSecs: 10,  // camelCase requis par Tauri!
         });
-        console.log('[WaitingPi] discover_pi returned:', piInfo);
+        console.log('[WaitingPi] discovery returned:', piInfo);
 
         if (cancelled) return;
 
@@ -73,7 +73,7 @@ export default function WaitingPi({ isQuickConnect = false, onPiFound, onBack }:
           addLogRef.current(`Pi non trouvé, nouvelle tentative dans 8s...`);
         }
       } catch (error) {
-        console.error('[WaitingPi] discover_pi error:', error);
+        console.error('[WaitingPi] discovery error:', error);
         addLogRef.current(`Erreur de recherche: ${error}`);
       }
     };
@@ -116,11 +116,11 @@ export default function WaitingPi({ isQuickConnect = false, onPiFound, onBack }:
         onPiFound({
           ip: input,
           hostname: hostname,
-          macAddress: undefined,
+          mac_address: null,
         });
       } else {
         // Essayer de résoudre le hostname via mDNS
-        const piInfo = await invoke<PiInfo | null>('discover_pi', {
+        const piInfo = await runOperation<PiInfo | null>('start_discovery', {
           hostname: hostname,
           timeoutSecs: 10,  // camelCase requis par Tauri!
         });
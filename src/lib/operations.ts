@@ -0,0 +1,27 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import type { Operation } from '../bindings/Operation';
+
+// Opérations longues (flash, installation, découverte): la commande start_* rend
+// un ID tout de suite, l'état est ensuite relu avec get_operation jusqu'à la fin
+
+const POLL_INTERVAL_MS = 1000;
+
+/** Lance une opération et attend sa fin; rejette avec l'erreur de l'opération */
+export async function runOperation<T>(command: string, args: Record<string, unknown>): Promise<T> {
+  const id = await invoke<string>(command, args);
+  for (;;) {
+    await new Promise((resolve) => setTimeout(resolve, POLL_INTERVAL_MS));
+    const operation = await invoke<Operation | null>('get_operation', { id });
+    if (!operation) throw new Error(`Opération ${id} introuvable`);
+    switch (operation.status) {
+      case 'running':
+        continue;
+      case 'completed':
+        return operation.result as T;
+      case 'cancelled':
+        throw new Error('Opération annulée');
+      case 'failed':
+        throw new Error(operation.error ?? 'Opération en échec');
+    }
+  }
+}
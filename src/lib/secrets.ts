@@ -0,0 +1,59 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import type { SecretKind } from '../bindings/SecretKind';
+import type { Config } from './store';
+
+// Champs secrets de la config: rangés dans le coffre de l'OS (commande store_secret),
+// jamais persistés dans localStorage
+export const SECRET_FIELDS = {
+  systemPassword: 'system_password',
+  wifiPassword: 'wifi_password',
+  alldebridKey: 'alldebrid_key',
+  jellyfinPassword: 'jellyfin_password',
+  yggPasskey: 'ygg_passkey',
+  cloudflareToken: 'cloudflare_token',
+  cloudflareApiToken: 'cloudflare_api_token',
+} as const satisfies Partial<Record<keyof Config, SecretKind>>;
+
+type SecretField = keyof typeof SECRET_FIELDS;
+
+const secretFields = Object.keys(SECRET_FIELDS) as SecretField[];
+
+/** Config sans ses champs secrets (ce qui peut aller dans localStorage) */
+export function withoutSecrets(config: Config): Partial<Config> {
+  const rest: Partial<Config> = { ...config };
+  for (const field of secretFields) {
+    delete rest[field];
+  }
+  return rest;
+}
+
+/** Range les secrets de la config dans le coffre (valeur vide: entrée supprimée) */
+export async function saveConfigSecrets(config: Config): Promise<void> {
+  await Promise.all(
+    secretFields.map((field) =>
+      invoke('store_secret', { kind: SECRET_FIELDS[field], scope: null, value: config[field] ?? '' })
+    )
+  );
+}
+
+/** Relit les secrets de la config depuis le coffre */
+export async function loadConfigSecrets(): Promise<Partial<Config>> {
+  const values = await Promise.all(
+    secretFields.map((field) =>
+      invoke<string | null>('get_secret', { kind: SECRET_FIELDS[field], scope: null })
+    )
+  );
+  const secrets: Partial<Config> = {};
+  secretFields.forEach((field, index) => {
+    const value = values[index];
+    if (value) {
+      secrets[field] = value;
+    }
+  });
+  return secrets;
+}
+
+/** Range la clé SSH privée générée pour un Pi */
+export async function storeSshPrivateKey(hostname: string, privateKey: string): Promise<void> {
+  await invoke('store_secret', { kind: 'ssh_private_key', scope: hostname, value: privateKey });
+}
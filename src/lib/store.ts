@@ -1,5 +1,13 @@
 import { create } from 'zustand';
 import { persist } from 'zustand/middleware';
+import type { PiInfo } from '../bindings/PiInfo';
+import type { SDCard } from '../bindings/SDCard';
+import type { JellyfinAuth } from '../bindings/JellyfinAuth';
+import type { SshKeyType } from '../bindings/SshKeyType';
+import { withoutSecrets, loadConfigSecrets } from './secrets';
+
+// Types partagés avec le backend (générés par ts-rs)
+export type { PiInfo, SDCard, JellyfinAuth };
 
 export interface Config {
   // Système Raspberry Pi
@@ -7,6 +15,10 @@ export interface Config {
   systemUsername: string;
   systemPassword: string;
 
+  // Clé SSH (générée au flash, ou importée)
+  sshKeyType?: SshKeyType;
+  sshKeyImported?: boolean;
+
   // Réseau WiFi
   wifiSSID: string;
   wifiPassword: string;
@@ -24,38 +36,25 @@ export interface Config {
   jellyfinPassword: string;
   jellyfinServerName: string;
 
-  // Email admin (pour Jellyseerr)
+  // Compte admin (Jellyseerr, Jellyfin, notifications)
   adminEmail?: string;
+  adminDisplayName?: string;
+  preferredLanguage?: string;
 
   // Optionnel
   yggPasskey?: string;
   discordWebhook?: string;
   cloudflareToken?: string;
+  cloudflareApiToken?: string;
+  cloudflareDomain?: string;
 }
 
-export interface PiInfo {
-  ip: string;
-  hostname: string;
-  macAddress?: string;
-}
-
-export interface SDCard {
-  path: string;
-  name: string;
-  size: number;
-  removable: boolean;
-}
 
 export interface SSHCredentials {
   publicKey: string;
   privateKey: string;
 }
 
-export interface JellyfinAuth {
-  server_id: string;
-  access_token: string;
-  user_id: string;
-}
 
 interface Store {
   // Configuration utilisateur
@@ -155,10 +154,19 @@ export const useStore = create<Store>()(
     {
       name: 'jellysetup-storage-v5',
       partialize: (state) => ({
-        // Ne persister que les données importantes
-        config: state.config,
+        // Ne persister que les données importantes (les secrets vont dans le coffre de l'OS)
+        config: withoutSecrets(state.config),
         selectedSD: state.selectedSD,
       }),
+      merge: (persisted, current) => {
+        const saved = persisted as Partial<Store> | undefined;
+        return { ...current, ...saved, config: { ...current.config, ...saved?.config } };
+      },
+      onRehydrateStorage: () => (state) => {
+        loadConfigSecrets()
+          .then((secrets) => state?.setConfig(secrets))
+          .catch((err) => console.warn('[Store] Keychain unavailable:', err));
+      },
     }
   )
 );
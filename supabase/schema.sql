@@ -140,6 +140,161 @@ FROM installations i
 LEFT JOIN installation_logs l ON l.installation_id = i.id
 GROUP BY i.id;
 
+-- =============================================================================
+-- Schémas par Pi: accès par token du Pi (en-tête X-Pi-Token)
+-- =============================================================================
+--
+-- jellysetup-init crée le schéma du Pi puis appelle jellysetup_register_pi() avec
+-- le token qu'il délivre à l'application: seul son SHA-256 est conservé. Les appels
+-- PostgREST et Storage directs de l'application (catalogue, demandes, sauvegardes,
+-- historique de configuration) envoient ce token; les politiques ci-dessous le
+-- comparent au schéma visé.
+
+CREATE EXTENSION IF NOT EXISTS pgcrypto WITH SCHEMA extensions;
+
+CREATE TABLE IF NOT EXISTS pi_tokens (
+  schema_name TEXT PRIMARY KEY,
+  token_hash TEXT NOT NULL,
+  created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- Aucune politique: table réservée à la clé service (Edge Functions)
+ALTER TABLE pi_tokens ENABLE ROW LEVEL SECURITY;
+
+-- Vrai si l'en-tête X-Pi-Token de la requête correspond au schéma
+CREATE OR REPLACE FUNCTION jellysetup_pi_token_ok(target_schema TEXT)
+RETURNS BOOLEAN
+LANGUAGE sql STABLE SECURITY DEFINER
+SET search_path = public, extensions
+AS $$
+  SELECT EXISTS (
+    SELECT 1 FROM pi_tokens t
+    WHERE t.schema_name = target_schema
+      AND t.token_hash = encode(
+        digest(coalesce(current_setting('request.headers', true)::json ->> 'x-pi-token', ''), 'sha256'),
+        'hex'
+      )
+  );
+$$;
+
+-- Enregistre le token d'un Pi et active la RLS sur toutes les tables de son schéma
+CREATE OR REPLACE FUNCTION jellysetup_register_pi(target_schema TEXT, pi_token TEXT)
+RETURNS VOID
+LANGUAGE plpgsql SECURITY DEFINER
+SET search_path = public, extensions
+AS $$
+DECLARE
+  t RECORD;
+BEGIN
+  INSERT INTO pi_tokens (schema_name, token_hash)
+  VALUES (target_schema, encode(digest(pi_token, 'sha256'), 'hex'))
+  ON CONFLICT (schema_name) DO UPDATE SET token_hash = EXCLUDED.token_hash;
+
+  EXECUTE format('GRANT USAGE ON SCHEMA %I TO anon, authenticated', target_schema);
+  FOR t IN SELECT tablename FROM pg_tables WHERE schemaname = target_schema LOOP
+    EXECUTE format('ALTER TABLE %I.%I ENABLE ROW LEVEL SECURITY', target_schema, t.tablename);
+    EXECUTE format('GRANT SELECT, INSERT, UPDATE ON %I.%I TO anon, authenticated', target_schema, t.tablename);
+    EXECUTE format('DROP POLICY IF EXISTS "Pi token" ON %I.%I', target_schema, t.tablename);
+    EXECUTE format(
+      'CREATE POLICY "Pi token" ON %I.%I FOR ALL USING (jellysetup_pi_token_ok(%L)) WITH CHECK (jellysetup_pi_token_ok(%L))',
+      target_schema, t.tablename, target_schema, target_schema
+    );
+  END LOOP;
+END;
+$$;
+
+REVOKE ALL ON FUNCTION jellysetup_register_pi(TEXT, TEXT) FROM PUBLIC, anon, authenticated;
+
+-- =============================================================================
+-- Storage: un dossier par Pi (<schéma>/...), accessible avec le token du Pi
+-- =============================================================================
+
+INSERT INTO storage.buckets (id, name, public)
+VALUES ('backups', 'backups', false), ('config-history', 'config-history', false)
+ON CONFLICT (id) DO NOTHING;
+
+CREATE POLICY "Pi token read" ON storage.objects
+  FOR SELECT USING (
+    bucket_id IN ('backups', 'config-history')
+    AND jellysetup_pi_token_ok((storage.foldername(name))[1])
+  );
+
+CREATE POLICY "Pi token insert" ON storage.objects
+  FOR INSERT WITH CHECK (
+    bucket_id IN ('backups', 'config-history')
+    AND jellysetup_pi_token_ok((storage.foldername(name))[1])
+  );
+
+CREATE POLICY "Pi token update" ON storage.objects
+  FOR UPDATE USING (
+    bucket_id IN ('backups', 'config-history')
+    AND jellysetup_pi_token_ok((storage.foldername(name))[1])
+  );
+
+-- =============================================================================
+-- Storage: un dossier par compte (<auth.uid()>/...), accessible à son seul propriétaire
+-- =============================================================================
+
+INSERT INTO storage.buckets (id, name, public)
+VALUES ('install-resume', 'install-resume', false), ('jellyfin-templates', 'jellyfin-templates', false)
+ON CONFLICT (id) DO NOTHING;
+
+CREATE POLICY "Owner read" ON storage.objects
+  FOR SELECT TO authenticated USING (
+    bucket_id IN ('install-resume', 'jellyfin-templates')
+    AND (storage.foldername(name))[1] = auth.uid()::text
+  );
+
+CREATE POLICY "Owner insert" ON storage.objects
+  FOR INSERT TO authenticated WITH CHECK (
+    bucket_id IN ('install-resume', 'jellyfin-templates')
+    AND (storage.foldername(name))[1] = auth.uid()::text
+  );
+
+CREATE POLICY "Owner update" ON storage.objects
+  FOR UPDATE TO authenticated USING (
+    bucket_id IN ('install-resume', 'jellyfin-templates')
+    AND (storage.foldername(name))[1] = auth.uid()::text
+  );
+
+CREATE POLICY "Owner delete" ON storage.objects
+  FOR DELETE TO authenticated USING (
+    bucket_id IN ('install-resume', 'jellyfin-templates')
+    AND (storage.foldername(name))[1] = auth.uid()::text
+  );
+
+-- =============================================================================
+-- Registre des Pis d'un compte (pi_registry.rs): sans clé SSH, visible de son seul propriétaire
+-- =============================================================================
+
+CREATE TABLE IF NOT EXISTS pi_registry (
+  id UUID DEFAULT gen_random_uuid() PRIMARY KEY,
+  owner_id UUID NOT NULL DEFAULT auth.uid() REFERENCES auth.users(id) ON DELETE CASCADE,
+  pi_name TEXT NOT NULL,
+  host TEXT NOT NULL,
+  username TEXT NOT NULL,
+  auth_method TEXT NOT NULL,
+  ssh_host_fingerprint TEXT,
+  installed_version TEXT NOT NULL,
+  added_at TIMESTAMPTZ DEFAULT NOW(),
+  last_seen TIMESTAMPTZ,
+  UNIQUE (owner_id, pi_name)
+);
+
+ALTER TABLE pi_registry ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Owner select" ON pi_registry
+  FOR SELECT TO authenticated USING (owner_id = auth.uid());
+
+CREATE POLICY "Owner insert" ON pi_registry
+  FOR INSERT TO authenticated WITH CHECK (owner_id = auth.uid());
+
+CREATE POLICY "Owner update" ON pi_registry
+  FOR UPDATE TO authenticated USING (owner_id = auth.uid()) WITH CHECK (owner_id = auth.uid());
+
+CREATE POLICY "Owner delete" ON pi_registry
+  FOR DELETE TO authenticated USING (owner_id = auth.uid());
+
 -- =============================================================================
 -- Données de test (optionnel, à supprimer en prod)
 -- =============================================================================